@@ -0,0 +1,49 @@
+// An example of embedding Uiua in a C program
+//
+// Build the library with `cargo build -p uiua-capi --release`, then compile this with
+// cc example.c -I. -L../target/release -luiua_capi -o example
+
+#include <stdio.h>
+
+#include "uiua.h"
+
+int main(void) {
+    UiuaInterpreter* interp = uiua_new();
+
+    // Compile a function once and call it with different arguments
+    UiuaProgram* mean = uiua_compile(interp, "÷⊃⧻/+");
+    if (!mean) {
+        fprintf(stderr, "%s\n", uiua_error(interp));
+        return 1;
+    }
+    double data[] = {1, 2, 3, 4};
+    for (size_t len = 1; len <= 4; len++) {
+        uiua_push_nums(interp, data, &len, 1);
+        if (uiua_run_program(interp, mean) != 0) {
+            fprintf(stderr, "%s\n", uiua_error(interp));
+            return 1;
+        }
+        double result;
+        uiua_pop_num(interp, &result);
+        printf("mean of %zu numbers: %g\n", len, result);
+    }
+    uiua_program_free(mean);
+
+    // Work with returned arrays
+    uiua_push_string(interp, "Hello, World!");
+    if (uiua_run(interp, "⌵") != 0) {
+        fprintf(stderr, "%s\n", uiua_error(interp));
+        return 1;
+    }
+    UiuaArray* upper = uiua_pop(interp);
+    printf("%s\n", uiua_array_string(upper));
+    uiua_array_free(upper);
+
+    // Errors are reported with their location
+    if (uiua_run(interp, "⊢[]") != 0) {
+        printf("%s\n", uiua_error(interp));
+    }
+
+    uiua_free(interp);
+    return 0;
+}
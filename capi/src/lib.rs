@@ -0,0 +1,391 @@
+//! A C API for embedding the Uiua interpreter
+//!
+//! The functions are documented in `uiua.h`.
+
+#![allow(clippy::missing_safety_doc)]
+
+use std::{
+    ffi::{c_char, c_int, CStr, CString},
+    ptr, slice,
+    sync::Arc,
+};
+
+use uiua::{
+    Array, Assembly, Compiler, IntoSysBackend, NativeSys, SafeSys, SysBackend, Uiua, UiuaError,
+    Value,
+};
+
+/// An interpreter with its own stack
+pub struct UiuaInterpreter {
+    env: Uiua,
+    backend: Arc<dyn SysBackend>,
+    error: Option<CString>,
+}
+
+/// A compiled program
+pub struct UiuaProgram(Assembly);
+
+/// An array that has been popped from an interpreter's stack
+///
+/// The data that C code can borrow is converted when the array is created.
+pub struct UiuaArray {
+    value: Value,
+    shape: Vec<usize>,
+    nums: Option<Vec<f64>>,
+    string: Option<CString>,
+}
+
+/// The type of an array's elements
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UiuaType {
+    Num = 0,
+    Char = 1,
+    Box = 2,
+    Complex = 3,
+}
+
+impl UiuaInterpreter {
+    fn create(backend: Arc<dyn SysBackend>) -> *mut Self {
+        Box::into_raw(Box::new(UiuaInterpreter {
+            env: Uiua::with_backend(backend.clone()),
+            backend,
+            error: None,
+        }))
+    }
+    /// Record the result of a call that can fail
+    fn result<T>(&mut self, res: Result<T, String>) -> Option<T> {
+        match res {
+            Ok(val) => {
+                self.error = None;
+                Some(val)
+            }
+            Err(e) => {
+                self.error = Some(CString::new(e.replace('\0', "")).unwrap());
+                None
+            }
+        }
+    }
+    fn status(&mut self, res: Result<(), String>) -> c_int {
+        self.result(res).map_or(1, |_| 0)
+    }
+    fn compile(&self, source: *const c_char) -> Result<Assembly, String> {
+        let source = unsafe { str_arg(source) }?;
+        let mut comp = Compiler::with_backend(self.backend.clone());
+        comp.print_diagnostics(false);
+        comp.load_str(source).map_err(report)?;
+        Ok(comp.finish())
+    }
+}
+
+impl From<Value> for UiuaArray {
+    fn from(value: Value) -> Self {
+        let nums = match &value {
+            Value::Num(arr) => Some(arr.elements().copied().collect()),
+            Value::Byte(arr) => Some(arr.elements().map(|&b| b as f64).collect()),
+            Value::Complex(arr) => Some(arr.elements().flat_map(|c| [c.re, c.im]).collect()),
+            _ => None,
+        };
+        let string = match &value {
+            Value::Char(arr) => {
+                let s: String = arr.elements().filter(|&&c| c != '\0').collect();
+                Some(CString::new(s).unwrap())
+            }
+            _ => None,
+        };
+        UiuaArray {
+            shape: value.shape().to_vec(),
+            value,
+            nums,
+            string,
+        }
+    }
+}
+
+fn report(e: UiuaError) -> String {
+    e.report().color(false).to_string()
+}
+
+unsafe fn str_arg<'a>(s: *const c_char) -> Result<&'a str, String> {
+    if s.is_null() {
+        return Err("String is null".into());
+    }
+    (CStr::from_ptr(s).to_str()).map_err(|_| "String is not valid UTF-8".into())
+}
+
+#[no_mangle]
+pub extern "C" fn uiua_new() -> *mut UiuaInterpreter {
+    UiuaInterpreter::create(NativeSys.into_sys_backend())
+}
+
+#[no_mangle]
+pub extern "C" fn uiua_new_safe() -> *mut UiuaInterpreter {
+    UiuaInterpreter::create(SafeSys::default().into_sys_backend())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn uiua_free(interp: *mut UiuaInterpreter) {
+    if !interp.is_null() {
+        drop(Box::from_raw(interp));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn uiua_error(interp: *const UiuaInterpreter) -> *const c_char {
+    (*interp).error.as_ref().map_or(ptr::null(), |e| e.as_ptr())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn uiua_compile(
+    interp: *mut UiuaInterpreter,
+    source: *const c_char,
+) -> *mut UiuaProgram {
+    let interp = &mut *interp;
+    let res = interp.compile(source);
+    (interp.result(res)).map_or(ptr::null_mut(), |asm| {
+        Box::into_raw(Box::new(UiuaProgram(asm)))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn uiua_program_free(program: *mut UiuaProgram) {
+    if !program.is_null() {
+        drop(Box::from_raw(program));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn uiua_run_program(
+    interp: *mut UiuaInterpreter,
+    program: *const UiuaProgram,
+) -> c_int {
+    let interp = &mut *interp;
+    let res = interp.env.run_asm((*program).0.clone()).map_err(report);
+    interp.status(res)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn uiua_run(interp: *mut UiuaInterpreter, source: *const c_char) -> c_int {
+    let interp = &mut *interp;
+    let res = (interp.compile(source)).and_then(|asm| interp.env.run_asm(asm).map_err(report));
+    interp.status(res)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn uiua_stack_len(interp: *const UiuaInterpreter) -> usize {
+    (*interp).env.stack().len()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn uiua_push_num(interp: *mut UiuaInterpreter, n: f64) {
+    (*interp).env.push(n);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn uiua_push_nums(
+    interp: *mut UiuaInterpreter,
+    data: *const f64,
+    shape: *const usize,
+    rank: usize,
+) -> c_int {
+    let interp = &mut *interp;
+    let shape = if rank == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(shape, rank)
+    };
+    let res = match shape.iter().try_fold(1usize, |acc, &d| acc.checked_mul(d)) {
+        Some(len) => {
+            let data = if len == 0 {
+                &[]
+            } else {
+                slice::from_raw_parts(data, len)
+            };
+            interp.env.push(Array::new(shape, data));
+            Ok(())
+        }
+        None => Err("Shape is too large".into()),
+    };
+    interp.status(res)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn uiua_push_string(interp: *mut UiuaInterpreter, s: *const c_char) -> c_int {
+    let interp = &mut *interp;
+    let res = str_arg(s).map(|s| interp.env.push(s));
+    interp.status(res)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn uiua_push_array(interp: *mut UiuaInterpreter, array: *const UiuaArray) {
+    (*interp).env.push((*array).value.clone());
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn uiua_pop(interp: *mut UiuaInterpreter) -> *mut UiuaArray {
+    let interp = &mut *interp;
+    let res = interp.env.pop("value").map_err(report);
+    (interp.result(res)).map_or(ptr::null_mut(), |value| {
+        Box::into_raw(Box::new(value.into()))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn uiua_pop_num(interp: *mut UiuaInterpreter, out: *mut f64) -> c_int {
+    let interp = &mut *interp;
+    let res = interp.env.pop("number").map_err(report).and_then(|value| {
+        let n = match &value {
+            Value::Num(arr) => arr.as_scalar().copied(),
+            Value::Byte(arr) => arr.as_scalar().map(|&b| b as f64),
+            _ => None,
+        };
+        n.ok_or_else(|| format!("Expected a number, but got {}", value.type_name()))
+    });
+    interp.result(res).map_or(1, |n| {
+        *out = n;
+        0
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn uiua_array_free(array: *mut UiuaArray) {
+    if !array.is_null() {
+        drop(Box::from_raw(array));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn uiua_array_type(array: *const UiuaArray) -> UiuaType {
+    match (*array).value {
+        Value::Num(_) | Value::Byte(_) => UiuaType::Num,
+        Value::Char(_) => UiuaType::Char,
+        Value::Box(_) => UiuaType::Box,
+        Value::Complex(_) => UiuaType::Complex,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn uiua_array_rank(array: *const UiuaArray) -> usize {
+    (*array).shape.len()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn uiua_array_shape(array: *const UiuaArray) -> *const usize {
+    (*array).shape.as_ptr()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn uiua_array_len(array: *const UiuaArray) -> usize {
+    (*array).value.element_count()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn uiua_array_nums(array: *const UiuaArray) -> *const f64 {
+    (*array)
+        .nums
+        .as_ref()
+        .map_or(ptr::null(), |nums| nums.as_ptr())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn uiua_array_string(array: *const UiuaArray) -> *const c_char {
+    (*array).string.as_ref().map_or(ptr::null(), |s| s.as_ptr())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn uiua_array_unbox(array: *const UiuaArray, index: usize) -> *mut UiuaArray {
+    match &(*array).value {
+        Value::Box(arr) => (arr.elements().nth(index)).map_or(ptr::null_mut(), |b| {
+            Box::into_raw(Box::new(b.0.clone().into()))
+        }),
+        _ => ptr::null_mut(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn c(s: &str) -> CString {
+        CString::new(s).unwrap()
+    }
+
+    unsafe fn error(interp: *const UiuaInterpreter) -> String {
+        CStr::from_ptr(uiua_error(interp))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn capi() {
+        unsafe {
+            let interp = uiua_new_safe();
+
+            // Running with pushed arguments
+            let program = uiua_compile(interp, c("+").as_ptr());
+            assert!(!program.is_null());
+            for (a, b) in [(1.0, 2.0), (3.0, 4.5)] {
+                uiua_push_num(interp, b);
+                uiua_push_num(interp, a);
+                assert_eq!(uiua_run_program(interp, program), 0);
+                let mut n = 0.0;
+                assert_eq!(uiua_pop_num(interp, &mut n), 0);
+                assert_eq!(n, a + b);
+            }
+            uiua_program_free(program);
+            assert!(uiua_error(interp).is_null());
+
+            // Arrays
+            let data = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+            let shape = [2, 3];
+            assert_eq!(uiua_push_nums(interp, data.as_ptr(), shape.as_ptr(), 2), 0);
+            assert_eq!(uiua_run(interp, c("⍉").as_ptr()), 0);
+            let arr = uiua_pop(interp);
+            assert_eq!(uiua_array_type(arr), UiuaType::Num);
+            assert_eq!(uiua_array_rank(arr), 2);
+            assert_eq!(slice::from_raw_parts(uiua_array_shape(arr), 2), [3, 2]);
+            assert_eq!(uiua_array_len(arr), 6);
+            let nums = slice::from_raw_parts(uiua_array_nums(arr), 6);
+            assert_eq!(nums, [1.0, 4.0, 2.0, 5.0, 3.0, 6.0]);
+            assert!(uiua_array_string(arr).is_null());
+            uiua_array_free(arr);
+
+            // Strings and boxes
+            assert_eq!(uiua_push_string(interp, c("hello wörld").as_ptr()), 0);
+            assert_eq!(uiua_run(interp, c("⊜□≠@ .").as_ptr()), 0);
+            assert_eq!(uiua_stack_len(interp), 1);
+            let arr = uiua_pop(interp);
+            assert_eq!(uiua_array_type(arr), UiuaType::Box);
+            assert_eq!(uiua_array_len(arr), 2);
+            let word = uiua_array_unbox(arr, 1);
+            assert_eq!(uiua_array_type(word), UiuaType::Char);
+            assert_eq!(
+                CStr::from_ptr(uiua_array_string(word)).to_str(),
+                Ok("wörld")
+            );
+            assert!(uiua_array_unbox(arr, 2).is_null());
+            uiua_push_array(interp, word);
+            uiua_array_free(word);
+            uiua_array_free(arr);
+            assert_eq!(uiua_run(interp, c("⧻").as_ptr()), 0);
+            let mut n = 0.0;
+            assert_eq!(uiua_pop_num(interp, &mut n), 0);
+            assert_eq!(n, 5.0);
+
+            // Errors
+            assert!(uiua_pop(interp).is_null());
+            assert!(error(interp).contains("Stack was empty"));
+            assert_ne!(uiua_run(interp, c("⊢[]").as_ptr()), 0);
+            assert!(error(interp).contains("empty"));
+            assert!(uiua_compile(interp, c("(").as_ptr()).is_null());
+            assert!(!uiua_error(interp).is_null());
+            assert_eq!(uiua_push_string(interp, c("x").as_ptr()), 0);
+            assert_ne!(uiua_pop_num(interp, &mut n), 0);
+            assert!(error(interp).contains("Expected a number"));
+
+            // The safe interpreter has no system access
+            assert_ne!(uiua_run(interp, c("&fras \"capi/uiua.h\"").as_ptr()), 0);
+            uiua_free(interp);
+        }
+    }
+}
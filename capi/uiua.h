@@ -0,0 +1,121 @@
+/*
+ * The C API for embedding the Uiua interpreter
+ *
+ * Link against the `uiua_capi` library built from this crate.
+ *
+ * Functions that can fail return 0 on success and a non-zero value on failure,
+ * or NULL for functions that return pointers. The message of the last failure
+ * can be retrieved with `uiua_error`.
+ *
+ * Unless stated otherwise, pointer arguments must not be NULL.
+ */
+
+#ifndef UIUA_H
+#define UIUA_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* An interpreter with its own stack */
+typedef struct UiuaInterpreter UiuaInterpreter;
+/* A compiled program */
+typedef struct UiuaProgram UiuaProgram;
+/* An array that has been popped from an interpreter's stack */
+typedef struct UiuaArray UiuaArray;
+
+/* The type of an array's elements */
+typedef enum UiuaType {
+    UIUA_NUM = 0,
+    UIUA_CHAR = 1,
+    UIUA_BOX = 2,
+    UIUA_COMPLEX = 3,
+} UiuaType;
+
+/* Interpreters */
+
+/* Create an interpreter that can access the file system, the network, and other system resources */
+UiuaInterpreter* uiua_new(void);
+/* Create an interpreter that cannot access any system resources */
+UiuaInterpreter* uiua_new_safe(void);
+/* Free an interpreter. Does nothing if `interp` is NULL. */
+void uiua_free(UiuaInterpreter* interp);
+/*
+ * Get the message of the last failure, or NULL if the last call that could fail succeeded
+ *
+ * The string is valid until the next call that takes `interp`.
+ */
+const char* uiua_error(const UiuaInterpreter* interp);
+
+/* Compiling and running */
+
+/* Compile UTF-8 source code into a program, or return NULL if compilation fails */
+UiuaProgram* uiua_compile(UiuaInterpreter* interp, const char* source);
+/* Free a program. Does nothing if `program` is NULL. */
+void uiua_program_free(UiuaProgram* program);
+/*
+ * Run a program on the interpreter's stack
+ *
+ * A program can be run any number of times. Bindings are not shared between programs.
+ */
+int uiua_run_program(UiuaInterpreter* interp, const UiuaProgram* program);
+/* Compile and run UTF-8 source code on the interpreter's stack */
+int uiua_run(UiuaInterpreter* interp, const char* source);
+
+/* The stack */
+
+/* Get the number of values on the stack */
+size_t uiua_stack_len(const UiuaInterpreter* interp);
+/* Push a number */
+void uiua_push_num(UiuaInterpreter* interp, double n);
+/*
+ * Push an array of numbers with the given shape
+ *
+ * `data` must point to as many numbers as the product of the `rank` numbers in `shape`.
+ * `shape` may be NULL if `rank` is 0.
+ */
+int uiua_push_nums(UiuaInterpreter* interp, const double* data, const size_t* shape, size_t rank);
+/* Push a null-terminated UTF-8 string as a character array */
+int uiua_push_string(UiuaInterpreter* interp, const char* s);
+/* Push a copy of an array */
+void uiua_push_array(UiuaInterpreter* interp, const UiuaArray* array);
+/* Pop an array from the stack, or return NULL if the stack is empty */
+UiuaArray* uiua_pop(UiuaInterpreter* interp);
+/* Pop a number from the stack into `out` */
+int uiua_pop_num(UiuaInterpreter* interp, double* out);
+
+/* Arrays */
+
+/* Free an array. Does nothing if `array` is NULL. */
+void uiua_array_free(UiuaArray* array);
+/* Get the type of an array's elements */
+UiuaType uiua_array_type(const UiuaArray* array);
+/* Get the rank of an array */
+size_t uiua_array_rank(const UiuaArray* array);
+/* Get the shape of an array, which has `uiua_array_rank` dimensions */
+const size_t* uiua_array_shape(const UiuaArray* array);
+/* Get the number of elements in an array */
+size_t uiua_array_len(const UiuaArray* array);
+/*
+ * Get the elements of a number array, or NULL if it is not one
+ *
+ * For complex arrays, the real and imaginary parts of each element are interleaved.
+ * The data is valid until the array is freed.
+ */
+const double* uiua_array_nums(const UiuaArray* array);
+/*
+ * Get the elements of a character array as a null-terminated UTF-8 string, or NULL if it is not one
+ *
+ * The string is valid until the array is freed.
+ */
+const char* uiua_array_string(const UiuaArray* array);
+/* Get the contents of the box at an index of a box array, or NULL if it is not one or the index is out of bounds */
+UiuaArray* uiua_array_unbox(const UiuaArray* array, size_t index);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
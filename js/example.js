@@ -0,0 +1,45 @@
+// An example of using Uiua from Node.js
+//
+// Build the package with `wasm-pack build --target nodejs --out-name uiua` in this directory,
+// then run this with `node example.js`
+
+const { Uiua, format } = require("./pkg/uiua");
+
+const uiua = new Uiua({ executionLimit: 5 });
+
+// Compile a function once and call it with different arguments
+const mean = uiua.compile("÷⊃⧻/+");
+for (let len = 1; len <= 4; len++) {
+  uiua.push(new Float64Array([1, 2, 3, 4].slice(0, len)));
+  uiua.runProgram(mean);
+  console.log(`mean of ${len} numbers: ${uiua.pop()}`);
+}
+
+// Arrays of any shape are converted to objects with a shape
+uiua.run("⊞×.+1⇡3");
+console.log(uiua.pop()); // { type: 'number', shape: [3, 3], data: Float64Array(9) [...] }
+
+// Programs can use the virtual filesystem
+uiua.writeFile("in.txt", "Hello, World!");
+uiua.run(`&fwa "out.txt" ⌵ &fras "in.txt"`);
+console.log(uiua.readTextFile("out.txt"));
+
+// Output is captured
+uiua.run(`&p "Printed"`);
+console.log(uiua.takeStdout());
+
+// State can be saved and restored later
+uiua.run("5");
+const saved = uiua.snapshot(); // a Uint8Array that can be stored anywhere
+uiua.run("×2");
+uiua.restore(saved);
+console.log(uiua.pop()); // 5
+
+// Errors are thrown with their location
+try {
+  uiua.run("⊢[]");
+} catch (e) {
+  console.log(e.message);
+}
+
+console.log(format("Mean = div fork length reduce add"));
@@ -0,0 +1,377 @@
+//! JavaScript bindings for the Uiua interpreter
+//!
+//! Build the package with `wasm-pack build js --target nodejs --out-name uiua`
+//! (or `--target web` for browsers and bundlers).
+//!
+//! Interpreters run with a [`MemoryBackend`], so programs can only do IO through
+//! the virtual filesystem and captured output streams exposed here.
+
+use std::{sync::Arc, time::Duration};
+
+use js_sys::{Array as JsArray, ArrayBuffer, Float64Array, Object, Reflect, Uint8Array};
+use uiua::{
+    format::{format_str, FormatConfig},
+    Array, Assembly, Boxed, Compiler, Complex, IntoSysBackend, MemoryBackend, Snapshot, SysBackend,
+    Uiua, UiuaError, Value,
+};
+use wasm_bindgen::{prelude::*, JsCast};
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPES: &str = r#"
+/**
+ * A Uiua value
+ *
+ * - Numeric scalars are `number`s
+ * - Character lists are `string`s
+ * - Numeric lists are `Float64Array`s
+ * - Box lists are `Array`s of the boxes' contents
+ * - All other arrays are `UiuaArray`s
+ */
+export type UiuaValue = number | string | Float64Array | UiuaValue[] | UiuaArray;
+
+/** A Uiua array of any shape */
+export interface UiuaArray {
+    type: "number" | "complex" | "character" | "box";
+    shape: number[];
+    /**
+     * The elements in row-major order
+     *
+     * Complex elements have their real and imaginary parts interleaved.
+     */
+    data: Float64Array | string | UiuaValue[];
+}
+
+/** Options for creating an interpreter */
+export interface UiuaOptions {
+    /** The contents of stdin */
+    stdin?: string | Uint8Array;
+    /** The maximum number of seconds a program can run for */
+    executionLimit?: number;
+    /** The values of `&args` */
+    args?: string[];
+}
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "UiuaValue")]
+    pub type JsValueRepr;
+    #[wasm_bindgen(typescript_type = "UiuaValue[]")]
+    pub type JsValues;
+    #[wasm_bindgen(typescript_type = "UiuaOptions")]
+    pub type UiuaOptions;
+    #[wasm_bindgen(typescript_type = "string | Uint8Array")]
+    pub type FileContents;
+    #[wasm_bindgen(typescript_type = "string[]")]
+    pub type Paths;
+}
+
+/// A Uiua interpreter with its own stack and virtual filesystem
+#[wasm_bindgen(js_name = Uiua)]
+pub struct Interpreter {
+    env: Uiua,
+    backend: Arc<dyn SysBackend>,
+}
+
+/// A compiled program
+#[wasm_bindgen]
+pub struct Program(Assembly);
+
+fn report(e: UiuaError) -> JsError {
+    JsError::new(&e.report().color(false).to_string())
+}
+
+fn get(obj: &JsValue, key: &str) -> Option<JsValue> {
+    Reflect::get(obj, &key.into())
+        .ok()
+        .filter(|val| !val.is_undefined() && !val.is_null())
+}
+
+fn bytes_arg(val: &JsValue, name: &str) -> Result<Vec<u8>, JsError> {
+    if let Some(s) = val.as_string() {
+        Ok(s.into_bytes())
+    } else if let Some(arr) = val.dyn_ref::<Uint8Array>() {
+        Ok(arr.to_vec())
+    } else {
+        Err(JsError::new(&format!(
+            "{name} must be a string or a Uint8Array"
+        )))
+    }
+}
+
+#[wasm_bindgen(js_class = Uiua)]
+impl Interpreter {
+    /// Create an interpreter
+    #[wasm_bindgen(constructor)]
+    pub fn new(options: Option<UiuaOptions>) -> Result<Interpreter, JsError> {
+        let options = options.map_or(JsValue::UNDEFINED, JsValue::from);
+        let mut backend = MemoryBackend::new();
+        if let Some(stdin) = get(&options, "stdin") {
+            backend = backend.with_stdin(bytes_arg(&stdin, "stdin")?);
+        }
+        let backend = backend.into_sys_backend();
+        let mut env = Uiua::with_backend(backend.clone());
+        if let Some(limit) = get(&options, "executionLimit") {
+            let limit = (limit.as_f64())
+                .filter(|n| *n >= 0.0)
+                .ok_or_else(|| JsError::new("executionLimit must be a non-negative number"))?;
+            env = env.with_execution_limit(Duration::from_secs_f64(limit));
+        }
+        if let Some(args) = get(&options, "args") {
+            let args = (JsArray::is_array(&args))
+                .then(|| JsArray::from(&args))
+                .and_then(|args| args.iter().map(|arg| arg.as_string()).collect())
+                .ok_or_else(|| JsError::new("args must be an array of strings"))?;
+            env = env.with_args(args);
+        }
+        Ok(Interpreter { env, backend })
+    }
+    fn memory(&self) -> &MemoryBackend {
+        self.env.downcast_backend().unwrap()
+    }
+    /// Compile code into a program
+    ///
+    /// Bindings are not shared between programs.
+    pub fn compile(&self, code: &str) -> Result<Program, JsError> {
+        let mut comp = Compiler::with_backend(self.backend.clone());
+        comp.print_diagnostics(false);
+        comp.load_str(code).map_err(report)?;
+        Ok(Program(comp.finish()))
+    }
+    /// Run a program on the stack
+    #[wasm_bindgen(js_name = runProgram)]
+    pub fn run_program(&mut self, program: &Program) -> Result<(), JsError> {
+        self.env.run_asm(program.0.clone()).map_err(report)
+    }
+    /// Compile and run code on the stack
+    pub fn run(&mut self, code: &str) -> Result<(), JsError> {
+        let asm = self.compile(code)?.0;
+        self.env.run_asm(asm).map_err(report)
+    }
+    /// Push a value onto the stack
+    pub fn push(&mut self, value: JsValueRepr) -> Result<(), JsError> {
+        let value = from_js(&value).map_err(|e| JsError::new(&e))?;
+        self.env.push(value);
+        Ok(())
+    }
+    /// Pop a value from the stack
+    pub fn pop(&mut self) -> Result<JsValueRepr, JsError> {
+        let value = self.env.pop("value").map_err(report)?;
+        Ok(to_js(&value).unchecked_into())
+    }
+    /// Get the values on the stack, from bottom to top
+    pub fn stack(&self) -> JsValues {
+        let values: JsArray = self.env.stack().iter().map(to_js).collect();
+        values.unchecked_into()
+    }
+    /// Remove all values from the stack and return them, from bottom to top
+    #[wasm_bindgen(js_name = takeStack)]
+    pub fn take_stack(&mut self) -> JsValues {
+        let values: JsArray = self.env.take_stack().iter().map(to_js).collect();
+        values.unchecked_into()
+    }
+    /// Save the interpreter's state
+    ///
+    /// This includes the stack, the bindings of the last program run,
+    /// and the seed of the random number generator.
+    /// The virtual filesystem and captured output are not included.
+    pub fn snapshot(&self) -> Vec<u8> {
+        self.env.snapshot().to_bytes()
+    }
+    /// Restore a state saved with `snapshot`
+    pub fn restore(&mut self, snapshot: &[u8]) -> Result<(), JsError> {
+        let snapshot = Snapshot::from_bytes(snapshot).map_err(|e| JsError::new(&e))?;
+        self.env.restore(&snapshot).map_err(|e| JsError::new(&e))
+    }
+    /// Take everything written to stdout so far
+    #[wasm_bindgen(js_name = takeStdout)]
+    pub fn take_stdout(&self) -> String {
+        String::from_utf8_lossy(&self.memory().take_stdout()).into()
+    }
+    /// Take everything written to stderr so far
+    #[wasm_bindgen(js_name = takeStderr)]
+    pub fn take_stderr(&self) -> String {
+        String::from_utf8_lossy(&self.memory().take_stderr()).into()
+    }
+    /// Get the status a program exited with using `&exit`
+    #[wasm_bindgen(js_name = exitStatus)]
+    pub fn exit_status(&self) -> Option<i32> {
+        self.memory().exit_status()
+    }
+    /// Write a file to the virtual filesystem
+    #[wasm_bindgen(js_name = writeFile)]
+    pub fn write_file(&self, path: &str, contents: FileContents) -> Result<(), JsError> {
+        let contents = bytes_arg(&contents, "contents")?;
+        self.memory().set_file(path, contents);
+        Ok(())
+    }
+    /// Read a file from the virtual filesystem
+    #[wasm_bindgen(js_name = readFile)]
+    pub fn read_file(&self, path: &str) -> Option<Vec<u8>> {
+        self.memory().file(path)
+    }
+    /// Read a UTF-8 file from the virtual filesystem
+    #[wasm_bindgen(js_name = readTextFile)]
+    pub fn read_text_file(&self, path: &str) -> Option<String> {
+        (self.memory().file(path)).map(|bytes| String::from_utf8_lossy(&bytes).into())
+    }
+    /// Get the paths of all files in the virtual filesystem
+    pub fn files(&self) -> Paths {
+        let paths: JsArray = (self.memory().file_paths().iter())
+            .map(|path| JsValue::from(path.to_string_lossy().as_ref()))
+            .collect();
+        paths.unchecked_into()
+    }
+}
+
+/// Format code
+#[wasm_bindgen]
+pub fn format(code: &str) -> Result<String, JsError> {
+    let formatted = format_str(code, &FormatConfig::default()).map_err(report)?;
+    Ok(formatted.output)
+}
+
+fn shaped(ty: &str, shape: &[usize], data: JsValue) -> JsValue {
+    let obj = Object::new();
+    let shape: JsArray = shape.iter().map(|&d| JsValue::from(d as f64)).collect();
+    Reflect::set(&obj, &"type".into(), &ty.into()).unwrap();
+    Reflect::set(&obj, &"shape".into(), &shape).unwrap();
+    Reflect::set(&obj, &"data".into(), &data).unwrap();
+    obj.into()
+}
+
+/// Convert a value to its JavaScript representation
+fn to_js(value: &Value) -> JsValue {
+    let shape = value.shape();
+    let nums = |nums: Vec<f64>| JsValue::from(Float64Array::from(nums.as_slice()));
+    match value {
+        Value::Num(arr) => match (arr.as_scalar(), arr.rank()) {
+            (Some(&n), _) => n.into(),
+            (None, 1) => nums(arr.elements().copied().collect()),
+            _ => shaped("number", shape, nums(arr.elements().copied().collect())),
+        },
+        Value::Byte(arr) => match (arr.as_scalar(), arr.rank()) {
+            (Some(&n), _) => (n as f64).into(),
+            (None, 1) => nums(arr.elements().map(|&n| n as f64).collect()),
+            _ => shaped(
+                "number",
+                shape,
+                nums(arr.elements().map(|&n| n as f64).collect()),
+            ),
+        },
+        Value::Complex(arr) => {
+            let data = nums(arr.elements().flat_map(|c| [c.re, c.im]).collect());
+            shaped("complex", shape, data)
+        }
+        Value::Char(arr) => {
+            let s: String = arr.elements().collect();
+            if arr.rank() == 1 {
+                s.into()
+            } else {
+                shaped("character", shape, s.into())
+            }
+        }
+        Value::Box(arr) => {
+            let data: JsArray = arr.elements().map(|b| to_js(&b.0)).collect();
+            if arr.rank() == 1 {
+                data.into()
+            } else {
+                shaped("box", shape, data.into())
+            }
+        }
+    }
+}
+
+/// Convert a JavaScript value to a value
+fn from_js(val: &JsValue) -> Result<Value, String> {
+    if let Some(n) = val.as_f64() {
+        Ok(n.into())
+    } else if let Some(b) = val.as_bool() {
+        Ok(b.into())
+    } else if let Some(s) = val.as_string() {
+        Ok(s.into())
+    } else if let Some(arr) = val.dyn_ref::<Uint8Array>() {
+        Ok(Array::<u8>::from(arr.to_vec().as_slice()).into())
+    } else if ArrayBuffer::is_view(val) {
+        Ok(Array::<f64>::from(Float64Array::new(val).to_vec().as_slice()).into())
+    } else if JsArray::is_array(val) {
+        let boxes = (JsArray::from(val).iter())
+            .map(|val| from_js(&val).map(Boxed))
+            .collect::<Result<Array<Boxed>, _>>()?;
+        Ok(boxes.into())
+    } else if val.is_object() {
+        from_shaped(val)
+    } else {
+        Err(format!("Cannot convert {val:?} to a Uiua value"))
+    }
+}
+
+/// Convert a JavaScript [`UiuaArray`] to a value
+fn from_shaped(val: &JsValue) -> Result<Value, String> {
+    let missing = |key: &str| format!("Array is missing its {key:?} property");
+    let ty = get(val, "type").and_then(|ty| ty.as_string());
+    let ty = ty.ok_or_else(|| missing("type"))?;
+    let shape = get(val, "shape").ok_or_else(|| missing("shape"))?;
+    let shape = (JsArray::is_array(&shape))
+        .then(|| JsArray::from(&shape))
+        .and_then(|dims| {
+            (dims.iter())
+                .map(|d| d.as_f64().filter(|d| *d >= 0.0 && d.fract() == 0.0))
+                .map(|d| d.map(|d| d as usize))
+                .collect::<Option<Vec<_>>>()
+        })
+        .ok_or("Array shape must be an array of natural numbers")?;
+    let data = get(val, "data").ok_or_else(|| missing("data"))?;
+    let len = shape.iter().try_fold(1usize, |acc, &d| acc.checked_mul(d));
+    let check = |data_len: usize| {
+        if Some(data_len) == len {
+            Ok(())
+        } else {
+            Err(format!(
+                "Array of shape {shape:?} cannot have {data_len} elements"
+            ))
+        }
+    };
+    let nums = || {
+        (ArrayBuffer::is_view(&data))
+            .then(|| Float64Array::new(&data).to_vec())
+            .ok_or_else(|| format!("Data of a {ty} array must be a typed array"))
+    };
+    Ok(match ty.as_str() {
+        "number" => {
+            let nums = nums()?;
+            check(nums.len())?;
+            Array::new(shape, nums.as_slice()).into()
+        }
+        "complex" => {
+            let nums = nums()?;
+            if nums.len() % 2 != 0 {
+                return Err("Data of a complex array must have an even length".into());
+            }
+            check(nums.len() / 2)?;
+            let elems: Vec<_> = (nums.chunks_exact(2))
+                .map(|c| Complex::new(c[0], c[1]))
+                .collect();
+            Array::new(shape, elems.as_slice()).into()
+        }
+        "character" => {
+            let chars: Vec<char> = (data.as_string())
+                .ok_or("Data of a character array must be a string")?
+                .chars()
+                .collect();
+            check(chars.len())?;
+            Array::new(shape, chars.as_slice()).into()
+        }
+        "box" => {
+            if !JsArray::is_array(&data) {
+                return Err("Data of a box array must be an array".into());
+            }
+            let boxes = (JsArray::from(&data).iter())
+                .map(|val| from_js(&val).map(Boxed))
+                .collect::<Result<Vec<_>, _>>()?;
+            check(boxes.len())?;
+            Array::new(shape, boxes.as_slice()).into()
+        }
+        ty => return Err(format!("Unknown array type {ty:?}")),
+    })
+}
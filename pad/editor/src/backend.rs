@@ -2,8 +2,10 @@ use std::{
     any::Any,
     cell::RefCell,
     collections::{BTreeSet, HashMap},
+    future::Future,
     hash::{DefaultHasher, Hash, Hasher},
     io::Cursor,
+    mem::take,
     path::{Path, PathBuf},
     sync::{
         atomic::{AtomicUsize, Ordering},
@@ -11,19 +13,34 @@ use std::{
     },
 };
 
-use crate::{get_ast_time, START_TIME};
+use crate::{
+    get_ast_time,
+    worker::{in_worker, spawn_thread, stream_stdout, threads_available},
+    START_TIME,
+};
 use js_sys::Date;
 use leptos::*;
+use serde::{Deserialize, Serialize};
 use uiua::{now, GitTarget, Handle, Report, Span, SysBackend, Uiua, EXAMPLE_TXT, EXAMPLE_UA};
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::JsFuture;
-use web_sys::{HtmlAudioElement, Request, RequestInit, RequestMode, Response};
+use web_sys::{
+    CustomEvent, CustomEventInit, FileSystemDirectoryHandle, FileSystemFileHandle,
+    FileSystemGetDirectoryOptions, FileSystemGetFileOptions, FileSystemWritableFileStream, Headers,
+    HtmlAudioElement, Request, RequestInit, RequestMode, Response, WorkerGlobalScope,
+};
 
 pub struct WebBackend {
     pub stdout: Mutex<Vec<OutputItem>>,
     pub stderr: Mutex<String>,
     pub trace: Mutex<String>,
     streams: Mutex<HashMap<Handle, VirtualStream>>,
+    /// The addresses of connected TCP sockets
+    sockets: Mutex<HashMap<Handle, String>>,
+    /// The keys of asynchronous results used in this run
+    used_results: Mutex<Vec<String>>,
+    /// The total number of seconds slept in this run
+    slept: Mutex<f64>,
     id: u64,
     breakpoint: AtomicUsize,
 }
@@ -59,13 +76,123 @@ fn weewuh() {
 }
 
 pub fn drop_file(path: PathBuf, contents: Vec<u8>) {
+    file_changed(&path);
     FILES.with(|files| files.borrow_mut().insert(path, contents));
 }
 
 pub fn delete_file(path: &PathBuf) {
+    file_changed(path);
     FILES.with(|files| files.borrow_mut().remove(path));
 }
 
+thread_local! {
+    /// Paths of files that have changed since they were last persisted
+    static CHANGED_FILES: RefCell<BTreeSet<PathBuf>> = Default::default();
+}
+
+fn file_changed(path: &Path) {
+    CHANGED_FILES.with(|changed| changed.borrow_mut().insert(path.into()));
+}
+
+/// Take the paths of the files that have changed since the last call
+pub(crate) fn take_changed_files() -> BTreeSet<PathBuf> {
+    CHANGED_FILES.with(|changed| take(&mut *changed.borrow_mut()))
+}
+
+/// The directory in the origin private file system where files are persisted
+const PERSIST_DIR: &str = "uiua-files";
+
+async fn persist_dir() -> Result<FileSystemDirectoryHandle, JsValue> {
+    let root = JsFuture::from(window().navigator().storage().get_directory()).await?;
+    let root: FileSystemDirectoryHandle = root.dyn_into()?;
+    let options = FileSystemGetDirectoryOptions::new();
+    options.set_create(true);
+    let dir = JsFuture::from(root.get_directory_handle_with_options(PERSIST_DIR, &options)).await?;
+    dir.dyn_into()
+}
+
+/// Save the files that have changed to the origin private file system
+///
+/// The directory is flat, so paths are percent-encoded into file names.
+pub fn persist_files() {
+    let changed = take_changed_files();
+    if changed.is_empty() {
+        return;
+    }
+    spawn_local(async move {
+        if let Err(e) = persist_changed(changed).await {
+            logging::warn!("Failed to persist files: {e:?}");
+        }
+    });
+}
+
+async fn persist_changed(changed: BTreeSet<PathBuf>) -> Result<(), JsValue> {
+    let dir = persist_dir().await?;
+    for path in changed {
+        let name = urlencoding::encode(&path.to_string_lossy()).into_owned();
+        let Some(contents) = FILES.with(|files| files.borrow().get(&path).cloned()) else {
+            _ = JsFuture::from(dir.remove_entry(&name)).await;
+            continue;
+        };
+        let options = FileSystemGetFileOptions::new();
+        options.set_create(true);
+        let file = JsFuture::from(dir.get_file_handle_with_options(&name, &options)).await?;
+        let file: FileSystemFileHandle = file.dyn_into()?;
+        let stream = JsFuture::from(file.create_writable()).await?;
+        let stream: FileSystemWritableFileStream = stream.dyn_into()?;
+        JsFuture::from(stream.write_with_u8_array(&contents)?).await?;
+        JsFuture::from(stream.close()).await?;
+    }
+    Ok(())
+}
+
+/// Load the files saved by [`persist_files`]
+///
+/// Files that already exist are not overwritten.
+/// Returns the number of files loaded.
+pub async fn load_persisted_files() -> Result<usize, JsValue> {
+    let dir = persist_dir().await?;
+    let names = dir.keys();
+    let mut count = 0;
+    loop {
+        let next: js_sys::IteratorNext = JsFuture::from(names.next()?).await?.unchecked_into();
+        if next.done() {
+            break;
+        }
+        let Some(name) = next.value().as_string() else {
+            continue;
+        };
+        let Ok(path) = urlencoding::decode(&name) else {
+            continue;
+        };
+        let path = PathBuf::from(path.into_owned());
+        if FILES.with(|files| files.borrow().contains_key(&path)) {
+            continue;
+        }
+        let file = JsFuture::from(dir.get_file_handle(&name)).await?;
+        let file: FileSystemFileHandle = file.dyn_into()?;
+        let file: web_sys::File = JsFuture::from(file.get_file()).await?.dyn_into()?;
+        let buffer = JsFuture::from(file.array_buffer()).await?;
+        let contents = js_sys::Uint8Array::new(&buffer).to_vec();
+        FILES.with(|files| files.borrow_mut().insert(path, contents));
+        count += 1;
+    }
+    Ok(count)
+}
+
+thread_local! {
+    /// The results of asynchronous browser operations, by key
+    ///
+    /// `None` means the operation has not finished yet.
+    static ASYNC_RESULTS: RefCell<HashMap<String, Option<Result<String, String>>>> = Default::default();
+}
+
+const BACKGROUND_INPUT: &str = "Input is not available when running in the background. \
+    You can turn off running in the background in the editor settings.";
+const BACKGROUND_CLIPBOARD: &str =
+    "The clipboard is not available when running in the background. \
+    You can turn off running in the background in the editor settings.";
+
 thread_local! {
     static BREAKPOINTS: RefCell<HashMap<u64, (u64, usize)>> = Default::default();
 }
@@ -97,6 +224,9 @@ impl WebBackend {
             stderr: String::new().into(),
             trace: String::new().into(),
             streams: HashMap::new().into(),
+            sockets: HashMap::new().into(),
+            used_results: Vec::new().into(),
+            slept: 0.0.into(),
             id,
             breakpoint: AtomicUsize::new(0),
         }
@@ -108,9 +238,50 @@ impl WebBackend {
                 *bp = 0;
             }
         });
+        // Asynchronous results are only reused by the rerun that waited for them
+        let used = take(&mut *self.used_results.lock().unwrap());
+        ASYNC_RESULTS.with(|results| {
+            let mut results = results.borrow_mut();
+            for key in used {
+                results.remove(&key);
+            }
+        });
+    }
+    /// Get the result of an asynchronous operation
+    ///
+    /// The code can not wait for the operation, so the first time this is called with a key,
+    /// the operation is started and an error is returned.
+    /// The editor reruns the code when it sees the error, by which time the result may be ready.
+    fn await_result<F>(
+        &self,
+        key: String,
+        waiting_for: &str,
+        start: impl FnOnce() -> F,
+    ) -> Result<String, String>
+    where
+        F: Future<Output = Result<String, String>> + 'static,
+    {
+        let waiting = format!("Waiting for {waiting_for}, try running to check...");
+        match ASYNC_RESULTS.with(|results| results.borrow().get(&key).cloned()) {
+            Some(Some(res)) => {
+                self.used_results.lock().unwrap().push(key);
+                res
+            }
+            Some(None) => Err(waiting),
+            None => {
+                ASYNC_RESULTS.with(|results| results.borrow_mut().insert(key.clone(), None));
+                let fut = start();
+                spawn_local(async move {
+                    let res = fut.await;
+                    ASYNC_RESULTS.with(|results| results.borrow_mut().insert(key, Some(res)));
+                });
+                Err(waiting)
+            }
+        }
     }
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub enum OutputItem {
     String(String),
     Svg(String),
@@ -119,7 +290,16 @@ pub enum OutputItem {
     Audio(Vec<u8>, Option<String>),
     Report(Report),
     Faint(String),
-    Classed(&'static str, String),
+    Classed(String, String),
+    /// A MathML element
+    Math(String),
+    /// A pause of some number of seconds before the rest of the output
+    Delay(f64),
+    Progress {
+        label: String,
+        current: f64,
+        total: f64,
+    },
     Separator,
 }
 
@@ -132,8 +312,9 @@ impl OutputItem {
 impl WebBackend {
     fn new_handle(&self) -> Handle {
         let streams = self.streams.lock().unwrap();
+        let sockets = self.sockets.lock().unwrap();
         for handle in (Handle::FIRST_UNRESERVED.0..u64::MAX).map(Handle) {
-            if !streams.contains_key(&handle) {
+            if !streams.contains_key(&handle) && !sockets.contains_key(&handle) {
                 return handle;
             }
         }
@@ -150,6 +331,28 @@ impl WebBackend {
     }
 }
 
+/// Get the origin to fetch from for an address passed to `&tlsc`
+///
+/// IPv6 addresses must be in brackets if they have a port.
+fn https_origin(addr: &str) -> String {
+    let (host, port) = if let Some(rest) = addr.strip_prefix('[') {
+        let (ip, rest) = rest.split_once(']').unwrap_or((rest, ""));
+        (format!("[{ip}]"), rest.strip_prefix(':'))
+    } else if addr.matches(':').count() > 1 {
+        (format!("[{addr}]"), None)
+    } else {
+        match addr.rsplit_once(':') {
+            Some((host, port)) => (host.into(), Some(port)),
+            None => (addr.into(), None),
+        }
+    };
+    match port {
+        None | Some("443") => format!("https://{host}"),
+        Some("80") => format!("http://{host}"),
+        Some(port) => format!("https://{host}:{port}"),
+    }
+}
+
 impl SysBackend for WebBackend {
     fn any(&self) -> &dyn Any {
         self
@@ -158,6 +361,7 @@ impl SysBackend for WebBackend {
         self
     }
     fn print_str_stdout(&self, s: &str) -> Result<(), String> {
+        stream_stdout(s);
         if s.contains('\u{07}') {
             weewuh();
         }
@@ -187,10 +391,19 @@ impl SysBackend for WebBackend {
         self.trace.lock().unwrap().push_str(s);
     }
     fn scan_line_stdin(&self) -> Result<Option<String>, String> {
+        if in_worker() {
+            return Err(BACKGROUND_INPUT.into());
+        }
         Ok(window()
             .prompt_with_message("Enter a line of text for stdin")
             .unwrap_or(None))
     }
+    fn prompt(&self, prompt: &str, _hidden: bool) -> Result<Option<String>, String> {
+        if in_worker() {
+            return Err(BACKGROUND_INPUT.into());
+        }
+        Ok(window().prompt_with_message(prompt).unwrap_or(None))
+    }
     fn show_image(&self, image: image::DynamicImage, label: Option<&str>) -> Result<(), String> {
         let mut bytes = Cursor::new(Vec::new());
         image
@@ -202,6 +415,43 @@ impl SysBackend for WebBackend {
             .push(OutputItem::Image(bytes.into_inner(), label.map(Into::into)));
         Ok(())
     }
+    fn progress(&self, current: f64, total: f64, label: &str) -> Result<(), String> {
+        if cfg!(target_arch = "wasm32") && !in_worker() {
+            let detail = js_sys::Object::new();
+            for (key, value) in [
+                ("label", JsValue::from_str(label)),
+                ("current", current.into()),
+                ("total", total.into()),
+            ] {
+                _ = js_sys::Reflect::set(&detail, &key.into(), &value);
+            }
+            let init = CustomEventInit::new();
+            init.set_detail(&detail);
+            if let Ok(event) = CustomEvent::new_with_event_init_dict("uiua-progress", &init) {
+                _ = window().dispatch_event(&event);
+            }
+        }
+        let mut stdout = self.stdout.lock().unwrap();
+        let existing = stdout.iter_mut().rev().find_map(|item| match item {
+            OutputItem::Progress {
+                label: l,
+                current,
+                total,
+            } if l == label => Some((current, total)),
+            _ => None,
+        });
+        if let Some((c, t)) = existing {
+            *c = current;
+            *t = total;
+        } else {
+            stdout.push(OutputItem::Progress {
+                label: label.into(),
+                current,
+                total,
+            });
+        }
+        Ok(())
+    }
     fn show_gif(&self, gif_bytes: Vec<u8>, label: Option<&str>) -> Result<(), String> {
         (self.stdout.lock().unwrap()).push(OutputItem::Gif(gif_bytes, label.map(Into::into)));
         Ok(())
@@ -230,6 +480,7 @@ impl SysBackend for WebBackend {
         self.file(path.as_ref(), |_| {}).is_ok()
     }
     fn file_write_all(&self, path: &Path, contents: &[u8]) -> Result<(), String> {
+        file_changed(path);
         FILES.with(|files| {
             if !files.borrow().contains_key(path) {
                 files.borrow_mut().insert(path.into(), contents.to_vec());
@@ -239,6 +490,13 @@ impl SysBackend for WebBackend {
         });
         Ok(())
     }
+    fn file_append(&self, path: &Path, contents: &[u8]) -> Result<(), String> {
+        file_changed(path);
+        FILES.with(|files| {
+            (files.borrow_mut().entry(path.into()).or_default()).extend_from_slice(contents)
+        });
+        Ok(())
+    }
     fn file_read_all(&self, path: &Path) -> Result<Vec<u8>, String> {
         self.file(path, |contents| contents.to_vec())
     }
@@ -258,6 +516,7 @@ impl SysBackend for WebBackend {
     }
     fn create_file(&self, path: &Path) -> Result<Handle, String> {
         let handle = self.new_handle();
+        file_changed(path);
         FILES.with(|files| files.borrow_mut().insert(path.into(), Vec::new()));
         self.streams.lock().unwrap().insert(
             handle,
@@ -271,6 +530,9 @@ impl SysBackend for WebBackend {
         Ok(handle)
     }
     fn close(&self, handle: Handle) -> Result<(), String> {
+        if self.sockets.lock().unwrap().remove(&handle).is_some() {
+            return Ok(());
+        }
         let stream = self
             .streams
             .lock()
@@ -324,6 +586,7 @@ impl SysBackend for WebBackend {
         Ok(data)
     }
     fn delete(&self, path: &str) -> Result<(), String> {
+        file_changed(Path::new(path));
         FILES.with(|files| files.borrow_mut().remove(Path::new(path)));
         Ok(())
     }
@@ -367,26 +630,67 @@ impl SysBackend for WebBackend {
         self.play_audio(bytes, None)
     }
     fn now(&self) -> f64 {
-        *START_TIME.get_or_init(|| 0.0) + now()
+        *START_TIME.get_or_init(|| 0.0) + now() + *self.slept.lock().unwrap()
     }
     fn clipboard(&self) -> Result<String, String> {
-        Ok(window()
-            .prompt_with_message("Paste clipboard contents")
-            .unwrap_or(None)
-            .unwrap_or_default())
+        if in_worker() {
+            return Err(BACKGROUND_CLIPBOARD.into());
+        }
+        // The clipboard API is only available in secure contexts
+        if !window().is_secure_context() {
+            return Ok(window()
+                .prompt_with_message("Paste clipboard contents")
+                .unwrap_or(None)
+                .unwrap_or_default());
+        }
+        self.await_result("clipboard".into(), "clipboard", || async {
+            let text = window().navigator().clipboard().read_text();
+            (JsFuture::from(text).await)
+                .map(|text| text.as_string().unwrap_or_default())
+                .map_err(|e| format!("Failed to read clipboard: {e:?}"))
+        })
     }
     fn set_clipboard(&self, contents: &str) -> Result<(), String> {
-        _ = window().navigator().clipboard().write_text(contents);
+        if in_worker() {
+            return Err(BACKGROUND_CLIPBOARD.into());
+        }
+        // The clipboard API is only available in secure contexts,
+        // and writing silently fails if permission is denied
+        if window().is_secure_context() {
+            _ = window().navigator().clipboard().write_text(contents);
+        }
         Ok(())
     }
     fn sleep(&self, seconds: f64) -> Result<(), String> {
-        let start = now();
-        while now() - start < seconds {}
+        // Blocking would freeze the page, so time is skipped ahead instead,
+        // and the editor shows the rest of the output after a timer
+        *self.slept.lock().unwrap() += seconds;
+        self.stdout.lock().unwrap().push(OutputItem::Delay(seconds));
         Ok(())
     }
+    fn tcp_connect(&self, addr: &str) -> Result<Handle, String> {
+        // No connection is made until a request is sent with fetch
+        let handle = self.new_handle();
+        self.sockets.lock().unwrap().insert(handle, addr.into());
+        Ok(handle)
+    }
+    fn https_get(&self, request: &str, handle: Handle) -> Result<String, String> {
+        let addr = (self.sockets.lock().unwrap().get(&handle).cloned())
+            .ok_or("Invalid tcp socket handle")?;
+        let origin = https_origin(&addr);
+        let request = HttpRequest::parse(request)?;
+        let key = format!("https {origin} {request:?}");
+        self.await_result(key, "response", move || request.fetch(origin))
+    }
     fn allow_thread_spawning(&self) -> bool {
         true
     }
+    fn can_spawn_threads(&self) -> bool {
+        threads_available()
+    }
+    fn spawn_thread(&self, f: Box<dyn FnOnce() + Send>) -> Result<(), String> {
+        spawn_thread(f).map_err(|e| format!("{e:?}"))
+    }
     fn load_git_module(&self, url: &str, target: GitTarget) -> Result<PathBuf, String> {
         match target {
             GitTarget::Default => {}
@@ -396,6 +700,9 @@ impl SysBackend for WebBackend {
             GitTarget::Commit(_) => {
                 return Err("Git commit specification is not supported in the web backend".into())
             }
+            GitTarget::Tag(_) => {
+                return Err("Git tag specification is not supported in the web backend".into())
+            }
         }
         let mut parts = url.rsplitn(3, '/');
         let repo_name = parts.next().ok_or("Invalid git url")?;
@@ -478,13 +785,20 @@ impl SysBackend for WebBackend {
     }
 }
 
+/// Fetch a request from either the page or the worker
+fn fetch_request(request: &Request) -> js_sys::Promise {
+    match js_sys::global().dyn_into::<WorkerGlobalScope>() {
+        Ok(scope) => scope.fetch_with_request(request),
+        Err(_) => window().fetch_with_request(request),
+    }
+}
+
 pub async fn fetch(url: &str) -> Result<String, String> {
     let opts = RequestInit::new();
     opts.set_method("GET");
     opts.set_mode(RequestMode::Cors);
     let request = Request::new_with_str_and_init(url, &opts).map_err(|e| format!("{e:?}"))?;
-    let window = web_sys::window().unwrap();
-    let resp_value = JsFuture::from(window.fetch_with_request(&request))
+    let resp_value = JsFuture::from(fetch_request(&request))
         .await
         .map_err(|e| format!("{e:?}"))?;
     assert!(resp_value.is_instance_of::<Response>());
@@ -499,3 +813,75 @@ pub async fn fetch(url: &str) -> Result<String, String> {
         Err(text)
     }
 }
+
+/// An HTTP request parsed from the text passed to `&httpsw`
+#[derive(Debug)]
+struct HttpRequest {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl HttpRequest {
+    fn parse(request: &str) -> Result<Self, String> {
+        let (head, body) = (request.split_once("\r\n\r\n"))
+            .or_else(|| request.split_once("\n\n"))
+            .unwrap_or((request, ""));
+        let mut lines = head.lines();
+        let mut first = lines.next().ok_or("Empty HTTP request")?.split_whitespace();
+        let method = first.next().ok_or("Empty first line")?.to_uppercase();
+        let path = first.next().unwrap_or("/").to_string();
+        let mut headers = Vec::new();
+        for line in lines.filter(|line| !line.trim().is_empty()) {
+            let (name, value) =
+                (line.split_once(':')).ok_or_else(|| format!("Invalid HTTP header: {line:?}"))?;
+            headers.push((name.trim().into(), value.trim().into()));
+        }
+        Ok(HttpRequest {
+            method,
+            path,
+            headers,
+            body: body.into(),
+        })
+    }
+    /// Make the request with fetch and format the response like an HTTP response
+    async fn fetch(self, origin: String) -> Result<String, String> {
+        let opts = RequestInit::new();
+        opts.set_method(&self.method);
+        opts.set_mode(RequestMode::Cors);
+        let headers = Headers::new().map_err(|e| format!("{e:?}"))?;
+        for (name, value) in &self.headers {
+            // The browser sets these itself
+            if ["host", "content-length", "connection"].contains(&name.to_lowercase().as_str()) {
+                continue;
+            }
+            headers.append(name, value).map_err(|e| format!("{e:?}"))?;
+        }
+        opts.set_headers(&headers);
+        if !self.body.is_empty() {
+            opts.set_body(&JsValue::from_str(&self.body));
+        }
+        let url = format!("{origin}{}", self.path);
+        let request = Request::new_with_str_and_init(&url, &opts).map_err(|e| format!("{e:?}"))?;
+        let resp = JsFuture::from(fetch_request(&request))
+            .await
+            .map_err(|e| format!("Failed to fetch {url}: {e:?}"))?;
+        let resp: Response = resp.dyn_into().map_err(|e| format!("{e:?}"))?;
+        let mut text = format!("HTTP/1.1 {} {}\r\n", resp.status(), resp.status_text());
+        if let Ok(Some(entries)) = js_sys::try_iter(&resp.headers()) {
+            for entry in entries.flatten() {
+                let entry = js_sys::Array::from(&entry);
+                let name = entry.get(0).as_string().unwrap_or_default();
+                let value = entry.get(1).as_string().unwrap_or_default();
+                text.push_str(&format!("{name}: {value}\r\n"));
+            }
+        }
+        text.push_str("\r\n");
+        let body = JsFuture::from(resp.text().map_err(|e| format!("{e:?}"))?)
+            .await
+            .map_err(|e| format!("{e:?}"))?;
+        text.push_str(&body.as_string().unwrap_or_default());
+        Ok(text)
+    }
+}
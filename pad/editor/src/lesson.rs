@@ -0,0 +1,56 @@
+//! Lessons for the editor's tutorial mode
+
+use serde::Deserialize;
+
+/// An interactive lesson made of steps
+///
+/// Lessons are loaded from JSON like this:
+/// ```json
+/// {
+///   "title": "Arithmetic",
+///   "steps": [
+///     {
+///       "prose": "Add 1 and 2.",
+///       "code": "",
+///       "check": "≍3"
+///     }
+///   ]
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Lesson {
+    /// The title of the lesson
+    #[serde(default)]
+    pub title: String,
+    /// The steps of the lesson
+    pub steps: Vec<LessonStep>,
+}
+
+/// A step of a [`Lesson`]
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LessonStep {
+    /// The text explaining the step
+    ///
+    /// Paragraphs are separated by blank lines.
+    pub prose: String,
+    /// The starter code
+    #[serde(default)]
+    pub code: String,
+    /// Code that checks the user's output
+    ///
+    /// It is run with the values the user's code left on the stack.
+    /// It should return `1` if the output is correct, or a string explaining what is wrong.
+    #[serde(default)]
+    pub check: Option<String>,
+}
+
+impl Lesson {
+    /// Load a lesson from JSON
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let lesson: Lesson = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        if lesson.steps.is_empty() {
+            return Err("A lesson must have at least one step".into());
+        }
+        Ok(lesson)
+    }
+}
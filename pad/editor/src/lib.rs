@@ -1,13 +1,19 @@
 pub mod backend;
+pub mod lesson;
 pub mod utils;
+pub mod worker;
 
-use std::{cell::Cell, iter::repeat, mem::take, path::PathBuf, rc::Rc, time::Duration};
+use std::{
+    cell::Cell, collections::VecDeque, iter::repeat, mem::take, path::PathBuf, rc::Rc,
+    time::Duration,
+};
 
 use base64::engine::{general_purpose::STANDARD, Engine};
 
 use ev::mousemove;
 use leptos::{
     ev::{keydown, keyup},
+    leptos_dom::helpers::TimeoutHandle,
     *,
 };
 
@@ -21,15 +27,18 @@ use uiua::{
 use wasm_bindgen::{closure::Closure, JsCast, JsValue};
 use web_sys::{
     DragEvent, Event, FileList, FileReader, HtmlAnchorElement, HtmlDivElement, HtmlInputElement,
-    HtmlSelectElement, HtmlTextAreaElement, MouseEvent,
+    HtmlSelectElement, HtmlTextAreaElement, MouseEvent, ScrollIntoViewOptions,
+    ScrollLogicalPosition,
 };
 
 use utils::*;
 use utils::{element, format_insert_file_code, get_ast_time};
 
-use backend::{delete_file, drop_file, OutputItem};
+use backend::{delete_file, drop_file, load_persisted_files, persist_files, OutputItem};
 use js_sys::Date;
+use lesson::Lesson;
 use std::sync::OnceLock;
+use worker::{run_in_worker, stop_worker, worker_available, RunResult};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum EditorMode {
@@ -37,6 +46,7 @@ pub enum EditorMode {
     Example,
     Showcase,
     Pad,
+    Tutorial,
 }
 
 thread_local! {
@@ -45,6 +55,17 @@ thread_local! {
 
 static START_TIME: OnceLock<f64> = OnceLock::new();
 
+/// The number of output items in each chunk of a long output
+///
+/// Only the chunks near the visible part of the page are rendered.
+const OUTPUT_CHUNK_LEN: usize = 200;
+/// The estimated height in pixels of an output item that has not been rendered
+const OUTPUT_ITEM_HEIGHT: f64 = 20.0;
+/// The number of lines of stdout shown while code is running
+const RUNNING_OUTPUT_LEN: usize = 500;
+/// How long a glyph button must be pressed to show its variants
+const LONG_PRESS_MS: u64 = 500;
+
 /// An editor for Uiua code
 #[component]
 pub fn Editor<'a>(
@@ -55,6 +76,7 @@ pub fn Editor<'a>(
     #[prop(optional)] challenge: Option<ChallengeDef>,
     #[prop(optional)] nonprogressive: bool,
     #[prop(optional)] examples: Option<Vec<String>>,
+    #[prop(optional)] lesson: Option<Lesson>,
 ) -> impl IntoView {
     START_TIME.get_or_init(|| Date::now() / 1000.0);
 
@@ -73,6 +95,11 @@ pub fn Editor<'a>(
     let examples = match mode {
         EditorMode::Example if !nonprogressive => progressive_strings(example),
         EditorMode::Showcase => examples.unwrap_or_default(),
+        EditorMode::Tutorial if lesson.is_some() => {
+            (lesson.iter().flat_map(|lesson| &lesson.steps))
+                .map(|step| step.code.clone())
+                .collect()
+        }
         _ => vec![example.into()],
     };
     let code_max_lines = if let EditorMode::Pad = mode {
@@ -86,6 +113,12 @@ pub fn Editor<'a>(
             .max()
             .unwrap()
     };
+    // Leave room to write code in tutorial steps that have little starter code
+    let code_max_lines = if let EditorMode::Tutorial = mode {
+        code_max_lines.max(3)
+    } else {
+        code_max_lines
+    };
     let code_height_em = code_max_lines as f32 * 1.25;
 
     let code_id = move || format!("code{id}");
@@ -109,6 +142,15 @@ pub fn Editor<'a>(
     let (example, set_example) = create_signal(0);
     let (diag_output, set_diag_output) = create_signal(View::default());
     let (output, set_output) = create_signal(View::default());
+    let (output_pages, set_output_pages) = create_signal(Vec::<View>::new());
+    let output_chunks = store_value(Vec::<Vec<OutputItem>>::new());
+    let chunk_heights = store_value(Vec::<Option<f64>>::new());
+    let (visible_chunks, set_visible_chunks) = create_signal(0..0);
+    let output_generation = store_value(0u64);
+    let history = store_value(RunHistory::new());
+    let final_output = store_value(Vec::<OutputItem>::new());
+    let (history_len, set_history_len) = create_signal(0);
+    let (history_pos, set_history_pos) = create_signal(0);
     let (token_count, set_token_count) = create_signal(0);
 
     // let code_text = move || code_text(&code_id());
@@ -139,7 +181,11 @@ pub fn Editor<'a>(
         past: Vec::new(),
         future: Vec::new(),
         challenge,
-        loading_module: false,
+        waiting: false,
+        history: Vec::new(),
+        lesson: lesson.clone(),
+        lesson_step: 0,
+        step_passed: false,
         curr: {
             let code = initial_code.get_untracked().unwrap();
             let len = code.chars().count() as u32;
@@ -249,106 +295,307 @@ pub fn Editor<'a>(
         (input, seed)
     };
 
+    // Find which chunks of a long output are near the visible part of the page
+    //
+    // Chunks that have not been rendered yet have an estimated height.
+    let update_visible_chunks = move || {
+        if output_chunks.with_value(Vec::is_empty) {
+            return;
+        }
+        let Some(output) = window()
+            .document()
+            .unwrap()
+            .get_element_by_id(&format!("output-{id}"))
+        else {
+            return;
+        };
+        let view_height = (window().inner_height().ok())
+            .and_then(|h| h.as_f64())
+            .unwrap_or(1000.0);
+        // Render a screen's worth of output above and below the visible part
+        let (min, max) = (-view_height, 2.0 * view_height);
+        let mut y = output.get_bounding_client_rect().top();
+        let visible = chunk_heights.with_value(|heights| {
+            let measured: Vec<_> = heights
+                .iter()
+                .enumerate()
+                .filter_map(|(i, h)| h.map(|h| (i, h)))
+                .collect();
+            let item_height = if measured.is_empty() {
+                OUTPUT_ITEM_HEIGHT
+            } else {
+                let items: usize = output_chunks
+                    .with_value(|chunks| measured.iter().map(|&(i, _)| chunks[i].len()).sum());
+                measured.iter().map(|&(_, h)| h).sum::<f64>() / items.max(1) as f64
+            };
+            let mut visible = 0..0;
+            output_chunks.with_value(|chunks| {
+                for (i, chunk) in chunks.iter().enumerate() {
+                    let height = heights[i].unwrap_or(item_height * chunk.len() as f64);
+                    if y + height >= min && y <= max {
+                        if visible.is_empty() {
+                            visible = i..i + 1;
+                        } else {
+                            visible.end = i + 1;
+                        }
+                    }
+                    y += height;
+                }
+            });
+            visible
+        });
+        if visible_chunks.get_untracked() != visible {
+            set_visible_chunks.set(visible);
+        }
+    };
+
+    // Measure the rendered chunks of a long output
+    let measure_chunks = move |generation: u64| {
+        if output_generation.get_value() != generation {
+            return;
+        }
+        let document = window().document().unwrap();
+        chunk_heights.update_value(|heights| {
+            for i in visible_chunks.get_untracked() {
+                if let Some(chunk) = document.get_element_by_id(&format!("output-{id}-chunk-{i}")) {
+                    heights[i] = Some(chunk.get_bounding_client_rect().height());
+                }
+            }
+        });
+        update_visible_chunks();
+    };
+
+    // Keep the rendered chunks up to date as the page scrolls or resizes
+    //
+    // Scroll events do not bubble, so they are caught on their way down to any scrolled element.
+    {
+        let on_scroll = Closure::<dyn Fn()>::new(update_visible_chunks);
+        (window())
+            .add_event_listener_with_callback_and_bool(
+                "scroll",
+                on_scroll.as_ref().unchecked_ref(),
+                true,
+            )
+            .unwrap();
+        let resize = window_event_listener(ev::resize, move |_| update_visible_chunks());
+        on_cleanup(move || {
+            _ = window().remove_event_listener_with_callback_and_bool(
+                "scroll",
+                on_scroll.as_ref().unchecked_ref(),
+                true,
+            );
+            resize.remove();
+        });
+    }
+
+    // Show the output of a run
+    //
+    // Long outputs are split into chunks, and only the chunks near the visible part
+    // of the page are rendered. The others are replaced with empty space of the same height,
+    // so huge outputs do not freeze the page and media is only encoded when it is near view.
+    let show_output = move |output: Vec<OutputItem>, allow_autoplay: bool| {
+        let (diags, items): (Vec<_>, Vec<_>) = output.into_iter().partition(OutputItem::is_report);
+        // Output after a sleep is shown after a delay
+        let shown_at = output_generation.get_value() + 1;
+        output_generation.set_value(shown_at);
+        let mut items = VecDeque::from(items);
+        let mut delay = 0.0;
+        let mut delayed = Vec::new();
+        if let Some(i) = items
+            .iter()
+            .position(|item| matches!(item, OutputItem::Delay(_)))
+        {
+            for item in items.split_off(i) {
+                match item {
+                    OutputItem::Delay(secs) => {
+                        delay += secs;
+                        delayed.push((delay, Vec::new()));
+                    }
+                    item => delayed.last_mut().unwrap().1.push(item),
+                }
+            }
+        }
+        for (delay, group) in delayed {
+            set_timeout(
+                move || {
+                    if output_generation.get_value() != shown_at {
+                        return;
+                    }
+                    let page: Vec<_> = (group.into_iter())
+                        .map(|item| render_output_item(item, allow_autoplay))
+                        .collect();
+                    set_output_pages.update(|pages| pages.push(page.into_view()));
+                },
+                Duration::from_secs_f64(delay),
+            );
+        }
+        let diags: Vec<_> = (diags.into_iter())
+            .map(|item| render_output_item(item, allow_autoplay))
+            .collect();
+        set_output_pages.set(Vec::new());
+        set_diag_output.set(diags.into_view());
+        if items.len() <= OUTPUT_CHUNK_LEN {
+            output_chunks.set_value(Vec::new());
+            chunk_heights.set_value(Vec::new());
+            set_visible_chunks.set(0..0);
+            let shown: Vec<_> = (items.into_iter())
+                .map(|item| render_output_item(item, allow_autoplay))
+                .collect();
+            set_output.set(shown.into_view());
+            return;
+        }
+        let items = Vec::from(items);
+        let chunks: Vec<Vec<OutputItem>> =
+            items.chunks(OUTPUT_CHUNK_LEN).map(<[_]>::to_vec).collect();
+        chunk_heights.set_value(vec![None; chunks.len()]);
+        output_chunks.set_value(chunks);
+        set_visible_chunks.set(0..1);
+        let chunks_view = move || {
+            let visible = visible_chunks.get();
+            let count = output_chunks.with_value(Vec::len);
+            let views: Vec<_> = (0..count)
+                .map(|i| {
+                    if visible.contains(&i) {
+                        let items = output_chunks.with_value(|chunks| chunks[i].clone());
+                        let items: Vec<_> = (items.into_iter())
+                            .map(|item| render_output_item(item, allow_autoplay))
+                            .collect();
+                        view!(<div id=format!("output-{id}-chunk-{i}")>{items}</div>).into_view()
+                    } else {
+                        let height = chunk_heights
+                            .with_value(|heights| heights[i])
+                            .unwrap_or_else(|| {
+                                let len = output_chunks.with_value(|chunks| chunks[i].len());
+                                OUTPUT_ITEM_HEIGHT * len as f64
+                            });
+                        view!(<div style=format!("height: {height}px")></div>).into_view()
+                    }
+                })
+                .collect();
+            request_animation_frame(move || measure_chunks(shown_at));
+            views.into_view()
+        };
+        set_output.set(chunks_view.into_view());
+    };
+
+    // Show the output of a run and keep its history
+    //
+    // Only the pad keeps history, and only if there is more than one point to scrub between.
+    let show_run_output = move |st: &mut State, output: Vec<OutputItem>, allow_autoplay: bool| {
+        let points = take(&mut st.history);
+        if mode == EditorMode::Pad && points.len() > 1 {
+            set_history_len.set(points.len());
+            set_history_pos.set(points.len());
+            history.set_value(points);
+            final_output.set_value(output.clone());
+        } else {
+            set_history_len.set(0);
+            history.set_value(Vec::new());
+            final_output.set_value(Vec::new());
+        }
+        if mode == EditorMode::Pad {
+            persist_files();
+        }
+        show_output(output, allow_autoplay);
+    };
+
+    // Show the stack after a line in the run's history
+    //
+    // The last position shows the run's full output.
+    let on_history_scrub = move |event: Event| {
+        let input: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
+        let pos = input.value().parse().unwrap_or(0);
+        set_history_pos.set(pos);
+        match history.with_value(|points| points.get(pos).map(|(_, stack)| stack.clone())) {
+            Some(stack) => show_output(history_output(stack), false),
+            None => show_output(final_output.get_value(), false),
+        }
+    };
+    let history_label = move || {
+        let pos = history_pos.get();
+        history
+            .with_value(|points| {
+                points
+                    .get(pos)
+                    .map(|(line, _)| format!("After line {line}"))
+            })
+            .unwrap_or_else(|| "End".into())
+    };
+
+    // Run code on the page, which freezes it until the run is done
+    let run_on_page = move |input: String, seed: u64, allow_autoplay: bool| {
+        state.update(|st| {
+            seed_random(seed);
+            let output = st.run_code(&input);
+            if take(&mut st.waiting) {
+                set_timeout(
+                    move || {
+                        state.update(|st| {
+                            seed_random(seed);
+                            let output = st.run_code(&input);
+                            show_run_output(st, output, allow_autoplay);
+                        });
+                    },
+                    Duration::from_millis(200),
+                );
+            } else {
+                show_run_output(st, output, allow_autoplay);
+            }
+        });
+    };
+
     // Run the code
+    //
+    // Challenges and lessons check code on the page, but other code is run in the worker if possible.
+    let (running, set_running) = create_signal(false);
     let run = move |do_format: bool, set_cursor: bool| {
         // Format code
         let (input, seed) = format(do_format, set_cursor);
 
         // Run code
         set_output.set(view!(<div class="running-text">"Running"</div>).into_view());
+        set_output_pages.set(Vec::new());
+        output_chunks.set_value(Vec::new());
+        chunk_heights.set_value(Vec::new());
+        set_visible_chunks.set(0..0);
         let allow_autoplay = !matches!(mode, EditorMode::Example) && get_autoplay();
-        let render_output_item = move |item| match item {
-            OutputItem::String(s) => {
-                if s.is_empty() {
-                    view!(<div class="output-item"><br/></div>).into_view()
-                } else {
-                    view!(<div class="output-item">{s}</div>).into_view()
-                }
-            }
-            OutputItem::Classed(class, s) => {
-                let class = format!("output-item {class}");
-                view!(<div class=class>{s}</div>).into_view()
-            }
-            OutputItem::Faint(s) => {
-                view!(<div class="output-item output-fainter">{s}</div>).into_view()
-            }
-            OutputItem::Image(bytes, label) => {
-                let encoded = STANDARD.encode(bytes);
-                view!(<div class="output-media-wrapper">
-                    <div class="output-image-label">{label}</div>
-                    <img class="output-image" src={format!("data:image/png;base64,{encoded}")} />
-                </div>)
-                .into_view()
-            }
-            OutputItem::Gif(bytes, label) => {
-                let encoded = STANDARD.encode(bytes);
-                view!(<div class="output-media-wrapper">
-                    <div class="output-image-label">{label}</div>
-                    <img class="output-image" src={format!("data:image/gif;base64,{encoded}")} />
-                </div>)
-                .into_view()
-            }
-            OutputItem::Audio(bytes, label) => {
-                let encoded = STANDARD.encode(bytes);
-                let src = format!("data:audio/wav;base64,{}", encoded);
-                let label = label.map(|s| format!("{s}:"));
-                if allow_autoplay {
-                    view!(<div class="output-media-wrapper">
-                        <div class="output-item output-audio-label">{label}</div>
-                        <audio class="output-audio" controls autoplay src=src/>
-                    </div>)
-                    .into_view()
-                } else {
-                    view!(<div class="output-media-wrapper">
-                        <div class="output-item output-audio-label">{label}</div>
-                        <audio class="output-audio" controls src=src/>
-                    </div>)
-                    .into_view()
+        let in_worker = get_run_in_worker()
+            && worker_available()
+            && get_state.with_untracked(|st| st.challenge.is_none() && st.lesson.is_none());
+        if !in_worker {
+            set_timeout(
+                move || run_on_page(input, seed, allow_autoplay),
+                Duration::ZERO,
+            );
+            return;
+        }
+        set_running.set(true);
+        let code_id = get_state.with_untracked(|st| st.code_id.clone());
+        // Show stdout while the code is running
+        let on_stdout = move |stdout: &str| {
+            let lines = stdout.lines().collect::<Vec<_>>();
+            let lines = lines[lines.len().saturating_sub(RUNNING_OUTPUT_LEN)..].iter();
+            let lines = lines.map(|line| view!(<div class="output-item">{line.to_string()}</div>));
+            set_output.set(
+                view! {
+                    <div class="running-text">"Running"</div>
+                    {lines.collect::<Vec<_>>()}
                 }
+                .into_view(),
+            );
+        };
+        let input_clone = input.clone();
+        let on_done = move |result: Option<RunResult>| {
+            set_running.set(false);
+            match result {
+                Some(result) => state.update(|st| {
+                    st.history = result.history;
+                    show_run_output(st, result.output, allow_autoplay);
+                }),
+                None => run_on_page(input, seed, allow_autoplay),
             }
-            OutputItem::Svg(s) => view!(<div><img
-                    class="output-image"
-                    src={format!("data:image/svg+xml;utf8, {}", urlencoding::encode(&s))}/>
-                </div>)
-            .into_view(),
-            OutputItem::Report(report) => report_view(&report).into_view(),
-            OutputItem::Separator => view!(<div class="output-item"><hr/></div>).into_view(),
         };
-        set_timeout(
-            move || {
-                state.update(|st| {
-                    seed_random(seed);
-                    let output = st.run_code(&input);
-                    if take(&mut st.loading_module) {
-                        set_timeout(
-                            move || {
-                                state.update(|st| {
-                                    seed_random(seed);
-                                    let output = st.run_code(&input);
-                                    let (diags, items): (Vec<_>, Vec<_>) =
-                                        output.into_iter().partition(OutputItem::is_report);
-                                    let items: Vec<_> =
-                                        items.into_iter().map(render_output_item).collect();
-                                    let diags: Vec<_> =
-                                        diags.into_iter().map(render_output_item).collect();
-                                    set_output.set(items.into_view());
-                                    set_diag_output.set(diags.into_view());
-                                });
-                            },
-                            Duration::from_millis(200),
-                        );
-                    } else {
-                        let (diags, items): (Vec<_>, Vec<_>) =
-                            output.into_iter().partition(OutputItem::is_report);
-                        let items: Vec<_> = items.into_iter().map(render_output_item).collect();
-                        let diags: Vec<_> = diags.into_iter().map(render_output_item).collect();
-                        set_output.set(items.into_view());
-                        set_diag_output.set(diags.into_view());
-                    }
-                });
-            },
-            Duration::ZERO,
-        );
+        run_in_worker(&code_id, &input_clone, seed, on_stdout, on_done);
     };
 
     // Replace the selected text in the editor with the given string
@@ -934,6 +1181,8 @@ pub fn Editor<'a>(
                 state.update(|state| {
                     state.set_code(&examples[*e], Cursor::Ignore);
                     state.clear_history();
+                    state.lesson_step = *e;
+                    state.step_passed = false;
                 });
                 run(false, false);
             })
@@ -948,18 +1197,77 @@ pub fn Editor<'a>(
                 state.update(|state| {
                     state.set_code(&examples[*e], Cursor::Ignore);
                     state.clear_history();
+                    state.lesson_step = *e;
+                    state.step_passed = false;
                 });
                 run(false, false);
             })
         }
     };
 
+    // Load the files persisted in earlier sessions
+    if mode == EditorMode::Pad {
+        spawn_local(async move {
+            match load_persisted_files().await {
+                Ok(0) => {}
+                Ok(_) => run(false, false),
+                Err(e) => logging::warn!("Failed to load persisted files: {e:?}"),
+            }
+        });
+    }
+
     // Glyph hover doc
     let (glyph_doc, set_glyph_doc) = create_signal(View::default());
     let onmouseleave = move |_| {
         _ = glyph_doc_element().style().set_property("display", "none");
     };
 
+    // Accessibility
+    // When spoken glyphs are enabled, screen readers are given glyph names instead of symbols
+    let (spoken_glyphs, set_spoken_glyphs_enabled) = create_signal(get_spoken_glyphs());
+    let (high_contrast, set_high_contrast_enabled) = create_signal(get_high_contrast());
+    let (announcement, set_announcement) = create_signal(String::new());
+    let spoken_id = move || format!("spoken{id}");
+
+    // Touch input
+    // When the touch keyboard is enabled, long-pressing a glyph button shows its variants
+    let (touch_keyboard, set_touch_keyboard_enabled) = create_signal(get_touch_keyboard());
+    let (variants_of, set_variants_of) = create_signal(None::<Primitive>);
+    let long_press_timer = store_value(None::<TimeoutHandle>);
+    let long_pressed = store_value(false);
+    let cancel_long_press = move |_| {
+        if let Some(timer) = long_press_timer.get_value() {
+            timer.clear();
+        }
+        long_press_timer.set_value(None);
+    };
+    let wrapper_class = move || {
+        let mut class = String::new();
+        if high_contrast.get() {
+            class.push_str("high-contrast ");
+        }
+        if touch_keyboard.get() {
+            class.push_str("touch-editor");
+        }
+        class
+    };
+
+    // Keep the code visible when an on-screen keyboard opens
+    let code_focus = move |_| {
+        if !touch_keyboard.get_untracked() {
+            return;
+        }
+        set_timeout(
+            move || {
+                let options = ScrollIntoViewOptions::new();
+                options.set_block(ScrollLogicalPosition::Nearest);
+                element::<HtmlDivElement>(&code_outer_id())
+                    .scroll_into_view_with_scroll_into_view_options(&options);
+            },
+            Duration::from_millis(300),
+        );
+    };
+
     // Glyph buttons
     // These are the buttons that appear above the editor and allow the user to insert glyphs
     let make_glyph_button = |prim: Primitive| {
@@ -973,7 +1281,10 @@ pub fn Editor<'a>(
         }
         // Navigate to the docs page on ctrl/shift+click
         let onclick = move |event: MouseEvent| {
-            if os_ctrl(&event) {
+            if long_pressed.get_value() {
+                // The variants are already shown
+                long_pressed.set_value(false);
+            } else if os_ctrl(&event) {
                 // Open the docs page
                 window()
                     .open_with_url_and_target(&format!("/docs/{}", prim.name()), "_blank")
@@ -986,6 +1297,9 @@ pub fn Editor<'a>(
                 );
             } else {
                 state.update(|state| replace_code(state, &prim.to_string()));
+                if get_spoken_glyphs() {
+                    set_announcement.set(format!("Inserted {}", prim.spoken_name()));
+                }
             }
         };
         // Show the glyph doc on mouseover
@@ -1007,6 +1321,23 @@ pub fn Editor<'a>(
             );
             _ = glyph_doc_element().style().remove_property("display");
         };
+        // Show the variants on long press
+        let ontouchstart = move |_| {
+            long_pressed.set_value(false);
+            let timer = set_timeout_with_handle(
+                move || {
+                    long_pressed.set_value(true);
+                    set_variants_of.set(Some(prim));
+                },
+                Duration::from_millis(LONG_PRESS_MS),
+            );
+            long_press_timer.set_value(timer.ok());
+        };
+        let oncontextmenu = move |event: MouseEvent| {
+            if touch_keyboard.get_untracked() {
+                event.prevent_default();
+            }
+        };
         let mut class = "glyph-button glyph-title".to_string();
         if prim.is_experimental() {
             class.push_str(" experimental-glyph-button");
@@ -1016,9 +1347,15 @@ pub fn Editor<'a>(
                 <button
                     class=class
                     data-title=title
+                    aria-label=prim.spoken_name()
                     on:click=onclick
                     on:mouseover=onmouseover
-                    on:mouseleave=onmouseleave>
+                    on:mouseleave=onmouseleave
+                    on:touchstart=ontouchstart
+                    on:touchmove=cancel_long_press
+                    on:touchend=cancel_long_press
+                    on:touchcancel=cancel_long_press
+                    on:contextmenu=oncontextmenu>
                     <div class={prim_class(prim)}>{ text }</div>
                 </button>
             }
@@ -1136,6 +1473,7 @@ pub fn Editor<'a>(
                 <button
                     class=class
                     data-title=title
+                    aria-label=title
                     on:click=onclick
                     on:mouseover=onmouseover
                     on:mouseleave=onmouseleave>
@@ -1194,10 +1532,47 @@ pub fn Editor<'a>(
         .into_view(),
     );
 
+    // Glyph variants popup
+    let variants_view = move || {
+        let prim = variants_of.get()?;
+        let buttons: Vec<_> = glyph_variants(prim)
+            .into_iter()
+            .map(|variant| {
+                let text = variant
+                    .glyph()
+                    .map(String::from)
+                    .unwrap_or_else(|| variant.name().into());
+                let onclick = move |_| {
+                    state.update(|state| replace_code(state, &variant.to_string()));
+                    set_variants_of.set(None);
+                };
+                view! {
+                    <button
+                        class="glyph-button glyph-variant"
+                        aria-label=variant.spoken_name()
+                        on:click=onclick>
+                        <div class={prim_class(variant)}>{text}</div>
+                    </button>
+                }
+            })
+            .collect();
+        Some(view! {
+            <div class="glyph-variants" role="menu" aria-label=format!("Variants of {}", prim.spoken_name())>
+                {buttons}
+                <button
+                    class="glyph-button"
+                    aria-label="Close"
+                    on:click=move |_| set_variants_of.set(None)>
+                    "✕"
+                </button>
+            </div>
+        })
+    };
+
     // Select a class for the editor and code area
     let editor_class = match mode {
         EditorMode::Example => "small-editor",
-        EditorMode::Showcase | EditorMode::Pad => "medium-editor",
+        EditorMode::Showcase | EditorMode::Pad | EditorMode::Tutorial => "medium-editor",
     };
 
     // Hide the example arrows if there is only one example
@@ -1210,7 +1585,7 @@ pub fn Editor<'a>(
     // Show or hide the glyph buttons
     let (show_glyphs, set_show_glyphs) = create_signal(match mode {
         EditorMode::Example => false,
-        EditorMode::Showcase | EditorMode::Pad => true,
+        EditorMode::Showcase | EditorMode::Pad | EditorMode::Tutorial => true,
     });
 
     // Glyphs toggle button
@@ -1239,14 +1614,30 @@ pub fn Editor<'a>(
         move || (examples_len > 1).then(|| format!("{}/{}", example.get() + 1, examples_len));
 
     // Select a class for the next example button
+    //
+    // In tutorial mode, the button is only highlighted once the current step is passed.
     let next_button_class = move || {
-        if example.get() == examples_len - 1 {
+        if example.get() == examples_len - 1
+            || mode == EditorMode::Tutorial && !get_state.with(|st| st.step_passed)
+        {
             "code-button"
         } else {
             "code-button important-button"
         }
     };
 
+    // Show the current step's title and prose in tutorial mode
+    let lesson_view = (lesson.filter(|_| mode == EditorMode::Tutorial)).map(|lesson| {
+        let title = (!lesson.title.is_empty()).then(|| view!(<h3>{lesson.title.clone()}</h3>));
+        let prose = move || {
+            let step = &lesson.steps[example.get().min(lesson.steps.len() - 1)];
+            (step.prose.split("\n\n"))
+                .map(|paragraph| view!(<p>{paragraph.trim().to_string()}</p>))
+                .collect::<Vec<_>>()
+        };
+        view!(<div class="tutorial-step">{title}{prose}</div>)
+    });
+
     // This ensures the output of the first example is shown
     set_timeout(
         move || {
@@ -1447,6 +1838,7 @@ pub fn Editor<'a>(
         set_show_experimental(!get_show_experimental());
     };
     let toggle_run_on_format = move |_| set_run_on_format(!get_run_on_format());
+    let toggle_run_in_worker = move |_| set_run_in_worker(!get_run_in_worker());
     let toggle_inlay_values = move |_| {
         set_timeout(
             move || get_state.get().refresh_code(),
@@ -1454,6 +1846,25 @@ pub fn Editor<'a>(
         );
         set_inlay_values(!get_inlay_values());
     };
+    let toggle_spoken_glyphs = move |_| {
+        set_spoken_glyphs(!get_spoken_glyphs());
+        set_spoken_glyphs_enabled.set(get_spoken_glyphs());
+    };
+    let toggle_high_contrast = move |_| {
+        set_high_contrast(!get_high_contrast());
+        set_high_contrast_enabled.set(get_high_contrast());
+    };
+    let toggle_touch_keyboard = move |_| {
+        set_touch_keyboard(!get_touch_keyboard());
+        set_touch_keyboard_enabled.set(get_touch_keyboard());
+    };
+    let toggle_full_trace = move |_| {
+        set_timeout(
+            move || get_state.get().refresh_code(),
+            Duration::from_millis(0),
+        );
+        set_full_trace(!get_full_trace());
+    };
     let on_select_font = move |event: Event| {
         let input: HtmlSelectElement = event.target().unwrap().dyn_into().unwrap();
         let name = input.value();
@@ -1570,10 +1981,50 @@ pub fn Editor<'a>(
 
     // Render
     view! {
-        <div id="editor-wrapper">
+        <div
+            id="editor-wrapper"
+            class=wrapper_class
+            role="region"
+            aria-label="Uiua editor">
             <div id="editor">
+                {lesson_view}
                 <div style=glyph_buttons_style>
-                    <div class="glyph-buttons">{glyph_buttons}</div>
+                    {variants_view}
+                    <div class="glyph-buttons" role="toolbar" aria-label="Glyphs">
+                        {glyph_buttons}
+                    </div>
+                    { move || touch_keyboard.get().then(|| view! {
+                        <div class="touch-toolbar" role="toolbar" aria-label="Selection">
+                            <button
+                                class="glyph-button"
+                                aria-label="Move selection start left"
+                                on:mousedown=|event| event.prevent_default()
+                                on:click=move |_| state.update(|state| move_selection(state, -1, 0))>
+                                "[◂"
+                            </button>
+                            <button
+                                class="glyph-button"
+                                aria-label="Move selection start right"
+                                on:mousedown=|event| event.prevent_default()
+                                on:click=move |_| state.update(|state| move_selection(state, 1, 0))>
+                                "[▸"
+                            </button>
+                            <button
+                                class="glyph-button"
+                                aria-label="Move selection end left"
+                                on:mousedown=|event| event.prevent_default()
+                                on:click=move |_| state.update(|state| move_selection(state, 0, -1))>
+                                "◂]"
+                            </button>
+                            <button
+                                class="glyph-button"
+                                aria-label="Move selection end right"
+                                on:mousedown=|event| event.prevent_default()
+                                on:click=move |_| state.update(|state| move_selection(state, 0, 1))>
+                                "▸]"
+                            </button>
+                        </div>
+                    })}
                 </div>
                 {file_tab_display}
                 <div id="settings" style=settings_style>
@@ -1635,6 +2086,13 @@ pub fn Editor<'a>(
                                 checked=get_run_on_format
                                 on:change=toggle_run_on_format/>
                         </div>
+                        <div title="Run code in the background so that long runs do not freeze the page">
+                            "Run in background:"
+                            <input
+                                type="checkbox"
+                                checked=get_run_in_worker
+                                on:change=toggle_run_in_worker/>
+                        </div>
                         <div title="Show line values to the right of the code">
                             "Show values:"
                             <input
@@ -1642,6 +2100,34 @@ pub fn Editor<'a>(
                                 checked=get_inlay_values
                                 on:change=toggle_inlay_values/>
                         </div>
+                        <div title="Describe glyphs to screen readers by their names">
+                            "Spoken glyphs:"
+                            <input
+                                type="checkbox"
+                                checked=get_spoken_glyphs
+                                on:change=toggle_spoken_glyphs/>
+                        </div>
+                        <div title="Render the editor with higher contrast">
+                            "High contrast:"
+                            <input
+                                type="checkbox"
+                                checked=get_high_contrast
+                                on:change=toggle_high_contrast/>
+                        </div>
+                        <div title="Show a touch-friendly glyph keyboard and selection handles">
+                            "Touch keyboard:"
+                            <input
+                                type="checkbox"
+                                checked=get_touch_keyboard
+                                on:change=toggle_touch_keyboard/>
+                        </div>
+                        <div title="Show the code at each frame of error traces">
+                            "Full traces:"
+                            <input
+                                type="checkbox"
+                                checked=get_full_trace
+                                on:change=toggle_full_trace/>
+                        </div>
                         <div>
                             "Stack:"
                             <select
@@ -1704,7 +2190,7 @@ pub fn Editor<'a>(
                             id=code_outer_id
                             class="code code-outer sized-code"
                             style={format!("height: {}em;", code_height_em + 1.25 / 2.0)}>
-                            <div class="line-numbers">
+                            <div class="line-numbers" aria-hidden="true">
                                 { line_numbers }
                             </div>
                             <div class="code-and-overlay">
@@ -1718,8 +2204,11 @@ pub fn Editor<'a>(
                                     autocapitalize="off"
                                     spellcheck="false"
                                     translate="no"
+                                    aria-label="Code"
+                                    aria-describedby=move || spoken_glyphs.get().then(spoken_id)
                                     on:paste=code_paste
                                     on:input=code_input
+                                    on:focus=code_focus
                                     on:mousemove=code_mouse_move
                                     on:mouseleave=code_mouse_leave
                                     value=initial_code_str>
@@ -1727,15 +2216,23 @@ pub fn Editor<'a>(
                                 /////////////////////////
                                 <div
                                     id=overlay_id
-                                    class="code-overlay">
+                                    class="code-overlay"
+                                    aria-hidden="true">
                                     { move || gen_code_view(&code_id(), &overlay.get()) }
                                 </div>
+                                <div id=spoken_id class="visually-hidden">
+                                    { move || spoken_glyphs.get().then(|| spoken_code(&overlay.get())) }
+                                </div>
+                                <div class="visually-hidden" aria-live="polite">
+                                    { move || announcement.get() }
+                                </div>
                             </div>
                         </div>
                         <div id="code-right-side">
                             <button
                                 class="editor-right-button"
                                 data-title=copy_link_title
+                                aria-label=copy_link_title
                                 on:click=copy_link>
                                 "🔗"
                             </button>
@@ -1743,11 +2240,13 @@ pub fn Editor<'a>(
                                 id="glyphs-toggle-button"
                                 class="editor-right-button"
                                 data-title=show_glyphs_title
+                                aria-label=show_glyphs_title
                                 on:click=toggle_show_glyphs>{show_glyphs_text}
                             </button>
                             <button
                                 class="editor-right-button"
                                 data-title=toggle_settings_title
+                                aria-label=toggle_settings_title
                                 on:click=toggle_settings_open>
                                 "⚙️"
                             </button>
@@ -1756,6 +2255,7 @@ pub fn Editor<'a>(
                                     Some(view!(<button
                                         class="editor-right-button"
                                         data-title="Upload file"
+                                        aria-label="Upload file"
                                         on:click=upload_file_dialog>
                                         "📄"
                                     </button>))
@@ -1769,12 +2269,29 @@ pub fn Editor<'a>(
                     <div id=hover_id class="code-hover"/>
                     <div class="output-frame">
                         <div class="output-lines">
+                            { move || (history_len.get() > 0).then(|| view! {
+                                <div class="output-history" title="Scrub through the stack after each line">
+                                    <input
+                                        type="range"
+                                        class="output-history-slider"
+                                        min="0"
+                                        max=history_len.get()
+                                        prop:value=history_pos
+                                        on:input=on_history_scrub/>
+                                    <div class="output-history-label">{history_label}</div>
+                                </div>
+                            })}
                             <div class="output-diagnostics">
                                 { move || diag_output.get() }
                             </div>
                             <div class="output-wrapper">
-                                <div id=format!("output-{id}") class="output sized-code">
+                                <div
+                                    id=format!("output-{id}")
+                                    class="output sized-code"
+                                    role="log"
+                                    aria-live="polite">
                                     { move || output.get() }
+                                    { move || output_pages.get() }
                                     { move || get_state.get().challenge.as_ref().map(|chal| {
                                         let intended = chal.intended_answer.clone();
                                         let click_intended = move|_| {
@@ -1813,7 +2330,16 @@ pub fn Editor<'a>(
                                 on:click=move |_| {format(true, false);}
                                 data-title=" ctrl Enter - Format        \nshift Enter - Format and Run"
                             >{ "Format" }</button>
-                            <button class="code-button" on:click=move |_| run(get_run_on_format(), false)>{ "Run" }</button>
+                            <button
+                                class="code-button"
+                                on:click=move |_| {
+                                    if running.get_untracked() {
+                                        stop_worker();
+                                    } else {
+                                        run(get_run_on_format(), false);
+                                    }
+                                }
+                            >{ move || if running.get() { "Stop" } else { "Run" } }</button>
                             <button
                                 id="prev-example"
                                 class="code-button"
@@ -1846,6 +2372,80 @@ pub fn Editor<'a>(
     }
 }
 
+fn render_output_item(item: OutputItem, allow_autoplay: bool) -> View {
+    match item {
+        OutputItem::String(s) => {
+            if s.is_empty() {
+                view!(<div class="output-item"><br/></div>).into_view()
+            } else {
+                view!(<div class="output-item">{s}</div>).into_view()
+            }
+        }
+        OutputItem::Classed(class, s) => {
+            let class = format!("output-item {class}");
+            view!(<div class=class>{s}</div>).into_view()
+        }
+        OutputItem::Faint(s) => {
+            view!(<div class="output-item output-fainter">{s}</div>).into_view()
+        }
+        OutputItem::Math(s) => {
+            view!(<div class="output-item output-math" inner_html=s/>).into_view()
+        }
+        OutputItem::Image(bytes, label) => {
+            let encoded = STANDARD.encode(bytes);
+            view!(<div class="output-media-wrapper">
+                <div class="output-image-label">{label}</div>
+                <img class="output-image" src={format!("data:image/png;base64,{encoded}")} />
+            </div>)
+            .into_view()
+        }
+        OutputItem::Gif(bytes, label) => {
+            let encoded = STANDARD.encode(bytes);
+            view!(<div class="output-media-wrapper">
+                <div class="output-image-label">{label}</div>
+                <img class="output-image" src={format!("data:image/gif;base64,{encoded}")} />
+            </div>)
+            .into_view()
+        }
+        OutputItem::Audio(bytes, label) => {
+            let encoded = STANDARD.encode(bytes);
+            let src = format!("data:audio/wav;base64,{}", encoded);
+            let label = label.map(|s| format!("{s}:"));
+            if allow_autoplay {
+                view!(<div class="output-media-wrapper">
+                    <div class="output-item output-audio-label">{label}</div>
+                    <audio class="output-audio" controls autoplay src=src/>
+                </div>)
+                .into_view()
+            } else {
+                view!(<div class="output-media-wrapper">
+                    <div class="output-item output-audio-label">{label}</div>
+                    <audio class="output-audio" controls src=src/>
+                </div>)
+                .into_view()
+            }
+        }
+        OutputItem::Svg(s) => view!(<div><img
+                class="output-image"
+                src={format!("data:image/svg+xml;utf8, {}", urlencoding::encode(&s))}/>
+            </div>)
+        .into_view(),
+        OutputItem::Report(report) => report_view(&report).into_view(),
+        OutputItem::Progress {
+            label,
+            current,
+            total,
+        } => view!(<div class="output-item">
+                {label}" "
+                <progress class="output-progress" value=current max=total/>
+                {format!(" {current}/{total}")}
+            </div>)
+        .into_view(),
+        OutputItem::Separator => view!(<div class="output-item"><hr/></div>).into_view(),
+        OutputItem::Delay(_) => View::default(),
+    }
+}
+
 #[component]
 pub fn Prim(
     prim: Primitive,
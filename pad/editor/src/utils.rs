@@ -3,7 +3,7 @@ use leptos::*;
 use std::path::Path;
 use std::{
     borrow::Cow,
-    cell::Cell,
+    cell::{Cell, RefCell},
     collections::HashMap,
     mem::{replace, take},
     str::FromStr,
@@ -14,9 +14,10 @@ use uiua::UiuaErrorKind;
 use uiua::{
     ast::Item,
     encode::SmartOutput,
+    lex,
     lsp::{BindingDocsKind, ImportSrc},
-    Compiler, DiagnosticKind, Inputs, Primitive, Report, ReportFragment, ReportKind, SpanKind,
-    Spans, Uiua, UiuaError, UiuaResult, Value,
+    Compiler, DiagnosticKind, Inputs, Primitive, Report, ReportFragment, ReportKind, SafeSys,
+    SpanKind, Spans, SysBackend, Token, Uiua, UiuaError, UiuaResult, Value,
 };
 use unicode_segmentation::UnicodeSegmentation;
 use wasm_bindgen::JsCast;
@@ -27,7 +28,9 @@ use web_sys::{
 
 use crate::{
     backend::{OutputItem, WebBackend},
-    binding_class, code_font, modifier_class, prim_sig_class,
+    binding_class, code_font,
+    lesson::Lesson,
+    modifier_class, prim_sig_class,
 };
 
 #[derive(Clone)]
@@ -53,9 +56,21 @@ pub struct State {
     pub future: Vec<Record>,
     pub curr: Record,
     pub challenge: Option<ChallengeDef>,
-    pub loading_module: bool,
+    /// Whether the last run is waiting for a module or other asynchronous result
+    pub waiting: bool,
+    /// The history of the last run
+    pub history: RunHistory,
+    /// The lesson being followed in tutorial mode
+    pub lesson: Option<Lesson>,
+    /// The index of the current lesson step
+    pub lesson_step: usize,
+    /// Whether the last run passed the current lesson step's check
+    pub step_passed: bool,
 }
 
+/// The line number and stack after each top-level line of a run
+pub type RunHistory = Vec<(usize, Vec<Value>)>;
+
 /// A record of a code change
 #[derive(Debug, Clone)]
 pub struct Record {
@@ -918,6 +933,24 @@ fn just_values(id: &str, code: &str) -> UiuaResult<Vec<Value>> {
     Ok(rt.take_stack())
 }
 
+/// Check the user's output for a lesson step
+///
+/// The check code is run with the values left on the stack by the user's code.
+fn check_lesson_step(id: &str, code: &str, check: &str) -> Result<(), String> {
+    let values = just_values(id, code).map_err(|_| "Your code has an error")?;
+    let mut rt = init_rt(id, check);
+    rt.push_all(values);
+    let incorrect = || "Incorrect".to_string();
+    rt.run_str(check).map_err(|_| incorrect())?;
+    match rt.pop("check result").map_err(|_| incorrect())? {
+        Value::Char(message) if message.rank() <= 1 && message.row_count() > 0 => {
+            Err(message.elements().collect())
+        }
+        result if result.as_nat(&rt, "").is_ok_and(|n| n == 1) => Ok(()),
+        _ => Err(incorrect()),
+    }
+}
+
 fn challenge_code(input: &str, test: &str, flip: bool) -> String {
     if flip {
         format!("{input}\n{test}")
@@ -930,6 +963,7 @@ impl State {
     /// Run code and return the output
     pub fn run_code(&mut self, code: &str) -> Vec<OutputItem> {
         if let Some(chal) = &self.challenge {
+            self.history.clear();
             let mut example = run_code_single(
                 &self.code_id,
                 &challenge_code(&chal.intended_answer, &chal.example, chal.flip),
@@ -994,11 +1028,27 @@ impl State {
             }
             output
         } else {
-            let (output, error) = run_code_single(&self.code_id, code);
-            self.loading_module = false;
+            let (mut output, error, history) = run_code_single(&self.code_id, code);
+            self.history = history;
+            let step = (self.lesson.as_ref()).and_then(|lesson| lesson.steps.get(self.lesson_step));
+            if let Some(step) = step {
+                // Steps without a check are always passed
+                self.step_passed = true;
+                if let Some(check) = &step.check {
+                    let result = check_lesson_step(&self.code_id, code, check);
+                    self.step_passed = result.is_ok();
+                    let verdict = match result {
+                        Ok(()) => "✅ Correct!".into(),
+                        Err(message) => format!("❌ {message}"),
+                    };
+                    output.insert(0, OutputItem::String(verdict));
+                    output.insert(1, OutputItem::Separator);
+                }
+            }
+            self.waiting = false;
             if let Some(error) = error {
-                if error.to_string().contains("Waiting for module") {
-                    self.loading_module = true;
+                if error.to_string().contains("try running to check") {
+                    self.waiting = true;
                 }
             }
             output
@@ -1006,44 +1056,11 @@ impl State {
     }
 }
 
-#[allow(clippy::mutable_key_type)]
-fn run_code_single(id: &str, code: &str) -> (Vec<OutputItem>, Option<UiuaError>) {
-    // Run
-    let mut rt = init_rt(id, code);
-    let mut error = None;
-    let mut comp = Compiler::with_backend(WebBackend::new(id, code));
-    let comp_backend;
-    let res = comp.load_str(code).map(|comp| rt.run_compiler(comp));
-    let (mut values, io) = match res {
-        Ok(Ok(())) => {
-            let stack = rt.take_stack();
-            let backend = rt.downcast_backend::<WebBackend>().unwrap();
-            backend.finish();
-            (stack, backend)
-        }
-        Ok(Err(e)) if matches!(e.kind, UiuaErrorKind::Interrupted) => (
-            rt.take_stack(),
-            rt.downcast_backend::<WebBackend>().unwrap(),
-        ),
-        Ok(Err(e)) => {
-            error = Some(e);
-            (
-                rt.take_stack(),
-                rt.downcast_backend::<WebBackend>().unwrap(),
-            )
-        }
-        Err(e) => {
-            error = Some(e);
-            comp_backend = comp.take_backend::<WebBackend>().unwrap();
-            (Vec::new(), &comp_backend)
-        }
-    };
+/// Get the output items that show the values on a stack
+fn stack_output(mut values: Vec<Value>, io: &dyn SysBackend) -> Vec<OutputItem> {
     if get_top_at_top() {
         values.reverse();
     }
-    let diagnostics = comp.take_diagnostics();
-    // Get stdout and stderr
-    let stdout = take(&mut *io.stdout.lock().unwrap());
     let mut stack = Vec::new();
     let value_count = values.len();
     for (i, value) in values.into_iter().enumerate() {
@@ -1077,8 +1094,66 @@ fn run_code_single(id: &str, code: &str) -> (Vec<OutputItem>, Option<UiuaError>)
             }
         };
         for line in value.show().lines() {
-            stack.push(OutputItem::Classed(class, line.to_string()));
+            stack.push(OutputItem::Classed(class.into(), line.to_string()));
+        }
+    }
+    stack
+}
+
+/// Get the output items that show the stack at a point in a run's history
+pub fn history_output(values: Vec<Value>) -> Vec<OutputItem> {
+    // The backend is only used to get the audio sample rate
+    stack_output(values, &SafeSys::new())
+}
+
+#[allow(clippy::mutable_key_type)]
+pub(crate) fn run_code_single(
+    id: &str,
+    code: &str,
+) -> (Vec<OutputItem>, Option<UiuaError>, RunHistory) {
+    // Run
+    let mut rt = init_rt(id, code);
+    let mut error = None;
+    let mut comp = Compiler::with_backend(WebBackend::new(id, code));
+    comp.record_lines(true);
+    let comp_backend;
+    let res = comp.load_str(code).map(|comp| rt.run_compiler(comp));
+    let history = (rt.take_line_history().into_iter())
+        .map(|(span, stack)| (span.start.line as usize, stack))
+        .collect();
+    let (values, io) = match res {
+        Ok(Ok(())) => {
+            let stack = rt.take_stack();
+            let backend = rt.downcast_backend::<WebBackend>().unwrap();
+            backend.finish();
+            (stack, backend)
+        }
+        Ok(Err(e)) if matches!(e.kind, UiuaErrorKind::Interrupted) => (
+            rt.take_stack(),
+            rt.downcast_backend::<WebBackend>().unwrap(),
+        ),
+        Ok(Err(e)) => {
+            error = Some(e);
+            (
+                rt.take_stack(),
+                rt.downcast_backend::<WebBackend>().unwrap(),
+            )
+        }
+        Err(e) => {
+            error = Some(e);
+            comp_backend = comp.take_backend::<WebBackend>().unwrap();
+            (Vec::new(), &comp_backend)
         }
+    };
+    let diagnostics = comp.take_diagnostics();
+    // Get stdout and stderr
+    let stdout = take(&mut *io.stdout.lock().unwrap());
+    let mut stack = stack_output(values, io);
+    // Show the results of derivatives and integrals as math
+    let mut notations: Vec<_> = comp.code_meta().math_notations.iter().collect();
+    notations.sort_by_key(|(span, _)| span.start.byte_pos);
+    for (_, notation) in notations {
+        stack.push(OutputItem::Math(notation.mathml.clone()));
     }
     let stderr = take(&mut *io.stderr.lock().unwrap());
     let trace = take(&mut *io.trace.lock().unwrap());
@@ -1126,7 +1201,11 @@ fn run_code_single(id: &str, code: &str) -> (Vec<OutputItem>, Option<UiuaError>)
             output = output.split_off(output.len() - MAX_OUTPUT_BEFORE_ERROR);
             output[0] = OutputItem::String("Previous output truncated...".into());
         }
-        let report = error.report();
+        let report = if get_full_trace() {
+            error.full_report()
+        } else {
+            error.report()
+        };
         let execution_limit_reached = report.fragments.iter().any(|frag| matches!(frag, ReportFragment::Plain(s) if s.contains("Maximum execution time exceeded")));
         output.push(OutputItem::Report(report));
         if execution_limit_reached {
@@ -1144,7 +1223,7 @@ fn run_code_single(id: &str, code: &str) -> (Vec<OutputItem>, Option<UiuaError>)
         }
     }
     output.extend(rt.take_reports().into_iter().map(OutputItem::Report));
-    (output, error)
+    (output, error, history)
 }
 
 pub fn report_view(report: &Report) -> impl IntoView {
@@ -1277,18 +1356,43 @@ pub fn url_encode_code(code: &str) -> String {
     )
 }
 
+thread_local! {
+    /// The local variables sent to the worker, which cannot access local storage
+    static WORKER_VARS: RefCell<Option<HashMap<String, String>>> = Default::default();
+}
+
+/// Get all local variables, so they can be sent to the worker
+pub(crate) fn local_vars() -> HashMap<String, String> {
+    let storage = window().local_storage().unwrap().unwrap();
+    let len = storage.length().unwrap_or(0);
+    (0..len)
+        .filter_map(|i| storage.key(i).ok().flatten())
+        .filter_map(|key| Some((key.clone(), storage.get_item(&key).ok()??)))
+        .collect()
+}
+
+/// Set the local variables of the worker
+pub(crate) fn set_worker_vars(vars: HashMap<String, String>) {
+    WORKER_VARS.with(|worker_vars| *worker_vars.borrow_mut() = Some(vars));
+}
+
 fn get_local_var<T>(name: &str, default: impl FnOnce() -> T) -> T
 where
     T: FromStr,
     T::Err: std::fmt::Display,
 {
-    window()
-        .local_storage()
-        .unwrap()
-        .unwrap()
-        .get_item(name)
-        .ok()
-        .flatten()
+    let worker_var =
+        WORKER_VARS.with(|vars| (vars.borrow().as_ref()).map(|vars| vars.get(name).cloned()));
+    worker_var
+        .unwrap_or_else(|| {
+            window()
+                .local_storage()
+                .unwrap()
+                .unwrap()
+                .get_item(name)
+                .ok()
+                .flatten()
+        })
         .and_then(|s| {
             s.parse()
                 .map_err(|e| logging::log!("Error parsing local var {name:?} = {s:?}: {e}"))
@@ -1309,6 +1413,13 @@ where
         .unwrap();
 }
 
+pub fn get_run_in_worker() -> bool {
+    get_local_var("run-in-worker", || true)
+}
+pub fn set_run_in_worker(run_in_worker: bool) {
+    set_local_var("run-in-worker", run_in_worker);
+}
+
 pub fn get_execution_limit() -> f64 {
     get_local_var("execution-limit", || 2.0)
 }
@@ -1390,6 +1501,39 @@ pub fn set_inlay_values(inlay_values: bool) {
     set_local_var("inlay-values", inlay_values);
 }
 
+pub fn get_spoken_glyphs() -> bool {
+    get_local_var("spoken-glyphs", || false)
+}
+pub fn set_spoken_glyphs(spoken_glyphs: bool) {
+    set_local_var("spoken-glyphs", spoken_glyphs);
+}
+
+pub fn get_high_contrast() -> bool {
+    get_local_var("high-contrast", || false)
+}
+pub fn set_high_contrast(high_contrast: bool) {
+    set_local_var("high-contrast", high_contrast);
+}
+
+/// Check if the editor is running on a touch screen
+pub fn is_touch_device() -> bool {
+    window().navigator().max_touch_points() > 0
+}
+
+pub fn get_touch_keyboard() -> bool {
+    get_local_var("touch-keyboard", is_touch_device)
+}
+pub fn set_touch_keyboard(touch_keyboard: bool) {
+    set_local_var("touch-keyboard", touch_keyboard);
+}
+
+pub fn get_full_trace() -> bool {
+    get_local_var("full-trace", || false)
+}
+pub fn set_full_trace(full_trace: bool) {
+    set_local_var("full-trace", full_trace);
+}
+
 fn update_style() {
     let font_name = get_font_name();
     let font_size = get_font_size();
@@ -1467,3 +1611,49 @@ pub fn format_insert_file_code(path: &Path, content: Vec<u8>) -> String {
         format!("# {byte_count} bytes\n# {function} {file_name:?}\n")
     }
 }
+
+/// Get a version of some code with glyphs replaced by their spoken names
+///
+/// This is what screen readers are given to describe the code
+pub fn spoken_code(code: &str) -> String {
+    let (tokens, ..) = lex(code, (), &mut Inputs::default());
+    let mut spoken = String::new();
+    for token in tokens {
+        match token.value {
+            Token::Glyph(prim) => {
+                spoken.push(' ');
+                spoken.push_str(&prim.spoken_name());
+                spoken.push(' ');
+            }
+            _ => spoken.push_str(&code[token.span.byte_range()]),
+        }
+    }
+    (spoken.lines())
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Get the primitives offered when a glyph button is long-pressed
+///
+/// These are the primitives of the same class as the pressed one
+pub fn glyph_variants(prim: Primitive) -> Vec<Primitive> {
+    Primitive::non_deprecated()
+        .filter(|p| p.class() == prim.class())
+        .filter(|p| get_show_experimental() || !p.is_experimental())
+        .collect()
+}
+
+/// Move the start and end of the code selection by some number of characters
+///
+/// The start can not move past the end, and vice versa.
+pub fn move_selection(state: &State, start: i32, end: i32) {
+    let Some((curr_start, curr_end)) = get_code_cursor(&state.code_id) else {
+        return;
+    };
+    let len = get_code(&state.code_id).chars().count() as i32;
+    let new_start = (curr_start as i32 + start).clamp(0, curr_end as i32);
+    let new_end = (curr_end as i32 + end).clamp(new_start, len);
+    _ = element::<HtmlTextAreaElement>(&state.code_id).focus();
+    state.set_cursor((new_start as u32, new_end as u32));
+}
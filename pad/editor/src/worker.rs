@@ -0,0 +1,471 @@
+//! Running code in a web worker
+//!
+//! Code run in the worker does not freeze the page,
+//! and a run can be stopped by terminating the worker.
+//!
+//! Messages are sent as JSON. The bytes of images, audio, and files
+//! are sent alongside the JSON as transferable buffers.
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    mem::take,
+    path::PathBuf,
+    rc::Rc,
+    time::Duration,
+};
+
+use js_sys::{Array, Date, Object, Reflect, Uint8Array};
+use leptos::*;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use uiua::seed_random;
+use wasm_bindgen::prelude::*;
+use web_sys::{DedicatedWorkerGlobalScope, Event, MessageEvent, Worker};
+
+use crate::{
+    backend::{delete_file, drop_file, take_changed_files, OutputItem, FILES},
+    utils::{local_vars, run_code_single, set_worker_vars, RunHistory},
+    START_TIME,
+};
+
+/// The script that starts the worker
+///
+/// This is the loader that trunk generates for the site's `worker` binary.
+pub const WORKER_URL: &str = "/worker_loader.js";
+
+/// The script that runs threads spawned by code in the worker
+pub const THREAD_URL: &str = "/thread.js";
+
+/// A request to run some code, sent to the worker
+#[derive(Serialize, Deserialize)]
+struct RunRequest {
+    run: u64,
+    code_id: String,
+    code: String,
+    seed: u64,
+    /// The editor settings, which the worker cannot read from local storage
+    settings: HashMap<String, String>,
+    /// The paths of the virtual files
+    ///
+    /// Their contents are sent as buffers.
+    files: Vec<PathBuf>,
+}
+
+/// A message sent from the worker
+#[derive(Serialize, Deserialize)]
+enum WorkerMessage {
+    /// The worker is ready for requests
+    Ready,
+    /// Text printed to stdout during a run
+    Stdout { run: u64, text: String },
+    /// A run has finished
+    Done {
+        run: u64,
+        output: Vec<OutputItem>,
+        /// The run's history as JSON, which is empty if it could not be serialized
+        history: String,
+        /// Whether the run is waiting for an asynchronous result and should be rerun
+        waiting: bool,
+        /// The paths of files changed by the run, and whether each still exists
+        ///
+        /// The contents of existing files are sent as buffers after the output's media.
+        changed: Vec<(PathBuf, bool)>,
+    },
+}
+
+/// The result of a run in the worker
+pub struct RunResult {
+    pub output: Vec<OutputItem>,
+    pub history: RunHistory,
+}
+
+/// A run that has not finished yet
+struct PendingRun {
+    code_id: String,
+    code: String,
+    seed: u64,
+    /// Everything printed to stdout by the current attempt
+    stdout: String,
+    on_stdout: Rc<dyn Fn(&str)>,
+    on_done: Box<dyn FnOnce(Option<RunResult>)>,
+}
+
+/// The worker along with its event handlers, which are dropped when it is terminated
+struct RunningWorker {
+    worker: Worker,
+    _on_message: Closure<dyn Fn(MessageEvent)>,
+    _on_error: Closure<dyn Fn(Event)>,
+}
+
+thread_local! {
+    static WORKER: RefCell<Option<RunningWorker>> = Default::default();
+    static PENDING: RefCell<HashMap<u64, PendingRun>> = Default::default();
+    static NEXT_RUN: Cell<u64> = const { Cell::new(0) };
+    /// Whether the worker has started and can receive requests
+    static READY: Cell<bool> = const { Cell::new(false) };
+    /// Whether the worker failed, in which case code is run on the page
+    static FAILED: Cell<bool> = const { Cell::new(false) };
+    /// The run whose stdout is being sent to the page, if this is the worker
+    static STREAMING: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+/// Whether this code is running in the worker
+pub(crate) fn in_worker() -> bool {
+    js_sys::global().is_instance_of::<DedicatedWorkerGlobalScope>()
+}
+
+/// Whether code in the worker can spawn threads that run in parallel
+///
+/// Threads share the module's memory, so this requires the module to be built
+/// with wasm atomics and the page to be cross-origin isolated.
+/// Otherwise, threads are run one after another.
+pub(crate) fn threads_available() -> bool {
+    cfg!(target_feature = "atomics")
+        && in_worker()
+        && Reflect::get(&js_sys::global(), &"crossOriginIsolated".into())
+            .is_ok_and(|isolated| isolated.is_truthy())
+}
+
+/// Run a function on a new thread
+///
+/// The thread is a worker that shares this module and its memory.
+pub(crate) fn spawn_thread(f: Box<dyn FnOnce() + Send>) -> Result<(), JsValue> {
+    let thread = Worker::new(THREAD_URL)?;
+    let ptr = Box::into_raw(Box::new(f));
+    let message = Array::of3(
+        &wasm_bindgen::module(),
+        &wasm_bindgen::memory(),
+        &JsValue::from(ptr as u32),
+    );
+    if let Err(e) = thread.post_message(&message) {
+        // SAFETY: The pointer was just created and was not sent to the thread
+        drop(unsafe { Box::from_raw(ptr) });
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Run a function sent to a thread by [`spawn_thread`]
+///
+/// This is called by the thread script.
+#[wasm_bindgen]
+pub fn run_thread(ptr: u32) {
+    // SAFETY: The pointer was created by `spawn_thread`, which sends it to only one thread
+    let f = unsafe { Box::from_raw(ptr as *mut Box<dyn FnOnce() + Send>) };
+    f();
+}
+
+/// Whether code can be run in the worker
+pub fn worker_available() -> bool {
+    !FAILED.get() && Reflect::has(&js_sys::global(), &"Worker".into()).unwrap_or(false)
+}
+
+/// Run code in the worker
+///
+/// Runs that are waiting for an asynchronous result are rerun until they finish.
+/// `on_stdout` is called with everything printed so far each time something is printed.
+/// If the worker fails, `on_done` is called with `None`, and the code should be run on the page instead.
+pub fn run_in_worker(
+    code_id: &str,
+    code: &str,
+    seed: u64,
+    on_stdout: impl Fn(&str) + 'static,
+    on_done: impl FnOnce(Option<RunResult>) + 'static,
+) {
+    let run = NEXT_RUN.get();
+    NEXT_RUN.set(run + 1);
+    let pending = PendingRun {
+        code_id: code_id.into(),
+        code: code.into(),
+        seed,
+        stdout: String::new(),
+        on_stdout: Rc::new(on_stdout),
+        on_done: Box::new(on_done),
+    };
+    PENDING.with(|runs| runs.borrow_mut().insert(run, pending));
+    send(run);
+}
+
+/// Stop all runs in the worker by terminating it
+///
+/// A new worker is started for the next run.
+pub fn stop_worker() {
+    terminate();
+    for run in take_pending() {
+        (run.on_done)(Some(RunResult {
+            output: vec![OutputItem::Faint("Stopped".into())],
+            history: Vec::new(),
+        }));
+    }
+}
+
+/// Stop using the worker, and have pending runs run on the page instead
+fn fail() {
+    FAILED.set(true);
+    terminate();
+    for run in take_pending() {
+        (run.on_done)(None);
+    }
+}
+
+fn terminate() {
+    if let Some(running) = WORKER.with(|worker| worker.borrow_mut().take()) {
+        running.worker.terminate();
+        // One of the handlers may be running, so they are dropped later
+        set_timeout(move || drop(running), Duration::ZERO);
+    }
+    READY.set(false);
+}
+
+fn take_pending() -> Vec<PendingRun> {
+    PENDING.with(|runs| runs.borrow_mut().drain().map(|(_, run)| run).collect())
+}
+
+/// Get the worker, starting it if it is not running
+fn worker() -> Result<Worker, JsValue> {
+    if let Some(worker) = WORKER.with(|worker| (worker.borrow().as_ref()).map(|w| w.worker.clone()))
+    {
+        return Ok(worker);
+    }
+    let worker = Worker::new(WORKER_URL)?;
+    let on_message = Closure::<dyn Fn(MessageEvent)>::new(handle_message);
+    worker.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    let on_error = Closure::<dyn Fn(Event)>::new(|event: Event| {
+        logging::warn!("Worker failed: {:?}", event);
+        fail();
+    });
+    worker.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+    WORKER.with(|w| {
+        *w.borrow_mut() = Some(RunningWorker {
+            worker: worker.clone(),
+            _on_message: on_message,
+            _on_error: on_error,
+        })
+    });
+    Ok(worker)
+}
+
+/// Send a pending run to the worker
+///
+/// If the worker is not ready yet, the run is sent once it is.
+fn send(run: u64) {
+    let Some((code_id, code, seed)) = PENDING.with(|runs| {
+        (runs.borrow().get(&run)).map(|run| (run.code_id.clone(), run.code.clone(), run.seed))
+    }) else {
+        return;
+    };
+    let worker = match worker() {
+        Ok(worker) => worker,
+        Err(e) => {
+            logging::warn!("Unable to start worker: {e:?}");
+            fail();
+            return;
+        }
+    };
+    if !READY.get() {
+        return;
+    }
+    let buffers = Array::new();
+    let files = FILES.with(|files| {
+        (files.borrow().iter())
+            .map(|(path, contents)| {
+                buffers.push(&Uint8Array::from(contents.as_slice()));
+                path.clone()
+            })
+            .collect()
+    });
+    let request = RunRequest {
+        run,
+        code_id,
+        code,
+        seed,
+        settings: local_vars(),
+        files,
+    };
+    let (message, transfer) = encode(&request, buffers);
+    if let Err(e) = worker.post_message_with_transfer(&message, &transfer) {
+        logging::warn!("Unable to send code to worker: {e:?}");
+        fail();
+    }
+}
+
+/// Handle a message from the worker
+fn handle_message(event: MessageEvent) {
+    let Some((message, buffers)) = decode::<WorkerMessage>(&event) else {
+        return;
+    };
+    match message {
+        WorkerMessage::Ready => {
+            READY.set(true);
+            let runs: Vec<u64> = PENDING.with(|runs| runs.borrow().keys().copied().collect());
+            for run in runs {
+                send(run);
+            }
+        }
+        WorkerMessage::Stdout { run, text } => {
+            let update = PENDING.with(|runs| {
+                let mut runs = runs.borrow_mut();
+                let run = runs.get_mut(&run)?;
+                run.stdout.push_str(&text);
+                Some((run.on_stdout.clone(), run.stdout.clone()))
+            });
+            if let Some((on_stdout, stdout)) = update {
+                on_stdout(&stdout);
+            }
+        }
+        WorkerMessage::Done {
+            run,
+            mut output,
+            history,
+            waiting,
+            changed,
+        } => {
+            let mut buffers = buffers.into_iter();
+            restore_media(&mut output, &mut buffers);
+            for (path, exists) in changed {
+                if exists {
+                    drop_file(path, buffers.next().unwrap_or_default());
+                } else {
+                    delete_file(&path);
+                }
+            }
+            if waiting {
+                PENDING.with(|runs| {
+                    if let Some(run) = runs.borrow_mut().get_mut(&run) {
+                        run.stdout.clear();
+                    }
+                });
+                set_timeout(move || send(run), Duration::from_millis(200));
+                return;
+            }
+            if let Some(run) = PENDING.with(|runs| runs.borrow_mut().remove(&run)) {
+                let history = serde_json::from_str(&history).unwrap_or_default();
+                (run.on_done)(Some(RunResult { output, history }));
+            }
+        }
+    }
+}
+
+/// Start handling run requests
+///
+/// This should be called once in the worker.
+/// It does nothing in threads spawned by the worker, which share its module.
+pub fn start_worker() {
+    if Reflect::has(&js_sys::global(), &"uiuaThread".into()).unwrap_or(false) {
+        return;
+    }
+    START_TIME.get_or_init(|| Date::now() / 1000.0);
+    let scope: DedicatedWorkerGlobalScope = js_sys::global().unchecked_into();
+    let on_message = Closure::<dyn Fn(MessageEvent)>::new(handle_request);
+    scope.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    on_message.forget();
+    post_to_page(&WorkerMessage::Ready, Array::new());
+}
+
+/// Handle a run request in the worker
+fn handle_request(event: MessageEvent) {
+    let Some((request, buffers)) = decode::<RunRequest>(&event) else {
+        return;
+    };
+    set_worker_vars(request.settings);
+    FILES.with(|files| *files.borrow_mut() = request.files.into_iter().zip(buffers).collect());
+    take_changed_files();
+
+    STREAMING.set(Some(request.run));
+    seed_random(request.seed);
+    let (mut output, error, history) = run_code_single(&request.code_id, &request.code);
+    STREAMING.set(None);
+
+    let waiting = error.is_some_and(|e| e.to_string().contains("try running to check"));
+    let buffers = Array::new();
+    take_media(&mut output, &buffers);
+    let changed = (take_changed_files().into_iter())
+        .map(|path| {
+            let contents = FILES.with(|files| files.borrow().get(&path).cloned());
+            if let Some(contents) = &contents {
+                buffers.push(&Uint8Array::from(contents.as_slice()));
+            }
+            (path, contents.is_some())
+        })
+        .collect();
+    post_to_page(
+        &WorkerMessage::Done {
+            run: request.run,
+            output,
+            history: serde_json::to_string(&history).unwrap_or_default(),
+            waiting,
+            changed,
+        },
+        buffers,
+    );
+}
+
+/// Send text printed to stdout to the page, if this is the worker
+pub(crate) fn stream_stdout(text: &str) {
+    if let Some(run) = STREAMING.get() {
+        let text = text.into();
+        post_to_page(&WorkerMessage::Stdout { run, text }, Array::new());
+    }
+}
+
+fn post_to_page(message: &WorkerMessage, buffers: Array) {
+    let scope: DedicatedWorkerGlobalScope = js_sys::global().unchecked_into();
+    let (message, transfer) = encode(message, buffers);
+    if let Err(e) = scope.post_message_with_transfer(&message, &transfer) {
+        logging::warn!("Unable to send message from worker: {e:?}");
+    }
+}
+
+/// Move the bytes of media output into buffers
+fn take_media(output: &mut [OutputItem], buffers: &Array) {
+    for item in output {
+        if let OutputItem::Image(bytes, _)
+        | OutputItem::Gif(bytes, _)
+        | OutputItem::Audio(bytes, _) = item
+        {
+            buffers.push(&Uint8Array::from(take(bytes).as_slice()));
+        }
+    }
+}
+
+/// Move the bytes of media output back out of buffers
+fn restore_media(output: &mut [OutputItem], buffers: &mut impl Iterator<Item = Vec<u8>>) {
+    for item in output {
+        if let OutputItem::Image(bytes, _)
+        | OutputItem::Gif(bytes, _)
+        | OutputItem::Audio(bytes, _) = item
+        {
+            *bytes = buffers.next().unwrap_or_default();
+        }
+    }
+}
+
+/// Encode a message and its buffers, returning the message and the list of objects to transfer
+fn encode(message: &impl Serialize, buffers: Array) -> (JsValue, Array) {
+    let json = serde_json::to_string(message).unwrap_or_else(|e| {
+        logging::warn!("Unable to serialize message: {e}");
+        String::new()
+    });
+    let object = Object::new();
+    _ = Reflect::set(&object, &"json".into(), &json.into());
+    _ = Reflect::set(&object, &"buffers".into(), &buffers);
+    let transfer = buffers
+        .iter()
+        .map(|buffer| buffer.unchecked_into::<Uint8Array>().buffer());
+    (object.into(), transfer.collect())
+}
+
+fn decode<T: DeserializeOwned>(event: &MessageEvent) -> Option<(T, Vec<Vec<u8>>)> {
+    let data = event.data();
+    let json = Reflect::get(&data, &"json".into()).ok()?.as_string()?;
+    let message = serde_json::from_str(&json)
+        .map_err(|e| logging::warn!("Unable to deserialize message: {e}"))
+        .ok()?;
+    let buffers: Array = Reflect::get(&data, &"buffers".into())
+        .ok()?
+        .dyn_into()
+        .ok()?;
+    let buffers = buffers.iter();
+    let buffers = buffers.map(|buffer| buffer.unchecked_into::<Uint8Array>().to_vec());
+    Some((message, buffers.collect()))
+}
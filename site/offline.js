@@ -0,0 +1,47 @@
+// Offline support for the site and its editor
+//
+// If the site was built with offline.ua, this registers the service worker in sw.js
+// and exposes `window.uiuaOffline` for querying and updating the cached build:
+//
+// ```js
+// const cached = await uiuaOffline.version(); // { version: "0.15.0", build: 1734567890 } or null
+// const updated = await uiuaOffline.update(); // The info of the newest build, which is now cached
+// ```
+
+(function () {
+  if (!("serviceWorker" in navigator)) {
+    return;
+  }
+
+  // Send a message to the active service worker and wait for its reply
+  async function ask(message) {
+    const registration = await navigator.serviceWorker.ready;
+    return new Promise((resolve, reject) => {
+      const channel = new MessageChannel();
+      channel.port1.onmessage = (event) => {
+        if (event.data && event.data.error) {
+          reject(new Error(event.data.error));
+        } else {
+          resolve(event.data);
+        }
+      };
+      registration.active.postMessage(message, [channel.port2]);
+    });
+  }
+
+  window.uiuaOffline = {
+    // Get the version and build of the cached interpreter, or null if nothing is cached
+    version: () => ask("version"),
+    // Cache the newest build if it is not already cached and get its version and build
+    update: () => ask("update"),
+  };
+
+  // Only bundles built for offline use have a manifest
+  fetch("/offline-manifest.json", { method: "HEAD", cache: "no-store" })
+    .then((response) => {
+      if (response.ok) {
+        return navigator.serviceWorker.register("/sw.js");
+      }
+    })
+    .catch(() => {});
+})();
@@ -0,0 +1,6 @@
+//! The web worker that runs code for the editor
+
+pub fn main() {
+    console_error_panic_hook::set_once();
+    uiua_editor::worker::start_worker();
+}
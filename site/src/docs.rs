@@ -293,9 +293,40 @@ struct Allowed {
 fn aliases() -> HashMap<&'static str, &'static [Primitive]> {
     use Primitive::*;
     [
-        ("filter", &[Keep] as &[_]),
+        ("filter", &[Keep, Convolve] as &[_]),
+        ("correlate", &[Convolve]),
+        ("groupby", &[Aggregate, Group]),
+        ("and", &[BitAnd, Min, Mul]),
+        ("xor", &[BitXor, Ne]),
+        ("shift", &[BitShift]),
+        ("cast", &[Signed, Unsigned]),
+        ("wrap", &[Signed, Unsigned]),
+        ("money", &[Decimal]),
+        ("currency", &[Decimal]),
+        ("uncertainty", &[Interval]),
+        ("error", &[Interval]),
+        ("root", &[Solve]),
+        ("zero", &[Solve]),
+        ("optimize", &[Minimize]),
+        ("integrate", &[Ode, Integral]),
+        ("rungekutta", &[Ode]),
+        ("simulate", &[Ode]),
+        ("latex", &[Math]),
+        ("symbolic", &[Math]),
+        ("notation", &[Math]),
+        ("eval", &[Evaluate]),
+        ("exec", &[Evaluate]),
+        ("sandbox", &[Evaluate]),
+        ("ast", &[Reflect]),
+        ("introspect", &[Reflect, Invertible]),
+        ("metaprogramming", &[Reflect, Reify, Quote]),
+        ("inverse", &[Invertible, Un]),
         ("search", &[Find, Mask]),
-        ("intersect", &[MemberOf]),
+        ("intersect", &[MemberOf, Intersection]),
+        (
+            "set",
+            &[Union, Intersection, Difference, SymDiff, Deduplicate],
+        ),
         ("split", &[Partition]),
         ("while", &[Do]),
         ("for", &[Repeat]),
@@ -420,8 +451,19 @@ impl Allowed {
                 ("images", &[PrimClass::Sys(SysOpClass::Media)]),
                 ("gifs", &[PrimClass::Sys(SysOpClass::Media)]),
                 ("audio", &[PrimClass::Sys(SysOpClass::Media)]),
+                ("window gui", &[PrimClass::Sys(SysOpClass::Window)]),
                 ("tcp", &[PrimClass::Sys(SysOpClass::Tcp)]),
+                ("network dns", &[PrimClass::Sys(SysOpClass::Network)]),
+                ("osc udp", &[PrimClass::Sys(SysOpClass::Osc)]),
+                ("mqtt iot", &[PrimClass::Sys(SysOpClass::Mqtt)]),
+                ("email smtp", &[PrimClass::Sys(SysOpClass::Email)]),
+                (
+                    "keyring keychain secret password",
+                    &[PrimClass::Sys(SysOpClass::Keyring)],
+                ),
+                ("embedded gpio i2c", &[PrimClass::Sys(SysOpClass::Embedded)]),
                 ("env", &[PrimClass::Sys(SysOpClass::Env)]),
+                ("terminal tui", &[PrimClass::Sys(SysOpClass::Terminal)]),
                 ("command", &[PrimClass::Sys(SysOpClass::Command)]),
                 ("filesystem", &[PrimClass::Sys(SysOpClass::Filesystem)]),
                 ("stream", &[PrimClass::Sys(SysOpClass::Stream)]),
@@ -429,6 +471,10 @@ impl Allowed {
                 ("thread", &[PrimClass::Thread]),
                 ("map", &[PrimClass::Map]),
                 ("encoding encode", &[PrimClass::Encoding]),
+                ("channel", &[PrimClass::Sys(SysOpClass::Channel)]),
+                ("atomic", &[PrimClass::Sys(SysOpClass::Atomic)]),
+                ("log", &[PrimClass::Sys(SysOpClass::Log)]),
+                ("metrics prometheus", &[PrimClass::Sys(SysOpClass::Metrics)]),
                 ("ffi", &[PrimClass::Sys(SysOpClass::Ffi)]),
                 ("misc", &[PrimClass::Sys(SysOpClass::Misc)]),
             ] {
@@ -560,10 +606,22 @@ impl Allowed {
                         SysOpClass::Filesystem => ("System - Filesystem".into_view(), "Work with files and directories"),
                         SysOpClass::StdIO => ("System - Standard I/O".into_view(), "Read and write standard input and output"),
                         SysOpClass::Env => ("System - Environment".into_view(), "Query the environment"),
+                        SysOpClass::Terminal => ("System - Terminal".into_view(), "Control the terminal and read its events"),
                         SysOpClass::Stream => ("System - Streams".into_view(), "Read from and write to streams"),
                         SysOpClass::Command => ("System - Commands".into_view(), "Execute commands"),
                         SysOpClass::Media => ("System - Media".into_view(), "Present media"),
+                        SysOpClass::Window => ("System - Windows".into_view(), "Draw to windows and read their input"),
                         SysOpClass::Tcp => ("System - TCP".into_view(), "Work with TCP sockets"),
+                        SysOpClass::Network => ("System - Network".into_view(), "Look up hosts and inspect the network"),
+                        SysOpClass::Osc => ("System - OSC".into_view(), "Send and receive Open Sound Control messages"),
+                        SysOpClass::Mqtt => ("System - MQTT".into_view(), "Publish and subscribe to MQTT topics"),
+                        SysOpClass::Email => ("System - Email".into_view(), "Send email over SMTP"),
+                        SysOpClass::Keyring => ("System - Keyring".into_view(), "Store secrets in the operating system's keychain"),
+                        SysOpClass::Embedded => ("System - Embedded".into_view(), "Control GPIO pins and I2C devices"),
+                        SysOpClass::Channel => ("System - Channels".into_view(), "Communicate between threads"),
+                        SysOpClass::Atomic => ("System - Atomics".into_view(), "Share mutable state between threads"),
+                        SysOpClass::Log => ("System - Logging".into_view(), "Write structured log records"),
+                        SysOpClass::Metrics => ("System - Metrics".into_view(), "Collect metrics for Prometheus"),
                         SysOpClass::Ffi => ("System - FFI".into_view(), "Foreign function interface"),
                         SysOpClass::Misc => ("System - Misc".into_view(), ""),
                     }
@@ -93,8 +93,41 @@ pub fn Install() -> impl IntoView {
         <p>"You should configure you editor so that it automatically reloads files if they change on disk. This will allow you to see the formatted file as soon as it is saved."</p>
         <p>"Use "<code>"uiua <PATH>"</code>" or "<code>"uiua run [PATH]"</code>" to format and run a file without watching it."</p>
         <p>"Use "<code>"uiua fmt [PATH]"</code>" to format a file without running it."</p>
+        <p>"Use "<code>"uiua fmt --names [PATH]"</code>" to convert glyphs back to their ASCII names. Formatting the file normally converts them to glyphs again."</p>
         <p>"Use "<code>"uiua test [PATH]"</code>" to run tests."</p>
+        <p>"Use "<code>"uiua lint [PATH]"</code>" to check for likely mistakes, like code after an assertion that always fails. Rules can be turned off with "<code>"--allow RULE"</code>" or, for a single file, with a comment like "<code>"# lint allow long-dip-chain"</code>"."</p>
+        <p>"Use "<code>"uiua check --types [PATH]"</code>" to find type and shape mismatches that will certainly cause errors, like "<code>"⊟"</code>" of arrays with different shapes, without running the code. The language server shows these as warnings and shows inferred types on hover."</p>
         <p>"Use "<code>"uiua module update"</code>" to update Git modules."</p>
+        <p>"Use "<code>"uiua new <NAME>"</code>" to create a package, "<code>"uiua fetch"</code>" to fetch its dependencies, and "<code>"uiua publish"</code>" to publish it. See "<a href="/tutorial/modules#packages">"Packages"</a>"."</p>
+
+        <Hd id="sandboxing">"Sandboxing"</Hd>
+        <p>"The "<code>"run"</code>", "<code>"watch"</code>", "<code>"eval"</code>", "<code>"test"</code>", and "<code>"repl"</code>" commands accept flags that restrict what a program can do. This is useful for running code you do not trust."</p>
+        <code class="code-block">"\
+--no-net          Deny network access
+--no-commands     Deny running commands and Python
+--no-ffi          Deny FFI and plugins
+--fs-read-only    Deny creating, modifying, and deleting files
+--allow-path DIR  Only allow filesystem access within DIR
+--sandbox         All of the above except --allow-path"
+        </code>
+        <p><code>"--allow-path"</code>" can be passed more than once. Imported modules must also be in an allowed directory."</p>
+        <p>"Denied operations throw an error that can be caught with "<Prim prim=Try/>"."</p>
+
+        <Hd id="recording-and-replaying">"Recording and Replaying"</Hd>
+        <p>"A run that depends on the time, the network, files, commands, or random numbers can be hard to reproduce. "<code>"uiua run"</code>" can record every system call a program makes to a file and replay them later."</p>
+        <code class="code-block">"\
+uiua run --record run.jsonl main.ua
+uiua run --replay run.jsonl main.ua"
+        </code>
+        <p>"A replayed program gets the same results it got during the recording, without touching the real system. Its output is still printed. If it makes a different system call than the recorded one, the call throws an error that says how they differ."</p>
+        <p>"Programs that make system calls from multiple threads are only guaranteed to replay faithfully if the calls happen in the same order."</p>
+
+        <Hd id="ffi-bindings">"Generating FFI Bindings"</Hd>
+        <p>"Writing the type strings for "<Prim prim=Sys(SysOp::Ffi)/>" by hand is error-prone, and a wrong type can crash the interpreter. "<code>"uiua ffi-gen"</code>" reads the declarations in a C header and generates a module with a wrapper function for each foreign function."</p>
+        <code class="code-block">"uiua ffi-gen mylib.h --lib ./libmylib.so -o mylib.ua"
+        </code>
+        <p>"Functions are named in "<code>"PascalCase"</code>", so "<code>"vec2_add"</code>" becomes "<code>"VecTwoAdd"</code>". Numeric "<code>"#define"</code>"s and enum values become constants. A pointer parameter followed by an integer parameter with a name like "<code>"len"</code>" or "<code>"count"</code>" becomes a list, and its length is passed automatically."</p>
+        <p>"Declarations that can't be translated, like variadic functions or unions passed by value, are left in the module as comments explaining why they were skipped."</p>
 
         <Hd id="the-output-window">"The Output Window"</Hd>
         <p>"If you download the native interpreter from the "<a href="https://github.com/uiua-lang/uiua/releases">"releases page"</a>", or if you build it from source with either the "<code>"window"</code>" or "<code>"full"</code>" features, you will have the option to show the output of a program (and certain system functions) in a window."</p>
@@ -108,6 +141,20 @@ uiua watch -w"
         <p>"It can also be enabled by default if you set the "<code>"UIUA_WINDOW"</code>" environment variable to "<code>"1"</code>"."</p>
         <p>"The values left on the stack after a program finishes will be shown in the window. In addition, the "<Prim prim=Sys(SysOp::Show)/>", "<Prim prim=Sys(SysOp::ImShow)/>", "<Prim prim=Sys(SysOp::GifShow)/>", and "<Prim prim=Sys(SysOp::AudioPlay)/>" functions will all show their output in the window."</p>
 
+        <Hd id="hot-reloading">"Hot Reloading"</Hd>
+        <p><code>"uiua watch"</code>" normally restarts a program every time its file changes. For live-coding visuals and music, "<code>"uiua watch --hot"</code>" instead keeps the program running and swaps in its new bindings."</p>
+        <p>"In this mode, the top level of the program is run once, and the values it leaves on the stack are the program's state. If the program binds a function called "<code>"Frame"</code>", it is then called over and over with the state, and the values it returns become the new state."</p>
+        <code class="code-block">"\
+# Experimental!
+Frame ← (
+  ⊸(&winshow \"Circle\" <÷2+1∿ ⌵⊞ℂ.-1÷50⇡100)
+  +×2 &winframe 60
+)
+0"
+        </code>
+        <p>"When the file changes, its top level is run again and its bindings are replaced. If the top level leaves the same number of values on the stack as before, they are discarded and the old state is kept. If the file fails to compile, the old code keeps running."</p>
+        <p>"If the program binds a function called "<code>"Sound"</code>", it is streamed as audio. Like the function passed to "<Prim prim=Sys(SysOp::AudioStream)/>", it takes an array of times in seconds and returns samples. The stream is not interrupted when the file changes."</p>
+
         <Hd id="local-site">"Running the Site Locally"</Hd>
         <p>"This website is a static, single-page application. As such, it can be build and run locally, without connecting to a server."</p>
         <p>"To do this, you will need "<a href="https://www.rust-lang.org/tools/install">"Rust"</a>" installed. You will also need the "<a href="https://github.com/uiua-lang/uiua">"Uiua repository"</a>" cloned locally."</p>
@@ -162,7 +209,7 @@ pub fn Constants() -> impl IntoView {
         by_class.entry(con.class).or_default().push(view);
     }
     let class_cols = [
-        vec![Math, Time, Color, Flags],
+        vec![ConstClass::Math, Time, Color, Flags],
         vec![External, Media, Spatial, System, Fun],
     ];
     let cols = class_cols.map(|col_classes| {
@@ -232,7 +232,8 @@ pub fn Documentation() -> impl IntoView {
         <p>"This is useful for making it clear to users that a function is no longer supported, but without breaking their code."</p>
         <Editor example="# Deprecated!\nF ← +2\nF 5"/> // Should fail
         <p>"You can note a suggested replacement after the "<code>"!"</code>". This will appear in the warning message."</p>
-        <Editor example="F ← +2 # Deprecated! Use G instead\nG ← ×2\nF 5"/> // Should fail
+        <Editor example="F ← +2 # Deprecated! Use `G` instead\nG ← ×2\nF 5"/> // Should fail
+        <p>"The first name in backticks is treated as the replacement. The language server offers a quick fix that replaces uses of the deprecated binding with it."</p>
         <p><code>"# Deprecated!"</code>" works for constants, macros, and modules as well."</p>
         <Editor example="# Deprecated! Don't use it!\n┌─╴Foo\n  # Deprecated! It's no good!\n  Bar ← 5\n└─╴\nFoo~Bar"/> // Should fail
     }
@@ -125,6 +125,7 @@ pub fn PrimDocs(prim: Primitive) -> impl IntoView {
             { match prim {
                 Primitive::Un => all_uns().into_view(),
                 Primitive::Under => all_unders().into_view(),
+                Primitive::Anti => all_antis().into_view(),
                 Primitive::Fill => all_fills().into_view(),
                 _ => View::default(),
             } }
@@ -304,11 +305,41 @@ fn all_uns() -> impl IntoView {
             { inverse_row([ImageEncode], Optional, "Decodes bytes", None) }
             { inverse_row([GifEncode], Optional, "Decodes bytes", None) }
             { inverse_row([Sys(Clip)], No, "Set the clipboard", None) }
+            { inverse_row([Sys(ClipWrite)], No, "Get the clipboard", None) }
             { inverse_row([Sys(RawMode)], No, "Terminal raw state", None) }
         </table>
     }
 }
 
+fn all_antis() -> impl IntoView {
+    use Primitive::*;
+    let rows: Vec<_> = Primitive::non_deprecated()
+        .filter(Primitive::is_anti_invertible)
+        .map(|prim| {
+            view! {
+                <tr>
+                    <td><h3><Prim prim=prim/></h3></td>
+                    <td><code>{ format!("{Anti}{prim}") }</code></td>
+                    <td><code>{ format!("{Pop}{Un}{On}{prim}") }</code></td>
+                </tr>
+            }
+        })
+        .collect();
+    view! {
+        <Hd id="antis"><Prim prim=Anti/>"-compatible primitives"</Hd>
+        <p>"These dyadic primitives can be used with "<Prim prim=Anti/>" on their own. This list is generated by the interpreter."</p>
+        <p>"Many more functions, including ones that contain constants, can be inverted with "<Prim prim=Anti/>". See the "<A href="/docs/un#uns">"table"</A>" for "<Prim prim=Un/>" for more."</p>
+        <table class="header-centered-table cell-centered-table" style="width: 100%">
+            <tr>
+                <th>"Primitive"</th>
+                <th>"Code"</th>
+                <th>"Equivalent"</th>
+            </tr>
+            { rows }
+        </table>
+    }
+}
+
 fn all_unders() -> impl IntoView {
     use Primitive::*;
     use SysOp::*;
@@ -1650,6 +1650,10 @@ fn TutorialModules() -> impl IntoView {
         <p>"To enter this arrow, you can put a "<code>"~"</code>" after a binding's normal "<code>"←"</code>" or "<code>"="</code>"."</p>
         <p>"Try formatting the following example to see how this works."</p>
         <Editor example="A = +1\nB ← +2\nC =~ +3\nD ←~ +4"/>
+        <p>"Scoped modules and data definitions can be made private by putting a "<code>"~"</code>" immediately before their name."</p>
+        <Editor example="┌─╴Mod\n  ┌─╴~Internal\n    X ← 5\n  └─╴\n  Y ← ×2 Internal~X\n└─╴\nMod~Y"/>
+        <Editor example="┌─╴Mod\n  ┌─╴~Internal\n    X ← 5\n  └─╴\n└─╴\nMod~Internal~X"/> // Should fail
+        <p>"Private items can be changed or removed without breaking code that imports the module."</p>
 
         <Hd id="git-modules">"Git Modules"</Hd>
         <p>"Modules can be imported from Git repositories. Instead of a path, use a URL prefixed with "<code>"git:"</code>"."</p>
@@ -1657,9 +1661,28 @@ fn TutorialModules() -> impl IntoView {
         <Editor example="~ \"git: github.com/uiua-lang/example-module\" ~ Upscale\nUpscale 3 [1_2 3_4]"/>
         <p>"On the site, code is pulled from a "<code>"lib.ua"</code>" file at the root of the repository. Loading other files on the site is not supported."</p>
         <p>"To use Git modules in the "<A href="/docs/install">"native interpreter"</A>", you must have Git installed. The repository is cloned and the "<code>"lib.ua"</code>" file is loaded as the module's contents. Code from other files can be made available by importing them as modules in the "<code>"lib.ua"</code>" file."</p>
-        <p>"The native interpreter also supports adding an additional "<code>"branch: <branch-name>"</code>" or "<code>"commit: <commit-hash>"</code>" specifier after the URL."</p>
-        <p>"The "<code>"uiua module"</code>" command can be used to list or update Git modules."</p>
+        <p>"The native interpreter also supports adding an additional "<code>"branch: <branch-name>"</code>", "<code>"tag: <tag-name>"</code>", or "<code>"commit: <commit-hash>"</code>" specifier after the URL."</p>
+        <p>"The commit that each Git module resolves to is recorded in a "<code>"uiua.lock"</code>" file. Later runs use the recorded commit, so builds are reproducible. Commit this file along with your code."</p>
+        <p>"The "<code>"uiua module"</code>" command can be used to list or update Git modules. "<code>"uiua module update"</code>" moves modules to the latest commit of their branch and records it in "<code>"uiua.lock"</code>". Modules pinned to a tag or commit are left alone."</p>
         <p>"You can find a curated list of Uiua modules "<a href="https://github.com/uiua-lang/uiua-modules">"here"</a>"."</p>
+
+        <Hd id="packages">"Packages"</Hd>
+        <p>"The native interpreter can also fetch versioned packages from a package registry. This requires curl to be installed."</p>
+        <p>"A package is a directory with a "<code>"uiua.toml"</code>" manifest and a "<code>"lib.ua"</code>" file. "<code>"uiua new <name>"</code>" creates one."</p>
+        <code class="code-block">"\
+[package]
+name = \"my-package\"
+version = \"0.1.0\"
+registry = \"https://example.com/registry\"
+
+[dependencies]
+other-package = \"1.2\""</code>
+        <p>"A version requirement is either "<code>"*"</code>" or a version prefix. "<code>"1.2"</code>" matches any "<code>"1.2.x"</code>" version."</p>
+        <p>"Run "<code>"uiua fetch"</code>" to download the dependencies into the "<code>"uiua-packages"</code>" directory. Their resolved versions are recorded in "<code>"uiua.lock"</code>" alongside Git modules and are reused until the requirements change or you run "<code>"uiua fetch --update"</code>"."</p>
+        <p>"A fetched package is imported with the "<code>"pkg:"</code>" prefix."</p>
+        <code class="code-block">"~ \"pkg: other-package\" ~ Foo"</code>
+        <p>"Run "<code>"uiua publish"</code>" to publish the package's "<code>".ua"</code>" files to its registry. The "<code>"UIUA_REGISTRY"</code>" environment variable overrides the registry URL, and "<code>"UIUA_REGISTRY_TOKEN"</code>" is sent as a bearer token when publishing."</p>
+        <p>"A registry is any HTTP server that responds to "<code>"GET <registry>/<name>/versions"</code>" with one version per line, serves package bundles at "<code>"GET <registry>/<name>/<version>"</code>", and accepts them at "<code>"PUT <registry>/<name>/<version>"</code>". A bundle is a TOML table of file paths to file contents."</p>
     }
 }
 
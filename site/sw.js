@@ -0,0 +1,123 @@
+// Service worker that lets the site and its editor work offline
+//
+// The files to cache are listed in offline-manifest.json,
+// which is generated by offline.ua.
+// Each build is cached separately, and old builds are removed once a new one is active.
+
+const MANIFEST = "/offline-manifest.json";
+const CACHE_PREFIX = "uiua-offline-";
+const MODULE_CACHE = "uiua-modules";
+
+async function fetchManifest() {
+  const response = await fetch(MANIFEST, { cache: "no-store" });
+  if (!response.ok) {
+    throw new Error(`Unable to fetch ${MANIFEST}: ${response.status}`);
+  }
+  return response.json();
+}
+
+// Cache every file in a manifest, returning the manifest's info
+async function cacheBuild(manifest) {
+  const cache = await caches.open(CACHE_PREFIX + manifest.build);
+  await cache.addAll(["/", ...manifest.files]);
+  await cache.put(MANIFEST, new Response(JSON.stringify(manifest)));
+  return { version: manifest.version, build: manifest.build };
+}
+
+// Remove all cached builds except one
+async function removeOldBuilds(build) {
+  const keep = CACHE_PREFIX + build;
+  for (const key of await caches.keys()) {
+    if (key.startsWith(CACHE_PREFIX) && key !== keep) {
+      await caches.delete(key);
+    }
+  }
+}
+
+// Get the info of the newest cached build
+async function cachedInfo() {
+  const response = await caches.match(MANIFEST);
+  if (!response) {
+    return null;
+  }
+  const manifest = await response.json();
+  return { version: manifest.version, build: manifest.build };
+}
+
+// Cache the newest build if it differs from the cached one
+async function update() {
+  const manifest = await fetchManifest();
+  const cached = await cachedInfo();
+  if (cached && cached.build === manifest.build) {
+    return cached;
+  }
+  const info = await cacheBuild(manifest);
+  await removeOldBuilds(manifest.build);
+  return info;
+}
+
+self.addEventListener("install", (event) => {
+  event.waitUntil(fetchManifest().then(cacheBuild).then(() => self.skipWaiting()));
+});
+
+self.addEventListener("activate", (event) => {
+  event.waitUntil(
+    cachedInfo()
+      .then((info) => info && removeOldBuilds(info.build))
+      .then(() => self.clients.claim())
+  );
+});
+
+self.addEventListener("fetch", (event) => {
+  const request = event.request;
+  if (request.method !== "GET") {
+    return;
+  }
+  const url = new URL(request.url);
+  // Git modules are fetched from the network when possible,
+  // falling back to the last version that was fetched
+  if (url.hostname === "raw.githubusercontent.com" && url.pathname.endsWith(".ua")) {
+    event.respondWith(
+      fetch(request)
+        .then(async (response) => {
+          if (response.ok) {
+            const cache = await caches.open(MODULE_CACHE);
+            await cache.put(request, response.clone());
+          }
+          return response;
+        })
+        .catch(() => caches.match(request))
+    );
+    return;
+  }
+  if (url.origin !== self.location.origin || url.pathname === MANIFEST) {
+    return;
+  }
+  event.respondWith(
+    caches.match(request).then((cached) => {
+      if (cached) {
+        return cached;
+      }
+      // The site is a single page app, so pages are all served by the root
+      return fetch(request).catch(() =>
+        request.mode === "navigate" ? caches.match("/") : Response.error()
+      );
+    })
+  );
+});
+
+// Messages from offline.js
+self.addEventListener("message", (event) => {
+  const reply = (result) => event.ports[0].postMessage(result);
+  const fail = (error) => event.ports[0].postMessage({ error: String(error) });
+  switch (event.data) {
+    case "version":
+      cachedInfo().then(reply, fail);
+      break;
+    case "update":
+      update().then(reply, fail);
+      break;
+    default:
+      fail(`Unknown message ${event.data}`);
+  }
+});
@@ -0,0 +1,20 @@
+// Runs a thread spawned by code running in the editor's worker
+//
+// Threads share the worker's module and memory, which requires the site to be
+// cross-origin isolated and the worker to be built with wasm atomics.
+
+// Keep the worker binary from starting another worker in this thread
+self.uiuaThread = true;
+
+// The worker's loader imports its hashed bindings script, so the thread imports the same one
+const loader = new XMLHttpRequest();
+loader.open("GET", "/worker_loader.js", false);
+loader.send();
+importScripts(loader.responseText.match(/importScripts\(\s*['"]([^'"]+)['"]\s*\)/)[1]);
+
+self.onmessage = async (event) => {
+  const [module, memory, ptr] = event.data;
+  await wasm_bindgen({ module_or_path: module, memory });
+  wasm_bindgen.run_thread(ptr);
+  close();
+};
@@ -0,0 +1,188 @@
+//! Bitwise operations on integer arrays
+
+use std::mem::take;
+
+use ecow::{eco_vec, EcoVec};
+
+use crate::{
+    algorithm::pervade::{bin_pervade, InfalliblePervasiveFn},
+    Array, ArrayFlags, Primitive, Uiua, UiuaResult, Value,
+};
+
+/// The largest integer magnitude that can be represented exactly
+const MAX_INT: f64 = 9007199254740992.0;
+
+impl Value {
+    /// Get the bitwise AND of two integer arrays
+    pub(crate) fn bit_and(self, other: Self, env: &Uiua) -> UiuaResult<Self> {
+        self.bitwise(other, Primitive::BitAnd, |a, b| a & b, |a, b| a & b, env)
+    }
+    /// Get the bitwise OR of two integer arrays
+    pub(crate) fn bit_or(self, other: Self, env: &Uiua) -> UiuaResult<Self> {
+        self.bitwise(other, Primitive::BitOr, |a, b| a | b, |a, b| a | b, env)
+    }
+    /// Get the bitwise XOR of two integer arrays
+    pub(crate) fn bit_xor(self, other: Self, env: &Uiua) -> UiuaResult<Self> {
+        self.bitwise(other, Primitive::BitXor, |a, b| a ^ b, |a, b| a ^ b, env)
+    }
+    fn bitwise(
+        self,
+        other: Self,
+        prim: Primitive,
+        byte: fn(u8, u8) -> u8,
+        int: fn(i64, i64) -> i64,
+        env: &Uiua,
+    ) -> UiuaResult<Self> {
+        Ok(match (self, other) {
+            (Value::Byte(a), Value::Byte(b)) => {
+                bin_pervade(a, b, env, InfalliblePervasiveFn::new(byte))?.into()
+            }
+            (a, b) => {
+                let a = a.into_bit_ints(prim, env)?;
+                let b = b.into_bit_ints(prim, env)?;
+                let f = move |a: f64, b: f64| int(a as i64, b as i64) as f64;
+                bin_pervade(a, b, env, InfalliblePervasiveFn::new(f))?.into()
+            }
+        })
+    }
+    /// Shift the bits of an integer array
+    ///
+    /// Positive amounts shift left, and negative amounts shift right.
+    pub(crate) fn bit_shift(self, other: Self, env: &Uiua) -> UiuaResult<Self> {
+        let amount = self.into_bit_ints(Primitive::BitShift, env)?;
+        let array = other.into_bit_ints(Primitive::BitShift, env)?;
+        let f = |n: f64, x: f64| {
+            if n >= 0.0 {
+                x * 2f64.powf(n)
+            } else {
+                (x * 2f64.powf(n)).floor()
+            }
+        };
+        Ok(bin_pervade(amount, array, env, InfalliblePervasiveFn::new(f))?.into())
+    }
+    /// Count the set bits of each integer in an array
+    pub(crate) fn popcount(self, env: &Uiua) -> UiuaResult<Self> {
+        Ok(match self {
+            Value::Byte(mut arr) => {
+                for b in arr.data.as_mut_slice() {
+                    *b = b.count_ones() as u8;
+                }
+                arr.into()
+            }
+            val => {
+                let mut arr = val.into_bit_ints(Primitive::PopCount, env)?;
+                for n in arr.data.as_mut_slice() {
+                    let count = (n.abs() as u64).count_ones() as f64;
+                    *n = if *n < 0.0 { -count } else { count };
+                }
+                arr.into()
+            }
+        })
+    }
+    /// Wrap integers to the range of a fixed-width integer
+    pub(crate) fn wrap_int(&mut self, bits: u8, signed: bool, env: &Uiua) -> UiuaResult {
+        let prim = if signed {
+            Primitive::Signed
+        } else {
+            Primitive::Unsigned
+        };
+        if let Value::Byte(_) = self {
+            if !signed && bits >= 8 || signed && bits > 8 {
+                return Ok(());
+            }
+        }
+        let mut arr = take(self).into_bit_ints(prim, env)?;
+        let modulus = 2f64.powi(bits as i32);
+        let half = modulus / 2.0;
+        for n in arr.data.as_mut_slice() {
+            *n = n.rem_euclid(modulus);
+            if signed && *n >= half {
+                *n -= modulus;
+            }
+        }
+        *self = if !signed && bits <= 8 {
+            let data: EcoVec<u8> = arr.data.iter().map(|&n| n as u8).collect();
+            let mut arr = Array::new(arr.shape, data);
+            arr.meta_mut().flags.set(ArrayFlags::BOOLEAN, bits == 1);
+            arr.into()
+        } else {
+            arr.into()
+        };
+        Ok(())
+    }
+    /// Pack the last axis of a boolean array into bytes
+    pub(crate) fn pack_bits(&self, env: &Uiua) -> UiuaResult<Self> {
+        let bits = self.as_natural_array(env, "Argument to packbits must be an array of bits")?;
+        if let Some(&n) = bits.data.iter().find(|&&n| n > 1) {
+            return Err(env.error(format!(
+                "Argument to {} must be an array of bits, but it contains {n}",
+                Primitive::PackBits.format()
+            )));
+        }
+        let mut shape = bits.shape.clone();
+        let Some(len) = shape.pop() else {
+            return Err(env.error(format!("Cannot {} a scalar", Primitive::PackBits.format())));
+        };
+        let byte_count = len.div_ceil(8);
+        shape.push(byte_count);
+        let mut data = eco_vec![0u8; shape.elements()];
+        if len > 0 {
+            let slice = data.make_mut();
+            for (bits, bytes) in bits
+                .data
+                .chunks_exact(len)
+                .zip(slice.chunks_exact_mut(byte_count))
+            {
+                // LSB first
+                for (i, &bit) in bits.iter().enumerate() {
+                    bytes[i / 8] |= (bit as u8) << (i % 8);
+                }
+            }
+        }
+        Ok(Array::new(shape, data).into())
+    }
+    /// Unpack the last axis of a byte array into bits
+    pub(crate) fn unpack_bits(&self, env: &Uiua) -> UiuaResult<Self> {
+        let bytes =
+            self.as_natural_array(env, "Argument to un packbits must be an array of bytes")?;
+        if let Some(&n) = bytes.data.iter().find(|&&n| n > u8::MAX as usize) {
+            return Err(env.error(format!(
+                "Argument to {}{} must be an array of bytes, but it contains {n}",
+                Primitive::Un.format(),
+                Primitive::PackBits.format()
+            )));
+        }
+        let mut shape = bytes.shape.clone();
+        let len = shape.pop().unwrap_or(1);
+        shape.push(len * 8);
+        let mut data = EcoVec::with_capacity(shape.elements());
+        for &byte in &bytes.data {
+            // LSB first
+            data.extend((0..8).map(|i| (byte >> i) as u8 & 1));
+        }
+        let mut arr = Array::new(shape, data);
+        arr.meta_mut().flags.set(ArrayFlags::BOOLEAN, true);
+        Ok(arr.into())
+    }
+    /// Get the value as an array of exactly representable integers
+    fn into_bit_ints(self, prim: Primitive, env: &Uiua) -> UiuaResult<Array<f64>> {
+        let arr = match self {
+            Value::Num(arr) => arr,
+            Value::Byte(arr) => arr.convert(),
+            val => {
+                return Err(env.error(format!(
+                    "Cannot {} {}",
+                    prim.format(),
+                    val.type_name_plural()
+                )))
+            }
+        };
+        if let Some(n) = (arr.data.iter()).find(|n| n.fract() != 0.0 || n.abs() > MAX_INT) {
+            return Err(env.error(format!(
+                "{} only works on integers, but {n} is not an exact integer",
+                prim.format()
+            )));
+        }
+        Ok(arr)
+    }
+}
@@ -0,0 +1,72 @@
+//! Decimal arithmetic
+
+use crate::{SigNode, Uiua, UiuaResult, Value};
+
+/// The maximum number of decimal places
+pub(crate) const MAX_DECIMAL_PLACES: u8 = 15;
+
+/// Call a function with decimal arithmetic at some number of places
+pub fn decimal(f: SigNode, places: u8, env: &mut Uiua) -> UiuaResult {
+    env.touch_stack(f.sig.args)?;
+    for arg in env.stack_mut().iter_mut().rev().take(f.sig.args) {
+        arg.round_decimal(places);
+    }
+    let outputs = f.sig.outputs;
+    let prev = env.rt.decimal_places.replace(places);
+    let res = env.exec(f);
+    env.rt.decimal_places = prev;
+    res?;
+    // Outputs are rounded too, in case they were computed at compile time
+    for output in env.stack_mut().iter_mut().rev().take(outputs) {
+        output.round_decimal(places);
+    }
+    Ok(())
+}
+
+impl Value {
+    /// Round numbers to some number of decimal places, with ties going to even
+    pub(crate) fn round_decimal(&mut self, places: u8) {
+        match self {
+            Value::Num(arr) => {
+                let mul = 10f64.powi(places as i32);
+                for n in arr.data.as_mut_slice() {
+                    *n = round_decimal(*n, mul);
+                }
+            }
+            Value::Complex(arr) => {
+                let mul = 10f64.powi(places as i32);
+                for c in arr.data.as_mut_slice() {
+                    c.re = round_decimal(c.re, mul);
+                    c.im = round_decimal(c.im, mul);
+                }
+            }
+            Value::Box(arr) => {
+                for b in arr.data.as_mut_slice() {
+                    b.0.round_decimal(places);
+                }
+            }
+            Value::Byte(_) | Value::Char(_) => {}
+        }
+    }
+}
+
+fn round_decimal(n: f64, mul: f64) -> f64 {
+    if !n.is_finite() {
+        return n;
+    }
+    let scaled = n * mul;
+    let floor = scaled.floor();
+    let diff = scaled - floor;
+    // Values that are within floating point error of a tie are treated as ties
+    let epsilon = scaled.abs().max(1.0) * 1e-9;
+    let rounded = if (diff - 0.5).abs() <= epsilon {
+        if floor % 2.0 == 0.0 {
+            floor
+        } else {
+            floor + 1.0
+        }
+    } else {
+        scaled.round()
+    };
+    rounded / mul
+}
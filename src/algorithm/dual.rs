@@ -0,0 +1,169 @@
+//! Forward-mode automatic differentiation
+
+use ecow::EcoVec;
+
+use crate::{
+    algorithm::scalar::{Scalar, ScalarEnv},
+    Array, Assembly, Primitive, SigNode, Uiua, UiuaResult, Value,
+};
+
+/// A dual number
+///
+/// The second component carries the derivative through each operation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Dual {
+    re: f64,
+    eps: f64,
+}
+
+impl Dual {
+    fn variable(re: f64) -> Self {
+        Dual { re, eps: 1.0 }
+    }
+}
+
+impl Scalar for Dual {
+    fn constant(re: f64) -> Self {
+        Dual { re, eps: 0.0 }
+    }
+    fn add(self, other: Self) -> Self {
+        Dual {
+            re: self.re + other.re,
+            eps: self.eps + other.eps,
+        }
+    }
+    fn neg(self) -> Self {
+        Dual {
+            re: -self.re,
+            eps: -self.eps,
+        }
+    }
+    fn mul(self, other: Self) -> Self {
+        Dual {
+            re: self.re * other.re,
+            eps: self.re * other.eps + self.eps * other.re,
+        }
+    }
+    fn recip(self) -> Self {
+        Dual {
+            re: 1.0 / self.re,
+            eps: -self.eps / (self.re * self.re),
+        }
+    }
+    fn abs(self) -> Self {
+        let sign = if self.re == 0.0 {
+            0.0
+        } else {
+            self.re.signum()
+        };
+        Dual {
+            re: self.re.abs(),
+            eps: self.eps * sign,
+        }
+    }
+    fn sqrt(self) -> Self {
+        let re = self.re.sqrt();
+        Dual {
+            re,
+            eps: self.eps / (2.0 * re),
+        }
+    }
+    fn sin(self) -> Self {
+        Dual {
+            re: self.re.sin(),
+            eps: self.eps * self.re.cos(),
+        }
+    }
+    fn cos(self) -> Self {
+        Dual {
+            re: self.re.cos(),
+            eps: -self.eps * self.re.sin(),
+        }
+    }
+    fn pow(self, power: Self) -> Self {
+        let re = self.re.powf(power.re);
+        // Skip terms without a derivative to avoid multiplying infinities by 0
+        let mut eps = 0.0;
+        if self.eps != 0.0 {
+            eps += power.re * self.re.powf(power.re - 1.0) * self.eps;
+        }
+        if power.eps != 0.0 {
+            eps += re * self.re.ln() * power.eps;
+        }
+        Dual { re, eps }
+    }
+    fn ln(self) -> Self {
+        Dual {
+            re: self.re.ln(),
+            eps: self.eps / self.re,
+        }
+    }
+    fn min(self, other: Self) -> Self {
+        if other.re < self.re {
+            other
+        } else {
+            self
+        }
+    }
+    fn max(self, other: Self) -> Self {
+        if other.re > self.re {
+            other
+        } else {
+            self
+        }
+    }
+    fn step(self, f: fn(f64) -> f64) -> Self {
+        Dual::constant(f(self.re))
+    }
+}
+
+/// Check that a function can be differentiated with dual numbers
+pub(crate) fn check_differentiable(f: &SigNode, asm: &Assembly) -> Result<(), String> {
+    if f.sig.args != 1 || f.sig.outputs != 1 {
+        return Err(format!(
+            "Only functions with 1 argument and 1 output can be differentiated, \
+            but the function's signature is {}",
+            f.sig
+        ));
+    }
+    let mut denv = ScalarEnv::new(Primitive::Derivative, asm);
+    denv.stack.push(Dual::variable(0.0));
+    denv.node(&f.node)
+}
+
+/// Get the value and derivative of a function at a number
+pub(crate) fn eval_dual(f: &SigNode, x: f64, asm: &Assembly) -> Result<(f64, f64), String> {
+    let mut denv = ScalarEnv::new(Primitive::Derivative, asm);
+    denv.stack.push(Dual::variable(x));
+    denv.node(&f.node)?;
+    match denv.stack[..] {
+        [y] => Ok((y.re, y.eps)),
+        _ => Err(format!(
+            "{}'s function did not produce 1 output. \
+            This is a bug in the interpreter",
+            Primitive::Derivative.format()
+        )),
+    }
+}
+
+/// Get the derivative of a function at each number in an array
+pub fn derivative(f: SigNode, env: &mut Uiua) -> UiuaResult {
+    let arr = match env.pop(1)? {
+        Value::Num(arr) => arr,
+        Value::Byte(arr) => arr.convert(),
+        val => {
+            return Err(env.error(format!(
+                "Cannot {} at {}",
+                Primitive::Derivative.format(),
+                val.type_name_plural()
+            )))
+        }
+    };
+    let mut data = EcoVec::with_capacity(arr.element_count());
+    for &x in &arr.data {
+        let (_, dy) = eval_dual(&f, x, &env.asm).map_err(|e| env.error(e))?;
+        data.push(dy);
+    }
+    env.push(Array::new(arr.shape, data));
+    Ok(())
+}
@@ -0,0 +1,326 @@
+//! N-dimensional convolution
+
+use ecow::EcoVec;
+
+use crate::{algorithm::FillContext, Array, Primitive, Uiua, UiuaResult, Value};
+
+/// How [`Primitive::Convolve`] treats values outside the array
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Boundary {
+    /// Use the fill value, or 0 if there is none
+    Fill,
+    /// Use the nearest edge value
+    Clamp,
+    /// Wrap around to the other side
+    Wrap,
+}
+
+/// Kernels with at least this many elements are convolved with FFT
+#[cfg(feature = "fft")]
+const FFT_THRESHOLD: usize = 64;
+
+impl Value {
+    /// Convolve an array with a kernel
+    pub(crate) fn convolve(
+        &self,
+        array: &Self,
+        boundary: Boundary,
+        env: &Uiua,
+    ) -> UiuaResult<Self> {
+        let as_nums = |val: &Value| match val {
+            Value::Num(arr) => Ok(arr.clone()),
+            Value::Byte(arr) => Ok(arr.convert_ref()),
+            val => Err(env.error(format!(
+                "Cannot {} {} array",
+                Primitive::Convolve.format(),
+                val.type_name_plural()
+            ))),
+        };
+        let kernel = as_nums(self)?;
+        let array = as_nums(array)?;
+        Ok(kernel.convolve(array, boundary, env)?.into())
+    }
+}
+
+impl Array<f64> {
+    fn convolve(&self, array: Self, boundary: Boundary, env: &Uiua) -> UiuaResult<Self> {
+        let k = self.rank();
+        if k > array.rank() {
+            return Err(env.error(format!(
+                "Cannot {} a rank {} array with a rank {k} kernel",
+                Primitive::Convolve.format(),
+                array.rank()
+            )));
+        }
+        let axes = &array.shape[..k];
+        let cell_size: usize = array.shape[k..].iter().product();
+        if axes.contains(&0) || cell_size == 0 {
+            return Ok(array);
+        }
+        if self.element_count() == 0 {
+            let data: EcoVec<f64> = (0..array.element_count()).map(|_| 0.0).collect();
+            return Ok(Array::new(array.shape.clone(), data));
+        }
+        let fill = match boundary {
+            Boundary::Fill => env.scalar_fill::<f64>().unwrap_or(0.0),
+            _ => 0.0,
+        };
+        let padded = pad(&array, &self.shape, boundary, fill);
+        let padded_axes: Vec<usize> = (axes.iter().zip(&self.shape))
+            .map(|(n, k)| n + k - 1)
+            .collect();
+        #[cfg(feature = "fft")]
+        if self.element_count() >= FFT_THRESHOLD {
+            let data = convolve_fft(&padded, &padded_axes, cell_size, self, axes);
+            return Ok(Array::new(array.shape.clone(), data));
+        }
+        let data = if let Some(factors) = separate(self) {
+            // Separable kernels are convolved one axis at a time
+            let mut shape = padded_axes;
+            let mut data = padded;
+            for (axis, factor) in factors.iter().enumerate() {
+                data = convolve_axis(&data, &shape, cell_size, axis, factor);
+                shape[axis] = axes[axis];
+            }
+            data.into()
+        } else {
+            convolve_direct(&padded, &padded_axes, cell_size, self, axes)
+        };
+        Ok(Array::new(array.shape.clone(), data))
+    }
+}
+
+/// Pad the leading axes of an array so that a "valid" convolution keeps its shape
+///
+/// Each axis of length `n` is padded to `n + k - 1`, with `k / 2` values before and `(k - 1) / 2` after
+fn pad(array: &Array<f64>, kernel_shape: &[usize], boundary: Boundary, fill: f64) -> Vec<f64> {
+    let k = kernel_shape.len();
+    let cell_size: usize = array.shape[k..].iter().product();
+    // For each axis, map padded positions to source positions
+    let maps: Vec<Vec<Option<usize>>> = (array.shape.iter().zip(kernel_shape))
+        .map(|(&n, &kn)| {
+            let before = kn / 2;
+            (0..n + kn - 1)
+                .map(|p| {
+                    let i = p as isize - before as isize;
+                    match boundary {
+                        _ if (0..n as isize).contains(&i) => Some(i as usize),
+                        Boundary::Fill => None,
+                        Boundary::Clamp => Some(i.clamp(0, n as isize - 1) as usize),
+                        Boundary::Wrap => Some(i.rem_euclid(n as isize) as usize),
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    let strides = strides(&array.shape[..k], cell_size);
+    let padded_axes: Vec<usize> = maps.iter().map(Vec::len).collect();
+    let count: usize = padded_axes.iter().product();
+    let mut padded = Vec::with_capacity(count * cell_size);
+    let mut index = vec![0; k];
+    for _ in 0..count {
+        let src = (index.iter().zip(&maps).zip(&strides))
+            .try_fold(0, |acc, ((&i, map), stride)| {
+                map[i].map(|i| acc + i * stride)
+            });
+        match src {
+            Some(start) => padded.extend_from_slice(&array.data[start..][..cell_size]),
+            None => padded.extend((0..cell_size).map(|_| fill)),
+        }
+        increment(&mut index, &padded_axes);
+    }
+    padded
+}
+
+/// Get the strides of some axes, in elements
+fn strides(axes: &[usize], cell_size: usize) -> Vec<usize> {
+    let mut strides = vec![cell_size; axes.len()];
+    for i in (0..axes.len().saturating_sub(1)).rev() {
+        strides[i] = strides[i + 1] * axes[i + 1];
+    }
+    strides
+}
+
+/// Increment a multi-dimensional index
+fn increment(index: &mut [usize], shape: &[usize]) {
+    for (i, &n) in index.iter_mut().zip(shape).rev() {
+        *i += 1;
+        if *i < n {
+            return;
+        }
+        *i = 0;
+    }
+}
+
+/// Convolve by summing over every kernel element for every output cell
+fn convolve_direct(
+    padded: &[f64],
+    padded_axes: &[usize],
+    cell_size: usize,
+    kernel: &Array<f64>,
+    axes: &[usize],
+) -> EcoVec<f64> {
+    let strides = strides(padded_axes, cell_size);
+    // The offset of each kernel element into the padded array
+    let mut offsets = Vec::with_capacity(kernel.element_count());
+    let mut index = vec![0; kernel.rank()];
+    for _ in 0..kernel.element_count() {
+        let offset: usize = (index.iter().zip(&kernel.shape).zip(&strides))
+            .map(|((i, n), stride)| (n - 1 - i) * stride)
+            .sum();
+        offsets.push(offset);
+        increment(&mut index, &kernel.shape);
+    }
+    let count: usize = axes.iter().product();
+    let mut data = EcoVec::from(vec![0.0; count * cell_size]);
+    let slice = data.make_mut();
+    let mut index = vec![0; axes.len()];
+    for out in slice.chunks_exact_mut(cell_size) {
+        let base: usize = index.iter().zip(&strides).map(|(i, s)| i * s).sum();
+        for (&w, offset) in kernel.data.iter().zip(&offsets) {
+            if w == 0.0 {
+                continue;
+            }
+            let cell = &padded[base + offset..][..cell_size];
+            for (o, x) in out.iter_mut().zip(cell) {
+                *o += w * x;
+            }
+        }
+        increment(&mut index, axes);
+    }
+    data
+}
+
+/// Convolve along a single axis of a padded array
+fn convolve_axis(
+    data: &[f64],
+    shape: &[usize],
+    cell_size: usize,
+    axis: usize,
+    kernel: &[f64],
+) -> Vec<f64> {
+    let outer: usize = shape[..axis].iter().product();
+    let len = shape[axis];
+    let inner: usize = shape[axis + 1..].iter().product::<usize>() * cell_size;
+    let new_len = len + 1 - kernel.len();
+    let mut conv = vec![0.0; outer * new_len * inner];
+    for o in 0..outer {
+        let src = &data[o * len * inner..][..len * inner];
+        let dst = &mut conv[o * new_len * inner..][..new_len * inner];
+        for (i, out) in dst.chunks_exact_mut(inner).enumerate() {
+            for (j, &w) in kernel.iter().enumerate() {
+                let cell = &src[(i + kernel.len() - 1 - j) * inner..][..inner];
+                for (o, x) in out.iter_mut().zip(cell) {
+                    *o += w * x;
+                }
+            }
+        }
+    }
+    conv
+}
+
+/// Try to split a kernel into the outer product of one vector per axis
+fn separate(kernel: &Array<f64>) -> Option<Vec<Vec<f64>>> {
+    let k = kernel.rank();
+    if k < 2 || kernel.shape.iter().sum::<usize>() >= kernel.element_count() {
+        return None;
+    }
+    let (pivot_index, &pivot) =
+        (kernel.data.iter().enumerate()).max_by(|(_, a), (_, b)| a.abs().total_cmp(&b.abs()))?;
+    if pivot == 0.0 || !pivot.is_finite() {
+        return None;
+    }
+    let strides = strides(&kernel.shape, 1);
+    let pivot_pos: Vec<usize> = (strides.iter().zip(&kernel.shape))
+        .map(|(s, n)| pivot_index / s % n)
+        .collect();
+    // Each factor is the line of the kernel through the pivot along an axis
+    let factors: Vec<Vec<f64>> = (0..k)
+        .map(|axis| {
+            let start = pivot_index - pivot_pos[axis] * strides[axis];
+            (0..kernel.shape[axis])
+                .map(|i| kernel.data[start + i * strides[axis]])
+                .map(|x| if axis == 0 { x } else { x / pivot })
+                .collect()
+        })
+        .collect();
+    let tolerance = pivot.abs() * 1e-12;
+    let mut index = vec![0; k];
+    for &x in &kernel.data {
+        let product: f64 = index.iter().zip(&factors).map(|(&i, f)| f[i]).product();
+        if (product - x).abs() > tolerance {
+            return None;
+        }
+        increment(&mut index, &kernel.shape);
+    }
+    Some(factors)
+}
+
+/// Convolve by multiplying in the frequency domain
+///
+/// Because the array is already padded, circular convolution over the padded shape
+/// gives the correct result at the output positions.
+#[cfg(feature = "fft")]
+fn convolve_fft(
+    padded: &[f64],
+    padded_axes: &[usize],
+    cell_size: usize,
+    kernel: &Array<f64>,
+    axes: &[usize],
+) -> EcoVec<f64> {
+    use rustfft::{num_complex::Complex64, FftDirection, FftPlanner};
+
+    let mut planner = FftPlanner::new();
+    let mut transform = |data: &mut [Complex64], cell_size: usize, direction: FftDirection| {
+        let strides = strides(padded_axes, cell_size);
+        for (axis, (&len, &stride)) in padded_axes.iter().zip(&strides).enumerate() {
+            let fft = planner.plan_fft(len, direction);
+            let outer: usize = padded_axes[..axis].iter().product();
+            let mut line = vec![Complex64::default(); len];
+            for o in 0..outer {
+                for r in 0..stride {
+                    let start = o * len * stride + r;
+                    for (i, c) in line.iter_mut().enumerate() {
+                        *c = data[start + i * stride];
+                    }
+                    fft.process(&mut line);
+                    for (i, c) in line.iter().enumerate() {
+                        data[start + i * stride] = *c;
+                    }
+                }
+            }
+        }
+    };
+    let mut signal: Vec<Complex64> = padded.iter().map(|&x| Complex64::new(x, 0.0)).collect();
+    transform(&mut signal, cell_size, FftDirection::Forward);
+    // Place the kernel in the corner of an array with the padded shape
+    let padded_strides = strides(padded_axes, 1);
+    let mut spectrum = vec![Complex64::default(); padded_axes.iter().product()];
+    let mut index = vec![0; kernel.rank()];
+    for &w in &kernel.data {
+        let i: usize = index.iter().zip(&padded_strides).map(|(i, s)| i * s).sum();
+        spectrum[i] = Complex64::new(w, 0.0);
+        increment(&mut index, &kernel.shape);
+    }
+    transform(&mut spectrum, 1, FftDirection::Forward);
+    for (cell, w) in signal.chunks_exact_mut(cell_size).zip(&spectrum) {
+        for c in cell {
+            *c *= w;
+        }
+    }
+    transform(&mut signal, cell_size, FftDirection::Inverse);
+    // Output positions are offset by one less than the kernel size along each axis
+    let scale = 1.0 / spectrum.len() as f64;
+    let strides = strides(padded_axes, cell_size);
+    let count: usize = axes.iter().product();
+    let mut data = EcoVec::with_capacity(count * cell_size);
+    let mut index = vec![0; axes.len()];
+    for _ in 0..count {
+        let start: usize = (index.iter().zip(&kernel.shape).zip(&strides))
+            .map(|((i, n), s)| (i + n - 1) * s)
+            .sum();
+        data.extend(signal[start..][..cell_size].iter().map(|c| c.re * scale));
+        increment(&mut index, axes);
+    }
+    data
+}
@@ -1,9 +1,12 @@
 //! Algorithms for dyadic array operations
 
 mod combine;
+mod convolve;
 mod search;
 mod structure;
 
+pub(crate) use convolve::Boundary;
+
 use core::f64;
 use std::{
     borrow::Cow,
@@ -240,6 +243,8 @@ impl<T: ArrayValue> Array<T> {
         self.take_map_keys();
         match count {
             Ok(count) => {
+                let elements = count.unsigned_abs().saturating_mul(self.data.len());
+                env.respect_array_size_limit(elements, size_of::<T>())?;
                 if count < 0 {
                     self.reverse();
                 }
@@ -1156,7 +1161,15 @@ fn derive_undices(mut indices: Vec<isize>, rank: usize, env: &Uiua) -> UiuaResul
     for i in &mut indices {
         let u = (*i).unsigned_abs();
         if *i >= 0 && u >= rank || *i < 0 && u > rank {
-            return Err(env.error(format!("Cannot orient axis {i} in array of rank {rank}")));
+            let i = if *i < 0 {
+                format!("¯{u}")
+            } else {
+                u.to_string()
+            };
+            return Err(env.error(format!(
+                "Cannot orient axis {i} in array of rank {rank}{}",
+                available_axes(rank)
+            )));
         }
         *i = if *i >= 0 {
             u as isize
@@ -1167,6 +1180,18 @@ fn derive_undices(mut indices: Vec<isize>, rank: usize, env: &Uiua) -> UiuaResul
     Ok(unsafe { transmute::<Vec<isize>, Vec<usize>>(indices) })
 }
 
+/// Describe the axes that can be used to orient an array of the given rank
+fn available_axes(rank: usize) -> String {
+    match rank {
+        0 => ". Scalars have no axes".into(),
+        1 => ". The only axis is 0 or ¯1".into(),
+        _ => format!(
+            ". Available axes are 0 through {} or ¯{rank} through ¯1",
+            rank - 1
+        ),
+    }
+}
+
 impl Value {
     /// `orient` a value by this value
     pub fn orient(&self, target: &mut Self, env: &Uiua) -> UiuaResult {
@@ -1229,7 +1254,10 @@ fn derive_orient_data(undices: &mut Vec<usize>, shape: &[usize], env: &Uiua) ->
     // Validate indices
     for &i in &*undices {
         if i >= rank {
-            return Err(env.error(format!("Cannot orient axis {i} in array of rank {rank}")));
+            return Err(env.error(format!(
+                "Cannot orient axis {i} in array of rank {rank}{}",
+                available_axes(rank)
+            )));
         }
     }
     // Derive new shape
@@ -1266,15 +1294,16 @@ impl<T: ArrayValue> Array<T> {
                     .fill()),
             };
         }
-        if undices
-            .iter()
-            .zip(undices.iter().skip(1))
-            .any(|(a, b)| a == b)
+        if let Some(dup) = (undices.iter().enumerate())
+            .find(|&(i, a)| undices[..i].contains(a))
+            .map(|(_, &a)| a)
         {
             return match env.scalar_fill() {
                 Ok(fill) => self.filled_orient(undices, fill, env),
                 Err(e) => Err(env
-                    .error(format!("Orient indices must be unique{e}"))
+                    .error(format!(
+                        "Orient indices must be unique, but axis {dup} appears more than once{e}"
+                    ))
                     .fill()),
             };
         }
@@ -1591,6 +1620,13 @@ impl<T: RealArrayValue> Array<T> {
         Ok(Array::new(new_shape, new_data))
     }
     fn antibase_scalar(&self, base: f64, env: &Uiua) -> UiuaResult<Array<f64>> {
+        // Unlike base, a base of 0 is allowed, because it evaluates a polynomial at 0
+        if base.is_infinite() {
+            return Err(env.error("Base cannot be infinite"));
+        }
+        if base.is_nan() {
+            return Err(env.error("Base cannot be NaN"));
+        }
         let mut shape = self.shape.clone();
         let row_len = shape.pop().unwrap_or(1);
         let elem_count = validate_size::<f64>(shape.iter().copied(), env)?;
@@ -1607,6 +1643,17 @@ impl<T: RealArrayValue> Array<T> {
     }
     fn antibase_list(&self, bases: &[f64], env: &Uiua) -> UiuaResult<Array<f64>> {
         let fill = env.scalar_fill::<f64>().ok();
+        for base in bases.iter().copied().chain(fill) {
+            if base == 0.0 {
+                return Err(env.error("Base cannot contain 0s"));
+            }
+            if base.is_infinite() && base.is_sign_negative() {
+                return Err(env.error("Base cannot contain negative infinities"));
+            }
+            if base.is_nan() {
+                return Err(env.error("Base cannot contain NaNs"));
+            }
+        }
         let mut shape = self.shape.clone();
         let row_len = shape.pop().unwrap_or(1);
         let elem_count = validate_size::<f64>(shape.iter().copied(), env)?;
@@ -98,6 +98,146 @@ impl<T: ArrayValue> Array<T> {
     }
 }
 
+/// A set operation on the rows of two arrays
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SetOp {
+    Union,
+    Intersection,
+    Difference,
+    SymDiff,
+}
+
+impl SetOp {
+    fn name(&self) -> &'static str {
+        match self {
+            SetOp::Union => "union",
+            SetOp::Intersection => "intersection",
+            SetOp::Difference => "difference",
+            SetOp::SymDiff => "symmetric difference",
+        }
+    }
+}
+
+impl Value {
+    /// Get the `union` of the rows of this value and another
+    pub fn union(&self, other: &Self, env: &Uiua) -> UiuaResult<Self> {
+        self.set_op(other, SetOp::Union, env)
+    }
+    /// Get the `intersection` of the rows of this value and another
+    pub fn intersection(&self, other: &Self, env: &Uiua) -> UiuaResult<Self> {
+        self.set_op(other, SetOp::Intersection, env)
+    }
+    /// Get the `difference` of the rows of this value and another
+    pub fn difference(&self, other: &Self, env: &Uiua) -> UiuaResult<Self> {
+        self.set_op(other, SetOp::Difference, env)
+    }
+    /// Get the symmetric difference of the rows of this value and another
+    pub fn sym_diff(&self, other: &Self, env: &Uiua) -> UiuaResult<Self> {
+        self.set_op(other, SetOp::SymDiff, env)
+    }
+    fn set_op(&self, other: &Self, op: SetOp, env: &Uiua) -> UiuaResult<Self> {
+        self.generic_bin_ref(
+            other,
+            |a, b| a.set_op(b, op, env).map(Into::into),
+            |a, b| a.set_op(b, op, env).map(Into::into),
+            |a, b| a.set_op(b, op, env).map(Into::into),
+            |a, b| a.set_op(b, op, env).map(Into::into),
+            |a, b| a.set_op(b, op, env).map(Into::into),
+            |a, b| {
+                env.error(format!(
+                    "Cannot get the {} of {} array and {} array",
+                    op.name(),
+                    a.type_name(),
+                    b.type_name(),
+                ))
+            },
+        )
+    }
+}
+
+impl<T: ArrayValue> Array<T> {
+    fn set_op(&self, other: &Self, op: SetOp, env: &Uiua) -> UiuaResult<Self> {
+        let mut a = self.clone();
+        let mut b = other.clone();
+        // Normalize so that both arrays are lists of rows of the same shape
+        if a.shape == [0] && b.rank() > 1 {
+            a.shape = b.shape.clone();
+            a.shape[0] = 0;
+        } else if b.shape == [0] && a.rank() > 1 {
+            b.shape = a.shape.clone();
+            b.shape[0] = 0;
+        }
+        if a.rank() == 0 {
+            a.fix();
+        }
+        if b.rank() == 0 {
+            b.fix();
+        }
+        if a.rank() + 1 == b.rank() {
+            a.fix();
+        } else if b.rank() + 1 == a.rank() {
+            b.fix();
+        }
+        if a.shape.row() != b.shape.row() {
+            return Err(env.error(format!(
+                "Cannot get the {} of arrays with shapes {} and {}",
+                op.name(),
+                self.shape,
+                other.shape
+            )));
+        }
+        let has_wildcard = a.data.iter().any(T::has_wildcard) || b.data.iter().any(T::has_wildcard);
+        let contains = |arr: &Self, row: &[T]| -> bool {
+            arr.row_slices()
+                .any(|r| ArrayCmpSlice(r) == ArrayCmpSlice(row))
+        };
+        let a_set: HashSet<_> = if has_wildcard {
+            HashSet::new()
+        } else {
+            a.row_slices().map(ArrayCmpSlice).collect()
+        };
+        let b_set: HashSet<_> = if has_wildcard {
+            HashSet::new()
+        } else {
+            b.row_slices().map(ArrayCmpSlice).collect()
+        };
+        let in_a = |row: &[T]| {
+            if has_wildcard {
+                contains(&a, row)
+            } else {
+                a_set.contains(&ArrayCmpSlice(row))
+            }
+        };
+        let in_b = |row: &[T]| {
+            if has_wildcard {
+                contains(&b, row)
+            } else {
+                b_set.contains(&ArrayCmpSlice(row))
+            }
+        };
+        let rows: Vec<&[T]> = match op {
+            SetOp::Union => a.row_slices().chain(b.row_slices()).collect(),
+            SetOp::Intersection => a.row_slices().filter(|row| in_b(row)).collect(),
+            SetOp::Difference => a.row_slices().filter(|row| !in_b(row)).collect(),
+            SetOp::SymDiff => (a.row_slices().filter(|row| !in_b(row)))
+                .chain(b.row_slices().filter(|row| !in_a(row)))
+                .collect(),
+        };
+        let mut seen = HashSet::with_capacity(rows.len());
+        let mut data = EcoVec::new();
+        let mut row_count = 0;
+        for row in rows {
+            if seen.insert(ArrayCmpSlice(row)) {
+                data.extend_from_slice(row);
+                row_count += 1;
+            }
+        }
+        let mut shape = a.shape.clone();
+        shape[0] = row_count;
+        Ok(Array::new(shape, data))
+    }
+}
+
 impl Value {
     /// Get the `index of` the rows of this value in another
     pub fn index_of(&self, haystack: &Value, env: &Uiua) -> UiuaResult<Value> {
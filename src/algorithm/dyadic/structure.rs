@@ -782,15 +782,24 @@ impl<T: ArrayValue> Array<T> {
             }
             &[untaking, ref sub_index @ ..] => {
                 let abs_untaking = untaking.unsigned_abs();
-                if abs_untaking != from.row_count() {
+                let into_row_count = into.row_count();
+                let from_row_count = from.row_count();
+                if from_row_count != abs_untaking {
+                    // If the other axes were taken in full, whole rows can be spliced in.
+                    // Otherwise, there is no way to tell which original row each row
+                    // of the section belongs to.
+                    let full_rows = (sub_index.iter().zip(&into.shape[1..]))
+                        .all(|(&i, &d)| i.unsigned_abs() == d);
+                    if full_rows {
+                        return from.undo_take_impl(name, past, &[untaking], into, env);
+                    }
                     return Err(env.error(format!(
                         "Attempted to undo {name}, but the {past} section's row \
-                        count was modified from {} to {}",
-                        abs_untaking,
-                        from.row_count()
+                        count was modified from {abs_untaking} to {from_row_count}. \
+                        Rows can only be added or removed when using {name} along \
+                        a single axis."
                     )));
                 }
-                let into_row_count = into.row_count();
                 let mut new_rows = Vec::with_capacity(into_row_count);
                 if untaking >= 0 {
                     for (from, into) in from.rows().zip(into.rows()) {
@@ -1280,14 +1289,35 @@ impl<T: ArrayValue> Array<T> {
                         into.validate_shape();
                         return Ok(into);
                     }
-                    if from.row_count() < indices_row_count {
+                    // Splicing a contiguous run of rows
+                    let start = (indices.first()).map(|&i| normalize_index(i, into_row_count));
+                    let contiguous = start.is_some_and(|start| {
+                        (indices.iter())
+                            .enumerate()
+                            .all(|(i, &j)| normalize_index(j, into_row_count) == start + i)
+                    });
+                    let Some(start) = start.filter(|_| contiguous) else {
                         return Err(env.error(format!(
                             "Attempted to undo selection, but \
                             the length of the selected array changed \
-                            from {indices_row_count} to {}",
+                            from {indices_row_count} to {}. This is only \
+                            allowed if the indices are a contiguous \
+                            ascending range.",
                             from.row_count()
                         )));
-                    }
+                    };
+                    let row_len = into.row_len();
+                    let end = start + indices_row_count;
+                    let mut data = EcoVec::with_capacity(
+                        (into_row_count - indices_row_count) * row_len + from.data.len(),
+                    );
+                    data.extend_from_slice(&into.data[..start * row_len]);
+                    data.extend_from_slice(&from.data);
+                    data.extend_from_slice(&into.data[end * row_len..]);
+                    into.data = data.into();
+                    into.shape[0] = into_row_count - indices_row_count + from.row_count();
+                    into.validate_shape();
+                    return Ok(into);
                 }
                 // Replacing multiple rows with multiple rows
                 let row_len = from.row_len();
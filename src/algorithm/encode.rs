@@ -1,4 +1,4 @@
-//! En/decode Uiua arrays to/from media formats
+//! En/decode Uiua arrays to/from media and configuration formats
 
 #[cfg(feature = "audio_encode")]
 use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
@@ -8,7 +8,7 @@ use serde::*;
 
 use crate::SysBackend;
 #[allow(unused_imports)]
-use crate::{Array, Uiua, UiuaResult, Value};
+use crate::{Array, Boxed, Uiua, UiuaResult, Value};
 
 /// Conversion of a value to some media format based on the value's shape
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -946,3 +946,296 @@ fn layout_text_impl(options: Value, text: Value, env: &Uiua) -> UiuaResult<Value
         Ok(Array::new(canvas_shape, canvas_data).into())
     })
 }
+
+/// A list of keys and values that keeps the order they were first set in
+type Entries = Vec<(String, String)>;
+
+fn set_entry(entries: &mut Entries, key: &str, value: String) {
+    if let Some((_, v)) = entries.iter_mut().find(|(k, _)| k == key) {
+        *v = value;
+    } else {
+        entries.push((key.into(), value));
+    }
+}
+
+fn entries_to_map(entries: Entries, env: &Uiua) -> UiuaResult<Value> {
+    let keys: Value = (entries.iter())
+        .map(|(k, _)| Boxed(k.as_str().into()))
+        .collect();
+    let mut values: Value = (entries.into_iter())
+        .map(|(_, v)| Boxed(v.into()))
+        .collect();
+    values.map(keys, env)?;
+    Ok(values)
+}
+
+/// Get the string of a setting's value
+fn setting(value: Value, format: &str, env: &Uiua) -> UiuaResult<String> {
+    match value.unpacked() {
+        Value::Char(arr) if arr.rank() <= 1 => Ok(arr.data.iter().collect()),
+        Value::Num(arr) if arr.rank() == 0 => Ok(arr.data[0].to_string()),
+        Value::Byte(arr) if arr.rank() == 0 => Ok(arr.data[0].to_string()),
+        value => Err(env.error(format!(
+            "{format} values must be strings or numbers, but one is {} {}",
+            if value.rank() == 0 {
+                "a"
+            } else {
+                "an array of"
+            },
+            if value.rank() == 0 {
+                value.type_name()
+            } else {
+                value.type_name_plural()
+            }
+        ))),
+    }
+}
+
+fn map_entries(value: &Value, format: &str, env: &Uiua) -> UiuaResult<Vec<(String, Value)>> {
+    if !value.is_map() {
+        return Err(env.error(format!(
+            "{format} can only be encoded from a map, but the value is not a map"
+        )));
+    }
+    (value.map_kv().into_iter())
+        .map(|(k, v)| {
+            let key = (k.unpacked()).as_string(env, "Keys must be strings")?;
+            Ok((key, v))
+        })
+        .collect()
+}
+
+/// Parse INI text into its sections
+///
+/// Keys that come before the first section are in a section with an empty name.
+pub fn parse_ini(text: &str) -> Result<Vec<(String, Entries)>, String> {
+    let mut sections: Vec<(String, Entries)> = Vec::new();
+    let mut current = 0;
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with([';', '#']) {
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix('[') {
+            let name = (rest.strip_suffix(']'))
+                .ok_or_else(|| format!("Section header on line {} is missing a ]", i + 1))?
+                .trim();
+            current = match sections.iter().position(|(n, _)| n == name) {
+                Some(j) => j,
+                None => {
+                    sections.push((name.into(), Vec::new()));
+                    sections.len() - 1
+                }
+            };
+            continue;
+        }
+        let Some(sep) = line.find(['=', ':']) else {
+            return Err(format!(
+                "Line {} is not a section header or a key and value",
+                i + 1
+            ));
+        };
+        let key = line[..sep].trim_end();
+        if key.is_empty() {
+            return Err(format!("Line {} has no key", i + 1));
+        }
+        let mut value = line[sep + 1..].trim_start();
+        for quote in ['"', '\''] {
+            if value.len() >= 2 && value.starts_with(quote) && value.ends_with(quote) {
+                value = &value[1..value.len() - 1];
+            }
+        }
+        if sections.is_empty() {
+            sections.push((String::new(), Vec::new()));
+        }
+        set_entry(&mut sections[current].1, key, value.into());
+    }
+    Ok(sections)
+}
+
+pub(crate) fn ini_encode(value: &Value, env: &Uiua) -> UiuaResult<String> {
+    let mut globals = String::new();
+    let mut sections = String::new();
+    let check_key = |key: &str| {
+        if key.is_empty() || key.starts_with(['[', ';', '#']) || key.contains(['=', ':', '\n']) {
+            Err(env.error(format!("{key:?} is not a valid INI key")))
+        } else {
+            Ok(())
+        }
+    };
+    let write = |out: &mut String, key: &str, value: Value| -> UiuaResult {
+        check_key(key)?;
+        let value = setting(value, "INI", env)?;
+        if value.contains('\n') {
+            return Err(env.error(format!("The value of INI key {key} has a newline")));
+        }
+        out.push_str(&format!("{key} = {value}\n"));
+        Ok(())
+    };
+    for (name, value) in map_entries(value, "INI", env)? {
+        let value = value.unpacked();
+        if !value.is_map() {
+            write(&mut globals, &name, value)?;
+            continue;
+        }
+        let out = if name.is_empty() {
+            &mut globals
+        } else {
+            if name.contains([']', '\n']) {
+                return Err(env.error(format!("{name:?} is not a valid INI section name")));
+            }
+            if !sections.is_empty() {
+                sections.push('\n');
+            }
+            sections.push_str(&format!("[{name}]\n"));
+            &mut sections
+        };
+        for (key, value) in map_entries(&value, "INI", env)? {
+            write(out, &key, value)?;
+        }
+    }
+    if !globals.is_empty() && !sections.is_empty() {
+        globals.push('\n');
+    }
+    Ok(globals + &sections)
+}
+
+pub(crate) fn ini_decode(text: &str, env: &Uiua) -> UiuaResult<Value> {
+    let sections = parse_ini(text).map_err(|e| env.error(e))?;
+    let names: Value = (sections.iter())
+        .map(|(name, _)| Boxed(name.as_str().into()))
+        .collect();
+    let mut maps = Vec::with_capacity(sections.len());
+    for (_, entries) in sections {
+        maps.push(Boxed(entries_to_map(entries, env)?));
+    }
+    let mut maps: Value = maps.into_iter().collect();
+    maps.map(names, env)?;
+    Ok(maps)
+}
+
+/// Parse the text of a `.env` file into its variables
+///
+/// Values can be unquoted, in single quotes, or in double quotes.
+/// Only double-quoted values have escape sequences.
+/// Quoted values can span multiple lines.
+pub fn parse_dotenv(text: &str) -> Result<Entries, String> {
+    let mut vars = Vec::new();
+    let mut rest = text;
+    let mut line = 1;
+    loop {
+        rest = rest.trim_start_matches(|c: char| {
+            line += (c == '\n') as usize;
+            c.is_whitespace()
+        });
+        if rest.is_empty() {
+            break;
+        }
+        if rest.starts_with('#') {
+            rest = rest.split_once('\n').map_or("", |(_, r)| r);
+            line += 1;
+            continue;
+        }
+        let start_line = line;
+        let stmt = rest.strip_prefix("export ").unwrap_or(rest).trim_start();
+        let (key, after) = (stmt.split_once('='))
+            .filter(|(key, _)| !key.contains('\n'))
+            .ok_or_else(|| format!("Line {start_line} is not a variable assignment"))?;
+        let key = key.trim_end();
+        let valid_key = key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && (key.chars()).all(|c| c.is_ascii_alphanumeric() || "_.".contains(c));
+        if !valid_key {
+            return Err(format!(
+                "{key:?} on line {start_line} is not a valid variable name"
+            ));
+        }
+        let raw = after;
+        let after = after.trim_start_matches([' ', '\t']);
+        let (value, tail) = match after.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let mut value = String::new();
+                let mut chars = after[1..].char_indices();
+                let mut end = None;
+                while let Some((i, c)) = chars.next() {
+                    match c {
+                        '\n' => {
+                            line += 1;
+                            value.push(c);
+                        }
+                        c if c == quote => {
+                            end = Some(i + 2);
+                            break;
+                        }
+                        '\\' if quote == '"' => match chars.next() {
+                            Some((_, 'n')) => value.push('\n'),
+                            Some((_, 'r')) => value.push('\r'),
+                            Some((_, 't')) => value.push('\t'),
+                            Some((_, c @ ('"' | '\\' | '$'))) => value.push(c),
+                            Some((_, c)) => value.extend(['\\', c]),
+                            None => {}
+                        },
+                        c => value.push(c),
+                    }
+                }
+                let end = end.ok_or_else(|| {
+                    format!("The value of {key} on line {start_line} is missing a closing {quote}")
+                })?;
+                let (tail, next) = after[end..].split_once('\n').unwrap_or((&after[end..], ""));
+                let tail = tail.trim();
+                if !tail.is_empty() && !tail.starts_with('#') {
+                    return Err(format!(
+                        "Unexpected text after the value of {key} on line {line}"
+                    ));
+                }
+                (value, next)
+            }
+            _ => {
+                let (value, next) = raw.split_once('\n').unwrap_or((raw, ""));
+                // A # only starts a comment if it comes after whitespace
+                let comment =
+                    (value.match_indices('#')).find(|(i, _)| value[..*i].ends_with([' ', '\t']));
+                let value = comment.map_or(value, |(i, _)| &value[..i]);
+                (value.trim().into(), next)
+            }
+        };
+        line += 1;
+        set_entry(&mut vars, key, value);
+        rest = tail;
+    }
+    Ok(vars)
+}
+
+pub(crate) fn dotenv_encode(value: &Value, env: &Uiua) -> UiuaResult<String> {
+    let mut text = String::new();
+    for (key, value) in map_entries(value, "Dotenv", env)? {
+        let valid = key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && (key.chars()).all(|c| c.is_ascii_alphanumeric() || "_.".contains(c));
+        if !valid {
+            return Err(env.error(format!("{key:?} is not a valid variable name")));
+        }
+        let value = setting(value, "Dotenv", env)?;
+        let plain = !value.is_empty()
+            && (value.chars()).all(|c| c.is_ascii_alphanumeric() || "_-+.,/:@%".contains(c));
+        if plain {
+            text.push_str(&format!("{key}={value}\n"));
+        } else {
+            let mut quoted = String::with_capacity(value.len() + 2);
+            for c in value.chars() {
+                match c {
+                    '\n' => quoted.push_str("\\n"),
+                    '\r' => quoted.push_str("\\r"),
+                    '\t' => quoted.push_str("\\t"),
+                    '"' | '\\' | '$' => quoted.extend(['\\', c]),
+                    c => quoted.push(c),
+                }
+            }
+            text.push_str(&format!("{key}=\"{quoted}\"\n"));
+        }
+    }
+    Ok(text)
+}
+
+pub(crate) fn dotenv_decode(text: &str, env: &Uiua) -> UiuaResult<Value> {
+    let vars = parse_dotenv(text).map_err(|e| env.error(e))?;
+    entries_to_map(vars, env)
+}
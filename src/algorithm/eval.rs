@@ -0,0 +1,43 @@
+//! Evaluating code in a sandbox
+
+use std::time::Duration;
+
+use crate::{Boxed, Compiler, SafeSys, Uiua, UiuaErrorKind, UiuaResult, Value};
+
+/// The maximum time in seconds that evaluated code may run
+const EVAL_TIME_LIMIT: f64 = 1.0;
+/// The maximum size in bytes of each array created by evaluated code
+const EVAL_ARRAY_SIZE_LIMIT: usize = 64 * 1024 * 1024;
+
+/// Compile and run code in a new environment with no system access
+///
+/// The size limit on arrays makes it hard for evaluated code to use a lot of memory by accident,
+/// but it is not a hard bound. Code that builds up many arrays or grows an array a little at a time
+/// can still use more memory than the limit.
+pub fn evaluate(env: &mut Uiua) -> UiuaResult {
+    let code = (env.pop(1)?).as_string(env, "Code must be a string")?;
+    // Evaluated code may not outlive the code that evaluates it
+    let mut limit = EVAL_TIME_LIMIT;
+    if let Some(parent) = env.rt.execution_limit {
+        let elapsed = env.rt.backend.now() - env.rt.execution_start;
+        limit = limit.min(parent - elapsed).max(0.0);
+    }
+    let limit = Duration::from_secs_f64(limit);
+    let mut comp = Compiler::with_backend(SafeSys::new());
+    comp.execution_limit(limit)
+        .array_size_limit(EVAL_ARRAY_SIZE_LIMIT);
+    let mut child = Uiua::with_safe_sys()
+        .with_execution_limit(limit)
+        .with_array_size_limit(EVAL_ARRAY_SIZE_LIMIT);
+    child.rt.interrupted = env.rt.interrupted.clone();
+    let res = (comp.load_str(&code)).and_then(|comp| child.run_asm(comp.finish()));
+    let (error, outputs) = match res {
+        Ok(()) => (Value::from(""), child.take_stack()),
+        Err(e) if matches!(e.kind, UiuaErrorKind::Interrupted) => return Err(e),
+        Err(e) if matches!(e.kind, UiuaErrorKind::Throw(..)) => (e.value(), Vec::new()),
+        Err(e) => (e.to_string().trim_end().into(), Vec::new()),
+    };
+    env.push(error);
+    env.push((outputs.into_iter().rev()).map(Boxed).collect::<Value>());
+    Ok(())
+}
@@ -0,0 +1,243 @@
+//! Interval arithmetic
+
+use std::f64::consts::{FRAC_PI_2, TAU};
+
+use ecow::EcoVec;
+
+use crate::{
+    algorithm::scalar::{Scalar, ScalarEnv},
+    Array, Primitive, Shape, SigNode, Uiua, UiuaResult, Value,
+};
+
+/// The bounds of a closed interval
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Bounds {
+    lo: f64,
+    hi: f64,
+}
+
+impl Bounds {
+    fn new(a: f64, b: f64) -> Self {
+        Bounds {
+            lo: a.min(b),
+            hi: a.max(b),
+        }
+    }
+    fn from_uncertain(value: f64, uncertainty: f64) -> Self {
+        let uncertainty = uncertainty.abs();
+        Bounds {
+            lo: value - uncertainty,
+            hi: value + uncertainty,
+        }
+    }
+    fn value(self) -> f64 {
+        (self.lo + self.hi) / 2.0
+    }
+    fn uncertainty(self) -> f64 {
+        (self.hi - self.lo) / 2.0
+    }
+    fn exact_value(self) -> Option<f64> {
+        (self.lo == self.hi).then_some(self.lo)
+    }
+    fn contains(self, n: f64) -> bool {
+        self.lo <= n && n <= self.hi
+    }
+    /// Map a monotonically increasing function over the interval
+    fn monotonic(self, f: impl Fn(f64) -> f64) -> Self {
+        Bounds::new(f(self.lo), f(self.hi))
+    }
+    /// Get the bounds of a function that is monotonic in each argument
+    fn corners(self, other: Self, f: impl Fn(f64, f64) -> f64) -> Self {
+        let corners = [
+            f(self.lo, other.lo),
+            f(self.lo, other.hi),
+            f(self.hi, other.lo),
+            f(self.hi, other.hi),
+        ];
+        if corners.iter().any(|n| n.is_nan()) {
+            return Bounds::constant(f64::NAN);
+        }
+        Bounds {
+            lo: corners.iter().copied().fold(f64::INFINITY, f64::min),
+            hi: corners.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        }
+    }
+}
+
+impl Scalar for Bounds {
+    fn constant(n: f64) -> Self {
+        Bounds { lo: n, hi: n }
+    }
+    fn add(self, other: Self) -> Self {
+        Bounds {
+            lo: self.lo + other.lo,
+            hi: self.hi + other.hi,
+        }
+    }
+    fn neg(self) -> Self {
+        Bounds {
+            lo: -self.hi,
+            hi: -self.lo,
+        }
+    }
+    fn mul(self, other: Self) -> Self {
+        self.corners(other, |a, b| if a == 0.0 || b == 0.0 { 0.0 } else { a * b })
+    }
+    fn recip(self) -> Self {
+        if self.contains(0.0) {
+            Bounds::new(f64::NEG_INFINITY, f64::INFINITY)
+        } else {
+            Bounds::new(1.0 / self.hi, 1.0 / self.lo)
+        }
+    }
+    fn abs(self) -> Self {
+        if self.lo >= 0.0 {
+            self
+        } else if self.hi <= 0.0 {
+            self.neg()
+        } else {
+            Bounds::new(0.0, self.hi.max(-self.lo))
+        }
+    }
+    fn sqrt(self) -> Self {
+        Bounds::new(self.lo.max(0.0), self.hi).monotonic(f64::sqrt)
+    }
+    fn sin(self) -> Self {
+        if self.hi - self.lo >= TAU {
+            return Bounds::new(-1.0, 1.0);
+        }
+        let mut res = self.monotonic(f64::sin);
+        res = Bounds::new(res.lo.min(self.hi.sin()), res.hi.max(self.lo.sin()));
+        // Check for peaks and troughs
+        let contains_phase = |phase: f64| {
+            let k = ((self.lo - phase) / TAU).ceil();
+            self.contains(phase + k * TAU)
+        };
+        if contains_phase(FRAC_PI_2) {
+            res.hi = 1.0;
+        }
+        if contains_phase(-FRAC_PI_2) {
+            res.lo = -1.0;
+        }
+        res
+    }
+    fn cos(self) -> Self {
+        self.add(Bounds::constant(FRAC_PI_2)).sin()
+    }
+    fn pow(self, power: Self) -> Self {
+        if let Some(n) = power.exact_value().filter(|n| n.fract() == 0.0) {
+            let powi = |x: f64| x.powf(n.abs());
+            let res = if n.abs() % 2.0 == 0.0 {
+                self.abs().monotonic(powi)
+            } else {
+                self.monotonic(powi)
+            };
+            return if n < 0.0 { res.recip() } else { res };
+        }
+        if self.lo < 0.0 {
+            return Bounds::constant(f64::NAN);
+        }
+        self.corners(power, f64::powf)
+    }
+    fn ln(self) -> Self {
+        if self.lo < 0.0 {
+            return Bounds::constant(f64::NAN);
+        }
+        self.monotonic(f64::ln)
+    }
+    fn min(self, other: Self) -> Self {
+        self.corners(other, f64::min)
+    }
+    fn max(self, other: Self) -> Self {
+        self.corners(other, f64::max)
+    }
+    fn step(self, f: fn(f64) -> f64) -> Self {
+        self.monotonic(f)
+    }
+    fn square(self) -> Self {
+        // Multiplying an interval by itself would give wider bounds than necessary
+        self.pow(Bounds::constant(2.0))
+    }
+}
+
+/// Call a function with interval arithmetic
+pub fn interval(f: SigNode, env: &mut Uiua) -> UiuaResult {
+    let sig = f.sig;
+    if sig.outputs == 0 {
+        return Err(env.error(format!(
+            "{}'s function must have at least 1 output, \
+            but its signature is {sig}",
+            Primitive::Interval.format()
+        )));
+    }
+    let mut args = Vec::with_capacity(sig.args);
+    let mut shape: Option<Shape> = None;
+    for i in 0..sig.args {
+        let arr = match env.pop(i + 1)? {
+            Value::Num(arr) => arr,
+            Value::Byte(arr) => arr.convert(),
+            val => {
+                return Err(env.error(format!(
+                    "Cannot use {} with {}",
+                    Primitive::Interval.format(),
+                    val.type_name_plural()
+                )))
+            }
+        };
+        if arr.rank() > 0 {
+            if arr.shape.last() != Some(&2) {
+                return Err(env.error(format!(
+                    "{} arguments must be scalars or have a last axis of 2, \
+                    but argument {} has shape {}",
+                    Primitive::Interval.format(),
+                    i + 1,
+                    arr.shape
+                )));
+            }
+            let row_shape = Shape::from(&arr.shape[..arr.rank() - 1]);
+            match &shape {
+                Some(shape) if *shape != row_shape => {
+                    return Err(env.error(format!(
+                        "{} arguments must have matching shapes, but shapes {} and {} do not match",
+                        Primitive::Interval.format(),
+                        shape,
+                        arr.shape
+                    )))
+                }
+                _ => shape = Some(row_shape),
+            }
+        }
+        args.push(arr);
+    }
+    let shape = shape.unwrap_or_default();
+    let count = shape.elements();
+    let mut outputs = vec![EcoVec::with_capacity(count * 2); sig.outputs];
+    for i in 0..count {
+        let mut ienv = ScalarEnv::new(Primitive::Interval, &env.asm);
+        for arg in args.iter().rev() {
+            ienv.stack.push(if arg.rank() == 0 {
+                Bounds::constant(arg.data[0])
+            } else {
+                Bounds::from_uncertain(arg.data[i * 2], arg.data[i * 2 + 1])
+            });
+        }
+        ienv.node(&f.node).map_err(|e| env.error(e))?;
+        if ienv.stack.len() != sig.outputs {
+            return Err(env.error(format!(
+                "{}'s function did not produce the expected number of outputs. \
+                This is a bug in the interpreter",
+                Primitive::Interval.format(),
+            )));
+        }
+        for (output, int) in outputs.iter_mut().zip(ienv.stack.into_iter().rev()) {
+            output.push(int.value());
+            output.push(int.uncertainty());
+        }
+    }
+    let mut shape = shape;
+    shape.push(2);
+    for data in outputs.into_iter().rev() {
+        env.push(Array::new(shape.clone(), data));
+    }
+    Ok(())
+}
@@ -825,6 +825,99 @@ pub fn group(f: SigNode, env: &mut Uiua) -> UiuaResult {
     )
 }
 
+pub fn aggregate(ops: Ops, env: &mut Uiua) -> UiuaResult {
+    crate::profile_function!();
+    let [key, agg] = get_ops(ops, env)?;
+    for (f, name) in [(&key, "key"), (&agg, "aggregating")] {
+        if f.sig.args != 1 || f.sig.outputs == 0 {
+            return Err(env.error(format!(
+                "{}'s {name} function must take 1 argument and return at least 1 value, \
+                but its signature is {}",
+                Primitive::Aggregate.format(),
+                f.sig
+            )));
+        }
+    }
+    if key.sig.outputs != 1 {
+        return Err(env.error(format!(
+            "{}'s key function must return 1 value, but its signature is {}",
+            Primitive::Aggregate.format(),
+            key.sig
+        )));
+    }
+    let xs = env.pop(1)?;
+    if xs.rank() == 0 {
+        return Err(env.error(format!("Cannot {} a scalar", Primitive::Aggregate.format())));
+    }
+    env.push(xs.clone());
+    env.exec(key)?;
+    let keys = env.pop("key function result")?;
+    if keys.row_count() != xs.row_count() || keys.rank() == 0 {
+        return Err(env.error(format!(
+            "{}'s key function must return one key per row, \
+            but it returned an array of shape {} for an array of shape {}",
+            Primitive::Aggregate.format(),
+            keys.shape(),
+            xs.shape()
+        )));
+    }
+    let classes = val_as_arr!(&keys, Array::classify);
+    let mut unique = keys;
+    unique.deduplicate(env)?;
+    if let Some(val) = fused_aggregate(&agg, &xs, &classes, unique.row_count()) {
+        env.push(unique);
+        env.push(val);
+        return Ok(());
+    }
+    // Group by the classified keys
+    env.push(unique);
+    env.push(xs);
+    env.push(Array::from_iter(classes.into_iter().map(|i| i as f64)));
+    group(agg, env)
+}
+
+/// Compute per-group reductions in one pass without materializing the groups
+///
+/// Returns `None` if the groups should be materialized instead
+fn fused_aggregate(f: &SigNode, xs: &Value, classes: &[usize], count: usize) -> Option<Value> {
+    let Node::Mod(Primitive::Reduce, args, _) = &f.node else {
+        return None;
+    };
+    let [g] = args.as_slice() else {
+        return None;
+    };
+    let (prim, _) = g.node.as_flipped_primitive()?;
+    let Value::Num(arr) = xs else {
+        return None;
+    };
+    use Primitive::*;
+    let combine: fn(f64, f64) -> f64 = match prim {
+        Add => |a, b| a + b,
+        Mul => |a, b| a * b,
+        Max => f64::max,
+        Min => f64::min,
+        _ => return None,
+    };
+    let row_len = arr.row_len();
+    let mut data = eco_vec![0.0; count * row_len];
+    let slice = data.make_mut();
+    let mut seen = vec![false; count];
+    for (&class, row) in classes.iter().zip(arr.row_slices()) {
+        let acc = &mut slice[class * row_len..][..row_len];
+        if seen[class] {
+            for (a, &x) in acc.iter_mut().zip(row) {
+                *a = combine(*a, x);
+            }
+        } else {
+            acc.copy_from_slice(row);
+            seen[class] = true;
+        }
+    }
+    let mut shape = arr.shape.clone();
+    shape[0] = count;
+    Some(Array::new(shape, data).into())
+}
+
 impl Value {
     fn group_groups(self, indices: &Array<isize>) -> Vec<Self> {
         val_as_arr!(self, |arr| arr
@@ -20,19 +20,30 @@ use crate::{
     UiuaError, UiuaErrorKind, UiuaResult, Value,
 };
 
+mod bitwise;
+pub mod decimal;
+pub mod dual;
 mod dyadic;
 pub mod encode;
+pub mod eval;
+pub mod interval;
 pub mod loops;
 pub mod map;
 mod monadic;
+pub mod ode;
 pub mod path;
 pub mod permute;
 pub mod pervade;
+pub mod property;
 pub mod reduce;
+mod scalar;
+pub mod solve;
 pub mod stencil;
 pub mod table;
 pub mod zip;
 
+pub(crate) use dyadic::Boundary;
+
 pub(crate) fn get_ops<const N: usize>(
     ops: EcoVec<SigNode>,
     env: &Uiua,
@@ -117,7 +128,9 @@ impl fmt::Display for FillShapeError {
 }
 
 pub fn validate_size<T>(sizes: impl IntoIterator<Item = usize>, env: &Uiua) -> UiuaResult<usize> {
-    validate_size_of::<T>(sizes).map_err(|e| env.error(e))
+    let elements = validate_size_of::<T>(sizes).map_err(|e| env.error(e))?;
+    env.respect_array_size_limit(elements, size_of::<T>())?;
+    Ok(elements)
 }
 
 pub fn validate_size_of<T>(sizes: impl IntoIterator<Item = usize>) -> Result<usize, SizeError> {
@@ -602,6 +615,10 @@ pub fn try_(ops: Ops, env: &mut Uiua) -> UiuaResult {
             err.is_case = false;
             return Err(err);
         }
+        if handler_sig.args > f_sig.args + 1 {
+            let thrown = matches!(err.kind, UiuaErrorKind::Throw(..));
+            env.push(thrown);
+        }
         if handler_sig.args > f_sig.args {
             (env.rt.backend).save_error_color(err.to_string(), err.report().to_string());
             env.push(err.value());
@@ -0,0 +1,210 @@
+//! Ordinary differential equation integration
+
+use ecow::EcoVec;
+
+use crate::{Array, Primitive, Shape, SigNode, Uiua, UiuaResult, Value};
+
+/// The relative error tolerance of each step
+const RELATIVE_TOLERANCE: f64 = 1e-9;
+/// The absolute error tolerance of each step
+const ABSOLUTE_TOLERANCE: f64 = 1e-12;
+/// The maximum number of function calls before giving up
+const MAX_CALLS: usize = 1_000_000;
+
+/// Dormand-Prince nodes
+const C: [f64; 6] = [1.0 / 5.0, 3.0 / 10.0, 4.0 / 5.0, 8.0 / 9.0, 1.0, 1.0];
+/// Dormand-Prince coefficients
+const A: [&[f64]; 6] = [
+    &[1.0 / 5.0],
+    &[3.0 / 40.0, 9.0 / 40.0],
+    &[44.0 / 45.0, -56.0 / 15.0, 32.0 / 9.0],
+    &[
+        19372.0 / 6561.0,
+        -25360.0 / 2187.0,
+        64448.0 / 6561.0,
+        -212.0 / 729.0,
+    ],
+    &[
+        9017.0 / 3168.0,
+        -355.0 / 33.0,
+        46732.0 / 5247.0,
+        49.0 / 176.0,
+        -5103.0 / 18656.0,
+    ],
+    &[
+        35.0 / 384.0,
+        0.0,
+        500.0 / 1113.0,
+        125.0 / 192.0,
+        -2187.0 / 6784.0,
+        11.0 / 84.0,
+    ],
+];
+/// The difference between the 5th and 4th order weights
+const E: [f64; 7] = [
+    35.0 / 384.0 - 5179.0 / 57600.0,
+    0.0,
+    500.0 / 1113.0 - 7571.0 / 16695.0,
+    125.0 / 192.0 - 393.0 / 640.0,
+    -2187.0 / 6784.0 + 92097.0 / 339200.0,
+    11.0 / 84.0 - 187.0 / 2100.0,
+    -1.0 / 40.0,
+];
+
+/// Integrate an ordinary differential equation
+pub fn ode(f: SigNode, env: &mut Uiua) -> UiuaResult {
+    if !(1..=2).contains(&f.sig.args) || f.sig.outputs != 1 {
+        return Err(env.error(format!(
+            "{}'s function must have 1 or 2 arguments and 1 output, \
+            but its signature is {}",
+            Primitive::Ode.format(),
+            f.sig
+        )));
+    }
+    let init = match env.pop("initial state")? {
+        Value::Num(arr) => arr,
+        Value::Byte(arr) => arr.convert(),
+        val => {
+            return Err(env.error(format!(
+                "{}'s initial state must be numbers, but it is {}",
+                Primitive::Ode.format(),
+                val.type_name_plural()
+            )))
+        }
+    };
+    let times = env.pop("times")?;
+    let times = times.as_nums(env, "Times must be a list of numbers")?;
+    let Some(&t0) = times.first() else {
+        return Err(env.error(format!(
+            "{}'s times must not be empty",
+            Primitive::Ode.format()
+        )));
+    };
+    let mut solver = Solver {
+        f: &f,
+        shape: init.shape.clone(),
+        calls: 0,
+    };
+    let mut y: Vec<f64> = init.data.iter().copied().collect();
+    let mut data = EcoVec::with_capacity(times.len() * y.len());
+    data.extend_from_slice(&y);
+    let mut dy = solver.call(t0, &y, env)?;
+    let mut h: f64 = 0.0;
+    for w in times.windows(2) {
+        let (mut t, end) = (w[0], w[1]);
+        if end == t {
+            data.extend_from_slice(&y);
+            continue;
+        }
+        if h == 0.0 || (end - t).signum() != h.signum() {
+            h = (end - t) / 100.0;
+        }
+        while t != end {
+            // Don't step past the end of the interval
+            let remaining = end - t;
+            let last = h.abs() >= remaining.abs();
+            let step = if last { remaining } else { h };
+            let (y_new, dy_new, err) = solver.step(t, step, &y, &dy, env)?;
+            let factor = if err == 0.0 {
+                5.0
+            } else {
+                (0.9 * err.powf(-0.2)).clamp(0.2, 5.0)
+            };
+            if err <= 1.0 {
+                t = if last { end } else { t + step };
+                y = y_new;
+                dy = dy_new;
+                if !last {
+                    h = step * factor;
+                }
+            } else {
+                h = step * factor;
+                if t + h == t || !h.is_finite() {
+                    return Err(env.error(format!(
+                        "{} could not take a small enough step at time {t}",
+                        Primitive::Ode.format()
+                    )));
+                }
+            }
+        }
+        data.extend_from_slice(&y);
+    }
+    let mut shape = init.shape;
+    shape.insert(0, times.len());
+    env.push(Array::new(shape, data));
+    Ok(())
+}
+
+struct Solver<'a> {
+    f: &'a SigNode,
+    shape: Shape,
+    calls: usize,
+}
+
+impl Solver<'_> {
+    /// Get the derivative at a time and state
+    fn call(&mut self, t: f64, y: &[f64], env: &mut Uiua) -> UiuaResult<Vec<f64>> {
+        self.calls += 1;
+        if self.calls > MAX_CALLS {
+            return Err(env.error(format!(
+                "{} took too many steps at time {t}",
+                Primitive::Ode.format()
+            )));
+        }
+        if self.f.sig.args == 2 {
+            env.push(t);
+        }
+        env.push(Array::new(self.shape.clone(), EcoVec::from(y)));
+        env.exec(self.f.clone())?;
+        let dy = env.pop("derivative")?;
+        let dy = match dy {
+            Value::Num(arr) if arr.shape == self.shape => arr.data.iter().copied().collect(),
+            Value::Byte(arr) if arr.shape == self.shape => {
+                arr.data.iter().map(|&n| n as f64).collect()
+            }
+            dy => {
+                return Err(env.error(format!(
+                    "{}'s function must return numbers with the same shape as the state, \
+                    but the state has shape {} and the function returned {} with shape {}",
+                    Primitive::Ode.format(),
+                    self.shape,
+                    dy.type_name_plural(),
+                    dy.shape()
+                )))
+            }
+        };
+        Ok(dy)
+    }
+    /// Take a Dormand-Prince step
+    ///
+    /// Returns the new state, its derivative, and the scaled error
+    fn step(
+        &mut self,
+        t: f64,
+        h: f64,
+        y: &[f64],
+        dy: &[f64],
+        env: &mut Uiua,
+    ) -> UiuaResult<(Vec<f64>, Vec<f64>, f64)> {
+        let mut k = Vec::with_capacity(7);
+        k.push(dy.to_vec());
+        let mut y_next = Vec::new();
+        for (c, a) in C.iter().zip(A) {
+            y_next = (y.iter().enumerate())
+                .map(|(i, y)| y + h * a.iter().zip(&k).map(|(a, k)| a * k[i]).sum::<f64>())
+                .collect();
+            k.push(self.call(t + c * h, &y_next, env)?);
+        }
+        // The last stage is evaluated at the new state
+        let dy_next = k[6].clone();
+        let mut sum = 0.0;
+        for (i, (y, y_next)) in y.iter().zip(&y_next).enumerate() {
+            let e = h * E.iter().zip(&k).map(|(e, k)| e * k[i]).sum::<f64>();
+            let scale = ABSOLUTE_TOLERANCE + RELATIVE_TOLERANCE * y.abs().max(y_next.abs());
+            sum += (e / scale).powi(2);
+        }
+        let err = (sum / y.len().max(1) as f64).sqrt();
+        let err = if err.is_nan() { f64::INFINITY } else { err };
+        Ok((y_next, dy_next, err))
+    }
+}
@@ -0,0 +1,258 @@
+//! Property testing
+
+use rand::prelude::*;
+
+use crate::{
+    cowslice::CowSlice, primitive::RNG, Array, Boxed, Ops, Primitive, Shape, SigNode, Uiua,
+    UiuaError, UiuaErrorKind, UiuaResult, Value,
+};
+
+use super::get_ops;
+
+/// The number of random cases to try
+const TEST_COUNT: usize = 100;
+/// The maximum number of times to call the predicate while shrinking
+const MAX_SHRINK_CALLS: usize = 1000;
+/// Generated numbers are in the range `[-MAX_MAGNITUDE, MAX_MAGNITUDE]`
+const MAX_MAGNITUDE: f64 = 100.0;
+
+pub fn property(ops: Ops, test: bool, env: &mut Uiua) -> UiuaResult {
+    let [f] = get_ops(ops, env)?;
+    let spec = env.pop("property spec")?;
+    let gens = generators(&spec, f.sig.args, env)?;
+    let mut rng = SmallRng::seed_from_u64(RNG.with_borrow_mut(|rng| rng.gen()));
+    for i in 0..TEST_COUNT {
+        let args: Vec<Value> = gens.iter().map(|gen| gen.generate(&mut rng)).collect();
+        let Err(err) = check(&f, &args, env) else {
+            continue;
+        };
+        if is_fatal(&err) {
+            return Err(err);
+        }
+        let (args, err) = shrink(&f, args, err, env)?;
+        let args: Vec<String> = args.iter().map(Value::representation).collect();
+        let message = format!(
+            "{} failed after {} {} with counterexample {}",
+            Primitive::Property.format(),
+            i + 1,
+            if i == 0 { "test" } else { "tests" },
+            args.join(" ")
+        );
+        let err = err.with_info([(message, Some(env.span().clone()))]);
+        if test {
+            env.rt.test_results.push(Err(err));
+            return Ok(());
+        }
+        return Err(err);
+    }
+    if test {
+        env.rt.test_results.push(Ok(()));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ElemKind {
+    Bool,
+    Nat,
+    Int,
+    Num,
+    Char,
+}
+
+/// Generates random arguments for the predicate
+struct Generator {
+    kind: ElemKind,
+    max_shape: Shape,
+}
+
+fn generators(spec: &Value, args: usize, env: &Uiua) -> UiuaResult<Vec<Generator>> {
+    match args {
+        0 => Err(env.error(format!(
+            "{}'s function must take at least 1 argument",
+            Primitive::Property.format()
+        ))),
+        1 => Ok(vec![generator(spec, env)?]),
+        _ => match spec {
+            Value::Box(arr) if arr.rank() == 1 && arr.row_count() == args => (arr.data.iter())
+                .map(|Boxed(spec)| generator(spec, env))
+                .collect(),
+            spec => Err(env.error(format!(
+                "{}'s function takes {args} arguments, so its spec must be \
+                a list of {args} boxed specs, but it is {}",
+                Primitive::Property.format(),
+                spec.shape_string()
+            ))),
+        },
+    }
+}
+
+fn generator(spec: &Value, env: &Uiua) -> UiuaResult<Generator> {
+    let (name, max_shape) = match spec {
+        Value::Char(_) => (spec.as_string(env, "")?, Shape::default()),
+        Value::Box(arr) if arr.rank() == 1 && arr.row_count() == 2 => {
+            let name = arr.data[0].0.as_string(env, "Spec type must be a string")?;
+            let shape = (arr.data[1].0).as_nats(env, "Spec shape must be natural numbers")?;
+            (name, Shape::from(shape))
+        }
+        _ => {
+            return Err(env.error(
+                "Property spec must be a type name, or a type name \
+                and a maximum shape in a box list",
+            ))
+        }
+    };
+    let kind = match name.as_str() {
+        "bool" => ElemKind::Bool,
+        "nat" => ElemKind::Nat,
+        "int" => ElemKind::Int,
+        "num" => ElemKind::Num,
+        "char" => ElemKind::Char,
+        _ => {
+            return Err(env.error(format!(
+                "Unknown property type {name:?}. \
+                The types are bool, nat, int, num, and char"
+            )))
+        }
+    };
+    Ok(Generator { kind, max_shape })
+}
+
+impl Generator {
+    fn generate(&self, rng: &mut SmallRng) -> Value {
+        let shape: Shape = self
+            .max_shape
+            .iter()
+            .map(|&d| rng.gen_range(0..=d))
+            .collect();
+        let len = shape.elements();
+        match self.kind {
+            ElemKind::Bool => {
+                let data = (0..len).map(|_| rng.gen_bool(0.5) as u8);
+                Array::new(shape, data.collect::<CowSlice<_>>()).into()
+            }
+            ElemKind::Char => {
+                let data = (0..len).map(|_| rng.gen_range(' '..='~'));
+                Array::new(shape, data.collect::<CowSlice<_>>()).into()
+            }
+            kind => {
+                let data = (0..len).map(|_| match kind {
+                    ElemKind::Nat => rng.gen_range(0.0..=MAX_MAGNITUDE).floor(),
+                    ElemKind::Int => rng.gen_range(-MAX_MAGNITUDE..=MAX_MAGNITUDE).round(),
+                    _ => rng.gen_range(-MAX_MAGNITUDE..=MAX_MAGNITUDE),
+                });
+                Array::new(shape, data.collect::<CowSlice<_>>()).into()
+            }
+        }
+    }
+}
+
+/// Call the predicate with some arguments
+fn check(f: &SigNode, args: &[Value], env: &mut Uiua) -> UiuaResult {
+    let height = env.stack_height();
+    for arg in args.iter().rev() {
+        env.push(arg.clone());
+    }
+    let res = env.exec(f.clone());
+    env.truncate_stack(height);
+    res
+}
+
+/// Whether an error should stop property testing rather than be a failed case
+fn is_fatal(err: &UiuaError) -> bool {
+    matches!(
+        err.kind,
+        UiuaErrorKind::Timeout(..) | UiuaErrorKind::Interrupted
+    )
+}
+
+/// Find smaller arguments that still fail
+fn shrink(
+    f: &SigNode,
+    mut args: Vec<Value>,
+    mut err: UiuaError,
+    env: &mut Uiua,
+) -> UiuaResult<(Vec<Value>, UiuaError)> {
+    let mut calls = 0;
+    'shrink: while calls < MAX_SHRINK_CALLS {
+        for i in 0..args.len() {
+            for smaller in smaller_values(&args[i]) {
+                if calls == MAX_SHRINK_CALLS {
+                    break 'shrink;
+                }
+                calls += 1;
+                let mut new_args = args.clone();
+                new_args[i] = smaller;
+                if let Err(e) = check(f, &new_args, env) {
+                    if is_fatal(&e) {
+                        return Err(e);
+                    }
+                    args = new_args;
+                    err = e;
+                    continue 'shrink;
+                }
+            }
+        }
+        break;
+    }
+    Ok((args, err))
+}
+
+/// Get values that are simpler than the given one, simplest first
+fn smaller_values(val: &Value) -> Vec<Value> {
+    let mut values = Vec::new();
+    // Remove rows
+    if val.rank() > 0 && val.row_count() > 0 {
+        let rows: Vec<Value> = val.rows().collect();
+        values.push(val.first_dim_zero());
+        let half = rows.len() / 2;
+        if half > 0 {
+            values.push(Value::from_row_values_infallible(rows[..half].to_vec()));
+            values.push(Value::from_row_values_infallible(rows[half..].to_vec()));
+        }
+        if rows.len() > 1 {
+            for i in 0..rows.len() {
+                let mut rows = rows.clone();
+                rows.remove(i);
+                values.push(Value::from_row_values_infallible(rows));
+            }
+        }
+    }
+    // Simplify elements
+    match val {
+        Value::Num(arr) => {
+            for (i, &n) in arr.data.iter().enumerate() {
+                let mut candidates =
+                    vec![0.0, (n / 2.0).trunc(), n.trunc(), n.trunc() - n.signum()];
+                candidates.dedup();
+                for m in candidates {
+                    if m != n && (m.abs() < n.abs() || m == n.trunc()) {
+                        let mut arr = arr.clone();
+                        arr.data.as_mut_slice()[i] = m;
+                        values.push(arr.into());
+                    }
+                }
+            }
+        }
+        Value::Byte(arr) => {
+            for (i, &n) in arr.data.iter().enumerate() {
+                if n > 0 {
+                    let mut arr = arr.clone();
+                    arr.data.as_mut_slice()[i] = 0;
+                    values.push(arr.into());
+                }
+            }
+        }
+        Value::Char(arr) => {
+            for (i, &c) in arr.data.iter().enumerate() {
+                if c != 'a' {
+                    let mut arr = arr.clone();
+                    arr.data.as_mut_slice()[i] = 'a';
+                    values.push(arr.into());
+                }
+            }
+        }
+        Value::Complex(_) | Value::Box(_) => {}
+    }
+    values
+}
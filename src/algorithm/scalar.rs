@@ -0,0 +1,191 @@
+//! Running functions on scalars of other number types
+
+use std::f64::consts::{FRAC_PI_2, PI, TAU};
+
+use crate::{Assembly, ImplPrimitive, Node, Primitive, Value};
+
+/// A number type that scalar functions can be run on
+pub(crate) trait Scalar: Copy {
+    fn constant(n: f64) -> Self;
+    fn add(self, other: Self) -> Self;
+    fn neg(self) -> Self;
+    fn mul(self, other: Self) -> Self;
+    fn recip(self) -> Self;
+    fn abs(self) -> Self;
+    fn sqrt(self) -> Self;
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn pow(self, power: Self) -> Self;
+    fn ln(self) -> Self;
+    fn min(self, other: Self) -> Self;
+    fn max(self, other: Self) -> Self;
+    /// Apply a piecewise constant function
+    fn step(self, f: fn(f64) -> f64) -> Self;
+    fn square(self) -> Self {
+        self.mul(self)
+    }
+}
+
+/// An environment for running a function on scalars
+pub(crate) struct ScalarEnv<'a, T> {
+    prim: Primitive,
+    asm: &'a Assembly,
+    pub stack: Vec<T>,
+}
+
+impl<'a, T: Scalar> ScalarEnv<'a, T> {
+    pub fn new(prim: Primitive, asm: &'a Assembly) -> Self {
+        ScalarEnv {
+            prim,
+            asm,
+            stack: Vec::new(),
+        }
+    }
+    fn pop(&mut self) -> Result<T, String> {
+        (self.stack.pop()).ok_or_else(|| {
+            format!(
+                "{}'s stack was empty. This is a bug in the interpreter",
+                self.prim.format()
+            )
+        })
+    }
+    fn pop_n(&mut self, n: usize) -> Result<Vec<T>, String> {
+        (0..n).map(|_| self.pop()).collect()
+    }
+    fn monadic(&mut self, f: impl Fn(T) -> T) -> Result<(), String> {
+        let a = self.pop()?;
+        self.stack.push(f(a));
+        Ok(())
+    }
+    fn dyadic(&mut self, f: impl Fn(T, T) -> T) -> Result<(), String> {
+        let a = self.pop()?;
+        let b = self.pop()?;
+        self.stack.push(f(a, b));
+        Ok(())
+    }
+    pub fn node(&mut self, node: &Node) -> Result<(), String> {
+        use Primitive::*;
+        let not_supported =
+            |name: String| format!("{} does not support {name}", self.prim.format());
+        match node {
+            Node::Run(nodes) => {
+                let mut nodes = nodes.iter();
+                while let Some(node) = nodes.next() {
+                    // Squaring is optimized to multiplying by itself,
+                    // which some number types handle differently
+                    if let Node::Prim(Dup, _) = node {
+                        if let Some(Node::Prim(Mul, _)) = nodes.as_slice().first() {
+                            nodes.next();
+                            self.monadic(T::square)?;
+                            continue;
+                        }
+                    }
+                    self.node(node)?;
+                }
+            }
+            Node::Call(f, _) => self.node(&self.asm[f])?,
+            Node::Push(val) => {
+                let n = match val {
+                    Value::Num(arr) if arr.rank() == 0 => arr.data[0],
+                    Value::Byte(arr) if arr.rank() == 0 => arr.data[0] as f64,
+                    _ => return Err(not_supported("non-scalar constants".into())),
+                };
+                self.stack.push(T::constant(n));
+            }
+            Node::Prim(prim, _) => match prim {
+                Identity => {}
+                Pop => _ = self.pop()?,
+                Dup => {
+                    let a = self.pop()?;
+                    self.stack.extend([a, a]);
+                }
+                Flip => {
+                    let a = self.pop()?;
+                    let b = self.pop()?;
+                    self.stack.extend([a, b]);
+                }
+                Over => {
+                    let a = self.pop()?;
+                    let b = self.pop()?;
+                    self.stack.extend([b, a, b]);
+                }
+                Neg => self.monadic(T::neg)?,
+                Not => self.monadic(|a| T::constant(1.0).add(a.neg()))?,
+                Abs => self.monadic(T::abs)?,
+                Sign => self.monadic(|a| a.step(|n| if n == 0.0 { 0.0 } else { n.signum() }))?,
+                Sqrt => self.monadic(T::sqrt)?,
+                Sin => self.monadic(T::sin)?,
+                Floor => self.monadic(|a| a.step(f64::floor))?,
+                Ceil => self.monadic(|a| a.step(f64::ceil))?,
+                Round => self.monadic(|a| a.step(f64::round))?,
+                Add => self.dyadic(|a, b| b.add(a))?,
+                Sub => self.dyadic(|a, b| b.add(a.neg()))?,
+                Mul => self.dyadic(|a, b| b.mul(a))?,
+                Div => self.dyadic(|a, b| b.mul(a.recip()))?,
+                Pow => self.dyadic(|a, b| b.pow(a))?,
+                Log => self.dyadic(|a, b| b.ln().mul(a.ln().recip()))?,
+                Min => self.dyadic(T::min)?,
+                Max => self.dyadic(T::max)?,
+                Eta => self.stack.push(T::constant(FRAC_PI_2)),
+                Pi => self.stack.push(T::constant(PI)),
+                Tau => self.stack.push(T::constant(TAU)),
+                prim => return Err(not_supported(prim.format().to_string())),
+            },
+            Node::ImplPrim(prim, _) => match prim {
+                ImplPrimitive::Cos => self.monadic(T::cos)?,
+                ImplPrimitive::Root => self.dyadic(|a, b| b.pow(a.recip()))?,
+                prim => return Err(not_supported(prim.to_string())),
+            },
+            Node::Mod(prim, args, _) => match (prim, args.as_slice()) {
+                (Dip, [f]) => {
+                    let a = self.pop()?;
+                    self.node(&f.node)?;
+                    self.stack.push(a);
+                }
+                (Gap, [f]) => {
+                    self.pop()?;
+                    self.node(&f.node)?;
+                }
+                (On, [f]) => {
+                    let a = self.pop()?;
+                    self.stack.push(a);
+                    self.node(&f.node)?;
+                    self.stack.push(a);
+                }
+                (By, [f]) => {
+                    let args = self.pop_n(f.sig.args)?;
+                    self.stack.extend(args.last().copied());
+                    self.stack.extend(args.into_iter().rev());
+                    self.node(&f.node)?;
+                }
+                (Both, [f]) => {
+                    let args = self.pop_n(f.sig.args)?;
+                    self.node(&f.node)?;
+                    self.stack.extend(args.into_iter().rev());
+                    self.node(&f.node)?;
+                }
+                (Bracket, [f, g]) => {
+                    let args = self.pop_n(f.sig.args)?;
+                    self.node(&g.node)?;
+                    self.stack.extend(args.into_iter().rev());
+                    self.node(&f.node)?;
+                }
+                (Fork, [f, g]) => {
+                    let args = self.pop_n(f.sig.args.max(g.sig.args))?;
+                    self.stack.extend(args.iter().take(g.sig.args).rev());
+                    self.node(&g.node)?;
+                    self.stack.extend(args.iter().take(f.sig.args).rev());
+                    self.node(&f.node)?;
+                }
+                (prim, _) => return Err(not_supported(prim.format().to_string())),
+            },
+            Node::CustomInverse(cust, _) => match &cust.normal {
+                Ok(normal) if !cust.is_obverse => self.node(&normal.node)?,
+                _ => return Err(not_supported("custom inverses".into())),
+            },
+            Node::CopyToUnder(..) | Node::PushUnder(..) | Node::PopUnder(..) => {}
+            _ => return Err(not_supported("this function".into())),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,300 @@
+//! Root finding and minimization
+
+use std::mem::swap;
+
+use crate::{
+    algorithm::dual::{check_differentiable, eval_dual},
+    Array, Primitive, SigNode, Uiua, UiuaResult, Value,
+};
+
+/// The maximum number of iterations per dimension before giving up
+const MAX_ITERATIONS: usize = 1000;
+/// The inverse of the golden ratio
+const INV_PHI: f64 = 0.618_033_988_749_895;
+
+fn check_sig(f: &SigNode, prim: Primitive, env: &Uiua) -> UiuaResult {
+    if f.sig.args != 1 || f.sig.outputs != 1 {
+        return Err(env.error(format!(
+            "{}'s function must have 1 argument and 1 output, \
+            but its signature is {}",
+            prim.format(),
+            f.sig
+        )));
+    }
+    Ok(())
+}
+
+fn not_converged(prim: Primitive, env: &Uiua) -> crate::UiuaError {
+    env.error(format!("{} did not converge", prim.format()))
+}
+
+/// Call a function that returns a scalar
+fn call(f: &SigNode, x: impl Into<Value>, env: &mut Uiua) -> UiuaResult<f64> {
+    env.push(x);
+    env.exec(f.clone())?;
+    let y = env.pop("function result")?;
+    y.as_num(env, "Function must return a number")
+}
+
+/// Find a root of a function
+pub fn solve(f: SigNode, env: &mut Uiua) -> UiuaResult {
+    check_sig(&f, Primitive::Solve, env)?;
+    let start = env.pop(1)?;
+    let start = start.as_nums(env, "Starting point must be a number or a pair of numbers")?;
+    // Use the derivative when the function can be differentiated
+    let differentiable = check_differentiable(&f, &env.asm).is_ok();
+    let mut eval = |x: f64, env: &mut Uiua| -> UiuaResult<(f64, Option<f64>)> {
+        if differentiable {
+            let (y, dy) = eval_dual(&f, x, &env.asm).map_err(|e| env.error(e))?;
+            Ok((y, Some(dy)))
+        } else {
+            Ok((call(&f, x, env)?, None))
+        }
+    };
+    let root = match *start {
+        [x] => newton(x, &mut eval, env)?,
+        [a, b] => bracketed(a, b, &mut eval, env)?,
+        _ => {
+            return Err(env.error(format!(
+                "{}'s starting point must be a number or a pair of numbers, \
+                but it has {} numbers",
+                Primitive::Solve.format(),
+                start.len()
+            )))
+        }
+    };
+    env.push(root);
+    Ok(())
+}
+
+type Eval<'a> = dyn FnMut(f64, &mut Uiua) -> UiuaResult<(f64, Option<f64>)> + 'a;
+
+/// Whether two successive approximations are close enough to stop
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() <= 4.0 * f64::EPSILON * a.abs().max(1.0)
+}
+
+/// Find a root with Newton's method
+///
+/// The secant method is used if the derivative is not known.
+fn newton(mut x: f64, eval: &mut Eval, env: &mut Uiua) -> UiuaResult<f64> {
+    for _ in 0..MAX_ITERATIONS {
+        let (y, dy) = eval(x, env)?;
+        if y == 0.0 {
+            return Ok(x);
+        }
+        let dy = match dy {
+            Some(dy) => dy,
+            None => {
+                let h = f64::EPSILON.sqrt() * x.abs().max(1.0);
+                (eval(x + h, env)?.0 - y) / h
+            }
+        };
+        let next = x - y / dy;
+        if !next.is_finite() {
+            return Err(env.error(format!(
+                "{} reached a point where the function is flat. \
+                Try a different starting point or a pair of bounds.",
+                Primitive::Solve.format()
+            )));
+        }
+        if close(next, x) {
+            return Ok(next);
+        }
+        x = next;
+    }
+    Err(not_converged(Primitive::Solve, env))
+}
+
+/// Find a root between two bounds with bisection
+///
+/// Newton's method is used for steps that stay within the bounds.
+fn bracketed(mut lo: f64, mut hi: f64, eval: &mut Eval, env: &mut Uiua) -> UiuaResult<f64> {
+    let (mut flo, _) = eval(lo, env)?;
+    let (fhi, _) = eval(hi, env)?;
+    if flo == 0.0 {
+        return Ok(lo);
+    }
+    if fhi == 0.0 {
+        return Ok(hi);
+    }
+    if flo.signum() == fhi.signum() || flo.is_nan() || fhi.is_nan() {
+        return Err(env.error(format!(
+            "{}'s function must have different signs at the bounds, \
+            but it is {flo} at {lo} and {fhi} at {hi}",
+            Primitive::Solve.format()
+        )));
+    }
+    let mut x = (lo + hi) / 2.0;
+    for _ in 0..MAX_ITERATIONS {
+        let (y, dy) = eval(x, env)?;
+        if y == 0.0 {
+            return Ok(x);
+        }
+        if y.signum() == flo.signum() {
+            lo = x;
+            flo = y;
+        } else {
+            hi = x;
+        }
+        let mid = (lo + hi) / 2.0;
+        let next = (dy.map(|dy| x - y / dy))
+            .filter(|&n| n > lo.min(hi) && n < lo.max(hi))
+            .unwrap_or(mid);
+        if close(next, x) || mid == lo || mid == hi {
+            return Ok(next);
+        }
+        x = next;
+    }
+    Err(not_converged(Primitive::Solve, env))
+}
+
+/// Find a local minimum of a function
+pub fn minimize(f: SigNode, env: &mut Uiua) -> UiuaResult {
+    check_sig(&f, Primitive::Minimize, env)?;
+    let start = env.pop(1)?;
+    match start.rank() {
+        0 => {
+            let x = start.as_num(env, "Starting point must be a number or a list of numbers")?;
+            let min = golden_section(&f, x, env)?;
+            env.push(min);
+        }
+        1 => {
+            let x = start.as_nums(env, "Starting point must be a number or a list of numbers")?;
+            let min = nelder_mead(&f, x, env)?;
+            env.push(Array::from_iter(min));
+        }
+        _ => {
+            return Err(env.error(format!(
+                "{}'s starting point must be a number or a list of numbers, \
+                but it has shape {}",
+                Primitive::Minimize.format(),
+                start.shape()
+            )))
+        }
+    }
+    Ok(())
+}
+
+/// Minimize a function of one number with a golden section search
+fn golden_section(f: &SigNode, x: f64, env: &mut Uiua) -> UiuaResult<f64> {
+    let no_minimum = |env: &Uiua| {
+        env.error(format!(
+            "{} could not find a minimum near {x}",
+            Primitive::Minimize.format()
+        ))
+    };
+    // Walk downhill until the function starts increasing
+    let step = 0.01 * x.abs().max(1.0);
+    let (mut a, mut fa) = (x, call(f, x, env)?);
+    let (mut b, mut fb) = (x + step, call(f, x + step, env)?);
+    if fb > fa {
+        swap(&mut a, &mut b);
+        swap(&mut fa, &mut fb);
+    }
+    let mut c = b + (b - a) / INV_PHI;
+    let mut fc = call(f, c, env)?;
+    let mut steps = 0;
+    while fc < fb {
+        (a, fa, b, fb) = (b, fb, c, fc);
+        c = b + (b - a) / INV_PHI;
+        fc = call(f, c, env)?;
+        steps += 1;
+        if steps == MAX_ITERATIONS || !c.is_finite() {
+            return Err(no_minimum(env));
+        }
+    }
+    if fa.is_nan() || fb.is_nan() || fc.is_nan() {
+        return Err(no_minimum(env));
+    }
+    // Narrow the bracket
+    let (mut lo, mut hi) = (a.min(c), a.max(c));
+    let mut x1 = hi - INV_PHI * (hi - lo);
+    let mut x2 = lo + INV_PHI * (hi - lo);
+    let mut f1 = call(f, x1, env)?;
+    let mut f2 = call(f, x2, env)?;
+    for _ in 0..MAX_ITERATIONS {
+        if hi - lo <= f64::EPSILON.sqrt() * x1.abs().max(1.0) {
+            return Ok((lo + hi) / 2.0);
+        }
+        if f1 < f2 {
+            (hi, x2, f2) = (x2, x1, f1);
+            x1 = hi - INV_PHI * (hi - lo);
+            f1 = call(f, x1, env)?;
+        } else {
+            (lo, x1, f1) = (x1, x2, f2);
+            x2 = lo + INV_PHI * (hi - lo);
+            f2 = call(f, x2, env)?;
+        }
+    }
+    Err(not_converged(Primitive::Minimize, env))
+}
+
+/// Minimize a function of a list of numbers with the Nelder-Mead method
+fn nelder_mead(f: &SigNode, x: Vec<f64>, env: &mut Uiua) -> UiuaResult<Vec<f64>> {
+    let n = x.len();
+    if n == 0 {
+        return Ok(x);
+    }
+    let eval = |x: &[f64], env: &mut Uiua| call(f, Array::from_iter(x.iter().copied()), env);
+    let towards = |a: &[f64], b: &[f64], t: f64| -> Vec<f64> {
+        a.iter().zip(b).map(|(a, b)| a + t * (b - a)).collect()
+    };
+    // Start with a simplex around the starting point
+    let mut simplex = Vec::with_capacity(n + 1);
+    simplex.push((eval(&x, env)?, x.clone()));
+    for i in 0..n {
+        let mut vertex = x.clone();
+        vertex[i] += if x[i] == 0.0 { 0.00025 } else { 0.05 * x[i] };
+        simplex.push((eval(&vertex, env)?, vertex));
+    }
+    for _ in 0..MAX_ITERATIONS * n {
+        simplex.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        let best = &simplex[0].1;
+        let scale = best.iter().fold(1f64, |acc, x| acc.max(x.abs()));
+        let size = (simplex[1..].iter())
+            .flat_map(|(_, v)| v.iter().zip(best).map(|(a, b)| (a - b).abs()))
+            .fold(0.0, f64::max);
+        if size <= 1e-10 * scale {
+            return Ok(simplex.swap_remove(0).1);
+        }
+        let mut centroid = vec![0.0; n];
+        for (_, vertex) in &simplex[..n] {
+            for (c, x) in centroid.iter_mut().zip(vertex) {
+                *c += x / n as f64;
+            }
+        }
+        let (f_worst, worst) = simplex[n].clone();
+        let reflected = towards(&centroid, &worst, -1.0);
+        let f_reflected = eval(&reflected, env)?;
+        if f_reflected < simplex[0].0 {
+            let expanded = towards(&centroid, &worst, -2.0);
+            let f_expanded = eval(&expanded, env)?;
+            simplex[n] = if f_expanded < f_reflected {
+                (f_expanded, expanded)
+            } else {
+                (f_reflected, reflected)
+            };
+        } else if f_reflected < simplex[n - 1].0 {
+            simplex[n] = (f_reflected, reflected);
+        } else {
+            let contracted = if f_reflected < f_worst {
+                towards(&centroid, &reflected, 0.5)
+            } else {
+                towards(&centroid, &worst, 0.5)
+            };
+            let f_contracted = eval(&contracted, env)?;
+            if f_contracted < f_reflected.min(f_worst) {
+                simplex[n] = (f_contracted, contracted);
+            } else {
+                // Shrink towards the best vertex
+                let best = simplex[0].1.clone();
+                for (fv, vertex) in &mut simplex[1..] {
+                    *vertex = towards(&best, vertex, 0.5);
+                    *fv = eval(vertex, env)?;
+                }
+            }
+        }
+    }
+    Err(not_converged(Primitive::Minimize, env))
+}
@@ -1,6 +1,6 @@
 use std::{collections::VecDeque, mem::take};
 
-use ecow::EcoVec;
+use ecow::{eco_vec, EcoVec};
 
 use crate::{
     algorithm::{pervade::*, FillContext, MultiOutput},
@@ -60,6 +60,13 @@ pub fn stencil(ops: Ops, env: &mut Uiua) -> UiuaResult {
     xs.match_fill(env);
     let has_fill = env.fill().value_for(&xs).is_some();
     let dims = derive_dims(&size, xs.shape(), has_fill, env)?;
+    // Fused windowed reduction or scan
+    if let (Some(fused), [dim]) = (FusedWindow::new(&f), dims.as_slice()) {
+        if let Some(val) = fused_stencil(&xs, *dim, fused, env) {
+            env.push(val);
+            return Ok(());
+        }
+    }
     val_as_arr!(xs, |arr| stencil_array(arr, &dims, f, env))
 }
 
@@ -324,6 +331,204 @@ fn derive_dims(
     Ok(dims)
 }
 
+/// A reduction or scan that can be applied to windows without materializing them
+#[derive(Debug, Clone, Copy)]
+struct FusedWindow {
+    prim: Primitive,
+    scan: bool,
+}
+
+impl FusedWindow {
+    fn new(f: &SigNode) -> Option<Self> {
+        let Node::Mod(modifier @ (Primitive::Reduce | Primitive::Scan), args, _) = &f.node else {
+            return None;
+        };
+        let [g] = args.as_slice() else {
+            return None;
+        };
+        let (prim, _) = g.node.as_flipped_primitive()?;
+        use Primitive::*;
+        matches!(prim, Add | Mul | Max | Min).then_some(FusedWindow {
+            prim,
+            scan: *modifier == Scan,
+        })
+    }
+}
+
+/// Reduce or scan each window along the first axis
+///
+/// Returns `None` if the windows should be materialized instead
+fn fused_stencil(xs: &Value, dim: WindowDim, fused: FusedWindow, env: &Uiua) -> Option<Value> {
+    use Primitive::*;
+    let (count, _) = fused_shape(xs.shape(), dim, fused.scan);
+    if count == 0 {
+        return None;
+    }
+    let num_fill = || env.scalar_fill::<f64>().ok().unwrap_or_else(f64::proxy);
+    let byte_fill = || env.scalar_fill::<u8>().ok().unwrap_or_else(u8::proxy);
+    Some(match (fused.prim, xs) {
+        (Add, Value::Num(arr)) => fused_sum(arr, dim, fused.scan, num_fill()).into(),
+        (Add, Value::Byte(arr)) => {
+            fused_sum(&arr.convert_ref(), dim, fused.scan, num_fill()).into()
+        }
+        (Mul, Value::Num(arr)) => {
+            fused_windows(arr, dim, fused.scan, num_fill(), mul::num_num).into()
+        }
+        (Mul, Value::Byte(arr)) => {
+            let arr = arr.convert_ref();
+            fused_windows(&arr, dim, fused.scan, num_fill(), mul::num_num).into()
+        }
+        (Max | Min, Value::Num(arr))
+            if fused.scan || num_fill().is_nan() || arr.data.iter().any(|x| x.is_nan()) =>
+        {
+            let f = if fused.prim == Max {
+                max::num_num
+            } else {
+                min::num_num
+            };
+            fused_windows(arr, dim, fused.scan, num_fill(), f).into()
+        }
+        (Max, Value::Num(arr)) => window_extrema(arr, dim, num_fill(), |a, b| a >= b).into(),
+        (Min, Value::Num(arr)) => window_extrema(arr, dim, num_fill(), |a, b| a <= b).into(),
+        (Max, Value::Byte(arr)) if fused.scan => {
+            fused_windows(arr, dim, true, byte_fill(), u8::max).into()
+        }
+        (Min, Value::Byte(arr)) if fused.scan => {
+            fused_windows(arr, dim, true, byte_fill(), u8::min).into()
+        }
+        (Max, Value::Byte(arr)) => window_extrema(arr, dim, byte_fill(), |a, b| a >= b).into(),
+        (Min, Value::Byte(arr)) => window_extrema(arr, dim, byte_fill(), |a, b| a <= b).into(),
+        _ => return None,
+    })
+}
+
+/// Get the number of windows and the shape of a fused stencil's result
+fn fused_shape(shape: &Shape, dim: WindowDim, scan: bool) -> (usize, Shape) {
+    let total_len = shape.row_count() + 2 * dim.fill * dim.stride;
+    let count = (total_len + dim.stride).saturating_sub(dim.size) / dim.stride;
+    let mut new_shape = Shape::from(count);
+    if scan {
+        new_shape.push(dim.size);
+    }
+    new_shape.extend(shape.iter().skip(1).copied());
+    (count, new_shape)
+}
+
+/// Get a row of a windowed array, or `None` if the index is in the fill padding
+fn window_row<T>(arr: &Array<T>, i: isize, row_len: usize) -> Option<&[T]> {
+    (0..arr.row_count() as isize)
+        .contains(&i)
+        .then(|| &arr.data[i as usize * row_len..][..row_len])
+}
+
+/// Fold each window in the same order as a reduction or scan would
+///
+/// Nothing is copied, but each element is visited once per window it is in
+fn fused_windows<T: ArrayValue + Copy>(
+    arr: &Array<T>,
+    dim: WindowDim,
+    scan: bool,
+    fill: T,
+    f: impl Fn(T, T) -> T,
+) -> Array<T> {
+    let (count, shape) = fused_shape(&arr.shape, dim, scan);
+    let row_len = arr.row_len();
+    let pad = (dim.fill * dim.stride) as isize;
+    let mut data = EcoVec::with_capacity(shape.elements());
+    let mut acc = vec![fill; row_len];
+    for w in 0..count {
+        let start = (w * dim.stride) as isize - pad;
+        for j in 0..dim.size {
+            let row = window_row(arr, start + j as isize, row_len);
+            for (k, acc) in acc.iter_mut().enumerate() {
+                let x = row.map_or(fill, |row| row[k]);
+                *acc = if j == 0 { x } else { f(*acc, x) };
+            }
+            if scan {
+                data.extend_from_slice(&acc);
+            }
+        }
+        if !scan {
+            data.extend_from_slice(&acc);
+        }
+    }
+    Array::new(shape, data)
+}
+
+/// Sum each window using running sums
+///
+/// Running sums are only used when they are guaranteed to be exact.
+/// Otherwise, this falls back to [`fused_windows`].
+fn fused_sum(arr: &Array<f64>, dim: WindowDim, scan: bool, fill: f64) -> Array<f64> {
+    const EXACT_LIMIT: f64 = 9007199254740992.0; // 2^53
+    let pad = dim.fill * dim.stride;
+    let is_int = |x: &f64| x.fract() == 0.0;
+    let abs_total = arr.data.iter().map(|x| x.abs()).sum::<f64>()
+        + (2 * pad * arr.row_len()) as f64 * fill.abs();
+    if scan || !is_int(&fill) || !arr.data.iter().all(is_int) || abs_total >= EXACT_LIMIT {
+        return fused_windows(arr, dim, scan, fill, add::num_num);
+    }
+    let (count, shape) = fused_shape(&arr.shape, dim, false);
+    let row_len = arr.row_len();
+    let end = (count - 1) * dim.stride + dim.size;
+    // Prefix sums of the padded array
+    let mut sums = vec![0.0; (end + 1) * row_len];
+    for i in 0..end {
+        let row = window_row(arr, i as isize - pad as isize, row_len);
+        for k in 0..row_len {
+            let x = row.map_or(fill, |row| row[k]);
+            sums[(i + 1) * row_len + k] = sums[i * row_len + k] + x;
+        }
+    }
+    let mut data = EcoVec::with_capacity(shape.elements());
+    for w in 0..count {
+        let start = w * dim.stride;
+        let end = start + dim.size;
+        data.extend((0..row_len).map(|k| sums[end * row_len + k] - sums[start * row_len + k]));
+    }
+    Array::new(shape, data)
+}
+
+/// Get the maximum or minimum of each window using a monotonic queue
+///
+/// `keep` returns whether a new element should replace an older one.
+/// This must not be used if there are any NaNs.
+fn window_extrema<T: ArrayValue + Copy>(
+    arr: &Array<T>,
+    dim: WindowDim,
+    fill: T,
+    keep: impl Fn(T, T) -> bool,
+) -> Array<T> {
+    let (count, shape) = fused_shape(&arr.shape, dim, false);
+    let row_len = arr.row_len();
+    let pad = (dim.fill * dim.stride) as isize;
+    let end = (count - 1) * dim.stride + dim.size;
+    let mut data = eco_vec![fill; shape.elements()];
+    let slice = data.make_mut();
+    let mut queue: VecDeque<(usize, T)> = VecDeque::with_capacity(dim.size);
+    for k in 0..row_len {
+        queue.clear();
+        for i in 0..end {
+            let x = window_row(arr, i as isize - pad, row_len).map_or(fill, |row| row[k]);
+            while queue.back().is_some_and(|&(_, y)| keep(x, y)) {
+                queue.pop_back();
+            }
+            queue.push_back((i, x));
+            let Some(start) = (i + 1).checked_sub(dim.size) else {
+                continue;
+            };
+            if start % dim.stride != 0 {
+                continue;
+            }
+            while queue.front().is_some_and(|&(j, _)| j < start) {
+                queue.pop_front();
+            }
+            slice[start / dim.stride * row_len + k] = queue.front().unwrap().1;
+        }
+    }
+    Array::new(shape, data)
+}
+
 fn adjacent_impl(f: SigNode, xs: Value, n: usize, env: &mut Uiua) -> UiuaResult {
     match (f.node.as_flipped_primitive(), xs) {
         (Some((prim, flipped)), Value::Num(nums)) => env.push(match prim {
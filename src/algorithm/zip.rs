@@ -566,6 +566,109 @@ pub fn rows(f: SigNode, inv: bool, env: &mut Uiua) -> UiuaResult {
     }
 }
 
+/// Apply a function along an axis
+///
+/// Arguments that have the axis are reoriented so that it is their first axis.
+/// Outputs that have as many axes as the highest-rank argument are reoriented back.
+pub fn along(f: SigNode, axis: i32, env: &mut Uiua) -> UiuaResult {
+    crate::profile_function!();
+    let sig = f.sig;
+    let args = env.pop_n(sig.args)?;
+    let max_rank = args.iter().map(Value::rank).max().unwrap_or(0);
+    let axis = if axis >= 0 {
+        axis as usize
+    } else if axis.unsigned_abs() as usize <= max_rank {
+        max_rank - axis.unsigned_abs() as usize
+    } else {
+        return Err(env.error(format!(
+            "Cannot operate along axis {axis} of a rank {max_rank} array"
+        )));
+    };
+    if axis == 0 {
+        for arg in args {
+            env.push(arg);
+        }
+        return env.exec(f);
+    }
+    let indices = Value::from(axis as f64);
+    for mut arg in args {
+        if arg.rank() > axis {
+            indices.orient(&mut arg, env)?;
+        }
+        env.push(arg);
+    }
+    env.exec(f)?;
+    for mut output in env.pop_n(sig.outputs)? {
+        if output.rank() == max_rank && max_rank > axis {
+            output = indices.anti_orient(output, env)?;
+        }
+        env.push(output);
+    }
+    Ok(())
+}
+
+pub fn parallel(ops: Ops, env: &mut Uiua) -> UiuaResult {
+    crate::profile_function!();
+    let [f] = get_ops(ops, env)?;
+    let workers = (env.pop(1)?).as_nat(env, "Worker count must be a natural number")?;
+    if workers == 0 {
+        return Err(env.error("Worker count must be at least 1"));
+    }
+    if workers == 1 || f.sig.args == 0 || !env.rt.backend.allow_thread_spawning() {
+        return rows(f, false, env);
+    }
+    let outputs = f.sig.outputs;
+    let mut args = Vec::with_capacity(f.sig.args);
+    for i in 0..f.sig.args {
+        args.push(env.pop(i + 2)?);
+    }
+    let FixedRowsData {
+        mut rows,
+        row_count,
+        is_empty,
+        all_scalar,
+        per_meta,
+    } = fixed_rows(Primitive::Parallel.format(), outputs, args, env)?;
+    let mut jobs = Vec::with_capacity(row_count);
+    for _ in 0..row_count {
+        let mut job = Vec::with_capacity(rows.len());
+        for arg in rows.iter_mut().rev() {
+            match arg {
+                Ok(rows) => job.push(rows.next().unwrap()),
+                Err(row) => job.push(row.clone()),
+            }
+        }
+        jobs.push(job);
+    }
+    let results = env.run_parallel(workers, &f, jobs)?;
+    let mut new_values = multi_output(outputs, Vec::with_capacity(row_count));
+    for mut stack in results {
+        if stack.len() != outputs {
+            return Err(env.error(format!(
+                "{}'s function returned {} values instead of {outputs}. \
+                This is a bug in the interpreter",
+                Primitive::Parallel.format(),
+                stack.len()
+            )));
+        }
+        for new_values in new_values.iter_mut() {
+            new_values.push(stack.pop().unwrap());
+        }
+    }
+    for new_values in new_values.into_iter().rev() {
+        let mut val = Value::from_row_values(new_values, env)?;
+        if all_scalar {
+            val.undo_fix();
+        } else if is_empty {
+            val.pop_row();
+        }
+        val.validate_shape();
+        val.set_per_meta(per_meta.clone());
+        env.push(val);
+    }
+    Ok(())
+}
+
 fn collect_outputs(
     outputs: MultiOutput<Vec<Value>>,
     is_scalar: bool,
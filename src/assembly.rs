@@ -176,7 +176,7 @@ impl Assembly {
 
         let mut root = Node::empty();
         for line in root_src.lines().filter(|line| !line.trim().is_empty()) {
-            let node: Node = serde_json::from_str(line).unwrap();
+            let node: Node = serde_json::from_str(line).map_err(|e| e.to_string())?;
             root.push(node);
         }
 
@@ -205,7 +205,7 @@ impl Assembly {
 
         let mut functions = EcoVec::new();
         for line in functions_src.lines().filter(|line| !line.trim().is_empty()) {
-            let func: Node = serde_json::from_str(line).unwrap();
+            let func: Node = serde_json::from_str(line).map_err(|e| e.to_string())?;
             functions.push(func);
         }
 
@@ -312,6 +312,252 @@ impl Assembly {
 
         uasm
     }
+    /// Pretty-print the compiled node tree
+    ///
+    /// Each node is shown on its own line, indented by nesting depth, along with the location of its span.
+    /// Named functions are shown first, followed by the top-level code.
+    pub fn node_tree(&self) -> String {
+        let lines = self.tree_lines();
+        let width = tree_text_width(&lines);
+        let mut s = String::new();
+        for line in &lines {
+            line.render(width, &mut s);
+        }
+        s
+    }
+    /// Pretty-print a diff of the compiled node trees of two assemblies
+    ///
+    /// Lines are compared without their spans, so code that merely moved is not considered changed.
+    /// Spans are taken from whichever assembly the line comes from.
+    pub fn node_tree_diff(&self, old: &Self) -> String {
+        let old = old.tree_lines();
+        let new = self.tree_lines();
+        // Longest common subsequence
+        let mut lcs = vec![vec![0u32; new.len() + 1]; old.len() + 1];
+        for i in (0..old.len()).rev() {
+            for j in (0..new.len()).rev() {
+                lcs[i][j] = if old[i].same(&new[j]) {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+        let mut diff = Vec::with_capacity(old.len().max(new.len()));
+        let (mut i, mut j) = (0, 0);
+        while i < old.len() || j < new.len() {
+            if i < old.len() && j < new.len() && old[i].same(&new[j]) {
+                diff.push((' ', &new[j]));
+                i += 1;
+                j += 1;
+            } else if j < new.len() && (i == old.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+                diff.push(('+', &new[j]));
+                j += 1;
+            } else {
+                diff.push(('-', &old[i]));
+                i += 1;
+            }
+        }
+        // Only show changed lines and some context
+        const CONTEXT: usize = 3;
+        let changed: Vec<usize> = (diff.iter().enumerate())
+            .filter(|(_, (mark, _))| *mark != ' ')
+            .map(|(i, _)| i)
+            .collect();
+        let mut shown = Vec::new();
+        let mut last_end = 0;
+        for &i in &changed {
+            let start = i.saturating_sub(CONTEXT).max(last_end);
+            let end = (i + CONTEXT + 1).min(diff.len());
+            if start > last_end {
+                shown.push(None);
+            }
+            shown.extend(diff[start..end].iter().map(Some));
+            last_end = end;
+        }
+        if last_end < diff.len() && !changed.is_empty() {
+            shown.push(None);
+        }
+        let width = tree_text_width(diff.iter().map(|(_, line)| *line));
+        let mut s = String::new();
+        for entry in shown {
+            match entry {
+                Some((mark, line)) => {
+                    s.push(*mark);
+                    s.push(' ');
+                    line.render(width, &mut s);
+                }
+                None => s.push_str("...\n"),
+            }
+        }
+        s
+    }
+    fn tree_lines(&self) -> Vec<TreeLine> {
+        let mut lines = Vec::new();
+        let mut named = Vec::new();
+        for binding in &self.bindings {
+            if let BindingKind::Func(f) = &binding.kind {
+                named.push(f.id.clone());
+                lines.push(TreeLine {
+                    depth: 0,
+                    text: format!("{} {}", f.id, f.sig),
+                    span: Some(binding.span.start.to_string()),
+                });
+                self.node_tree_lines(&self[f], 1, &named, &mut lines);
+            }
+        }
+        lines.push(TreeLine {
+            depth: 0,
+            text: "main".into(),
+            span: None,
+        });
+        self.node_tree_lines(&self.root, 1, &named, &mut lines);
+        lines
+    }
+    fn node_tree_lines(
+        &self,
+        node: &Node,
+        depth: usize,
+        named: &[FunctionId],
+        lines: &mut Vec<TreeLine>,
+    ) {
+        let span = |node: &Node| match node.span().and_then(|i| self.spans.get(i)) {
+            Some(Span::Code(span)) => Some(span.start.to_string()),
+            _ => None,
+        };
+        let mut line = |depth: usize, text: String, node: &Node| {
+            lines.push(TreeLine {
+                depth,
+                text,
+                span: span(node),
+            })
+        };
+        match node {
+            Node::Run(nodes) => {
+                for node in nodes {
+                    self.node_tree_lines(node, depth, named, lines);
+                }
+            }
+            Node::Push(val) => {
+                let mut text = format!("push {val}");
+                if text.chars().count() > 40 {
+                    text = text.chars().take(39).chain(['…']).collect();
+                }
+                line(depth, text, node);
+            }
+            Node::Prim(prim, _) => line(depth, prim.to_string(), node),
+            Node::ImplPrim(prim, _) => line(depth, prim.to_string(), node),
+            Node::Mod(prim, args, _) => {
+                line(depth, prim.to_string(), node);
+                for arg in args {
+                    self.sig_node_tree_lines(arg.sig.to_string(), arg, depth + 1, named, lines);
+                }
+            }
+            Node::ImplMod(prim, args, _) => {
+                line(depth, prim.to_string(), node);
+                for arg in args {
+                    self.sig_node_tree_lines(arg.sig.to_string(), arg, depth + 1, named, lines);
+                }
+            }
+            Node::Array {
+                len, inner, boxed, ..
+            } => {
+                let kind = if *boxed { "box array" } else { "array" };
+                line(depth, format!("{kind} {len}"), node);
+                self.node_tree_lines(inner, depth + 1, named, lines);
+            }
+            Node::Switch { branches, sig, .. } => {
+                line(depth, format!("switch {sig}"), node);
+                for (i, branch) in branches.iter().enumerate() {
+                    let label = format!("branch {i} {}", branch.sig);
+                    self.sig_node_tree_lines(label, branch, depth + 1, named, lines);
+                }
+            }
+            Node::CustomInverse(cust, _) => {
+                line(depth, "custom inverse".into(), node);
+                if let Ok(normal) = &cust.normal {
+                    self.sig_node_tree_lines("normal".into(), normal, depth + 1, named, lines);
+                }
+                if let Some(un) = &cust.un {
+                    self.sig_node_tree_lines("un".into(), un, depth + 1, named, lines);
+                }
+                if let Some(anti) = &cust.anti {
+                    self.sig_node_tree_lines("anti".into(), anti, depth + 1, named, lines);
+                }
+                if let Some((do_, undo)) = &cust.under {
+                    self.sig_node_tree_lines("under do".into(), do_, depth + 1, named, lines);
+                    self.sig_node_tree_lines("under undo".into(), undo, depth + 1, named, lines);
+                }
+            }
+            Node::NoInline(inner) | Node::TrackCaller(inner) => {
+                let label = if let Node::NoInline(_) = node {
+                    "no-inline"
+                } else {
+                    "track-caller"
+                };
+                line(depth, label.into(), node);
+                self.node_tree_lines(inner, depth + 1, named, lines);
+            }
+            Node::Call(func, _) => {
+                line(depth, format!("call {} {}", func.id, func.sig), node);
+                if !named.contains(&func.id) {
+                    self.node_tree_lines(&self[func], depth + 1, named, lines);
+                }
+            }
+            node => line(depth, format!("{node:?}"), node),
+        }
+    }
+    fn sig_node_tree_lines(
+        &self,
+        label: String,
+        sn: &SigNode,
+        depth: usize,
+        named: &[FunctionId],
+        lines: &mut Vec<TreeLine>,
+    ) {
+        lines.push(TreeLine {
+            depth,
+            text: label,
+            span: None,
+        });
+        self.node_tree_lines(&sn.node, depth + 1, named, lines);
+    }
+}
+
+/// A line of a pretty-printed node tree
+struct TreeLine {
+    depth: usize,
+    text: String,
+    span: Option<String>,
+}
+
+impl TreeLine {
+    fn same(&self, other: &Self) -> bool {
+        self.depth == other.depth && self.text == other.text
+    }
+    fn width(&self) -> usize {
+        self.depth * 2 + self.text.chars().count()
+    }
+    fn render(&self, width: usize, s: &mut String) {
+        for _ in 0..self.depth {
+            s.push_str("  ");
+        }
+        s.push_str(&self.text);
+        if let Some(span) = &self.span {
+            for _ in self.width()..width {
+                s.push(' ');
+            }
+            s.push_str("  @ ");
+            s.push_str(span);
+        }
+        s.push('\n');
+    }
+}
+
+fn tree_text_width<'a>(lines: impl IntoIterator<Item = &'a TreeLine>) -> usize {
+    (lines.into_iter().map(TreeLine::width).max())
+        .unwrap_or(0)
+        .min(60)
 }
 
 impl Index<&Function> for Assembly {
@@ -381,6 +627,17 @@ pub struct BindingMeta {
     pub deprecation: Option<EcoString>,
 }
 
+impl BindingMeta {
+    /// Get the replacement for a deprecated binding
+    ///
+    /// This is the first `` `code` `` in the deprecation message
+    pub fn deprecation_replacement(&self) -> Option<&str> {
+        let (_, rest) = self.deprecation.as_ref()?.split_once('`')?;
+        let (replacement, _) = rest.split_once('`')?;
+        Some(replacement.trim()).filter(|r| !r.is_empty())
+    }
+}
+
 /// A kind of global binding
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -765,3 +1022,21 @@ impl fmt::Debug for Assembly {
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn node_tree_diff() {
+        use crate::*;
+        let build = |code: &str| Compiler::new().load_str(code).unwrap().finish();
+        let old = build("F ← +1\nF 2");
+        let new = build("F ← ×2\nF 2");
+        let tree = new.node_tree();
+        assert!(tree.starts_with("F |1.1"), "{tree}");
+        assert!(tree.lines().any(|line| line == "main"), "{tree}");
+        let diff = new.node_tree_diff(&old);
+        assert!(diff.lines().any(|line| line.starts_with("- ")), "{diff}");
+        assert!(diff.lines().any(|line| line.starts_with("+ ")), "{diff}");
+        assert!(new.node_tree_diff(&new).is_empty());
+    }
+}
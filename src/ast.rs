@@ -62,6 +62,8 @@ impl Binding {
 pub struct ScopedModule {
     /// The span of the opening delimiter
     pub open_span: CodeSpan,
+    /// The span of the ~ that makes the module private
+    pub private_span: Option<CodeSpan>,
     /// The module kind
     pub kind: ModuleKind,
     /// The items
@@ -127,6 +129,8 @@ pub struct DataDef {
     pub init_span: CodeSpan,
     /// Whether this is a variant
     pub variant: bool,
+    /// The span of the ~ that makes the module private
+    pub private_span: Option<CodeSpan>,
     /// The name of the module
     pub name: Option<Sp<Ident>>,
     /// The fields of the data definition
@@ -707,3 +711,217 @@ impl fmt::Debug for Subscripted {
         write!(f, "{}", self.n.value)
     }
 }
+
+impl Item {
+    /// Get the span of this item
+    ///
+    /// Returns `None` if the item is empty code
+    pub fn span(&self) -> Option<CodeSpan> {
+        match self {
+            Item::Words(lines) => {
+                let mut words = lines.iter().flatten();
+                let first = words.next()?.span.clone();
+                let last = words.last().map(|w| w.span.clone());
+                Some(match last {
+                    Some(last) => first.merge(last),
+                    None => first,
+                })
+            }
+            Item::Binding(binding) => Some(binding.span()),
+            Item::Import(import) => Some(import.span()),
+            Item::Module(module) => Some(module.span.clone()),
+            Item::Data(data) => Some(data.span()),
+        }
+    }
+}
+
+/// A visitor over the AST
+///
+/// Every method has a default implementation that visits the node's children,
+/// so implementors only need to override the methods for the nodes they care about.
+/// An overriding method can call the matching `walk_*` function to continue into the children.
+/// ```
+/// # use uiua::{ast::*, parse, CodeSpan, Inputs, Primitive};
+/// #[derive(Default)]
+/// struct Prims(Vec<Primitive>);
+/// impl Visitor for Prims {
+///     fn visit_primitive(&mut self, prim: Primitive, _: &CodeSpan) {
+///         self.0.push(prim);
+///     }
+/// }
+/// let (items, errors, _) = parse("F ← /+\n≡F [1_2 3_4]", (), &mut Inputs::default());
+/// assert!(errors.is_empty());
+/// let mut prims = Prims::default();
+/// prims.visit_items(&items);
+/// assert_eq!(prims.0, [Primitive::Reduce, Primitive::Add, Primitive::Rows]);
+/// ```
+#[allow(unused_variables)]
+pub trait Visitor {
+    /// Visit a list of top-level items
+    fn visit_items(&mut self, items: &[Item]) {
+        for item in items {
+            self.visit_item(item);
+        }
+    }
+    /// Visit a top-level item
+    fn visit_item(&mut self, item: &Item) {
+        walk_item(self, item)
+    }
+    /// Visit a binding
+    fn visit_binding(&mut self, binding: &Binding) {
+        walk_binding(self, binding)
+    }
+    /// Visit an import
+    fn visit_import(&mut self, import: &Import) {}
+    /// Visit a scoped module
+    fn visit_module(&mut self, module: &Sp<ScopedModule>) {
+        walk_module(self, module)
+    }
+    /// Visit a data definition
+    fn visit_data_def(&mut self, data: &DataDef) {
+        walk_data_def(self, data)
+    }
+    /// Visit a word
+    fn visit_word(&mut self, word: &Sp<Word>) {
+        walk_word(self, word)
+    }
+    /// Visit a reference to a binding
+    fn visit_ref(&mut self, r: &Ref, span: &CodeSpan) {}
+    /// Visit a primitive
+    fn visit_primitive(&mut self, prim: Primitive, span: &CodeSpan) {}
+    /// Visit a function
+    fn visit_func(&mut self, func: &Func, span: &CodeSpan) {
+        walk_func(self, func)
+    }
+    /// Visit a modifier
+    fn visit_modifier(&mut self, modifier: &Sp<Modifier>) {
+        walk_modifier(self, modifier)
+    }
+}
+
+/// Visit the children of an item
+pub fn walk_item<V: Visitor + ?Sized>(visitor: &mut V, item: &Item) {
+    match item {
+        Item::Words(lines) => {
+            for word in lines.iter().flatten() {
+                visitor.visit_word(word);
+            }
+        }
+        Item::Binding(binding) => visitor.visit_binding(binding),
+        Item::Import(import) => visitor.visit_import(import),
+        Item::Module(module) => visitor.visit_module(module),
+        Item::Data(data) => visitor.visit_data_def(data),
+    }
+}
+
+/// Visit the words of a binding
+pub fn walk_binding<V: Visitor + ?Sized>(visitor: &mut V, binding: &Binding) {
+    for word in &binding.words {
+        visitor.visit_word(word);
+    }
+}
+
+/// Visit the items of a scoped module
+pub fn walk_module<V: Visitor + ?Sized>(visitor: &mut V, module: &Sp<ScopedModule>) {
+    visitor.visit_items(&module.value.items);
+}
+
+/// Visit the validators, initializers, and function of a data definition
+pub fn walk_data_def<V: Visitor + ?Sized>(visitor: &mut V, data: &DataDef) {
+    for field in data.fields.iter().flat_map(|fields| &fields.fields) {
+        if let Some(validator) = &field.validator {
+            for word in &validator.words {
+                visitor.visit_word(word);
+            }
+        }
+        if let Some(init) = &field.init {
+            for word in &init.words {
+                visitor.visit_word(word);
+            }
+        }
+    }
+    for word in data.func.iter().flatten() {
+        visitor.visit_word(word);
+    }
+}
+
+/// Visit the children of a word
+pub fn walk_word<V: Visitor + ?Sized>(visitor: &mut V, word: &Sp<Word>) {
+    match &word.value {
+        Word::Ref(r) => visitor.visit_ref(r, &word.span),
+        Word::Primitive(prim) => visitor.visit_primitive(*prim, &word.span),
+        Word::Strand(items) => {
+            for item in items {
+                visitor.visit_word(item);
+            }
+        }
+        Word::Array(arr) => {
+            for word in arr.lines.iter().flatten() {
+                visitor.visit_word(word);
+            }
+        }
+        Word::Func(func) => visitor.visit_func(func, &word.span),
+        Word::Pack(pack) => {
+            for branch in &pack.branches {
+                visitor.visit_func(&branch.value, &branch.span);
+            }
+        }
+        Word::Modified(m) => {
+            visitor.visit_modifier(&m.modifier);
+            for operand in &m.operands {
+                visitor.visit_word(operand);
+            }
+        }
+        Word::Subscripted(sub) => visitor.visit_word(&sub.word),
+        Word::InlineMacro(mac) => visitor.visit_func(&mac.func.value, &mac.func.span),
+        _ => {}
+    }
+}
+
+/// Visit the words of a function
+pub fn walk_func<V: Visitor + ?Sized>(visitor: &mut V, func: &Func) {
+    for word in func.lines.iter().flatten() {
+        visitor.visit_word(word);
+    }
+}
+
+/// Visit the primitive, reference, or inline macro function of a modifier
+pub fn walk_modifier<V: Visitor + ?Sized>(visitor: &mut V, modifier: &Sp<Modifier>) {
+    match &modifier.value {
+        Modifier::Primitive(prim) => visitor.visit_primitive(*prim, &modifier.span),
+        Modifier::Ref(r) => visitor.visit_ref(r, &modifier.span),
+        Modifier::Macro(mac) => visitor.visit_func(&mac.func.value, &mac.func.span),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn ast_visitor() {
+        use super::*;
+        use crate::*;
+        #[derive(Default)]
+        struct Refs(Vec<String>);
+        impl Visitor for Refs {
+            fn visit_ref(&mut self, r: &Ref, span: &CodeSpan) {
+                self.0.push(format!("{r}@{}", span.start.col));
+            }
+        }
+        let mut inputs = Inputs::default();
+        let code = "\
+┌─╴M
+  F ← +1
+  G! ← ^0 F
+└─╴
+~Foo {A: M~F|B ← M~G!M~F}
+X ← Foo A 2";
+        let (items, errors, _) = parse(code, (), &mut inputs);
+        assert!(errors.is_empty(), "{errors:?}");
+        let mut refs = Refs::default();
+        refs.visit_items(&items);
+        assert_eq!(
+            refs.0,
+            ["F@11", "M~F@10", "M~G!@18", "M~F@22", "Foo@5", "A@9"]
+        );
+    }
+}
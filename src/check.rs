@@ -418,7 +418,7 @@ impl VirtualEnv {
                     let args = sig.args.saturating_sub(sig.outputs);
                     self.handle_args_outputs(args, sig.outputs);
                 }
-                Each | Rows | Inventory => {
+                Each | Rows | Inventory | Along => {
                     let [sig] = get_args(args)?;
                     self.handle_sig(sig)
                 }
@@ -437,10 +437,30 @@ impl VirtualEnv {
                     let [sig] = get_args(args)?;
                     self.handle_args_outputs(sig.args.max(1) + 1, sig.outputs);
                 }
+                Solve | Minimize => {
+                    let [_] = get_args(args)?;
+                    self.handle_args_outputs(1, 1);
+                }
+                Ode => {
+                    let [_] = get_args(args)?;
+                    self.handle_args_outputs(2, 1);
+                }
+                Decimal | Interval => {
+                    let [f] = get_args(args)?;
+                    self.handle_sig(f);
+                }
+                Aggregate => {
+                    let [_, agg] = get_args(args)?;
+                    self.handle_args_outputs(1, agg.outputs + 1);
+                }
                 Spawn | Pool => {
                     let [sig] = get_args(args)?;
                     self.handle_args_outputs(sig.args, 1);
                 }
+                Parallel => {
+                    let [sig] = get_args(args)?;
+                    self.handle_args_outputs(sig.args + 1, sig.outputs);
+                }
                 Repeat => {
                     let [f] = get_args_nodes(args)?;
                     let n = self.pop();
@@ -560,10 +580,14 @@ impl VirtualEnv {
                     let [f] = get_args(args)?;
                     self.handle_args_outputs(f.args, f.outputs + 1);
                 }
-                Sys(SysOp::ReadLines) => {
+                Sys(SysOp::ReadLines | SysOp::Use) => {
                     let [f] = get_args(args)?;
                     self.handle_sig(f);
                 }
+                Sys(SysOp::ReadChunks) => {
+                    let [f] = get_args(args)?;
+                    self.handle_args_outputs(f.args + 1, f.outputs);
+                }
                 Sys(SysOp::AudioStream) => {
                     let [f] = get_args(args)?;
                     self.handle_args_outputs(f.args.saturating_sub(1), f.outputs.saturating_sub(1));
@@ -627,11 +651,13 @@ impl VirtualEnv {
                     let [f, g] = get_args(args)?;
                     self.handle_args_outputs(f.args + g.args, f.outputs + g.outputs);
                 }
-                EachSub(_) => {
+                EachSub(_) | AlongSub(_) | DecimalSub(_) => {
                     let [f] = get_args_nodes(args)?;
                     self.node(&f.node)?;
                 }
                 UnScan => self.handle_args_outputs(1, 1),
+                TestProperty => self.handle_args_outputs(1, 0),
+                AutoDerivative => self.handle_args_outputs(1, 1),
                 SplitBy | SplitByScalar | SplitByKeepEmpty => {
                     let [f] = get_args(args)?;
                     self.handle_args_outputs(2, f.outputs);
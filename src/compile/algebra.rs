@@ -143,6 +143,23 @@ pub fn derivative(node: &Node, asm: &Assembly) -> AlgebraResult<Node> {
     Ok(node)
 }
 
+/// A notation for displaying expressions as math
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MathFormat {
+    /// Plain text with Unicode superscripts
+    Text,
+    /// LaTeX
+    Latex,
+    /// MathML, without the surrounding `<math>` element
+    MathMl,
+}
+
+/// Display a function as a mathematical expression of `x`
+pub fn math_notation(node: &Node, asm: &Assembly, format: MathFormat) -> AlgebraResult<String> {
+    let expr = nodes_expr(node, asm).expr?;
+    Ok(MathNotation(&expr, format).to_string())
+}
+
 pub fn integral(node: &Node, asm: &Assembly) -> AlgebraResult<Node> {
     dbgln!("integral of {node:?}");
     let data = nodes_expr(node, asm);
@@ -168,7 +185,9 @@ fn expr_deriv(expr: Expr) -> Option<Expr> {
                 *deriv.0.entry(Term::X(x)).or_default() += coef;
             }
             Term::Div(expr) => {
-                *deriv.0.entry(Term::Div(expr.pow(2.0.into())?)).or_default() += coef
+                let prime = expr_deriv(expr.clone())?.as_constant()?;
+                let term = Term::Div(expr.pow(2.0.into())?);
+                *deriv.0.entry(term).or_default() -= coef * prime;
             }
             Term::Log(base, expr) => {
                 let prime = expr_deriv(expr.clone())?.as_constant()?;
@@ -715,7 +734,7 @@ impl Expr {
         if power.fract() == 0.0 && power >= 0.0 {
             let n = power as usize;
             Some(if n == 0 {
-                0.0.into()
+                1.0.into()
             } else {
                 let mut acc = self.clone();
                 for _ in 1..n {
@@ -756,6 +775,227 @@ impl fmt::Debug for Expr {
     }
 }
 
+const SUPERSCRIPT_DIGITS: [char; 10] = ['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹'];
+
+impl MathFormat {
+    fn num(self, f: &mut fmt::Formatter, n: impl fmt::Display) -> fmt::Result {
+        match self {
+            MathFormat::MathMl => write!(f, "<mn>{n}</mn>"),
+            _ => write!(f, "{n}"),
+        }
+    }
+    fn ident(self, f: &mut fmt::Formatter, name: &str) -> fmt::Result {
+        match self {
+            MathFormat::MathMl => write!(f, "<mi>{name}</mi>"),
+            _ => write!(f, "{name}"),
+        }
+    }
+    fn op(self, f: &mut fmt::Formatter, op: &str) -> fmt::Result {
+        match self {
+            MathFormat::MathMl => write!(f, "<mo>{}</mo>", op.trim()),
+            _ => write!(f, "{op}"),
+        }
+    }
+    /// Write implicit multiplication
+    fn times(self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MathFormat::MathMl => write!(f, "<mo>\u{2062}</mo>"),
+            _ => Ok(()),
+        }
+    }
+    fn frac(
+        self,
+        f: &mut fmt::Formatter,
+        num: impl FnOnce(&mut fmt::Formatter) -> fmt::Result,
+        den: impl FnOnce(&mut fmt::Formatter) -> fmt::Result,
+    ) -> fmt::Result {
+        match self {
+            MathFormat::Text => {
+                num(f)?;
+                write!(f, "/")?;
+                den(f)
+            }
+            MathFormat::Latex => {
+                write!(f, "\\frac{{")?;
+                num(f)?;
+                write!(f, "}}{{")?;
+                den(f)?;
+                write!(f, "}}")
+            }
+            MathFormat::MathMl => {
+                write!(f, "<mfrac><mrow>")?;
+                num(f)?;
+                write!(f, "</mrow><mrow>")?;
+                den(f)?;
+                write!(f, "</mrow></mfrac>")
+            }
+        }
+    }
+    fn parens(self, f: &mut fmt::Formatter, expr: &Expr) -> fmt::Result {
+        match self {
+            MathFormat::Text => write!(f, "({})", MathNotation(expr, self)),
+            MathFormat::Latex => write!(f, "\\left({}\\right)", MathNotation(expr, self)),
+            MathFormat::MathMl => write!(f, "<mo>(</mo>{}<mo>)</mo>", MathNotation(expr, self)),
+        }
+    }
+}
+
+/// An expression formatted as conventional math notation
+struct MathNotation<'a>(&'a Expr, MathFormat);
+
+impl fmt::Display for MathNotation<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let MathNotation(expr, format) = *self;
+        // Higher powers first, then other terms, then the constant
+        let group = |term: &Term| match term {
+            Term::X(p) if *p == 0.0 => 2,
+            Term::X(_) => 0,
+            _ => 1,
+        };
+        let mut terms: Vec<_> = (expr.0.iter()).filter(|(_, coef)| **coef != ZERO).collect();
+        terms.sort_by(|(a, _), (b, _)| {
+            group(a).cmp(&group(b)).then_with(|| match (a, b) {
+                (Term::X(a), Term::X(b)) => b.total_cmp(a),
+                _ => Ordering::Equal,
+            })
+        });
+        if terms.is_empty() {
+            return format.num(f, 0);
+        }
+        for (i, (term, coef)) in terms.into_iter().enumerate() {
+            let (neg, coef) = match coef.into_real() {
+                Some(re) if re < 0.0 => (true, Complex::from(-re)),
+                _ => (false, *coef),
+            };
+            match (i, neg) {
+                (0, true) => format.op(f, "-")?,
+                (0, false) => {}
+                (_, true) => format.op(f, " - ")?,
+                (_, false) => format.op(f, " + ")?,
+            }
+            fmt_math_term(f, term, coef, format)?;
+        }
+        Ok(())
+    }
+}
+
+fn fmt_math_coef(f: &mut fmt::Formatter<'_>, coef: Complex, format: MathFormat) -> fmt::Result {
+    match coef.into_real() {
+        Some(re) => format.num(f, re),
+        None if coef.re == 0.0 => {
+            format.num(f, coef.im)?;
+            format.ident(f, "i")
+        }
+        None => {
+            format.op(f, "(")?;
+            format.num(f, coef.re)?;
+            format.op(f, " + ")?;
+            format.num(f, coef.im)?;
+            format.ident(f, "i")?;
+            format.op(f, ")")
+        }
+    }
+}
+
+fn fmt_math_term(
+    f: &mut fmt::Formatter<'_>,
+    term: &Term,
+    coef: Complex,
+    format: MathFormat,
+) -> fmt::Result {
+    // Show rational coefficients as fractions
+    let fraction = (coef.into_real())
+        .filter(|re| re.fract() != 0.0)
+        .and_then(|re| {
+            (2..=16)
+                .map(|d| (re * d as f64, d))
+                .find(|(n, _)| (n - n.round()).abs() < 1e-9)
+                .map(|(n, d)| (n.round(), d))
+        });
+    let call = |f: &mut fmt::Formatter<'_>, name: &str, expr| {
+        match format {
+            MathFormat::Latex => write!(f, "\\{name}")?,
+            _ => format.ident(f, name)?,
+        }
+        format.parens(f, expr)
+    };
+    let write_body = |f: &mut fmt::Formatter<'_>| -> fmt::Result {
+        match term {
+            Term::X(p) if *p == 1.0 => format.ident(f, "x"),
+            Term::X(p) if *p == 0.5 => match format {
+                MathFormat::Text => write!(f, "√x"),
+                MathFormat::Latex => write!(f, "\\sqrt{{x}}"),
+                MathFormat::MathMl => write!(f, "<msqrt><mi>x</mi></msqrt>"),
+            },
+            Term::X(p) => match format {
+                MathFormat::Text if p.fract() == 0.0 && p.abs() < 1e6 => {
+                    write!(f, "x")?;
+                    if *p < 0.0 {
+                        write!(f, "⁻")?;
+                    }
+                    for c in (p.abs() as u64).to_string().chars() {
+                        write!(f, "{}", SUPERSCRIPT_DIGITS[c as usize - '0' as usize])?;
+                    }
+                    Ok(())
+                }
+                MathFormat::Text => write!(f, "x^{p}"),
+                MathFormat::Latex => write!(f, "x^{{{p}}}"),
+                MathFormat::MathMl => write!(f, "<msup><mi>x</mi><mn>{p}</mn></msup>"),
+            },
+            Term::Log(base, expr) if *base == E => call(f, "ln", expr),
+            Term::Log(base, expr) => {
+                match format {
+                    MathFormat::Text => write!(f, "log_{base}")?,
+                    MathFormat::Latex => write!(f, "\\log_{{{base}}}")?,
+                    MathFormat::MathMl => write!(f, "<msub><mi>log</mi><mn>{base}</mn></msub>")?,
+                }
+                format.parens(f, expr)
+            }
+            Term::Sin(expr) => call(f, "sin", expr),
+            Term::Cos(expr) => call(f, "cos", expr),
+            Term::Div(_) => Ok(()),
+        }
+    };
+    match term {
+        Term::X(p) if *p == 0.0 => match fraction {
+            Some((n, d)) => format.frac(f, |f| format.num(f, n), |f| format.num(f, d)),
+            None => fmt_math_coef(f, coef, format),
+        },
+        Term::Div(expr) => format.frac(
+            f,
+            |f| fmt_math_coef(f, coef, format),
+            |f| {
+                let single = expr.single().is_some_and(|(_, coef)| coef == ONE);
+                if format == MathFormat::Text && !single {
+                    format.parens(f, expr)
+                } else {
+                    write!(f, "{}", MathNotation(expr, format))
+                }
+            },
+        ),
+        _ => match fraction {
+            Some((n, d)) => format.frac(
+                f,
+                |f| {
+                    if n != 1.0 {
+                        format.num(f, n)?;
+                        format.times(f)?;
+                    }
+                    write_body(f)
+                },
+                |f| format.num(f, d),
+            ),
+            None => {
+                if coef != ONE {
+                    fmt_math_coef(f, coef, format)?;
+                    format.times(f)?;
+                }
+                write_body(f)
+            }
+        },
+    }
+}
+
 impl PartialEq for Expr {
     fn eq(&self, other: &Self) -> bool {
         self.0.len() == other.0.len()
@@ -286,7 +286,12 @@ impl Compiler {
                 if !binds_above {
                     // Validate signature
                     if let Some(declared_sig) = &binding.signature {
-                        node = self.force_sig(node, declared_sig.value, &declared_sig.span);
+                        node = self.force_sig(
+                            node,
+                            declared_sig.value,
+                            &declared_sig.span,
+                            &words_span,
+                        );
                         sig = declared_sig.value;
                     }
                 }
@@ -356,7 +361,12 @@ impl Compiler {
                         let mut node = Node::empty();
                         // Validate signature
                         if let Some(declared_sig) = &binding.signature {
-                            node = self.force_sig(node, declared_sig.value, &declared_sig.span);
+                            node = self.force_sig(
+                                node,
+                                declared_sig.value,
+                                &declared_sig.span,
+                                &words_span,
+                            );
                             sig = declared_sig.value;
                         }
                         let func = make_fn(node, sig, self);
@@ -426,7 +436,7 @@ impl Compiler {
                 self.next_global += 1;
                 let local = LocalName {
                     index: global_index,
-                    public: true,
+                    public: m.private_span.is_none(),
                 };
                 let meta = BindingMeta {
                     comment: prelude.comment.as_deref().map(DocComment::from),
@@ -444,6 +454,9 @@ impl Compiler {
                 (self.code_meta.global_references).insert(name.span.clone(), local.index);
             }
             ModuleKind::Test => {
+                if let Some(span) = &m.private_span {
+                    self.add_error(span.clone(), "Test modules cannot be private");
+                }
                 if let Some(line) = &m.imports {
                     self.add_error(
                         line.tilde_span.clone(),
@@ -468,7 +481,7 @@ impl Compiler {
             self.next_global += 1;
             let local = LocalName {
                 index: global_index,
-                public: true,
+                public: false,
             };
             self.asm.add_binding_at(
                 local,
@@ -491,11 +504,12 @@ impl Compiler {
             {
                 self.validate_local(&item.value, local, &item.span);
                 (self.code_meta.global_references).insert(item.span.clone(), local.index);
+                (self.code_meta.import_items).insert(item.span.clone(), local.index);
                 self.scope.names.insert(
                     item.value.clone(),
                     LocalName {
                         index: local.index,
-                        public: true,
+                        public: false,
                     },
                 );
             } else {
@@ -593,3 +607,30 @@ impl Compiler {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn deprecated_binding() {
+        use crate::*;
+        let code = "┌─╴M\n  # Deprecated! Use `G` instead\n  F ← +1\n  G ← +1\n└─╴\nM~F 1 2";
+        let mut comp = Compiler::new();
+        comp.load_str(code).unwrap();
+        let replacements: Vec<_> = (comp.assembly().bindings.iter())
+            .filter_map(|binding| binding.meta.deprecation_replacement())
+            .collect();
+        assert_eq!(replacements, ["G"]);
+        let diagnostics: Vec<_> = (comp.take_diagnostics().into_iter())
+            .map(|diag| {
+                let Span::Code(span) = diag.span else {
+                    panic!("Diagnostic has no code span");
+                };
+                (diag.message, span.start.line, span.start.col)
+            })
+            .collect();
+        assert_eq!(
+            diagnostics,
+            [("F is deprecated. Use `G` instead.".into(), 6, 3)]
+        );
+    }
+}
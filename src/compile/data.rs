@@ -35,6 +35,8 @@ impl Compiler {
         if top_level {
             if let Some(name) = data.name.clone() {
                 let comment = prelude.comment.clone();
+                let variant = data.variant;
+                let public = data.private_span.is_none();
                 let (module, ()) = self
                     .in_scope(ScopeKind::Module(name.value.clone()), |comp| {
                         comp.data_def(data, false, prelude)
@@ -45,7 +47,7 @@ impl Compiler {
                 self.next_global += 1;
                 let local = LocalName {
                     index: global_index,
-                    public: true,
+                    public,
                 };
                 let comment = comment.map(|text| DocComment::from(text.as_str()));
                 self.asm.add_binding_at(
@@ -57,6 +59,10 @@ impl Compiler {
                         ..Default::default()
                     },
                 );
+                // Register variant
+                if let Some(set) = self.scope.variant_set.filter(|_| variant) {
+                    (self.variant_modules).insert(global_index, (set, name.value.clone()));
+                }
                 // Add local
                 self.scope.names.insert(name.value.clone(), local);
                 (self.code_meta.global_references).insert(name.span.clone(), local.index);
@@ -192,8 +198,18 @@ impl Compiler {
         let mut variant_index = 0;
         if data.variant {
             let module_scope = self.higher_scopes.last_mut().unwrap_or(&mut self.scope);
-            variant_index = module_scope.data_variants;
-            module_scope.data_variants += 1;
+            let set = *module_scope.variant_set.get_or_insert_with(|| {
+                self.variant_sets.push(Vec::new());
+                self.variant_sets.len() - 1
+            });
+            let variants = &mut self.variant_sets[set];
+            variant_index = variants.len();
+            variants.push(
+                data.name
+                    .as_ref()
+                    .map(|n| n.value.clone())
+                    .unwrap_or_default(),
+            );
         }
 
         // Make getters
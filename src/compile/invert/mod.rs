@@ -8,6 +8,7 @@ use std::{
     error::Error,
     fmt,
     hash::{DefaultHasher, Hasher},
+    mem::replace,
 };
 
 use ecow::eco_vec;
@@ -189,6 +190,7 @@ pub enum InversionError {
     UnUnderExperimental,
     UnUnderSignature(Signature),
     ReduceFormat,
+    Explained(boxed::Box<Self>, EcoString),
 }
 
 pub type InversionResult<T = ()> = Result<T, InversionError>;
@@ -252,11 +254,21 @@ impl fmt::Display for InversionError {
                 "Only format functions with 2 arguments \
                 and text only between them can be inverted"
             ),
+            InversionError::Explained(inner, explanation) => write!(f, "{inner}\n{explanation}"),
         }
     }
 }
 
 impl InversionError {
+    /// Attach an explanation of why inversion failed
+    pub(crate) fn explained(self, explanation: impl Into<EcoString>) -> Self {
+        match self {
+            InversionError::Explained(inner, _) => {
+                InversionError::Explained(inner, explanation.into())
+            }
+            e => InversionError::Explained(e.into(), explanation.into()),
+        }
+    }
     fn func(self, f: &Function) -> Self {
         match self {
             InversionError::InnerFunc(mut ids, inner) => {
@@ -281,6 +293,88 @@ impl From<()> for InversionError {
 
 impl Error for InversionError {}
 
+/// A place where inversion got stuck
+#[derive(Debug, Clone)]
+pub(crate) struct InversionBlock {
+    /// The modifier whose kind of inversion was attempted
+    pub kind: Primitive,
+    /// The first node that no pattern could invert
+    pub node: Node,
+    /// The number of nodes that were left to invert
+    pub remaining: usize,
+    /// The number of patterns that were tried
+    pub tried: usize,
+    /// The patterns that matched partially, and why they failed
+    pub partial: Vec<(String, InversionError)>,
+}
+
+thread_local! {
+    static TRACE: RefCell<Option<Vec<InversionBlock>>> = const { RefCell::new(None) };
+}
+
+fn tracing() -> bool {
+    TRACE.with(|trace| trace.borrow().is_some())
+}
+
+/// Run some inversion while recording where it gets stuck
+///
+/// Inversion caches are bypassed while tracing so that every failure is recorded.
+/// Blocks are recorded innermost first.
+pub(crate) fn trace_inversion<T>(f: impl FnOnce() -> T) -> (T, Vec<InversionBlock>) {
+    let prev = TRACE.with(|trace| trace.borrow_mut().replace(Vec::new()));
+    let res = f();
+    let blocks = TRACE
+        .with(|trace| replace(&mut *trace.borrow_mut(), prev))
+        .unwrap_or_default();
+    (res, blocks)
+}
+
+/// Pattern attempts at a single position
+struct Attempts {
+    tracing: bool,
+    tried: usize,
+    partial: Vec<(String, InversionError)>,
+}
+
+impl Attempts {
+    fn new() -> Self {
+        Attempts {
+            tracing: tracing(),
+            tried: 0,
+            partial: Vec::new(),
+        }
+    }
+    fn failed(&mut self, pattern: &dyn fmt::Debug, e: &InversionError) {
+        if !self.tracing {
+            return;
+        }
+        self.tried += 1;
+        if *e != Generic {
+            self.partial.push((format!("{pattern:?}"), e.clone()));
+        }
+    }
+    fn blocked(self, kind: Primitive, nodes: &[Node]) {
+        if !self.tracing {
+            return;
+        }
+        let Some(node) = nodes.first() else {
+            return;
+        };
+        let block = InversionBlock {
+            kind,
+            node: node.clone(),
+            remaining: nodes.len(),
+            tried: self.tried,
+            partial: self.partial,
+        };
+        TRACE.with(|trace| {
+            if let Some(blocks) = &mut *trace.borrow_mut() {
+                blocks.push(block);
+            }
+        });
+    }
+}
+
 use ecow::{EcoString, EcoVec};
 use regex::Regex;
 use InversionError::Generic;
@@ -340,3 +434,24 @@ pub(crate) fn match_format_pattern(parts: EcoVec<EcoString>, env: &mut Uiua) ->
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn explain_inverse() {
+        use crate::*;
+        let code = "⌝⇌ 1 2";
+        let Err(e) = Compiler::new().load_str(code) else {
+            panic!("Anti reverse compiled");
+        };
+        assert!(!e.to_string().contains("Why inversion failed"));
+        let mut comp = Compiler::new();
+        let Err(e) = comp.explain_inverse(true).load_str(code) else {
+            panic!("Anti reverse compiled");
+        };
+        let message = e.to_string();
+        assert!(message.contains("Why inversion failed"), "{message}");
+        assert!(message.contains("got stuck on"), "{message}");
+        assert_eq!(comp.code_meta.inversion_explanations.len(), 1);
+    }
+}
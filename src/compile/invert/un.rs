@@ -40,17 +40,22 @@ pub fn un_inverse(input: &[Node], asm: &Assembly) -> InversionResult<Node> {
         node.hash_with_span(&mut hasher);
     }
     let hash = hasher.finish();
-    if let Some(cached) = CACHE.with(|cache| {
-        (cache.borrow_mut().get(&hash))
-            .filter(|node| {
-                // Bit of a hack
-                !node.as_ref().is_ok_and(|node| {
-                    node.iter()
-                        .any(|node| matches!(node, ImplPrim(MatchPattern, _)))
-                })
+    if let Some(cached) = (!tracing())
+        .then(|| {
+            CACHE.with(|cache| {
+                (cache.borrow_mut().get(&hash))
+                    .filter(|node| {
+                        // Bit of a hack
+                        !node.as_ref().is_ok_and(|node| {
+                            node.iter()
+                                .any(|node| matches!(node, ImplPrim(MatchPattern, _)))
+                        })
+                    })
+                    .cloned()
             })
-            .cloned()
-    }) {
+        })
+        .flatten()
+    {
         return cached;
     }
     let res = un_inverse_impl(input, asm);
@@ -63,6 +68,7 @@ fn un_inverse_impl(input: &[Node], asm: &Assembly) -> InversionResult<Node> {
     let mut curr = input;
     let mut error = Generic;
     'find_pattern: loop {
+        let mut attempts = Attempts::new();
         for pattern in UN_PATTERNS {
             match pattern.invert_extract(curr, asm) {
                 Ok((new, inv)) => {
@@ -75,9 +81,13 @@ fn un_inverse_impl(input: &[Node], asm: &Assembly) -> InversionResult<Node> {
                     curr = new;
                     continue 'find_pattern;
                 }
-                Err(e) => error = error.max(e),
+                Err(e) => {
+                    attempts.failed(pattern, &e);
+                    error = error.max(e)
+                }
             }
         }
+        attempts.blocked(Primitive::Un, curr);
         break;
     }
     Err(error)
@@ -95,7 +105,10 @@ fn anti_inverse(input: &[Node], asm: &Assembly, for_un: bool) -> InversionResult
         node.hash_with_span(&mut hasher);
     }
     let hash = hasher.finish();
-    if let Some(cached) = CACHE.with(|cache| cache.borrow_mut().get(&hash).cloned()) {
+    if let Some(cached) = (!tracing())
+        .then(|| CACHE.with(|cache| cache.borrow_mut().get(&hash).cloned()))
+        .flatten()
+    {
         return cached;
     }
     let res = anti_inverse_impl(input, asm, for_un);
@@ -112,6 +125,7 @@ fn anti_inverse_impl(mut input: &[Node], asm: &Assembly, for_un: bool) -> Invers
     let mut got_anti = false;
     let mut pre = Node::empty();
     let mut anti = Node::empty();
+    let mut attempts = Attempts::new();
     'find_anti: for s in 0..input.len() {
         error = Generic;
         let curr = &input[s..];
@@ -128,11 +142,15 @@ fn anti_inverse_impl(mut input: &[Node], asm: &Assembly, for_un: bool) -> Invers
                     got_anti = true;
                     break 'find_anti;
                 }
-                Err(e) => error = error.max(e),
+                Err(e) => {
+                    attempts.failed(pattern, &e);
+                    error = error.max(e)
+                }
             }
         }
     }
     if !got_anti {
+        attempts.blocked(Primitive::Anti, input);
         return Err(error);
     }
 
@@ -256,6 +274,7 @@ pub static ANTI_PATTERNS: &[&dyn InvertPattern] = &[
     &(Select, AntiSelect),
     &(Pick, AntiPick),
     &(Base, AntiBase),
+    &(Modulus, AntiModulus),
     &MatrixDivPat,
     &NoUnder(AntiCouplePat),
     &AntiFillPat,
@@ -1020,6 +1039,7 @@ inverse!(PrimPat, input, _, Prim(prim, span), {
         Reverse => Prim(Reverse, span),
         Transpose => ImplPrim(TransposeN(-1), span),
         Bits => ImplPrim(UnBits, span),
+        PackBits => ImplPrim(UnPackBits, span),
         Couple => ImplPrim(UnCouple, span),
         Box => ImplPrim(UnBox, span),
         Where => ImplPrim(UnWhere, span),
@@ -1035,11 +1055,14 @@ inverse!(PrimPat, input, _, Prim(prim, span), {
         AudioEncode => ImplPrim(AudioDecode, span),
         ImageEncode => ImplPrim(ImageDecode, span),
         Sys(SysOp::Clip) => ImplPrim(UnClip, span),
+        Sys(SysOp::ClipWrite) => Prim(Sys(SysOp::Clip), span),
         Sys(SysOp::RawMode) => ImplPrim(UnRawMode, span),
         Json => ImplPrim(UnJson, span),
         Binary => ImplPrim(UnBinary, span),
         Csv => ImplPrim(UnCsv, span),
         Xlsx => ImplPrim(UnXlsx, span),
+        Ini => ImplPrim(UnIni, span),
+        Dotenv => ImplPrim(UnDotenv, span),
         Fft => ImplPrim(UnFft, span),
         DateTime => ImplPrim(UnDatetime, span),
         Trace => ImplPrim(
@@ -1062,6 +1085,7 @@ inverse!(ImplPrimPat, input, _, ImplPrim(prim, span), {
         Acos => ImplPrim(Cos, span),
         TransposeN(n) => ImplPrim(TransposeN(-n), span),
         UnWhere => Prim(Where, span),
+        UnPackBits => Prim(PackBits, span),
         UnUtf8 => Prim(Utf8, span),
         Utf16 => ImplPrim(UnUtf16, span),
         UnUtf16 => ImplPrim(Utf16, span),
@@ -1081,6 +1105,8 @@ inverse!(ImplPrimPat, input, _, ImplPrim(prim, span), {
         UnBinary => Prim(Binary, span),
         UnCsv => Prim(Csv, span),
         UnXlsx => Prim(Xlsx, span),
+        UnIni => Prim(Ini, span),
+        UnDotenv => Prim(Dotenv, span),
         UnFft => Prim(Fft, span),
         ImageDecode => Prim(ImageEncode, span),
         GifDecode => Prim(GifEncode, span),
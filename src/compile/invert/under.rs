@@ -48,8 +48,9 @@ fn under_inverse(
         node.hash_with_span(&mut hasher);
     }
     let hash = hasher.finish();
-    if let Some(cached) =
-        CACHE.with(|cache| cache.borrow_mut().get(&(hash, g_sig, inverse)).cloned())
+    if let Some(cached) = (!tracing())
+        .then(|| CACHE.with(|cache| cache.borrow_mut().get(&(hash, g_sig, inverse)).cloned()))
+        .flatten()
     {
         return cached;
     }
@@ -76,6 +77,7 @@ fn under_inverse_impl(
     let mut curr = input;
     let mut error = Generic;
     'find_pattern: loop {
+        let mut attempts = Attempts::new();
         for pattern in UNDER_PATTERNS {
             match pattern.under_extract(curr, g_sig, inverse, asm) {
                 Ok((new, bef, aft)) => {
@@ -91,9 +93,13 @@ fn under_inverse_impl(
                     curr = new;
                     continue 'find_pattern;
                 }
-                Err(e) => error = error.max(e),
+                Err(e) => {
+                    attempts.failed(pattern, &e);
+                    error = error.max(e)
+                }
             }
         }
+        attempts.blocked(Primitive::Under, curr);
         break;
     }
     Err(error)
@@ -101,6 +107,7 @@ fn under_inverse_impl(
 
 static UNDER_PATTERNS: &[&dyn UnderPattern] = &[
     &CustomPat,
+    &MaybeVal(CustomUnderPat),
     &OnPat,
     &BothPat,
     &Trivial,
@@ -244,6 +251,8 @@ static UNDER_PATTERNS: &[&dyn UnderPattern] = &[
     &MaybeVal(Store1Copy(Sys(SysOp::TcpAccept), Sys(SysOp::Close))),
     &MaybeVal(Store1Copy(Sys(SysOp::TcpListen), Sys(SysOp::Close))),
     &MaybeVal(Store1Copy(Sys(SysOp::TlsListen), Sys(SysOp::Close))),
+    &MaybeVal(Store1Copy(Sys(SysOp::OscListen), Sys(SysOp::Close))),
+    &MaybeVal(Store1Copy(Sys(SysOp::MqttConnect), Sys(SysOp::Close))),
     &MaybeVal(Stash(1, Sys(SysOp::FReadAllStr), Sys(SysOp::FWriteAll))),
     &MaybeVal(Stash(1, Sys(SysOp::FReadAllBytes), Sys(SysOp::FWriteAll))),
     &MaybeVal((
@@ -635,6 +644,22 @@ under!(
     }
 );
 
+// An explicit under inverse takes precedence over
+// anti-inverting the function with a constant argument
+under!((CustomUnderPat, input, g_sig, inverse, asm), {
+    let has_under = |node: &Node| matches!(node, CustomInverse(cust, _) if cust.under.is_some());
+    match input {
+        [node, ..] if has_under(node) => CustomPat.under_extract(input, g_sig, inverse, asm),
+        [Call(f, _), input @ ..] if has_under(&asm[f]) => {
+            let (before, after) = asm[f]
+                .under_inverse(g_sig, inverse, asm)
+                .map_err(|e| e.func(f))?;
+            Ok((input, before, after))
+        }
+        _ => generic(),
+    }
+});
+
 under!(DupPat, input, g_sig, inverse, asm, Prim(Dup, dup_span), {
     let dyadic_i = (0..=input.len())
         .find(|&i| nodes_clean_sig(&input[..i]).is_some_and(|sig| sig == (2, 1)))
@@ -5,6 +5,8 @@ pub(crate) mod invert;
 mod modifier;
 pub(crate) mod optimize;
 mod pre_eval;
+mod reflect;
+mod suggest;
 
 use std::{
     cell::RefCell,
@@ -18,6 +20,7 @@ use std::{
     path::{Path, PathBuf},
     slice,
     sync::Arc,
+    time::Duration,
 };
 
 use ecow::{eco_vec, EcoString, EcoVec};
@@ -33,11 +36,13 @@ use crate::{
     lex::{CodeSpan, Sp, Span},
     lsp::{CodeMeta, ImportSrc, SetInverses, SigDecl},
     parse::{flip_unsplit_lines, max_placeholder, parse, split_words},
-    Array, ArrayLen, Assembly, BindingKind, BindingMeta, Boxed, CustomInverse, Diagnostic,
-    DiagnosticKind, DocComment, DocCommentSig, Function, FunctionId, GitTarget, Ident,
-    ImplPrimitive, InputSrc, IntoInputSrc, IntoSysBackend, Node, PrimClass, Primitive, Purity,
-    RunMode, SemanticComment, SigNode, Signature, SysBackend, Uiua, UiuaError, UiuaErrorKind,
-    UiuaResult, Value, CONSTANTS, EXAMPLE_UA, SUBSCRIPT_DIGITS, VERSION,
+    sys::AssetSys,
+    types::check_types,
+    Array, ArrayLen, Assembly, BindingKind, BindingMeta, Boxed, ConstantValue, CustomInverse,
+    Diagnostic, DiagnosticKind, DocComment, DocCommentSig, Function, FunctionId, GitTarget, Ident,
+    ImplPrimitive, InputSrc, IntoInputSrc, IntoSysBackend, MemoryBackend, Node, PrimClass,
+    Primitive, Purity, RunMode, SemanticComment, SigNode, Signature, SysBackend, Uiua, UiuaError,
+    UiuaErrorKind, UiuaResult, Value, CONSTANTS, EXAMPLE_UA, SUBSCRIPT_DIGITS, VERSION,
 };
 pub use pre_eval::PreEvalMode;
 
@@ -66,6 +71,8 @@ pub struct Compiler {
     code_macros: HashMap<usize, CodeMacro>,
     /// The depth of compile-time evaluation
     comptime_depth: usize,
+    /// The depth of macro expansion
+    macro_depth: usize,
     /// Whether the compiler is in a try
     in_try: bool,
     /// Accumulated errors
@@ -84,6 +91,20 @@ pub struct Compiler {
     macro_env: Uiua,
     /// Start addresses
     start_addrs: Vec<usize>,
+    /// The names of the variants in each module that has them
+    variant_sets: Vec<Vec<Ident>>,
+    /// Map variant modules' global indices to their variant set and name
+    variant_modules: HashMap<usize, (usize, Ident)>,
+    /// The names of the enabled features
+    features: Vec<EcoString>,
+    /// Files that can be read by code run at compile time
+    assets: Arc<MemoryBackend>,
+    /// Whether to statically check types and shapes
+    type_check: bool,
+    /// Whether to explain why inversions fail
+    explain_inverse: bool,
+    /// Whether to record the stack after each top-level line
+    record_lines: bool,
 }
 
 impl Default for Compiler {
@@ -101,6 +122,7 @@ impl Default for Compiler {
             index_macros: HashMap::new(),
             code_macros: HashMap::new(),
             comptime_depth: 0,
+            macro_depth: 0,
             in_try: false,
             errors: Vec::new(),
             deprecated_prim_errors: HashSet::new(),
@@ -110,6 +132,13 @@ impl Default for Compiler {
             pre_eval_mode: PreEvalMode::default(),
             macro_env: Uiua::default(),
             start_addrs: Vec::new(),
+            variant_sets: Vec::new(),
+            variant_modules: HashMap::new(),
+            features: Vec::new(),
+            assets: Arc::new(MemoryBackend::new()),
+            type_check: false,
+            explain_inverse: false,
+            record_lines: false,
         }
     }
 }
@@ -180,8 +209,8 @@ pub(crate) struct Scope {
     comment: Option<EcoString>,
     /// Map local names to global indices
     names: IndexMap<Ident, LocalName>,
-    /// Number of named data variants
-    data_variants: usize,
+    /// The index of this scope's set of data variants, if it has any
+    variant_set: Option<usize>,
     /// Whether to allow experimental features
     pub experimental: bool,
     /// Whether an error has been emitted for experimental features
@@ -225,7 +254,7 @@ impl Default for Scope {
             file_path: None,
             comment: None,
             names: IndexMap::new(),
-            data_variants: 0,
+            variant_set: None,
             experimental: false,
             experimental_error: false,
             fill_sig_error: false,
@@ -275,6 +304,14 @@ impl Compiler {
     pub fn code_meta_mut(&mut self) -> &mut CodeMeta {
         &mut self.code_meta
     }
+    /// Get the module made by the current scope
+    pub(crate) fn scope_module(&self) -> Module {
+        Module {
+            comment: self.scope.comment.clone(),
+            names: self.scope.names.clone(),
+            experimental: self.scope.experimental,
+        }
+    }
     /// Take a completed assembly from the compiler
     pub fn finish(&mut self) -> Assembly {
         take(&mut self.asm)
@@ -289,6 +326,16 @@ impl Compiler {
         self.pre_eval_mode = mode;
         self
     }
+    /// Limit the execution duration of code run at compile time
+    pub fn execution_limit(&mut self, limit: Duration) -> &mut Self {
+        self.macro_env.rt.execution_limit = Some(limit.as_secs_f64());
+        self
+    }
+    /// Limit the size of each array created by code run at compile time
+    pub fn array_size_limit(&mut self, bytes: usize) -> &mut Self {
+        self.macro_env.rt.array_size_limit = Some(bytes);
+        self
+    }
     /// Set whether to print diagnostics as they are encountered
     ///
     /// If this is set to false, diagnostics will be accumulated and can be retrieved with [`Compiler::take_diagnostics`]
@@ -298,6 +345,37 @@ impl Compiler {
         self.print_diagnostics = print_diagnostics;
         self
     }
+    /// Set whether to statically check types and shapes
+    ///
+    /// Mismatches that will certainly cause errors at runtime are emitted as warning diagnostics.
+    /// The inferred types are recorded in the [`CodeMeta`].
+    ///
+    /// Defaults to false
+    pub fn type_check(&mut self, type_check: bool) -> &mut Self {
+        self.type_check = type_check;
+        self
+    }
+    /// Set whether to explain why inversions fail
+    ///
+    /// When an [`Primitive::Un`], [`Primitive::Anti`], or [`Primitive::Under`] inversion fails,
+    /// its error will say which nodes could not be inverted and which inversion patterns partially matched.
+    /// The explanations are also recorded in the [`CodeMeta`].
+    ///
+    /// Defaults to false
+    pub fn explain_inverse(&mut self, explain_inverse: bool) -> &mut Self {
+        self.explain_inverse = explain_inverse;
+        self
+    }
+    /// Set whether to record the stack after each top-level line of the main file
+    ///
+    /// The recorded stacks can be retrieved with [`Uiua::take_line_history`].
+    /// Lines are not evaluated together at compile time when this is enabled.
+    ///
+    /// Defaults to false
+    pub fn record_lines(&mut self, record_lines: bool) -> &mut Self {
+        self.record_lines = record_lines;
+        self
+    }
     /// Set the run mode
     pub fn mode(&mut self, mode: RunMode) -> &mut Self {
         self.mode = mode;
@@ -308,6 +386,24 @@ impl Compiler {
         self.scope.experimental = experimental;
         self
     }
+    /// Set the enabled features
+    ///
+    /// These can be checked with the `Features` constant
+    pub fn features<S: Into<EcoString>>(
+        &mut self,
+        features: impl IntoIterator<Item = S>,
+    ) -> &mut Self {
+        self.features = features.into_iter().map(Into::into).collect();
+        self
+    }
+    /// Add a file that can be read by code run at compile time
+    ///
+    /// Code run with `comptime` reads assets as files before looking in the real filesystem.
+    /// The values it produces are baked into the assembly, so the assets are not needed at runtime.
+    pub fn asset(&mut self, path: impl AsRef<Path>, contents: impl Into<Vec<u8>>) -> &mut Self {
+        self.assets.set_file(path, contents);
+        self
+    }
     /// Get the backend
     pub fn backend(&self) -> Arc<dyn SysBackend> {
         self.macro_env.rt.backend.clone()
@@ -429,6 +525,12 @@ impl Compiler {
         }
         // dbg!(&self.asm.root);
 
+        // Check types
+        if self.type_check && matches!(res, Ok(Ok(_))) {
+            let root = self.asm.root.clone();
+            self.check_types(&root.as_slice()[node_start..]);
+        }
+
         // Print diagnostics
         if self.print_diagnostics {
             for diagnostic in self.take_diagnostics() {
@@ -621,7 +723,13 @@ code:
                 once(&line).chain(&lines).any(|line| {
                     line.iter()
                         .find(|w| w.value.is_code())
-                        .is_some_and(|w| matches!(w.value, Word::Primitive(Primitive::Assert)))
+                        .is_some_and(|w| match &w.value {
+                            Word::Primitive(Primitive::Assert) => true,
+                            Word::Modified(m) => {
+                                matches!(m.modifier.value, Modifier::Primitive(Primitive::Property))
+                            }
+                            _ => false,
+                        })
                 })
             };
             if line.is_empty()
@@ -653,6 +761,7 @@ code:
                     // Update scope stack height
                     if let Ok(height) = &mut self.scope.stack_height {
                         *height = (*height + sig.outputs).saturating_sub(sig.args);
+                        (self.code_meta.stack_heights).insert(span.clone(), *height);
                         // Compile test assert
                         if self.mode != RunMode::Normal
                             && !from_macro
@@ -661,13 +770,18 @@ code:
                                 .any(|sc| sc.kind == ScopeKind::File(FileScopeKind::Git))
                         {
                             let test_assert = line_node
-                                .last_mut_recursive(&mut self.asm, |node| {
-                                    if let &mut Node::Prim(Primitive::Assert, span) = node {
+                                .last_mut_recursive(&mut self.asm, |node| match node {
+                                    &mut Node::Prim(Primitive::Assert, span) => {
                                         *node = Node::ImplPrim(ImplPrimitive::TestAssert, span);
                                         true
-                                    } else {
-                                        false
                                     }
+                                    Node::Mod(Primitive::Property, args, span) => {
+                                        let (args, span) = (take(args), *span);
+                                        *node =
+                                            Node::ImplMod(ImplPrimitive::TestProperty, args, span);
+                                        true
+                                    }
+                                    _ => false,
                                 })
                                 .unwrap_or(false);
                             if test_assert {
@@ -694,6 +808,10 @@ code:
                         // push nodes, followed by the current line
                         let mut node = Node::from(&self.asm.root[self.asm.root.len() - sig.args..]);
                         node.extend(line_node.iter().cloned());
+                        // Check types before they are lost to pre-evaluation
+                        if self.type_check {
+                            self.check_types(node.as_slice());
+                        }
                         if let Some((node, errs)) = self.pre_eval(&node) {
                             self.errors.extend(errs);
                             // Track top-level values
@@ -705,7 +823,7 @@ code:
                                         _ => unreachable!(),
                                     })
                                     .collect();
-                                self.code_meta.top_level_values.insert(span, vals);
+                                self.code_meta.top_level_values.insert(span.clone(), vals);
                             }
                             // Truncate root
                             self.asm.root.truncate(self.asm.root.len() - sig.args);
@@ -715,9 +833,13 @@ code:
                     }
                 }
                 Err(e) if matches!(e.kind, SigCheckErrorKind::LoopVariable { .. }) => {
-                    self.scope.stack_height = Err(span.sp(e))
+                    self.scope.stack_height = Err(span.clone().sp(e))
                 }
-                Err(e) => self.add_error(span, e),
+                Err(e) => self.add_error(span.clone(), e),
+            }
+            if self.record_lines && !from_macro && self.higher_scopes.is_empty() {
+                let span = self.add_span(span);
+                line_node.push(Node::ImplPrim(ImplPrimitive::RecordLine, span));
             }
             self.asm.root.push(line_node)
         }
@@ -778,15 +900,23 @@ code:
     pub(crate) fn import_module(&mut self, path_str: &str, span: &CodeSpan) -> UiuaResult<PathBuf> {
         // Resolve path
         let (path, file_kind) = if let Some(mut url) = path_str.trim().strip_prefix("git:") {
-            if url.contains("branch:") && url.contains("commit:") {
+            if ["branch:", "tag:", "commit:"]
+                .iter()
+                .filter(|s| url.contains(*s))
+                .count()
+                > 1
+            {
                 return Err(self.error(
                     span.clone(),
-                    "Cannot specify both branch and commit in git import",
+                    "Cannot specify more than one of branch, tag, and commit in git import",
                 ));
             }
             let target = if let Some((a, b)) = url.split_once("branch:") {
                 url = a;
                 GitTarget::Branch(b.trim().into())
+            } else if let Some((a, b)) = url.split_once("tag:") {
+                url = a;
+                GitTarget::Tag(b.trim().into())
             } else if let Some((a, b)) = url.split_once("commit:") {
                 url = a;
                 GitTarget::Commit(b.trim().into())
@@ -825,6 +955,20 @@ code:
                 .load_git_module(&url, target)
                 .map_err(|e| self.error(span.clone(), e))?;
             (path, FileScopeKind::Git)
+        } else if let Some(name) = path_str.trim().strip_prefix("pkg:") {
+            // Package import
+            let name = name.trim();
+            let path = Path::new("uiua-packages").join(name).join("lib.ua");
+            if !self.backend().file_exists(&path.to_string_lossy()) {
+                return Err(self.error(
+                    span.clone(),
+                    format!("Package `{name}` has not been fetched. Run `uiua fetch` to fetch it."),
+                ));
+            }
+            self.code_meta
+                .import_srcs
+                .insert(span.clone(), ImportSrc::File(path.clone()));
+            (path, FileScopeKind::Git)
         } else {
             // Normal import
             let path = self.resolve_import_path(Path::new(path_str));
@@ -1307,7 +1451,12 @@ code:
                     Ok(mut sig) => {
                         // Validate signature
                         if let Some(declared_sig) = arr.signature {
-                            inner = self.force_sig(inner, declared_sig.value, &declared_sig.span);
+                            inner = self.force_sig(
+                                inner,
+                                declared_sig.value,
+                                &declared_sig.span,
+                                &word.span,
+                            );
                             sig = declared_sig.value;
                         }
                         if sig.outputs == 0 && any_contents {
@@ -1432,7 +1581,16 @@ code:
             }
         })
     }
-    fn force_sig(&mut self, mut node: Node, new_sig: Signature, span: &CodeSpan) -> Node {
+    /// Make a node have a declared signature
+    ///
+    /// `body_span` is the span of the code whose signature was inferred.
+    fn force_sig(
+        &mut self,
+        mut node: Node,
+        new_sig: Signature,
+        span: &CodeSpan,
+        body_span: &CodeSpan,
+    ) -> Node {
         let Ok(sig) = node.sig() else {
             return node;
         };
@@ -1441,7 +1599,7 @@ code:
         }
         let delta = sig.outputs as isize - sig.args as isize;
         let new_delta = new_sig.outputs as isize - new_sig.args as isize;
-        match delta.cmp(&new_delta) {
+        let message = match delta.cmp(&new_delta) {
             Ordering::Equal => {
                 if sig.args < new_sig.args {
                     let spandex = self.add_span(span.clone());
@@ -1451,11 +1609,7 @@ code:
                     }
                     node.prepend(dip);
                 }
-                self.emit_diagnostic(
-                    format!("Signature mismatch: declared {new_sig} but inferred {sig}"),
-                    DiagnosticKind::Warning,
-                    span.clone(),
-                );
+                format!("Signature mismatch: declared {new_sig} but inferred {sig}")
             }
             Ordering::Less => {
                 let diff = (new_delta - delta).unsigned_abs();
@@ -1467,15 +1621,11 @@ code:
                     extra = Node::Mod(Primitive::Dip, eco_vec![extra.sig_node().unwrap()], spandex);
                 }
                 node.push(extra);
-                self.emit_diagnostic(
-                    format!(
-                        "Signature mismatch: declared {new_sig} but inferred {sig}. \
-                        {diff} debug output{} will be generated.",
-                        if diff == 1 { "" } else { "s" }
-                    ),
-                    DiagnosticKind::Warning,
-                    span.clone(),
-                );
+                format!(
+                    "Signature mismatch: declared {new_sig} but inferred {sig}. \
+                    {diff} debug output{} will be generated.",
+                    if diff == 1 { "" } else { "s" }
+                )
             }
             Ordering::Greater => {
                 let diff = (delta - new_delta).unsigned_abs();
@@ -1486,16 +1636,19 @@ code:
                     pops = Node::Mod(Primitive::Dip, eco_vec![pops.sig_node().unwrap()], spandex);
                 }
                 node.push(pops);
-                self.emit_diagnostic(
-                    format!(
-                        "Signature mismatch: declared {new_sig} but inferred {sig}. \
-                        Additional arguments will be popped."
-                    ),
-                    DiagnosticKind::Warning,
-                    span.clone(),
-                );
+                format!(
+                    "Signature mismatch: declared {new_sig} but inferred {sig}. \
+                    Additional arguments will be popped."
+                )
             }
-        }
+        };
+        let inputs = self.asm.inputs.clone();
+        let diagnostic = Diagnostic::new(message, span.clone(), DiagnosticKind::Warning, inputs)
+            .with_info(
+                body_span.clone(),
+                format!("{sig} was inferred from this code"),
+            );
+        self.emit_diagnostic_impl(diagnostic);
         node
     }
     #[must_use]
@@ -1538,10 +1691,7 @@ code:
         } else if r.path.is_empty() && CONSTANTS.iter().any(|def| def.name == r.name.value) {
             Ok(None)
         } else {
-            Err(self.error(
-                r.name.span.clone(),
-                format!("Unknown identifier `{}`", r.name.value),
-            ))
+            Err(self.unknown_identifier(r.name.span.clone(), &r.name.value))
         }
     }
     fn find_name(&self, name: &str, skip_local: bool) -> Option<LocalName> {
@@ -1711,13 +1861,16 @@ code:
                 self.code_meta
                     .constant_references
                     .insert(span.clone().sp(ident));
-                Node::Push(
-                    constant
-                        .value
-                        .resolve(self.scope_file_path(), &*self.backend()),
-                )
+                Node::Push(match &*constant.value {
+                    ConstantValue::Features => {
+                        let features = self.features.iter().map(|f| Boxed(f.as_str().into()));
+                        Array::<Boxed>::from_iter(features).into()
+                    }
+                    ConstantValue::Experimental => self.scope.experimental.into(),
+                    value => value.resolve(self.scope_file_path(), &*self.backend()),
+                })
             } else {
-                return Err(self.error(span, format!("Unknown identifier `{ident}`")));
+                return Err(self.unknown_identifier(span, &ident));
             },
         )
     }
@@ -1738,6 +1891,7 @@ code:
             BindingKind::Import(path) => {
                 if let Some(local) = self.imports.get(&path).and_then(|m| m.names.get("Call")) {
                     self.code_meta.global_references.remove(&span);
+                    (self.code_meta.called_modules).insert(span.clone(), index);
                     self.code_meta
                         .global_references
                         .insert(span.clone(), local.index);
@@ -1754,6 +1908,7 @@ code:
             BindingKind::Module(m) => {
                 if let Some(local) = m.names.get("Call").or_else(|| m.names.get("New")) {
                     self.code_meta.global_references.remove(&span);
+                    (self.code_meta.called_modules).insert(span.clone(), index);
                     self.code_meta
                         .global_references
                         .insert(span.clone(), local.index);
@@ -1784,7 +1939,7 @@ code:
         let sig = match root.sig() {
             Ok(mut sig) => {
                 if let Some(declared_sig) = &func.signature {
-                    root = self.force_sig(root, declared_sig.value, &declared_sig.span);
+                    root = self.force_sig(root, declared_sig.value, &declared_sig.span, &span);
                     sig = declared_sig.value;
                 }
                 Some(sig)
@@ -1943,6 +2098,8 @@ code:
                                 | Primitive::Rows
                                 | Primitive::Each
                                 | Primitive::Inventory
+                                | Primitive::Along
+                                | Primitive::Decimal
                                 | Primitive::Stencil
                         ) {
                             self.add_error(
@@ -2050,6 +2207,33 @@ code:
                             self.primitive(Primitive::Utf8, span)
                         }
                     },
+                    Primitive::Convolve => match n {
+                        0 => self.primitive(prim, span),
+                        1 => Node::ImplPrim(ImplPrimitive::ConvolveClamp, self.add_span(span)),
+                        2 => Node::ImplPrim(ImplPrimitive::ConvolveWrap, self.add_span(span)),
+                        _ => {
+                            self.add_error(
+                                span.clone(),
+                                format!("{} subscript must be 0, 1, or 2", prim.format()),
+                            );
+                            self.primitive(prim, span)
+                        }
+                    },
+                    Primitive::Signed | Primitive::Unsigned => {
+                        if !(1..=53).contains(&n) {
+                            self.add_error(
+                                span.clone(),
+                                format!("{} subscript must be between 1 and 53", prim.format()),
+                            );
+                        }
+                        let bits = n.clamp(1, 53) as u8;
+                        let prim = if prim == Primitive::Signed {
+                            ImplPrimitive::SignedN(bits)
+                        } else {
+                            ImplPrimitive::UnsignedN(bits)
+                        };
+                        Node::ImplPrim(prim, self.add_span(span))
+                    }
                     Primitive::Couple => match n {
                         1 => self.primitive(Primitive::Fix, span),
                         2 => self.primitive(Primitive::Couple, span),
@@ -2256,6 +2440,18 @@ impl Compiler {
         let inputs = self.asm.inputs.clone();
         self.emit_diagnostic_impl(Diagnostic::new(message.into(), span, kind, inputs));
     }
+    fn check_types(&mut self, nodes: &[Node]) {
+        let check = check_types(nodes, &self.asm);
+        for (span, ty) in check.types {
+            if let (Some(ty), Span::Code(span)) = (ty, &self.asm.spans[span]) {
+                self.code_meta.inferred_types.insert(span.clone(), ty);
+            }
+        }
+        for (span, message) in check.mismatches {
+            let span = self.asm.spans[span].clone();
+            self.emit_diagnostic(message, DiagnosticKind::Warning, span);
+        }
+    }
     fn emit_diagnostic_impl(&mut self, diagnostic: Diagnostic) {
         if self.print_diagnostics {
             println!("{}", diagnostic.report()); // Allow println
@@ -2491,3 +2687,23 @@ fn line_sig(line: &[Sp<Word>]) -> Option<Sp<DocCommentSig>> {
             _ => None,
         })
 }
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn signature_mismatch_note() {
+        use crate::*;
+        let mut comp = Compiler::new();
+        comp.load_str("F ← |2 +1\nF 1 2").unwrap();
+        let diags = comp.take_diagnostics();
+        let [diag] = diags.iter().collect::<Vec<_>>()[..] else {
+            panic!("Expected 1 diagnostic, got {diags:?}");
+        };
+        let notes: Vec<_> = (diag.info.iter())
+            .map(|info| (info.value.as_str(), info.span.to_string()))
+            .collect();
+        assert_eq!(notes, [("|1.1 was inferred from this code", "1:8".into())]);
+        let report = diag.report().color(false).to_string();
+        assert!(report.contains("Info: |1.1 was inferred from this code"));
+    }
+}
@@ -2,13 +2,167 @@
 #![allow(clippy::redundant_closure_call)]
 
 use super::*;
-use algebra::{derivative, integral};
-use invert::InversionError;
+use crate::algorithm::{decimal::MAX_DECIMAL_PLACES, dual::check_differentiable};
+use crate::lsp::MathNotation;
+use algebra::{derivative, integral, math_notation, MathFormat};
+use invert::{trace_inversion, InversionBlock, InversionError, InversionResult};
 use pre_eval::PreEvalMode;
+use reflect::reify;
 
 const MAX_COMPTIME_DEPTH: usize = if cfg!(debug_assertions) { 5 } else { 20 };
 
 impl Compiler {
+    /// Run an inversion, explaining why it failed if [`Compiler::explain_inverse`] is enabled
+    fn explain_inversion<T>(
+        &mut self,
+        span: &CodeSpan,
+        f: impl FnOnce(&Assembly) -> InversionResult<T>,
+    ) -> InversionResult<T> {
+        if !self.explain_inverse {
+            return f(&self.asm);
+        }
+        let (res, blocks) = trace_inversion(|| f(&self.asm));
+        res.map_err(|e| {
+            let Some(explanation) = self.inversion_explanation(&blocks) else {
+                return e;
+            };
+            (self.code_meta.inversion_explanations).insert(span.clone(), explanation.clone());
+            e.explained(explanation)
+        })
+    }
+    fn inversion_explanation(&self, blocks: &[InversionBlock]) -> Option<String> {
+        const MAX_BLOCKS: usize = 5;
+        // Outermost first
+        let mut seen = HashSet::new();
+        let blocks: Vec<_> = (blocks.iter().rev())
+            .filter(|block| seen.insert((block.kind, format!("{:?}", block.node), block.remaining)))
+            .collect();
+        if blocks.is_empty() {
+            return None;
+        }
+        let mut s = String::from("Why inversion failed:");
+        for block in blocks.iter().take(MAX_BLOCKS) {
+            let location = match block.node.span().and_then(|i| self.asm.spans.get(i)) {
+                Some(Span::Code(span)) => format!(" at {}", span.start),
+                _ => String::new(),
+            };
+            s.push_str(&format!(
+                "\n  {} got stuck on {:?}{location}, with {} node{} left to invert",
+                block.kind.format(),
+                block.node,
+                block.remaining,
+                if block.remaining == 1 { "" } else { "s" }
+            ));
+            if block.partial.is_empty() {
+                s.push_str(&format!(
+                    "\n    none of the {} patterns matched",
+                    block.tried
+                ));
+            } else {
+                s.push_str(&format!(
+                    "\n    {} of the {} patterns matched partially:",
+                    block.partial.len(),
+                    block.tried
+                ));
+                for (pattern, e) in &block.partial {
+                    let e = e.to_string();
+                    let e = e.lines().next().unwrap_or_default();
+                    s.push_str(&format!("\n      {pattern}: {e}"));
+                }
+            }
+        }
+        if blocks.len() > MAX_BLOCKS {
+            s.push_str(&format!("\n  ...and {} more", blocks.len() - MAX_BLOCKS));
+        }
+        Some(s)
+    }
+    /// Record a function and the result of a calculus operation on it in math notation
+    fn record_math_notation(&mut self, span: &CodeSpan, prim: Primitive, f: &Node, res: &Node) {
+        let notation = |format| -> Option<(String, String)> {
+            let f = math_notation(f, &self.asm, format).ok()?;
+            let res = math_notation(res, &self.asm, format).ok()?;
+            Some((f, res))
+        };
+        let (Some(text), Some(latex), Some(mathml)) = (
+            notation(MathFormat::Text),
+            notation(MathFormat::Latex),
+            notation(MathFormat::MathMl),
+        ) else {
+            return;
+        };
+        let integral = prim == Primitive::Integral;
+        let text = match text {
+            (f, res) if integral => format!("∫ {f} dx = {res} + C"),
+            (f, res) => format!("d/dx ({f}) = {res}"),
+        };
+        let latex = match latex {
+            (f, res) if integral => format!("\\int {f} \\, dx = {res} + C"),
+            (f, res) => format!("\\frac{{d}}{{dx}} \\left({f}\\right) = {res}"),
+        };
+        let mathml = match mathml {
+            (f, res) if integral => format!(
+                "<mo>∫</mo>{f}<mspace width=\"0.2em\"/><mi>d</mi><mi>x</mi>\
+                <mo>=</mo>{res}<mo>+</mo><mi>C</mi>"
+            ),
+            (f, res) => format!(
+                "<mfrac><mi>d</mi><mrow><mi>d</mi><mi>x</mi></mrow></mfrac>\
+                <mo>(</mo>{f}<mo>)</mo><mo>=</mo>{res}"
+            ),
+        };
+        let alttext = (latex.replace('&', "&amp;"))
+            .replace('"', "&quot;")
+            .replace('<', "&lt;");
+        let mathml = format!("<math display=\"block\" alttext=\"{alttext}\">{mathml}</math>");
+        (self.code_meta.math_notations).insert(span.clone(), MathNotation { text, mathml });
+    }
+    /// Warn if every branch of a [`Primitive::Try`] pack matches a variant
+    /// of the same module, but not all of that module's variants are matched
+    fn check_variant_exhaustiveness(&mut self, branches: &[Sp<Func>], span: &CodeSpan) {
+        let mut set = None;
+        let mut matched = HashSet::new();
+        for branch in branches {
+            let variant = (branch.value.lines.iter().flatten()).find_map(|word| {
+                let Word::Modified(m) = &word.value else {
+                    return None;
+                };
+                let (Modifier::Primitive(Primitive::Un), [operand]) =
+                    (&m.modifier.value, m.operands.as_slice())
+                else {
+                    return None;
+                };
+                let Word::Ref(r) = &operand.value else {
+                    return None;
+                };
+                let (_, local) = self.ref_local(r).ok()??;
+                self.variant_modules.get(&local.index)
+            });
+            // A branch that does not match a variant may handle any value
+            let Some((variant_set, name)) = variant else {
+                return;
+            };
+            if set.is_some_and(|set| set != *variant_set) {
+                return;
+            }
+            set = Some(*variant_set);
+            matched.insert(name.clone());
+        }
+        let Some(set) = set else {
+            return;
+        };
+        let missing: Vec<_> = (self.variant_sets[set].iter())
+            .filter(|name| !matched.contains(*name))
+            .map(|name| format!("`{name}`"))
+            .collect();
+        if missing.is_empty() {
+            return;
+        }
+        let message = format!(
+            "Pattern match is not exhaustive. Missing variant{} {}",
+            if missing.len() == 1 { "" } else { "s" },
+            missing.join(", ")
+        );
+        self.emit_diagnostic(message, DiagnosticKind::Warning, span.clone());
+    }
     fn desugar_function_pack(
         &mut self,
         modifier: &Sp<Modifier>,
@@ -83,8 +237,11 @@ impl Compiler {
                 self.modified(new, subscript)
             }
             Modifier::Primitive(
-                Primitive::Fork | Primitive::Bracket | Primitive::Try | Primitive::Fill,
+                prim @ (Primitive::Fork | Primitive::Bracket | Primitive::Try | Primitive::Fill),
             ) => {
+                if *prim == Primitive::Try {
+                    self.check_variant_exhaustiveness(&pack.branches, &modifier.span);
+                }
                 let mut branches = pack.branches.iter().cloned().rev();
                 let mut new = Modified {
                     modifier: modifier.clone(),
@@ -149,6 +306,18 @@ impl Compiler {
                             cust.un = Some(b.clone());
                         } else if a.sig.anti().is_some_and(|sig| sig == b.sig) {
                             cust.anti = Some(b.clone());
+                        } else if a.sig.args > 0 && b.sig.args == a.sig.args + a.sig.outputs {
+                            // The second function sets the first function's outputs
+                            // back into its arguments, like the undo part of ⍜⊡
+                            let span = self.add_span(modifier.span.clone());
+                            let mut before = Node::CopyToUnder(a.sig.args, span);
+                            before.push(a.node.clone());
+                            let mut after = Node::PopUnder(a.sig.args, span);
+                            after.push(b.node.clone());
+                            cust.under = Some((
+                                SigNode::new(a.sig, before),
+                                SigNode::new((a.sig.outputs, b.sig.outputs), after),
+                            ));
                         } else {
                             cust.under = Some((a.clone(), b.clone()));
                         }
@@ -605,39 +774,7 @@ impl Compiler {
                 let span = self.add_span(modified.modifier.span.clone());
                 Node::Mod(prim, eco_vec![sn], span)
             }
-            Backward => {
-                let (SigNode { mut node, sig }, _) = self.monadic_modifier_op(modified)?;
-                match sig.args {
-                    0 | 1 => {
-                        self.emit_diagnostic(
-                            format!(
-                                "Remove {} here, as it does nothing for {} functions",
-                                Backward.format(),
-                                if sig.args == 0 { "noadic" } else { "monadic" }
-                            ),
-                            DiagnosticKind::Style,
-                            modified.modifier.span.clone(),
-                        );
-                        node
-                    }
-                    2 => {
-                        let span = self.add_span(modified.modifier.span.clone());
-                        node.prepend(Node::Prim(Flip, span));
-                        node
-                    }
-                    _ => {
-                        self.add_error(
-                            modified.modifier.span.clone(),
-                            format!(
-                                "Currently, {}'s function may take at most, \
-                                2 arguments, but its signature is {sig}",
-                                Backward.format(),
-                            ),
-                        );
-                        node
-                    }
-                }
-            }
+            Backward => self.backward_modifier(modified)?,
             Content => {
                 let mut sn = self.monadic_modifier_op(modified)?.0;
                 let span = self.add_span(modified.modifier.span.clone());
@@ -649,42 +786,7 @@ impl Compiler {
                 );
                 sn.node
             }
-            Repeat => {
-                let (sn, span) = self.monadic_modifier_op(modified)?;
-                let spandex = self.add_span(modified.modifier.span.clone());
-                let mut node = if let Some((inv, inv_sig)) = sn
-                    .node
-                    .un_inverse(&self.asm)
-                    .ok()
-                    .and_then(|inv| inv.sig().ok().map(|sig| (inv, sig)))
-                    .filter(|(_, inv_sig)| sn.sig.is_compatible_with(*inv_sig))
-                {
-                    // If an inverse for repeat's function exists we use a special
-                    // implementation that allows for negative repeatition counts
-                    if sn.sig.inverse() != inv_sig {
-                        self.add_error(
-                            span,
-                            format!(
-                                "Repeated function's inverse must have \
-                                the inverse signature, but their signatures \
-                                are {} and {}",
-                                sn.sig, inv_sig
-                            ),
-                        )
-                    }
-                    Node::ImplMod(
-                        ImplPrimitive::RepeatWithInverse,
-                        eco_vec![sn, SigNode::new(inv_sig, inv)],
-                        spandex,
-                    )
-                } else {
-                    Node::Mod(Primitive::Repeat, eco_vec![sn], spandex)
-                };
-                if let Some(n) = subscript.and_then(|n| self.subscript_n(n, Repeat.format())) {
-                    node.prepend(Node::new_push(n.value));
-                }
-                node
-            }
+            Repeat => self.repeat_modifier(modified, subscript)?,
             Tuples => {
                 let (sn, _) = self.monadic_modifier_op(modified)?;
                 let span = self.add_span(modified.modifier.span.clone());
@@ -728,7 +830,8 @@ impl Compiler {
             Un => {
                 let (sn, span) = self.monadic_modifier_op(modified)?;
                 self.add_span(span.clone());
-                let normal = sn.un_inverse(&self.asm);
+                let normal =
+                    self.explain_inversion(&modified.modifier.span, |asm| sn.un_inverse(asm));
                 let cust = CustomInverse {
                     normal,
                     un: Some(sn),
@@ -739,254 +842,27 @@ impl Compiler {
             }
             Anti => {
                 let (sn, span) = self.monadic_modifier_op(modified)?;
-                match sn.node.anti_inverse(&self.asm) {
+                match self
+                    .explain_inversion(&modified.modifier.span, |asm| sn.node.anti_inverse(asm))
+                {
                     Ok(inv) => inv,
                     Err(e) => return Err(self.error(span, e)),
                 }
             }
-            Under => {
-                let (f, g, f_span, _) = self.dyadic_modifier_ops(modified)?;
-                let normal = {
-                    let (f_before, f_after) = f
-                        .node
-                        .under_inverse(g.sig, false, &self.asm)
-                        .map_err(|e| self.error(f_span.clone(), e))?;
-                    let mut node = f_before;
-                    node.push(g.node.clone());
-                    node.push(f_after);
-                    let sig = self.sig_of(&node, &f_span)?;
-                    SigNode::new(sig, node)
-                };
-                let span = self.add_span(modified.modifier.span.clone());
-                let un = if self.scope.experimental {
-                    if f.sig.args == f.sig.outputs {
-                        let (f_before, f_after) = f
-                            .node
-                            .under_inverse(g.sig, true, &self.asm)
-                            .map_err(|e| self.error(f_span.clone(), e))?;
-                        (g.node.un_inverse(&self.asm).ok())
-                            .map(|g_inv| -> UiuaResult<SigNode> {
-                                let mut node = f_before;
-                                node.push(g_inv);
-                                node.push(f_after);
-                                let sig = self.sig_of(&node, &f_span)?;
-                                Ok(SigNode::new(sig, node))
-                            })
-                            .transpose()?
-                    } else {
-                        let cust = CustomInverse::from(InversionError::UnUnderSignature(f.sig));
-                        Some(SigNode::new(
-                            normal.sig.inverse(),
-                            Node::CustomInverse(cust.into(), span),
-                        ))
-                    }
-                } else {
-                    let cust = CustomInverse::from(InversionError::UnUnderExperimental);
-                    Some(SigNode::new(
-                        normal.sig.inverse(),
-                        Node::CustomInverse(cust.into(), span),
-                    ))
-                };
-                let cust = CustomInverse {
-                    normal: Ok(normal),
-                    un,
-                    ..Default::default()
-                };
-                let span = self.add_span(modified.modifier.span.clone());
-                Node::CustomInverse(cust.into(), span)
-            }
-            Obverse => {
-                // Empty inverse case, where only one function is supplied
-                let (sn, span) = self.monadic_modifier_op(modified)?;
-                let spandex = self.add_span(span.clone());
-                let mut cust = CustomInverse {
-                    normal: Ok(sn.clone()),
-                    un: Some(SigNode::default()),
-                    anti: None,
-                    under: Some((sn.clone(), SigNode::default())),
-                    is_obverse: true,
-                };
-                if sn.sig == sn.sig.inverse() {
-                    cust.un = Some(sn.clone());
-                }
-                if sn.sig.anti() == Some(sn.sig) {
-                    cust.anti = Some(sn.clone());
-                }
-                let set_inverses = SetInverses {
-                    un: cust.un.is_some(),
-                    anti: cust.anti.is_some(),
-                    under: cust.under.is_some(),
-                };
-                self.code_meta
-                    .obverses
-                    .insert(modified.modifier.span.clone(), set_inverses);
-                if let Some(sig_decl) = self.code_meta.function_sigs.get_mut(&span) {
-                    sig_decl.set_inverses = set_inverses;
-                }
-                Node::CustomInverse(cust.into(), spandex)
-            }
-            Try => {
-                let in_try = replace(&mut self.in_try, true);
-                let nodes = self.dyadic_modifier_ops(modified);
-                self.in_try = in_try;
-                let (mut tried, mut handler, _, handler_span) = nodes?;
-
-                // Adjust handler signature if it is a noreturn function
-                if let [init @ .., Node::Prim(Assert, _)] = handler.node.as_slice() {
-                    let noreturn = match init {
-                        [.., Node::Push(val), Node::Prim(Dup | Flip, _)] if *val != 1 => true,
-                        [.., Node::Format(..), Node::Prim(Dup, _)] => true,
-                        [.., Node::Push(val), Node::Push(_)] if *val != 1 => true,
-                        [.., Node::Mod(Dip, args, _)]
-                            if args.len() == 1
-                                && matches!(&args[0].node, Node::Push(val) if *val != 1) =>
-                        {
-                            true
-                        }
-                        _ => false,
-                    };
-                    if noreturn {
-                        handler.sig.outputs = tried.sig.outputs;
-                    }
-                }
-
-                match tried.sig.outputs.cmp(&handler.sig.outputs) {
-                    Ordering::Equal => {}
-                    Ordering::Less => {
-                        tried.sig.args += handler.sig.outputs - tried.sig.outputs;
-                        tried.sig.outputs = handler.sig.outputs;
-                    }
-                    Ordering::Greater => {
-                        handler.sig.args += tried.sig.outputs - handler.sig.outputs;
-                        handler.sig.outputs = tried.sig.outputs;
-                    }
-                }
-
-                if handler.sig.args > tried.sig.args + 1 {
-                    self.add_error(
-                        handler_span.clone(),
-                        format!(
-                            "Handler function must have at most \
-                            one more argument than the tried function, \
-                            but their signatures are {} and \
-                            {} respectively.",
-                            handler.sig, tried.sig
-                        ),
-                    );
-                }
-
-                let span = self.add_span(modified.modifier.span.clone());
-                Node::Mod(Primitive::Try, eco_vec![tried, handler], span)
-            }
+            Under => self.under_modifier(modified)?,
+            Obverse => self.obverse_modifier(modified)?,
+            Try => self.try_modifier(modified)?,
             Switch => self.switch(
                 modified.code_operands().cloned().collect(),
                 modified.modifier.span.clone(),
             )?,
-            Fill => {
-                let mut operands = modified.code_operands().rev().cloned();
-
-                // Filled function
-                let mode = replace(&mut self.pre_eval_mode, PreEvalMode::Lsp);
-                let f = self.word_sig(operands.next().unwrap());
-                self.pre_eval_mode = mode;
-                let f = f?;
-
-                // Get-fill function
-                let fill_word = operands.next().unwrap();
-                let fill_span = fill_word.span.clone();
-                let fill = self.word_sig(fill_word)?;
-                if fill.sig.outputs > 1 && !self.scope.fill_sig_error {
-                    self.scope.fill_sig_error = true;
-                    self.add_error(
-                        fill_span,
-                        format!(
-                            "{} function can have at most 1 output, but its signature is {}",
-                            Primitive::Fill.format(),
-                            fill.sig
-                        ),
-                    );
-                }
-                let span = self.add_span(modified.modifier.span.clone());
-                Node::Mod(Primitive::Fill, eco_vec![fill, f], span)
-            }
+            Fill => self.fill_modifier(modified)?,
             Comptime => {
                 let word = modified.code_operands().next().unwrap().clone();
                 self.do_comptime(prim, word, &modified.modifier.span)?
             }
-            Each => {
-                // Each pervasive
-                let operand = modified.code_operands().next().unwrap().clone();
-                let op_span = operand.span.clone();
-                let full_span = modified.modifier.span.clone().merge(op_span);
-                let words_look_pervasive = subscript
-                    .as_ref()
-                    .map_or(true, |sub| sub.value == Subscript::N(0))
-                    && words_look_pervasive(slice::from_ref(&operand));
-                let sn = self.word_sig(operand)?;
-                if words_look_pervasive {
-                    self.emit_diagnostic(
-                        if let Some((prim, _)) = sn
-                            .node
-                            .as_flipped_primitive()
-                            .filter(|(prim, _)| prim.class().is_pervasive())
-                        {
-                            format!(
-                                "{} is pervasive, so {} is redundant here.",
-                                prim.format(),
-                                Each.format(),
-                            )
-                        } else {
-                            format!(
-                                "{m}'s function is pervasive, \
-                                so {m} is redundant here.",
-                                m = Each.format(),
-                            )
-                        },
-                        DiagnosticKind::Advice,
-                        full_span.clone(),
-                    );
-                }
-                let span = self.add_span(modified.modifier.span.clone());
-                if let Some(n) = subscript
-                    .and_then(|n| self.subscript_n_or_side(n))
-                    .filter(|i| i.value != 0)
-                {
-                    if n.value == -1 {
-                        Node::Mod(Rows, eco_vec![sn], span)
-                    } else {
-                        match n.value {
-                            SubNOrSide::N(n) => {
-                                Node::ImplMod(ImplPrimitive::EachSub(n), eco_vec![sn], span)
-                            }
-                            SubNOrSide::Side(side) => {
-                                let sub_span = self.add_span(n.span);
-                                let mut node = match side {
-                                    SubSide::Left => Node::Prim(Fix, sub_span),
-                                    SubSide::Right => match sn.sig.args {
-                                        0 => Node::empty(),
-                                        1 => Node::Prim(Fix, sub_span),
-                                        n => {
-                                            let mut node = Node::Prim(Fix, sub_span);
-                                            for _ in 1..n {
-                                                node = Node::Mod(
-                                                    Dip,
-                                                    eco_vec![node.sig_node().unwrap()],
-                                                    sub_span,
-                                                );
-                                            }
-                                            node
-                                        }
-                                    },
-                                };
-                                node.push(Node::Mod(Each, eco_vec![sn], span));
-                                node
-                            }
-                        }
-                    }
-                } else {
-                    Node::Mod(Each, eco_vec![sn], span)
-                }
-            }
+            Cfg => self.cfg_modifier(prim, modified)?,
+            Each => self.each_modifier(modified, subscript)?,
             prim @ (Rows | Inventory) => {
                 let (sn, _) = self.monadic_modifier_op(modified)?;
                 let span = self.add_span(modified.modifier.span.clone());
@@ -1037,60 +913,10 @@ impl Compiler {
                     Node::Mod(prim, eco_vec![sn], span)
                 }
             }
-            Table => {
-                // Normal table compilation, but get some diagnostics
-                let (sn, span) = self.monadic_modifier_op(modified)?;
-                match sn.sig.args {
-                    0 => self.emit_diagnostic(
-                        format!("{} of 0 arguments is redundant", Table.format()),
-                        DiagnosticKind::Advice,
-                        span,
-                    ),
-                    1 => self.emit_diagnostic(
-                        format!(
-                            "{} with 1 argument is just {rows}. \
-                            Use {rows} instead.",
-                            Table.format(),
-                            rows = Rows.format()
-                        ),
-                        DiagnosticKind::Advice,
-                        span,
-                    ),
-                    _ => {}
-                }
-
-                fn table_fork(sn: SigNode, table_span: usize, asm: &Assembly) -> Node {
-                    match sn.node {
-                        Node::Mod(Fork, args, fork_span)
-                            if (args.iter()).all(|arg| arg.node.is_pure(Purity::Pure, asm))
-                                && args.windows(2).all(|w| w[0].sig.args == w[1].sig.args) =>
-                        {
-                            let args: EcoVec<SigNode> = args
-                                .into_iter()
-                                .map(|arg| SigNode::new(arg.sig, table_fork(arg, table_span, asm)))
-                                .collect();
-                            Node::Mod(Fork, args, fork_span)
-                        }
-                        node => Node::Mod(Table, eco_vec![SigNode::new(sn.sig, node)], table_span),
-                    }
-                }
-                let table_span = self.add_span(modified.modifier.span.clone());
-                table_fork(sn, table_span, &self.asm)
-            }
-            Fold => {
-                let (sn, _) = self.monadic_modifier_op(modified)?;
-                if sn.sig.args <= sn.sig.outputs {
-                    self.experimental_error(&modified.modifier.span, || {
-                        format!(
-                            "{} with arguments ≤ outputs is experimental. To use it, \
-                            add `# Experimental!` to the top of the file.",
-                            prim.format()
-                        )
-                    });
-                }
-                let span = self.add_span(modified.modifier.span.clone());
-                Node::Mod(Fold, eco_vec![sn], span)
-            }
+            Along => self.along_modifier(modified, subscript)?,
+            Decimal => self.decimal_modifier(modified, subscript)?,
+            Table => self.table_modifier(modified)?,
+            Fold => self.fold_modifier(modified)?,
             prim @ (Spawn | Pool) => {
                 let recurses_before = self
                     .current_bindings
@@ -1111,76 +937,614 @@ impl Compiler {
                 let s = format_word(operand, &self.asm.inputs);
                 Node::new_push(s)
             }
-            Quote => {
-                let operand = modified.code_operands().next().unwrap().clone();
-                let node = self.do_comptime(prim, operand, &modified.modifier.span)?;
-                let code: String = match node {
-                    Node::Push(Value::Char(chars)) if chars.rank() == 1 => {
-                        chars.data.iter().collect()
-                    }
-                    Node::Push(Value::Char(chars)) => {
-                        return Err(self.error(
-                            modified.modifier.span.clone(),
-                            format!(
-                                "quote's argument compiled to a \
-                                rank {} array rather than a string",
-                                chars.rank()
-                            ),
-                        ))
-                    }
-                    Node::Push(value) => {
-                        return Err(self.error(
-                            modified.modifier.span.clone(),
-                            format!(
-                                "quote's argument compiled to a \
-                                {} array rather than a string",
-                                value.type_name()
-                            ),
-                        ))
-                    }
-                    _ => {
-                        return Err(self.error(
-                            modified.modifier.span.clone(),
-                            "quote's argument did not compile to a string",
-                        ));
-                    }
-                };
-                self.quote(&code, Some("quote".into()), &modified.modifier.span)?
-            }
+            Quote => self.quote_modifier(modified)?,
+            Reflect => self.reflect_modifier(modified),
+            Reify => self.reify_modifier(modified)?,
             Sig => {
                 let (sn, _) = self.monadic_modifier_op(modified)?;
                 Node::from_iter([Node::new_push(sn.sig.outputs), Node::new_push(sn.sig.args)])
             }
-            Derivative => {
-                let (sn, _) = self.monadic_modifier_op(modified)?;
-                self.add_span(modified.modifier.span.clone());
-                match derivative(&sn.node, &self.asm) {
-                    Ok(node) => node,
-                    Err(e) => {
-                        self.add_error(
-                            modified.modifier.span.clone(),
-                            format!("Cannot differentiate. {e}"),
-                        );
-                        sn.node
+            Invertible => self.invertible_modifier(modified)?,
+            Math => self.math_modifier(modified)?,
+            Derivative => self.derivative_modifier(modified)?,
+            Integral => self.integral_modifier(modified)?,
+            _ => return Ok(None),
+        }))
+    }
+    // These are kept out of `inline_modifier` so that their locals
+    // do not grow its stack frame, which is part of every level of recursion
+    #[inline(never)]
+    fn each_modifier(
+        &mut self,
+        modified: &Modified,
+        subscript: Option<Sp<Subscript>>,
+    ) -> UiuaResult<Node> {
+        use Primitive::*;
+        // Each pervasive
+        let operand = modified.code_operands().next().unwrap().clone();
+        let op_span = operand.span.clone();
+        let full_span = modified.modifier.span.clone().merge(op_span);
+        let words_look_pervasive = subscript
+            .as_ref()
+            .map_or(true, |sub| sub.value == Subscript::N(0))
+            && words_look_pervasive(slice::from_ref(&operand));
+        let sn = self.word_sig(operand)?;
+        if words_look_pervasive {
+            self.emit_diagnostic(
+                if let Some((prim, _)) = sn
+                    .node
+                    .as_flipped_primitive()
+                    .filter(|(prim, _)| prim.class().is_pervasive())
+                {
+                    format!(
+                        "{} is pervasive, so {} is redundant here.",
+                        prim.format(),
+                        Each.format(),
+                    )
+                } else {
+                    format!(
+                        "{m}'s function is pervasive, \
+                        so {m} is redundant here.",
+                        m = Each.format(),
+                    )
+                },
+                DiagnosticKind::Advice,
+                full_span.clone(),
+            );
+        }
+        let span = self.add_span(modified.modifier.span.clone());
+        Ok(
+            if let Some(n) = subscript
+                .and_then(|n| self.subscript_n_or_side(n))
+                .filter(|i| i.value != 0)
+            {
+                if n.value == -1 {
+                    Node::Mod(Rows, eco_vec![sn], span)
+                } else {
+                    match n.value {
+                        SubNOrSide::N(n) => {
+                            Node::ImplMod(ImplPrimitive::EachSub(n), eco_vec![sn], span)
+                        }
+                        SubNOrSide::Side(side) => {
+                            let sub_span = self.add_span(n.span);
+                            let mut node = match side {
+                                SubSide::Left => Node::Prim(Fix, sub_span),
+                                SubSide::Right => match sn.sig.args {
+                                    0 => Node::empty(),
+                                    1 => Node::Prim(Fix, sub_span),
+                                    n => {
+                                        let mut node = Node::Prim(Fix, sub_span);
+                                        for _ in 1..n {
+                                            node = Node::Mod(
+                                                Dip,
+                                                eco_vec![node.sig_node().unwrap()],
+                                                sub_span,
+                                            );
+                                        }
+                                        node
+                                    }
+                                },
+                            };
+                            node.push(Node::Mod(Each, eco_vec![sn], span));
+                            node
+                        }
                     }
                 }
+            } else {
+                Node::Mod(Each, eco_vec![sn], span)
+            },
+        )
+    }
+    #[inline(never)]
+    fn try_modifier(&mut self, modified: &Modified) -> UiuaResult<Node> {
+        use Primitive::*;
+        let in_try = replace(&mut self.in_try, true);
+        let nodes = self.dyadic_modifier_ops(modified);
+        self.in_try = in_try;
+        let (mut tried, mut handler, _, handler_span) = nodes?;
+
+        // Adjust handler signature if it is a noreturn function
+        if let [init @ .., Node::Prim(Assert, _)] = handler.node.as_slice() {
+            let noreturn = match init {
+                [.., Node::Push(val), Node::Prim(Dup | Flip, _)] if *val != 1 => true,
+                [.., Node::Format(..), Node::Prim(Dup, _)] => true,
+                [.., Node::Push(val), Node::Push(_)] if *val != 1 => true,
+                [.., Node::Mod(Dip, args, _)]
+                    if args.len() == 1 && matches!(&args[0].node, Node::Push(val) if *val != 1) =>
+                {
+                    true
+                }
+                _ => false,
+            };
+            if noreturn {
+                handler.sig.outputs = tried.sig.outputs;
             }
-            Integral => {
-                let (sn, _) = self.monadic_modifier_op(modified)?;
-                self.add_span(modified.modifier.span.clone());
-                match integral(&sn.node, &self.asm) {
-                    Ok(node) => node,
-                    Err(e) => {
-                        self.add_error(
-                            modified.modifier.span.clone(),
-                            format!("Cannot integrate. {e}"),
-                        );
-                        sn.node
-                    }
+        }
+
+        match tried.sig.outputs.cmp(&handler.sig.outputs) {
+            Ordering::Equal => {}
+            Ordering::Less => {
+                tried.sig.args += handler.sig.outputs - tried.sig.outputs;
+                tried.sig.outputs = handler.sig.outputs;
+            }
+            Ordering::Greater => {
+                handler.sig.args += tried.sig.outputs - handler.sig.outputs;
+                handler.sig.outputs = tried.sig.outputs;
+            }
+        }
+
+        if handler.sig.args == tried.sig.args + 2 {
+            self.experimental_error(&handler_span, || {
+                "Try handlers that take whether the error was thrown \
+                are experimental. To use them, add `# Experimental!` \
+                to the top of the file."
+            });
+        } else if handler.sig.args > tried.sig.args + 1 {
+            self.add_error(
+                handler_span.clone(),
+                format!(
+                    "Handler function must have at most \
+                    one more argument than the tried function, \
+                    but their signatures are {} and \
+                    {} respectively.",
+                    handler.sig, tried.sig
+                ),
+            );
+        }
+
+        let span = self.add_span(modified.modifier.span.clone());
+        Ok(Node::Mod(Primitive::Try, eco_vec![tried, handler], span))
+    }
+    #[inline(never)]
+    fn under_modifier(&mut self, modified: &Modified) -> UiuaResult<Node> {
+        let (f, g, f_span, _) = self.dyadic_modifier_ops(modified)?;
+        let normal = {
+            let (f_before, f_after) = self
+                .explain_inversion(&modified.modifier.span, |asm| {
+                    f.node.under_inverse(g.sig, false, asm)
+                })
+                .map_err(|e| self.error(f_span.clone(), e))?;
+            let mut node = f_before;
+            node.push(g.node.clone());
+            node.push(f_after);
+            let sig = self.sig_of(&node, &f_span)?;
+            SigNode::new(sig, node)
+        };
+        let span = self.add_span(modified.modifier.span.clone());
+        let un = if self.scope.experimental {
+            if f.sig.args == f.sig.outputs {
+                let (f_before, f_after) = f
+                    .node
+                    .under_inverse(g.sig, true, &self.asm)
+                    .map_err(|e| self.error(f_span.clone(), e))?;
+                (g.node.un_inverse(&self.asm).ok())
+                    .map(|g_inv| -> UiuaResult<SigNode> {
+                        let mut node = f_before;
+                        node.push(g_inv);
+                        node.push(f_after);
+                        let sig = self.sig_of(&node, &f_span)?;
+                        Ok(SigNode::new(sig, node))
+                    })
+                    .transpose()?
+            } else {
+                let cust = CustomInverse::from(InversionError::UnUnderSignature(f.sig));
+                Some(SigNode::new(
+                    normal.sig.inverse(),
+                    Node::CustomInverse(cust.into(), span),
+                ))
+            }
+        } else {
+            let cust = CustomInverse::from(InversionError::UnUnderExperimental);
+            Some(SigNode::new(
+                normal.sig.inverse(),
+                Node::CustomInverse(cust.into(), span),
+            ))
+        };
+        let cust = CustomInverse {
+            normal: Ok(normal),
+            un,
+            ..Default::default()
+        };
+        let span = self.add_span(modified.modifier.span.clone());
+        Ok(Node::CustomInverse(cust.into(), span))
+    }
+    #[inline(never)]
+    fn table_modifier(&mut self, modified: &Modified) -> UiuaResult<Node> {
+        use Primitive::*;
+        // Normal table compilation, but get some diagnostics
+        let (sn, span) = self.monadic_modifier_op(modified)?;
+        match sn.sig.args {
+            0 => self.emit_diagnostic(
+                format!("{} of 0 arguments is redundant", Table.format()),
+                DiagnosticKind::Advice,
+                span,
+            ),
+            1 => self.emit_diagnostic(
+                format!(
+                    "{} with 1 argument is just {rows}. \
+                    Use {rows} instead.",
+                    Table.format(),
+                    rows = Rows.format()
+                ),
+                DiagnosticKind::Advice,
+                span,
+            ),
+            _ => {}
+        }
+
+        fn table_fork(sn: SigNode, table_span: usize, asm: &Assembly) -> Node {
+            match sn.node {
+                Node::Mod(Fork, args, fork_span)
+                    if (args.iter()).all(|arg| arg.node.is_pure(Purity::Pure, asm))
+                        && args.windows(2).all(|w| w[0].sig.args == w[1].sig.args) =>
+                {
+                    let args: EcoVec<SigNode> = args
+                        .into_iter()
+                        .map(|arg| SigNode::new(arg.sig, table_fork(arg, table_span, asm)))
+                        .collect();
+                    Node::Mod(Fork, args, fork_span)
                 }
+                node => Node::Mod(Table, eco_vec![SigNode::new(sn.sig, node)], table_span),
             }
-            _ => return Ok(None),
-        }))
+        }
+        let table_span = self.add_span(modified.modifier.span.clone());
+        Ok(table_fork(sn, table_span, &self.asm))
+    }
+    #[inline(never)]
+    fn repeat_modifier(
+        &mut self,
+        modified: &Modified,
+        subscript: Option<Sp<Subscript>>,
+    ) -> UiuaResult<Node> {
+        use Primitive::*;
+        let (sn, span) = self.monadic_modifier_op(modified)?;
+        let spandex = self.add_span(modified.modifier.span.clone());
+        let mut node = if let Some((inv, inv_sig)) = sn
+            .node
+            .un_inverse(&self.asm)
+            .ok()
+            .and_then(|inv| inv.sig().ok().map(|sig| (inv, sig)))
+            .filter(|(_, inv_sig)| sn.sig.is_compatible_with(*inv_sig))
+        {
+            // If an inverse for repeat's function exists we use a special
+            // implementation that allows for negative repeatition counts
+            if sn.sig.inverse() != inv_sig {
+                self.add_error(
+                    span,
+                    format!(
+                        "Repeated function's inverse must have \
+                        the inverse signature, but their signatures \
+                        are {} and {}",
+                        sn.sig, inv_sig
+                    ),
+                )
+            }
+            Node::ImplMod(
+                ImplPrimitive::RepeatWithInverse,
+                eco_vec![sn, SigNode::new(inv_sig, inv)],
+                spandex,
+            )
+        } else {
+            Node::Mod(Primitive::Repeat, eco_vec![sn], spandex)
+        };
+        if let Some(n) = subscript.and_then(|n| self.subscript_n(n, Repeat.format())) {
+            node.prepend(Node::new_push(n.value));
+        }
+        Ok(node)
+    }
+    #[inline(never)]
+    fn quote_modifier(&mut self, modified: &Modified) -> UiuaResult<Node> {
+        use Primitive::*;
+        let operand = modified.code_operands().next().unwrap().clone();
+        let node = self.do_comptime(Quote, operand, &modified.modifier.span)?;
+        let code: String = match node {
+            Node::Push(Value::Char(chars)) if chars.rank() == 1 => chars.data.iter().collect(),
+            Node::Push(Value::Char(chars)) => {
+                return Err(self.error(
+                    modified.modifier.span.clone(),
+                    format!(
+                        "quote's argument compiled to a \
+                        rank {} array rather than a string",
+                        chars.rank()
+                    ),
+                ))
+            }
+            Node::Push(value) => {
+                return Err(self.error(
+                    modified.modifier.span.clone(),
+                    format!(
+                        "quote's argument compiled to a \
+                        {} array rather than a string",
+                        value.type_name()
+                    ),
+                ))
+            }
+            _ => {
+                return Err(self.error(
+                    modified.modifier.span.clone(),
+                    "quote's argument did not compile to a string",
+                ));
+            }
+        };
+        self.quote(&code, &modified.modifier.span)
+    }
+    #[inline(never)]
+    fn backward_modifier(&mut self, modified: &Modified) -> UiuaResult<Node> {
+        use Primitive::*;
+        let (SigNode { mut node, sig }, _) = self.monadic_modifier_op(modified)?;
+        Ok(match sig.args {
+            0 | 1 => {
+                self.emit_diagnostic(
+                    format!(
+                        "Remove {} here, as it does nothing for {} functions",
+                        Backward.format(),
+                        if sig.args == 0 { "noadic" } else { "monadic" }
+                    ),
+                    DiagnosticKind::Style,
+                    modified.modifier.span.clone(),
+                );
+                node
+            }
+            2 => {
+                let span = self.add_span(modified.modifier.span.clone());
+                node.prepend(Node::Prim(Flip, span));
+                node
+            }
+            _ => {
+                self.add_error(
+                    modified.modifier.span.clone(),
+                    format!(
+                        "Currently, {}'s function may take at most, \
+                        2 arguments, but its signature is {sig}",
+                        Backward.format(),
+                    ),
+                );
+                node
+            }
+        })
+    }
+    #[inline(never)]
+    fn obverse_modifier(&mut self, modified: &Modified) -> UiuaResult<Node> {
+        // Empty inverse case, where only one function is supplied
+        let (sn, span) = self.monadic_modifier_op(modified)?;
+        let spandex = self.add_span(span.clone());
+        let mut cust = CustomInverse {
+            normal: Ok(sn.clone()),
+            un: Some(SigNode::default()),
+            anti: None,
+            under: Some((sn.clone(), SigNode::default())),
+            is_obverse: true,
+        };
+        if sn.sig == sn.sig.inverse() {
+            cust.un = Some(sn.clone());
+        }
+        if sn.sig.anti() == Some(sn.sig) {
+            cust.anti = Some(sn.clone());
+        }
+        let set_inverses = SetInverses {
+            un: cust.un.is_some(),
+            anti: cust.anti.is_some(),
+            under: cust.under.is_some(),
+        };
+        self.code_meta
+            .obverses
+            .insert(modified.modifier.span.clone(), set_inverses);
+        if let Some(sig_decl) = self.code_meta.function_sigs.get_mut(&span) {
+            sig_decl.set_inverses = set_inverses;
+        }
+        Ok(Node::CustomInverse(cust.into(), spandex))
+    }
+    #[inline(never)]
+    fn fold_modifier(&mut self, modified: &Modified) -> UiuaResult<Node> {
+        use Primitive::*;
+        let (sn, _) = self.monadic_modifier_op(modified)?;
+        if sn.sig.args <= sn.sig.outputs {
+            self.experimental_error(&modified.modifier.span, || {
+                format!(
+                    "{} with arguments ≤ outputs is experimental. To use it, \
+                    add `# Experimental!` to the top of the file.",
+                    Fold.format()
+                )
+            });
+        }
+        let span = self.add_span(modified.modifier.span.clone());
+        Ok(Node::Mod(Fold, eco_vec![sn], span))
+    }
+    #[inline(never)]
+    fn fill_modifier(&mut self, modified: &Modified) -> UiuaResult<Node> {
+        let mut operands = modified.code_operands().rev().cloned();
+
+        // Filled function
+        let mode = replace(&mut self.pre_eval_mode, PreEvalMode::Lsp);
+        let f = self.word_sig(operands.next().unwrap());
+        self.pre_eval_mode = mode;
+        let f = f?;
+
+        // Get-fill function
+        let fill_word = operands.next().unwrap();
+        let fill_span = fill_word.span.clone();
+        let fill = self.word_sig(fill_word)?;
+        if fill.sig.outputs > 1 && !self.scope.fill_sig_error {
+            self.scope.fill_sig_error = true;
+            self.add_error(
+                fill_span,
+                format!(
+                    "{} function can have at most 1 output, but its signature is {}",
+                    Primitive::Fill.format(),
+                    fill.sig
+                ),
+            );
+        }
+        let span = self.add_span(modified.modifier.span.clone());
+        Ok(Node::Mod(Primitive::Fill, eco_vec![fill, f], span))
+    }
+    #[inline(never)]
+    fn cfg_modifier(&mut self, prim: Primitive, modified: &Modified) -> UiuaResult<Node> {
+        let mut operands = modified.code_operands().cloned();
+        let if_false = operands.next().unwrap();
+        let if_true = operands.next().unwrap();
+        let cond = operands.next().unwrap();
+        let span = &modified.modifier.span;
+        let values = self.comptime_values(prim, cond, span)?;
+        let cond = match values.as_slice() {
+            [Value::Num(arr)] if arr.rank() == 0 && [0.0, 1.0].contains(&arr.data[0]) => {
+                Some(arr.data[0] == 1.0)
+            }
+            [Value::Byte(arr)] if arr.rank() == 0 && arr.data[0] <= 1 => Some(arr.data[0] == 1),
+            _ => None,
+        };
+        let Some(cond) = cond else {
+            return Err(self.error(
+                span.clone(),
+                format!("{}'s condition must return a boolean", prim.format()),
+            ));
+        };
+        self.word(if cond { if_true } else { if_false })
+    }
+    #[inline(never)]
+    fn along_modifier(
+        &mut self,
+        modified: &Modified,
+        subscript: Option<Sp<Subscript>>,
+    ) -> UiuaResult<Node> {
+        let (sn, _) = self.monadic_modifier_op(modified)?;
+        let span = self.add_span(modified.modifier.span.clone());
+        let axis = subscript
+            .and_then(|n| self.subscript_n(n, Primitive::Along))
+            .map_or(1, |n| n.value);
+        Ok(if axis == 0 {
+            sn.node
+        } else if axis > 0 && sn.sig == (1, 1) && is_row_agnostic(&sn.node) {
+            // The function only sees rows, so nested rows
+            // give the same result without reorienting the array
+            let mut node = Node::Mod(Primitive::Rows, eco_vec![sn], span);
+            for _ in 1..axis {
+                node = Node::Mod(Primitive::Rows, eco_vec![node.sig_node().unwrap()], span);
+            }
+            node
+        } else {
+            Node::ImplMod(ImplPrimitive::AlongSub(axis), eco_vec![sn], span)
+        })
+    }
+    #[inline(never)]
+    fn decimal_modifier(
+        &mut self,
+        modified: &Modified,
+        subscript: Option<Sp<Subscript>>,
+    ) -> UiuaResult<Node> {
+        let (sn, _) = self.monadic_modifier_op(modified)?;
+        let span = self.add_span(modified.modifier.span.clone());
+        let places = subscript
+            .and_then(|n| self.subscript_n(n, Primitive::Decimal))
+            .map_or(2, |n| {
+                if !(0..=MAX_DECIMAL_PLACES as i32).contains(&n.value) {
+                    self.add_error(
+                        n.span,
+                        format!(
+                            "{} subscript must be between 0 and {MAX_DECIMAL_PLACES}",
+                            Primitive::Decimal.format()
+                        ),
+                    );
+                }
+                n.value.clamp(0, MAX_DECIMAL_PLACES as i32) as u8
+            });
+        Ok(Node::ImplMod(
+            ImplPrimitive::DecimalSub(places),
+            eco_vec![sn],
+            span,
+        ))
+    }
+    #[inline(never)]
+    fn invertible_modifier(&mut self, modified: &Modified) -> UiuaResult<Node> {
+        let (sn, _) = self.monadic_modifier_op(modified)?;
+        Ok(Node::new_push(sn.un_inverse(&self.asm).is_ok()))
+    }
+    #[inline(never)]
+    fn derivative_modifier(&mut self, modified: &Modified) -> UiuaResult<Node> {
+        let (sn, _) = self.monadic_modifier_op(modified)?;
+        let span = self.add_span(modified.modifier.span.clone());
+        Ok(match derivative(&sn.node, &self.asm) {
+            Ok(node) => {
+                self.record_math_notation(
+                    &modified.modifier.span,
+                    Primitive::Derivative,
+                    &sn.node,
+                    &node,
+                );
+                node
+            }
+            // Fall back to automatic differentiation at runtime
+            Err(_) => match check_differentiable(&sn, &self.asm) {
+                Ok(()) => Node::ImplMod(ImplPrimitive::AutoDerivative, eco_vec![sn], span),
+                Err(e) => {
+                    self.add_error(
+                        modified.modifier.span.clone(),
+                        format!("Cannot differentiate. {e}"),
+                    );
+                    sn.node
+                }
+            },
+        })
+    }
+    #[inline(never)]
+    fn integral_modifier(&mut self, modified: &Modified) -> UiuaResult<Node> {
+        let (sn, _) = self.monadic_modifier_op(modified)?;
+        self.add_span(modified.modifier.span.clone());
+        Ok(match integral(&sn.node, &self.asm) {
+            Ok(node) => {
+                self.record_math_notation(
+                    &modified.modifier.span,
+                    Primitive::Integral,
+                    &sn.node,
+                    &node,
+                );
+                node
+            }
+            Err(e) => {
+                self.add_error(
+                    modified.modifier.span.clone(),
+                    format!("Cannot integrate. {e}"),
+                );
+                sn.node
+            }
+        })
+    }
+    #[inline(never)]
+    fn reflect_modifier(&mut self, modified: &Modified) -> Node {
+        let operand = modified.code_operands().next().unwrap();
+        Node::new_push(self.reflect_operand(operand))
+    }
+    #[inline(never)]
+    fn reify_modifier(&mut self, modified: &Modified) -> UiuaResult<Node> {
+        let operand = modified.code_operands().next().unwrap().clone();
+        let node = self.do_comptime(Primitive::Reify, operand, &modified.modifier.span)?;
+        let Node::Push(items) = node else {
+            return Err(self.error(
+                modified.modifier.span.clone(),
+                "reify's argument did not compile to a value",
+            ));
+        };
+        let code = reify(&items).map_err(|e| {
+            self.error(
+                modified.modifier.span.clone(),
+                format!("Cannot reify code. {e}"),
+            )
+        })?;
+        self.quote(&code, &modified.modifier.span)
+    }
+    #[inline(never)]
+    fn math_modifier(&mut self, modified: &Modified) -> UiuaResult<Node> {
+        let (sn, _) = self.monadic_modifier_op(modified)?;
+        Ok(match math_notation(&sn.node, &self.asm, MathFormat::Text) {
+            Ok(s) => Node::new_push(s),
+            Err(e) => {
+                self.add_error(
+                    modified.modifier.span.clone(),
+                    format!("Cannot convert function to math notation. {e}"),
+                );
+                Node::new_push("")
+            }
+        })
     }
     // Compile an inline macro
     fn inline_macro(
@@ -1202,26 +1566,31 @@ impl Compiler {
         .flatten()
         .collect();
         // Track
+        let definition = mac.func.span.clone();
         self.code_meta
             .inline_macros
             .insert(mac.func.span, ident_modifier_args(&mac.ident.value));
-        Ok(if mac.caret_span.is_some() {
-            let root = self.words_sig(words)?;
-            let code_mac = CodeMacro {
-                root,
-                names: Default::default(),
-            };
-            self.code_macro(None, span, operands, code_mac)?
-        } else {
-            // Expand
-            self.expand_index_macro(None, &mut words, operands, span.clone(), true)?;
-            // Compile
-            let node = self.suppress_diagnostics(|comp| comp.words(words))?;
-            // Add
-            let sig = self.sig_of(&node, &span)?;
-            let func = (self.asm).add_function(FunctionId::Macro(None, span.clone()), sig, node);
-            let span = self.add_span(span);
-            Node::Call(func, span)
+        let invocation = span.clone();
+        self.in_macro_expansion(None, &invocation, &definition, |comp| {
+            Ok(if mac.caret_span.is_some() {
+                let root = comp.words_sig(words)?;
+                let code_mac = CodeMacro {
+                    root,
+                    names: Default::default(),
+                };
+                comp.code_macro(None, span, operands, code_mac)?
+            } else {
+                // Expand
+                comp.expand_index_macro(None, &mut words, operands, span.clone(), true)?;
+                // Compile
+                let node = comp.suppress_diagnostics(|comp| comp.words(words))?;
+                // Add
+                let sig = comp.sig_of(&node, &span)?;
+                let id = FunctionId::Macro(None, definition.clone());
+                let func = comp.asm.add_function(id, sig, node);
+                let span = comp.add_span(span);
+                Node::Call(func, span)
+            })
         })
     }
     fn modifier_ref(
@@ -1248,6 +1617,9 @@ impl Compiler {
                 "Macro makes compilation recur too deep",
             ));
         }
+        let name = Some(r.name.value.clone());
+        let definition = (self.asm.bindings.get(local.index))
+            .map_or_else(|| r.name.span.clone(), |binding| binding.span.clone());
         let node = if let Some(mut mac) = self.index_macros.get(&local.index).cloned() {
             // Index macros
             let span = self.add_span(modifier_span.clone());
@@ -1265,56 +1637,60 @@ impl Compiler {
                     }
                 }
                 _ => {
-                    // Expand
-                    self.expand_index_macro(
-                        Some(r.name.value.clone()),
-                        &mut mac.words,
-                        operands,
-                        modifier_span.clone(),
-                        mac.hygenic,
-                    )?;
-                    // Handle recursion
-                    // Recursive macros work by creating a binding for the expansion.
-                    // Recursive calls then call that binding.
-                    // We know that this is a recursive call if the scope tracks
-                    // a macro with the same index.
-                    let macro_local = mac.recursive.then(|| {
-                        let expansion_index = self.next_global;
-                        let count = ident_modifier_args(&r.name.value);
-                        // Add temporary binding
-                        self.asm.add_binding_at(
-                            LocalName {
-                                index: expansion_index,
-                                public: false,
-                            },
-                            BindingKind::IndexMacro(count),
-                            Some(modifier_span.clone()),
-                            BindingMeta::default(),
-                        );
-                        self.next_global += 1;
-                        MacroLocal {
-                            macro_index: local.index,
-                            expansion_index,
+                    self.in_macro_expansion(name.clone(), &modifier_span, &definition, |comp| {
+                        // Expand
+                        comp.expand_index_macro(
+                            name.clone(),
+                            &mut mac.words,
+                            operands,
+                            modifier_span.clone(),
+                            mac.hygenic,
+                        )?;
+                        // Handle recursion
+                        // Recursive macros work by creating a binding for the expansion.
+                        // Recursive calls then call that binding.
+                        // We know that this is a recursive call if the scope tracks
+                        // a macro with the same index.
+                        let macro_local = mac.recursive.then(|| {
+                            let expansion_index = comp.next_global;
+                            let count = ident_modifier_args(&r.name.value);
+                            // Add temporary binding
+                            comp.asm.add_binding_at(
+                                LocalName {
+                                    index: expansion_index,
+                                    public: false,
+                                },
+                                BindingKind::IndexMacro(count),
+                                Some(modifier_span.clone()),
+                                BindingMeta::default(),
+                            );
+                            comp.next_global += 1;
+                            MacroLocal {
+                                macro_index: local.index,
+                                expansion_index,
+                            }
+                        });
+                        // Compile
+                        let node = comp.suppress_diagnostics(|comp| {
+                            comp.temp_scope(mac.names, macro_local, |comp| comp.words(mac.words))
+                        })?;
+                        // Add
+                        let sig = comp.sig_of(&node, &modifier_span)?;
+                        let id = FunctionId::Macro(name, definition.clone());
+                        let func = comp.asm.add_function(id, sig, node);
+                        if let Some(macro_local) = macro_local {
+                            comp.asm.bindings.make_mut()[macro_local.expansion_index].kind =
+                                BindingKind::Func(func.clone());
                         }
-                    });
-                    // Compile
-                    let node = self.suppress_diagnostics(|comp| {
-                        comp.temp_scope(mac.names, macro_local, |comp| comp.words(mac.words))
-                    })?;
-                    // Add
-                    let sig = self.sig_of(&node, &modifier_span)?;
-                    let id = FunctionId::Macro(Some(r.name.value), r.name.span);
-                    let func = self.asm.add_function(id, sig, node);
-                    if let Some(macro_local) = macro_local {
-                        self.asm.bindings.make_mut()[macro_local.expansion_index].kind =
-                            BindingKind::Func(func.clone());
-                    }
-                    Node::Call(func, span)
+                        Ok(Node::Call(func, span))
+                    })?
                 }
             }
         } else if let Some(mac) = self.code_macros.get(&local.index).cloned() {
             // Code macros
-            self.code_macro(Some(r.name.value), modifier_span, operands, mac)?
+            self.in_macro_expansion(name.clone(), &modifier_span, &definition, |comp| {
+                comp.code_macro(name, modifier_span.clone(), operands, mac)
+            })?
         } else if let Some(m) =
             (self.asm.bindings.get(local.index)).and_then(|binfo| match &binfo.kind {
                 BindingKind::Module(m) => Some(m),
@@ -1335,6 +1711,23 @@ impl Compiler {
         self.comptime_depth -= 1;
         Ok(node)
     }
+    /// Run a macro expansion, adding it to the trace of any errors that occur
+    fn in_macro_expansion<T>(
+        &mut self,
+        name: Option<Ident>,
+        span: &CodeSpan,
+        definition: &CodeSpan,
+        f: impl FnOnce(&mut Self) -> UiuaResult<T>,
+    ) -> UiuaResult<T> {
+        let errors_len = self.errors.len();
+        self.macro_depth += 1;
+        let res = f(self);
+        self.macro_depth -= 1;
+        let trace = |e: UiuaError| e.trace_macro(name.clone(), span.clone(), definition.clone());
+        let errors: Vec<_> = self.errors.drain(errors_len..).map(trace).collect();
+        self.errors.extend(errors);
+        res.map_err(trace)
+    }
     fn code_macro(
         &mut self,
         mac_name: Option<Ident>,
@@ -1461,18 +1854,15 @@ impl Compiler {
 
             swap(&mut env.asm, &mut self.asm);
             Ok(())
-        })()
-        .map_err(|e| e.trace_macro(mac_name.clone(), modifier_span.clone()))?;
+        })()?;
 
         // Quote
         if let Some(code) = code {
-            self.code_meta
-                .macro_expansions
-                .insert(full_span, (mac_name.clone(), code.clone()));
+            if self.macro_depth <= 1 {
+                (self.code_meta.macro_expansions).insert(full_span, (mac_name, code.clone()));
+            }
             self.suppress_diagnostics(|comp| {
-                comp.temp_scope(mac.names, None, |comp| {
-                    comp.quote(&code, mac_name, &modifier_span)
-                })
+                comp.temp_scope(mac.names, None, |comp| comp.quote(&code, &modifier_span))
             })
         } else {
             Ok(Node::empty())
@@ -1508,16 +1898,21 @@ impl Compiler {
         let span = span.merge(operands.last().unwrap().span.clone());
         let operands: Vec<Sp<Word>> = operands.into_iter().filter(|w| w.value.is_code()).collect();
         self.replace_placeholders(macro_words, &operands)?;
-        // Format and store the expansion for the LSP
-        let mut words_to_format = Vec::new();
-        for word in &*macro_words {
-            match &word.value {
-                Word::Func(func) => words_to_format.extend(func.lines.iter().flatten().cloned()),
-                _ => words_to_format.push(word.clone()),
+        // Format and store the expansion for the LSP and `uiua expand`
+        // Expansions inside other expansions are skipped because their spans are not meaningful
+        if self.macro_depth <= 1 {
+            let mut words_to_format = Vec::new();
+            for word in &*macro_words {
+                match &word.value {
+                    Word::Func(func) => {
+                        words_to_format.extend(func.lines.iter().flatten().cloned())
+                    }
+                    _ => words_to_format.push(word.clone()),
+                }
             }
+            let formatted = format_words(&words_to_format, &self.asm.inputs);
+            (self.code_meta.macro_expansions).insert(span, (name, formatted));
         }
-        let formatted = format_words(&words_to_format, &self.asm.inputs);
-        (self.code_meta.macro_expansions).insert(span, (name, formatted));
         Ok(())
     }
     fn replace_placeholders(&self, words: &mut Vec<Sp<Word>>, initial: &[Sp<Word>]) -> UiuaResult {
@@ -1542,16 +1937,14 @@ impl Compiler {
         words.retain(|word| !matches!(word.value, Word::Placeholder(_)));
         error.map_or(Ok(()), Err)
     }
-    fn quote(&mut self, code: &str, name: Option<Ident>, span: &CodeSpan) -> UiuaResult<Node> {
+    fn quote(&mut self, code: &str, span: &CodeSpan) -> UiuaResult<Node> {
         let (items, errors, _) = parse(
             code,
             InputSrc::Macro(span.clone().into()),
             &mut self.asm.inputs,
         );
         if !errors.is_empty() {
-            return Err(UiuaErrorKind::Parse(errors, self.asm.inputs.clone().into())
-                .error()
-                .trace_macro(name, span.clone()));
+            return Err(UiuaErrorKind::Parse(errors, self.asm.inputs.clone().into()).error());
         }
 
         let root_node_len = self.asm.root.len();
@@ -1562,9 +1955,7 @@ impl Compiler {
         if self.comptime_depth > MAX_COMPTIME_DEPTH {
             return Err(self.error(span.clone(), "Compile-time evaluation recurs too deep"));
         }
-        let res = self
-            .items(items, true)
-            .map_err(|e| e.trace_macro(name, span.clone()));
+        let res = self.items(items, true);
         self.comptime_depth -= 1;
         self.pre_eval_mode = pre_eval_mod;
         // Extract generated root node
@@ -1581,6 +1972,20 @@ impl Compiler {
         if self.pre_eval_mode == PreEvalMode::Lsp {
             return self.word(operand);
         }
+        let values = self.comptime_values(prim, operand, span)?;
+        let mut node = Node::empty();
+        for value in values {
+            node.push(Node::new_push(value));
+        }
+        Ok(node)
+    }
+    /// Run a function at compile time and get its outputs
+    fn comptime_values(
+        &mut self,
+        prim: Primitive,
+        operand: Sp<Word>,
+        span: &CodeSpan,
+    ) -> UiuaResult<Vec<Value>> {
         let orig_spans_len = self.asm.spans.len();
         let sn = self.word_sig(operand)?;
         if sn.sig.args > 0 {
@@ -1594,6 +1999,12 @@ impl Compiler {
             ));
         }
         let mut comp = self.clone();
+        let base = (self.scope_file_path())
+            .and_then(Path::parent)
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .map(Path::to_path_buf);
+        comp.macro_env.rt.backend =
+            Arc::new(AssetSys::new(comp.backend(), self.assets.clone(), base));
         if let Some(index) = comp.node_unbound_index(&sn.node) {
             let name = comp.scope.names.iter().find_map(|(ident, local)| {
                 if local.index == index {
@@ -1624,11 +2035,8 @@ impl Compiler {
         self.asm.spans.truncate(orig_spans_len);
         comp.asm.root.truncate(asm_root_len);
         let val_count = sn.sig.outputs;
-        let mut node = Node::empty();
-        for value in values.into_iter().rev().take(val_count).rev() {
-            node.push(Node::new_push(value));
-        }
-        Ok(node)
+        let skip = values.len().saturating_sub(val_count);
+        Ok(values.into_iter().skip(skip).collect())
     }
     /// Run a function in a temporary scope with the given names.
     /// Newly created bindings will be added to the current scope after the function is run.
@@ -1655,3 +2063,53 @@ impl Compiler {
         res
     }
 }
+
+/// Whether a monadic function treats its argument only as a list of rows
+///
+/// Applying such a function along an axis is the same as applying it with nested [`Primitive::Rows`]
+fn is_row_agnostic(node: &Node) -> bool {
+    use Primitive::*;
+    let mut nodes = node.as_slice();
+    while !nodes.is_empty() {
+        nodes = match nodes {
+            [Node::Prim(Identity | Reverse | First | Last, _), rest @ ..] => rest,
+            [Node::Prim(prim, _), rest @ ..] if prim.class() == PrimClass::MonadicPervasive => rest,
+            [Node::Push(val), Node::Prim(Take | Drop | Rotate, _), rest @ ..]
+                if val.rank() == 0 =>
+            {
+                rest
+            }
+            [Node::Push(val), Node::Prim(Select, _), rest @ ..] if val.rank() <= 1 => rest,
+            [Node::Mod(Reduce | Scan, args, _), rest @ ..]
+                if matches!(&args[0].node, Node::Prim(prim, _)
+                    if prim.class() == PrimClass::DyadicPervasive) =>
+            {
+                rest
+            }
+            _ => return false,
+        };
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn calculus_math_notation() {
+        use crate::*;
+        let mut comp = Compiler::new();
+        comp.load_str("# Experimental!\n∂(ⁿ3 +1) 2\n∫(×2) 3")
+            .unwrap();
+        let notations = comp.code_meta.math_notations.values();
+        assert!(notations.clone().all(|n| n.mathml.starts_with("<math")));
+        let mut notations: Vec<_> = notations.map(|n| n.text.as_str()).collect();
+        notations.sort();
+        assert_eq!(
+            notations,
+            [
+                "d/dx (x³ + 3x² + 3x + 1) = 3x² + 6x + 3",
+                "∫ 2x dx = x² + C"
+            ]
+        );
+    }
+}
@@ -171,6 +171,7 @@ impl Compiler {
                 Uiua::with_safe_sys()
             }
             .with_execution_limit(Duration::from_millis(40));
+            env.rt.array_size_limit = self.macro_env.rt.array_size_limit;
             match env.run_asm(asm) {
                 Ok(()) => {
                     let stack = env.take_stack();
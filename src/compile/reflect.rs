@@ -0,0 +1,200 @@
+//! Converting code to and from data
+
+use crate::format::format_word;
+
+use super::*;
+
+impl Compiler {
+    /// Get the data representation of some words
+    ///
+    /// Each item is a list of a kind, a value, and a span
+    pub(super) fn reflect_words<'a>(&self, words: impl IntoIterator<Item = &'a Sp<Word>>) -> Value {
+        (words.into_iter())
+            .filter(|word| word.value.is_code())
+            .map(|word| Boxed(self.reflect_word(word)))
+            .collect()
+    }
+    /// Get the data representation of a modifier operand
+    ///
+    /// The contents of single-line parentheses are reflected directly.
+    pub(super) fn reflect_operand(&self, operand: &Sp<Word>) -> Value {
+        match &operand.value {
+            Word::Func(func) if func.signature.is_none() => match single_line(&func.lines) {
+                Some(line) => self.reflect_words(line),
+                None => self.reflect_words([operand]),
+            },
+            _ => self.reflect_words([operand]),
+        }
+    }
+    fn reflect_word(&self, word: &Sp<Word>) -> Value {
+        let (kind, value) = match &word.value {
+            Word::Primitive(prim) => ("primitive", prim.name().into()),
+            Word::Number(Ok(n)) => ("constant", (*n).into()),
+            Word::Char(c) if c.chars().count() == 1 => {
+                ("constant", c.chars().next().unwrap().into())
+            }
+            Word::Char(s) | Word::String(s) => ("constant", s.as_str().into()),
+            Word::MultilineString(lines) => {
+                let lines: Vec<_> = lines.iter().map(|line| line.value.as_str()).collect();
+                ("constant", lines.join("\n").into())
+            }
+            Word::Ref(r) => ("name", ref_name(r).into()),
+            Word::Strand(items) => ("strand", self.reflect_words(items)),
+            Word::Array(arr) if arr.signature.is_none() => match single_line(&arr.lines) {
+                Some(line) => {
+                    let kind = if arr.boxes { "box array" } else { "array" };
+                    (kind, self.reflect_words(line))
+                }
+                None => ("code", format_word(word, &self.asm.inputs).into()),
+            },
+            Word::Func(func) if func.signature.is_none() => match single_line(&func.lines) {
+                Some(line) => ("function", self.reflect_words(line)),
+                None => ("code", format_word(word, &self.asm.inputs).into()),
+            },
+            Word::Modified(m) => {
+                let name = match &m.modifier.value {
+                    Modifier::Primitive(prim) => prim.name().into(),
+                    Modifier::Ref(r) => ref_name(r),
+                    Modifier::Macro(_) => {
+                        return self.reflected(word, "code", format_word(word, &self.asm.inputs))
+                    }
+                };
+                let mut value = vec![Boxed(name.into())];
+                for op in m.code_operands() {
+                    if let Word::Pack(pack) = &op.value {
+                        for branch in &pack.branches {
+                            let branch = branch.clone().map(Word::Func);
+                            value.push(Boxed(self.reflect_operand(&branch)));
+                        }
+                    } else {
+                        value.push(Boxed(self.reflect_operand(op)));
+                    }
+                }
+                ("modifier", value.into_iter().collect())
+            }
+            _ => ("code", format_word(word, &self.asm.inputs).into()),
+        };
+        self.reflected(word, kind, value)
+    }
+    fn reflected(&self, word: &Sp<Word>, kind: &str, value: impl Into<Value>) -> Value {
+        let span = &word.span;
+        let span = [span.start.line, span.start.col, span.end.line, span.end.col];
+        let span: Value = span.into_iter().map(f64::from).collect();
+        [Boxed(kind.into()), Boxed(value.into()), Boxed(span)]
+            .into_iter()
+            .collect()
+    }
+}
+
+/// Get the only line of code in some lines, if there is only one
+fn single_line(lines: &[Vec<Sp<Word>>]) -> Option<&[Sp<Word>]> {
+    let mut lines = (lines.iter()).filter(|line| line.iter().any(|w| w.value.is_code()));
+    match (lines.next(), lines.next()) {
+        (line, None) => Some(line.map(Vec::as_slice).unwrap_or_default()),
+        _ => None,
+    }
+}
+
+fn ref_name(r: &Ref) -> String {
+    let mut name = String::new();
+    for comp in &r.path {
+        name.push_str(&comp.module.value);
+        name.push('~');
+    }
+    name.push_str(&r.name.value);
+    name
+}
+
+/// Convert the data representation of some code back to code
+pub(super) fn reify(items: &Value) -> Result<String, String> {
+    let Value::Box(items) = items else {
+        return Err(format!(
+            "Code must be a list of boxes, but it is {}",
+            items.type_name_plural()
+        ));
+    };
+    if items.rank() > 1 {
+        return Err(format!(
+            "Code must be a list of boxes, but it has shape {}",
+            items.shape
+        ));
+    }
+    let mut code = String::new();
+    for Boxed(item) in &items.data {
+        if !code.is_empty() {
+            code.push(' ');
+        }
+        reify_item(item, &mut code)?;
+    }
+    Ok(code)
+}
+
+fn reify_item(item: &Value, code: &mut String) -> Result<(), String> {
+    let Value::Box(item) = item else {
+        return Err(format!(
+            "Code items must be lists of boxes, but one is {}",
+            item.type_name_plural()
+        ));
+    };
+    let (kind, value) = match item.data.as_slice() {
+        [Boxed(Value::Char(kind)), Boxed(value), ..] if kind.rank() == 1 => {
+            (kind.data.iter().collect::<String>(), value)
+        }
+        _ => return Err("Code items must start with a kind string and a value".into()),
+    };
+    let string = |value: &Value| match value {
+        Value::Char(s) if s.rank() <= 1 => Ok(s.data.iter().collect::<String>()),
+        _ => Err(format!("The value of a {kind} must be a string")),
+    };
+    match kind.as_str() {
+        "primitive" => {
+            let name = string(value)?;
+            let prim = Primitive::from_name(&name)
+                .ok_or_else(|| format!("{name:?} is not a primitive"))?;
+            code.push_str(&prim.to_string());
+        }
+        "constant" => code.push_str(&value.representation()),
+        "name" | "code" => code.push_str(&string(value)?),
+        "strand" => {
+            let Value::Box(items) = value else {
+                return Err("The value of a strand must be a list of boxes".into());
+            };
+            for (i, Boxed(item)) in items.data.iter().enumerate() {
+                if i > 0 {
+                    code.push('_');
+                }
+                reify_item(item, code)?;
+            }
+        }
+        "array" | "box array" | "function" => {
+            let (open, close) = match kind.as_str() {
+                "array" => ('[', ']'),
+                "box array" => ('{', '}'),
+                _ => ('(', ')'),
+            };
+            code.push(open);
+            code.push_str(&reify(value)?);
+            code.push(close);
+        }
+        "modifier" => {
+            let Value::Box(parts) = value else {
+                return Err("The value of a modifier must be a list of boxes".into());
+            };
+            let Some((Boxed(name), operands)) = parts.data.split_first() else {
+                return Err("The value of a modifier must start with its name".into());
+            };
+            let name = string(name)?;
+            match Primitive::from_name(&name) {
+                Some(prim) => code.push_str(&prim.to_string()),
+                None => code.push_str(&name),
+            }
+            for Boxed(operand) in operands {
+                code.push('(');
+                code.push_str(&reify(operand)?);
+                code.push(')');
+            }
+        }
+        _ => return Err(format!("Unknown code item kind {kind:?}")),
+    }
+    Ok(())
+}
@@ -0,0 +1,128 @@
+//! Suggestions for unknown identifiers
+
+use super::*;
+
+/// Glyphs that are easily mistaken for primitive glyphs, and the glyphs they are mistaken for
+pub(crate) const CONFUSED_GLYPHS: &[(char, char)] = &[
+    ('○', '∘'),
+    ('◯', '∘'),
+    ('⚪', '∘'),
+    ('◦', '∘'),
+    ('∆', '△'),
+    ('Δ', '△'),
+    ('▵', '△'),
+    ('▲', '△'),
+    ('✕', '×'),
+    ('✖', '×'),
+    ('⨯', '×'),
+    ('−', '-'),
+    ('⁻', '¯'),
+    ('ˉ', '¯'),
+    ('‾', '¯'),
+    ('·', '⋅'),
+    ('∙', '⋅'),
+    ('•', '⋅'),
+    ('⩽', '≤'),
+    ('≦', '≤'),
+    ('⩾', '≥'),
+    ('≧', '≥'),
+    ('⍳', '⇡'),
+    ('⌽', '⇌'),
+    ('⍴', '↯'),
+];
+
+impl Compiler {
+    /// Make an error for an unknown identifier, suggesting a similar name if there is one
+    pub(super) fn unknown_identifier(&self, span: CodeSpan, name: &str) -> UiuaError {
+        let error = self.error(span, format!("Unknown identifier `{name}`"));
+        match self.suggestion(name) {
+            Some(suggestion) => error.with_info([(format!("Did you mean {suggestion}?"), None)]),
+            None => error,
+        }
+    }
+    fn suggestion(&self, name: &str) -> Option<String> {
+        // Mistaken glyphs
+        let mut chars = name.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            let &(_, glyph) = CONFUSED_GLYPHS.iter().find(|(wrong, _)| *wrong == c)?;
+            return Primitive::from_glyph(glyph).map(|prim| prim.format().to_string());
+        }
+        let max_distance = (name.chars().count() / 3).max(1);
+        // Bindings and constants
+        if name.starts_with(|c: char| c.is_uppercase()) {
+            let mut names: Vec<&str> = self.scope.names.keys().map(|name| name.as_str()).collect();
+            let mut hit_file = false;
+            for scope in self.higher_scopes.iter().rev() {
+                if matches!(scope.kind, ScopeKind::File(_)) {
+                    if hit_file {
+                        break;
+                    }
+                    hit_file = true;
+                }
+                names.extend(scope.names.keys().map(|name| name.as_str()));
+            }
+            names.extend(CONSTANTS.iter().map(|def| def.name));
+            if let Some((_, best)) = (names.into_iter())
+                .map(|known| (edit_distance(name, known), known))
+                .filter(|&(dist, _)| dist <= max_distance)
+                .min_by_key(|&(dist, _)| dist)
+            {
+                return Some(format!("`{best}`"));
+            }
+        }
+        // Primitives
+        let lower = name.to_lowercase();
+        (Primitive::non_deprecated())
+            .filter(|prim| prim.name().len() > 1)
+            .map(|prim| (edit_distance(&lower, prim.name()), prim))
+            .filter(|&(dist, _)| dist <= max_distance)
+            .min_by_key(|&(dist, _)| dist)
+            .map(|(_, prim)| prim.format().to_string())
+    }
+}
+
+/// The number of single-character insertions, deletions,
+/// substitutions, and adjacent transpositions to turn one string into another
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut prev_prev = vec![0; b.len() + 1];
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut curr = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = (a[i - 1] != b[j - 1]) as usize;
+            curr[j] = (prev[j] + 1).min(curr[j - 1] + 1).min(prev[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                curr[j] = curr[j].min(prev_prev[j - 2] + 1);
+            }
+        }
+        prev_prev = prev;
+        prev = curr;
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn unknown_identifier_suggestions() {
+        use crate::*;
+        let suggestion = |code: &str| -> Option<String> {
+            let Err(err) = Compiler::new().load_str(code).map(drop) else {
+                panic!("{code:?} did not fail");
+            };
+            let report = err.report().color(false).to_string();
+            (report.lines())
+                .find_map(|line| line.split_once("Did you mean ")?.1.strip_suffix('?'))
+                .map(Into::into)
+        };
+        assert_eq!(suggestion("rwos [1]").as_deref(), Some("≡ rows"));
+        assert_eq!(suggestion("lenght [1]").as_deref(), Some("⧻ length"));
+        assert_eq!(suggestion("Reverse [1]").as_deref(), Some("⇌ reverse"));
+        assert_eq!(suggestion("∆ [1]").as_deref(), Some("△ shape"));
+        assert_eq!(suggestion("Fooo ← 5\nFoo").as_deref(), Some("`Fooo`"));
+        assert_eq!(suggestion("Pii").as_deref(), Some("π pi"));
+        assert_eq!(suggestion("xyzq"), None);
+    }
+}
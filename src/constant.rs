@@ -49,6 +49,10 @@ pub enum ConstantValue {
     ThisFileDir,
     /// The compile-time working directory
     WorkingDir,
+    /// The names of the features enabled for compilation
+    Features,
+    /// Whether experimental features are enabled
+    Experimental,
 }
 
 impl ConstantValue {
@@ -87,6 +91,8 @@ impl ConstantValue {
                 .display()
                 .to_string()
                 .into(),
+            ConstantValue::Features => Array::<Boxed>::default().into(),
+            ConstantValue::Experimental => false.into(),
         }
     }
 }
@@ -206,6 +212,12 @@ constant!(
     ("WorkingDir", System, ConstantValue::WorkingDir),
     /// The number of processors available
     ("NumProcs", System, num_cpus::get() as f64),
+    /// The compilation target, either `"native"` or `"web"`
+    ("Target", System, if cfg!(target_arch = "wasm32") { "web" } else { "native" }),
+    /// The names of the features enabled with `--feature`
+    ("Features", System, ConstantValue::Features),
+    /// Whether experimental features are enabled in the current scope
+    ("Experimental", System, ConstantValue::Experimental),
     /// A boolean `true` value for use in `json`
     ("True", External, Array::json_bool(true)),
     /// A boolean `false` value for use in `json`
@@ -0,0 +1,627 @@
+//! Generate API documentation from the doc comments of a Uiua module
+
+use std::{fmt::Write, fs, path::Path, time::Duration};
+
+use base64::{engine::general_purpose::URL_SAFE, Engine};
+use ecow::EcoString;
+
+use crate::{
+    BindingKind, Compiler, Ident, IntoSysBackend, Module, RunMode, SafeSys, Uiua, UiuaErrorKind,
+    UiuaResult, VERSION,
+};
+
+/// How long an example may run before it is stopped
+const EXAMPLE_TIME_LIMIT: Duration = Duration::from_secs(5);
+
+/// Documentation for a module
+#[derive(Debug, Clone)]
+pub struct ModuleDocs {
+    /// The name of the module
+    pub name: Ident,
+    /// The module's top-level comment
+    pub lines: Vec<DocLine>,
+    /// The module's public bindings
+    pub items: Vec<ItemDocs>,
+    /// The source of the file, used to make examples runnable
+    source: EcoString,
+}
+
+/// Documentation for a binding
+#[derive(Debug, Clone)]
+pub struct ItemDocs {
+    /// The name of the binding, qualified with the names of its parent modules
+    pub name: EcoString,
+    /// The kind of binding
+    pub kind: ItemKind,
+    /// The signature of the binding, either from its doc comment or inferred
+    pub signature: Option<String>,
+    /// The binding's doc comment
+    pub lines: Vec<DocLine>,
+    /// The deprecation message
+    pub deprecation: Option<EcoString>,
+}
+
+/// A kind of documented binding
+#[derive(Debug, Clone)]
+pub enum ItemKind {
+    /// A constant
+    Constant,
+    /// A function
+    Function,
+    /// An index macro
+    IndexMacro,
+    /// A code macro
+    CodeMacro,
+    /// A scoped module
+    Module(ModuleDocs),
+}
+
+/// A line of a doc comment
+#[derive(Debug, Clone)]
+pub enum DocLine {
+    /// Just text
+    Text(String),
+    /// An example
+    Example(DocExample),
+}
+
+/// A code example in a doc comment
+///
+/// Examples are written like the ones in primitive documentation.
+/// A line starting with `ex:` begins an example, `ex!` begins an example that should fail,
+/// and a line starting with `:` continues the previous example.
+///
+/// A line starting with `out:` after an example begins its expected output,
+/// which `uiua test` checks. Later lines starting with `:` continue the expected output.
+#[derive(Debug, Clone)]
+pub struct DocExample {
+    /// The example's source code
+    pub input: String,
+    /// Whether the example should fail
+    pub should_error: bool,
+    /// The output the example is expected to have
+    pub expected: Option<String>,
+    /// What the example printed to stdout
+    pub printed: String,
+    /// The formatted values the example left on the stack, or its error message
+    pub output: Result<Vec<String>, String>,
+}
+
+/// A format to render documentation in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DocFormat {
+    /// Markdown, which uiua.org renders with runnable examples
+    #[default]
+    Markdown,
+    /// A standalone HTML page
+    Html,
+}
+
+impl ModuleDocs {
+    /// Generate documentation for the file at the given path
+    ///
+    /// The file is compiled and run with the given backend.
+    /// Examples are run without IO after the file has been run.
+    pub fn from_file(path: impl AsRef<Path>, backend: impl IntoSysBackend) -> UiuaResult<Self> {
+        let path = path.as_ref();
+        let source =
+            fs::read_to_string(path).map_err(|e| UiuaErrorKind::Load(path.into(), e.into()))?;
+        let mut comp = Compiler::with_backend(backend);
+        comp.mode(RunMode::Normal).load_file(path)?;
+        let mut env = Uiua::with_backend(comp.backend());
+        env.run_compiler(&mut comp)?;
+        let name: Ident = (path.file_stem())
+            .map(|stem| stem.to_string_lossy().into())
+            .unwrap_or_default();
+        Ok(Self::from_compiler(name, source, comp))
+    }
+    /// Generate documentation from a compiler that has already compiled and run some code
+    ///
+    /// Examples are run without IO.
+    pub fn from_compiler(
+        name: impl Into<Ident>,
+        source: impl Into<EcoString>,
+        mut comp: Compiler,
+    ) -> Self {
+        comp.mode(RunMode::Normal).assembly_mut().root.clear();
+        let module = comp.scope_module();
+        Self::new(name.into(), "", &module, &comp, &source.into())
+    }
+    fn new(
+        name: Ident,
+        prefix: &str,
+        module: &Module,
+        comp: &Compiler,
+        source: &EcoString,
+    ) -> Self {
+        let lines = (module.comment.as_deref())
+            .map(|text| doc_lines(text, comp))
+            .unwrap_or_default();
+        let mut items = Vec::new();
+        for (local_name, local) in &module.names {
+            if !local.public {
+                continue;
+            }
+            let binding = &comp.assembly().bindings[local.index];
+            let name: EcoString = format!("{prefix}{local_name}").into();
+            let kind = match &binding.kind {
+                BindingKind::Const(_) => ItemKind::Constant,
+                BindingKind::Func(_) => ItemKind::Function,
+                BindingKind::IndexMacro(_) => ItemKind::IndexMacro,
+                BindingKind::CodeMacro(_) => ItemKind::CodeMacro,
+                BindingKind::Module(m) => ItemKind::Module(ModuleDocs::new(
+                    local_name.clone(),
+                    &format!("{name}~"),
+                    m,
+                    comp,
+                    source,
+                )),
+                BindingKind::Import(_) | BindingKind::Error => continue,
+            };
+            let comment = binding.meta.comment.as_ref();
+            let signature = match (comment.and_then(|c| c.sig.as_ref()), binding.kind.sig()) {
+                (Some(doc_sig), _) => Some(doc_sig.to_string().trim().into()),
+                (None, Some(sig)) if !matches!(kind, ItemKind::Constant) => Some(sig.to_string()),
+                _ => None,
+            };
+            let lines = comment
+                .map(|c| doc_lines(&c.text, comp))
+                .unwrap_or_default();
+            items.push(ItemDocs {
+                name,
+                kind,
+                signature,
+                lines,
+                deprecation: binding.meta.deprecation.clone(),
+            });
+        }
+        ModuleDocs {
+            name,
+            lines,
+            items,
+            source: source.clone(),
+        }
+    }
+    /// Get the examples that failed when they should have succeeded or vice versa
+    ///
+    /// Each example is paired with the name of the item it documents
+    pub fn unexpected_examples(&self) -> Vec<(&str, &DocExample)> {
+        fn examples<'a>(
+            name: &'a str,
+            lines: &'a [DocLine],
+        ) -> impl Iterator<Item = (&'a str, &'a DocExample)> {
+            lines.iter().filter_map(move |line| match line {
+                DocLine::Example(ex) if ex.output.is_err() != ex.should_error => Some((name, ex)),
+                _ => None,
+            })
+        }
+        let mut unexpected: Vec<_> = examples(&self.name, &self.lines).collect();
+        for item in &self.items {
+            unexpected.extend(examples(&item.name, &item.lines));
+            if let ItemKind::Module(module) = &item.kind {
+                unexpected.extend(module.unexpected_examples());
+            }
+        }
+        unexpected
+    }
+    /// Get the examples whose output does not match their expected output
+    ///
+    /// Each example is paired with the name of the item it documents
+    pub fn mismatched_examples(&self) -> Vec<(&str, &DocExample)> {
+        fn examples<'a>(
+            name: &'a str,
+            lines: &'a [DocLine],
+        ) -> impl Iterator<Item = (&'a str, &'a DocExample)> {
+            lines.iter().filter_map(move |line| match line {
+                DocLine::Example(ex) if ex.diff().is_some() => Some((name, ex)),
+                _ => None,
+            })
+        }
+        let mut mismatched: Vec<_> = examples(&self.name, &self.lines).collect();
+        for item in &self.items {
+            mismatched.extend(examples(&item.name, &item.lines));
+            if let ItemKind::Module(module) = &item.kind {
+                mismatched.extend(module.mismatched_examples());
+            }
+        }
+        mismatched
+    }
+    /// Count the examples that have an expected output
+    pub fn checked_example_count(&self) -> usize {
+        let count = |lines: &[DocLine]| {
+            (lines.iter())
+                .filter(|line| matches!(line, DocLine::Example(ex) if ex.expected.is_some()))
+                .count()
+        };
+        let mut total = count(&self.lines);
+        for item in &self.items {
+            total += count(&item.lines);
+            if let ItemKind::Module(module) = &item.kind {
+                total += module.checked_example_count();
+            }
+        }
+        total
+    }
+    /// Render the documentation in a format
+    pub fn render(&self, format: DocFormat) -> String {
+        match format {
+            DocFormat::Markdown => self.markdown(),
+            DocFormat::Html => self.html(),
+        }
+    }
+    /// Render the documentation as Markdown
+    pub fn markdown(&self) -> String {
+        let mut s = String::new();
+        _ = writeln!(s, "# {}\n", self.name);
+        self.markdown_lines(&mut s, &self.lines);
+        self.markdown_items(&mut s, 2);
+        while s.ends_with("\n\n") {
+            s.pop();
+        }
+        s
+    }
+    fn markdown_items(&self, s: &mut String, depth: usize) {
+        for item in &self.items {
+            _ = writeln!(s, "{} `{}`\n", "#".repeat(depth.min(6)), item.name);
+            _ = write!(s, "*{}*", item.kind.name());
+            if let Some(sig) = &item.signature {
+                _ = write!(s, " `{sig}`");
+            }
+            s.push_str("\n\n");
+            if let Some(deprecation) = &item.deprecation {
+                _ = writeln!(s, "> **Deprecated**{}\n", deprecation_suffix(deprecation));
+            }
+            self.markdown_lines(s, item.lines());
+            if let ItemKind::Module(module) = &item.kind {
+                module.markdown_items(s, depth + 1);
+            }
+        }
+    }
+    fn markdown_lines(&self, s: &mut String, lines: &[DocLine]) {
+        for (i, line) in lines.iter().enumerate() {
+            match line {
+                DocLine::Text(text) => {
+                    _ = writeln!(s, "{text}");
+                    if !matches!(lines.get(i + 1), Some(DocLine::Text(_))) {
+                        s.push('\n');
+                    }
+                }
+                DocLine::Example(ex) => {
+                    _ = writeln!(s, "```uiua\n{}\n```", ex.input);
+                    match &ex.output {
+                        Ok(values) if !values.is_empty() => {
+                            _ = writeln!(s, "```\n{}\n```", values.join("\n"));
+                        }
+                        Ok(_) => {}
+                        Err(message) => _ = writeln!(s, "```\nError: {message}\n```"),
+                    }
+                    _ = writeln!(s, "[Run]({})\n", self.pad_url(ex));
+                }
+            }
+        }
+    }
+    /// Render the documentation as a standalone HTML page
+    pub fn html(&self) -> String {
+        let mut s = String::new();
+        _ = writeln!(
+            s,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>",
+            escape_html(&self.name)
+        );
+        s.push_str(HTML_STYLE);
+        s.push_str("</head>\n<body>\n");
+        _ = writeln!(s, "<h1>{}</h1>", escape_html(&self.name));
+        self.html_lines(&mut s, &self.lines);
+        self.html_items(&mut s, 2);
+        s.push_str("</body>\n</html>\n");
+        s
+    }
+    fn html_items(&self, s: &mut String, depth: usize) {
+        for item in &self.items {
+            let name = escape_html(&item.name);
+            let h = depth.min(6);
+            _ = writeln!(s, "<h{h} id=\"{name}\"><code>{name}</code></h{h}>");
+            _ = write!(s, "<p class=\"kind\"><em>{}</em>", item.kind.name());
+            if let Some(sig) = &item.signature {
+                _ = write!(s, " <code>{}</code>", escape_html(sig));
+            }
+            s.push_str("</p>\n");
+            if let Some(deprecation) = &item.deprecation {
+                _ = writeln!(
+                    s,
+                    "<p class=\"deprecated\"><strong>Deprecated</strong>{}</p>",
+                    escape_html(&deprecation_suffix(deprecation))
+                );
+            }
+            self.html_lines(s, item.lines());
+            if let ItemKind::Module(module) = &item.kind {
+                _ = writeln!(s, "<div class=\"module\">");
+                module.html_items(s, depth + 1);
+                _ = writeln!(s, "</div>");
+            }
+        }
+    }
+    fn html_lines(&self, s: &mut String, lines: &[DocLine]) {
+        let mut paragraph = Vec::new();
+        let flush = |s: &mut String, paragraph: &mut Vec<&str>| {
+            if !paragraph.is_empty() {
+                _ = writeln!(s, "<p>{}</p>", inline_code_html(&paragraph.join(" ")));
+                paragraph.clear();
+            }
+        };
+        for line in lines {
+            match line {
+                DocLine::Text(text) if text.trim().is_empty() => flush(s, &mut paragraph),
+                DocLine::Text(text) => paragraph.push(text),
+                DocLine::Example(ex) => {
+                    flush(s, &mut paragraph);
+                    _ = write!(
+                        s,
+                        "<div class=\"example\">\n<pre><code>{}</code></pre>\n",
+                        escape_html(&ex.input)
+                    );
+                    match &ex.output {
+                        Ok(values) if !values.is_empty() => {
+                            _ = writeln!(
+                                s,
+                                "<pre class=\"output\">{}</pre>",
+                                escape_html(&values.join("\n"))
+                            )
+                        }
+                        Ok(_) => {}
+                        Err(message) => {
+                            _ = writeln!(
+                                s,
+                                "<pre class=\"output error\">Error: {}</pre>",
+                                escape_html(message)
+                            )
+                        }
+                    }
+                    _ = writeln!(s, "<a href=\"{}\">Run</a>\n</div>", self.pad_url(ex));
+                }
+            }
+        }
+        flush(s, &mut paragraph);
+    }
+    /// Get a link to the example in the online pad
+    ///
+    /// The module's source is included so that the example can use its bindings.
+    fn pad_url(&self, ex: &DocExample) -> String {
+        let code = format!("{}\n{}", self.source.trim_end(), ex.input);
+        format!(
+            "https://uiua.org/pad?src={}__{}",
+            VERSION.replace('.', "_"),
+            URL_SAFE.encode(code)
+        )
+    }
+}
+
+impl DocExample {
+    /// Get the example's output as it would be printed
+    ///
+    /// This is anything the example printed followed by its stack or its error
+    pub fn output_text(&self) -> String {
+        let mut text = self.printed.clone();
+        match &self.output {
+            Ok(values) => {
+                for value in values {
+                    text.push_str(value);
+                    text.push('\n');
+                }
+            }
+            Err(message) => _ = writeln!(text, "Error: {message}"),
+        }
+        text
+    }
+    /// Get a diff between the example's expected and actual output
+    ///
+    /// Returns `None` if the example has no expected output or if they match.
+    /// Trailing whitespace is ignored.
+    pub fn diff(&self) -> Option<String> {
+        let expected: Vec<&str> = trimmed_lines(self.expected.as_deref()?);
+        let actual = self.output_text();
+        let actual: Vec<&str> = trimmed_lines(&actual);
+        if expected == actual {
+            return None;
+        }
+        // Find the longest common subsequence of lines
+        let mut lcs = vec![vec![0; actual.len() + 1]; expected.len() + 1];
+        for i in (0..expected.len()).rev() {
+            for j in (0..actual.len()).rev() {
+                lcs[i][j] = if expected[i] == actual[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+        let mut diff = String::new();
+        let (mut i, mut j) = (0, 0);
+        while i < expected.len() || j < actual.len() {
+            if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+                _ = writeln!(diff, "  {}", expected[i]);
+                i += 1;
+                j += 1;
+            } else if j == actual.len() || i < expected.len() && lcs[i + 1][j] >= lcs[i][j + 1] {
+                _ = writeln!(diff, "- {}", expected[i]);
+                i += 1;
+            } else {
+                _ = writeln!(diff, "+ {}", actual[j]);
+                j += 1;
+            }
+        }
+        Some(diff)
+    }
+}
+
+fn trimmed_lines(text: &str) -> Vec<&str> {
+    let mut lines: Vec<&str> = text.lines().map(str::trim_end).collect();
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    lines
+}
+
+impl ItemDocs {
+    /// The lines to show for the item
+    ///
+    /// A module without a doc comment of its own uses its top-level comment.
+    fn lines(&self) -> &[DocLine] {
+        match &self.kind {
+            ItemKind::Module(module) if self.lines.is_empty() => &module.lines,
+            _ => &self.lines,
+        }
+    }
+}
+
+impl ItemKind {
+    fn name(&self) -> &'static str {
+        match self {
+            ItemKind::Constant => "constant",
+            ItemKind::Function => "function",
+            ItemKind::IndexMacro => "index macro",
+            ItemKind::CodeMacro => "code macro",
+            ItemKind::Module(_) => "module",
+        }
+    }
+}
+
+/// Split a doc comment into text and examples, running the examples
+fn doc_lines(text: &str, comp: &Compiler) -> Vec<DocLine> {
+    let mut lines = Vec::new();
+    for line in text.lines() {
+        let example = (line.strip_prefix("ex:").map(|ex| (ex, false)))
+            .or_else(|| line.strip_prefix("ex!").map(|ex| (ex, true)));
+        if let Some((input, should_error)) = example {
+            lines.push(DocLine::Example(DocExample {
+                input: input.strip_prefix(' ').unwrap_or(input).into(),
+                should_error,
+                expected: None,
+                printed: String::new(),
+                output: Ok(Vec::new()),
+            }));
+        } else if let (Some(output), Some(DocLine::Example(ex))) =
+            (line.strip_prefix("out:"), lines.last_mut())
+        {
+            ex.expected = Some(output.strip_prefix(' ').unwrap_or(output).into());
+        } else if let (Some(input), Some(DocLine::Example(ex))) =
+            (line.strip_prefix(':'), lines.last_mut())
+        {
+            let text = ex.expected.as_mut().unwrap_or(&mut ex.input);
+            text.push('\n');
+            text.push_str(input.strip_prefix(' ').unwrap_or(input));
+        } else {
+            lines.push(DocLine::Text(line.into()));
+        }
+    }
+    for line in &mut lines {
+        if let DocLine::Example(ex) = line {
+            run_example(comp, ex);
+        }
+    }
+    lines
+}
+
+fn run_example(comp: &Compiler, ex: &mut DocExample) {
+    let mut comp = comp.clone();
+    comp.set_backend(SafeSys::default());
+    let mut env = Uiua::with_safe_sys().with_execution_limit(EXAMPLE_TIME_LIMIT);
+    let res = (comp.load_str(&ex.input).map(drop)).and_then(|()| env.run_compiler(&mut comp));
+    if let Some(sys) = env.downcast_backend::<SafeSys>() {
+        ex.printed = String::from_utf8_lossy(&sys.take_stdout()).into();
+    }
+    ex.output = match res {
+        Ok(()) => Ok(env.take_stack().into_iter().map(|val| val.show()).collect()),
+        Err(e) => Err(match e.kind {
+            UiuaErrorKind::Run { message, .. } => message.value,
+            UiuaErrorKind::Throw(value, ..) => value.to_string(),
+            _ => e.to_string().lines().next().unwrap_or_default().into(),
+        }),
+    };
+}
+
+fn deprecation_suffix(deprecation: &str) -> String {
+    if deprecation.is_empty() {
+        String::new()
+    } else {
+        format!(": {deprecation}")
+    }
+}
+
+pub(crate) fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escape text and turn `backticked` spans into code elements
+pub(crate) fn inline_code_html(text: &str) -> String {
+    let mut s = String::new();
+    for (i, part) in text.split('`').enumerate() {
+        if i % 2 == 1 {
+            _ = write!(s, "<code>{}</code>", escape_html(part));
+        } else {
+            s.push_str(&escape_html(part));
+        }
+    }
+    s
+}
+
+const HTML_STYLE: &str = "<style>
+body { max-width: 50em; margin: auto; padding: 1em; font-family: sans-serif; }
+code, pre { font-family: \"Uiua386\", \"DejaVu Sans Mono\", monospace; }
+pre { padding: 0.5em; background-color: #0001; border-radius: 0.2em; }
+.example { margin-bottom: 1em; }
+.example pre { margin: 0.2em 0; }
+.output { color: #555; }
+.error { color: #a00; }
+.module { margin-left: 1em; }
+</style>
+";
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn doc_generation() {
+        use super::*;
+        let docs = ModuleDocs::from_file("tests_special/docs.ua", SafeSys::default()).unwrap();
+        assert!(docs.unexpected_examples().is_empty());
+        let names: Vec<_> = (docs.items.iter()).map(|item| item.name.as_str()).collect();
+        assert_eq!(names, ["Inc", "Sq", "Geo"]);
+        assert_eq!(docs.items[1].signature.as_deref(), Some("x ? n"));
+        let ItemKind::Module(geo) = &docs.items[2].kind else {
+            panic!("Geo is not a module");
+        };
+        assert_eq!(geo.items[0].name, "Geo~Area");
+        let markdown = docs.markdown();
+        assert!(markdown.contains("```uiua\nInc [1 2 3]\nInc\n```\n```\n[3 4 5]\n```"));
+        assert!(markdown.contains("### `Geo~Area`"));
+        let html = docs.html();
+        assert!(html.contains("<pre class=\"output error\">Error: Cannot multiply"));
+        // Expected outputs
+        assert_eq!(docs.checked_example_count(), 2);
+        assert!(docs.mismatched_examples().is_empty());
+        let code = "\
+# Doubling
+
+# ex: &p \"hi\" F 2
+# out: hi
+#    : 5
+F ← ×2";
+        let mut comp = Compiler::new();
+        comp.load_str(code).unwrap();
+        Uiua::with_safe_sys().run_compiler(&mut comp).unwrap();
+        let docs = ModuleDocs::from_compiler("test", code, comp);
+        let mismatched = docs.mismatched_examples();
+        assert_eq!(mismatched.len(), 1);
+        assert_eq!(mismatched[0].1.diff().unwrap(), "  hi\n- 5\n+ 4\n");
+    }
+}
@@ -1,8 +1,10 @@
 use std::{convert::Infallible, error::Error, fmt, io, mem::take, path::PathBuf, sync::Arc};
 
 use colored::*;
+use serde::{Deserialize, Serialize};
 
 use crate::{
+    explain::error_code,
     function::FunctionId,
     lex::{Sp, Span},
     parse::ParseError,
@@ -154,9 +156,17 @@ impl UiuaError {
         self.trace.push(frame);
         self
     }
-    pub(crate) fn trace_macro(mut self, name: Option<Ident>, span: CodeSpan) -> Self {
+    /// Add a macro expansion to the trace of the error
+    ///
+    /// `span` is where the macro was invoked, and `definition` is where it was defined.
+    pub(crate) fn trace_macro(
+        mut self,
+        name: Option<Ident>,
+        span: CodeSpan,
+        definition: CodeSpan,
+    ) -> Self {
         let frame = TraceFrame {
-            id: Some(FunctionId::Macro(name, span.clone())),
+            id: Some(FunctionId::Macro(name, definition)),
             span: Span::Code(span),
         };
         self.trace.push(frame);
@@ -180,11 +190,33 @@ impl UiuaError {
     }
 }
 
-fn format_trace(trace: &[TraceFrame]) -> Vec<String> {
-    let max_id_length = trace
-        .iter()
-        .filter(|frame| frame.span != Span::Builtin)
-        .map(|frame| (frame.id.as_ref()).map_or(0, |id| id.to_string().chars().count()))
+/// Format the lines of a trace, along with the span of each line's call
+fn format_trace(trace: &[TraceFrame]) -> Vec<(String, Option<CodeSpan>)> {
+    // Frames with neither a function nor a span have no line, so they are not part of cycles
+    let trace: Vec<&TraceFrame> = (trace.iter())
+        .filter(|frame| frame.id.is_some() || frame.span != Span::Builtin)
+        .collect();
+    // The function that made the call in a frame
+    let caller = |i: usize| {
+        let id = trace[i + 1..].iter().find_map(|frame| frame.id.as_ref())?;
+        match id {
+            FunctionId::Main => None,
+            FunctionId::Macro(Some(name), _) => Some(name.to_string()),
+            id => Some(id.to_string()),
+        }
+    };
+    let labels: Vec<Option<String>> = (0..trace.len())
+        .map(|i| {
+            let id = trace[i].id.as_ref()?;
+            Some(match caller(i) {
+                Some(caller) => format!("{id}, called from {caller}"),
+                None => id.to_string(),
+            })
+        })
+        .collect();
+    let max_label_length = (trace.iter().zip(&labels))
+        .filter(|(frame, _)| frame.span != Span::Builtin)
+        .map(|(_, label)| label.as_ref().map_or(0, |label| label.chars().count()))
         .max()
         .unwrap_or(0);
     let max_span_length = trace
@@ -195,7 +227,7 @@ fn format_trace(trace: &[TraceFrame]) -> Vec<String> {
         })
         .max()
         .unwrap_or(0);
-    let mut lines: Vec<String> = Vec::new();
+    let mut lines: Vec<(String, Option<CodeSpan>)> = Vec::new();
     let mut i = 0;
     'outer: while i < trace.len() {
         let frame = &trace[i];
@@ -203,16 +235,16 @@ fn format_trace(trace: &[TraceFrame]) -> Vec<String> {
             i += 1;
             continue;
         }
-        // Look for cycles
+        // Look for cycles of calls from the same places
         for n in 1..=4 {
             if i >= n
                 && i + n < trace.len()
                 && trace[i - n..][..n]
                     .iter()
                     .zip(&trace[i..][..n])
-                    .all(|(a, b)| a.id == b.id)
+                    .all(|(a, b)| a.id == b.id && a.span == b.span)
             {
-                for (i, line) in lines.iter_mut().rev().take(n).rev().enumerate() {
+                for (i, (line, _)) in lines.iter_mut().rev().take(n).rev().enumerate() {
                     let sep = match (n, i) {
                         (1, _) => " ×",
                         (_, 0) => " ┬×",
@@ -237,13 +269,14 @@ fn format_trace(trace: &[TraceFrame]) -> Vec<String> {
                 continue 'outer;
             }
         }
-        lines.push(match (&frame.id, &frame.span) {
-            (Some(id), Span::Code(span)) => {
-                format!("  in {id:max_id_length$} at {span:max_span_length$}")
-            }
-            (Some(id), Span::Builtin) => format!("  in {id:max_id_length$}"),
+        lines.push(match (&labels[i], &frame.span) {
+            (Some(label), Span::Code(span)) => (
+                format!("  in {label:max_label_length$} at {span:max_span_length$}"),
+                Some(span.clone()),
+            ),
+            (Some(label), Span::Builtin) => (format!("  in {label:max_label_length$}"), None),
             (None, Span::Code(span)) => {
-                format!("  at {span:max_span_length$}")
+                (format!("  at {span:max_span_length$}"), Some(span.clone()))
             }
             (None, Span::Builtin) => {
                 i += 1;
@@ -255,6 +288,49 @@ fn format_trace(trace: &[TraceFrame]) -> Vec<String> {
     lines
 }
 
+/// Push the line of code at a span with the span underlined
+fn push_excerpt(
+    fragments: &mut Vec<ReportFragment>,
+    span: &CodeSpan,
+    inputs: &Inputs,
+    kind: ReportKind,
+    indent: &str,
+) {
+    let line_prefix = format!("{} | ", span.start.line);
+    fragments.push(ReportFragment::Fainter(line_prefix.clone()));
+    let input = inputs.get(&span.src);
+    let line = input
+        .lines()
+        .nth(span.start.line as usize - 1)
+        .unwrap_or("");
+    let start_char_pos = span.start.col - 1;
+    let end_char_pos = if span.start.line == span.end.line {
+        span.end.col - 1
+    } else {
+        line.chars().count() as u16
+    };
+    let pre_color: String = line.chars().take(start_char_pos as usize).collect();
+    let color: String = line
+        .chars()
+        .skip(start_char_pos as usize)
+        .take(end_char_pos.saturating_sub(start_char_pos).max(1) as usize)
+        .collect();
+    let post_color: String = line.chars().skip(end_char_pos as usize).collect();
+    fragments.push(ReportFragment::Faint(pre_color));
+    fragments.push(ReportFragment::Colored(color, kind));
+    fragments.push(ReportFragment::Faint(post_color));
+    fragments.push(ReportFragment::Newline);
+    fragments.push(ReportFragment::Plain(format!(
+        "{indent}{}",
+        " ".repeat(line_prefix.chars().count())
+    )));
+    fragments.push(ReportFragment::Plain(" ".repeat(start_char_pos as usize)));
+    fragments.push(ReportFragment::Colored(
+        "─".repeat(end_char_pos.saturating_sub(start_char_pos).max(1) as usize),
+        kind,
+    ));
+}
+
 impl Error for UiuaError {}
 
 impl From<Infallible> for UiuaError {
@@ -266,6 +342,13 @@ impl From<Infallible> for UiuaError {
 impl UiuaError {
     /// Get a rich-text report for the error
     pub fn report(&self) -> Report {
+        self.report_impl(false)
+    }
+    /// Get a rich-text report for the error that shows the code at each frame of its trace
+    pub fn full_report(&self) -> Report {
+        self.report_impl(true)
+    }
+    fn report_impl(&self, full_trace: bool) -> Report {
         let kind = ReportKind::Error;
         let mut report = match &self.kind {
             UiuaErrorKind::Parse(errors, inputs) => Report::new_multi(
@@ -279,30 +362,16 @@ impl UiuaError {
                 message,
                 info,
                 inputs,
-            } => {
-                let mut report =
-                    Report::new_multi(kind, inputs, [(&message.value, message.span.clone())]);
-                for info in info {
-                    report.fragments.push(ReportFragment::Newline);
-                    report.fragments.extend(
-                        Report::new_multi(
-                            DiagnosticKind::Info.into(),
-                            inputs,
-                            [(&info.value, info.span.clone())],
-                        )
-                        .fragments,
-                    );
-                }
-                report
-            }
+            } => Report::new_multi(kind, inputs, [(&message.value, message.span.clone())])
+                .code(&message.value)
+                .info(info, inputs),
             UiuaErrorKind::Throw(message, span, inputs) => {
                 Report::new_multi(kind, inputs, [(&message, span.clone())])
             }
-            UiuaErrorKind::Timeout(span, inputs) => Report::new_multi(
-                kind,
-                inputs,
-                [("Maximum execution time exceeded", span.clone())],
-            ),
+            UiuaErrorKind::Timeout(span, inputs) => {
+                let message = "Maximum execution time exceeded";
+                Report::new_multi(kind, inputs, [(message, span.clone())]).code(message)
+            }
             UiuaErrorKind::CompilerPanic(message) => Report::new(kind, message),
             UiuaErrorKind::Load(..) | UiuaErrorKind::Format(..) => {
                 Report::new(kind, self.to_string())
@@ -314,7 +383,6 @@ impl UiuaError {
                 }
             }
         };
-        report = report.trace(&self.trace);
         let default_inputs = Inputs::default();
         let inputs = match &self.kind {
             UiuaErrorKind::Parse(_, inputs)
@@ -323,6 +391,11 @@ impl UiuaError {
             | UiuaErrorKind::Timeout(_, inputs) => inputs,
             _ => &default_inputs,
         };
+        report = if full_trace {
+            report.full_trace(&self.trace, inputs)
+        } else {
+            report.trace(&self.trace)
+        };
         for (info, span) in &self.infos {
             if let Some(span) = span {
                 report.fragments.extend(
@@ -341,7 +414,9 @@ impl UiuaError {
         }
         for error in &self.multi {
             report.fragments.push(ReportFragment::Newline);
-            report.fragments.extend(error.report().fragments);
+            report
+                .fragments
+                .extend(error.report_impl(full_trace).fragments);
         }
         report
     }
@@ -356,6 +431,8 @@ pub struct Diagnostic {
     pub message: String,
     /// What kind of diagnostic this is
     pub kind: DiagnosticKind,
+    /// Notes about related code
+    pub info: Vec<Sp<String, Span>>,
     /// The inputs of the program
     pub inputs: Inputs,
 }
@@ -381,7 +458,7 @@ impl Ord for Diagnostic {
 }
 
 /// Kinds of non-error diagnostics
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum DiagnosticKind {
     /// Informational message
     Info,
@@ -411,9 +488,15 @@ impl Diagnostic {
             message,
             span: span.into(),
             kind,
+            info: Vec::new(),
             inputs,
         }
     }
+    /// Add a note about some related code
+    pub fn with_info(mut self, span: impl Into<Span>, message: impl Into<String>) -> Self {
+        self.info.push(span.into().sp(message.into()));
+        self
+    }
     /// Get a rich-text report for the diagnostic
     pub fn report(&self) -> Report {
         Report::new_multi(
@@ -421,11 +504,13 @@ impl Diagnostic {
             &self.inputs,
             [(&self.message, self.span.clone())],
         )
+        .code(&self.message)
+        .info(&self.info, &self.inputs)
     }
 }
 
 /// Kinds of reports
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ReportKind {
     /// An error
     Error,
@@ -453,7 +538,7 @@ impl ReportKind {
 }
 
 /// A text fragment of a report
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ReportFragment {
     /// Just plain text
     Plain(String),
@@ -468,7 +553,7 @@ pub enum ReportFragment {
 }
 
 /// A rich-text error/diagnostic report
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Report {
     /// The rich-text fragments of the report
     pub fragments: Vec<ReportFragment>,
@@ -498,14 +583,55 @@ impl Report {
         self.color = color;
         self
     }
+    /// Add the code of the error with the given message to the report's label, if it has one
+    fn code(mut self, message: &str) -> Self {
+        if let (Some(code), Some(&ReportFragment::Colored(_, kind))) =
+            (error_code(message), self.fragments.first())
+        {
+            (self.fragments).insert(1, ReportFragment::Colored(format!("[{code}]"), kind));
+        }
+        self
+    }
+    /// Add notes about related code to the report
+    pub fn info(mut self, info: &[Sp<String, Span>], inputs: &Inputs) -> Self {
+        for info in info {
+            self.fragments.push(ReportFragment::Newline);
+            self.fragments.extend(
+                Report::new_multi(
+                    DiagnosticKind::Info.into(),
+                    inputs,
+                    [(&info.value, info.span.clone())],
+                )
+                .fragments,
+            );
+        }
+        self
+    }
     /// Add a trace to the report
     pub fn trace(mut self, trace: &[TraceFrame]) -> Self {
-        for line in format_trace(trace) {
+        for (line, _) in format_trace(trace) {
             self.fragments.push(ReportFragment::Newline);
             self.fragments.push(ReportFragment::Plain(line));
         }
         self
     }
+    /// Add a trace to the report, showing the code at each frame
+    pub fn full_trace(mut self, trace: &[TraceFrame], inputs: &Inputs) -> Self {
+        let kind = DiagnosticKind::Info.into();
+        for (line, span) in format_trace(trace) {
+            self.fragments.push(ReportFragment::Newline);
+            self.fragments.push(ReportFragment::Plain(line));
+            if let Some(mut span) = span {
+                while let InputSrc::Macro(inner) = span.src {
+                    span = *inner;
+                }
+                self.fragments.push(ReportFragment::Newline);
+                self.fragments.push(ReportFragment::Plain("  ".into()));
+                push_excerpt(&mut self.fragments, &span, inputs, kind, "  ");
+            }
+        }
+        self
+    }
     /// Create a new report
     pub fn new(kind: ReportKind, message: impl Into<String>) -> Self {
         let message = message.into();
@@ -557,38 +683,7 @@ impl Report {
                     span.start.line, span.start.col
                 )));
                 fragments.push(ReportFragment::Newline);
-                let line_prefix = format!("{} | ", span.start.line);
-                fragments.push(ReportFragment::Plain(line_prefix.clone()));
-                let input = inputs.get(&span.src);
-                let line = input
-                    .lines()
-                    .nth(span.start.line as usize - 1)
-                    .unwrap_or("");
-                let start_char_pos = span.start.col - 1;
-                let end_char_pos = if span.start.line == span.end.line {
-                    span.end.col - 1
-                } else {
-                    line.chars().count() as u16
-                };
-                let pre_color: String = line.chars().take(start_char_pos as usize).collect();
-                let color: String = line
-                    .chars()
-                    .skip(start_char_pos as usize)
-                    .take(end_char_pos.saturating_sub(start_char_pos).max(1) as usize)
-                    .collect();
-                let post_color: String = line.chars().skip(end_char_pos as usize).collect();
-                fragments.push(ReportFragment::Faint(pre_color));
-                fragments.push(ReportFragment::Colored(color, kind));
-                fragments.push(ReportFragment::Faint(post_color));
-                fragments.push(ReportFragment::Newline);
-                fragments.push(ReportFragment::Plain(
-                    " ".repeat(line_prefix.chars().count()),
-                ));
-                fragments.push(ReportFragment::Plain(" ".repeat(start_char_pos as usize)));
-                fragments.push(ReportFragment::Colored(
-                    "─".repeat(end_char_pos.saturating_sub(start_char_pos).max(1) as usize),
-                    kind,
-                ));
+                push_excerpt(&mut fragments, &span, inputs, kind, "");
             }
         }
         Self {
@@ -656,9 +751,14 @@ impl fmt::Display for Report {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         for frag in &self.fragments {
             match frag {
-                ReportFragment::Plain(s)
-                | ReportFragment::Faint(s)
-                | ReportFragment::Fainter(s) => write!(f, "{s}")?,
+                ReportFragment::Plain(s) | ReportFragment::Faint(s) => write!(f, "{s}")?,
+                ReportFragment::Fainter(s) => {
+                    if self.color {
+                        write!(f, "{}", s.dimmed())?
+                    } else {
+                        write!(f, "{s}")?
+                    }
+                }
                 ReportFragment::Colored(s, kind) => {
                     if self.color {
                         let s = s.color(match kind {
@@ -672,6 +772,7 @@ impl fmt::Display for Report {
                             },
                             ReportKind::Diagnostic(DiagnosticKind::Info) => Color::BrightCyan,
                         });
+                        let s = s.bold();
                         write!(f, "{s}")?
                     } else {
                         write!(f, "{s}")?
@@ -683,3 +784,82 @@ impl fmt::Display for Report {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn macro_error_trace() {
+        use crate::*;
+        let code = "F! ←^ $\"_ Foo\" ◌\nG! ← F!^0\nG!1";
+        let Err(err) = Compiler::new().load_str(code).map(drop) else {
+            panic!("Expansion did not fail");
+        };
+        let report = err.report().color(false).to_string();
+        let lines: Vec<_> = (report.lines())
+            .filter(|line| line.contains("in macro"))
+            .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "in macro F! (defined at 1:1), called from G! at 2:6",
+                "in macro G! (defined at 2:1) at 3:1"
+            ]
+        );
+    }
+
+    #[test]
+    fn call_chain_trace() {
+        use crate::*;
+        let code = "F ← ⍤\"x\" 0\n⊞(≡F) [1 2] [3 4]";
+        let Err(err) = Uiua::with_safe_sys().run_str(code) else {
+            panic!("Code did not fail");
+        };
+        let trace = |report: Report| -> Vec<String> {
+            (report.color(false).to_string().lines())
+                .skip_while(|line| !line.starts_with("  in"))
+                .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+                .collect()
+        };
+        assert_eq!(
+            trace(err.report()),
+            [
+                "in F, called from ≡ rows at 2:4",
+                "in ≡ rows, called from ⊞ table at 2:3",
+                "in ⊞ table at 2:1",
+            ]
+        );
+        assert_eq!(
+            trace(err.full_report()),
+            [
+                "in F, called from ≡ rows at 2:4",
+                "2 | ⊞(≡F) [1 2] [3 4]",
+                "─",
+                "in ≡ rows, called from ⊞ table at 2:3",
+                "2 | ⊞(≡F) [1 2] [3 4]",
+                "─",
+                "in ⊞ table at 2:1",
+                "2 | ⊞(≡F) [1 2] [3 4]",
+                "─",
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_modifier_trace() {
+        use crate::*;
+        let code = "⍥(⊙⊙(+\"a\")) 3 1 2 3";
+        let Err(err) = Uiua::with_safe_sys().run_str(code) else {
+            panic!("Code did not fail");
+        };
+        let report = err.report().color(false).to_string();
+        let lines: Vec<_> = (report.lines())
+            .filter(|line| line.starts_with("  in"))
+            .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect();
+        assert_eq!(
+            lines,
+            ["in ⊙ dip, called from ⊙ dip at 1:4", "in ⊙ dip at 1:3"]
+        );
+    }
+}
@@ -0,0 +1,204 @@
+//! Stable codes and extended explanations for common errors
+//!
+//! Codes are assigned by matching error and diagnostic messages,
+//! so [`Explanation::matches`] must be kept in sync with the messages themselves.
+
+/// An extended explanation of an error
+#[derive(Debug, Clone, Copy)]
+pub struct Explanation {
+    /// The error's code
+    pub code: &'static str,
+    /// A short summary of the error
+    pub title: &'static str,
+    /// The explanation
+    pub text: &'static str,
+    /// Code that causes the error
+    pub example: &'static str,
+    /// Whether a message is for this error
+    pub matches: fn(&str) -> bool,
+}
+
+/// Type names as they appear in error messages
+const TYPE_NAMES: [&str; 4] = ["number", "character", "complex", "box"];
+
+/// All error explanations
+pub static EXPLANATIONS: &[Explanation] = &[
+    Explanation {
+        code: "E0001",
+        title: "Signature mismatch",
+        text: "\
+A function was declared with a signature that does not match the one inferred from its code.
+
+The declared signature wins, so the function is adjusted to fit it. \
+Extra arguments are popped, and missing outputs are filled with debug values.
+
+Either fix the declared signature or change the code so that it takes \
+and returns the declared number of values.",
+        example: "F ← |2 +1\nF 1 2",
+        matches: |message| message.starts_with("Signature mismatch: declared"),
+    },
+    Explanation {
+        code: "E0002",
+        title: "Incompatible shapes",
+        text: "\
+A pervasive function like `+` was called on arrays whose shapes are not compatible.
+
+Two shapes are compatible if one is a prefix of the other. \
+For example, `[2 3]` and `[2]` are compatible, but `[2]` and `[3]` are not.
+
+Use `fill ⬚` to extend the shorter array, \
+or `table ⊞` to combine every element with every other element.",
+        example: "+ [1 2] [1 2 3]",
+        matches: |message| {
+            message.starts_with("Shapes ") && message.ends_with(" are not compatible")
+        },
+    },
+    Explanation {
+        code: "E0003",
+        title: "Cannot couple differently shaped arrays",
+        text: "\
+`couple ⊟` makes an array with its two arguments as rows, so they must have the same shape.
+
+Use `fill ⬚` to pad the smaller array, or `box □` the arguments to couple them anyway.",
+        example: "⊟ [1 2] [1 2 3]",
+        matches: |message| message.starts_with("Cannot couple arrays with shapes"),
+    },
+    Explanation {
+        code: "E0004",
+        title: "Cannot join differently shaped arrays",
+        text: "\
+`join ⊂` adds the rows of one array to the other, \
+so the rows of both arrays must have the same shape.
+
+Use `fill ⬚` to pad the rows, or `box □` the arguments to join them anyway.",
+        example: "⊂ [1_2] [1 2 3]",
+        matches: |message| message.starts_with("Cannot join arrays of shapes"),
+    },
+    Explanation {
+        code: "E0005",
+        title: "Empty stack",
+        text: "\
+A function needed more arguments than there were values on the stack.
+
+Check the signatures of the functions involved. \
+Remember that code runs right to left, so arguments must be to the right of a function.",
+        example: "+ 1",
+        matches: |message| message.starts_with("Stack was empty when getting"),
+    },
+    Explanation {
+        code: "E0006",
+        title: "Unknown identifier",
+        text: "\
+A name was used that is not bound to anything in scope.
+
+Check the spelling, and make sure the binding comes before its use. \
+Bindings from other modules must be imported or accessed with `~`.",
+        example: "Foo 1",
+        matches: |message| message.starts_with("Unknown identifier"),
+    },
+    Explanation {
+        code: "E0007",
+        title: "Index out of bounds",
+        text: "\
+An index was greater than or equal to the length of the indexed axis.
+
+Indices start at 0, and negative indices count from the end. \
+Use `fill ⬚` to get a default value for out-of-bounds indices.",
+        example: "⊡ 5 [1 2 3]",
+        matches: |message| message.starts_with("Index ") && message.contains(" is out of bounds"),
+    },
+    Explanation {
+        code: "E0008",
+        title: "Unsupported types",
+        text: "\
+A function was called on values of types it does not work with, \
+such as adding two characters.
+
+Check which types the function supports in its documentation. \
+Characters can be converted to numbers by subtracting `@\\0`.",
+        example: "+ @a @b",
+        matches: |message| {
+            message.starts_with("Cannot ")
+                && TYPE_NAMES
+                    .iter()
+                    .any(|name| message.ends_with(&format!(" {name}")))
+        },
+    },
+    Explanation {
+        code: "E0009",
+        title: "Function pack without a modifier",
+        text: "\
+A function pack like `(+|×)` can only be used as the operand of a modifier.
+
+Either pass it to a modifier like `fork ⊃` or `switch ⨬`, \
+or remove the `|`s to make a normal inline function.",
+        example: "(+|×) 2 3",
+        matches: |message| message.starts_with("Function packs are not allowed"),
+    },
+    Explanation {
+        code: "E0010",
+        title: "Different numbers of rows",
+        text: "\
+A function that iterates over the rows of multiple arrays, like `rows ≡`, \
+was given arrays with different numbers of rows.
+
+Make the arrays the same length, or `fix ¤` an array to use it whole for every row.",
+        example: "≡⊂ [1 2] [1 2 3]",
+        matches: |message| message.contains("with different number of rows"),
+    },
+    Explanation {
+        code: "E0011",
+        title: "Maximum execution time exceeded",
+        text: "\
+The program ran for longer than the execution time limit.
+
+This is often caused by an infinite loop. \
+The limit can be changed with the `--limit` option.",
+        example: "⍥(+1)∞ 0",
+        matches: |message| message == "Maximum execution time exceeded",
+    },
+];
+
+/// Get the explanation for an error code
+pub fn explanation(code: &str) -> Option<&'static Explanation> {
+    EXPLANATIONS
+        .iter()
+        .find(|ex| ex.code.eq_ignore_ascii_case(code))
+}
+
+/// Get the code of the error with the given message
+pub fn error_code(message: &str) -> Option<&'static str> {
+    EXPLANATIONS
+        .iter()
+        .find(|ex| (ex.matches)(message))
+        .map(|ex| ex.code)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn error_codes() {
+        use super::*;
+        use crate::Uiua;
+        use std::{collections::HashSet, time::Duration};
+        let mut codes = HashSet::new();
+        for ex in EXPLANATIONS {
+            assert!(codes.insert(ex.code), "Duplicate error code {}", ex.code);
+            assert_eq!(explanation(ex.code).map(|e| e.code), Some(ex.code));
+            let mut rt = Uiua::with_safe_sys().with_execution_limit(Duration::from_secs(1));
+            let reports: Vec<String> = match rt.run_str(ex.example) {
+                Ok(mut comp) => (comp.take_diagnostics().into_iter())
+                    .map(|diag| diag.report().color(false).to_string())
+                    .collect(),
+                Err(e) => vec![e.report().color(false).to_string()],
+            };
+            let label = format!("[{}]: ", ex.code);
+            assert!(
+                reports.iter().any(|report| report.contains(&label)),
+                "Example for {} did not produce its error:\n{}",
+                ex.code,
+                reports.join("\n")
+            );
+        }
+    }
+}
@@ -5,6 +5,8 @@ use std::{
     str::FromStr,
 };
 
+use crate::Value;
+
 #[allow(dead_code)]
 pub(crate) const DEBUG: bool = false;
 
@@ -37,12 +39,22 @@ pub enum FfiType {
     Struct {
         fields: Vec<Self>,
     },
+    /// Marks where the variadic arguments of a function begin
+    Variadic,
 }
 
+/// A Uiua function that can be called from foreign code
+///
+/// It is passed the arguments of the call and returns its result.
+pub type FfiCallbackFn = Box<dyn Fn(Vec<Value>) -> Result<Value, String> + Send + Sync>;
+
 impl FromStr for FfiType {
     type Err = String;
     fn from_str(mut s: &str) -> Result<Self, String> {
         s = s.trim();
+        if s == "..." {
+            return Ok(FfiType::Variadic);
+        }
         // Parse const
         let mut mutable = true;
         if let Some(t) = s.strip_prefix("const ") {
@@ -164,6 +176,7 @@ impl fmt::Display for FfiType {
                 }
                 write!(f, "}}")
             }
+            FfiType::Variadic => write!(f, "..."),
         }
     }
 }
@@ -172,7 +185,7 @@ impl FfiType {
     /// Get the C-ABI-compatible size and alignment of a type
     pub fn size_align(&self) -> (usize, usize) {
         match self {
-            FfiType::Void => (0, 1),
+            FfiType::Void | FfiType::Variadic => (0, 1),
             FfiType::Char => (size_of::<c_char>(), align_of::<c_char>()),
             FfiType::Short => (size_of::<c_short>(), align_of::<c_short>()),
             FfiType::Int => (size_of::<c_int>(), align_of::<c_int>()),
@@ -192,11 +205,21 @@ impl FfiType {
     /// Check if a type is a scalar type
     pub fn is_scalar(&self) -> bool {
         match self {
-            FfiType::Void | FfiType::Ptr { .. } | FfiType::List { .. } => false,
+            FfiType::Void | FfiType::Ptr { .. } | FfiType::List { .. } | FfiType::Variadic => false,
             FfiType::Struct { fields } => fields.iter().all(|f| f.is_scalar() && *f == fields[0]),
             _ => true,
         }
     }
+    /// Get the type a variadic argument of this type is passed as
+    ///
+    /// C promotes small integers to `int` and `float`s to `double`.
+    pub fn promoted(&self) -> Self {
+        match self {
+            FfiType::Char | FfiType::Short | FfiType::UChar | FfiType::UShort => FfiType::Int,
+            FfiType::Float => FfiType::Double,
+            ty => ty.clone(),
+        }
+    }
 }
 
 fn struct_fields_size_align(fields: &[FfiType]) -> (usize, usize) {
@@ -224,14 +247,16 @@ mod enabled {
         any::{type_name, Any},
         mem::{forget, take, transmute},
         ptr, slice,
+        sync::Arc,
     };
 
     use dashmap::DashMap;
     use ecow::EcoVec;
-    use libffi::middle::*;
+    use libffi::{low, middle::*, raw};
+    use parking_lot::Mutex;
 
     use super::*;
-    use crate::{Array, Boxed, MetaPtr, Value};
+    use crate::{Array, Boxed, MetaPtr};
 
     macro_rules! dbgln {
         ($($arg:tt)*) => {
@@ -244,8 +269,27 @@ mod enabled {
     #[derive(Default)]
     pub struct FfiState {
         libraries: DashMap<String, libloading::Library>,
+        callbacks: Mutex<Vec<Callback>>,
+        callback_error: Arc<Mutex<Option<String>>>,
+    }
+
+    struct CallbackInfo {
+        result_ty: FfiType,
+        arg_tys: Vec<FfiType>,
+        f: FfiCallbackFn,
+        error: Arc<Mutex<Option<String>>>,
+    }
+
+    struct Callback {
+        // The closure must be dropped before the info it points to
+        _closure: Closure<'static>,
+        _info: Box<CallbackInfo>,
     }
 
+    // Safety: a callback's closure and info are never mutated after it is created
+    unsafe impl Send for Callback {}
+    unsafe impl Sync for Callback {}
+
     impl FfiState {
         pub(crate) fn do_ffi(
             &self,
@@ -264,6 +308,20 @@ mod enabled {
             let fptr: libloading::Symbol<unsafe extern "C" fn()> =
                 unsafe { lib.get(name.as_bytes()) }.map_err(|e| e.to_string())?;
 
+            // Split off variadic arguments
+            let fixed_count = arg_tys.iter().position(|ty| *ty == FfiType::Variadic);
+            let arg_tys: Vec<FfiType> = if let Some(n) = fixed_count {
+                let var_tys = &arg_tys[n + 1..];
+                if var_tys.contains(&FfiType::Variadic) {
+                    return Err("An FFI signature can only have one `...`".into());
+                }
+                (arg_tys[..n].iter().cloned())
+                    .chain(var_tys.iter().map(FfiType::promoted))
+                    .collect()
+            } else {
+                arg_tys.to_vec()
+            };
+
             let mut cif_arg_tys = Vec::new();
             let mut bindings = FfiBindings::default();
             let mut lengths: Vec<Option<usize>> = vec![None; arg_tys.len()];
@@ -334,7 +392,22 @@ mod enabled {
                 cif_arg_tys.len(),
                 bindings.args.len()
             );
+            let arg_count = cif_arg_tys.len();
             let cif = Cif::new(cif_arg_tys, ffity_to_cty(&return_ty));
+            if let Some(fixed_count) = fixed_count {
+                let raw_cif = cif.as_raw_ptr();
+                unsafe {
+                    low::prep_cif_var(
+                        raw_cif,
+                        low::ffi_abi_FFI_DEFAULT_ABI,
+                        fixed_count,
+                        arg_count,
+                        (*raw_cif).rtype,
+                        (*raw_cif).arg_types,
+                    )
+                }
+                .map_err(|e| format!("Invalid variadic FFI signature: {e:?}"))?;
+            }
             let fptr = CodePtr::from_fun(*fptr);
             let mut results = Vec::new();
 
@@ -440,37 +513,27 @@ mod enabled {
                 },
                 FfiType::Struct { fields } => {
                     let (size, _) = return_ty.size_align();
-                    let args = &bindings.args;
-                    macro_rules! call_ret_struct {
-                        ($n:literal) => {
-                            bindings.struct_repr_to_value(
-                                &unsafe { cif.call::<[u8; $n]>(fptr, args) },
-                                fields,
-                            )
-                        };
-                    }
-                    let val = match size {
-                        0 => Value::default(),
-                        1 => call_ret_struct!(1)?,
-                        2 => call_ret_struct!(2)?,
-                        4 => call_ret_struct!(4)?,
-                        8 => call_ret_struct!(8)?,
-                        12 => call_ret_struct!(12)?,
-                        16 => call_ret_struct!(16)?,
-                        20 => call_ret_struct!(20)?,
-                        24 => call_ret_struct!(24)?,
-                        32 => call_ret_struct!(32)?,
-                        48 => call_ret_struct!(48)?,
-                        64 => call_ret_struct!(64)?,
-                        128 => call_ret_struct!(128)?,
-                        192 => call_ret_struct!(192)?,
-                        256 => call_ret_struct!(256)?,
-                        384 => call_ret_struct!(384)?,
-                        512 => call_ret_struct!(512)?,
-                        n => return Err(format!("Unsupported return struct size: {n}")),
+                    // Use a buffer of u64s so that the struct is sufficiently aligned
+                    let mut ret = vec![0u64; size.div_ceil(size_of::<u64>()).max(1)];
+                    unsafe {
+                        raw::ffi_call(
+                            cif.as_raw_ptr(),
+                            Some(*fptr.as_fun()),
+                            ret.as_mut_ptr().cast(),
+                            bindings.args.as_ptr() as *mut *mut c_void,
+                        )
                     };
-                    results.push(val);
+                    let repr = unsafe { slice::from_raw_parts(ret.as_ptr().cast::<u8>(), size) };
+                    results.push(if size == 0 {
+                        Value::default()
+                    } else {
+                        bindings.struct_repr_to_value(repr, fields)?
+                    });
                 }
+                FfiType::Variadic => return Err("`...` is not a valid FFI return type".into()),
+            }
+            if let Some(error) = self.callback_error.lock().take() {
+                return Err(error);
             }
 
             // Get out parameters
@@ -636,6 +699,170 @@ mod enabled {
         }
     }
 
+    impl FfiState {
+        /// Create a C function pointer that calls a Uiua function
+        ///
+        /// Callbacks are never freed, so the pointer is valid for the rest of the program.
+        pub(crate) fn make_callback(
+            &self,
+            result_ty: FfiType,
+            arg_tys: &[FfiType],
+            f: FfiCallbackFn,
+        ) -> Result<Value, String> {
+            for ty in arg_tys.iter().chain([&result_ty]) {
+                if let FfiType::List { .. } | FfiType::Variadic = ty {
+                    return Err(format!("{ty} is not a supported FFI callback type"));
+                }
+            }
+            let cif = Cif::new(arg_tys.iter().map(ffity_to_cty), ffity_to_cty(&result_ty));
+            let info = Box::new(CallbackInfo {
+                result_ty,
+                arg_tys: arg_tys.to_vec(),
+                f,
+                error: self.callback_error.clone(),
+            });
+            // The info is stored alongside the closure, so it lives as long as the closure does
+            let info_ref = unsafe { &*(&*info as *const CallbackInfo) };
+            let closure = Closure::new(cif, call_callback, info_ref);
+            let ptr = *closure.code_ptr() as *const ();
+            self.callbacks.lock().push(Callback {
+                _closure: closure,
+                _info: info,
+            });
+            let mut val = Value::from(ptr as usize);
+            val.meta_mut().pointer = Some(MetaPtr::new(ptr, true));
+            Ok(val)
+        }
+    }
+
+    /// The function libffi calls when foreign code calls a callback
+    ///
+    /// Errors cannot be propagated through foreign code, so they are saved
+    /// and returned from the [`FfiState::do_ffi`] call that invoked the callback.
+    unsafe extern "C" fn call_callback(
+        _: &low::ffi_cif,
+        result: &mut raw::ffi_arg,
+        args: *const *const c_void,
+        info: &CallbackInfo,
+    ) {
+        let result = result as *mut raw::ffi_arg as *mut u8;
+        let res = (|| {
+            let mut values = Vec::with_capacity(info.arg_tys.len());
+            for (i, ty) in info.arg_tys.iter().enumerate() {
+                values.push(unsafe { c_to_value(ty, (*args.add(i)).cast())? });
+            }
+            let ret = (info.f)(values)?;
+            unsafe { write_c_value(&info.result_ty, &ret, result) }
+        })();
+        if let Err(e) = res {
+            info.error.lock().get_or_insert(e);
+        }
+    }
+
+    /// Read a C value of the given type into a [`Value`]
+    unsafe fn c_to_value(ty: &FfiType, ptr: *const u8) -> Result<Value, String> {
+        macro_rules! num {
+            ($ty:ty) => {
+                Value::from(ptr.cast::<$ty>().read_unaligned() as f64)
+            };
+        }
+        Ok(match ty {
+            FfiType::Char => Value::from(ptr.cast::<c_char>().read() as u8 as char),
+            FfiType::Short => num!(c_short),
+            FfiType::Int => num!(c_int),
+            FfiType::Long => num!(c_long),
+            FfiType::LongLong => num!(c_longlong),
+            FfiType::UChar => num!(c_uchar),
+            FfiType::UShort => num!(c_ushort),
+            FfiType::UInt => num!(c_uint),
+            FfiType::ULong => num!(c_ulong),
+            FfiType::ULongLong => num!(c_ulonglong),
+            FfiType::Float => num!(c_float),
+            FfiType::Double => num!(c_double),
+            FfiType::Ptr { inner, .. } => {
+                let ptr = ptr.cast::<*const c_void>().read_unaligned();
+                if **inner == FfiType::Char && !ptr.is_null() {
+                    let s = CStr::from_ptr(ptr.cast()).to_str();
+                    Value::from(s.map_err(|e| e.to_string())?)
+                } else {
+                    let mut val = Value::from(ptr as usize);
+                    val.meta_mut().pointer = Some(MetaPtr::new(ptr, true));
+                    val
+                }
+            }
+            FfiType::Struct { fields } => {
+                let (size, _) = ty.size_align();
+                let repr = slice::from_raw_parts(ptr, size);
+                FfiBindings::default().struct_repr_to_value(repr, fields)?
+            }
+            ty => {
+                return Err(format!(
+                    "{ty} is not a supported FFI callback argument type"
+                ))
+            }
+        })
+    }
+
+    /// Write a [`Value`] as a C value of the given type
+    ///
+    /// Integers are widened to the size of a libffi return value, as libffi requires.
+    unsafe fn write_c_value(ty: &FfiType, val: &Value, out: *mut u8) -> Result<(), String> {
+        let invalid = || {
+            format!(
+                "Callback returned an array of {} with shape {}, \
+                but its return type is {ty}",
+                val.type_name_plural(),
+                val.shape()
+            )
+        };
+        let num = || match val {
+            Value::Num(arr) if arr.rank() == 0 => Ok(arr.data[0]),
+            Value::Byte(arr) if arr.rank() == 0 => Ok(arr.data[0] as f64),
+            Value::Char(arr) if arr.rank() == 0 => Ok(arr.data[0] as u32 as f64),
+            _ => Err(invalid()),
+        };
+        macro_rules! signed {
+            ($ty:ty) => {
+                out.cast::<raw::ffi_sarg>()
+                    .write(num()? as $ty as raw::ffi_sarg)
+            };
+        }
+        macro_rules! unsigned {
+            ($ty:ty) => {
+                out.cast::<raw::ffi_arg>()
+                    .write(num()? as $ty as raw::ffi_arg)
+            };
+        }
+        match ty {
+            FfiType::Void => {}
+            FfiType::Char => signed!(c_char),
+            FfiType::Short => signed!(c_short),
+            FfiType::Int => signed!(c_int),
+            FfiType::Long => signed!(c_long),
+            FfiType::LongLong => out.cast::<c_longlong>().write(num()? as c_longlong),
+            FfiType::UChar => unsigned!(c_uchar),
+            FfiType::UShort => unsigned!(c_ushort),
+            FfiType::UInt => unsigned!(c_uint),
+            FfiType::ULong => unsigned!(c_ulong),
+            FfiType::ULongLong => out.cast::<c_ulonglong>().write(num()? as c_ulonglong),
+            FfiType::Float => out.cast::<c_float>().write(num()? as c_float),
+            FfiType::Double => out.cast::<c_double>().write(num()?),
+            FfiType::Ptr { .. } => {
+                let ptr = val.meta().pointer.ok_or_else(invalid)?;
+                out.cast::<usize>().write(ptr.ptr);
+            }
+            FfiType::Struct { fields } => {
+                let mut bindings = FfiBindings::default();
+                let repr = bindings.value_to_struct_repr(val, fields)?;
+                ptr::copy_nonoverlapping(repr.as_ptr(), out, repr.len());
+                // Data that the struct's fields point to must outlive the callback
+                forget(bindings);
+            }
+            ty => return Err(format!("{ty} is not a supported FFI callback return type")),
+        }
+        Ok(())
+    }
+
     type ListStorage<T> = (*mut T, Box<[T]>);
 
     #[derive(Default)]
@@ -1226,6 +1453,7 @@ mod enabled {
             FfiType::Double => Type::f64(),
             FfiType::Ptr { .. } => Type::pointer(),
             FfiType::List { .. } => Type::pointer(),
+            FfiType::Variadic => Type::void(),
             FfiType::Struct { fields } => {
                 let mut types = Vec::with_capacity(fields.len());
                 for field in fields {
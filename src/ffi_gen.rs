@@ -0,0 +1,1081 @@
+//! Generate Uiua bindings for the functions declared in a C header
+//!
+//! This is not a full C parser. It understands the declarations that usually
+//! appear in library headers: function prototypes, structs, enums, typedefs,
+//! and numeric `#define`s. Everything else is skipped.
+
+use std::{collections::HashMap, fmt::Write, mem::size_of, os::raw::c_ulong};
+
+use crate::FfiType;
+
+/// Generate a Uiua module that wraps the functions declared in a C header
+///
+/// `header_name` is only used in the module's leading comment.
+/// `lib_path` is the path of the shared library that the module loads.
+pub fn generate_bindings(
+    header: &str,
+    header_name: &str,
+    lib_path: &str,
+) -> Result<String, String> {
+    let (src, defines) = preprocess(header)?;
+    let tokens = tokenize(&src)?;
+    let mut gen = Generator {
+        src: &src,
+        tokens: &tokens,
+        pos: 0,
+        typedefs: HashMap::new(),
+        structs: HashMap::new(),
+        constants: Vec::new(),
+        values: HashMap::new(),
+        functions: Vec::new(),
+    };
+    for (name, value) in defines {
+        let Ok(toks) = tokenize(&value) else {
+            continue;
+        };
+        let value = if let [Token {
+            tok: Tok::Num(n), ..
+        }] = toks.as_slice()
+        {
+            parse_float(n).map(Constant::Float)
+        } else {
+            None
+        };
+        let value = value.or_else(|| gen.eval(&toks, &name).ok().map(Constant::Int));
+        if let Some(value) = value {
+            gen.constant(name, value);
+        }
+    }
+    gen.items();
+
+    let mut uiua = String::new();
+    let mut names = HashMap::new();
+    _ = writeln!(
+        uiua,
+        "# Bindings for {header_name}, generated by `uiua ffi-gen`"
+    );
+    _ = writeln!(uiua, "# Experimental!");
+    _ = writeln!(uiua);
+    _ = writeln!(uiua, "LibPath ← {lib_path:?}");
+    _ = writeln!(uiua, "Lib ← &ffi ⊂□LibPath");
+    names.insert("LibPath".to_string(), "LibPath".to_string());
+    names.insert("Lib".to_string(), "Lib".to_string());
+    if !gen.constants.is_empty() {
+        _ = writeln!(uiua);
+    }
+    for (name, value) in &gen.constants {
+        let binding = binding_name(name);
+        if let Some(other) = names.get(&binding) {
+            _ = writeln!(
+                uiua,
+                "# Skipped `{name}`: its name conflicts with `{other}`"
+            );
+            continue;
+        }
+        names.insert(binding.clone(), name.clone());
+        let value = match *value {
+            Constant::Int(i) if i < 0 => format!("¯{}", i.unsigned_abs()),
+            Constant::Int(i) => i.to_string(),
+            Constant::Float(f) if f < 0.0 => format!("¯{}", -f),
+            Constant::Float(f) => f.to_string(),
+        };
+        _ = writeln!(uiua, "{binding} ← {value}");
+    }
+    for func in &gen.functions {
+        _ = writeln!(uiua);
+        _ = writeln!(uiua, "# {}", func.decl);
+        let sig = match &func.sig {
+            Ok(sig) => sig,
+            Err(e) => {
+                _ = writeln!(uiua, "# Skipped `{}`: {e}", func.name);
+                continue;
+            }
+        };
+        let binding = binding_name(&func.name);
+        if let Some(other) = names.get(&binding) {
+            _ = writeln!(
+                uiua,
+                "# Skipped `{}`: its name conflicts with `{other}`",
+                func.name
+            );
+            continue;
+        }
+        names.insert(binding.clone(), func.name.clone());
+        let mut tys = format!("{:?} {:?}", sig.ret.to_string(), func.name);
+        for ty in &sig.params {
+            _ = write!(tys, " {:?}", ty.to_string());
+        }
+        let args = match sig.arg_count {
+            0 => "{}".to_string(),
+            n => format!("{{{}∘}}", "⊙".repeat(n - 1)),
+        };
+        if sig.arg_count == 0 {
+            _ = writeln!(uiua, "{binding} ← (Lib {{{tys}}} {args})");
+        } else {
+            _ = writeln!(uiua, "{binding} ← Lib {{{tys}}} {args}");
+        }
+    }
+    Ok(uiua)
+}
+
+/// Convert a C name to a Uiua binding name
+///
+/// `snake_case` and `SCREAMING_CASE` become `PascalCase`. Digits at the end of
+/// the name become a subscript, and other digits are spelled out.
+fn binding_name(name: &str) -> String {
+    const DIGITS: [&str; 10] = [
+        "Zero", "One", "Two", "Three", "Four", "Five", "Six", "Seven", "Eight", "Nine",
+    ];
+    const SUBSCRIPTS: [char; 10] = ['₀', '₁', '₂', '₃', '₄', '₅', '₆', '₇', '₈', '₉'];
+    let trimmed = name.trim_end_matches(|c: char| c.is_ascii_digit());
+    let (body, suffix) = name.split_at(trimmed.len());
+    let screaming = !name.contains(|c: char| c.is_ascii_lowercase());
+    let mut binding = String::new();
+    let mut capitalize = true;
+    for c in body.chars() {
+        if c == '_' {
+            capitalize = true;
+        } else if let Some(d) = c.to_digit(10) {
+            binding.push_str(DIGITS[d as usize]);
+            capitalize = true;
+        } else if capitalize {
+            binding.push(c.to_ascii_uppercase());
+            capitalize = false;
+        } else if screaming {
+            binding.push(c.to_ascii_lowercase());
+        } else {
+            binding.push(c);
+        }
+    }
+    if binding.is_empty() {
+        binding.push('N');
+    }
+    binding.extend(
+        suffix
+            .chars()
+            .map(|c| SUBSCRIPTS[c.to_digit(10).unwrap() as usize]),
+    );
+    binding
+}
+
+/// Remove comments and preprocessor lines, gathering object-like `#define`s
+fn preprocess(header: &str) -> Result<(String, Vec<(String, String)>), String> {
+    let mut src = String::with_capacity(header.len());
+    let mut defines = Vec::new();
+    let mut chars = header.chars().peekable();
+    let mut line_start = true;
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.peek().is_some_and(|&c| c != '\n') {
+                    chars.next();
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                loop {
+                    let Some(c) = chars.next() else {
+                        return Err("Unterminated comment".into());
+                    };
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    src.push(if c == '\n' { '\n' } else { ' ' });
+                    prev = c;
+                }
+                src.push_str("  ");
+            }
+            '#' if line_start => {
+                let mut line = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' if chars.peek() == Some(&'\n') => {
+                            chars.next();
+                            line.push(' ');
+                            src.push('\n');
+                        }
+                        '\n' => {
+                            src.push('\n');
+                            break;
+                        }
+                        c => line.push(c),
+                    }
+                }
+                if let Some(def) = line.trim_start().strip_prefix("define") {
+                    let def = def.trim();
+                    let name_len = (def.find(|c: char| !(c.is_alphanumeric() || c == '_')))
+                        .unwrap_or(def.len());
+                    let (name, value) = def.split_at(name_len);
+                    // Function-like macros are skipped
+                    if !name.is_empty() && !value.starts_with('(') && !value.trim().is_empty() {
+                        let value = match value.find("//").or_else(|| value.find("/*")) {
+                            Some(i) => &value[..i],
+                            None => value,
+                        };
+                        defines.push((name.to_string(), value.trim().to_string()));
+                    }
+                }
+                line_start = true;
+                continue;
+            }
+            '"' | '\'' => {
+                src.push(c);
+                while let Some(d) = chars.next() {
+                    src.push(d);
+                    if d == '\\' {
+                        src.extend(chars.next());
+                    } else if d == c {
+                        break;
+                    }
+                }
+            }
+            c => src.push(c),
+        }
+        line_start = c == '\n' || line_start && c.is_whitespace();
+    }
+    Ok((src, defines))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Ident(String),
+    Num(String),
+    Str,
+    Punct(&'static str),
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    tok: Tok,
+    start: usize,
+    end: usize,
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>, String> {
+    const PUNCTS: &[&str] = &[
+        "...", "<<", ">>", "(", ")", "[", "]", "{", "}", ";", ",", "*", "=", "+", "-", "/", "%",
+        "|", "&", "^", "~", ":", "<", ">", "!", "?", ".",
+    ];
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let bytes = src.as_bytes();
+    while i < src.len() {
+        let c = src[i..].chars().next().unwrap();
+        let start = i;
+        let tok = if c.is_whitespace() {
+            i += c.len_utf8();
+            continue;
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            while i < src.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                i += 1;
+            }
+            Tok::Ident(src[start..i].into())
+        } else if c.is_ascii_digit() {
+            while i < src.len() {
+                let b = bytes[i];
+                let exp = (b == b'+' || b == b'-')
+                    && matches!(bytes[i - 1], b'e' | b'E')
+                    && !src[start..].starts_with("0x");
+                if b.is_ascii_alphanumeric() || b == b'.' || exp {
+                    i += 1;
+                } else {
+                    break;
+                }
+            }
+            Tok::Num(src[start..i].into())
+        } else if c == '"' || c == '\'' {
+            i += 1;
+            loop {
+                match bytes.get(i) {
+                    None => return Err("Unterminated string literal".into()),
+                    Some(b'\\') => i += 2,
+                    Some(&b) => {
+                        i += 1;
+                        if b == c as u8 {
+                            break;
+                        }
+                    }
+                }
+            }
+            Tok::Str
+        } else if let Some(p) = PUNCTS.iter().find(|p| src[i..].starts_with(**p)) {
+            i += p.len();
+            Tok::Punct(p)
+        } else {
+            return Err(format!("Unexpected character {c:?}"));
+        };
+        tokens.push(Token { tok, start, end: i });
+    }
+    Ok(tokens)
+}
+
+fn parse_float(s: &str) -> Option<f64> {
+    if s.starts_with("0x") || s.starts_with("0X") || !s.contains(['.', 'e', 'E']) {
+        return None;
+    }
+    s.trim_end_matches(['f', 'F', 'l', 'L']).parse().ok()
+}
+
+fn parse_int(s: &str) -> Option<i64> {
+    let s = s.trim_end_matches(['u', 'U', 'l', 'L']);
+    if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16).ok().map(|u| u as i64)
+    } else if let Some(bin) = s.strip_prefix("0b").or_else(|| s.strip_prefix("0B")) {
+        u64::from_str_radix(bin, 2).ok().map(|u| u as i64)
+    } else if s.len() > 1 && s.starts_with('0') {
+        u64::from_str_radix(&s[1..], 8).ok().map(|u| u as i64)
+    } else {
+        s.parse::<u64>().ok().map(|u| u as i64)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Constant {
+    Int(i64),
+    Float(f64),
+}
+
+/// A C type, before it is converted to an [`FfiType`]
+#[derive(Debug, Clone)]
+enum CType {
+    /// Void, a scalar, or a complete struct
+    Ffi(FfiType),
+    /// A struct or union whose fields are not known
+    Opaque(String),
+    /// A function
+    Func,
+    /// A pointer. `constant` is whether the pointed-to value is const.
+    Ptr { constant: bool, inner: Box<CType> },
+    /// An array. `len` is 0 if it is not specified.
+    Array {
+        constant: bool,
+        inner: Box<CType>,
+        len: usize,
+    },
+}
+
+impl CType {
+    fn to_ffi(&self) -> Result<FfiType, String> {
+        Ok(match self {
+            CType::Ffi(ty) => ty.clone(),
+            CType::Opaque(name) => {
+                return Err(format!("`{name}` is a union or an incomplete struct"))
+            }
+            CType::Func => return Err("functions cannot be passed by value".into()),
+            CType::Ptr { constant, inner } => match &**inner {
+                CType::Func | CType::Opaque(_) | CType::Array { .. } => FfiType::Ptr {
+                    mutable: !constant && !matches!(**inner, CType::Func),
+                    inner: FfiType::Void.into(),
+                },
+                inner => FfiType::Ptr {
+                    mutable: !constant,
+                    inner: inner.to_ffi()?.into(),
+                },
+            },
+            CType::Array { .. } => return Err("arrays cannot be passed by value".into()),
+        })
+    }
+    /// Convert a parameter type, which decays arrays and functions to pointers
+    fn param_to_ffi(&self) -> Result<FfiType, String> {
+        let ty = match self {
+            CType::Array {
+                constant, inner, ..
+            } => CType::Ptr {
+                constant: *constant,
+                inner: inner.clone(),
+            },
+            CType::Func => CType::Ptr {
+                constant: true,
+                inner: CType::Func.into(),
+            },
+            ty => ty.clone(),
+        };
+        Ok(match ty.to_ffi()? {
+            // Uiua cannot write to a `void*`, so it is passed as an input
+            FfiType::Ptr {
+                mutable: true,
+                inner,
+            } if *inner == FfiType::Void => FfiType::Ptr {
+                mutable: false,
+                inner,
+            },
+            ty => ty,
+        })
+    }
+    /// Add a struct field's types, which flattens arrays
+    fn push_fields(&self, fields: &mut Vec<FfiType>) -> Result<(), String> {
+        match self {
+            CType::Array { len: 0, .. } => {
+                return Err("flexible array members are not supported".into())
+            }
+            CType::Array { inner, len, .. } => {
+                for _ in 0..*len {
+                    inner.push_fields(fields)?;
+                }
+            }
+            ty => fields.push(ty.to_ffi()?),
+        }
+        Ok(())
+    }
+}
+
+struct Param {
+    name: Option<String>,
+    ty: CType,
+}
+
+struct Function {
+    name: String,
+    decl: String,
+    sig: Result<Signature, String>,
+}
+
+struct Signature {
+    ret: FfiType,
+    params: Vec<FfiType>,
+    /// The number of arguments, which does not include array lengths
+    arg_count: usize,
+}
+
+struct Declarator {
+    name: Option<String>,
+    ty: CType,
+    params: Option<(Vec<Param>, bool)>,
+}
+
+struct Generator<'a> {
+    src: &'a str,
+    tokens: &'a [Token],
+    pos: usize,
+    typedefs: HashMap<String, CType>,
+    structs: HashMap<String, CType>,
+    constants: Vec<(String, Constant)>,
+    values: HashMap<String, i64>,
+    functions: Vec<Function>,
+}
+
+/// Whether a parameter name looks like the length of the preceding array
+fn is_length_name(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    ["n", "len", "length", "count", "size", "num"].contains(&name.as_str())
+        || ["len", "length", "count", "size"]
+            .iter()
+            .any(|suffix| name.ends_with(&format!("_{suffix}")))
+}
+
+impl Generator<'_> {
+    fn peek(&self) -> Option<&Tok> {
+        self.tokens.get(self.pos).map(|t| &t.tok)
+    }
+    fn peek_at(&self, n: usize) -> Option<&Tok> {
+        self.tokens.get(self.pos + n).map(|t| &t.tok)
+    }
+    fn next_if_punct(&mut self, p: &'static str) -> bool {
+        if self.peek() == Some(&Tok::Punct(p)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+    fn next_if_ident(&mut self, s: &str) -> bool {
+        if matches!(self.peek(), Some(Tok::Ident(i)) if i == s) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+    fn expect(&mut self, p: &'static str) -> Result<(), String> {
+        if self.next_if_punct(p) {
+            Ok(())
+        } else {
+            Err(format!("expected `{p}`"))
+        }
+    }
+    /// Skip a balanced group, starting at its opening delimiter
+    fn skip_group(&mut self) {
+        let mut depth = 0;
+        while let Some(tok) = self.peek().cloned() {
+            self.pos += 1;
+            match tok {
+                Tok::Punct("(" | "[" | "{") => depth += 1,
+                Tok::Punct(")" | "]" | "}") => {
+                    depth -= 1;
+                    if depth <= 0 {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    fn constant(&mut self, name: String, value: Constant) {
+        if let Constant::Int(i) = value {
+            self.values.insert(name.clone(), i);
+        }
+        self.constants.push((name, value));
+    }
+    /// Parse all top-level items
+    fn items(&mut self) {
+        while let Some(tok) = self.peek() {
+            match tok {
+                Tok::Punct(";" | "}") => self.pos += 1,
+                // `extern "C"`
+                Tok::Ident(i) if i == "extern" && self.peek_at(1) == Some(&Tok::Str) => {
+                    self.pos += 2;
+                    self.next_if_punct("{");
+                }
+                _ => {
+                    let start = self.pos;
+                    if let Err(e) = self.item() {
+                        // Report functions that could not be parsed
+                        let end = self.item_end(start);
+                        let name = self.tokens[start..end].windows(2).find_map(|w| {
+                            match (&w[0].tok, &w[1].tok) {
+                                (Tok::Ident(name), Tok::Punct("(")) => Some(name.clone()),
+                                _ => None,
+                            }
+                        });
+                        if let Some(name) = name {
+                            let decl = self.decl_text(start, end);
+                            self.functions.push(Function {
+                                name,
+                                decl,
+                                sig: Err(e),
+                            });
+                        }
+                        self.pos = end;
+                    }
+                }
+            }
+        }
+    }
+    /// Find the end of the item beginning at `start`
+    fn item_end(&self, start: usize) -> usize {
+        let mut depth = 0;
+        let mut body = false;
+        let mut i = start;
+        while let Some(token) = self.tokens.get(i) {
+            match token.tok {
+                Tok::Punct("{") if depth == 0 && i > start => {
+                    body = self.tokens[i - 1].tok == Tok::Punct(")");
+                    depth += 1;
+                }
+                Tok::Punct("(" | "[" | "{") => depth += 1,
+                Tok::Punct(")" | "]" | "}") => {
+                    depth -= 1;
+                    if depth == 0 && body {
+                        return i + 1;
+                    }
+                }
+                Tok::Punct(";") if depth == 0 => return i + 1,
+                _ => {}
+            }
+            i += 1;
+        }
+        i
+    }
+    /// The source text of tokens, with whitespace collapsed
+    fn decl_text(&self, start: usize, end: usize) -> String {
+        let (Some(first), Some(last)) = (self.tokens.get(start), self.tokens.get(end - 1)) else {
+            return String::new();
+        };
+        let text = &self.src[first.start..last.end];
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+    /// Parse a top-level item
+    fn item(&mut self) -> Result<(), String> {
+        let start = self.pos;
+        let typedef = self.next_if_ident("typedef");
+        let (base, base_const) = self.specifiers()?;
+        if self.next_if_punct(";") {
+            return Ok(());
+        }
+        loop {
+            let decl = self.declarator(base.clone(), base_const)?;
+            // A function body
+            if decl.params.is_some() && self.peek() == Some(&Tok::Punct("{")) {
+                self.skip_group();
+                return Ok(());
+            }
+            self.attributes();
+            if self.next_if_punct("=") {
+                while !matches!(self.peek(), None | Some(Tok::Punct("," | ";"))) {
+                    if matches!(self.peek(), Some(Tok::Punct("(" | "[" | "{"))) {
+                        self.skip_group();
+                    } else {
+                        self.pos += 1;
+                    }
+                }
+            }
+            let name = decl.name.ok_or("expected a name")?;
+            if typedef {
+                let ty = if decl.params.is_some() {
+                    CType::Func
+                } else {
+                    decl.ty
+                };
+                self.typedefs.insert(name, ty);
+            } else if let Some((params, variadic)) = decl.params {
+                let end = self.item_end(start);
+                let decl_text = self.decl_text(start, end);
+                let sig = if variadic {
+                    Err("variadic functions must be called with `&ffi` directly".into())
+                } else {
+                    self.signature(&decl.ty, &params)
+                };
+                self.functions.push(Function {
+                    name,
+                    decl: decl_text,
+                    sig,
+                });
+            }
+            if !self.next_if_punct(",") {
+                break;
+            }
+        }
+        self.expect(";")
+    }
+    fn signature(&self, ret: &CType, params: &[Param]) -> Result<Signature, String> {
+        let ret = match ret {
+            CType::Array { .. } | CType::Func => {
+                return Err("functions cannot return arrays or functions".into())
+            }
+            ret => ret.to_ffi()?,
+        };
+        let mut tys = (params.iter())
+            .map(|param| param.ty.param_to_ffi())
+            .collect::<Result<Vec<_>, _>>()?;
+        // Turn pointers followed by lengths into lists
+        let mut arg_count = tys.len();
+        for i in 0..tys.len().saturating_sub(1) {
+            let is_len = params[i + 1].name.as_deref().is_some_and(is_length_name)
+                && matches!(
+                    tys[i + 1],
+                    FfiType::Short
+                        | FfiType::UShort
+                        | FfiType::Int
+                        | FfiType::UInt
+                        | FfiType::Long
+                        | FfiType::ULong
+                        | FfiType::LongLong
+                        | FfiType::ULongLong
+                );
+            if let FfiType::Ptr { mutable, inner } = &tys[i] {
+                if is_len && **inner != FfiType::Void {
+                    tys[i] = FfiType::List {
+                        mutable: *mutable,
+                        len_index: i + 1,
+                        inner: inner.clone(),
+                    };
+                    arg_count -= 1;
+                }
+            }
+        }
+        Ok(Signature {
+            ret,
+            params: tys,
+            arg_count,
+        })
+    }
+    /// Skip `__attribute__((...))` and similar
+    fn attributes(&mut self) {
+        while let Some(Tok::Ident(i)) = self.peek() {
+            if !["__attribute__", "__declspec", "__asm__", "asm"].contains(&i.as_str()) {
+                break;
+            }
+            self.pos += 1;
+            if self.peek() == Some(&Tok::Punct("(")) {
+                self.skip_group();
+            }
+        }
+    }
+    /// Parse declaration specifiers, returning the base type and whether it is const
+    fn specifiers(&mut self) -> Result<(CType, bool), String> {
+        let mut constant = false;
+        let mut ty = None;
+        let mut signed = None;
+        let mut longs = 0;
+        let mut short = false;
+        let mut int = false;
+        while let Some(tok) = self.peek() {
+            let Tok::Ident(word) = tok else {
+                break;
+            };
+            let word = word.clone();
+            match word.as_str() {
+                "const" => constant = true,
+                "volatile" | "restrict" | "__restrict" | "__restrict__" | "extern" | "static"
+                | "inline" | "__inline" | "__inline__" | "register" | "__extension__"
+                | "__cdecl" | "__stdcall" | "__fastcall" => {}
+                "__attribute__" | "__declspec" => {
+                    self.attributes();
+                    continue;
+                }
+                "signed" => signed = Some(true),
+                "unsigned" => signed = Some(false),
+                "long" => longs += 1,
+                "short" => short = true,
+                "int" => int = true,
+                "struct" | "union" | "enum" if ty.is_none() => {
+                    self.pos += 1;
+                    ty = Some(self.tagged(&word)?);
+                    continue;
+                }
+                "void" => ty = Some(CType::Ffi(FfiType::Void)),
+                "char" => ty = Some(CType::Ffi(FfiType::Char)),
+                "float" => ty = Some(CType::Ffi(FfiType::Float)),
+                "double" => ty = Some(CType::Ffi(FfiType::Double)),
+                // The declarator's name
+                _ if ty.is_some() || signed.is_some() || longs > 0 || short || int => break,
+                _ => {
+                    if let Some(def) = self.typedefs.get(&word).cloned() {
+                        ty = Some(def);
+                    } else if let Some(known) = known_typedef(&word) {
+                        ty = Some(CType::Ffi(known));
+                    } else if word.chars().all(|c| c.is_ascii_uppercase() || c == '_')
+                        && matches!(self.peek_at(1), Some(Tok::Ident(_)))
+                    {
+                        // Probably an export macro like `MYLIB_API`
+                    } else {
+                        return Err(format!("unknown type `{word}`"));
+                    }
+                }
+            }
+            self.pos += 1;
+        }
+        let ty = match ty {
+            Some(CType::Ffi(FfiType::Char)) => CType::Ffi(match signed {
+                Some(false) => FfiType::UChar,
+                _ => FfiType::Char,
+            }),
+            Some(CType::Ffi(FfiType::Double)) if longs > 0 => {
+                return Err("`long double` is not supported".into())
+            }
+            Some(ty) => ty,
+            None if signed.is_none() && longs == 0 && !short && !int => {
+                return Err("expected a type".into())
+            }
+            None => {
+                let unsigned = signed == Some(false);
+                CType::Ffi(match (short, longs, unsigned) {
+                    (true, _, false) => FfiType::Short,
+                    (true, _, true) => FfiType::UShort,
+                    (false, 0, false) => FfiType::Int,
+                    (false, 0, true) => FfiType::UInt,
+                    (false, 1, false) => FfiType::Long,
+                    (false, 1, true) => FfiType::ULong,
+                    (false, _, false) => FfiType::LongLong,
+                    (false, _, true) => FfiType::ULongLong,
+                })
+            }
+        };
+        Ok((ty, constant))
+    }
+    /// Parse a struct, union, or enum specifier, after the keyword
+    fn tagged(&mut self, keyword: &str) -> Result<CType, String> {
+        self.attributes();
+        let name = if let Some(Tok::Ident(name)) = self.peek() {
+            let name = name.clone();
+            self.pos += 1;
+            Some(name)
+        } else {
+            None
+        };
+        let key = format!("{keyword} {}", name.as_deref().unwrap_or(""));
+        if keyword == "enum" {
+            if self.next_if_punct("{") {
+                self.enumerators()?;
+            }
+            return Ok(CType::Ffi(FfiType::Int));
+        }
+        if self.peek() != Some(&Tok::Punct("{")) {
+            let name = name.ok_or("expected a struct name")?;
+            return Ok((self.structs.get(&key).cloned()).unwrap_or(CType::Opaque(name)));
+        }
+        if keyword == "union" {
+            self.skip_group();
+            let ty = CType::Opaque(key.clone());
+            if name.is_some() {
+                self.structs.insert(key, ty.clone());
+            }
+            return Ok(ty);
+        }
+        self.pos += 1;
+        let mut fields = Vec::new();
+        while !self.next_if_punct("}") {
+            let (base, base_const) = self.specifiers()?;
+            loop {
+                let decl = self.declarator(base.clone(), base_const)?;
+                if self.peek() == Some(&Tok::Punct(":")) {
+                    return Err("bit fields are not supported".into());
+                }
+                decl.ty.push_fields(&mut fields)?;
+                if !self.next_if_punct(",") {
+                    break;
+                }
+            }
+            self.expect(";")?;
+        }
+        let ty = CType::Ffi(FfiType::Struct { fields });
+        if name.is_some() {
+            self.structs.insert(key, ty.clone());
+        }
+        Ok(ty)
+    }
+    /// Parse the enumerators of an enum, after the opening brace
+    fn enumerators(&mut self) -> Result<(), String> {
+        let mut next = Some(0);
+        while !self.next_if_punct("}") {
+            let Some(Tok::Ident(name)) = self.peek() else {
+                return Err("expected an enumerator".into());
+            };
+            let name = name.clone();
+            self.pos += 1;
+            self.attributes();
+            if self.next_if_punct("=") {
+                let start = self.pos;
+                let mut depth = 0;
+                while let Some(tok) = self.peek() {
+                    match tok {
+                        Tok::Punct("(") => depth += 1,
+                        Tok::Punct(")") => depth -= 1,
+                        Tok::Punct("," | "}") if depth == 0 => break,
+                        _ => {}
+                    }
+                    self.pos += 1;
+                }
+                next = self.eval(&self.tokens[start..self.pos], &name).ok();
+            }
+            // Enumerators after one that cannot be evaluated are unknown
+            if let Some(value) = next {
+                self.constant(name, Constant::Int(value));
+                next = value.checked_add(1);
+            }
+            if !self.next_if_punct(",") {
+                self.expect("}")?;
+                break;
+            }
+        }
+        Ok(())
+    }
+    /// Parse a declarator, which may be abstract
+    fn declarator(&mut self, base: CType, base_const: bool) -> Result<Declarator, String> {
+        let mut ty = base;
+        let mut constant = base_const;
+        while self.next_if_punct("*") {
+            ty = CType::Ptr {
+                constant,
+                inner: ty.into(),
+            };
+            constant = false;
+            while let Some(Tok::Ident(q)) = self.peek() {
+                match q.as_str() {
+                    "const" => constant = true,
+                    "volatile" | "restrict" | "__restrict" | "__restrict__" => {}
+                    _ => break,
+                }
+                self.pos += 1;
+            }
+        }
+        self.attributes();
+        // A function pointer
+        if self.peek() == Some(&Tok::Punct("(")) && self.peek_at(1) == Some(&Tok::Punct("*")) {
+            self.pos += 1;
+            while self.next_if_punct("*") || self.next_if_ident("const") {}
+            let name = if let Some(Tok::Ident(name)) = self.peek() {
+                let name = name.clone();
+                self.pos += 1;
+                Some(name)
+            } else {
+                None
+            };
+            self.expect(")")?;
+            self.expect("(")?;
+            self.params()?;
+            let ty = CType::Ptr {
+                constant: true,
+                inner: CType::Func.into(),
+            };
+            return Ok(Declarator {
+                name,
+                ty,
+                params: None,
+            });
+        }
+        let name = if let Some(Tok::Ident(name)) = self.peek() {
+            let name = name.clone();
+            self.pos += 1;
+            Some(name)
+        } else {
+            None
+        };
+        let mut params = None;
+        if self.next_if_punct("(") {
+            params = Some(self.params()?);
+        } else {
+            let mut dims = Vec::new();
+            while self.next_if_punct("[") {
+                let start = self.pos;
+                while !matches!(self.peek(), None | Some(Tok::Punct("]"))) {
+                    self.pos += 1;
+                }
+                let dim = &self.tokens[start..self.pos];
+                dims.push(if dim.is_empty() {
+                    0
+                } else {
+                    let name = name.as_deref().unwrap_or("array");
+                    let len = self.eval(dim, name)?;
+                    usize::try_from(len).map_err(|_| "invalid array length")?
+                });
+                self.expect("]")?;
+            }
+            for len in dims.into_iter().rev() {
+                ty = CType::Array {
+                    constant,
+                    inner: ty.into(),
+                    len,
+                };
+            }
+        }
+        Ok(Declarator { name, ty, params })
+    }
+    /// Parse function parameters, after the opening parenthesis
+    ///
+    /// Returns the parameters and whether the function is variadic
+    fn params(&mut self) -> Result<(Vec<Param>, bool), String> {
+        let mut params = Vec::new();
+        let mut variadic = false;
+        if self.next_if_punct(")") {
+            return Ok((params, variadic));
+        }
+        if self.peek() == Some(&Tok::Ident("void".into()))
+            && self.peek_at(1) == Some(&Tok::Punct(")"))
+        {
+            self.pos += 2;
+            return Ok((params, variadic));
+        }
+        loop {
+            if self.next_if_punct("...") {
+                variadic = true;
+            } else {
+                let (base, base_const) = self.specifiers()?;
+                let decl = self.declarator(base, base_const)?;
+                let ty = if decl.params.is_some() {
+                    CType::Func
+                } else {
+                    decl.ty
+                };
+                params.push(Param {
+                    name: decl.name,
+                    ty,
+                });
+            }
+            if !self.next_if_punct(",") {
+                break;
+            }
+        }
+        self.expect(")")?;
+        Ok((params, variadic))
+    }
+    /// Evaluate an integer constant expression
+    fn eval(&self, tokens: &[Token], name: &str) -> Result<i64, String> {
+        let mut pos = 0;
+        let value = self.eval_binary(tokens, &mut pos, 0)?;
+        if pos != tokens.len() {
+            return Err(format!("cannot evaluate the value of `{name}`"));
+        }
+        Ok(value)
+    }
+    fn eval_binary(&self, tokens: &[Token], pos: &mut usize, min_prec: u8) -> Result<i64, String> {
+        let mut left = self.eval_unary(tokens, pos)?;
+        while let Some(Token {
+            tok: Tok::Punct(op),
+            ..
+        }) = tokens.get(*pos)
+        {
+            let prec = match *op {
+                "|" => 1,
+                "^" => 2,
+                "&" => 3,
+                "<<" | ">>" => 4,
+                "+" | "-" => 5,
+                "*" | "/" | "%" => 6,
+                _ => break,
+            };
+            if prec < min_prec {
+                break;
+            }
+            *pos += 1;
+            let right = self.eval_binary(tokens, pos, prec + 1)?;
+            left = match *op {
+                "|" => left | right,
+                "^" => left ^ right,
+                "&" => left & right,
+                "<<" => left.checked_shl(right as u32).ok_or("shift overflow")?,
+                ">>" => left.checked_shr(right as u32).ok_or("shift overflow")?,
+                "+" => left.wrapping_add(right),
+                "-" => left.wrapping_sub(right),
+                "*" => left.wrapping_mul(right),
+                "/" => left.checked_div(right).ok_or("division by zero")?,
+                _ => left.checked_rem(right).ok_or("division by zero")?,
+            };
+        }
+        Ok(left)
+    }
+    fn eval_unary(&self, tokens: &[Token], pos: &mut usize) -> Result<i64, String> {
+        let tok = tokens.get(*pos).map(|t| &t.tok).ok_or("expected a value")?;
+        *pos += 1;
+        Ok(match tok {
+            Tok::Punct("-") => self.eval_unary(tokens, pos)?.wrapping_neg(),
+            Tok::Punct("+") => self.eval_unary(tokens, pos)?,
+            Tok::Punct("~") => !self.eval_unary(tokens, pos)?,
+            Tok::Punct("(") => {
+                let value = self.eval_binary(tokens, pos, 0)?;
+                match tokens.get(*pos) {
+                    Some(Token {
+                        tok: Tok::Punct(")"),
+                        ..
+                    }) => *pos += 1,
+                    _ => return Err("expected `)`".into()),
+                }
+                value
+            }
+            Tok::Num(n) => parse_int(n).ok_or_else(|| format!("invalid integer `{n}`"))?,
+            Tok::Ident(name) => {
+                *(self.values.get(name)).ok_or_else(|| format!("unknown `{name}`"))?
+            }
+            _ => return Err("expected a value".into()),
+        })
+    }
+}
+
+/// Types from the C standard library that headers use without defining
+fn known_typedef(name: &str) -> Option<FfiType> {
+    let size = if size_of::<c_ulong>() == size_of::<usize>() {
+        FfiType::ULong
+    } else {
+        FfiType::ULongLong
+    };
+    let ssize = if size_of::<c_ulong>() == size_of::<usize>() {
+        FfiType::Long
+    } else {
+        FfiType::LongLong
+    };
+    Some(match name {
+        "bool" | "_Bool" => FfiType::UChar,
+        "int8_t" => FfiType::Char,
+        "uint8_t" => FfiType::UChar,
+        "int16_t" => FfiType::Short,
+        "uint16_t" => FfiType::UShort,
+        "int32_t" => FfiType::Int,
+        "uint32_t" => FfiType::UInt,
+        "int64_t" => FfiType::LongLong,
+        "uint64_t" => FfiType::ULongLong,
+        "size_t" | "uintptr_t" => size,
+        "ssize_t" | "intptr_t" | "ptrdiff_t" => ssize,
+        _ => return None,
+    })
+}
@@ -6,13 +6,15 @@ use std::{
     collections::HashMap,
     env,
     fmt::Display,
-    fs,
+    fs, io,
     iter::repeat,
     path::{Path, PathBuf},
+    slice,
     time::Duration,
 };
 
 use paste::paste;
+use serde::Deserialize;
 use InlineMacro;
 
 use crate::{
@@ -22,7 +24,7 @@ use crate::{
     lex::{CodeSpan, Loc, Sp},
     parse::{flip_unsplit_lines, parse, split_words, trim_spaces},
     Compiler, Handle, Ident, InputSrc, Inputs, PreEvalMode, Primitive, RunMode, SafeSys, Signature,
-    SysBackend, Uiua, UiuaErrorKind, UiuaResult, Value, SUBSCRIPT_DIGITS,
+    SysBackend, Uiua, UiuaError, UiuaErrorKind, UiuaResult, Value, SUBSCRIPT_DIGITS,
 };
 
 trait ConfigValue: Sized {
@@ -93,7 +95,8 @@ macro_rules! create_config {
             $default:expr
         )
     ),* $(,)?) => {
-        #[derive(Debug, Clone)]
+        #[derive(Debug, Clone, Deserialize)]
+        #[serde(deny_unknown_fields)]
         struct PartialFormatConfig {
             $(
                 $name: Option<$ty>,
@@ -106,9 +109,14 @@ macro_rules! create_config {
                 let mut s: String = r#"
 # Uiua Formatter Configuration
 
-You can configure Uiua's formatter by creating a file called `.fmt.ua` in the directory from which you run the interpreter. This configuration file is also a Uiua program.
+You can configure Uiua's formatter by creating a configuration file in the directory of the files being formatted or in any of its parent directories. The closest configuration file is used by both the `uiua` command and the language server.
+
+The configuration can be written in one of three files. If a directory contains more than one, the first one in this list is used.
+- `.fmt.ua`, which is a Uiua program
+- `.uiuafmt.toml`, which is a TOML file
+- `uiua.toml`, which is a TOML file with the configuration in a `[fmt]` table
 
-Configuration options are specified by binding values to specific names.
+In a `.fmt.ua` file, configuration options are specified by binding values to specific names.
 
 Example with default values:
 ```uiua
@@ -117,12 +125,25 @@ Example with default values:
                     s.push_str(&format!("{} ← {}\n", stringify!([<$name:camel>]), default_to_uiua!($default)));
                 )*
                 s.push_str(r#"```
+
+In a TOML file, the options use snake case names.
+
+Example with default values:
+```toml
+"#);
+                $(
+                    s.push_str(&format!("{} = {:?}\n", stringify!($name), $default));
+                )*
+                s.push_str(r#"```
+Options that are not specified keep their default values.
+
 The following configuration options are available:
 
 "#);
 
                 $(
                     s.push_str(&format!("### {}\n", stringify!([<$name:camel>])));
+                    s.push_str(&format!("TOML name: `{}`\n\n", stringify!($name)));
                     s.push_str(&format!("Type: {}\n\n", param_type!($ty)));
                     s.push_str(&format!("Default: `{}`\n\n", default_to_uiua!($default)));
                     $(s.push_str(&format!("{}\n", $doc.trim()));)*
@@ -159,6 +180,21 @@ The following configuration options are available:
                     });
                 }
             }
+            fn from_toml_file(file_path: &Path) -> io::Result<Self> {
+                let invalid = |e: &dyn Display| {
+                    io::Error::new(io::ErrorKind::InvalidData, e.to_string().trim())
+                };
+                let text = fs::read_to_string(file_path)?;
+                let mut table: toml::Table = toml::from_str(&text).map_err(|e| invalid(&e))?;
+                if file_path.ends_with(UIUA_TOML) {
+                    table = match table.remove("fmt") {
+                        Some(toml::Value::Table(fmt)) => fmt,
+                        Some(_) => return Err(invalid(&"`fmt` must be a table")),
+                        None => toml::Table::new(),
+                    };
+                }
+                table.try_into().map_err(|e| invalid(&e))
+            }
         }
 
         /// Configuration for the Uiua formatter.
@@ -222,12 +258,31 @@ create_config!(
     (align_comments, bool, true),
     /// Whether to indent item imports
     (indent_item_imports, bool, true),
+    /// Whether to convert primitive names to glyphs
+    (names_to_glyphs, bool, true),
+    /// Whether to convert glyphs to ASCII names instead
+    ///
+    /// This applies to primitives and their subscripts, numbers, binding arrows, and module delimiters.
+    /// Code formatted this way formats back to glyphs when this is disabled.
+    (glyphs_to_names, bool, false),
+    /// Whether to align the arrows of consecutive single-line bindings
+    (align_binding_arrows, bool, true),
+    /// The line width that formatted code should stay within
+    ///
+    /// Lines of code that are too long are broken between words onto multiple lines.
+    /// End-of-line comments are not aligned past this width, and output comments are truncated to it.
+    (line_width, usize, 200),
 );
 
+const FMT_UA: &str = ".fmt.ua";
+const UIUAFMT_TOML: &str = ".uiuafmt.toml";
+const UIUA_TOML: &str = "uiua.toml";
+
 /// The source from which to populate the formatter configuration.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FormatConfigSource {
-    /// Recursively search for a .fmt.ua file and use it as the formatter configuration,
+    /// Recursively search for a .fmt.ua, .uiuafmt.toml, or uiua.toml file
+    /// and use it as the formatter configuration,
     /// if none is found, use the default formatter configuration
     SearchFile,
     /// Use the default formatter configuration
@@ -258,8 +313,14 @@ impl Display for FormatConfigSource {
 
 impl FormatConfig {
     /// Load the formatter configuration from the specified file
+    ///
+    /// TOML files are read as TOML. Any other file is run as a Uiua program.
     pub fn from_file(path: PathBuf) -> UiuaResult<Self> {
-        let partial = PartialFormatConfig::from_file(path);
+        let partial = if path.extension().is_some_and(|ext| ext == "toml") {
+            PartialFormatConfig::from_toml_file(&path).map_err(|e| UiuaError::load(path, e))
+        } else {
+            PartialFormatConfig::from_file(path)
+        };
         partial.map(Into::into)
     }
     /// Find the formatter configuration relative to the current directory
@@ -286,9 +347,18 @@ impl FormatConfig {
             .and_then(|p| std::fs::canonicalize(p).ok())
             .unwrap_or(env::current_dir().ok()?);
         loop {
-            let file_path = path.join(".fmt.ua");
-            if file_path.exists() {
-                return Some(file_path);
+            for name in [FMT_UA, UIUAFMT_TOML] {
+                let file_path = path.join(name);
+                if file_path.exists() {
+                    return Some(file_path);
+                }
+            }
+            // A uiua.toml is only used if it has a fmt table or can't be read
+            let file_path = path.join(UIUA_TOML);
+            if let Ok(text) = fs::read_to_string(&file_path) {
+                if toml::from_str::<toml::Table>(&text).map_or(true, |t| t.contains_key("fmt")) {
+                    return Some(file_path);
+                }
             }
             if !path.pop() {
                 return None;
@@ -379,15 +449,20 @@ pub fn format<P: AsRef<Path>>(
     path: P,
     config: &FormatConfig,
 ) -> UiuaResult<FormatOutput> {
-    format_impl(input, path.as_ref().into(), config)
+    format_impl(input, path.as_ref().into(), config, true)
 }
 
 /// Format Uiua code without a path
 pub fn format_str(input: &str, config: &FormatConfig) -> UiuaResult<FormatOutput> {
-    format_impl(input, InputSrc::Str(0), config)
+    format_impl(input, InputSrc::Str(0), config, true)
 }
 
-fn format_impl(input: &str, src: InputSrc, config: &FormatConfig) -> UiuaResult<FormatOutput> {
+pub(crate) fn format_impl(
+    input: &str,
+    src: InputSrc,
+    config: &FormatConfig,
+    eval_output_comments: bool,
+) -> UiuaResult<FormatOutput> {
     let mut inputs = Inputs::default();
     let (items, errors, _) = parse(input, src.clone(), &mut inputs);
     if errors.is_empty() {
@@ -400,7 +475,7 @@ fn format_impl(input: &str, src: InputSrc, config: &FormatConfig) -> UiuaResult<
             end_of_line_comments: Vec::new(),
             prev_import_function: None,
             output_comments: None,
-            eval_output_comments: true,
+            eval_output_comments,
         }
         .format_top_items(&items);
         let formatted = FormatOutput {
@@ -514,11 +589,14 @@ impl Formatter<'_> {
             }
             // Calculate max name length to align single-line bindings
             match item {
-                Item::Binding(binding) if !words_are_multiline(&binding.words) => {
+                Item::Binding(binding)
+                    if self.config.align_binding_arrows
+                        && self.binding_is_single_line(binding, depth) =>
+                {
                     if max_name_len == 0 {
                         max_name_len = items[i..]
                             .iter()
-                            .take_while(|item| matches!(item, Item::Binding(binding) if !words_are_multiline(&binding.words)))
+                            .take_while(|item| matches!(item, Item::Binding(binding) if self.binding_is_single_line(binding, depth)))
                             .map(|item| match item {
                                 Item::Binding(binding) => binding.name.value.chars().count(),
                                 _ => 0,
@@ -570,7 +648,14 @@ impl Formatter<'_> {
                     let line = &mut lines[line_number - 1];
                     let start_byte_len = line.len();
                     let start_char_len = line.chars().count();
-                    let spaces = (max + 1).saturating_sub(line.chars().count());
+                    // Don't align past the line width
+                    let aligned_len = max + 2 + comment.chars().count();
+                    let target = if aligned_len > self.config.line_width {
+                        start_char_len
+                    } else {
+                        max
+                    };
+                    let spaces = (target + 1).saturating_sub(start_char_len);
                     line.push_str(&" ".repeat(spaces));
                     line.push('#');
                     if !comment.starts_with(' ')
@@ -616,7 +701,10 @@ impl Formatter<'_> {
         match item {
             Item::Module(m) => {
                 self.prev_import_function = None;
-                self.output.push_str("┌─╴");
+                self.output.push_str(self.glyph("┌─╴", "---"));
+                if let Some(span) = &m.value.private_span {
+                    self.push(span, "~");
+                }
                 match &m.value.kind {
                     ModuleKind::Named(name) => self.push(&name.span, &name.value),
                     ModuleKind::Test => self.output.push_str("test"),
@@ -636,12 +724,13 @@ impl Formatter<'_> {
                     self.output.pop();
                 }
                 self.newline(depth);
-                self.output.push_str("└─╴");
+                self.output.push_str(self.glyph("└─╴", "---"));
             }
             Item::Words(lines) => {
                 self.prev_import_function = None;
                 let lines =
                     flip_unsplit_lines(lines.iter().cloned().flat_map(split_words).collect());
+                let lines = self.wrap_lines(lines, self.config.multiline_indent * depth);
                 self.format_multiline_words(&lines, Compact::Never, false, true, false, depth);
             }
             Item::Binding(binding) => {
@@ -666,12 +755,16 @@ impl Formatter<'_> {
                         self.output.push(' ');
                     }
                 }
-                self.output
-                    .push_str(if binding.public { " ←" } else { " ↚" });
+                self.output.push_str(if binding.public {
+                    self.glyph(" ←", " =")
+                } else {
+                    self.glyph(" ↚", " =~")
+                });
                 if binding.code_macro {
                     self.output.push('^');
                 }
-                if !binding.words.is_empty() || binding.signature.is_some() {
+                let mut lines = self.binding_lines(binding, depth);
+                if lines.iter().any(|line| !line.is_empty()) || binding.signature.is_some() {
                     self.output.push(' ');
                 }
                 if let Some(sig) = &binding.signature {
@@ -684,7 +777,6 @@ impl Formatter<'_> {
                     .map(|(first, last)| first.span.clone().merge(last.span.clone()))
                     .or_else(|| binding.signature.as_ref().map(|sig| sig.span.clone()))
                     .unwrap_or_else(|| binding.arrow_span.clone());
-                let mut lines = flip_unsplit_lines(split_words(binding.words.clone()));
                 if lines.len() == 1 {
                     self.format_words(&lines[0], true, depth);
                 } else {
@@ -702,6 +794,9 @@ impl Formatter<'_> {
             }
             Item::Data(data) => {
                 self.push(&data.init_span, if data.variant { "|" } else { "~" });
+                if let Some(span) = &data.private_span {
+                    self.push(span, "~");
+                }
                 if let Some(name) = &data.name {
                     self.push(&name.span, &name.value);
                     self.output.push(' ');
@@ -909,6 +1004,35 @@ impl Formatter<'_> {
         }
     }
     fn format_word(&mut self, word: &Sp<Word>, depth: usize) {
+        // Names must be separated from adjacent names and numbers,
+        // except for constants like `2pi`, which are written as glyphs without a space
+        if self.config.glyphs_to_names
+            && !matches!(word.value, Word::Spaces)
+            && self.output.ends_with(char::is_alphanumeric)
+        {
+            let formatted = self.format_alone(slice::from_ref(word));
+            if formatted.starts_with(char::is_alphanumeric)
+                && !(self.output.ends_with(|c: char| c.is_ascii_digit())
+                    && ["eta", "pi", "tau", "infinity"]
+                        .iter()
+                        .any(|name| formatted.starts_with(name)))
+            {
+                self.output.push(' ');
+            }
+        }
+        // Spaces that separated names are not needed once they are glyphs
+        if self.config.names_to_glyphs
+            && !self.config.glyphs_to_names
+            && !matches!(word.value, Word::Spaces)
+            && (word.span).as_str(self.inputs, |s| s.starts_with(char::is_alphanumeric))
+            && self.ends_with_separating_space()
+            && (self.output[..self.output.len() - 1].chars().next_back()).is_some_and(|c| {
+                !c.is_alphanumeric() && c != '¯' && Primitive::from_glyph(c).is_some()
+            })
+        {
+            self.output.pop();
+            self.glyph_map.pop();
+        }
         match &word.value {
             Word::Number(Ok(n)) => {
                 let grid_str = n.grid_string(false);
@@ -947,10 +1071,21 @@ impl Formatter<'_> {
                         }
                     }
                 });
+                let formatted = if self.config.glyphs_to_names {
+                    let mut formatted = formatted.replace('¯', "`");
+                    for (glyph, name) in
+                        [("η", "eta"), ("π", "pi"), ("τ", "tau"), ("∞", "infinity")]
+                    {
+                        formatted = formatted.replace(glyph, name);
+                    }
+                    formatted
+                } else {
+                    formatted
+                };
                 if formatted.starts_with(|c: char| c.is_ascii_digit())
                     && (self
                         .output
-                        .ends_with(|c: char| c.is_ascii_digit() || c == '¯')
+                        .ends_with(|c: char| c.is_ascii_digit() || c == '¯' || c == '`')
                         || self.output.ends_with('.')
                             && (self.output.chars().nth_back(1))
                                 .is_some_and(|c| c.is_ascii_digit()))
@@ -1137,6 +1272,7 @@ impl Formatter<'_> {
             },
             Word::Spaces => self.push(&word.span, " "),
             Word::Comment(comment) => {
+                let comment = comment.trim_end_matches('\r');
                 let beginning_of_line = self
                     .output
                     .split('\n')
@@ -1247,8 +1383,9 @@ impl Formatter<'_> {
                                 lines.push(str::repeat(" ", prepad));
                             }
                             lines[j].push_str(line);
-                            if lines[j].chars().count() > 200 {
-                                lines[j] = lines[j].chars().take(199).collect();
+                            let width = self.config.line_width.max(1);
+                            if lines[j].chars().count() > width {
+                                lines[j] = lines[j].chars().take(width - 1).collect();
                                 lines[j].push('…');
                             }
                         }
@@ -1281,14 +1418,28 @@ impl Formatter<'_> {
         }
     }
     fn format_primitive(&mut self, prim: Primitive, span: &CodeSpan) {
+        if self.config.glyphs_to_names {
+            // Deprecated primitives cannot be written with names
+            match prim.glyph().and(primitive_name(prim)) {
+                Some(name) => self.push(span, name),
+                None => self.push(span, &prim.to_string()),
+            }
+            return;
+        }
+        if !self.config.names_to_glyphs {
+            let text = span.as_str(self.inputs, |s| s.to_string());
+            self.push(span, &text);
+            return;
+        }
         let as_str = prim.to_string();
         if self.output.ends_with(' ')
             && span.end.char_pos - span.start.char_pos > 1
             && !(as_str.starts_with(is_ident_char) || as_str.starts_with('&'))
-            && self.output[..self.output.len() - 1].ends_with(is_ident_char)
-            && (self.glyph_map.last()).is_some_and(|(last_span, _)| {
-                last_span.end.char_pos - last_span.start.char_pos == 1
-            })
+            && (self.output[..self.output.len() - 1].ends_with(is_ident_char)
+                && (self.glyph_map.last()).is_some_and(|(last_span, _)| {
+                    last_span.end.char_pos - last_span.start.char_pos == 1
+                })
+                || self.ends_with_separating_space())
         {
             self.output.pop();
             self.glyph_map.pop();
@@ -1298,6 +1449,15 @@ impl Formatter<'_> {
             _ => self.push(span, &as_str),
         }
     }
+    /// Whether the output ends with a space that separated alphanumeric source text
+    fn ends_with_separating_space(&self) -> bool {
+        matches!(self.glyph_map.as_slice(), [.., (prev, (_, prev_end)), (space, (space_start, space_end))]
+            if space_end.byte_pos as usize == self.output.len()
+                && prev_end.byte_pos == space_start.byte_pos
+                && prev.end.char_pos == space.start.char_pos
+                && space.as_str(self.inputs, |s| s == " ")
+                && prev.as_str(self.inputs, |s| s.ends_with(char::is_alphanumeric)))
+    }
 }
 
 #[derive(PartialEq)]
@@ -1455,15 +1615,186 @@ impl Formatter<'_> {
             }
         }
 
-        self.format_multiline_words(&func.lines, compact, true, true, true, depth + 1);
+        let wrapped;
+        let lines =
+            if func.lines.len() > 1 && (compact == Compact::Never || func.lines[0].is_empty()) {
+                let indent = self.config.multiline_indent * (depth + 1);
+                wrapped = self.wrap_lines(func.lines.clone(), indent);
+                &wrapped
+            } else {
+                &func.lines
+            };
+        self.format_multiline_words(lines, compact, true, true, true, depth + 1);
         self.output.push(')');
     }
+    fn binding_is_single_line(&self, binding: &Binding, depth: usize) -> bool {
+        !words_are_multiline(&binding.words) && self.binding_lines(binding, depth).len() == 1
+    }
+    /// Get the lines of a binding's code, wrapping it if it is too wide
+    ///
+    /// Arrow alignment is not taken into account so that whether a
+    /// binding wraps does not depend on its neighbors.
+    fn binding_lines(&self, binding: &Binding, depth: usize) -> Vec<Vec<Sp<Word>>> {
+        let lines = flip_unsplit_lines(split_words(binding.words.clone()));
+        if lines.len() != 1 {
+            return lines;
+        }
+        let arrow = if binding.public {
+            self.glyph(" ← ", " = ")
+        } else {
+            self.glyph(" ↚ ", " =~ ")
+        };
+        let mut start = self.config.multiline_indent * depth
+            + binding.name.value.chars().count()
+            + arrow.chars().count();
+        if binding.code_macro {
+            start += 1;
+        }
+        if let Some(sig) = &binding.signature {
+            start += sig.value.args.to_string().len() + 2;
+            if sig.value.outputs != 1 {
+                start += sig.value.outputs.to_string().len() + 1;
+            }
+        }
+        let indent = self.config.multiline_indent * (depth + 1);
+        (self.wrap_line(&lines[0], start, indent)).unwrap_or(lines)
+    }
+    /// Get the width of some words when formatted on one line
+    fn words_width(&self, words: &[Sp<Word>]) -> usize {
+        self.format_alone(words).chars().count()
+    }
+    /// Format some words on one line without affecting the output
+    fn format_alone(&self, words: &[Sp<Word>]) -> String {
+        let mut formatter = Formatter {
+            src: self.src.clone(),
+            config: self.config,
+            inputs: self.inputs,
+            output: String::new(),
+            glyph_map: Vec::new(),
+            end_of_line_comments: Vec::new(),
+            prev_import_function: None,
+            output_comments: None,
+            eval_output_comments: false,
+        };
+        formatter.format_words(words, true, 0);
+        formatter.output
+    }
+    fn wrap_lines(&self, lines: Vec<Vec<Sp<Word>>>, indent: usize) -> Vec<Vec<Sp<Word>>> {
+        (lines.into_iter())
+            .flat_map(|line| self.wrap_line(&line, indent, indent).unwrap_or(vec![line]))
+            .collect()
+    }
+    /// Break a line that is wider than the line width into lines that fit within it
+    ///
+    /// The line starts at column `start`, and the lines it is broken into start at `indent`.
+    /// Lines are broken between words, preferably at spaces. The resulting lines are
+    /// ordered so that the code keeps its meaning, just like lines split with `;;`.
+    ///
+    /// Returns `None` if the line fits or cannot be broken.
+    fn wrap_line(
+        &self,
+        line: &[Sp<Word>],
+        start: usize,
+        indent: usize,
+    ) -> Option<Vec<Vec<Sp<Word>>>> {
+        if line.iter().any(|word| {
+            word_is_multiline(&word.value)
+                || matches!(
+                    word.value,
+                    Word::Comment(_)
+                        | Word::SemanticComment(_)
+                        | Word::OutputComment { .. }
+                        | Word::BreakLine
+                        | Word::FlipLine
+                )
+        }) {
+            return None;
+        }
+        if start + self.words_width(line) <= self.config.line_width {
+            return None;
+        }
+        let avail = self.config.line_width.saturating_sub(indent);
+        // Collect the pieces that lines can be broken between,
+        // and whether each one is preceded by a space
+        let mut pieces: Vec<(&[Sp<Word>], bool)> = Vec::new();
+        for chunk in line.split(|word| matches!(word.value, Word::Spaces)) {
+            if chunk.is_empty() {
+                continue;
+            }
+            if self.words_width(chunk) <= avail {
+                pieces.push((chunk, true));
+            } else {
+                for i in 0..chunk.len() {
+                    pieces.push((&chunk[i..=i], i == 0));
+                }
+            }
+        }
+        // Greedily fill lines
+        let mut lines: Vec<Vec<Sp<Word>>> = Vec::new();
+        let mut width = 0;
+        for (piece, spaced) in pieces {
+            let piece_width = self.words_width(piece);
+            let space = spaced as usize;
+            match lines.last_mut() {
+                Some(line) if width + space + piece_width <= avail => {
+                    if spaced {
+                        line.push(piece[0].span.clone().sp(Word::Spaces));
+                    }
+                    line.extend_from_slice(piece);
+                    width += space + piece_width;
+                }
+                _ => {
+                    lines.push(piece.to_vec());
+                    width = piece_width;
+                }
+            }
+        }
+        if lines.len() < 2 {
+            return None;
+        }
+        lines.reverse();
+        Some(lines)
+    }
     fn subscript(&mut self, sub: &Sp<Subscript>) {
         match &sub.value {
             Subscript::TooLarge => sub.span.as_str(self.inputs, |s| self.push(&sub.span, s)),
+            _ if self.config.glyphs_to_names => {
+                let s = sub.value.to_string();
+                let s: String = (s.chars())
+                    .map(|c| match SUBSCRIPT_DIGITS.iter().position(|&d| d == c) {
+                        Some(i) => char::from(b'0' + i as u8),
+                        None => match c {
+                            '₋' => '`',
+                            '⌞' => '<',
+                            '⌟' => '>',
+                            c => c,
+                        },
+                    })
+                    .collect();
+                self.push(&sub.span, &format!("__{s}"))
+            }
             _ => self.push(&sub.span, &sub.value.to_string()),
         }
     }
+    /// Choose between a glyph and its ASCII replacement
+    fn glyph<'b>(&self, glyph: &'b str, ascii: &'b str) -> &'b str {
+        if self.config.glyphs_to_names {
+            ascii
+        } else {
+            glyph
+        }
+    }
+}
+
+/// Get a name for a primitive that formats back to it
+///
+/// Names with spaces are shortened or replaced with an alias.
+fn primitive_name(prim: Primitive) -> Option<&'static str> {
+    let mut aliases = prim.aliases().to_vec();
+    aliases.sort_by_key(|alias| std::cmp::Reverse(alias.len()));
+    (prim.name().split(' ').take(1))
+        .chain(aliases)
+        .find(|name| Primitive::from_format_name(name) == Some(prim))
 }
 
 fn words_are_multiline(words: &[Sp<Word>]) -> bool {
@@ -1792,3 +2123,163 @@ F₁
     let formatted = format_str(input, &FormatConfig::default()).unwrap().output;
     assert_eq!(formatted, output);
 }
+
+#[test]
+#[cfg(test)]
+fn formatter_config_options() {
+    let input = "\
+X ← 1 # a
+LongName ← add 2 # b
+";
+    let config = FormatConfig::default()
+        .with_names_to_glyphs(false)
+        .with_align_binding_arrows(false);
+    let output = "\
+X ← 1            # a
+LongName ← add 2 # b
+";
+    let formatted = format_str(input, &config).unwrap().output;
+    assert_eq!(formatted, output);
+    let config = FormatConfig::default().with_line_width(16);
+    let output = "\
+X        ← 1 # a
+LongName ← +2 # b
+";
+    let formatted = format_str(input, &config).unwrap().output;
+    assert_eq!(formatted, output);
+}
+
+#[test]
+#[cfg(test)]
+fn formatter_line_wrapping() {
+    let input = "\
+⊂ [1 2 3 4 5] [6 7 8 9 10] ⇌ ⇡ 10
+F ← ⊂ [1 2 3 4 5] [6 7 8 9 10] ⇌ ⇡
+G ← (
+  ⊂ [1 2 3 4 5] [6 7 8 9 10] ⇌ ⇡
+)
+";
+    let output = "\
+[6 7 8 9 10] ⇌ ⇡ 10
+⊂ [1 2 3 4 5]
+F ← (
+  [6 7 8 9 10] ⇌ ⇡
+  ⊂ [1 2 3 4 5]
+)
+G ← (
+  [6 7 8 9 10] ⇌ ⇡
+  ⊂ [1 2 3 4 5]
+)
+";
+    let config = FormatConfig::default().with_line_width(24);
+    let formatted = format_str(input, &config).unwrap().output;
+    assert_eq!(formatted, output);
+}
+
+#[test]
+#[cfg(test)]
+fn formatter_wrapping_idempotence() {
+    let mut paths = Vec::new();
+    for dir in ["tests", "examples"] {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|ext| ext == "ua") {
+                paths.push(path);
+            }
+        }
+    }
+    for path in paths {
+        let input = std::fs::read_to_string(&path).unwrap();
+        for line_width in [200, 60, 30] {
+            let config = FormatConfig::default().with_line_width(line_width);
+            let Ok(once) = format_str(&input, &config) else {
+                continue;
+            };
+            let twice = format_str(&once.output, &config).unwrap_or_else(|e| {
+                panic!(
+                    "{} formatted with width {line_width} failed to reformat: {e}",
+                    path.display()
+                )
+            });
+            assert_eq!(
+                once.output,
+                twice.output,
+                "Formatting {} with width {line_width} is non-idempotent",
+                path.display()
+            );
+        }
+    }
+}
+
+#[test]
+#[cfg(test)]
+fn formatter_glyphs_to_names() {
+    let input = "\
+X ← ¯1
+Y ↚ 2π
+⊃₂(⇌⇡10|/+⊂X Y|⊂@a⇌\"hi\")
+┌─╴M
+  A ← ≡⊂⌟ 1 2
+└─╴
+";
+    let output = "\
+X = `1
+Y =~ 2pi
+fork__2(reverse range 10|reduce add join X Y|join@a reverse\"hi\")
+---M
+  A = rows join__> 1 2
+---
+";
+    let config = FormatConfig::default().with_glyphs_to_names(true);
+    let formatted = format_str(input, &config).unwrap().output;
+    assert_eq!(formatted, output);
+    let formatted = format_str(&formatted, &FormatConfig::default())
+        .unwrap()
+        .output;
+    assert_eq!(formatted, input);
+}
+
+#[test]
+#[cfg(test)]
+fn formatter_glyphs_to_names_round_trip() {
+    // Names are longer than glyphs, so code must not be wrapped for the
+    // formatting to round-trip
+    let glyphs = FormatConfig::default().with_line_width(1000);
+    let names = glyphs.clone().with_glyphs_to_names(true);
+    for dir in ["tests", "examples"] {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if !path.extension().is_some_and(|ext| ext == "ua") {
+                continue;
+            }
+            let input = std::fs::read_to_string(&path).unwrap();
+            let Ok(formatted) = format_str(&input, &glyphs) else {
+                continue;
+            };
+            let named = format_str(&formatted.output, &names)
+                .unwrap_or_else(|e| panic!("{} failed to format with names: {e}", path.display()));
+            let round_trip = (format_str(&named.output, &glyphs)).unwrap_or_else(|e| {
+                panic!(
+                    "{} formatted with names failed to reformat: {e}",
+                    path.display()
+                )
+            });
+            // Only whitespace may differ, because spaces that separated names
+            // are removed and function layout depends on the width of glyphs
+            let without_whitespace = |s: &str| s.replace(char::is_whitespace, "");
+            assert_eq!(
+                without_whitespace(&formatted.output),
+                without_whitespace(&round_trip.output),
+                "Formatting {} with names does not round-trip",
+                path.display()
+            );
+            let named_again = format_str(&round_trip.output, &names).unwrap();
+            assert_eq!(
+                named.output,
+                named_again.output,
+                "Formatting {} with names is not stable",
+                path.display()
+            );
+        }
+    }
+}
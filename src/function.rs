@@ -160,9 +160,9 @@ impl fmt::Display for FunctionId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             FunctionId::Named(name) => write!(f, "{name}"),
-            FunctionId::Primitive(prim) => write!(f, "{prim}"),
-            FunctionId::Macro(Some(name), span) => write!(f, "macro expansion of {name} at {span}"),
-            FunctionId::Macro(None, span) => write!(f, "macro expansion of at {span}"),
+            FunctionId::Primitive(prim) => write!(f, "{}", prim.format()),
+            FunctionId::Macro(Some(name), span) => write!(f, "macro {name} (defined at {span})"),
+            FunctionId::Macro(None, _) => write!(f, "inline macro"),
             FunctionId::Main => write!(f, "main"),
             FunctionId::Unnamed => write!(f, "unnamed"),
         }
@@ -0,0 +1,67 @@
+//! Entry points for fuzzing with `cargo fuzz`
+//!
+//! The fuzz targets themselves are in the `fuzz` directory.
+//! Inputs that once made these functions panic are kept in `tests_special/fuzz`.
+
+use std::str;
+
+use crate::{
+    format::{format_impl, FormatConfig},
+    parse, InputSrc, Inputs,
+};
+
+/// Lex and parse some bytes as Uiua code
+pub fn parse_bytes(data: &[u8]) {
+    let Ok(input) = str::from_utf8(data) else {
+        return;
+    };
+    _ = parse(input, InputSrc::Str(0), &mut Inputs::default());
+}
+
+/// Format some bytes as Uiua code
+///
+/// Panics if the formatted code does not parse
+/// or if formatting it again changes it.
+pub fn format_bytes(data: &[u8]) {
+    let Ok(input) = str::from_utf8(data) else {
+        return;
+    };
+    let config = FormatConfig::default();
+    // Output comments are not evaluated so that formatting is deterministic
+    let Ok(once) = format_impl(input, InputSrc::Str(0), &config, false) else {
+        return;
+    };
+    let twice = match format_impl(&once.output, InputSrc::Str(0), &config, false) {
+        Ok(twice) => twice,
+        Err(e) => panic!(
+            "Formatted code failed to parse:\n{}\n{}",
+            once.output,
+            e.report()
+        ),
+    };
+    assert_eq!(
+        once.output, twice.output,
+        "Formatting is not idempotent for {input:?}"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn fuzz_regressions() {
+        for entry in std::fs::read_dir("tests_special/fuzz").unwrap() {
+            let path = entry.unwrap().path();
+            let data = std::fs::read(&path).unwrap();
+            let res = std::panic::catch_unwind(|| {
+                crate::fuzz::parse_bytes(&data);
+                crate::fuzz::format_bytes(&data);
+            });
+            if let Err(e) = res {
+                let message = (e.downcast_ref::<String>().map(String::as_str))
+                    .or_else(|| e.downcast_ref::<&str>().copied())
+                    .unwrap_or_default();
+                panic!("Fuzz regression {} panicked: {message}", path.display());
+            }
+        }
+    }
+}
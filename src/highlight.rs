@@ -0,0 +1,292 @@
+//! Syntax highlighting of Uiua code
+//!
+//! This module highlights code the same way the pad does, but without any of the editor machinery.
+//! It is meant for static site generators, chat bots, and other tools that want to display Uiua code.
+//!
+//! The class names used in HTML output are stable and match the ones used by the pad's stylesheet.
+
+use colored::{Color, Colorize};
+
+use crate::{
+    docgen::escape_html,
+    lsp::{BindingDocsKind, SpanKind, Spans},
+    Compiler, PrimClass, Primitive, Signature,
+};
+
+/// A class of highlighted code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HighlightClass {
+    /// A function that takes no arguments
+    NoadicFunction,
+    /// A function that takes 1 argument
+    MonadicFunction,
+    /// A function that takes 2 arguments
+    DyadicFunction,
+    /// A function that takes 3 arguments
+    TriadicFunction,
+    /// A function that takes 4 arguments
+    TetradicFunction,
+    /// A modifier that takes 1 function
+    MonadicModifier,
+    /// A modifier that takes 2 functions
+    DyadicModifier,
+    /// A modifier that takes 3 or more functions
+    TriadicModifier,
+    /// A stack or debug function
+    StackFunction,
+    /// A number literal or constant
+    Number,
+    /// A string or character literal
+    String,
+    /// A comment
+    Comment,
+    /// A strand's underscores
+    Strand,
+    /// A module reference
+    Module,
+}
+
+impl HighlightClass {
+    /// All highlight classes
+    pub const ALL: [Self; 14] = [
+        Self::NoadicFunction,
+        Self::MonadicFunction,
+        Self::DyadicFunction,
+        Self::TriadicFunction,
+        Self::TetradicFunction,
+        Self::MonadicModifier,
+        Self::DyadicModifier,
+        Self::TriadicModifier,
+        Self::StackFunction,
+        Self::Number,
+        Self::String,
+        Self::Comment,
+        Self::Strand,
+        Self::Module,
+    ];
+    /// Get the CSS class name
+    ///
+    /// These are the same class names the pad uses
+    pub fn class_name(&self) -> &'static str {
+        match self {
+            Self::NoadicFunction => "noadic-function",
+            Self::MonadicFunction => "monadic-function",
+            Self::DyadicFunction => "dyadic-function",
+            Self::TriadicFunction => "triadic-function",
+            Self::TetradicFunction => "tetradic-function",
+            Self::MonadicModifier => "monadic-modifier",
+            Self::DyadicModifier => "dyadic-modifier",
+            Self::TriadicModifier => "triadic-modifier",
+            Self::StackFunction => "stack-function",
+            Self::Number => "number-literal",
+            Self::String => "string-literal-span",
+            Self::Comment => "comment-span",
+            Self::Strand => "strand-span",
+            Self::Module => "module",
+        }
+    }
+    /// Get the terminal color
+    ///
+    /// Returns `None` for classes that are not colored in the terminal
+    pub fn ansi_color(&self) -> Option<Color> {
+        Some(match self {
+            Self::NoadicFunction => Color::Red,
+            Self::MonadicFunction => Color::Green,
+            Self::DyadicFunction => Color::Blue,
+            Self::MonadicModifier => Color::Yellow,
+            Self::DyadicModifier | Self::TriadicModifier => Color::Magenta,
+            Self::Number => Color::TrueColor {
+                r: 235,
+                g: 136,
+                b: 68,
+            },
+            Self::String => Color::Cyan,
+            Self::Comment | Self::Strand => Color::BrightBlack,
+            Self::TriadicFunction | Self::TetradicFunction | Self::StackFunction | Self::Module => {
+                return None
+            }
+        })
+    }
+    /// Get the class of a function with the given signature
+    pub fn function(sig: Signature) -> Option<Self> {
+        Some(match sig.args {
+            0 => Self::NoadicFunction,
+            1 => Self::MonadicFunction,
+            2 => Self::DyadicFunction,
+            3 => Self::TriadicFunction,
+            4 => Self::TetradicFunction,
+            _ => return None,
+        })
+    }
+    /// Get the class of a modifier with the given number of arguments
+    pub fn modifier(margs: usize) -> Self {
+        match margs {
+            0 | 1 => Self::MonadicModifier,
+            2 => Self::DyadicModifier,
+            _ => Self::TriadicModifier,
+        }
+    }
+    /// Get the class of a primitive, possibly subscripted
+    pub fn primitive(prim: Primitive, subscript: Option<i32>) -> Option<Self> {
+        match prim.class() {
+            PrimClass::Stack | PrimClass::Debug if prim.modifier_args().is_none() => {
+                Some(Self::StackFunction)
+            }
+            PrimClass::Constant => Some(Self::Number),
+            _ => {
+                if let Some(margs) = prim.modifier_args() {
+                    Some(Self::modifier(margs))
+                } else {
+                    (prim.subscript_sig(subscript).or(prim.sig())).and_then(Self::function)
+                }
+            }
+        }
+    }
+}
+
+/// A fragment of highlighted code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Highlighted {
+    /// The text of the fragment
+    pub text: String,
+    /// The class of the fragment, if it has one
+    pub class: Option<HighlightClass>,
+}
+
+/// Split code into highlighted fragments
+///
+/// Concatenating the text of the fragments yields the original code
+pub fn highlight(code: &str) -> Vec<Highlighted> {
+    highlight_spans(code, Spans::from_input(code))
+}
+
+/// Split code into highlighted fragments, using bindings from a compiler
+///
+/// This is useful for highlighting code in the context of previously compiled code, such as in a REPL
+pub fn highlight_with_compiler(code: &str, compiler: &Compiler) -> Vec<Highlighted> {
+    highlight_spans(code, Spans::with_compiler(code, compiler))
+}
+
+fn highlight_spans(code: &str, spans: Spans) -> Vec<Highlighted> {
+    let mut frags = Vec::new();
+    let mut push = |text: &str, class: Option<HighlightClass>| {
+        if text.is_empty() {
+            return;
+        }
+        match frags.last_mut() {
+            Some(Highlighted {
+                text: last,
+                class: last_class,
+            }) if *last_class == class => last.push_str(text),
+            _ => frags.push(Highlighted {
+                text: text.into(),
+                class,
+            }),
+        }
+    };
+    let mut end = 0;
+    for span in spans.spans {
+        let start = span.span.start.byte_pos as usize;
+        let span_end = (span.span.end.byte_pos as usize).min(code.len());
+        if start < end || start > span_end {
+            continue;
+        }
+        push(&code[end..start], None);
+        let class = match span.value {
+            SpanKind::Primitive(prim, sub) | SpanKind::Subscript(Some(prim), sub) => {
+                HighlightClass::primitive(prim, sub)
+            }
+            SpanKind::Obverse(_) => HighlightClass::primitive(Primitive::Obverse, None),
+            SpanKind::Ident {
+                docs: Some(docs), ..
+            } => match docs.kind {
+                BindingDocsKind::Function { sig, .. } => HighlightClass::function(sig),
+                BindingDocsKind::Modifier(margs) => Some(HighlightClass::modifier(margs)),
+                BindingDocsKind::Module { .. } => Some(HighlightClass::Module),
+                _ => None,
+            },
+            SpanKind::String | SpanKind::ImportSrc(_) => Some(HighlightClass::String),
+            SpanKind::Number | SpanKind::Subscript(None, _) => Some(HighlightClass::Number),
+            SpanKind::Comment | SpanKind::OutputComment => Some(HighlightClass::Comment),
+            SpanKind::Strand => Some(HighlightClass::Strand),
+            SpanKind::MacroDelim(margs) => Some(HighlightClass::modifier(margs)),
+            SpanKind::Ident { .. }
+            | SpanKind::Label
+            | SpanKind::Signature
+            | SpanKind::Whitespace
+            | SpanKind::Placeholder(_)
+            | SpanKind::Delimiter
+            | SpanKind::FuncDelim(..) => None,
+        };
+        push(&code[start..span_end], class);
+        end = span_end;
+    }
+    push(&code[end..], None);
+    frags
+}
+
+/// Render highlighted fragments as ANSI-colored text
+pub fn to_ansi(frags: &[Highlighted]) -> String {
+    let mut s = String::new();
+    for frag in frags {
+        match frag.class.and_then(|class| class.ansi_color()) {
+            Some(color) => s.push_str(&frag.text.color(color).to_string()),
+            None => s.push_str(&frag.text),
+        }
+    }
+    s
+}
+
+/// Render highlighted fragments as HTML `<span>`s
+///
+/// Each classed fragment is wrapped in a `<span>` with the class name from [`HighlightClass::class_name`].
+/// The output is not wrapped in any containing element.
+pub fn to_html(frags: &[Highlighted]) -> String {
+    let mut s = String::new();
+    for frag in frags {
+        let text = escape_html(&frag.text);
+        match frag.class {
+            Some(class) => s.push_str(&format!(
+                r#"<span class="{}">{text}</span>"#,
+                class.class_name()
+            )),
+            None => s.push_str(&text),
+        }
+    }
+    s
+}
+
+/// Highlight code as ANSI-colored text
+pub fn highlight_ansi(code: &str) -> String {
+    to_ansi(&highlight(code))
+}
+
+/// Highlight code as HTML `<span>`s
+pub fn highlight_html(code: &str) -> String {
+    to_html(&highlight(code))
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn highlight_code() {
+        use super::*;
+        use colored::Colorize;
+        let code = "# Sum\n/+ ⇡5 \"<b>\"";
+        let frags = highlight(code);
+        assert_eq!(
+            frags.iter().map(|f| f.text.as_str()).collect::<String>(),
+            code
+        );
+        assert_eq!(
+            highlight_html(code),
+            "<span class=\"comment-span\"># Sum</span>\n\
+            <span class=\"monadic-modifier\">/</span>\
+            <span class=\"dyadic-function\">+</span> \
+            <span class=\"monadic-function\">⇡</span>\
+            <span class=\"number-literal\">5</span> \
+            <span class=\"string-literal-span\">&quot;&lt;b&gt;&quot;</span>"
+        );
+        assert!(highlight_ansi(code).contains(&"+".blue().to_string()));
+    }
+}
@@ -0,0 +1,231 @@
+//! Hot reloading of running programs
+//!
+//! A hot session runs a program's top level once and then calls its `Frame` function over and over.
+//! The values on the stack are the program's state. `Frame` takes the state and returns the new state.
+//!
+//! When the program is reloaded, its new top level is run and its bindings replace the old ones,
+//! but the state is kept as long as the new top level leaves the same number of values on the stack.
+//! This lets a running visual or piece of music pick up changes without restarting.
+//!
+//! If the program binds a `Sound` function, it is streamed as audio.
+//! Like the function passed to [`&ast`](https://uiua.org/docs/&ast),
+//! `Sound` takes an array of times in seconds and returns an array of samples.
+//! Reloading swaps in the new `Sound` without interrupting the stream.
+
+use std::{sync::Arc, thread};
+
+use parking_lot::Mutex;
+
+use crate::{
+    sys::stereo_samples, Array, Compiler, Function, IntoSysBackend, Uiua, UiuaResult, Value,
+};
+
+/// The name of the function that is called for each frame
+pub const FRAME: &str = "Frame";
+/// The name of the function that is streamed as audio
+pub const SOUND: &str = "Sound";
+
+/// A running program that can be reloaded without losing its state
+pub struct HotSession {
+    env: Uiua,
+    loaded: bool,
+    frame: Option<Function>,
+    sound: Arc<Mutex<SoundState>>,
+    streaming: bool,
+}
+
+#[derive(Default)]
+struct SoundState {
+    /// The environment and function that generate samples
+    current: Option<(Uiua, Function)>,
+    /// An error from generating samples, which stops the sound
+    error: Option<String>,
+}
+
+impl HotSession {
+    /// Create a new hot session
+    pub fn new(backend: impl IntoSysBackend) -> Self {
+        Self::with_env(Uiua::with_backend(backend))
+    }
+    /// Create a new hot session that runs programs in an environment
+    pub fn with_env(env: Uiua) -> Self {
+        HotSession {
+            env,
+            loaded: false,
+            frame: None,
+            sound: Default::default(),
+            streaming: false,
+        }
+    }
+    /// Get the environment that the program runs in
+    pub fn env(&mut self) -> &mut Uiua {
+        &mut self.env
+    }
+    /// Get the program's state
+    pub fn state(&self) -> &[Value] {
+        self.env.stack()
+    }
+    /// Whether the program has a [`FRAME`] function that is being called
+    ///
+    /// Frames stop being called if one fails, until the program is reloaded.
+    pub fn is_running(&self) -> bool {
+        self.frame.is_some()
+    }
+    /// Load or reload the program
+    ///
+    /// The top level of the code is run with an empty stack.
+    /// If a program was already loaded and the new top level leaves the same number of values on the stack,
+    /// those values are discarded and the previous state is kept.
+    ///
+    /// Returns whether the previous state was kept.
+    ///
+    /// If the code fails to compile, the previous program keeps running.
+    /// If its top level fails, frames are not called until the next reload.
+    pub fn load(
+        &mut self,
+        compile: impl FnOnce(&mut Compiler) -> UiuaResult<&mut Compiler>,
+    ) -> UiuaResult<bool> {
+        let mut comp = Compiler::with_backend(self.env.rt.backend.clone());
+        let asm = compile(&mut comp)?.finish();
+        let state = self.env.take_stack();
+        let res = self.env.run_asm(asm);
+        let init = self.env.take_stack();
+        let kept = self.loaded && init.len() == state.len();
+        self.env
+            .push_all(if kept || res.is_err() { state } else { init });
+        self.frame = None;
+        res?;
+        self.loaded = true;
+
+        let functions = self.env.bound_functions();
+        let frame = functions.get(FRAME).cloned();
+        if let Some(f) = &frame {
+            if f.sig.args != f.sig.outputs {
+                return Err(self.env.error(format!(
+                    "{FRAME} must have the same number of inputs and outputs, \
+                    but its signature is {}",
+                    f.sig
+                )));
+            }
+        }
+        let sound = functions.get(SOUND).cloned();
+        if let Some(f) = &sound {
+            if f.sig != (1, 1) {
+                return Err(self.env.error(format!(
+                    "{SOUND} must have 1 input and 1 output, \
+                    but its signature is {}",
+                    f.sig
+                )));
+            }
+        }
+        self.frame = frame;
+        // The sound gets its own copy of the environment so that it can run on another thread
+        let mut sound_env = self.env.clone();
+        sound_env.take_stack();
+        self.sound.lock().current = sound.map(|f| (sound_env, f));
+        if self.sound.lock().current.is_some() && !self.streaming {
+            self.start_sound();
+        }
+        Ok(kept)
+    }
+    /// Load or reload the program from a file
+    pub fn load_file(&mut self, path: impl AsRef<std::path::Path>) -> UiuaResult<bool> {
+        self.load(|comp| comp.load_file(path))
+    }
+    /// Call the program's [`FRAME`] function once
+    ///
+    /// If it fails, the state is restored to what it was before the frame,
+    /// and frames are not called until the program is reloaded.
+    pub fn frame(&mut self) -> UiuaResult {
+        let Some(f) = self.frame.clone() else {
+            return Ok(());
+        };
+        let backup = self.env.stack().to_vec();
+        if let Err(e) = self.env.call(&f) {
+            self.env.take_stack();
+            self.env.push_all(backup);
+            self.frame = None;
+            return Err(e);
+        }
+        Ok(())
+    }
+    /// Take the error that stopped the program's [`SOUND`], if there is one
+    pub fn take_sound_error(&mut self) -> Option<String> {
+        self.sound.lock().error.take()
+    }
+    fn start_sound(&mut self) {
+        self.streaming = true;
+        let backend = self.env.rt.backend.clone();
+        let sound = self.sound.clone();
+        let stream_sound = sound.clone();
+        thread::spawn(move || {
+            let res = backend.stream_audio(Box::new(move |times| {
+                let mut sound = stream_sound.lock();
+                let Some((env, f)) = &mut sound.current else {
+                    return Ok(vec![[0.0; 2]; times.len()]);
+                };
+                env.push(Array::<f64>::from(times));
+                let res = env.call(f).and_then(|()| {
+                    let samples = env.pop(1)?;
+                    stereo_samples(&samples, env)
+                });
+                match res {
+                    Ok(samples) => Ok(samples),
+                    Err(e) => {
+                        // Stop the sound but keep the stream going so that a reload can fix it
+                        env.take_stack();
+                        sound.current = None;
+                        sound.error = Some(e.report().to_string());
+                        Ok(vec![[0.0; 2]; times.len()])
+                    }
+                }
+            }));
+            if let Err(e) = res {
+                let mut sound = sound.lock();
+                sound.current = None;
+                sound.error = Some(e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    #[cfg(feature = "native_sys")]
+    fn hot_reload() {
+        use super::*;
+        use crate::SafeSys;
+        let mut session = HotSession::new(SafeSys::default());
+        let state = |session: &HotSession| session.state().to_vec();
+        assert!(!session.load(|comp| comp.load_str("Frame ← +1\n0")).unwrap());
+        for _ in 0..3 {
+            session.frame().unwrap();
+        }
+        assert_eq!(state(&session), [Value::from(3.0)]);
+        // The state is kept while the number of values is the same
+        assert!(session.load(|comp| comp.load_str("Frame ← ×2\n0")).unwrap());
+        session.frame().unwrap();
+        assert_eq!(state(&session), [Value::from(6.0)]);
+        // Failing to compile keeps the old program running
+        assert!(session.load(|comp| comp.load_str("Frame ← (")).is_err());
+        session.frame().unwrap();
+        assert_eq!(state(&session), [Value::from(12.0)]);
+        // A different number of values resets the state
+        let code = "Frame ← ∩(+1)\n0 10";
+        assert!(!session.load(|comp| comp.load_str(code)).unwrap());
+        session.frame().unwrap();
+        assert_eq!(state(&session), [Value::from(11.0), Value::from(1.0)]);
+        // A failing frame restores the state and stops the frames
+        let code = "Frame ← ⍤\"Oops\" 0\n0 0";
+        assert!(session.load(|comp| comp.load_str(code)).unwrap());
+        assert!(session.frame().is_err());
+        assert!(!session.is_running());
+        assert_eq!(state(&session), [Value::from(11.0), Value::from(1.0)]);
+        // Frame must return the state
+        assert!(session
+            .load(|comp| comp.load_str("Frame ← +\n0 0"))
+            .is_err());
+        assert!(!session.is_running());
+    }
+}
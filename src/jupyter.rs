@@ -0,0 +1,540 @@
+//! A Jupyter kernel for Uiua
+//!
+//! The kernel speaks the Jupyter messaging protocol over ZeroMQ's wire protocol (ZMTP 3.0)
+//! with the `NULL` security mechanism, which is what Jupyter uses for local kernels.
+//! Messages are signed with HMAC-SHA256 using the key from the connection file.
+
+use std::{
+    env, fs,
+    io::{self, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use crossbeam_channel::{unbounded, Sender};
+use hmac::{Hmac, Mac};
+use parking_lot::Mutex;
+use serde::*;
+use serde_json::{json, Value as Json};
+use sha2::Sha256;
+
+use crate::{
+    notebook::{CellOutput, Session},
+    NativeSys, VERSION,
+};
+
+const PROTOCOL_VERSION: &str = "5.3";
+const DELIMITER: &[u8] = b"<IDS|MSG>";
+
+/// The contents of a Jupyter connection file
+#[derive(Debug, Clone, Deserialize)]
+struct ConnectionInfo {
+    ip: String,
+    transport: String,
+    shell_port: u16,
+    iopub_port: u16,
+    stdin_port: u16,
+    control_port: u16,
+    hb_port: u16,
+    #[serde(default)]
+    key: String,
+    #[serde(default)]
+    signature_scheme: String,
+}
+
+/// A Jupyter message
+#[derive(Debug, Clone)]
+struct Message {
+    ids: Vec<Vec<u8>>,
+    header: Json,
+    parent_header: Json,
+    metadata: Json,
+    content: Json,
+}
+
+impl Message {
+    fn msg_type(&self) -> &str {
+        self.header["msg_type"].as_str().unwrap_or_default()
+    }
+}
+
+/// Run the kernel until it is asked to shut down
+///
+/// The connection file is written by Jupyter when it starts the kernel.
+pub fn run_kernel(connection_file: &Path) -> Result<(), String> {
+    let info = fs::read_to_string(connection_file)
+        .map_err(|e| format!("Failed to read {}: {e}", connection_file.display()))?;
+    let info: ConnectionInfo = serde_json::from_str(&info)
+        .map_err(|e| format!("Invalid connection file {}: {e}", connection_file.display()))?;
+    if info.transport != "tcp" {
+        return Err(format!("Unsupported transport {:?}", info.transport));
+    }
+    if !info.key.is_empty() && info.signature_scheme != "hmac-sha256" {
+        return Err(format!(
+            "Unsupported signature scheme {:?}",
+            info.signature_scheme
+        ));
+    }
+    let listen = |port: u16| {
+        TcpListener::bind((info.ip.as_str(), port))
+            .map_err(|e| format!("Failed to listen on {}:{port}: {e}", info.ip))
+    };
+    let key = Arc::new(info.key.clone().into_bytes());
+    let (send, recv) = unbounded::<(Message, Sender<Message>)>();
+
+    // Heartbeats are echoed back as they are
+    let hb = listen(info.hb_port)?;
+    thread::spawn(move || {
+        for stream in hb.incoming().flatten() {
+            thread::spawn(move || -> io::Result<()> {
+                let mut stream = stream;
+                handshake(&mut stream, "REP")?;
+                loop {
+                    let frames = read_frames(&mut stream)?;
+                    write_frames(&mut stream, &frames)?;
+                }
+            });
+        }
+    });
+
+    // Output is published to every subscriber
+    let iopub = listen(info.iopub_port)?;
+    let subscribers = Arc::new(Mutex::new(Vec::<TcpStream>::new()));
+    let subs = subscribers.clone();
+    thread::spawn(move || {
+        for mut stream in iopub.incoming().flatten() {
+            if handshake(&mut stream, "PUB").is_ok() {
+                subs.lock().push(stream);
+            }
+        }
+    });
+
+    // Requests on the shell and control sockets are handled on this thread
+    for port in [info.shell_port, info.control_port] {
+        let listener = listen(port)?;
+        let send = send.clone();
+        let key = key.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let send = send.clone();
+                let key = key.clone();
+                thread::spawn(move || serve_router(stream, &key, send));
+            }
+        });
+    }
+    // Input requests are not supported, but clients still connect
+    let stdin = listen(info.stdin_port)?;
+    thread::spawn(move || {
+        for mut stream in stdin.incoming().flatten() {
+            _ = handshake(&mut stream, "ROUTER");
+        }
+    });
+
+    let mut kernel = Kernel {
+        session_id: new_id(),
+        key: key.to_vec(),
+        subscribers,
+        session: Session::new(NativeSys),
+        execution_count: 0,
+    };
+    kernel.publish(None, "status", json!({ "execution_state": "starting" }));
+    for (request, reply) in recv {
+        kernel.publish(
+            Some(&request),
+            "status",
+            json!({ "execution_state": "busy" }),
+        );
+        let shutdown = request.msg_type() == "shutdown_request";
+        if let Some((msg_type, content)) = kernel.handle(&request) {
+            _ = reply.send(kernel.reply(&request, msg_type, content));
+        }
+        kernel.publish(
+            Some(&request),
+            "status",
+            json!({ "execution_state": "idle" }),
+        );
+        if shutdown {
+            break;
+        }
+    }
+    Ok(())
+}
+
+struct Kernel {
+    session_id: String,
+    key: Vec<u8>,
+    subscribers: Arc<Mutex<Vec<TcpStream>>>,
+    session: Session,
+    execution_count: usize,
+}
+
+impl Kernel {
+    fn new_message(&self, parent: Option<&Message>, msg_type: &str, content: Json) -> Message {
+        Message {
+            ids: Vec::new(),
+            header: json!({
+                "msg_id": new_id(),
+                "session": self.session_id,
+                "username": "kernel",
+                "date": timestamp(),
+                "msg_type": msg_type,
+                "version": PROTOCOL_VERSION,
+            }),
+            parent_header: parent.map_or_else(|| json!({}), |p| p.header.clone()),
+            metadata: json!({}),
+            content,
+        }
+    }
+    fn reply(&self, request: &Message, msg_type: &str, content: Json) -> Message {
+        Message {
+            ids: request.ids.clone(),
+            ..self.new_message(Some(request), msg_type, content)
+        }
+    }
+    /// Send a message to every subscriber, dropping any that have disconnected
+    fn publish(&self, parent: Option<&Message>, msg_type: &str, content: Json) {
+        let mut message = self.new_message(parent, msg_type, content);
+        message.ids = vec![format!("kernel.{}.{msg_type}", self.session_id).into_bytes()];
+        let frames = encode_message(&message, &self.key);
+        (self.subscribers.lock()).retain_mut(|stream| write_frames(stream, &frames).is_ok());
+    }
+    /// Handle a request and get the type and content of the reply
+    fn handle(&mut self, request: &Message) -> Option<(&'static str, Json)> {
+        Some(match request.msg_type() {
+            "kernel_info_request" => (
+                "kernel_info_reply",
+                json!({
+                    "status": "ok",
+                    "protocol_version": PROTOCOL_VERSION,
+                    "implementation": "uiua",
+                    "implementation_version": VERSION,
+                    "language_info": {
+                        "name": "uiua",
+                        "version": VERSION,
+                        "mimetype": "text/x-uiua",
+                        "file_extension": ".ua",
+                    },
+                    "banner": format!("Uiua {VERSION}"),
+                }),
+            ),
+            "execute_request" => ("execute_reply", self.execute(request)),
+            "is_complete_request" => ("is_complete_reply", json!({ "status": "complete" })),
+            "comm_info_request" => ("comm_info_reply", json!({ "status": "ok", "comms": {} })),
+            "history_request" => ("history_reply", json!({ "status": "ok", "history": [] })),
+            "inspect_request" => (
+                "inspect_reply",
+                json!({ "status": "ok", "found": false, "data": {}, "metadata": {} }),
+            ),
+            "complete_request" => {
+                let cursor = request.content["cursor_pos"].as_u64().unwrap_or(0);
+                (
+                    "complete_reply",
+                    json!({
+                        "status": "ok",
+                        "matches": [],
+                        "cursor_start": cursor,
+                        "cursor_end": cursor,
+                        "metadata": {},
+                    }),
+                )
+            }
+            "interrupt_request" => ("interrupt_reply", json!({ "status": "ok" })),
+            "shutdown_request" => (
+                "shutdown_reply",
+                json!({
+                    "status": "ok",
+                    "restart": request.content["restart"].as_bool().unwrap_or(false),
+                }),
+            ),
+            _ => return None,
+        })
+    }
+    fn execute(&mut self, request: &Message) -> Json {
+        let code = request.content["code"].as_str().unwrap_or_default();
+        let silent = request.content["silent"].as_bool().unwrap_or(false);
+        if !silent {
+            self.execution_count += 1;
+        }
+        let count = self.execution_count;
+        if !silent {
+            let content = json!({ "code": code, "execution_count": count });
+            self.publish(Some(request), "execute_input", content);
+        }
+        let outputs = self.session.run_cell(code);
+        let publish = |msg_type: &str, content: Json| {
+            if !silent {
+                self.publish(Some(request), msg_type, content);
+            }
+        };
+        let mut values = Vec::new();
+        let mut error = None;
+        for output in outputs {
+            match output {
+                CellOutput::Text { text } => {
+                    publish("stream", json!({ "name": "stdout", "text": text }))
+                }
+                CellOutput::Value { text } => values.push(text),
+                CellOutput::Image { png, .. } => publish(
+                    "display_data",
+                    json!({ "data": { "image/png": STANDARD.encode(png) }, "metadata": {} }),
+                ),
+                CellOutput::Gif { gif, .. } => publish(
+                    "display_data",
+                    json!({ "data": { "image/gif": STANDARD.encode(gif) }, "metadata": {} }),
+                ),
+                CellOutput::Audio { wav, .. } => {
+                    let html = format!(
+                        "<audio controls src=\"data:audio/wav;base64,{}\"></audio>",
+                        STANDARD.encode(wav)
+                    );
+                    publish(
+                        "display_data",
+                        json!({ "data": { "text/html": html }, "metadata": {} }),
+                    )
+                }
+                CellOutput::Error { message } => error = Some(message),
+            }
+        }
+        if !values.is_empty() {
+            publish(
+                "execute_result",
+                json!({
+                    "execution_count": count,
+                    "data": { "text/plain": values.join("\n") },
+                    "metadata": {},
+                }),
+            );
+        }
+        if let Some(message) = error {
+            let error = json!({
+                "ename": "Error",
+                "evalue": message,
+                "traceback": message.lines().collect::<Vec<_>>(),
+            });
+            publish("error", error.clone());
+            let mut reply = error;
+            reply["status"] = "error".into();
+            reply["execution_count"] = count.into();
+            return reply;
+        }
+        json!({
+            "status": "ok",
+            "execution_count": count,
+            "user_expressions": {},
+            "payload": [],
+        })
+    }
+}
+
+/// Read requests from a ROUTER connection and write back their replies
+fn serve_router(mut stream: TcpStream, key: &[u8], send: Sender<(Message, Sender<Message>)>) {
+    if handshake(&mut stream, "ROUTER").is_err() {
+        return;
+    }
+    while let Ok(frames) = read_frames(&mut stream) {
+        let Some(request) = decode_message(frames, key) else {
+            continue;
+        };
+        let (reply_send, reply_recv) = unbounded();
+        if send.send((request, reply_send)).is_err() {
+            return;
+        }
+        for reply in reply_recv {
+            if write_frames(&mut stream, &encode_message(&reply, key)).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+fn sign(key: &[u8], parts: &[&[u8]]) -> String {
+    if key.is_empty() {
+        return String::new();
+    }
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    for part in parts {
+        mac.update(part);
+    }
+    let bytes = mac.finalize().into_bytes();
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn encode_message(message: &Message, key: &[u8]) -> Vec<Vec<u8>> {
+    let parts = [
+        &message.header,
+        &message.parent_header,
+        &message.metadata,
+        &message.content,
+    ]
+    .map(|part| serde_json::to_vec(part).unwrap_or_default());
+    let signature = sign(key, &parts.each_ref().map(Vec::as_slice));
+    let mut frames = message.ids.clone();
+    frames.push(DELIMITER.to_vec());
+    frames.push(signature.into_bytes());
+    frames.extend(parts);
+    frames
+}
+
+/// Decode a message, returning `None` if it is malformed or its signature is wrong
+fn decode_message(mut frames: Vec<Vec<u8>>, key: &[u8]) -> Option<Message> {
+    let delim = frames.iter().position(|frame| frame == DELIMITER)?;
+    let mut rest = frames.split_off(delim + 1);
+    frames.pop();
+    if rest.len() < 5 {
+        return None;
+    }
+    let parts: Vec<&[u8]> = rest[1..5].iter().map(Vec::as_slice).collect();
+    if rest[0] != sign(key, &parts).as_bytes() {
+        return None;
+    }
+    let mut parse = |i: usize| serde_json::from_slice(&std::mem::take(&mut rest[i])).ok();
+    Some(Message {
+        ids: frames,
+        header: parse(1)?,
+        parent_header: parse(2)?,
+        metadata: parse(3)?,
+        content: parse(4)?,
+    })
+}
+
+/// Exchange ZMTP greetings and `READY` commands
+fn handshake(stream: &mut TcpStream, socket_type: &str) -> io::Result<()> {
+    let mut greeting = [0u8; 64];
+    greeting[0] = 0xFF;
+    greeting[9] = 0x7F;
+    greeting[10] = 3;
+    greeting[12..16].copy_from_slice(b"NULL");
+    stream.write_all(&greeting)?;
+    let mut peer = [0u8; 64];
+    stream.read_exact(&mut peer)?;
+    if peer[0] != 0xFF || peer[9] != 0x7F || peer[10] < 3 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Unsupported ZMTP version",
+        ));
+    }
+    let mut ready = b"\x05READY\x0bSocket-Type".to_vec();
+    ready.extend((socket_type.len() as u32).to_be_bytes());
+    ready.extend(socket_type.as_bytes());
+    write_frame(stream, &ready, false, true)?;
+    // Wait for the peer's READY
+    loop {
+        let (_, _, command) = read_frame(stream)?;
+        if command {
+            return Ok(());
+        }
+    }
+}
+
+fn write_frame(stream: &mut TcpStream, body: &[u8], more: bool, command: bool) -> io::Result<()> {
+    let long = body.len() > 255;
+    let flags = more as u8 | (long as u8) << 1 | (command as u8) << 2;
+    let mut buffer = vec![flags];
+    if long {
+        buffer.extend((body.len() as u64).to_be_bytes());
+    } else {
+        buffer.push(body.len() as u8);
+    }
+    buffer.extend_from_slice(body);
+    stream.write_all(&buffer)
+}
+
+fn write_frames(stream: &mut TcpStream, frames: &[Vec<u8>]) -> io::Result<()> {
+    for (i, frame) in frames.iter().enumerate() {
+        write_frame(stream, frame, i + 1 < frames.len(), false)?;
+    }
+    stream.flush()
+}
+
+/// Read a frame and whether more frames follow and whether it is a command
+fn read_frame(stream: &mut TcpStream) -> io::Result<(Vec<u8>, bool, bool)> {
+    let mut flags = [0u8];
+    stream.read_exact(&mut flags)?;
+    let len = if flags[0] & 2 != 0 {
+        let mut len = [0u8; 8];
+        stream.read_exact(&mut len)?;
+        u64::from_be_bytes(len) as usize
+    } else {
+        let mut len = [0u8];
+        stream.read_exact(&mut len)?;
+        len[0] as usize
+    };
+    let mut body = vec![0; len];
+    stream.read_exact(&mut body)?;
+    Ok((body, flags[0] & 1 != 0, flags[0] & 4 != 0))
+}
+
+/// Read the frames of a message, skipping commands
+fn read_frames(stream: &mut TcpStream) -> io::Result<Vec<Vec<u8>>> {
+    let mut frames = Vec::new();
+    loop {
+        let (body, more, command) = read_frame(stream)?;
+        if command {
+            continue;
+        }
+        frames.push(body);
+        if !more {
+            return Ok(frames);
+        }
+    }
+}
+
+fn new_id() -> String {
+    let n: u128 = rand::random();
+    let hex = format!("{n:032x}");
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+fn timestamp() -> String {
+    let now = time::OffsetDateTime::now_utc();
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}Z",
+        now.year(),
+        now.month() as u8,
+        now.day(),
+        now.hour(),
+        now.minute(),
+        now.second(),
+        now.microsecond()
+    )
+}
+
+/// Install the kernel spec so that Jupyter can find the kernel
+///
+/// Returns the directory the spec was written to
+pub fn install_kernel_spec() -> Result<PathBuf, String> {
+    let exe = env::current_exe().map_err(|e| format!("Failed to find the uiua binary: {e}"))?;
+    let data_dir = if let Some(dir) = env::var_os("JUPYTER_DATA_DIR") {
+        PathBuf::from(dir)
+    } else if cfg!(windows) {
+        PathBuf::from(env::var_os("APPDATA").ok_or("APPDATA is not set")?).join("jupyter")
+    } else {
+        let home = PathBuf::from(env::var_os("HOME").ok_or("HOME is not set")?);
+        if cfg!(target_os = "macos") {
+            home.join("Library").join("Jupyter")
+        } else {
+            home.join(".local").join("share").join("jupyter")
+        }
+    };
+    let dir = data_dir.join("kernels").join("uiua");
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+    let spec = json!({
+        "argv": [exe, "kernel", "{connection_file}"],
+        "display_name": "Uiua",
+        "language": "uiua",
+    });
+    let spec = serde_json::to_string_pretty(&spec).map_err(|e| e.to_string())?;
+    let path = dir.join("kernel.json");
+    fs::write(&path, spec).map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+    Ok(dir)
+}
@@ -1052,12 +1052,8 @@ impl<'a> Lexer<'a> {
                 "←" if self.next_char_exact("~") => self.end(LeftArrowTilde, start),
                 "←" => self.end(LeftArrow, start),
                 "↚" => self.end(LeftStrokeArrow, start),
-                "┌" if self.next_char_exact("─") && self.next_char_exact("╴") => {
-                    self.end(OpenModule, start)
-                }
-                "└" if self.next_char_exact("─") && self.next_char_exact("╴") => {
-                    self.end(CloseModule, start)
-                }
+                "┌" if self.next_chars_exact(["─", "╴"]) => self.end(OpenModule, start),
+                "└" if self.next_chars_exact(["─", "╴"]) => self.end(CloseModule, start),
                 // Stack
                 "?" => {
                     self.end(Primitive::Stack, start);
@@ -133,6 +133,8 @@ The `uiua` crate has the following noteable feature flags:
 - `invoke`: Enables the `&invk` system function
 - `trash`: Enables the `&ftr` system function
 - `raw_mode`: Enables the `&raw` system function
+- `jupyter`: Enables the Jupyter kernel in the [`jupyter`] module
+- `embedded`: Enables GPIO and I2C access in the [`NativeSys`] backend on Linux
 */
 
 #![allow(
@@ -153,20 +155,37 @@ mod compile;
 mod complex;
 mod constant;
 mod cowslice;
+pub mod docgen;
 mod error;
+pub mod explain;
 mod ffi;
+#[cfg(feature = "ffi_gen")]
+#[doc(hidden)]
+pub mod ffi_gen;
 mod fill;
 pub mod format;
 mod function;
+#[cfg(any(test, feature = "fuzz"))]
+#[doc(hidden)]
+pub mod fuzz;
 mod grid_fmt;
+pub mod highlight;
+#[cfg(feature = "native_sys")]
+pub mod hot;
+#[cfg(feature = "jupyter")]
+pub mod jupyter;
 mod lex;
+pub mod lint;
 pub mod lsp;
+pub mod notebook;
 mod parse;
+mod plugin;
 mod primitive;
 #[doc(hidden)]
 pub mod profile;
 mod run;
 mod shape;
+mod snapshot;
 #[cfg(feature = "stand")]
 #[doc(hidden)]
 pub mod stand;
@@ -174,6 +193,7 @@ mod sys;
 mod tree;
 mod types;
 mod value;
+mod wasm;
 #[cfg(feature = "window")]
 #[doc(hidden)]
 pub mod window;
@@ -194,9 +214,11 @@ pub use self::{
     lex::*,
     lsp::{SpanKind, Spans},
     parse::{ident_modifier_args, parse, ParseError},
+    plugin::*,
     primitive::*,
     run::*,
     shape::*,
+    snapshot::*,
     sys::*,
     tree::*,
     value::*,
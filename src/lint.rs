@@ -0,0 +1,384 @@
+//! Check Uiua code for likely mistakes and hard-to-read patterns
+
+use std::{collections::HashMap, fmt, slice, str::FromStr};
+
+use crate::{
+    ast::*, parse, CodeSpan, DiagnosticKind, Ident, Inputs, IntoInputSrc, Primitive, Report,
+    ReportKind, SemanticComment, Sp,
+};
+
+/// A lint rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LintRule {
+    /// A binding is named like a primitive
+    ShadowedPrimitive,
+    /// A chain of [`Primitive::Dip`]s and [`Primitive::Gap`]s is too long
+    LongDipChain,
+    /// Code comes after an assertion that always fails
+    UnreachableCode,
+    /// An experimental primitive is used without `# Experimental!`
+    MissingExperimental,
+}
+
+impl LintRule {
+    /// All lint rules
+    pub const ALL: [Self; 4] = [
+        LintRule::ShadowedPrimitive,
+        LintRule::LongDipChain,
+        LintRule::UnreachableCode,
+        LintRule::MissingExperimental,
+    ];
+    /// Get the name of the rule
+    pub fn name(&self) -> &'static str {
+        match self {
+            LintRule::ShadowedPrimitive => "shadowed-primitive",
+            LintRule::LongDipChain => "long-dip-chain",
+            LintRule::UnreachableCode => "unreachable-code",
+            LintRule::MissingExperimental => "missing-experimental",
+        }
+    }
+    /// Get the level the rule has by default
+    pub fn default_level(&self) -> LintLevel {
+        match self {
+            LintRule::MissingExperimental => LintLevel::Deny,
+            _ => LintLevel::Warn,
+        }
+    }
+}
+
+impl fmt::Display for LintRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.name().fmt(f)
+    }
+}
+
+impl FromStr for LintRule {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        (LintRule::ALL.into_iter())
+            .find(|rule| rule.name() == s)
+            .ok_or_else(|| format!("Unknown lint rule `{s}`"))
+    }
+}
+
+/// How severe a lint is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LintLevel {
+    /// The lint is not reported
+    Allow,
+    /// The lint is reported as a warning
+    Warn,
+    /// The lint is reported as an error
+    Deny,
+}
+
+impl FromStr for LintLevel {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "allow" => Ok(LintLevel::Allow),
+            "warn" => Ok(LintLevel::Warn),
+            "deny" => Ok(LintLevel::Deny),
+            _ => Err(format!("Unknown lint level `{s}`")),
+        }
+    }
+}
+
+/// Configuration for the linter
+///
+/// A file can change the level of rules for itself with comments like
+/// `# lint allow shadowed-primitive long-dip-chain`.
+#[derive(Debug, Clone)]
+pub struct LintConfig {
+    levels: HashMap<LintRule, LintLevel>,
+    /// The longest chain of dips and gaps that is allowed
+    pub max_dip_chain: usize,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        LintConfig {
+            levels: HashMap::new(),
+            max_dip_chain: 3,
+        }
+    }
+}
+
+impl LintConfig {
+    /// Set the level of a rule
+    pub fn set_level(&mut self, rule: LintRule, level: LintLevel) -> &mut Self {
+        self.levels.insert(rule, level);
+        self
+    }
+    /// Get the level of a rule
+    pub fn level(&self, rule: LintRule) -> LintLevel {
+        (self.levels.get(&rule).copied()).unwrap_or_else(|| rule.default_level())
+    }
+}
+
+/// A reported lint
+#[derive(Debug, Clone)]
+pub struct Lint {
+    /// The rule that was broken
+    pub rule: LintRule,
+    /// The level of the rule
+    pub level: LintLevel,
+    /// The span of the offending code
+    pub span: CodeSpan,
+    /// The message
+    pub message: String,
+}
+
+impl Lint {
+    /// Get a rich-text report for the lint
+    pub fn report(&self, inputs: &Inputs) -> Report {
+        let kind = match self.level {
+            LintLevel::Deny => ReportKind::Error,
+            _ => ReportKind::Diagnostic(DiagnosticKind::Warning),
+        };
+        let message = format!("{} [{}]", self.message, self.rule);
+        Report::new_multi(kind, inputs, [(message, self.span.clone().into())])
+    }
+}
+
+/// Lint some Uiua code
+///
+/// Code that fails to parse is not linted.
+pub fn lint(
+    input: &str,
+    src: impl IntoInputSrc,
+    inputs: &mut Inputs,
+    config: &LintConfig,
+) -> Vec<Lint> {
+    let (items, errors, _) = parse(input, src, inputs);
+    if !errors.is_empty() {
+        return Vec::new();
+    }
+    let mut linter = Linter {
+        config: config.clone(),
+        experimental: false,
+        lints: Vec::new(),
+    };
+    linter.scan_comments(&items);
+    linter.visit_items(&items);
+    linter.lints.sort_by_key(|lint| lint.span.start.byte_pos);
+    linter.lints
+}
+
+struct Linter {
+    config: LintConfig,
+    experimental: bool,
+    lints: Vec<Lint>,
+}
+
+impl Linter {
+    fn report(&mut self, rule: LintRule, span: CodeSpan, message: impl Into<String>) {
+        let level = self.config.level(rule);
+        if level == LintLevel::Allow {
+            return;
+        }
+        self.lints.push(Lint {
+            rule,
+            level,
+            span,
+            message: message.into(),
+        });
+    }
+    /// Find lint directives and `# Experimental!` in top-level comments
+    fn scan_comments(&mut self, items: &[Item]) {
+        for item in items {
+            match item {
+                Item::Words(lines) => {
+                    for word in lines.iter().flatten() {
+                        match &word.value {
+                            Word::SemanticComment(SemanticComment::Experimental) => {
+                                self.experimental = true
+                            }
+                            Word::Comment(comment) => self.directive(comment),
+                            _ => {}
+                        }
+                    }
+                }
+                Item::Module(module) => self.scan_comments(&module.value.items),
+                _ => {}
+            }
+        }
+    }
+    fn directive(&mut self, comment: &str) {
+        let mut words = comment.split_whitespace();
+        if words.next() != Some("lint") {
+            return;
+        }
+        let Some(Ok(level)) = words.next().map(str::parse::<LintLevel>) else {
+            return;
+        };
+        for rule in words.filter_map(|word| word.parse().ok()) {
+            self.config.set_level(rule, level);
+        }
+    }
+    fn shadowed(&mut self, name: &Sp<Ident>, what: &str) {
+        let lower = name.value.trim_end_matches('!').to_lowercase();
+        if let Some(prim) = Primitive::from_name(&lower) {
+            self.report(
+                LintRule::ShadowedPrimitive,
+                name.span.clone(),
+                format!(
+                    "{what} {} has the same name as {}",
+                    name.value,
+                    prim.format()
+                ),
+            );
+        }
+    }
+    /// Check for code after an assertion that always fails
+    fn unreachable(&mut self, lines: &[Vec<Sp<Word>>]) {
+        for (i, line) in lines.iter().enumerate() {
+            let words: Vec<_> = line.iter().filter(|w| w.value.is_code()).collect();
+            let Some(assert) = words.windows(3).position(|w| {
+                matches!(w[0].value, Word::Primitive(Primitive::Assert))
+                    && w[1].value.is_literal()
+                    && matches!(w[2].value, Word::Number(Ok(n)) if n == 0.0)
+            }) else {
+                continue;
+            };
+            // Code to the left of the assertion runs after it, as do later lines
+            let later = lines[i + 1..].iter().flatten();
+            let unreachable: Vec<_> = if assert > 0 {
+                words[..assert].iter().map(|w| &w.span).collect()
+            } else {
+                later
+                    .filter(|w| w.value.is_code())
+                    .map(|w| &w.span)
+                    .collect()
+            };
+            let (Some(first), Some(last)) = (unreachable.first(), unreachable.last()) else {
+                continue;
+            };
+            let span = (*first).clone().merge((*last).clone());
+            self.report(
+                LintRule::UnreachableCode,
+                span,
+                "This code is unreachable because the assertion always fails",
+            );
+            return;
+        }
+    }
+}
+
+/// Get the length of a chain of dips and gaps and the word at its end
+fn dip_chain(mut word: &Sp<Word>) -> (usize, &Sp<Word>) {
+    let mut len = 0;
+    while let Word::Modified(m) = &word.value {
+        let (Modifier::Primitive(Primitive::Dip | Primitive::Gap), [operand]) =
+            (&m.modifier.value, m.operands.as_slice())
+        else {
+            break;
+        };
+        len += 1;
+        word = operand;
+    }
+    (len, word)
+}
+
+impl Visitor for Linter {
+    fn visit_item(&mut self, item: &Item) {
+        if let Item::Words(lines) = item {
+            self.unreachable(lines);
+        }
+        walk_item(self, item)
+    }
+    fn visit_binding(&mut self, binding: &Binding) {
+        self.shadowed(&binding.name, "Binding");
+        self.unreachable(slice::from_ref(&binding.words));
+        walk_binding(self, binding)
+    }
+    fn visit_module(&mut self, module: &Sp<ScopedModule>) {
+        if let ModuleKind::Named(name) = &module.value.kind {
+            self.shadowed(name, "Module");
+        }
+        walk_module(self, module)
+    }
+    fn visit_data_def(&mut self, data: &DataDef) {
+        if let Some(name) = &data.name {
+            self.shadowed(name, "Data definition");
+        }
+        walk_data_def(self, data)
+    }
+    fn visit_func(&mut self, func: &Func, _: &CodeSpan) {
+        self.unreachable(&func.lines);
+        walk_func(self, func)
+    }
+    fn visit_word(&mut self, word: &Sp<Word>) {
+        let (len, end) = dip_chain(word);
+        if len > self.config.max_dip_chain {
+            self.report(
+                LintRule::LongDipChain,
+                word.span.clone(),
+                format!(
+                    "This chain of {len} dips and gaps is hard to read. \
+                    Consider using {} or a function pack",
+                    Primitive::Fork.format()
+                ),
+            );
+            self.visit_word(end);
+            return;
+        }
+        walk_word(self, word)
+    }
+    fn visit_primitive(&mut self, prim: Primitive, span: &CodeSpan) {
+        if prim.is_experimental() && !self.experimental {
+            self.report(
+                LintRule::MissingExperimental,
+                span.clone(),
+                format!(
+                    "{} is experimental. Add `# Experimental!` to the top of the file to use it",
+                    prim.format()
+                ),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn lints() {
+        use super::*;
+        let rules = |code: &str, config: &LintConfig| -> Vec<_> {
+            (lint(code, (), &mut Inputs::default(), config).into_iter())
+                .map(|lint| (lint.rule, lint.level))
+                .collect()
+        };
+        let code = "\
+Rows ← +1
+F ← ⊙⊙⊙⋅+
+G ← ⊙⊙⋅+
+&p \"hi\" ⍤\"bad\" 0
+H ← union";
+        let mut config = LintConfig::default();
+        assert_eq!(
+            rules(code, &config),
+            [
+                (LintRule::ShadowedPrimitive, LintLevel::Warn),
+                (LintRule::LongDipChain, LintLevel::Warn),
+                (LintRule::UnreachableCode, LintLevel::Warn),
+                (LintRule::MissingExperimental, LintLevel::Deny),
+            ]
+        );
+        config.max_dip_chain = 4;
+        config.set_level(LintRule::UnreachableCode, LintLevel::Deny);
+        assert_eq!(
+            rules(code, &config),
+            [
+                (LintRule::ShadowedPrimitive, LintLevel::Warn),
+                (LintRule::UnreachableCode, LintLevel::Deny),
+                (LintRule::MissingExperimental, LintLevel::Deny),
+            ]
+        );
+        let code = format!("# Experimental!\n# lint allow shadowed-primitive\n{code}");
+        assert_eq!(
+            rules(&code, &config),
+            [(LintRule::UnreachableCode, LintLevel::Deny)]
+        );
+    }
+}
@@ -158,6 +158,14 @@ impl Spans {
 pub struct CodeMeta {
     /// A map of references to global bindings
     pub global_references: HashMap<CodeSpan, usize>,
+    /// A map of references to modules that are called to the modules' indices
+    ///
+    /// These spans are also in `global_references`, mapped to the function that is called.
+    pub called_modules: HashMap<CodeSpan, usize>,
+    /// A map of imported items to their bindings' indices
+    ///
+    /// These spans are also in `global_references`.
+    pub import_items: HashMap<CodeSpan, usize>,
     /// A map of references to shadowable constants
     pub constant_references: HashSet<Sp<Ident>>,
     /// Spans of functions and their signatures and whether they are explicit
@@ -170,6 +178,8 @@ pub struct CodeMeta {
     pub incomplete_refs: HashMap<CodeSpan, usize>,
     /// A map of top-level binding names to their indices
     pub top_level_names: HashMap<Ident, LocalName>,
+    /// A map of the spans of top-level lines to the stack height after them
+    pub stack_heights: HashMap<CodeSpan, usize>,
     /// A map of the spans of top-level lines to values
     pub top_level_values: HashMap<CodeSpan, Vec<Value>>,
     /// A map of strand spans
@@ -182,6 +192,25 @@ pub struct CodeMeta {
     pub import_srcs: HashMap<CodeSpan, ImportSrc>,
     /// A map of obverse spans to their set inverses
     pub obverses: HashMap<CodeSpan, SetInverses>,
+    /// A map of spans to the statically inferred types of their outputs
+    ///
+    /// This is only filled if [`Compiler::type_check`] is enabled.
+    pub inferred_types: HashMap<CodeSpan, String>,
+    /// A map of un, anti, and under spans to explanations of why their inversions failed
+    ///
+    /// This is only filled if [`Compiler::explain_inverse`] is enabled.
+    pub inversion_explanations: HashMap<CodeSpan, String>,
+    /// A map of derivative and integral spans to their functions and results in math notation
+    pub math_notations: HashMap<CodeSpan, MathNotation>,
+}
+
+/// A derivative or integral in math notation
+#[derive(Debug, Clone)]
+pub struct MathNotation {
+    /// Plain text
+    pub text: String,
+    /// A MathML `<math>` element with the LaTeX as its alt text
+    pub mathml: String,
 }
 
 /// Data for the signature of a function
@@ -275,7 +304,9 @@ struct Spanner {
 impl Spanner {
     fn new(src: InputSrc, input: &str, backend: impl SysBackend) -> Self {
         let mut compiler = Compiler::with_backend(backend);
-        compiler.pre_eval_mode(PreEvalMode::Lsp);
+        (compiler.pre_eval_mode(PreEvalMode::Lsp))
+            .type_check(true)
+            .explain_inverse(true);
         let errors = match compiler.load_str_src(input, src.clone()) {
             Ok(_) => Vec::new(),
             Err(e) => e.into_multi(),
@@ -298,6 +329,9 @@ impl Spanner {
             match item {
                 Item::Module(m) => {
                     spans.push(m.value.open_span.clone().sp(SpanKind::Delimiter));
+                    if let Some(span) = &m.value.private_span {
+                        spans.push(span.clone().sp(SpanKind::Delimiter));
+                    }
                     match &m.value.kind {
                         ModuleKind::Named(name) => {
                             let binding_docs = self.binding_docs(&name.span);
@@ -344,6 +378,9 @@ impl Spanner {
                 }
                 Item::Data(data) => {
                     spans.push(data.init_span.clone().sp(SpanKind::Delimiter));
+                    if let Some(span) = &data.private_span {
+                        spans.push(span.clone().sp(SpanKind::Delimiter));
+                    }
                     if let Some(name) = &data.name {
                         spans.push(name.span.clone().sp(SpanKind::Ident {
                             docs: self.binding_docs(&name.span),
@@ -806,7 +843,13 @@ pub use server::run_language_server;
 
 #[cfg(feature = "lsp")]
 mod server {
-    use std::{char::decode_utf16, env::current_dir, path::Path, sync::Arc};
+    use std::{
+        char::decode_utf16,
+        env::current_dir,
+        fs,
+        path::Path,
+        sync::{Arc, RwLock},
+    };
 
     use dashmap::DashMap;
     use tower_lsp::{
@@ -821,12 +864,12 @@ mod server {
     use super::*;
 
     use crate::{
-        format::{format_str, FormatConfig},
+        format::{format_str, FormatConfig, FormatConfigSource},
         is_ident_char,
         lex::{lex, Loc},
         primitive::{PrimClass, PrimDocFragment},
         subscript, AsciiToken, Assembly, BindingInfo, NativeSys, PrimDocLine, Span, Token,
-        UiuaErrorKind,
+        UiuaErrorKind, EXAMPLE_UA,
     };
 
     pub struct LspDoc {
@@ -862,6 +905,76 @@ mod server {
                 diagnostics: spanner.diagnostics,
             }
         }
+        /// Get the index of the binding whose name is at a position, and the span of that name
+        fn binding_at(&self, path: &Path, pos: Position) -> Option<(usize, CodeSpan)> {
+            let (line, col) = lsp_pos_to_uiua(pos, &self.input);
+            let at = |span: &CodeSpan| span.contains_line_col(line, col) && span.src == *path;
+            if let Some((i, binfo)) =
+                (self.asm.bindings.iter().enumerate()).find(|(_, binfo)| at(&binfo.span))
+            {
+                return Some((i, binfo.span.clone()));
+            }
+            // Called modules are also references to their called functions,
+            // so they must be checked first
+            (self.code_meta.called_modules.iter())
+                .chain(&self.code_meta.global_references)
+                .find(|(span, _)| at(span))
+                .map(|(span, i)| (*i, span.clone()))
+        }
+        /// Get the name of a binding if it can be renamed
+        fn binding_name(&self, index: usize) -> Result<Ident> {
+            let span = &self.asm.bindings[index].span;
+            let name = if let InputSrc::Macro(_) = span.src {
+                None
+            } else if let InputSrc::File(file) = &span.src {
+                // Bundled sources cannot be edited
+                (file.exists() || bundled_source(file).is_none())
+                    .then(|| single_ident(&self.asm.inputs.get(&span.src)[span.byte_range()]))
+                    .flatten()
+            } else {
+                single_ident(&self.asm.inputs.get(&span.src)[span.byte_range()])
+            };
+            name.ok_or_else(|| Error::invalid_params("This binding cannot be renamed"))
+        }
+        /// Get the names bound in the same scope as a binding
+        fn scope_names(&self, index: usize, doc_path: &Path) -> Vec<Ident> {
+            // Module items
+            for binfo in &self.asm.bindings {
+                if let BindingKind::Module(m) = &binfo.kind {
+                    if m.names.values().any(|local| local.index == index) {
+                        return m.names.keys().cloned().collect();
+                    }
+                }
+            }
+            // Top level of this document
+            let span = &self.asm.bindings[index].span;
+            if span.src == *doc_path {
+                return self.code_meta.top_level_names.keys().cloned().collect();
+            }
+            // Top level of an imported file
+            let in_module = |i: usize| {
+                (self.asm.bindings.iter()).any(|binfo| match &binfo.kind {
+                    BindingKind::Module(m) => m.names.values().any(|local| local.index == i),
+                    _ => false,
+                })
+            };
+            (self.asm.bindings.iter().enumerate())
+                .filter(|(i, binfo)| binfo.span.src == span.src && !in_module(*i))
+                .filter_map(|(i, _)| self.binding_name(i).ok())
+                .collect()
+        }
+    }
+
+    /// Get the identifier that some code consists of, if it is exactly one identifier
+    fn single_ident(code: &str) -> Option<Ident> {
+        let (tokens, errors, _) = lex(code, InputSrc::Str(0), &mut Inputs::default());
+        match tokens.as_slice() {
+            [Sp {
+                value: Token::Ident(ident),
+                ..
+            }] if errors.is_empty() => Some(ident.clone()),
+            _ => None,
+        }
     }
 
     #[doc(hidden)]
@@ -881,6 +994,7 @@ mod server {
                 let (service, socket) = LspService::new(|client| Backend {
                     client,
                     docs: DashMap::new(),
+                    roots: RwLock::new(Vec::new()),
                 });
                 Server::new(stdin, stdout, socket)
                     .concurrency_level(1)
@@ -892,6 +1006,7 @@ mod server {
     struct Backend {
         client: Client,
         docs: DashMap<Url, Arc<LspDoc>>,
+        roots: RwLock<Vec<PathBuf>>,
     }
 
     const UIUA_NUMBER_STT: SemanticTokenType = SemanticTokenType::new("uiua_number");
@@ -906,8 +1021,9 @@ mod server {
     const DYADIC_MODIFIER_STT: SemanticTokenType = SemanticTokenType::new("dyadic_modifier");
     const TRIADIC_MODIFIER_STT: SemanticTokenType = SemanticTokenType::new("triadic_modifier");
     const MODULE_STT: SemanticTokenType = SemanticTokenType::new("uiua_module");
+    const CONSTANT_STT: SemanticTokenType = SemanticTokenType::new("uiua_constant");
 
-    const UIUA_SEMANTIC_TOKEN_TYPES: [SemanticTokenType; 14] = [
+    const UIUA_SEMANTIC_TOKEN_TYPES: [SemanticTokenType; 15] = [
         SemanticTokenType::COMMENT,
         SemanticTokenType::PARAMETER,
         UIUA_NUMBER_STT,
@@ -922,10 +1038,11 @@ mod server {
         DYADIC_MODIFIER_STT,
         TRIADIC_MODIFIER_STT,
         MODULE_STT,
+        CONSTANT_STT,
     ];
 
     const NO_STT: SemanticTokenType = SemanticTokenType::new("none");
-    const GENERIC_SEMANTIC_TOKEN_TYPES: [SemanticTokenType; 14] = [
+    const GENERIC_SEMANTIC_TOKEN_TYPES: [SemanticTokenType; 15] = [
         SemanticTokenType::COMMENT,
         SemanticTokenType::PARAMETER,
         SemanticTokenType::NUMBER,
@@ -940,19 +1057,46 @@ mod server {
         SemanticTokenType::KEYWORD,
         NO_STT,
         SemanticTokenType::NAMESPACE,
+        SemanticTokenType::VARIABLE,
+    ];
+
+    const EXPERIMENTAL_STM: SemanticTokenModifier = SemanticTokenModifier::new("experimental");
+
+    const SEMANTIC_TOKEN_MODIFIERS: [SemanticTokenModifier; 3] = [
+        SemanticTokenModifier::DEPRECATED,
+        EXPERIMENTAL_STM,
+        SemanticTokenModifier::READONLY,
     ];
 
+    fn semantic_token_modifiers(modifiers: &[SemanticTokenModifier]) -> u32 {
+        (SEMANTIC_TOKEN_MODIFIERS.iter().enumerate())
+            .filter(|(_, m)| modifiers.contains(m))
+            .fold(0, |bits, (i, _)| bits | (1 << i))
+    }
+
     #[tower_lsp::async_trait]
     impl LanguageServer for Backend {
-        async fn initialize(&self, _params: InitializeParams) -> Result<InitializeResult> {
+        async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
             self.debug("Initializing Uiua language server").await;
             // self.client
             //     .log_message(
             //         MessageType::INFO,
-            //         format!("Client capabilities: {:#?}", _params.capabilities),
+            //         format!("Client capabilities: {:#?}", params.capabilities),
             //     )
             //     .await;
 
+            #[allow(deprecated)]
+            let mut roots: Vec<PathBuf> = (params.workspace_folders.into_iter().flatten())
+                .map(|folder| folder.uri)
+                .chain(params.root_uri)
+                .map(|uri| uri_path(&uri))
+                .collect();
+            if roots.is_empty() {
+                roots.extend(current_dir().ok());
+            }
+            roots.dedup();
+            *self.roots.write().unwrap() = roots;
+
             Ok(InitializeResult {
                 capabilities: ServerCapabilities {
                     text_document_sync: Some(TextDocumentSyncCapability::Kind(
@@ -964,6 +1108,7 @@ mod server {
                         ..Default::default()
                     }),
                     document_formatting_provider: Some(OneOf::Left(true)),
+                    document_range_formatting_provider: Some(OneOf::Left(true)),
                     document_on_type_formatting_provider: Some(DocumentOnTypeFormattingOptions {
                         first_trigger_character: ' '.to_string(),
                         more_trigger_character: Some(
@@ -982,17 +1127,22 @@ mod server {
                                         .into_iter()
                                         .chain(GENERIC_SEMANTIC_TOKEN_TYPES)
                                         .collect(),
-                                    token_modifiers: vec![],
+                                    token_modifiers: SEMANTIC_TOKEN_MODIFIERS.into(),
                                 },
                                 range: Some(false),
                                 full: Some(SemanticTokensFullOptions::Bool(true)),
                             },
                         ),
                     ),
-                    rename_provider: Some(OneOf::Left(true)),
+                    rename_provider: Some(OneOf::Right(RenameOptions {
+                        prepare_provider: Some(true),
+                        work_done_progress_options: WorkDoneProgressOptions::default(),
+                    })),
                     definition_provider: Some(OneOf::Left(true)),
                     declaration_provider: Some(DeclarationCapability::Simple(true)),
                     references_provider: Some(OneOf::Left(true)),
+                    workspace_symbol_provider: Some(OneOf::Left(true)),
+                    document_highlight_provider: Some(OneOf::Left(true)),
                     diagnostic_provider: Some(DiagnosticServerCapabilities::Options(
                         DiagnosticOptions {
                             inter_file_dependencies: true,
@@ -1046,10 +1196,22 @@ mod server {
                 if sp.span.contains_line_col(line, col) && sp.span.src == path {
                     match sp.value {
                         SpanKind::Primitive(prim, _) => {
+                            let mut value = full_prim_doc_markdown(prim);
+                            if let Some(ty) = doc.code_meta.inferred_types.get(&sp.span) {
+                                value = format!("→ `{ty}`\n\n{value}");
+                            }
+                            if let Some(explanation) =
+                                doc.code_meta.inversion_explanations.get(&sp.span)
+                            {
+                                value = format!("```\n{explanation}\n```\n\n{value}");
+                            }
+                            if let Some(notation) = doc.code_meta.math_notations.get(&sp.span) {
+                                value = format!("```\n{}\n```\n\n{value}", notation.text);
+                            }
                             return Ok(Some(Hover {
                                 contents: HoverContents::Markup(MarkupContent {
                                     kind: MarkupKind::Markdown,
-                                    value: full_prim_doc_markdown(prim),
+                                    value,
                                 }),
                                 range: Some(uiua_span_to_lsp(&sp.span, &doc.asm.inputs)),
                             }));
@@ -1113,6 +1275,9 @@ mod server {
                         _ => {}
                     }
                     value.push_str("\n```");
+                    if let Some(ty) = doc.code_meta.inferred_types.get(&span) {
+                        value.push_str(&format!("\n→ `{ty}`"));
+                    }
                     if let Some(escape) = &docs.escape {
                         value.push_str(&format!("\n`{escape}`"));
                     }
@@ -1403,7 +1568,7 @@ mod server {
                     }
                     BindingKind::Module(module) => {
                         for (item_name, local) in &module.names {
-                            if !local.public && !name.to_lowercase().starts_with(&lower_token) {
+                            if !local.public || !name.to_lowercase().starts_with(&lower_token) {
                                 continue;
                             }
                             completions.push(make_completion(
@@ -1456,20 +1621,27 @@ mod server {
             } else {
                 return Ok(None);
             };
-            match format_str(&doc.input, &FormatConfig::find().unwrap_or_default()) {
-                Ok(formatted) => {
-                    let range = Range::new(Position::new(0, 0), Position::new(u32::MAX, u32::MAX));
-                    Ok(Some(vec![TextEdit {
-                        range,
-                        new_text: formatted.output,
-                    }]))
-                }
-                Err(e) => {
-                    let mut error = Error::parse_error();
-                    error.message = e.to_string().into();
-                    Err(error)
-                }
+            let path = uri_path(&params.text_document.uri);
+            format_edits(&doc.input, &path, |_| true).map(Some)
+        }
+
+        async fn range_formatting(
+            &self,
+            params: DocumentRangeFormattingParams,
+        ) -> Result<Option<Vec<TextEdit>>> {
+            let Some(doc) = self.doc(&params.text_document.uri) else {
+                return Ok(None);
+            };
+            let start = params.range.start.line as usize;
+            let mut end = params.range.end.line as usize;
+            if params.range.end.character > 0 || end == start {
+                end += 1;
             }
+            let path = uri_path(&params.text_document.uri);
+            format_edits(&doc.input, &path, |lines| {
+                lines.start < end && start < lines.end.max(lines.start + 1)
+            })
+            .map(Some)
         }
 
         async fn on_type_formatting(
@@ -1524,6 +1696,19 @@ mod server {
             } else {
                 pos.line
             };
+
+            // Format the completed line
+            if is_newline {
+                let line = line as usize;
+                let path = uri_path(&params.text_document_position.text_document.uri);
+                if let Ok(edits) =
+                    format_edits(&doc.input, &path, |lines| lines == (line..line + 1))
+                {
+                    if !edits.is_empty() {
+                        return Ok(Some(edits));
+                    }
+                }
+            }
             let Some(line_str) = doc.input.lines().nth(line as usize) else {
                 return Ok(None);
             };
@@ -1651,7 +1836,18 @@ mod server {
                     _ => return None,
                 })
             };
+            let prim_modifiers = |p: Primitive| {
+                let mut modifiers = Vec::new();
+                if p.is_deprecated() {
+                    modifiers.push(SemanticTokenModifier::DEPRECATED);
+                }
+                if p.is_experimental() {
+                    modifiers.push(EXPERIMENTAL_STM);
+                }
+                modifiers
+            };
             for sp in &doc.spans {
+                let mut modifiers = Vec::new();
                 let token_type = match &sp.value {
                     SpanKind::String => UIUA_STRING_STT,
                     SpanKind::Number => UIUA_NUMBER_STT,
@@ -1660,30 +1856,39 @@ mod server {
                         let Some(stt) = for_prim(*p, *sub) else {
                             continue;
                         };
+                        modifiers = prim_modifiers(*p);
                         stt
                     }
                     SpanKind::Obverse(_) => for_prim(Primitive::Obverse, None).unwrap(),
                     SpanKind::Ident {
                         docs: Some(docs), ..
-                    } => match docs.kind {
-                        BindingDocsKind::Constant(_) => continue,
-                        BindingDocsKind::Function { sig, .. } => match sig.args {
-                            0 => NOADIC_FUNCTION_STT,
-                            1 => MONADIC_FUNCTION_STT,
-                            2 => DYADIC_FUNCTION_STT,
-                            3 => TRIADIC_FUNCTION_STT,
-                            4 => TETRADIC_FUNCTION_STT,
-                            _ => continue,
-                        },
-                        BindingDocsKind::Modifier(margs) => match margs {
-                            1 => MONADIC_MODIFIER_STT,
-                            2 => DYADIC_MODIFIER_STT,
-                            3 => TRIADIC_MODIFIER_STT,
-                            _ => continue,
-                        },
-                        BindingDocsKind::Module { .. } => MODULE_STT,
-                        BindingDocsKind::Error => continue,
-                    },
+                    } => {
+                        if docs.meta.deprecation.is_some() {
+                            modifiers.push(SemanticTokenModifier::DEPRECATED);
+                        }
+                        match docs.kind {
+                            BindingDocsKind::Constant(_) => {
+                                modifiers.push(SemanticTokenModifier::READONLY);
+                                CONSTANT_STT
+                            }
+                            BindingDocsKind::Function { sig, .. } => match sig.args {
+                                0 => NOADIC_FUNCTION_STT,
+                                1 => MONADIC_FUNCTION_STT,
+                                2 => DYADIC_FUNCTION_STT,
+                                3 => TRIADIC_FUNCTION_STT,
+                                4 => TETRADIC_FUNCTION_STT,
+                                _ => continue,
+                            },
+                            BindingDocsKind::Modifier(margs) => match margs {
+                                1 => MONADIC_MODIFIER_STT,
+                                2 => DYADIC_MODIFIER_STT,
+                                3 => TRIADIC_MODIFIER_STT,
+                                _ => continue,
+                            },
+                            BindingDocsKind::Module { .. } => MODULE_STT,
+                            BindingDocsKind::Error => continue,
+                        }
+                    }
                     SpanKind::Subscript(Some(prim), n) => {
                         let Some(stt) = for_prim(*prim, *n) else {
                             continue;
@@ -1713,7 +1918,7 @@ mod server {
                     delta_start,
                     length,
                     token_type,
-                    token_modifiers_bitset: 0,
+                    token_modifiers_bitset: semantic_token_modifiers(&modifiers),
                 };
                 tokens.push(token);
                 prev_line = start.line;
@@ -1930,6 +2135,267 @@ mod server {
                 }
             }
 
+            let quick_fix = |title: String, edits: Vec<TextEdit>| {
+                CodeActionOrCommand::CodeAction(CodeAction {
+                    title,
+                    kind: Some(CodeActionKind::QUICKFIX),
+                    edit: Some(WorkspaceEdit {
+                        changes: Some([(params.text_document.uri.clone(), edits)].into()),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })
+            };
+            let line_text = |line: usize| doc.input.split('\n').nth(line - 1).unwrap_or("");
+            let indent = |line: usize| {
+                let text = line_text(line);
+                text[..text.len() - text.trim_start().len()].to_string()
+            };
+            let line_start = Position::new(line as u32 - 1, 0);
+
+            // Add signature comment
+            for binding in &doc.asm.bindings {
+                if binding.span.src != path || binding.span.start.line as usize != line {
+                    continue;
+                }
+                let BindingKind::Func(f) = &binding.kind else {
+                    continue;
+                };
+                let has_sig_comment =
+                    (binding.meta.comment.as_ref()).is_some_and(|c| c.sig.is_some());
+                if f.sig.args == 0 || has_sig_comment {
+                    continue;
+                }
+                let mut names = (b'A'..=b'Z').map(|c| (c as char).to_string());
+                let args: Vec<_> = names.by_ref().take(f.sig.args).collect();
+                let outputs: Vec<_> = names.take(f.sig.outputs).collect();
+                let mut comment = format!("{}#", indent(line));
+                for output in &outputs {
+                    comment.push(' ');
+                    comment.push_str(output);
+                }
+                comment.push_str(" ? ");
+                comment.push_str(&args.join(" "));
+                comment.push('\n');
+                actions.push(quick_fix(
+                    "Add signature comment".into(),
+                    vec![TextEdit {
+                        range: Range::new(line_start, line_start),
+                        new_text: comment,
+                    }],
+                ));
+            }
+
+            // Format name as glyph
+            for span in &doc.spans {
+                let SpanKind::Primitive(prim, _) = span.value else {
+                    continue;
+                };
+                if !span.span.contains_line_col(line, col) || span.span.src != path {
+                    continue;
+                }
+                let Some(glyph) = prim.glyph() else {
+                    continue;
+                };
+                let text = span.span.as_str(&doc.asm.inputs, |s| s.to_string());
+                if !text.chars().any(|c| c.is_ascii_alphabetic()) {
+                    continue;
+                }
+                actions.push(quick_fix(
+                    format!("Format `{text}` as {glyph}"),
+                    vec![TextEdit {
+                        range: uiua_span_to_lsp(&span.span, &doc.asm.inputs),
+                        new_text: glyph.to_string(),
+                    }],
+                ));
+            }
+
+            // Use inferred signature
+            for diag in &doc.diagnostics {
+                let Span::Code(span) = &diag.span else {
+                    continue;
+                };
+                if !span.contains_line_col_end(line, col) || span.src != path {
+                    continue;
+                }
+                let Some((_, inferred)) = (diag.message.strip_prefix("Signature mismatch: "))
+                    .and_then(|message| message.split_once(" but inferred "))
+                else {
+                    continue;
+                };
+                let inferred: String = (inferred.chars())
+                    .take_while(|&c| c == '|' || c == '.' || c.is_ascii_digit())
+                    .collect();
+                let inferred = inferred.trim_end_matches('.');
+                let trailing =
+                    span.as_str(&doc.asm.inputs, |s| s[s.trim_end().len()..].to_string());
+                actions.push(quick_fix(
+                    format!("Change signature to {inferred}"),
+                    vec![TextEdit {
+                        range: uiua_span_to_lsp(span, &doc.asm.inputs),
+                        new_text: format!("{inferred}{trailing}"),
+                    }],
+                ));
+            }
+
+            // Create binding for unknown identifier
+            for error in &doc.errors {
+                let UiuaErrorKind::Run { message, .. } = &error.kind else {
+                    continue;
+                };
+                let Span::Code(span) = &message.span else {
+                    continue;
+                };
+                if !span.contains_line_col_end(line, col) || span.src != path {
+                    continue;
+                }
+                let Some(name) = (message.value.strip_prefix("Unknown identifier `"))
+                    .and_then(|s| s.strip_suffix('`'))
+                    .and_then(single_ident)
+                else {
+                    continue;
+                };
+                // Replace with suggested name
+                let suggestion = (error.infos.iter())
+                    .find_map(|(info, _)| info.strip_prefix("Did you mean "))
+                    .and_then(|s| s.strip_suffix('?'));
+                if let Some(suggestion) = suggestion {
+                    let replacement = if let Some(name) =
+                        (suggestion.strip_prefix('`')).and_then(|s| s.strip_suffix('`'))
+                    {
+                        name
+                    } else if let Some((glyph, prim_name)) = suggestion.split_once(' ') {
+                        if name.chars().all(|c| c.is_ascii_alphabetic()) {
+                            prim_name
+                        } else {
+                            glyph
+                        }
+                    } else {
+                        suggestion
+                    };
+                    actions.push(quick_fix(
+                        format!("Replace with `{replacement}`"),
+                        vec![TextEdit {
+                            range: uiua_span_to_lsp(span, &doc.asm.inputs),
+                            new_text: replacement.into(),
+                        }],
+                    ));
+                }
+                let error_line = span.start.line as usize;
+                let error_line_start = Position::new(error_line as u32 - 1, 0);
+                actions.push(quick_fix(
+                    format!("Create binding `{name}`"),
+                    vec![TextEdit {
+                        range: Range::new(error_line_start, error_line_start),
+                        new_text: format!("{}{name} ← ()\n", indent(error_line)),
+                    }],
+                ));
+            }
+
+            // Replace deprecated binding
+            for sp in &doc.spans {
+                let SpanKind::Ident {
+                    docs: Some(docs),
+                    original: false,
+                } = &sp.value
+                else {
+                    continue;
+                };
+                if !sp.span.contains_line_col_end(line, col) || sp.span.src != path {
+                    continue;
+                }
+                let Some(replacement) = docs.meta.deprecation_replacement() else {
+                    continue;
+                };
+                actions.push(quick_fix(
+                    format!("Replace with `{replacement}`"),
+                    vec![TextEdit {
+                        range: uiua_span_to_lsp(&sp.span, &doc.asm.inputs),
+                        new_text: replacement.into(),
+                    }],
+                ));
+            }
+
+            // Remove unused imports
+            let is_used = |index: usize| {
+                (doc.code_meta.global_references.iter())
+                    .chain(&doc.code_meta.incomplete_refs)
+                    .any(|(span, &i)| {
+                        i == index
+                            && span.src == path
+                            && !doc.code_meta.import_items.contains_key(span)
+                    })
+            };
+            for (span, &index) in &doc.code_meta.import_items {
+                if span.src != path || span.start.line as usize != line || is_used(index) {
+                    continue;
+                }
+                let text = line_text(line);
+                let text_start = text.as_ptr() as usize - doc.input.as_ptr() as usize;
+                let start = span.start.byte_pos as usize - text_start;
+                let end = span.end.byte_pos as usize - text_start;
+                let mut before = text[..start].trim_end();
+                let others_on_line = (doc.code_meta.import_items.keys()).any(|other| {
+                    other != span && other.src == path && other.start.line == span.start.line
+                });
+                if !others_on_line {
+                    if let Some(b) = before.strip_suffix('~') {
+                        before = b.trim_end();
+                    }
+                }
+                let range = if before.is_empty() && text[end..].trim().is_empty() {
+                    Range::new(line_start, Position::new(line as u32, 0))
+                } else {
+                    Range::new(
+                        Position::new(line as u32 - 1, before.encode_utf16().count() as u32),
+                        Position::new(line as u32 - 1, text[..end].encode_utf16().count() as u32),
+                    )
+                };
+                actions.push(quick_fix(
+                    format!("Remove unused import `{}`", &text[start..end]),
+                    vec![TextEdit {
+                        range,
+                        new_text: String::new(),
+                    }],
+                ));
+            }
+            for (index, binding) in doc.asm.bindings.iter().enumerate() {
+                if binding.span.src != path
+                    || binding.span.start.line as usize != line
+                    || !matches!(binding.kind, BindingKind::Import(_))
+                    || is_used(index)
+                {
+                    continue;
+                }
+                let text = line_text(line);
+                let has_items = text.matches('~').count() > 1
+                    || (doc.input.split('\n').nth(line))
+                        .is_some_and(|next| next.trim_start().starts_with('~'));
+                let range = if has_items {
+                    // Only remove the name
+                    let name_end = binding.span.end.byte_pos as usize
+                        - (text.as_ptr() as usize - doc.input.as_ptr() as usize);
+                    let tilde = name_end + text[name_end..].find('~').unwrap_or(0);
+                    let start = text.len() - text.trim_start().len();
+                    Range::new(
+                        Position::new(line as u32 - 1, text[..start].encode_utf16().count() as u32),
+                        Position::new(line as u32 - 1, text[..tilde].encode_utf16().count() as u32),
+                    )
+                } else {
+                    Range::new(line_start, Position::new(line as u32, 0))
+                };
+                actions.push(quick_fix(
+                    format!(
+                        "Remove unused import `{}`",
+                        binding.span.as_str(&doc.asm.inputs, |s| s.to_string())
+                    ),
+                    vec![TextEdit {
+                        range,
+                        new_text: String::new(),
+                    }],
+                ));
+            }
+
             Ok(if actions.is_empty() {
                 None
             } else {
@@ -1937,65 +2403,73 @@ mod server {
             })
         }
 
+        async fn prepare_rename(
+            &self,
+            params: TextDocumentPositionParams,
+        ) -> Result<Option<PrepareRenameResponse>> {
+            let Some(doc) = self.doc(&params.text_document.uri) else {
+                return Ok(None);
+            };
+            let path = uri_path(&params.text_document.uri);
+            let Some((index, span)) = doc.binding_at(&path, params.position) else {
+                return Ok(None);
+            };
+            doc.binding_name(index)?;
+            let range = uiua_span_to_lsp(&span, &doc.asm.inputs);
+            Ok(Some(PrepareRenameResponse::Range(range)))
+        }
+
         async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
-            let Some(doc) = (self.docs).get(&params.text_document_position.text_document.uri)
+            let doc_uri = &params.text_document_position.text_document.uri;
+            let Some(doc) = self.doc(doc_uri) else {
+                return Ok(None);
+            };
+            let path = uri_path(doc_uri);
+            let Some((index, _)) = doc.binding_at(&path, params.text_document_position.position)
             else {
                 return Ok(None);
             };
-            let (line, col) = lsp_pos_to_uiua(params.text_document_position.position, &doc.input);
-            let path = uri_path(&params.text_document_position.text_document.uri);
-            let mut binding: Option<(&BindingInfo, usize)> = None;
-            // Check for span in bindings
-            for (i, gb) in doc.asm.bindings.iter().enumerate() {
-                if gb.span.contains_line_col(line, col) && gb.span.src == path {
-                    binding = Some((gb, i));
-                    break;
-                }
-            }
-            // Check for span in binding references
-            if binding.is_none() {
-                for (name_span, index) in &doc.code_meta.global_references {
-                    if name_span.contains_line_col(line, col) && name_span.src == path {
-                        binding = Some((&doc.asm.bindings[*index], *index));
-                        break;
+            let old_name = doc.binding_name(index)?;
+            // Validate the new name
+            let new_name = single_ident(&params.new_name).ok_or_else(|| {
+                let (tokens, ..) = lex(&params.new_name, InputSrc::Str(0), &mut Inputs::default());
+                let prim = tokens.iter().find_map(|token| match token.value {
+                    Token::Glyph(prim) => Some(prim),
+                    _ => None,
+                });
+                match prim {
+                    Some(prim) => Error::invalid_params(format!(
+                        "`{}` would be parsed as {}",
+                        params.new_name,
+                        prim.name()
+                    )),
+                    None => {
+                        Error::invalid_params(format!("`{}` is not a valid name", params.new_name))
                     }
                 }
-            }
-            let Some((binding, index)) = binding else {
+            })?;
+            if new_name == old_name {
                 return Ok(None);
-            };
-            // Collect edits
+            }
+            if ident_modifier_args(&new_name) != ident_modifier_args(&old_name) {
+                return Err(Error::invalid_params(format!(
+                    "`{new_name}` must end in the same number of !s as `{old_name}`"
+                )));
+            }
+            if doc.scope_names(index, &path).contains(&new_name) {
+                return Err(Error::invalid_params(format!(
+                    "`{new_name}` is already bound in this scope"
+                )));
+            }
             let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
-            changes.insert(
-                match &binding.span.src {
-                    InputSrc::Str(_) | InputSrc::Macro(_) => {
-                        params.text_document_position.text_document.uri.clone()
-                    }
-                    InputSrc::File(file) => path_to_uri(file)?,
-                },
-                vec![TextEdit {
-                    range: uiua_span_to_lsp(&binding.span, &doc.asm.inputs),
-                    new_text: params.new_name.clone(),
-                }],
-            );
-            for entry in &self.docs {
-                let uri = entry.key();
-                let doc = entry.value();
-                for (name_span, idx) in &doc.code_meta.global_references {
-                    if *idx == index {
-                        let uri = match &name_span.src {
-                            InputSrc::Str(_) | InputSrc::Macro(_) => uri.clone(),
-                            InputSrc::File(file) => path_to_uri(file)?,
-                        };
-                        changes.entry(uri).or_default().push(TextEdit {
-                            range: uiua_span_to_lsp(name_span, &doc.asm.inputs),
-                            new_text: params.new_name.clone(),
-                        });
-                    }
-                }
+            for (location, _) in self.binding_locations(&doc, &path, index)? {
+                changes.entry(location.uri).or_default().push(TextEdit {
+                    range: location.range,
+                    new_text: new_name.to_string(),
+                });
             }
             Ok(Some(WorkspaceEdit {
-                changes: Some(changes.clone()),
+                changes: Some(changes),
                 document_changes: None,
                 change_annotations: None,
             }))
@@ -2076,6 +2550,66 @@ mod server {
             Ok(None)
         }
 
+        async fn symbol(
+            &self,
+            params: WorkspaceSymbolParams,
+        ) -> Result<Option<Vec<SymbolInformation>>> {
+            let query = params.query.to_lowercase();
+            let matches_query = |name: &str| {
+                let mut name = name.chars().flat_map(char::to_lowercase);
+                query.chars().all(|q| name.any(|c| c == q))
+            };
+            let mut files = Vec::new();
+            for root in self.roots.read().unwrap().iter() {
+                workspace_files(root, &mut files);
+            }
+            files.sort();
+            files.dedup();
+            let mut symbols = Vec::new();
+            for file in files {
+                let Ok(uri) = path_to_uri(&file) else {
+                    continue;
+                };
+                let doc = if let Some(doc) = self.doc(&uri) {
+                    doc
+                } else {
+                    let Ok(input) = fs::read_to_string(&file) else {
+                        continue;
+                    };
+                    LspDoc::new(&uri_path(&uri), input).into()
+                };
+                for binding in self.bindings_in_doc(&doc, &uri) {
+                    let name = binding.span.as_str(&doc.asm.inputs, |s| s.to_string());
+                    if !matches_query(&name) {
+                        continue;
+                    }
+                    let kind = match &binding.kind {
+                        BindingKind::Const(_) => SymbolKind::CONSTANT,
+                        BindingKind::Func(_) => SymbolKind::FUNCTION,
+                        BindingKind::Import(_) | BindingKind::Module(_) => SymbolKind::MODULE,
+                        BindingKind::IndexMacro(_) | BindingKind::CodeMacro(_) => {
+                            SymbolKind::OPERATOR
+                        }
+                        BindingKind::Error => SymbolKind::VARIABLE,
+                    };
+                    #[allow(deprecated)]
+                    symbols.push(SymbolInformation {
+                        name,
+                        kind,
+                        tags: (binding.meta.deprecation.is_some())
+                            .then(|| vec![SymbolTag::DEPRECATED]),
+                        deprecated: None,
+                        location: Location {
+                            uri: uri.clone(),
+                            range: uiua_span_to_lsp(&binding.span, &doc.asm.inputs),
+                        },
+                        container_name: None,
+                    });
+                }
+            }
+            Ok(Some(symbols))
+        }
+
         async fn diagnostic(
             &self,
             params: DocumentDiagnosticParams,
@@ -2120,10 +2654,15 @@ mod server {
                         let Some(range) = range(err, &message.span) else {
                             continue;
                         };
+                        let mut message = message.value.clone();
+                        for (info, _) in err.infos.iter().filter(|(_, span)| span.is_none()) {
+                            message.push('\n');
+                            message.push_str(info);
+                        }
                         diagnostics.push(Diagnostic {
                             severity: Some(DiagnosticSeverity::ERROR),
                             range,
-                            message: message.value.clone(),
+                            message,
                             ..Default::default()
                         });
                     }
@@ -2196,6 +2735,7 @@ mod server {
                         "inlineSignatureHints",
                         "inlineHintMinLength",
                         "values",
+                        "stackHeight",
                     ]
                     .iter()
                     .map(|s| ConfigurationItem {
@@ -2206,25 +2746,19 @@ mod server {
                 )
                 .await
                 .unwrap_or_default();
-            let (binding_sigs, inline_sigs, min_length, show_values) = if let [serde_json::Value::Bool(
-                    binding_sigs,
-                ), serde_json::Value::Bool(
-                    inline_sigs,
-                ), serde_json::Value::Number(
-                    min_length,
-                ), serde_json::Value::Bool(
-                    show_values,
-                )] = config.as_slice()
-            {
-                (
-                    *binding_sigs,
-                    *inline_sigs,
-                    min_length.as_u64().unwrap_or(1) as usize,
-                    *show_values,
-                )
-            } else {
-                (true, true, 3, true)
+            let bool_config = |i: usize, default: bool| {
+                (config.get(i))
+                    .and_then(serde_json::Value::as_bool)
+                    .unwrap_or(default)
+            };
+            let binding_sigs = bool_config(0, true);
+            let inline_sigs = bool_config(1, true);
+            let min_length = match config.get(2) {
+                Some(serde_json::Value::Number(n)) => n.as_u64().unwrap_or(1) as usize,
+                _ => 3,
             };
+            let show_values = bool_config(3, true);
+            let stack_heights = bool_config(4, false);
             let path = uri_path(&params.text_document.uri);
             // Signature hints
             let mut hints = Vec::new();
@@ -2330,38 +2864,72 @@ mod server {
                     });
                 }
             }
+            // Stack heights
+            if stack_heights {
+                for (span, height) in &doc.code_meta.stack_heights {
+                    if span.src != path {
+                        continue;
+                    }
+                    hints.push(InlayHint {
+                        text_edits: None,
+                        position: uiua_span_to_lsp(span, &doc.asm.inputs).end,
+                        label: InlayHintLabel::String(format!("stack: {height}")),
+                        kind: None,
+                        tooltip: Some(InlayHintTooltip::String(format!(
+                            "The stack height after this line is {height}"
+                        ))),
+                        padding_left: Some(true),
+                        padding_right: None,
+                        data: None,
+                    });
+                }
+            }
 
             Ok(Some(hints))
         }
 
         async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
-            let Some(doc) = (self.docs).get(&params.text_document_position.text_document.uri)
+            let uri = &params.text_document_position.text_document.uri;
+            let Some(doc) = self.doc(uri) else {
+                return Ok(None);
+            };
+            let path = uri_path(uri);
+            let Some((index, _)) = doc.binding_at(&path, params.text_document_position.position)
             else {
                 return Ok(None);
             };
-            let (line, col) = lsp_pos_to_uiua(params.text_document_position.position, &doc.input);
-            let path = uri_path(&params.text_document_position.text_document.uri);
-            for (i, binfo) in doc.asm.bindings.iter().enumerate() {
-                if binfo.span.contains_line_col(line, col) && binfo.span.src == path {
-                    let mut locations = Vec::new();
-                    for entry in &self.docs {
-                        let uri = entry.key();
-                        let doc = entry.value();
-                        for (name_span, idx) in &doc.code_meta.global_references {
-                            if *idx == i {
-                                let uri = match &name_span.src {
-                                    InputSrc::Str(_) | InputSrc::Macro(_) => uri.clone(),
-                                    InputSrc::File(file) => path_to_uri(file)?,
-                                };
-                                let range = uiua_span_to_lsp(name_span, &doc.asm.inputs);
-                                locations.push(Location { uri, range });
-                            }
-                        }
-                    }
-                    return Ok(Some(locations));
-                }
-            }
-            Ok(None)
+            let locations = (self.binding_locations(&doc, &path, index)?.into_iter())
+                .filter(|(_, is_def)| params.context.include_declaration || !is_def)
+                .map(|(location, _)| location)
+                .collect();
+            Ok(Some(locations))
+        }
+
+        async fn document_highlight(
+            &self,
+            params: DocumentHighlightParams,
+        ) -> Result<Option<Vec<DocumentHighlight>>> {
+            let uri = &params.text_document_position_params.text_document.uri;
+            let Some(doc) = self.doc(uri) else {
+                return Ok(None);
+            };
+            let path = uri_path(uri);
+            let position = params.text_document_position_params.position;
+            let Some((index, _)) = doc.binding_at(&path, position) else {
+                return Ok(None);
+            };
+            let highlights = (self.binding_locations(&doc, &path, index)?.into_iter())
+                .filter(|(location, _)| location.uri == *uri)
+                .map(|(location, is_def)| DocumentHighlight {
+                    range: location.range,
+                    kind: Some(if is_def {
+                        DocumentHighlightKind::WRITE
+                    } else {
+                        DocumentHighlightKind::READ
+                    }),
+                })
+                .collect();
+            Ok(Some(highlights))
         }
 
         async fn inline_value(
@@ -2393,6 +2961,19 @@ mod server {
         fn doc(&self, uri: &Url) -> Option<Arc<LspDoc>> {
             self.docs.get(uri).map(|doc| Arc::clone(&doc))
         }
+        /// Get the bindings of a document that are defined in the document itself
+        fn bindings_in_doc<'a>(
+            &self,
+            doc: &'a LspDoc,
+            doc_uri: &Url,
+        ) -> impl Iterator<Item = &'a BindingInfo> {
+            let doc_path = uri_path(doc_uri);
+            (doc.asm.bindings.iter()).filter(move |binfo| match &binfo.span.src {
+                InputSrc::File(_) => binfo.span.src == doc_path,
+                InputSrc::Str(_) => true,
+                InputSrc::Macro(_) => false,
+            })
+        }
         fn bindings_in_file(
             &self,
             doc_uri: &Url,
@@ -2413,6 +2994,66 @@ mod server {
                     .collect::<Vec<_>>()
             })
         }
+        /// Get the locations of a binding's name and all references to it
+        ///
+        /// Every open document that includes the binding is searched,
+        /// since references may be in any file that imports it.
+        /// Each location is paired with whether it is the binding's definition.
+        fn binding_locations(
+            &self,
+            doc: &LspDoc,
+            doc_path: &Path,
+            index: usize,
+        ) -> Result<Vec<(Location, bool)>> {
+            let target = &doc.asm.bindings[index].span;
+            let target_path = match &target.src {
+                InputSrc::File(file) => file.canonicalize().unwrap_or_else(|_| file.to_path_buf()),
+                InputSrc::Str(_) | InputSrc::Macro(_) => doc_path.to_path_buf(),
+            };
+            let mut locations: Vec<(Location, bool)> = Vec::new();
+            for entry in &self.docs {
+                let (uri, doc) = (entry.key(), entry.value());
+                let doc_path = uri_path(uri);
+                let is_target = |span: &CodeSpan| {
+                    span.start == target.start
+                        && span.end == target.end
+                        && match &span.src {
+                            InputSrc::File(file) => {
+                                file.canonicalize().unwrap_or_else(|_| file.to_path_buf())
+                                    == target_path
+                            }
+                            InputSrc::Str(_) => doc_path == target_path,
+                            InputSrc::Macro(_) => false,
+                        }
+                };
+                for (i, binfo) in doc.asm.bindings.iter().enumerate() {
+                    if !is_target(&binfo.span) {
+                        continue;
+                    }
+                    let meta = &doc.code_meta;
+                    let references = (meta.global_references.iter())
+                        .filter(|(span, _)| !meta.called_modules.contains_key(*span))
+                        .chain(&meta.called_modules)
+                        .filter(|(_, idx)| **idx == i)
+                        .map(|(span, _)| (span, false));
+                    for (span, is_def) in [(&binfo.span, true)].into_iter().chain(references) {
+                        let uri = match &span.src {
+                            InputSrc::Str(_) => uri.clone(),
+                            InputSrc::File(file) => path_to_uri(file)?,
+                            InputSrc::Macro(_) => continue,
+                        };
+                        let location = Location {
+                            uri,
+                            range: uiua_span_to_lsp(span, &doc.asm.inputs),
+                        };
+                        if !locations.iter().any(|(loc, _)| *loc == location) {
+                            locations.push((location, is_def));
+                        }
+                    }
+                }
+            }
+            Ok(locations)
+        }
         async fn debug(&self, message: impl Into<String>) {
             self.client
                 .log_message(MessageType::INFO, message.into())
@@ -2421,6 +3062,10 @@ mod server {
     }
 
     fn path_to_uri(path: &Path) -> Result<Url> {
+        let path = match bundled_source(path) {
+            Some(source) if !path.exists() => materialize_bundled(path, source)?,
+            _ => path.to_path_buf(),
+        };
         Url::from_file_path(
             path.canonicalize()
                 .map_err(|e| Error::invalid_params(format!("Invalid file path: {}", e)))?,
@@ -2428,6 +3073,169 @@ mod server {
         .map_err(|_| Error::invalid_params("Invalid file path"))
     }
 
+    /// Format a document and get the edits to the lines that changed
+    ///
+    /// The formatter configuration closest to the document's path is used.
+    /// Only edits to line ranges accepted by `filter` are returned.
+    fn format_edits(
+        input: &str,
+        path: &Path,
+        filter: impl Fn(Lines) -> bool,
+    ) -> Result<Vec<TextEdit>> {
+        let config = FormatConfig::from_source(FormatConfigSource::SearchFile, Some(path))
+            .unwrap_or_default();
+        let formatted = format_str(input, &config).map_err(|e| {
+            let mut error = Error::parse_error();
+            error.message = e.to_string().into();
+            error
+        })?;
+        let old: Vec<&str> = input.split_inclusive('\n').collect();
+        let new: Vec<&str> = formatted.output.split_inclusive('\n').collect();
+        let line_pos = |line: usize| {
+            if line < old.len() || input.ends_with('\n') || old.is_empty() {
+                Position::new(line as u32, 0)
+            } else {
+                let last = old[old.len() - 1];
+                Position::new(line as u32 - 1, last.encode_utf16().count() as u32)
+            }
+        };
+        Ok((line_diff(&old, &new).into_iter())
+            .filter(|(old_lines, _)| filter(old_lines.clone()))
+            .map(|(old_lines, new_lines)| TextEdit {
+                range: Range::new(line_pos(old_lines.start), line_pos(old_lines.end)),
+                new_text: new[new_lines].concat(),
+            })
+            .collect())
+    }
+
+    /// A range of line indices
+    type Lines = std::ops::Range<usize>;
+
+    /// Find the ranges of lines that differ between two texts
+    ///
+    /// Each range of old lines is paired with the range of new lines that replaces it.
+    /// Runs of differing lines are paired up line by line so that edits stay small.
+    fn line_diff(old: &[&str], new: &[&str]) -> Vec<(Lines, Lines)> {
+        let prefix = (old.iter().zip(new)).take_while(|(a, b)| a == b).count();
+        let suffix = (old[prefix..].iter().rev())
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let old_mid = &old[prefix..old.len() - suffix];
+        let new_mid = &new[prefix..new.len() - suffix];
+        if old_mid.is_empty() && new_mid.is_empty() {
+            return Vec::new();
+        }
+        // Fall back to a single hunk if the changed region is too big to diff
+        if old_mid.len() * new_mid.len() > 1 << 22 {
+            return vec![(
+                prefix..prefix + old_mid.len(),
+                prefix..prefix + new_mid.len(),
+            )];
+        }
+        // Longest common subsequence lengths of the suffixes
+        let width = new_mid.len() + 1;
+        let mut lcs = vec![0u32; (old_mid.len() + 1) * width];
+        for i in (0..old_mid.len()).rev() {
+            for j in (0..new_mid.len()).rev() {
+                lcs[i * width + j] = if old_mid[i] == new_mid[j] {
+                    lcs[(i + 1) * width + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+                };
+            }
+        }
+        // Walk the table, collecting runs of differing lines
+        let mut hunks = Vec::new();
+        let mut push_run = |old: Lines, new: Lines| {
+            let paired = old.len().min(new.len());
+            for k in 0..paired {
+                hunks.push((
+                    old.start + k..old.start + k + 1,
+                    new.start + k..new.start + k + 1,
+                ));
+            }
+            if old.len() != new.len() {
+                hunks.push((old.start + paired..old.end, new.start + paired..new.end));
+            }
+        };
+        let (mut i, mut j) = (0, 0);
+        let (mut hunk_i, mut hunk_j) = (0, 0);
+        while i < old_mid.len() || j < new_mid.len() {
+            if i < old_mid.len() && j < new_mid.len() && old_mid[i] == new_mid[j] {
+                if (hunk_i, hunk_j) != (i, j) {
+                    push_run(prefix + hunk_i..prefix + i, prefix + hunk_j..prefix + j);
+                }
+                i += 1;
+                j += 1;
+                (hunk_i, hunk_j) = (i, j);
+            } else if j == new_mid.len()
+                || i < old_mid.len() && lcs[(i + 1) * width + j] >= lcs[i * width + j + 1]
+            {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        if (hunk_i, hunk_j) != (i, j) {
+            push_run(prefix + hunk_i..prefix + i, prefix + hunk_j..prefix + j);
+        }
+        hunks
+    }
+
+    /// Get the source of a library that is bundled with the interpreter
+    ///
+    /// The compiler falls back to these when the file does not exist.
+    fn bundled_source(path: &Path) -> Option<&'static str> {
+        path.ends_with("example.ua").then_some(EXAMPLE_UA)
+    }
+
+    /// Write a bundled library's source to a read-only file so that editors can open it
+    fn materialize_bundled(path: &Path, source: &str) -> Result<PathBuf> {
+        let internal = |e: std::io::Error| Error {
+            message: format!("Failed to write bundled source: {e}").into(),
+            ..Error::internal_error()
+        };
+        let dir = std::env::temp_dir().join("uiua").join(crate::VERSION);
+        let file = dir.join(path.file_name().unwrap_or_default());
+        if fs::read_to_string(&file).is_ok_and(|existing| existing == source) {
+            return Ok(file);
+        }
+        fs::create_dir_all(&dir).map_err(internal)?;
+        if let Ok(meta) = fs::metadata(&file) {
+            let mut perms = meta.permissions();
+            #[allow(clippy::permissions_set_readonly_false)]
+            perms.set_readonly(false);
+            fs::set_permissions(&file, perms).map_err(internal)?;
+        }
+        fs::write(&file, source).map_err(internal)?;
+        let mut perms = fs::metadata(&file).map_err(internal)?.permissions();
+        perms.set_readonly(true);
+        fs::set_permissions(&file, perms).map_err(internal)?;
+        Ok(file)
+    }
+
+    /// Collect the paths of all Uiua files in a workspace directory
+    fn workspace_files(root: &Path, acc: &mut Vec<PathBuf>) {
+        let Ok(entries) = fs::read_dir(root) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                if (path.file_name()).is_some_and(|name| {
+                    let name = name.to_string_lossy();
+                    name.starts_with('.') || name == "target"
+                }) {
+                    continue;
+                }
+                workspace_files(&path, acc);
+            } else if path.extension().is_some_and(|ext| ext == "ua") {
+                acc.push(path.canonicalize().unwrap_or(path));
+            }
+        }
+    }
+
     fn uri_path(uri: &Url) -> PathBuf {
         let path = uri.path().replace("/c%3A", "C:");
         let path = PathBuf::from(path);
@@ -2,6 +2,7 @@
 compile_error!("To compile the uiua interpreter binary, you must enable the `binary` feature flag");
 
 use std::{
+    cmp::Reverse,
     env,
     error::Error,
     fmt, fs,
@@ -10,13 +11,14 @@ use std::{
     process::{exit, Child, Command, Stdio},
     sync::{
         atomic::{AtomicBool, Ordering},
-        mpsc::channel,
+        mpsc::{channel, Receiver},
+        Arc,
     },
     thread::sleep,
     time::{Duration, Instant},
 };
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use colored::*;
 use notify::{EventKind, RecursiveMode, Watcher};
 use once_cell::sync::Lazy;
@@ -24,11 +26,19 @@ use parking_lot::Mutex;
 use rustyline::{error::ReadlineError, DefaultEditor};
 use terminal_size::terminal_size;
 use uiua::{
+    docgen::{DocFormat, ModuleDocs},
+    explain::{explanation, EXPLANATIONS},
+    fetch_packages,
     format::{format_file, format_str, FormatConfig, FormatConfigSource},
-    lsp::BindingDocsKind,
-    print_stack, Assembly, CodeSpan, Compiler, NativeSys, PreEvalMode, PrimClass, PrimDocFragment,
-    PrimDocLine, Primitive, RunMode, SafeSys, SpanKind, Spans, Uiua, UiuaError, UiuaErrorKind,
-    UiuaResult, CONSTANTS,
+    highlight,
+    hot::HotSession,
+    lint::{LintConfig, LintLevel},
+    new_package,
+    notebook::{Cell, CellOutput, Notebook, NotebookCache, Session},
+    print_stack, publish_package, update_git_module, Assembly, CodeSpan, Compiler, HeadlessSys,
+    Inputs, Lockfile, NativeSys, PreEvalMode, PrimClass, PrimDocFragment, PrimDocLine, Primitive,
+    RecordSys, ReplaySys, Report, RunMode, SafeSys, SandboxProfile, SandboxSys, SysBackend, Uiua,
+    UiuaError, UiuaErrorKind, UiuaResult, CONSTANTS,
 };
 
 static PRESSED_CTRL_C: AtomicBool = AtomicBool::new(false);
@@ -39,6 +49,14 @@ fn fail<T>(e: UiuaError) -> T {
     exit(1)
 }
 
+fn report(e: &UiuaError, full_trace: bool) -> Report {
+    if full_trace {
+        e.full_report()
+    } else {
+        e.report()
+    }
+}
+
 #[cfg(feature = "window")]
 fn use_window() -> bool {
     #[cfg(feature = "window")]
@@ -126,12 +144,35 @@ fn main() {
         .filter(|arg| arg.ends_with(".ua") || arg.contains(is_separator))
     {
         let args = args.collect();
-        run(path.as_ref(), args, false, None, None, None, false);
+        run(
+            path.as_ref(),
+            args,
+            false,
+            None,
+            None,
+            None,
+            false,
+            false,
+            false,
+            Vec::new(),
+            SandboxOptions::default(),
+            ReplayOptions::default(),
+            HeadlessOptions::default(),
+        );
         return;
     }
 
     // Main command parsing
     let app = App::parse();
+    // Colors are set through the environment so that `watch`'s child processes use them too
+    match app.color {
+        Some(ColorChoice::Always) => env::set_var("CLICOLOR_FORCE", "1"),
+        Some(ColorChoice::Never) => {
+            env::remove_var("CLICOLOR_FORCE");
+            env::set_var("NO_COLOR", "1");
+        }
+        Some(ColorChoice::Auto) | None => {}
+    }
     match app.command {
         Some(Comm::Init) => {
             if let Ok(path) = working_file_path() {
@@ -140,14 +181,29 @@ fn main() {
                 fs::write("main.ua", "\"Hello, World!\"").unwrap();
             }
         }
+        Some(Comm::New { name }) => match new_package(&name) {
+            Ok(dir) => println!(
+                "{} package {}",
+                "Created".bold().bright_green(),
+                dir.display()
+            ),
+            Err(e) => {
+                eprintln!("Failed to create package: {e}");
+                exit(1);
+            }
+        },
         Some(Comm::Fmt {
             path,
             formatter_options,
             io,
+            names,
         }) => {
-            let config =
+            let mut config =
                 FormatConfig::from_source(formatter_options.format_config_source, path.as_deref())
                     .unwrap_or_else(fail);
+            if names {
+                config = config.with_glyphs_to_names(true);
+            }
 
             if io {
                 let mut buffer = String::new();
@@ -180,11 +236,37 @@ fn main() {
             time_instrs,
             limit,
             mode,
+            full_trace,
+            explain_inverse,
+            markdown,
+            update,
             #[cfg(feature = "audio")]
             audio_options,
             window,
+            features,
+            sandbox,
+            replay,
+            headless,
+            dotenv,
+            #[cfg(feature = "ffi")]
+            plugin_dir,
             args,
         }) => {
+            if let Some(path) = dotenv {
+                load_dotenv(&path);
+            }
+            #[cfg(feature = "ffi")]
+            if let Some(dir) = plugin_dir {
+                uiua::set_plugin_dir(dir);
+            }
+            if markdown {
+                let Some(path) = path else {
+                    eprintln!("Pass the path of a Markdown file to run");
+                    exit(1);
+                };
+                run_markdown(&path, update, sandbox);
+                return;
+            }
             let path = if let Some(path) = path {
                 path
             } else {
@@ -207,9 +289,21 @@ fn main() {
                 mode,
                 (!no_format).then_some(formatter_options),
                 no_color,
+                full_trace,
+                explain_inverse,
+                features,
+                sandbox,
+                replay,
+                headless,
             );
         }
-        Some(Comm::Build { path, output }) => {
+        Some(Comm::Build {
+            path,
+            output,
+            emit,
+            diff,
+            features,
+        }) => {
             let path = if let Some(path) = path {
                 path
             } else {
@@ -221,16 +315,42 @@ fn main() {
                     }
                 }
             };
-            let assembly = Compiler::with_backend(NativeSys)
-                .mode(RunMode::Normal)
-                .print_diagnostics(true)
-                .load_file(&path)
-                .unwrap_or_else(fail)
-                .finish();
-            let output = output.unwrap_or_else(|| path.with_extension("uasm"));
-            let uasm = assembly.to_uasm();
-            if let Err(e) = fs::write(output, uasm) {
-                eprintln!("Failed to write assembly: {e}");
+            let build = |path: &Path| {
+                Compiler::with_backend(NativeSys)
+                    .mode(RunMode::Normal)
+                    .features(features.clone())
+                    .print_diagnostics(true)
+                    .load_file(path)
+                    .unwrap_or_else(fail)
+                    .finish()
+            };
+            let assembly = build(&path);
+            match emit {
+                BuildEmit::Uasm => {
+                    if diff.is_some() {
+                        eprintln!("--diff can only be used with --emit node");
+                        exit(1);
+                    }
+                    let output = output.unwrap_or_else(|| path.with_extension("uasm"));
+                    let uasm = assembly.to_uasm();
+                    if let Err(e) = fs::write(output, uasm) {
+                        eprintln!("Failed to write assembly: {e}");
+                    }
+                }
+                BuildEmit::Node => {
+                    let tree = if let Some(old_path) = diff {
+                        assembly.node_tree_diff(&build(&old_path))
+                    } else {
+                        assembly.node_tree()
+                    };
+                    if let Some(output) = output {
+                        if let Err(e) = fs::write(output, tree) {
+                            eprintln!("Failed to write node tree: {e}");
+                        }
+                    } else {
+                        print!("{tree}");
+                    }
+                }
             }
         }
         Some(Comm::Eval {
@@ -239,14 +359,17 @@ fn main() {
             experimental,
             #[cfg(feature = "audio")]
             audio_options,
+            features,
+            sandbox,
             args,
         }) => {
             #[cfg(feature = "audio")]
             setup_audio(audio_options);
-            let mut rt = Uiua::with_native_sys().with_args(args);
+            let mut rt = Uiua::with_backend(sandbox.backend()).with_args(args);
             rt.compile_run(|comp| {
                 comp.mode(RunMode::Normal)
                     .experimental(experimental)
+                    .features(features)
                     .print_diagnostics(true)
                     .load_str(&code)
             })
@@ -256,6 +379,9 @@ fn main() {
         Some(Comm::Test {
             path,
             formatter_options,
+            full_trace,
+            features,
+            sandbox,
             args,
         }) => {
             let path = if let Some(path) = path {
@@ -273,19 +399,24 @@ fn main() {
                 FormatConfig::from_source(formatter_options.format_config_source, Some(&path))
                     .unwrap_or_else(fail);
             format_file(&path, &config).unwrap_or_else(fail);
-            let mut rt = Uiua::with_native_sys()
+            let mut rt = Uiua::with_backend(sandbox.backend())
                 .with_file_path(&path)
                 .with_args(args);
             let res = rt.compile_run(|comp| {
                 comp.mode(RunMode::Test)
+                    .features(features)
                     .print_diagnostics(true)
-                    .load_file(path)
+                    .load_file(&path)
+            });
+            let res = res.map(|mut comp| {
+                *comp.assembly_mut() = rt.take_asm();
+                check_doc_examples(&path, comp)
             });
             if let Err(e) = &res {
-                println!("{}", e.report());
+                println!("{}", report(e, full_trace));
             }
             rt.print_reports();
-            if res.is_err() {
+            if !matches!(res, Ok(true)) {
                 exit(1);
             }
         }
@@ -294,9 +425,13 @@ fn main() {
             no_color,
             formatter_options,
             clear,
+            full_trace,
             window,
+            hot,
             args,
             stdin_file,
+            features,
+            sandbox,
         }) => {
             set_use_window(window);
             if let Err(e) = (WatchArgs {
@@ -305,8 +440,12 @@ fn main() {
                 color: !no_color,
                 format_config_source: formatter_options.format_config_source,
                 clear,
+                full_trace,
+                hot,
                 args,
                 stdin_file,
+                features,
+                sandbox,
             })
             .watch()
             {
@@ -321,6 +460,8 @@ fn main() {
             #[cfg(feature = "audio")]
             audio_options,
             stack,
+            features,
+            sandbox,
             args,
         }) => {
             let config = FormatConfig {
@@ -331,9 +472,12 @@ fn main() {
 
             #[cfg(feature = "audio")]
             setup_audio(audio_options);
-            let mut rt = Uiua::with_native_sys().with_args(args);
-            let mut compiler = Compiler::with_backend(NativeSys);
-            compiler.mode(RunMode::Normal).print_diagnostics(true);
+            let backend = sandbox.backend();
+            let mut rt = Uiua::with_backend(backend.clone()).with_args(args);
+            let mut compiler = Compiler::with_backend(backend);
+            (compiler.mode(RunMode::Normal))
+                .features(features)
+                .print_diagnostics(true);
             if let Some(file) = file {
                 compiler.load_file(file).unwrap_or_else(fail);
                 rt.run_compiler(&mut compiler).unwrap_or_else(fail);
@@ -376,6 +520,33 @@ fn main() {
                 }
             }
         }
+        Some(Comm::Fetch { update }) => match fetch_packages(update) {
+            Ok(packages) => {
+                for package in packages {
+                    if package.downloaded {
+                        println!(
+                            "{} {} {}",
+                            "Fetched".bold().bright_green(),
+                            package.name,
+                            package.version
+                        );
+                    }
+                }
+            }
+            Err(e) => {
+                eprintln!("Failed to fetch packages: {e}");
+                exit(1);
+            }
+        },
+        Some(Comm::Publish) => match publish_package() {
+            Ok((name, version)) => {
+                println!("{} {name} {version}", "Published".bold().bright_green())
+            }
+            Err(e) => {
+                eprintln!("Failed to publish package: {e}");
+                exit(1);
+            }
+        },
         #[cfg(feature = "stand")]
         Some(Comm::Stand { main, name }) => {
             let main = main.unwrap_or_else(|| "main.ua".into());
@@ -416,8 +587,129 @@ fn main() {
                 }
             }
         }
-        Some(Comm::Doc { name }) => doc(&name),
-        Some(Comm::Check { path }) => check(path).unwrap_or_else(fail),
+        #[cfg(feature = "ffi_gen")]
+        Some(Comm::FfiGen {
+            header,
+            lib,
+            output,
+        }) => {
+            let text = fs::read_to_string(&header).unwrap_or_else(|e| {
+                eprintln!("Failed to read {}: {e}", header.display());
+                exit(1)
+            });
+            let name = header.file_name().unwrap_or_default().to_string_lossy();
+            let lib = lib.unwrap_or_else(|| {
+                let stem = header.file_stem().unwrap_or_default().to_string_lossy();
+                let (prefix, suffix) = (env::consts::DLL_PREFIX, env::consts::DLL_SUFFIX);
+                format!("{prefix}{stem}{suffix}")
+            });
+            match uiua::ffi_gen::generate_bindings(&text, &name, &lib) {
+                Ok(bindings) => {
+                    let output = output.unwrap_or_else(|| header.with_extension("ua"));
+                    if let Err(e) = fs::write(output, bindings) {
+                        eprintln!("Failed to write bindings: {e}");
+                        exit(1);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Failed to generate bindings: {e}");
+                    exit(1);
+                }
+            }
+        }
+        Some(Comm::Doc { name, html, output }) => match name {
+            Some(name) if !name.ends_with(".ua") => doc(&name),
+            name => {
+                let path = if let Some(name) = name {
+                    PathBuf::from(name)
+                } else {
+                    match working_file_path() {
+                        Ok(path) => path,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            return;
+                        }
+                    }
+                };
+                gen_docs(&path, html, output).unwrap_or_else(fail);
+            }
+        },
+        Some(Comm::Check {
+            path,
+            types,
+            explain_inverse,
+        }) => check(path, types, explain_inverse).unwrap_or_else(fail),
+        Some(Comm::Lint {
+            path,
+            allow,
+            warn,
+            deny,
+            max_dip_chain,
+        }) => {
+            let mut config = LintConfig::default();
+            if let Some(max) = max_dip_chain {
+                config.max_dip_chain = max;
+            }
+            for (rules, level) in [
+                (allow, LintLevel::Allow),
+                (warn, LintLevel::Warn),
+                (deny, LintLevel::Deny),
+            ] {
+                for rule in rules {
+                    match rule.parse() {
+                        Ok(rule) => _ = config.set_level(rule, level),
+                        Err(e) => {
+                            eprintln!("{e}");
+                            exit(1);
+                        }
+                    }
+                }
+            }
+            lint(path, &config).unwrap_or_else(fail)
+        }
+        Some(Comm::Explain { code }) => explain(code),
+        Some(Comm::Notebook {
+            path,
+            output,
+            no_cache,
+        }) => notebook(&path, output, no_cache),
+        #[cfg(feature = "jupyter")]
+        Some(Comm::Kernel {
+            connection_file,
+            install,
+        }) => {
+            if install {
+                match uiua::jupyter::install_kernel_spec() {
+                    Ok(dir) => println!("Installed the Uiua kernel to {}", dir.display()),
+                    Err(e) => {
+                        eprintln!("Failed to install the kernel: {e}");
+                        exit(1);
+                    }
+                }
+            } else if let Some(connection_file) = connection_file {
+                if let Err(e) = uiua::jupyter::run_kernel(&connection_file) {
+                    eprintln!("{e}");
+                    exit(1);
+                }
+            } else {
+                eprintln!("Pass a connection file, or use --install to install the kernel");
+                exit(1);
+            }
+        }
+        Some(Comm::Expand { path }) => {
+            let path = if let Some(path) = path {
+                path
+            } else {
+                match working_file_path() {
+                    Ok(path) => path,
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        return;
+                    }
+                }
+            };
+            expand(&path).unwrap_or_else(fail);
+        }
         Some(Comm::Find { path, text, raw }) => find(path, text, raw).unwrap_or_else(fail),
         None => {
             set_use_window(app.window);
@@ -445,6 +737,7 @@ fn main() {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn run(
     path: &Path,
     args: Vec<String>,
@@ -453,8 +746,21 @@ fn run(
     mode: Option<RunMode>,
     formatter_options: Option<FormatterOptions>,
     no_color: bool,
+    full_trace: bool,
+    explain_inverse: bool,
+    features: Vec<String>,
+    sandbox: SandboxOptions,
+    replay: ReplayOptions,
+    headless: HeadlessOptions,
 ) {
-    let mut rt = Uiua::with_native_sys()
+    let backend = match (replay.backend(sandbox.backend())).and_then(|b| headless.backend(b)) {
+        Ok(backend) => backend,
+        Err(e) => {
+            eprintln!("{e}");
+            exit(1);
+        }
+    };
+    let mut rt = Uiua::with_backend(backend)
         .with_file_path(path)
         .with_args(args)
         .time_instrs(time_instrs)
@@ -483,9 +789,16 @@ fn run(
             format_file(path, &config).unwrap_or_else(fail);
         }
         let mode = mode.unwrap_or(RunMode::Normal);
-        let res = rt.compile_run(|comp| comp.mode(mode).print_diagnostics(true).load_file(path));
+        let res = rt.compile_run(|comp| {
+            comp.mode(mode)
+                .features(features)
+                .print_diagnostics(true)
+                .explain_inverse(explain_inverse)
+                .load_file(path)
+        });
+        headless.save(&rt);
         if let Err(e) = &res {
-            println!("{}", e.report());
+            println!("{}", report(e, full_trace));
         }
         rt.print_reports();
         if res.is_err() {
@@ -552,8 +865,12 @@ struct WatchArgs {
     color: bool,
     format_config_source: FormatConfigSource,
     clear: bool,
+    full_trace: bool,
+    hot: bool,
     args: Vec<String>,
     stdin_file: Option<PathBuf>,
+    features: Vec<String>,
+    sandbox: SandboxOptions,
 }
 
 impl Default for WatchArgs {
@@ -564,22 +881,33 @@ impl Default for WatchArgs {
             color: true,
             format_config_source: FormatConfigSource::SearchFile,
             clear: false,
+            full_trace: false,
+            hot: false,
             args: Vec::new(),
             stdin_file: None,
+            features: Vec::new(),
+            sandbox: SandboxOptions::default(),
         }
     }
 }
 
 impl WatchArgs {
     fn watch(self) -> Result<(), Box<dyn Error>> {
+        if self.hot {
+            return self.watch_hot();
+        }
         let WatchArgs {
             initial_path,
             format,
             color,
             format_config_source,
             clear,
+            full_trace,
+            hot: _,
             args,
             stdin_file,
+            features,
+            sandbox,
         } = self;
         let (send, recv) = channel();
         let mut watcher = notify::recommended_watcher(send)?;
@@ -636,6 +964,7 @@ impl WatchArgs {
                             com.arg("run")
                                 .arg(path)
                                 .args((!color).then_some("--no-color"))
+                                .args(full_trace.then_some("--full-trace"))
                                 .args([
                                     "--no-format",
                                     "--mode",
@@ -653,7 +982,11 @@ impl WatchArgs {
                             if use_window() {
                                 com.arg("--window");
                             }
-                            com.args(&args)
+                            for feature in &features {
+                                com.args(["--feature", feature]);
+                            }
+                            com.args(sandbox.to_args())
+                                .args(&args)
                                 .stdin(stdin_file.map_or_else(Stdio::inherit, Into::into))
                                 .spawn()
                                 .unwrap()
@@ -681,21 +1014,10 @@ impl WatchArgs {
         let mut last_time = Instant::now();
         loop {
             sleep(Duration::from_millis(10));
-            if let Some(path) = recv
-                .try_iter()
-                .filter_map(Result::ok)
-                .filter(|event| matches!(event.kind, EventKind::Modify(_)))
-                .flat_map(|event| event.paths)
-                .filter(|path| path.extension().map_or(false, |ext| ext == "ua"))
-                .last()
-            {
+            if let Some(path) = changed_file(&recv) {
                 if last_time.elapsed() > Duration::from_millis(100) {
                     if clear {
-                        if cfg!(target_os = "windows") {
-                            _ = Command::new("cmd").args(["/C", "cls"]).status();
-                        } else {
-                            _ = Command::new("clear").status();
-                        }
+                        clear_terminal();
                     }
                     run(&path, stdin_file.as_ref())?;
                     last_time = Instant::now();
@@ -718,6 +1040,115 @@ impl WatchArgs {
             }
         }
     }
+    /// Run the file in this process, reloading its bindings on change while keeping its state
+    fn watch_hot(self) -> Result<(), Box<dyn Error>> {
+        let WatchArgs {
+            initial_path,
+            format,
+            color: _,
+            format_config_source,
+            clear,
+            full_trace,
+            hot: _,
+            args,
+            stdin_file: _,
+            features,
+            sandbox,
+        } = self;
+        let (send, recv) = channel();
+        let mut watcher = notify::recommended_watcher(send)?;
+        watcher.watch(Path::new("."), RecursiveMode::Recursive)?;
+
+        println!("Hot reloading on changes... (end with ctrl+C, use `uiua help` to see options)");
+
+        let config = FormatConfig::from_source(format_config_source, initial_path.as_deref()).ok();
+        let mut env = Uiua::with_backend(sandbox.backend()).with_args(args);
+        if let Some(path) = &initial_path {
+            env = env.with_file_path(path);
+        }
+        let mut session = HotSession::with_env(env);
+        let mut loaded = false;
+        let mut load = |session: &mut HotSession, path: &Path| {
+            const TRIES: u8 = 10;
+            clear_watching();
+            let mut res = Ok(());
+            for i in 0..TRIES {
+                res = match (&config, format) {
+                    (Some(config), true) => format_file(path, config).map(drop),
+                    _ => Ok(()),
+                };
+                match &res {
+                    Err(e) if matches!(e.kind, UiuaErrorKind::Format(..)) => {
+                        sleep(Duration::from_millis((i as u64 + 1) * 10))
+                    }
+                    _ => break,
+                }
+            }
+            let res = res.and_then(|()| {
+                session.load(|comp| {
+                    comp.mode(RunMode::All)
+                        .features(features.clone())
+                        .print_diagnostics(true)
+                        .load_file(path)
+                })
+            });
+            match res {
+                Ok(false) if loaded => println!("# State was reset"),
+                Ok(_) => {}
+                Err(e) => println!("{}", report(&e, full_trace)),
+            }
+            loaded = true;
+            session.env().print_reports();
+            if !session.is_running() {
+                print_watching();
+            }
+        };
+        if let Some(path) = &initial_path {
+            load(&mut session, path);
+        }
+        let mut last_time = Instant::now();
+        loop {
+            if let Some(path) = changed_file(&recv) {
+                if last_time.elapsed() > Duration::from_millis(100) {
+                    if clear {
+                        clear_terminal();
+                    }
+                    load(&mut session, &path);
+                    last_time = Instant::now();
+                }
+            }
+            if let Some(e) = session.take_sound_error() {
+                println!("{e}");
+            }
+            if session.is_running() {
+                if let Err(e) = session.frame() {
+                    println!("{}", report(&e, full_trace));
+                    println!("# Frames are paused until the file changes");
+                    print_watching();
+                }
+            } else {
+                sleep(Duration::from_millis(10));
+            }
+        }
+    }
+}
+
+/// Get the last .ua file that was modified since the last check
+fn changed_file(recv: &Receiver<notify::Result<notify::Event>>) -> Option<PathBuf> {
+    recv.try_iter()
+        .filter_map(Result::ok)
+        .filter(|event| matches!(event.kind, EventKind::Modify(_)))
+        .flat_map(|event| event.paths)
+        .filter(|path| path.extension().map_or(false, |ext| ext == "ua"))
+        .last()
+}
+
+fn clear_terminal() {
+    if cfg!(target_os = "windows") {
+        _ = Command::new("cmd").args(["/C", "cls"]).status();
+    } else {
+        _ = Command::new("clear").status();
+    }
 }
 
 #[derive(Parser)]
@@ -732,14 +1163,33 @@ struct App {
                 Set UIUA_WINDOW=1 to always use a window."
     )]
     window: bool,
+    #[clap(
+        long,
+        global = true,
+        value_name = "WHEN",
+        help = "Whether to color output. auto respects NO_COLOR and CLICOLOR_FORCE"
+    )]
+    color: Option<ColorChoice>,
     #[clap(trailing_var_arg = true, help = "Arguments to pass to the program")]
     args: Vec<String>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
 #[derive(Subcommand)]
 enum Comm {
     #[clap(about = "Initialize a new main.ua file")]
     Init,
+    #[clap(about = "Create a new package")]
+    New {
+        #[clap(help = "The name of the package")]
+        name: String,
+    },
     #[clap(about = "Format and run a file")]
     Run {
         path: Option<PathBuf>,
@@ -755,6 +1205,21 @@ enum Comm {
         limit: Option<f64>,
         #[clap(long, help = "Run the file in a specific mode")]
         mode: Option<RunMode>,
+        #[clap(long, help = "Show the code at each frame of error traces")]
+        full_trace: bool,
+        #[clap(long, help = "Explain why un, anti, and under inversions fail")]
+        explain_inverse: bool,
+        #[clap(
+            long,
+            help = "Run the uiua code blocks of a Markdown file in order in a shared scope"
+        )]
+        markdown: bool,
+        #[clap(
+            long,
+            requires = "markdown",
+            help = "Rewrite the Markdown file with an output block after each code block"
+        )]
+        update: bool,
         #[cfg(feature = "audio")]
         #[clap(flatten)]
         audio_options: AudioOptions,
@@ -765,6 +1230,35 @@ enum Comm {
                     Set UIUA_WINDOW=1 to always use a window."
         )]
         window: bool,
+        #[clap(
+            long = "feature",
+            value_name = "NAME",
+            help = "Enable a feature for `cfg`. Can be passed multiple times"
+        )]
+        features: Vec<String>,
+        #[clap(flatten)]
+        sandbox: SandboxOptions,
+        #[clap(flatten)]
+        replay: ReplayOptions,
+        #[clap(flatten)]
+        headless: HeadlessOptions,
+        #[clap(
+            long,
+            value_name = "PATH",
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = ".env",
+            help = "Load environment variables from a .env file before running. \
+                    Variables that are already set are not changed."
+        )]
+        dotenv: Option<PathBuf>,
+        #[cfg(feature = "ffi")]
+        #[clap(
+            long,
+            value_name = "DIR",
+            help = "Load plugins for &plugin from the shared libraries in this directory"
+        )]
+        plugin_dir: Option<PathBuf>,
         #[clap(trailing_var_arg = true, help = "Arguments to pass to the program")]
         args: Vec<String>,
     },
@@ -773,6 +1267,25 @@ enum Comm {
         path: Option<PathBuf>,
         #[clap(short, long, help = "The path to the output file")]
         output: Option<PathBuf>,
+        #[clap(
+            long,
+            value_enum,
+            default_value_t = BuildEmit::Uasm,
+            help = "What to emit. `node` pretty-prints the compiled node tree with spans"
+        )]
+        emit: BuildEmit,
+        #[clap(
+            long,
+            value_name = "OLD_PATH",
+            help = "Show how the emitted node tree differs from that of another version of the file"
+        )]
+        diff: Option<PathBuf>,
+        #[clap(
+            long = "feature",
+            value_name = "NAME",
+            help = "Enable a feature for `cfg`. Can be passed multiple times"
+        )]
+        features: Vec<String>,
     },
     #[clap(about = "Evaluate an expression and print its output")]
     Eval {
@@ -784,6 +1297,14 @@ enum Comm {
         #[cfg(feature = "audio")]
         #[clap(flatten)]
         audio_options: AudioOptions,
+        #[clap(
+            long = "feature",
+            value_name = "NAME",
+            help = "Enable a feature for `cfg`. Can be passed multiple times"
+        )]
+        features: Vec<String>,
+        #[clap(flatten)]
+        sandbox: SandboxOptions,
         #[clap(trailing_var_arg = true, help = "Arguments to pass to the program")]
         args: Vec<String>,
     },
@@ -792,6 +1313,16 @@ enum Comm {
         path: Option<PathBuf>,
         #[clap(flatten)]
         formatter_options: FormatterOptions,
+        #[clap(long, help = "Show the code at each frame of error traces")]
+        full_trace: bool,
+        #[clap(
+            long = "feature",
+            value_name = "NAME",
+            help = "Enable a feature for `cfg`. Can be passed multiple times"
+        )]
+        features: Vec<String>,
+        #[clap(flatten)]
+        sandbox: SandboxOptions,
         #[clap(trailing_var_arg = true, help = "Arguments to pass to the program")]
         args: Vec<String>,
     },
@@ -805,6 +1336,8 @@ enum Comm {
         formatter_options: FormatterOptions,
         #[clap(long, help = "Clear the terminal on file change")]
         clear: bool,
+        #[clap(long, help = "Show the code at each frame of error traces")]
+        full_trace: bool,
         #[clap(
             short,
             long,
@@ -812,8 +1345,24 @@ enum Comm {
                     Set UIUA_WINDOW=1 to always use a window."
         )]
         window: bool,
+        #[clap(
+            long,
+            conflicts_with = "stdin_file",
+            help = "Keep the program running and reload its bindings on change. \
+                    The program's Frame function is called repeatedly on the stack, \
+                    which is kept between reloads"
+        )]
+        hot: bool,
         #[clap(long, help = "Read stdin from file")]
         stdin_file: Option<PathBuf>,
+        #[clap(
+            long = "feature",
+            value_name = "NAME",
+            help = "Enable a feature for `cfg`. Can be passed multiple times"
+        )]
+        features: Vec<String>,
+        #[clap(flatten)]
+        sandbox: SandboxOptions,
         #[clap(trailing_var_arg = true, help = "Arguments to pass to the program")]
         args: Vec<String>,
     },
@@ -824,16 +1373,71 @@ enum Comm {
         formatter_options: FormatterOptions,
         #[clap(long, help = "Format lines read from stdin")]
         io: bool,
+        #[clap(long, help = "Convert glyphs to their ASCII names")]
+        names: bool,
     },
-    #[clap(about = "Show the documentation for a function, modifier, or constant")]
+    #[clap(
+        about = "Show the documentation for a function, modifier, or constant, \
+                 or generate documentation for a Uiua file"
+    )]
     Doc {
-        #[clap(help = "The name of the function, modifier, or constant")]
-        name: String,
+        #[clap(
+            help = "The name of the function, modifier, or constant, or the path to a Uiua file"
+        )]
+        name: Option<String>,
+        #[clap(long, help = "Generate HTML instead of Markdown")]
+        html: bool,
+        #[clap(short, long, help = "The file to write generated documentation to")]
+        output: Option<PathBuf>,
     },
     #[clap(about = "Check that Uiua files compile")]
     Check {
         #[clap(help = "The path to a file or directory to check")]
         path: Option<PathBuf>,
+        #[clap(long, help = "Also report type and shape mismatches")]
+        types: bool,
+        #[clap(long, help = "Explain why un, anti, and under inversions fail")]
+        explain_inverse: bool,
+    },
+    #[clap(about = "Check Uiua files for likely mistakes and hard-to-read code")]
+    Lint {
+        #[clap(help = "The path to a file or directory to lint")]
+        path: Option<PathBuf>,
+        #[clap(long, value_name = "RULE", help = "Don't report a rule")]
+        allow: Vec<String>,
+        #[clap(long, value_name = "RULE", help = "Report a rule as a warning")]
+        warn: Vec<String>,
+        #[clap(long, value_name = "RULE", help = "Report a rule as an error")]
+        deny: Vec<String>,
+        #[clap(long, help = "The longest chain of dips and gaps to allow")]
+        max_dip_chain: Option<usize>,
+    },
+    #[clap(about = "Explain an error code, or list all error codes")]
+    Explain {
+        #[clap(help = "The error code, like E0001")]
+        code: Option<String>,
+    },
+    #[clap(about = "Run a Markdown notebook and render it with its outputs as HTML")]
+    Notebook {
+        #[clap(help = "The path to the notebook")]
+        path: PathBuf,
+        #[clap(short, long, help = "The file to write the HTML to")]
+        output: Option<PathBuf>,
+        #[clap(long, help = "Run every cell instead of using cached outputs")]
+        no_cache: bool,
+    },
+    #[cfg(feature = "jupyter")]
+    #[clap(about = "Run the Jupyter kernel")]
+    Kernel {
+        #[clap(help = "The connection file written by Jupyter")]
+        connection_file: Option<PathBuf>,
+        #[clap(long, help = "Install the kernel so that Jupyter can find it")]
+        install: bool,
+    },
+    #[clap(about = "Print a file with its macros expanded")]
+    Expand {
+        #[clap(help = "The path to the file to expand")]
+        path: Option<PathBuf>,
     },
     #[clap(about = "Find some Uiua code that matches the given unformatted text")]
     Find {
@@ -854,6 +1458,14 @@ enum Comm {
         audio_options: AudioOptions,
         #[clap(short = 's', long, help = "Don't clear the stack after each line")]
         stack: bool,
+        #[clap(
+            long = "feature",
+            value_name = "NAME",
+            help = "Enable a feature for `cfg`. Can be passed multiple times"
+        )]
+        features: Vec<String>,
+        #[clap(flatten)]
+        sandbox: SandboxOptions,
         #[clap(trailing_var_arg = true)]
         args: Vec<String>,
     },
@@ -871,6 +1483,13 @@ enum Comm {
         #[clap(subcommand)]
         command: Option<ModuleCommand>,
     },
+    #[clap(about = "Fetch the dependencies of a package from its registry. Requires curl")]
+    Fetch {
+        #[clap(long, help = "Update dependencies to the latest matching versions")]
+        update: bool,
+    },
+    #[clap(about = "Publish a package to its registry. Requires curl")]
+    Publish,
     #[cfg(feature = "stand")]
     #[clap(about = "Create a standalone executable")]
     Stand {
@@ -879,16 +1498,32 @@ enum Comm {
         #[clap(short = 'o', long, help = "The name of the output executable")]
         name: Option<String>,
     },
+    #[cfg(feature = "ffi_gen")]
+    #[clap(about = "Generate a module of FFI bindings from a C header")]
+    FfiGen {
+        #[clap(help = "The C header file")]
+        header: PathBuf,
+        #[clap(short, long, help = "The path of the shared library to load")]
+        lib: Option<String>,
+        #[clap(short, long, help = "The path to the output file")]
+        output: Option<PathBuf>,
+    },
     #[cfg(feature = "lsp")]
     #[clap(about = "Run the Language Server")]
     Lsp,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum BuildEmit {
+    Uasm,
+    Node,
+}
+
 #[derive(Subcommand)]
 enum ModuleCommand {
     #[clap(about = "List all modules")]
     List,
-    #[clap(about = "Update a module or all modules")]
+    #[clap(about = "Update a module or all modules and record their commits in uiua.lock")]
     Update {
         #[clap(help = "The module to update")]
         module: Option<PathBuf>,
@@ -900,11 +1535,180 @@ struct FormatterOptions {
     #[clap(
         long = "format-config",
         default_value_t = FormatConfigSource::SearchFile,
-        help = "Select the formatter configuration source (one of search-file, default, or a path to a fmt.ua file)"
+        help = "Select the formatter configuration source (one of search-file, default, or a path to a .fmt.ua or TOML file)"
     )]
     format_config_source: FormatConfigSource,
 }
 
+#[derive(clap::Args, Clone, Default)]
+struct SandboxOptions {
+    #[clap(long, help = "Deny network access")]
+    no_net: bool,
+    #[clap(long, help = "Deny running commands and Python")]
+    no_commands: bool,
+    #[clap(long, help = "Deny FFI and plugins")]
+    no_ffi: bool,
+    #[clap(long, help = "Deny webcam access")]
+    no_webcam: bool,
+    #[clap(long, help = "Deny reading environment variables")]
+    no_env: bool,
+    #[clap(long, help = "Deny clipboard access")]
+    no_clipboard: bool,
+    #[clap(long, help = "Deny creating, modifying, and deleting files")]
+    fs_read_only: bool,
+    #[clap(
+        long = "allow-path",
+        value_name = "DIR",
+        help = "Only allow filesystem access within this directory. Can be passed multiple times"
+    )]
+    allow_paths: Vec<PathBuf>,
+    #[clap(
+        long,
+        help = "Deny network access, commands, FFI, webcams, environment variables, the clipboard, and filesystem modification"
+    )]
+    sandbox: bool,
+}
+
+impl SandboxOptions {
+    fn profile(&self) -> SandboxProfile {
+        let mut profile = if self.sandbox {
+            SandboxProfile::strict()
+        } else {
+            SandboxProfile::default()
+        };
+        profile.no_net |= self.no_net;
+        profile.no_commands |= self.no_commands;
+        profile.no_ffi |= self.no_ffi;
+        profile.no_webcam |= self.no_webcam;
+        profile.no_env |= self.no_env;
+        profile.no_clipboard |= self.no_clipboard;
+        profile.fs_read_only |= self.fs_read_only;
+        profile.allowed_paths.clone_from(&self.allow_paths);
+        profile
+    }
+    fn backend(&self) -> Arc<dyn SysBackend> {
+        let profile = self.profile();
+        if profile.is_unrestricted() {
+            Arc::new(NativeSys)
+        } else {
+            Arc::new(SandboxSys::new(NativeSys, profile))
+        }
+    }
+    /// The arguments to pass these options on to a child process
+    fn to_args(&self) -> Vec<String> {
+        let flags = [
+            (self.no_net, "--no-net"),
+            (self.no_commands, "--no-commands"),
+            (self.no_ffi, "--no-ffi"),
+            (self.no_webcam, "--no-webcam"),
+            (self.no_env, "--no-env"),
+            (self.no_clipboard, "--no-clipboard"),
+            (self.fs_read_only, "--fs-read-only"),
+            (self.sandbox, "--sandbox"),
+        ];
+        let mut args: Vec<String> = (flags.into_iter())
+            .filter(|(set, _)| *set)
+            .map(|(_, flag)| flag.into())
+            .collect();
+        for path in &self.allow_paths {
+            args.push("--allow-path".into());
+            args.push(path.to_string_lossy().into_owned());
+        }
+        args
+    }
+}
+
+#[derive(clap::Args, Clone, Default)]
+struct ReplayOptions {
+    #[clap(
+        long,
+        value_name = "FILE",
+        help = "Record the program's system calls to a file"
+    )]
+    record: Option<PathBuf>,
+    #[clap(
+        long,
+        value_name = "FILE",
+        conflicts_with = "record",
+        help = "Replay the system calls in a file made with --record instead of making them"
+    )]
+    replay: Option<PathBuf>,
+}
+
+impl ReplayOptions {
+    fn backend(&self, inner: Arc<dyn SysBackend>) -> Result<Arc<dyn SysBackend>, String> {
+        Ok(if let Some(path) = &self.record {
+            Arc::new(RecordSys::create(path, inner)?)
+        } else if let Some(path) = &self.replay {
+            Arc::new(ReplaySys::open(path, inner)?)
+        } else {
+            inner
+        })
+    }
+}
+
+#[derive(clap::Args, Clone, Default)]
+struct HeadlessOptions {
+    #[clap(
+        long,
+        value_name = "DIR",
+        help = "Render windows to frames in a directory instead of showing them"
+    )]
+    headless: Option<PathBuf>,
+    #[clap(
+        long,
+        value_name = "COUNT",
+        requires = "headless",
+        help = "Close each window after it renders this many frames"
+    )]
+    frames: Option<usize>,
+    #[clap(
+        long,
+        value_name = "FILE",
+        requires = "headless",
+        help = "Give windows the input events in a JSON file"
+    )]
+    events: Option<PathBuf>,
+}
+
+impl HeadlessOptions {
+    fn backend(&self, inner: Arc<dyn SysBackend>) -> Result<Arc<dyn SysBackend>, String> {
+        if self.headless.is_none() {
+            return Ok(inner);
+        }
+        let mut backend = HeadlessSys::new(inner);
+        if let Some(limit) = self.frames {
+            backend = backend.with_frame_limit(limit);
+        }
+        if let Some(path) = &self.events {
+            let json = fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+            let events = serde_json::from_str(&json)
+                .map_err(|e| format!("Failed to parse {}: {e}", path.display()))?;
+            backend = backend.with_events(events);
+        }
+        Ok(Arc::new(backend))
+    }
+    fn save(&self, rt: &Uiua) {
+        let (Some(dir), Some(backend)) = (&self.headless, rt.downcast_backend::<HeadlessSys>())
+        else {
+            return;
+        };
+        match backend.save(dir) {
+            Ok(paths) => {
+                for name in backend.window_names() {
+                    let count = backend.frames(&name).len();
+                    eprintln!("Rendered {count} frame(s) of {name:?}");
+                }
+                if paths.is_empty() {
+                    eprintln!("No windows were shown");
+                }
+            }
+            Err(e) => eprintln!("Failed to save frames: {e}"),
+        }
+    }
+}
+
 #[cfg(feature = "audio")]
 #[derive(clap::Args)]
 struct AudioOptions {
@@ -1209,64 +2013,7 @@ fn color_mod(margs: usize) -> Color {
 }
 
 fn color_code(code: &str, compiler: &Compiler) -> String {
-    let mut colored = String::new();
-    let spans = Spans::with_compiler(code, compiler);
-
-    let mut prev: Option<CodeSpan> = None;
-    for span in spans.spans {
-        if let Some(prev) = prev {
-            if prev.end.byte_pos < span.span.start.byte_pos {
-                colored
-                    .push_str(&code[prev.end.byte_pos as usize..span.span.start.byte_pos as usize]);
-            }
-        }
-        let color = match span.value {
-            SpanKind::Primitive(prim, sig) => color_prim(prim, sig),
-            SpanKind::Ident {
-                docs: Some(docs), ..
-            } => match docs.kind {
-                BindingDocsKind::Function { sig, .. } => match sig.args {
-                    0 => Some(NOADIC),
-                    1 => Some(MONADIC),
-                    2 => Some(DYADIC),
-                    _ => None,
-                },
-                BindingDocsKind::Modifier(margs) => Some(match margs {
-                    1 => MONADIC_MOD,
-                    _ => DYADIC_MOD,
-                }),
-                _ => None,
-            },
-            SpanKind::String | SpanKind::ImportSrc(_) => Some(Color::Cyan),
-            SpanKind::Number | SpanKind::Subscript(None, _) => Some(Color::TrueColor {
-                r: 235,
-                g: 136,
-                b: 68,
-            }),
-            SpanKind::Subscript(Some(prim), n) => color_prim(prim, n),
-            SpanKind::Comment | SpanKind::OutputComment | SpanKind::Strand => {
-                Some(Color::BrightBlack)
-            }
-            SpanKind::MacroDelim(margs) => Some(color_mod(margs)),
-            SpanKind::Ident { .. }
-            | SpanKind::Label
-            | SpanKind::Signature
-            | SpanKind::Whitespace
-            | SpanKind::Placeholder(_)
-            | SpanKind::Delimiter
-            | SpanKind::FuncDelim(..)
-            | SpanKind::Obverse(_) => None,
-        };
-        span.span.as_str(&spans.inputs, |s| {
-            colored.push_str(&if let Some(color) = color {
-                s.color(color).to_string()
-            } else {
-                s.to_string()
-            });
-        });
-        prev = Some(span.span);
-    }
-    colored
+    highlight::to_ansi(&highlight::highlight_with_compiler(code, compiler))
 }
 
 fn list_modules() -> io::Result<Option<Vec<PathBuf>>> {
@@ -1296,19 +2043,26 @@ fn list_modules() -> io::Result<Option<Vec<PathBuf>>> {
 }
 
 fn update_modules(modules: &[PathBuf]) -> io::Result<()> {
-    let canonical: Vec<PathBuf> = modules
-        .iter()
-        .map(|p| Path::new("uiua-modules").join(p).canonicalize())
-        .collect::<io::Result<_>>()?;
-    for (path, canonical) in modules.iter().zip(canonical) {
-        env::set_current_dir(&canonical)?;
+    let mut lockfile = Lockfile::load()?;
+    for path in modules {
+        let dir = Path::new("uiua-modules").join(path);
+        if !dir.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Module {} does not exist", path.display()),
+            ));
+        }
         println!("{} {}", "Updating".bold().bright_green(), path.display());
-        Command::new("git").args(["pull"]).spawn()?.wait()?;
+        match update_git_module(&mut lockfile, &dir) {
+            Ok(Some(commit)) => println!("Locked to {commit}"),
+            Ok(None) => println!("Pinned to a tag or commit, skipping"),
+            Err(e) => eprintln!("{e}"),
+        }
     }
-    Ok(())
+    lockfile.save()
 }
 
-fn check(path: Option<PathBuf>) -> UiuaResult {
+fn check(path: Option<PathBuf>, types: bool, explain_inverse: bool) -> UiuaResult {
     let paths = uiua_files(path.as_deref())?;
     let path_count = paths.len();
     let mut successes = 0;
@@ -1327,11 +2081,17 @@ fn check(path: Option<PathBuf>) -> UiuaResult {
         );
         stdout().flush().unwrap();
         let mut comp = Compiler::with_backend(NativeSys);
+        comp.type_check(types).explain_inverse(explain_inverse);
         if let Err(e) = comp.load_file(path) {
             println!("\n{}", e.report());
         } else {
             successes += 1;
         }
+        if types {
+            for diagnostic in comp.take_diagnostics() {
+                println!("\n{}", diagnostic.report());
+            }
+        }
     }
     let message = format!(
         "{successes}/{path_count} file{} compiled successfully",
@@ -1356,6 +2116,80 @@ fn check(path: Option<PathBuf>) -> UiuaResult {
     Ok(())
 }
 
+/// Lint files and exit with an error if any lint is denied
+fn lint(path: Option<PathBuf>, config: &LintConfig) -> UiuaResult {
+    let mut denied = false;
+    for path in uiua_files(path.as_deref())? {
+        let input = fs::read_to_string(&path).map_err(|e| UiuaError::load(path.clone(), e))?;
+        let mut inputs = Inputs::default();
+        for lint in uiua::lint::lint(&input, &path, &mut inputs, config) {
+            denied |= lint.level == LintLevel::Deny;
+            eprintln!("{}", lint.report(&inputs));
+        }
+    }
+    if denied {
+        exit(1);
+    }
+    Ok(())
+}
+
+/// Print a file with each macro invocation replaced by its expansion
+///
+/// Macros used in expansions are not expanded further.
+fn expand(path: &Path) -> UiuaResult {
+    let code = fs::read_to_string(path).map_err(|e| UiuaErrorKind::Load(path.into(), e.into()))?;
+    let mut comp = Compiler::with_backend(NativeSys);
+    let res = comp.mode(RunMode::All).load_file(path).map(drop);
+    let expansions = &comp.code_meta().macro_expansions;
+    let mut spans: Vec<&CodeSpan> = (expansions.keys())
+        .filter(|span| span.src == *path)
+        .collect();
+    spans.sort_by_key(|span| (span.start.byte_pos, Reverse(span.end.byte_pos)));
+    let mut expanded = String::new();
+    let mut pos = 0;
+    for span in spans {
+        let start = span.start.byte_pos as usize;
+        if start < pos {
+            continue;
+        }
+        expanded.push_str(&code[pos..start]);
+        expanded.push_str(&expansions[span].1);
+        pos = span.end.byte_pos as usize;
+    }
+    expanded.push_str(&code[pos..]);
+    print!("{expanded}");
+    res
+}
+
+fn explain(code: Option<String>) {
+    let Some(code) = code else {
+        for ex in EXPLANATIONS {
+            println!("{}  {}", ex.code.bold(), ex.title);
+        }
+        return;
+    };
+    let Some(ex) = explanation(&code) else {
+        eprintln!("Unknown error code `{code}`. Run `uiua explain` to list all error codes.");
+        exit(1);
+    };
+    println!("{}: {}\n", ex.code.bold(), ex.title.bold());
+    println!("{}\n", ex.text);
+    println!("{}", "Example:".bold());
+    for line in ex.example.lines() {
+        println!("    {line}");
+    }
+    println!();
+    let mut rt = Uiua::with_safe_sys().with_execution_limit(Duration::from_secs(1));
+    match rt.run_str(ex.example) {
+        Ok(mut comp) => {
+            for diagnostic in comp.take_diagnostics() {
+                println!("{}", diagnostic.report());
+            }
+        }
+        Err(e) => println!("{}", e.report()),
+    }
+}
+
 fn find(path: Option<PathBuf>, mut text: String, raw: bool) -> UiuaResult {
     if raw {
         colored::control::set_override(false);
@@ -1406,6 +2240,185 @@ fn find(path: Option<PathBuf>, mut text: String, raw: bool) -> UiuaResult {
     Ok(())
 }
 
+/// Check that the doc comment examples in a file have their expected outputs
+///
+/// Returns whether they all do
+fn check_doc_examples(path: &Path, comp: Compiler) -> bool {
+    let source = fs::read_to_string(path).unwrap_or_default();
+    // Avoid running examples when there is nothing to check
+    if !source.contains("out:") {
+        return true;
+    }
+    let name = (path.file_stem())
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let docs = ModuleDocs::from_compiler(name, source, comp);
+    let total = docs.checked_example_count();
+    if total == 0 {
+        return true;
+    }
+    let mismatched = docs.mismatched_examples();
+    for (name, ex) in &mismatched {
+        eprintln!(
+            "{}\n{}\n{}",
+            format!("Example for {name} does not match its expected output:").bright_red(),
+            ex.input,
+            ex.diff().unwrap_or_default().trim_end()
+        );
+    }
+    let succeeded = total - mismatched.len();
+    if mismatched.is_empty() {
+        println!(
+            "{}",
+            format!(
+                "{total} doc example{} passed",
+                if total == 1 { "" } else { "s" }
+            )
+            .green()
+        );
+    } else {
+        println!(
+            "{}",
+            format!("{succeeded} of {total} doc examples passed").bright_red()
+        );
+    }
+    mismatched.is_empty()
+}
+
+/// Generate documentation for a file from its doc comments
+fn gen_docs(path: &Path, html: bool, output: Option<PathBuf>) -> UiuaResult {
+    let docs = ModuleDocs::from_file(path, NativeSys)?;
+    for (name, ex) in docs.unexpected_examples() {
+        match &ex.output {
+            Ok(_) => eprintln!("Example for {name} should have failed: {}", ex.input),
+            Err(e) => eprintln!("Example for {name} failed: {e}"),
+        }
+    }
+    for (name, ex) in docs.mismatched_examples() {
+        eprintln!(
+            "Example for {name} does not match its expected output: {}",
+            ex.input
+        );
+    }
+    let format = if html {
+        DocFormat::Html
+    } else {
+        DocFormat::Markdown
+    };
+    let text = docs.render(format);
+    if let Some(output) = output {
+        if let Err(e) = fs::write(output, text) {
+            eprintln!("Failed to write documentation: {e}");
+            exit(1);
+        }
+    } else {
+        print!("{text}");
+    }
+    Ok(())
+}
+
+/// Set environment variables from a dotenv file
+///
+/// Variables that are already set are not overridden.
+fn load_dotenv(path: &Path) {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => {
+            eprintln!("Failed to read {}: {e}", path.display());
+            exit(1);
+        }
+    };
+    let vars = match uiua::encode::parse_dotenv(&text) {
+        Ok(vars) => vars,
+        Err(e) => {
+            eprintln!("Failed to load {}: {e}", path.display());
+            exit(1);
+        }
+    };
+    for (name, value) in vars {
+        if env::var_os(&name).is_none() {
+            env::set_var(name, value);
+        }
+    }
+}
+
+/// Run the code blocks of a Markdown file, optionally rewriting it with their outputs
+fn run_markdown(path: &Path, update: bool, sandbox: SandboxOptions) {
+    let text = fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("Failed to read {}: {e}", path.display());
+        exit(1)
+    });
+    let mut notebook = Notebook::parse(&text);
+    let mut session = Session::new(sandbox.backend()).with_file_path(path);
+    let mut failed = false;
+    for cell in &mut notebook.cells {
+        let Cell::Code(cell) = cell else {
+            continue;
+        };
+        cell.outputs = session.run_cell(&cell.code);
+        for output in &cell.outputs {
+            if let CellOutput::Error { message } = output {
+                eprintln!("{message}");
+                failed = true;
+            } else {
+                print!("{}", output.text());
+            }
+        }
+    }
+    if update {
+        let updated = notebook.markdown();
+        if updated != text {
+            if let Err(e) = fs::write(path, updated) {
+                eprintln!("Failed to write {}: {e}", path.display());
+                exit(1);
+            }
+        }
+    }
+    if failed {
+        exit(1);
+    }
+}
+
+/// Run a notebook and write it as HTML
+fn notebook(path: &Path, output: Option<PathBuf>, no_cache: bool) {
+    let text = fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("Failed to read {}: {e}", path.display());
+        exit(1)
+    });
+    let mut notebook = Notebook::parse(&text);
+    let cache_path = NotebookCache::path_for(path);
+    let mut cache = if no_cache {
+        NotebookCache::default()
+    } else {
+        NotebookCache::load(&cache_path)
+    };
+    let mut session = Session::new(NativeSys).with_file_path(path);
+    notebook.run(&mut session, &mut cache);
+    if let Err(e) = cache.save(&cache_path) {
+        eprintln!("{e}");
+    }
+    let cells: Vec<_> = notebook.code_cells().collect();
+    let cached = cells.iter().filter(|cell| cell.cached).count();
+    let failed = (cells.iter())
+        .filter(|cell| (cell.outputs.iter()).any(|o| matches!(o, CellOutput::Error { .. })))
+        .count();
+    let title = path.file_stem().unwrap_or_default().to_string_lossy();
+    let output = output.unwrap_or_else(|| path.with_extension("html"));
+    if let Err(e) = fs::write(&output, notebook.html(&title)) {
+        eprintln!("Failed to write {}: {e}", output.display());
+        exit(1);
+    }
+    println!(
+        "Ran {} cells ({cached} cached) and wrote {}",
+        cells.len(),
+        output.display()
+    );
+    if failed > 0 {
+        eprintln!("{}", format!("{failed} cells failed").bright_red());
+        exit(1);
+    }
+}
+
 fn doc(name: &str) {
     fn print_doc_frag(frag: &PrimDocFragment) {
         match frag {
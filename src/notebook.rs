@@ -0,0 +1,683 @@
+//! Run Markdown notebooks whose code cells are Uiua
+//!
+//! A notebook is a Markdown file. Fenced code blocks marked `uiua` are code cells,
+//! and everything else is Markdown. Code cells run in order and share a scope.
+//!
+//! A fenced block marked `output` right after a code cell holds the cell's output as text.
+//! It is replaced when the notebook is written back as Markdown.
+
+use std::{
+    any::Any,
+    collections::{hash_map::DefaultHasher, HashMap},
+    fmt::Write,
+    fs,
+    hash::{Hash, Hasher},
+    mem::take,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+#[cfg(feature = "image")]
+use image::DynamicImage;
+use parking_lot::Mutex;
+use serde::*;
+
+use crate::{
+    docgen::{escape_html, inline_code_html},
+    encode::SmartOutput,
+    Compiler, Handle, IntoSysBackend, PreEvalMode, ReadLinesReturnFn, SysBackend, Uiua, Value,
+    VERSION,
+};
+
+/// A parsed notebook
+#[derive(Debug, Clone, Default)]
+pub struct Notebook {
+    /// The notebook's cells, in order
+    pub cells: Vec<Cell>,
+}
+
+/// A cell in a notebook
+#[derive(Debug, Clone)]
+pub enum Cell {
+    /// Markdown text
+    Markdown(String),
+    /// Uiua code
+    Code(CodeCell),
+}
+
+/// A cell of Uiua code
+#[derive(Debug, Clone, Default)]
+pub struct CodeCell {
+    /// The cell's code
+    pub code: String,
+    /// The backticks that open and close the cell
+    pub fence: String,
+    /// The cell's outputs from the last time it was run
+    pub outputs: Vec<CellOutput>,
+    /// Whether the outputs came from the cache
+    pub cached: bool,
+}
+
+/// An output of a code cell
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CellOutput {
+    /// Text that was printed
+    Text {
+        /// The text
+        text: String,
+    },
+    /// A value left on the stack
+    Value {
+        /// The formatted value
+        text: String,
+    },
+    /// A PNG image
+    Image {
+        /// The encoded image
+        #[serde(with = "base64_bytes")]
+        png: Vec<u8>,
+        /// The image's label
+        label: Option<String>,
+    },
+    /// A GIF
+    Gif {
+        /// The encoded GIF
+        #[serde(with = "base64_bytes")]
+        gif: Vec<u8>,
+        /// The GIF's label
+        label: Option<String>,
+    },
+    /// WAV audio
+    Audio {
+        /// The encoded audio
+        #[serde(with = "base64_bytes")]
+        wav: Vec<u8>,
+        /// The audio's label
+        label: Option<String>,
+    },
+    /// An error
+    Error {
+        /// The error message
+        message: String,
+    },
+}
+
+mod base64_bytes {
+    use super::*;
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        STANDARD.encode(bytes).serialize(serializer)
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        STANDARD.decode(s).map_err(de::Error::custom)
+    }
+}
+
+impl CellOutput {
+    /// Get the output for a value, showing it as media if it looks like media
+    pub fn from_value(value: Value, backend: &dyn SysBackend) -> Self {
+        match SmartOutput::from_value(value, backend) {
+            SmartOutput::Normal(value) => CellOutput::Value { text: value.show() },
+            SmartOutput::Png(png, label) => CellOutput::Image { png, label },
+            SmartOutput::Gif(gif, label) => CellOutput::Gif { gif, label },
+            SmartOutput::Wav(wav, label) => CellOutput::Audio { wav, label },
+        }
+    }
+    /// Get the output as plain text
+    ///
+    /// Media is shown as a placeholder with its label.
+    pub fn text(&self) -> String {
+        let media = |kind: &str, label: &Option<String>| match label {
+            Some(label) => format!("<{kind}: {label}>\n"),
+            None => format!("<{kind}>\n"),
+        };
+        match self {
+            CellOutput::Text { text } => text.clone(),
+            CellOutput::Value { text } => format!("{text}\n"),
+            CellOutput::Image { label, .. } => media("image", label),
+            CellOutput::Gif { label, .. } => media("gif", label),
+            CellOutput::Audio { label, .. } => media("audio", label),
+            CellOutput::Error { message } => format!("Error: {message}\n"),
+        }
+    }
+    /// Render the output as HTML
+    pub fn html(&self) -> String {
+        let label = |label: &Option<String>| {
+            (label.as_ref())
+                .map(|label| format!("<div class=\"label\">{}</div>", escape_html(label)))
+                .unwrap_or_default()
+        };
+        match self {
+            CellOutput::Text { text } | CellOutput::Value { text } => {
+                format!("<pre class=\"output\">{}</pre>", escape_html(text))
+            }
+            CellOutput::Image { png, label: l } => format!(
+                "{}<img src=\"data:image/png;base64,{}\">",
+                label(l),
+                STANDARD.encode(png)
+            ),
+            CellOutput::Gif { gif, label: l } => format!(
+                "{}<img src=\"data:image/gif;base64,{}\">",
+                label(l),
+                STANDARD.encode(gif)
+            ),
+            CellOutput::Audio { wav, label: l } => format!(
+                "{}<audio controls src=\"data:audio/wav;base64,{}\"></audio>",
+                label(l),
+                STANDARD.encode(wav)
+            ),
+            CellOutput::Error { message } => {
+                format!("<pre class=\"output error\">{}</pre>", escape_html(message))
+            }
+        }
+    }
+}
+
+impl Notebook {
+    /// Parse a notebook from Markdown
+    pub fn parse(text: &str) -> Self {
+        let mut cells = Vec::new();
+        let mut markdown = String::new();
+        let mut lines = text.lines();
+        let mut other_fence: Option<&str> = None;
+        while let Some(line) = lines.next() {
+            let trimmed = line.trim_start();
+            if let Some(fence) = other_fence {
+                if trimmed.trim_end() == fence {
+                    other_fence = None;
+                }
+            } else if let Some(info) = trimmed.strip_prefix("```") {
+                let fence_len = 3 + info.chars().take_while(|&c| c == '`').count();
+                let fence = &trimmed[..fence_len];
+                if info[fence_len - 3..].trim() == "uiua" {
+                    if !markdown.is_empty() {
+                        cells.push(Cell::Markdown(take(&mut markdown)));
+                    }
+                    let mut code = String::new();
+                    for line in lines.by_ref() {
+                        if line.trim() == fence {
+                            break;
+                        }
+                        code.push_str(line);
+                        code.push('\n');
+                    }
+                    // Skip the cell's previous output
+                    if lines.clone().next().map(str::trim) == Some("```output") {
+                        lines.next();
+                        lines.by_ref().find(|line| line.trim() == "```");
+                    }
+                    cells.push(Cell::Code(CodeCell {
+                        code,
+                        fence: fence.into(),
+                        ..CodeCell::default()
+                    }));
+                    continue;
+                }
+                other_fence = Some(fence);
+            }
+            markdown.push_str(line);
+            markdown.push('\n');
+        }
+        if !markdown.is_empty() {
+            cells.push(Cell::Markdown(markdown));
+        }
+        Notebook { cells }
+    }
+    /// Write the notebook back as Markdown, with an output block after each code cell that has output
+    pub fn markdown(&self) -> String {
+        let mut s = String::new();
+        for cell in &self.cells {
+            match cell {
+                Cell::Markdown(text) => s.push_str(text),
+                Cell::Code(cell) => {
+                    _ = write!(s, "{}uiua\n{}{}\n", cell.fence, cell.code, cell.fence);
+                    let output: String = cell.outputs.iter().map(CellOutput::text).collect();
+                    if !output.is_empty() {
+                        _ = write!(s, "```output\n{output}");
+                        if !output.ends_with('\n') {
+                            s.push('\n');
+                        }
+                        s.push_str("```\n");
+                    }
+                }
+            }
+        }
+        s
+    }
+    /// Iterate over the code cells
+    pub fn code_cells(&self) -> impl Iterator<Item = &CodeCell> {
+        self.cells.iter().filter_map(|cell| match cell {
+            Cell::Code(cell) => Some(cell),
+            Cell::Markdown(_) => None,
+        })
+    }
+    /// Run the notebook's code cells in order
+    ///
+    /// A cell whose outputs are in the cache is only run again if a later cell
+    /// that is not cached needs its bindings.
+    /// The cache is updated to hold exactly the outputs of this run.
+    pub fn run(&mut self, session: &mut Session, cache: &mut NotebookCache) {
+        let mut hasher = DefaultHasher::new();
+        VERSION.hash(&mut hasher);
+        let mut pending = Vec::new();
+        let mut entries = HashMap::new();
+        for cell in &mut self.cells {
+            let Cell::Code(cell) = cell else {
+                continue;
+            };
+            cell.code.hash(&mut hasher);
+            let key = format!("{:016x}", hasher.finish());
+            if let Some(outputs) = cache.entries.get(&key) {
+                cell.outputs.clone_from(outputs);
+                cell.cached = true;
+                pending.push(cell.code.clone());
+            } else {
+                for code in pending.drain(..) {
+                    session.run_cell(&code);
+                }
+                cell.outputs = session.run_cell(&cell.code);
+                cell.cached = false;
+            }
+            entries.insert(key, cell.outputs.clone());
+        }
+        cache.entries = entries;
+    }
+    /// Render the notebook as a standalone HTML page
+    pub fn html(&self, title: &str) -> String {
+        let mut s = String::new();
+        _ = writeln!(
+            s,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>",
+            escape_html(title)
+        );
+        s.push_str(HTML_STYLE);
+        s.push_str("</head>\n<body>\n");
+        for cell in &self.cells {
+            match cell {
+                Cell::Markdown(text) => s.push_str(&markdown_html(text)),
+                Cell::Code(cell) => {
+                    _ = writeln!(
+                        s,
+                        "<div class=\"cell\">\n<pre class=\"code\"><code>{}</code></pre>",
+                        escape_html(cell.code.trim_end())
+                    );
+                    for output in &cell.outputs {
+                        s.push_str(&output.html());
+                        s.push('\n');
+                    }
+                    s.push_str("</div>\n");
+                }
+            }
+        }
+        s.push_str("</body>\n</html>\n");
+        s
+    }
+}
+
+/// Cached outputs of a notebook's code cells
+///
+/// Outputs are keyed by a hash of the cell's code and the code of every cell before it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NotebookCache {
+    entries: HashMap<String, Vec<CellOutput>>,
+}
+
+impl NotebookCache {
+    /// The path of the cache file for a notebook
+    pub fn path_for(notebook: &Path) -> PathBuf {
+        let name = notebook.file_name().unwrap_or_default().to_string_lossy();
+        notebook.with_file_name(format!(".{name}.cache.json"))
+    }
+    /// Load a cache from a file
+    ///
+    /// A missing or invalid file results in an empty cache.
+    pub fn load(path: &Path) -> Self {
+        (fs::read_to_string(path).ok())
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+    /// Save the cache to a file
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        fs::write(path, json).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+    }
+}
+
+/// A scope in which code cells are run one after another
+pub struct Session {
+    env: Uiua,
+    comp: Compiler,
+}
+
+impl Session {
+    /// Create a new session whose IO goes through a backend
+    ///
+    /// Output is captured rather than sent to the backend.
+    pub fn new(backend: impl IntoSysBackend) -> Self {
+        let backend: Arc<dyn SysBackend> = Arc::new(NotebookSys::new(backend.into_sys_backend()));
+        let env = Uiua::with_backend(backend.clone());
+        let mut comp = Compiler::with_backend(backend);
+        comp.pre_eval_mode(PreEvalMode::Line);
+        Session { env, comp }
+    }
+    /// Set the path that relative imports are resolved against
+    pub fn with_file_path(self, path: impl Into<PathBuf>) -> Self {
+        Session {
+            env: self.env.with_file_path(path),
+            ..self
+        }
+    }
+    /// Run a cell and get its outputs
+    ///
+    /// If the cell fails, its bindings are discarded.
+    pub fn run_cell(&mut self, code: &str) -> Vec<CellOutput> {
+        let backup = self.comp.clone();
+        let res = (self.comp.load_str(code).map(drop))
+            .and_then(|()| self.env.run_compiler(&mut self.comp));
+        let sys = self.env.downcast_backend::<NotebookSys>();
+        let mut outputs = sys.map(NotebookSys::take_outputs).unwrap_or_default();
+        let stack = self.env.take_stack();
+        match res {
+            Ok(()) => {
+                for value in stack {
+                    outputs.push(CellOutput::from_value(value, self.env.backend()));
+                }
+            }
+            Err(e) => {
+                self.comp = backup;
+                outputs.push(CellOutput::Error {
+                    message: e.to_string(),
+                });
+            }
+        }
+        self.comp.assembly_mut().root.clear();
+        outputs
+    }
+}
+
+/// A system backend that captures output and defers everything else to another backend
+struct NotebookSys {
+    inner: Arc<dyn SysBackend>,
+    outputs: Mutex<Vec<CellOutput>>,
+}
+
+impl NotebookSys {
+    fn new(inner: Arc<dyn SysBackend>) -> Self {
+        NotebookSys {
+            inner,
+            outputs: Mutex::new(Vec::new()),
+        }
+    }
+    fn take_outputs(&self) -> Vec<CellOutput> {
+        take(&mut *self.outputs.lock())
+    }
+    fn push_text(&self, s: &str) {
+        let mut outputs = self.outputs.lock();
+        if let Some(CellOutput::Text { text }) = outputs.last_mut() {
+            text.push_str(s);
+        } else {
+            outputs.push(CellOutput::Text { text: s.into() });
+        }
+    }
+}
+
+impl SysBackend for NotebookSys {
+    fn any(&self) -> &dyn Any {
+        self
+    }
+    fn any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn print_str_stdout(&self, s: &str) -> Result<(), String> {
+        self.push_text(s);
+        Ok(())
+    }
+    fn print_str_stderr(&self, s: &str) -> Result<(), String> {
+        self.push_text(s);
+        Ok(())
+    }
+    fn print_str_trace(&self, s: &str) {
+        self.push_text(s);
+    }
+    fn show(&self, value: Value) -> Result<(), String> {
+        let output = CellOutput::from_value(value, self);
+        self.outputs.lock().push(output);
+        Ok(())
+    }
+    #[cfg(feature = "image")]
+    fn show_image(&self, image: DynamicImage, label: Option<&str>) -> Result<(), String> {
+        let png = crate::encode::image_to_bytes(&image, image::ImageOutputFormat::Png)?;
+        let label = label.map(Into::into);
+        self.outputs.lock().push(CellOutput::Image { png, label });
+        Ok(())
+    }
+    fn show_gif(&self, gif: Vec<u8>, label: Option<&str>) -> Result<(), String> {
+        let label = label.map(Into::into);
+        self.outputs.lock().push(CellOutput::Gif { gif, label });
+        Ok(())
+    }
+    fn play_audio(&self, wav: Vec<u8>, label: Option<&str>) -> Result<(), String> {
+        let label = label.map(Into::into);
+        self.outputs.lock().push(CellOutput::Audio { wav, label });
+        Ok(())
+    }
+    fn audio_sample_rate(&self) -> u32 {
+        self.inner.audio_sample_rate()
+    }
+    fn var(&self, name: &str) -> Option<String> {
+        self.inner.var(name)
+    }
+    fn file_exists(&self, path: &str) -> bool {
+        self.inner.file_exists(path)
+    }
+    fn list_dir(&self, path: &str) -> Result<Vec<String>, String> {
+        self.inner.list_dir(path)
+    }
+    fn is_file(&self, path: &str) -> Result<bool, String> {
+        self.inner.is_file(path)
+    }
+    fn delete(&self, path: &str) -> Result<(), String> {
+        self.inner.delete(path)
+    }
+    fn trash(&self, path: &str) -> Result<(), String> {
+        self.inner.trash(path)
+    }
+    fn read(&self, handle: Handle, count: usize) -> Result<Vec<u8>, String> {
+        self.inner.read(handle, count)
+    }
+    fn read_all(&self, handle: Handle) -> Result<Vec<u8>, String> {
+        self.inner.read_all(handle)
+    }
+    fn read_until(&self, handle: Handle, delim: &[u8]) -> Result<Vec<u8>, String> {
+        self.inner.read_until(handle, delim)
+    }
+    fn read_lines<'a>(&self, handle: Handle) -> Result<ReadLinesReturnFn<'a>, String> {
+        self.inner.read_lines(handle)
+    }
+    fn write(&self, handle: Handle, contents: &[u8]) -> Result<(), String> {
+        self.inner.write(handle, contents)
+    }
+    fn create_file(&self, path: &Path) -> Result<Handle, String> {
+        self.inner.create_file(path)
+    }
+    fn open_file(&self, path: &Path, write: bool) -> Result<Handle, String> {
+        self.inner.open_file(path, write)
+    }
+    fn make_dir(&self, path: &Path) -> Result<(), String> {
+        self.inner.make_dir(path)
+    }
+    fn file_read_all(&self, path: &Path) -> Result<Vec<u8>, String> {
+        self.inner.file_read_all(path)
+    }
+    fn file_write_all(&self, path: &Path, contents: &[u8]) -> Result<(), String> {
+        self.inner.file_write_all(path, contents)
+    }
+    fn file_append(&self, path: &Path, contents: &[u8]) -> Result<(), String> {
+        self.inner.file_append(path, contents)
+    }
+    fn close(&self, handle: Handle) -> Result<(), String> {
+        self.inner.close(handle)
+    }
+    fn sleep(&self, seconds: f64) -> Result<(), String> {
+        self.inner.sleep(seconds)
+    }
+    fn now(&self) -> f64 {
+        self.inner.now()
+    }
+    fn allow_thread_spawning(&self) -> bool {
+        self.inner.allow_thread_spawning()
+    }
+    fn load_git_module(&self, url: &str, target: crate::GitTarget) -> Result<PathBuf, String> {
+        self.inner.load_git_module(url, target)
+    }
+}
+
+/// Render a small subset of Markdown as HTML
+///
+/// Supported are headings, lists, fenced code blocks, paragraphs, and inline code.
+fn markdown_html(text: &str) -> String {
+    let mut s = String::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut list: Option<&str> = None;
+    let mut fence: Option<(&str, String)> = None;
+    let flush = |s: &mut String, paragraph: &mut Vec<&str>, list: &mut Option<&str>| {
+        if !paragraph.is_empty() {
+            _ = writeln!(s, "<p>{}</p>", inline_code_html(&paragraph.join(" ")));
+            paragraph.clear();
+        }
+        if let Some(tag) = list.take() {
+            _ = writeln!(s, "</{tag}>");
+        }
+    };
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if let Some((marker, code)) = &mut fence {
+            if trimmed == *marker {
+                _ = writeln!(s, "<pre><code>{}</code></pre>", escape_html(code));
+                fence = None;
+            } else {
+                code.push_str(line);
+                code.push('\n');
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") {
+            flush(&mut s, &mut paragraph, &mut list);
+            let len = trimmed.chars().take_while(|&c| c == '`').count();
+            fence = Some((&trimmed[..len], String::new()));
+            continue;
+        }
+        if trimmed.is_empty() {
+            flush(&mut s, &mut paragraph, &mut list);
+            continue;
+        }
+        let level = trimmed.chars().take_while(|&c| c == '#').count();
+        if (1..=6).contains(&level) && trimmed[level..].starts_with(' ') {
+            flush(&mut s, &mut paragraph, &mut list);
+            let heading = inline_code_html(trimmed[level..].trim());
+            _ = writeln!(s, "<h{level}>{heading}</h{level}>");
+            continue;
+        }
+        let item = (trimmed
+            .strip_prefix("- ")
+            .or_else(|| trimmed.strip_prefix("* ")))
+        .map(|item| ("ul", item))
+        .or_else(|| {
+            let digits = trimmed.chars().take_while(char::is_ascii_digit).count();
+            (digits > 0)
+                .then(|| trimmed[digits..].strip_prefix(". "))
+                .flatten()
+                .map(|item| ("ol", item))
+        });
+        if let Some((tag, item)) = item {
+            if !paragraph.is_empty() || list.is_some_and(|t| t != tag) {
+                flush(&mut s, &mut paragraph, &mut list);
+            }
+            if list.is_none() {
+                _ = writeln!(s, "<{tag}>");
+                list = Some(tag);
+            }
+            _ = writeln!(s, "<li>{}</li>", inline_code_html(item));
+            continue;
+        }
+        if list.is_some() {
+            flush(&mut s, &mut paragraph, &mut list);
+        }
+        paragraph.push(trimmed);
+    }
+    if let Some((_, code)) = fence {
+        _ = writeln!(s, "<pre><code>{}</code></pre>", escape_html(&code));
+    }
+    flush(&mut s, &mut paragraph, &mut list);
+    s
+}
+
+const HTML_STYLE: &str = "<style>
+body { max-width: 50em; margin: auto; padding: 1em; font-family: sans-serif; }
+code, pre { font-family: \"Uiua386\", \"DejaVu Sans Mono\", monospace; }
+.cell { margin: 1em 0; }
+.code { background: #8882; padding: 0.5em; border-radius: 0.3em; }
+.output { margin: 0.3em 0 0 1em; }
+.error { color: #c33; }
+.label { font-style: italic; margin-left: 1em; }
+img, audio { display: block; margin: 0.3em 0 0 1em; max-width: 100%; }
+</style>
+";
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn notebook_cells() {
+        use super::*;
+        use crate::SafeSys;
+        let text = "\
+# Title
+
+```uiua
+X ← 5
+&p \"hi\"
+```
+
+```py
+not uiua
+```
+
+```uiua
++1 X
+```
+";
+        let mut notebook = Notebook::parse(text);
+        assert_eq!(notebook.code_cells().count(), 2);
+        let mut cache = NotebookCache::default();
+        notebook.run(&mut Session::new(SafeSys::default()), &mut cache);
+        let outputs: Vec<_> = notebook.code_cells().map(|c| c.outputs.clone()).collect();
+        assert_eq!(
+            outputs,
+            [
+                vec![CellOutput::Text {
+                    text: "hi\n".into()
+                }],
+                vec![CellOutput::Value { text: "6".into() }]
+            ]
+        );
+        let html = notebook.html("Test");
+        assert!(html.contains("<h1>Title</h1>"));
+        assert!(html.contains("not uiua"));
+        let updated = notebook.markdown();
+        assert!(updated.contains("+1 X\n```\n```output\n6\n```\n"));
+        assert_eq!(Notebook::parse(&updated).markdown(), text);
+
+        // Cached cells are not run again
+        let mut notebook = Notebook::parse(text);
+        notebook.run(&mut Session::new(SafeSys::default()), &mut cache);
+        assert!(notebook.code_cells().all(|c| c.cached));
+        let text = text.replace("+1 X", "+2 X");
+        let mut notebook = Notebook::parse(&text);
+        notebook.run(&mut Session::new(SafeSys::default()), &mut cache);
+        let cells: Vec<_> = notebook.code_cells().collect();
+        assert!(cells[0].cached && !cells[1].cached);
+        assert_eq!(cells[1].outputs, [CellOutput::Value { text: "7".into() }]);
+    }
+}
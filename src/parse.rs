@@ -351,6 +351,7 @@ impl Parser<'_> {
         let open_span = self.module_open()?;
         self.spaces();
         // Name
+        let private_span = self.private_tilde();
         let name = self.ident();
         if in_scope && name.is_none() {
             self.index = backup;
@@ -393,6 +394,7 @@ impl Parser<'_> {
         };
         let module = ScopedModule {
             open_span,
+            private_span,
             kind,
             items,
             imports,
@@ -588,6 +590,7 @@ impl Parser<'_> {
             self.exact(Bar.into())
         })?;
         self.spaces();
+        let private_span = self.private_tilde();
         let name = self.ident();
         self.spaces();
         let mut boxed = false;
@@ -696,11 +699,25 @@ impl Parser<'_> {
         Some(DataDef {
             init_span,
             variant,
+            private_span,
             name,
             fields,
             func,
         })
     }
+    /// A ~ immediately before a module or data definition name makes it private
+    fn private_tilde(&mut self) -> Option<CodeSpan> {
+        let reset = self.index;
+        let span = self.exact(Tilde.into())?;
+        if (self.tokens.get(self.index))
+            .and_then(|token| token.value.as_ident())
+            .is_none()
+        {
+            self.index = reset;
+            return None;
+        }
+        Some(span)
+    }
     fn validate_binding_name(&mut self, name: &Sp<Ident>) {
         if name.value.contains('&') {
             self.errors
@@ -0,0 +1,170 @@
+//! The plugin interface
+//!
+//! A plugin is a shared library which exports the following C functions:
+//! - `uint32_t uiua_plugin_abi_version(void)`, which must return [`PLUGIN_ABI_VERSION`]
+//! - `const UiuaPluginFunction* uiua_plugin_functions(void)`, which returns an array of [`PluginFunction`]s terminated by one with a null name
+//! - `void uiua_plugin_free(char* s)`, which frees strings returned by plugin functions
+//!
+//! Values are passed to and from plugin functions as null-terminated JSON strings.
+
+use std::ffi::{c_char, c_int};
+
+/// The version of the plugin ABI
+///
+/// Plugins whose `uiua_plugin_abi_version` returns a different version will not be loaded.
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+/// A function exported by a plugin
+///
+/// The function receives its argument as a JSON string.
+/// It returns a JSON string on success.
+/// On failure, it sets `*is_error` to a non-zero value and returns an error message.
+/// The returned string is freed with the plugin's `uiua_plugin_free`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct PluginFunction {
+    /// The name of the function
+    pub name: *const c_char,
+    /// The function itself
+    pub call: Option<unsafe extern "C" fn(arg: *const c_char, is_error: *mut c_int) -> *mut c_char>,
+}
+
+#[cfg(feature = "ffi")]
+pub(crate) use enabled::*;
+#[cfg(feature = "ffi")]
+mod enabled {
+    use std::{
+        collections::HashMap,
+        ffi::{CStr, CString},
+        path::{Path, PathBuf},
+        sync::OnceLock,
+    };
+
+    use super::*;
+
+    type CallFn = unsafe extern "C" fn(*const c_char, *mut c_int) -> *mut c_char;
+    type FreeFn = unsafe extern "C" fn(*mut c_char);
+
+    #[derive(Default)]
+    pub struct PluginState {
+        dir: OnceLock<PathBuf>,
+        plugins: OnceLock<Plugins>,
+    }
+
+    #[derive(Default)]
+    struct Plugins {
+        functions: HashMap<String, (CallFn, FreeFn)>,
+        errors: Vec<String>,
+        _libraries: Vec<libloading::Library>,
+    }
+
+    impl Plugins {
+        fn load(dir: &Path) -> Self {
+            let mut plugins = Plugins::default();
+            let Ok(entries) = std::fs::read_dir(dir) else {
+                return plugins;
+            };
+            let mut paths: Vec<PathBuf> = entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| {
+                    path.extension()
+                        .is_some_and(|ext| ext == std::env::consts::DLL_EXTENSION)
+                })
+                .collect();
+            paths.sort();
+            for path in paths {
+                if let Err(e) = plugins.load_library(&path) {
+                    plugins.errors.push(format!("{}: {e}", path.display()));
+                }
+            }
+            plugins
+        }
+        fn load_library(&mut self, path: &Path) -> Result<(), String> {
+            let lib = unsafe { libloading::Library::new(path) }.map_err(|e| e.to_string())?;
+            unsafe {
+                let version: libloading::Symbol<unsafe extern "C" fn() -> u32> =
+                    match lib.get(b"uiua_plugin_abi_version") {
+                        Ok(version) => version,
+                        // Not a plugin
+                        Err(_) => return Ok(()),
+                    };
+                let version = version();
+                if version != PLUGIN_ABI_VERSION {
+                    return Err(format!(
+                        "Plugin ABI version is {version}, but version \
+                        {PLUGIN_ABI_VERSION} is required"
+                    ));
+                }
+                let functions: libloading::Symbol<unsafe extern "C" fn() -> *const PluginFunction> =
+                    lib.get(b"uiua_plugin_functions")
+                        .map_err(|e| e.to_string())?;
+                let free: libloading::Symbol<FreeFn> =
+                    lib.get(b"uiua_plugin_free").map_err(|e| e.to_string())?;
+                let free = *free;
+                let mut func = functions();
+                while !func.is_null() && !(*func).name.is_null() {
+                    let name = CStr::from_ptr((*func).name).to_string_lossy().into_owned();
+                    if let Some(call) = (*func).call {
+                        self.functions.insert(name, (call, free));
+                    }
+                    func = func.add(1);
+                }
+            }
+            self._libraries.push(lib);
+            Ok(())
+        }
+    }
+
+    impl PluginState {
+        /// Set the directory plugins are loaded from
+        ///
+        /// Returns `false` if a directory was already set
+        pub(crate) fn set_dir(&self, dir: PathBuf) -> bool {
+            self.dir.set(dir).is_ok()
+        }
+        pub(crate) fn call(&self, name: &str, arg: &str) -> Result<String, String> {
+            let Some(dir) = self.dir.get() else {
+                return Err("Plugins are not enabled. \
+                    Pass a plugin directory with --plugin-dir to load them."
+                    .into());
+            };
+            let plugins = self.plugins.get_or_init(|| Plugins::load(dir));
+            let Some(&(call, free)) = plugins.functions.get(name) else {
+                let mut message = format!("Plugin function `{name}` not found");
+                for error in &plugins.errors {
+                    message.push_str("\nFailed to load ");
+                    message.push_str(error);
+                }
+                return Err(message);
+            };
+            let arg = CString::new(arg).map_err(|e| e.to_string())?;
+            let mut is_error: c_int = 0;
+            let out = unsafe { call(arg.as_ptr(), &mut is_error) };
+            if out.is_null() {
+                return Err(format!("Plugin function `{name}` returned null"));
+            }
+            let out_str = unsafe { CStr::from_ptr(out) }
+                .to_string_lossy()
+                .into_owned();
+            unsafe { free(out) };
+            if is_error == 0 {
+                Ok(out_str)
+            } else {
+                Err(out_str)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    #[cfg(all(feature = "ffi", feature = "native_sys"))]
+    fn not_loaded_without_dir() {
+        let mut env = crate::Uiua::with_native_sys();
+        let Err(err) = env.run_str("# Experimental!\n&plugin \"echo\" 1") else {
+            panic!("Plugin call succeeded without a plugin directory");
+        };
+        assert!(err.to_string().contains("not enabled"), "{err}");
+    }
+}
@@ -498,6 +498,83 @@ primitive!(
     /// A complex number [equals] a real one if the imaginary part is 0 and the real parts [match].
     /// ex: = 5 ℂ0 5
     (2, Complex, DyadicPervasive, ("complex", 'ℂ')),
+    /// Get the bitwise AND of two arrays of integers
+    ///
+    /// ex: # Experimental!
+    ///   : bitand 12 10
+    /// ex: # Experimental!
+    ///   : bitand 3 [1 2 3 4 5 6 7]
+    /// Negative numbers use two's complement.
+    /// ex: # Experimental!
+    ///   : bitand ¯4 13
+    ///
+    /// See also: [bitor], [bitxor]
+    (2, BitAnd, DyadicPervasive, "bitand"),
+    /// Get the bitwise OR of two arrays of integers
+    ///
+    /// ex: # Experimental!
+    ///   : bitor 12 10
+    /// ex: # Experimental!
+    ///   : bitor 8 [1 2 3 4 5 6 7]
+    ///
+    /// See also: [bitand], [bitxor]
+    (2, BitOr, DyadicPervasive, "bitor"),
+    /// Get the bitwise XOR of two arrays of integers
+    ///
+    /// ex: # Experimental!
+    ///   : bitxor 12 10
+    /// [bitxor]ing with the same value twice gives back the original array.
+    /// ex: # Experimental!
+    ///   : bitxor 42 bitxor 42 [1 2 3 4 5]
+    ///
+    /// See also: [bitand], [bitor]
+    (2, BitXor, DyadicPervasive, "bitxor"),
+    /// Shift the bits of an array of integers
+    ///
+    /// The first argument is the number of bits to shift by.
+    /// Positive amounts shift left.
+    /// ex: # Experimental!
+    ///   : bitshift 3 [1 2 3]
+    /// Negative amounts shift right, rounding down.
+    /// ex: # Experimental!
+    ///   : bitshift ¯2 [13 ¯13]
+    (2, BitShift, DyadicPervasive, "bitshift"),
+    /// Count the set bits of an array of integers
+    ///
+    /// ex: # Experimental!
+    ///   : popcount [0 1 2 3 255 256]
+    /// [popcount] is equivalent to `reduce``add``bits` on each number, but it does not create the array of bits.
+    /// ex: # Experimental!
+    ///   : popcount ¯7
+    ///   : /+⋯ ¯7
+    (1, PopCount, MonadicPervasive, "popcount"),
+    /// Wrap an array of integers to the range of a signed fixed-width integer
+    ///
+    /// The subscript is the number of bits, up to 53. The default is 32.
+    /// ex: # Experimental!
+    ///   : signed [1 2147483647 2147483648 ¯2147483649]
+    /// ex: # Experimental!
+    ///   : signed₈ [127 128 255 256]
+    /// This allows emulating the wraparound arithmetic of other languages.
+    /// ex: # Experimental!
+    ///   : ∧(signed +⊙(×31)) -@\0 "hello, world" 0
+    /// Numbers are still stored as 64-bit floats, so intermediate results larger than 2⁵³ lose precision before they are wrapped.
+    ///
+    /// See also: [unsigned]
+    (1, Signed, MonadicPervasive, "signed"),
+    /// Wrap an array of integers to the range of an unsigned fixed-width integer
+    ///
+    /// The subscript is the number of bits, up to 53. The default is 32.
+    /// ex: # Experimental!
+    ///   : unsigned [¯1 4294967296 4294967297]
+    /// ex: # Experimental!
+    ///   : unsigned₁₆ [¯1 65535 65536]
+    /// [unsigned] with 8 bits or fewer makes an array with the same compact storage as [bits] and [utf₈].
+    /// ex: # Experimental!
+    ///   : unsigned₈ [¯1 0 255 256 1000]
+    ///
+    /// See also: [signed]
+    (1, Unsigned, MonadicPervasive, "unsigned"),
     /// Get the number of rows in an array
     ///
     /// ex: ⧻5
@@ -637,6 +714,19 @@ primitive!(
     /// [under][bits] can be used to perform bit-wise operations.
     /// ex: ⍜⋯(¬⬚0↙8) 5
     (1, Bits, MonadicArray, ("bits", '⋯')),
+    /// Pack the last axis of an array of bits into bytes
+    ///
+    /// Bits are packed LSB-first, like [bits].
+    /// ex: # Experimental!
+    ///   : packbits [1 0 1 1]
+    /// ex: # Experimental!
+    ///   : packbits [1_0_0_0_0_0_0_0_1 0_1_0_0_0_0_0_0_1]
+    /// [un][packbits] unpacks bytes into bits. Each byte becomes 8 bits.
+    /// ex: # Experimental!
+    ///   : °packbits [13 255]
+    /// ex: # Experimental!
+    ///   : °packbits packbits [1 0 1]
+    (1, PackBits, MonadicArray, "packbits"),
     /// Rotate the shape of an array
     ///
     /// ex: ⍉.[1_2 3_4 5_6]
@@ -949,6 +1039,9 @@ primitive!(
     /// ex: ⍜⊏⋅η_τ 1_5 ⇡10
     /// ex: ⍜⊏≡⋅[] 1_5 ⇡10
     /// ex: ⍜⊏≡⋅η_τ_π 1_5 ⇡10
+    /// If the indices are a contiguous ascending range, the function may change the number of selected rows. The selected rows are spliced back in.
+    /// ex: ⍜⊏(⊂0) 1_2 [1 2 3 4 5]
+    /// ex: ⍜⊏(↘1) 1_2 [1 2 3 4 5]
     ///
     /// [anti][select] puts the rows of an array at their corresponding indices. This requires a [fill] value if not all indices are present.
     /// ex: ⌝⊏ 3_1_2_0 "abcd"
@@ -1066,6 +1159,12 @@ primitive!(
     /// [infinity] can be used to take every row along an axis.
     /// ex: ↯2_3_4⇡24
     ///   : ↙¯1_∞_2.
+    ///
+    /// [under][take] splices the taken section back in, so the function may change its length.
+    /// ex: ⍜↙(⊂0) 2 [1 2 3 4 5]
+    /// ex: ⍜↙(↘1) 2_∞ ↯3_3⇡9
+    /// When taking along multiple axes, rows can only be added or removed if every other axis is taken in full.
+    /// ex! ⍜↙(↘1) 2_2 ↯3_3⇡9
     (2, Take, DyadicArray, ("take", '↙')),
     /// Drop the first n rows of an array
     ///
@@ -1357,6 +1456,87 @@ primitive!(
     ///   : ⌝base[12 20 ∞] [11 1 3]
     ///   : ⬚10⌝base[12 20] [3 13 6 6 1 4]
     (2, Base, DyadicArray, "base"),
+    /// Get the union of the rows of two arrays
+    ///
+    /// Both arrays are treated as sets of rows.
+    /// The result contains every row that is in either array, in the order in which the rows first appear.
+    /// Rows of the first argument come before rows of the second.
+    /// ex: # Experimental!
+    ///   : union [1 2 3 4] [3 4 5 6]
+    /// ex: # Experimental!
+    ///   : union [1 1 2 2] [2 3 3]
+    /// ex: # Experimental!
+    ///   : union ["ab" "cd"] ["cd" "ef"]
+    /// A scalar or a single row of the other array is treated as a set of one row.
+    /// ex: # Experimental!
+    ///   : union 5 [1 2 3]
+    ///
+    /// [union] is equivalent to `deduplicate``join`, but it does not build the intermediate array.
+    /// See also: [intersection], [difference], [symdiff]
+    (2, Union, DyadicArray, "union"),
+    /// Get the intersection of the rows of two arrays
+    ///
+    /// Both arrays are treated as sets of rows.
+    /// The result contains every row of the first argument that is also in the second, in the order of the first argument.
+    /// ex: # Experimental!
+    ///   : intersection [1 2 3 4] [6 4 5 3]
+    /// ex: # Experimental!
+    ///   : intersection "hello" "world"
+    ///
+    /// [intersection] is equivalent to `deduplicate``keep``memberof``flip``dup`, but it is faster on large arrays.
+    /// See also: [union], [difference], [symdiff]
+    (2, Intersection, DyadicArray, "intersection"),
+    /// Get the rows of one array that are not in another
+    ///
+    /// Both arrays are treated as sets of rows.
+    /// The result contains every row of the first argument that is not in the second, in the order of the first argument.
+    /// ex: # Experimental!
+    ///   : difference [1 2 3 4] [3 4 5 6]
+    /// ex: # Experimental!
+    ///   : difference [1_2 3_4 5_6] [3_4]
+    ///
+    /// See also: [union], [intersection], [symdiff]
+    (2, Difference, DyadicArray, "difference"),
+    /// Get the symmetric difference of the rows of two arrays
+    ///
+    /// Both arrays are treated as sets of rows.
+    /// The result contains every row that is in exactly one of the arrays.
+    /// Rows of the first argument come before rows of the second.
+    /// ex: # Experimental!
+    ///   : symdiff [1 2 3 4] [3 4 5 6]
+    /// ex: # Experimental!
+    ///   : symdiff "hello" "world"
+    ///
+    /// See also: [union], [intersection], [difference]
+    (2, SymDiff, DyadicArray, "symdiff"),
+    /// Convolve an array with a kernel
+    ///
+    /// The kernel slides over the first axes of the array, one for each axis of the kernel.
+    /// Each element of the result is the sum of the overlapping elements multiplied by the reversed kernel.
+    /// The result has the same shape as the array.
+    /// ex: # Experimental!
+    ///   : convolve [1 1 1] [1 2 3 4 5]
+    /// ex: # Experimental!
+    ///   : convolve [1 2 3] [0 0 1 0 0]
+    /// Elements outside the array are `0` by default. [fill] sets a different value.
+    /// ex: # Experimental!
+    ///   : ⬚5convolve [1 1 1] [1 2 3 4 5]
+    /// A subscript sets how elements outside the array are chosen.
+    /// `convolve₁` uses the nearest element at the edge, and `convolve₂` wraps around to the other side.
+    /// ex: # Experimental!
+    ///   : convolve₁ [1 1 1] [1 2 3 4 5]
+    ///   : convolve₂ [1 1 1] [1 2 3 4 5]
+    /// This is useful for filtering images.
+    /// ex: # Experimental!
+    ///   : convolve₁ ÷16 [1_2_1 2_4_2 1_2_1] ▽⟜≡▽ 8 [0_1 1_0]
+    /// If the array has more axes than the kernel, the remaining axes are convolved separately.
+    /// ex: # Experimental!
+    ///   : convolve [1 0 ¯1] [1_10 2_20 4_40]
+    ///
+    /// For correlation instead of convolution, [reverse] the kernel along every axis.
+    /// Kernels that are the outer product of a list for each axis are applied one axis at a time.
+    /// Large kernels are convolved with a fast Fourier transform.
+    (2, Convolve, DyadicArray, "convolve"),
     /// Apply a reducing function to an array
     ///
     /// For reducing with an initial value, see [fold].
@@ -1533,6 +1713,32 @@ primitive!(
     ///   : ⍚₂∘ °△2_3_4
     ///   : ⍚₃∘ °△2_3_4
     ([1], Inventory, IteratingModifier, ("inventory", '⍚')),
+    /// Apply a function along an axis of an array
+    ///
+    /// The subscript specifies the axis. The function is called with that axis as the array's first axis.
+    /// ex: # Experimental!
+    ///   : along₁⇌ °△2_3_4
+    ///   : ⌝⤸1⇌⤸1 °△2_3_4
+    /// If the function keeps the axis, it is moved back to where it was.
+    /// ex: # Experimental!
+    ///   : along₁(↙2) °△2_3_4
+    /// If the function removes the axis, the rest of the axes stay in order.
+    /// ex: # Experimental!
+    ///   : along₁/+ °△2_3_4
+    /// Negative subscripts count from the last axis.
+    /// ex: # Experimental!
+    ///   : along₋₁\+ °△2_3
+    /// Without a subscript, [along] operates along the second axis.
+    /// ex: # Experimental!
+    ///   : along(⊏[2 0]) °△2_3
+    ///
+    /// Arguments with too few axes are passed to the function unchanged.
+    /// ex: # Experimental!
+    ///   : along₁⊂ 0 °△2_3
+    ///
+    /// Functions that only rearrange, take, or reduce rows, like [reverse], [take], and [reduce] with a pervasive function, do not require the array to actually be reoriented.
+    /// For them, [along] with a positive subscript is as fast as nested [rows].
+    ([1], Along, IteratingModifier, "along"),
     /// Apply a function to each combination of rows of some arrays
     ///
     /// ex: ⊞+ 1_2_3 4_5_6_7
@@ -1652,6 +1858,12 @@ primitive!(
     /// Adding a third row to the array allows the fill amount to be specified for each axis.
     /// ex: ⬚0⧈□ [2_2 1_1 0_1] +1°△2_2
     /// ex: ⬚0⧈□ [2_2 2_2 0_1] +1°△4_6
+    ///
+    /// Windowed [reduce]s and [scan]s of [add], [multiply], [maximum], and [minimum] along a single axis do not materialize the windows.
+    /// Moving sums and moving extrema run in time proportional to the length of the array rather than to the length times the window size.
+    /// ex: ⧈/+ 3 [1 5 2 8 3]
+    /// ex: ⧈/↥ [¤3¤2] [1 5 2 8 3 7 4]
+    /// ex: ⬚0⧈\+ 2 [1 2 3 4]
     (2[1], Stencil, IteratingModifier, ("stencil", '⧈')),
     /// Repeat a function a number of times
     ///
@@ -1713,6 +1925,26 @@ primitive!(
     ///
     /// [group] is closely related to [partition].
     (2[1], Group, AggregatingModifier, ("group", '⊕')),
+    /// Group the rows of an array by a key and aggregate each group
+    ///
+    /// Takes 2 functions and an array.
+    /// The first function is called on the whole array and must return one key for each row.
+    /// The second function is called on the rows that share each key.
+    /// The unique keys are returned below the aggregated results, in the order in which they first appear.
+    /// ex: # Experimental!
+    ///   : aggregate(◿3|/+) [1 2 3 4 5 6 7]
+    /// ex: # Experimental!
+    ///   : aggregate(∘|⧻) "mississippi"
+    /// Rows may be grouped by a key computed from them.
+    /// ex: # Experimental!
+    ///   : aggregate(≡⊢|≡⊣) ["ab" "cd" "ae" "cf"]
+    /// The aggregating function may return more than 1 value.
+    /// ex: # Experimental!
+    ///   : aggregate(⌊÷10|⊃/↥/↧) [12 35 17 31 14]
+    ///
+    /// [aggregate] is similar to `group``f``classify``dup` with the [deduplicate]d keys, but it does not require computing the keys twice.
+    /// If the aggregating function is a [reduce] of [add], [multiply], [maximum], or [minimum], the groups are never materialized, so each key is aggregated in a single pass.
+    ([2], Aggregate, AggregatingModifier, "aggregate"),
     /// Group sequential sections of an array
     ///
     /// The most common use of [partition] is to split an array by a delimiter.
@@ -1983,6 +2215,11 @@ primitive!(
     /// This sort of inverse also works with [under].
     /// ex: F ← ⌅(+×10:|÷10-)
     ///   : ⍜F? 2 5
+    /// If the second function takes as many arguments as the first function takes and returns combined, then it is used to set the first function's outputs back into its arguments in an [under].
+    /// It is passed the first function's original arguments, followed by the new outputs. This is how [under][pick] works.
+    /// ex: F ← ⌅(⊡|⍜⊡◌)
+    ///   : F 1 [1 2 3]
+    ///   : ⍜(F 1)(×10) [1 2 3]
     /// Otherwise, an [under]-compatible inverse is set.
     /// ex: F ← ⌅(+|¯)
     ///   : ⍜F? 1 2
@@ -2030,6 +2267,8 @@ primitive!(
     ([1], Un, InversionModifier, ("un", '°')),
     /// Invert the behavior of a function, treating its first argument as a constant
     ///
+    /// A list of all dyadic primitives that are [anti]-compatible on their own can be found [below](#antis).
+    ///
     /// [un] has a guarantee that the inverted function will have a signature that is the inverse of original function's signature. For dyadic functions, if we want the inverse to *also* be dyadic, then we have to do some workarounds. We can either include the first argument in the inverted function, or we can use [on].
     /// For example, here are two ways to invert [rotate].
     /// ex: °(↻1) [1 2 3]
@@ -2045,6 +2284,9 @@ primitive!(
     /// ex: ⌝⍥(+1) 3 10
     /// ex: ⌝⊂ 1 [1 2 3]
     /// ex! ⌝⊂ 1 [2 3 4]
+    /// [anti][modulus] checks that the value is a possible result of the [modulus] and returns it as a representative.
+    /// ex: ⌝◿ 4 3
+    /// ex! ⌝◿ 4 5
     /// A function's [anti]-inverse can be set with [obverse].
     /// For more about inverses, see the [Inverse Tutorial](/tutorial/inverses).
     ([1], Anti, InversionModifier, ("anti", '⌝')),
@@ -2220,6 +2462,14 @@ primitive!(
     ///   : ⍣(⍤0.+)¤     3 5 # First argument only
     ///   : ⍣(⍤0.+)⊟     3 5 # Both arguments
     ///   : ⍣(⍤0.+)[⊙⊙∘] 3 5 # Both arguments and error
+    /// Values thrown with [assert] are passed to the handler intact, so a library can throw structured errors.
+    /// ex: ⍣(⍤{"not found" 404} 0 +1)⋅∘ 5
+    /// If the handler takes two more arguments than the tried function, it is also passed a boolean indicating whether the error was thrown with [assert]. This distinguishes thrown values from runtime errors.
+    /// ex: # Experimental!
+    ///   : F ← ⍣(⍤{"zero" 0}≠0.⋕)⋅(⨬({"runtime" ∘}|∘):)
+    ///   : F "5"
+    ///   : F "0"
+    ///   : F "dog"
     /// If we want to provide a default value from the stack, we can ignore it in the tried function with [gap] and then use [identity] in the handler.
     /// ex: ⍣⋅⋕∘ 5 "12"  # No error
     ///   : ⍣⋅⋕∘ 5 "dog" # Error
@@ -2292,6 +2542,26 @@ primitive!(
     /// ex! ⍤. =8 9
     /// Errors thrown by [assert] can be caught with [try].
     (2(0), Assert, Misc, ("assert", '⍤'), Impure),
+    /// Check that a function does not fail for randomly generated arguments
+    ///
+    /// Expects a function and a spec that describes how to generate its arguments.
+    /// The function is called 100 times. It usually uses [assert] to check a property that should hold for all arguments.
+    /// ex: # Experimental!
+    ///   : property(⍤"Reversing twice is identity" ≍⇌⇌.) {"int" 10}
+    /// A spec is either the name of a type or a box list of a type name and a maximum shape.
+    /// The types are `"bool"`, `"nat"`, `"int"`, `"num"`, and `"char"`. Numbers are between `¯100` and `100`.
+    /// With just a type name, scalars are generated. With a maximum shape, each axis has a random length up to the maximum.
+    /// If the function takes more than one argument, the spec must be a box list with a spec for each argument.
+    /// ex: # Experimental!
+    ///   : property(⍤"Addition commutes" ≍⊃+(+:)) {"num" "num"}
+    ///
+    /// When the function fails, the arguments are shrunk to a smaller case that still fails.
+    /// The error says how many tests were run and what the minimal counterexample is.
+    /// ex! # Experimental!
+    ///   : property(⍤"Sorted" ≍⊸⍆) {"nat" 10}
+    ///
+    /// When running `uiua test`, a line that ends in [property] counts as a test, just like a line that ends in [assert].
+    (1(0)[1], Property, Misc, "property", Impure),
     /// Generate a random number in the range `[0, 1)`
     ///
     /// If you need a seeded random number, use [gen].
@@ -2316,6 +2586,52 @@ primitive!(
     ///   : ∵F [1 1 2 2 3 3]
     /// In general, this should only be used with functions that perform a potentially expensive calculation.
     ([1], Memo, OtherModifier, "memo"),
+    /// Call a function with decimal arithmetic
+    ///
+    /// The subscript is the number of decimal places, up to 15. The default is 2.
+    /// The arguments and outputs of the function, as well as the result of every [add], [subtract], [multiply], and [divide] in the function, are rounded to that many places.
+    /// ex: # Experimental!
+    ///   : + 0.1 0.2
+    ///   : decimal(+) 0.1 0.2
+    /// ex: # Experimental!
+    ///   : decimal(× 1.15) 19.99
+    /// ex: # Experimental!
+    ///   : decimal₃(÷ 3) 10
+    /// Ties are rounded to the nearest even digit, which avoids bias when many values are summed.
+    /// ex: # Experimental!
+    ///   : decimal(× 0.5) [0.05 0.15 2.675]
+    /// Other rounding modes are available with the subscripted forms of [floor], [ceiling], and [round].
+    /// ex: # Experimental!
+    ///   : decimal(⌈₁ × 1.15) 19.99
+    ///
+    /// Numbers are still stored as binary floating point, so each result is the closest representable number to the correct decimal value.
+    /// Because the [divide] of any decimals is rounded, results may differ from calculating with more precision and rounding at the end.
+    /// ex: # Experimental!
+    ///   : decimal(×3 ÷3) 1
+    ([1], Decimal, OtherModifier, "decimal"),
+    /// Call a function with interval arithmetic
+    ///
+    /// Each argument is either an exact scalar or an array whose last axis has length 2.
+    /// Each pair along that axis is a value and its uncertainty.
+    /// The function is called on every value, and each output is a pair of the value and uncertainty of the result.
+    /// ex: # Experimental!
+    ///   : interval(+) [1 0.1] [2 0.2]
+    /// ex: # Experimental!
+    ///   : interval(×) [3 0.5] [4 0.1]
+    /// Exact scalar arguments have no uncertainty.
+    /// ex: # Experimental!
+    ///   : interval(÷) 2 [10 1]
+    /// If the arguments have more axes, the function is called on each value.
+    /// ex: # Experimental!
+    ///   : interval(ⁿ2) [[1 0.1] [2 0.1] [3 0.1]]
+    ///
+    /// The results are guaranteed bounds, not statistical estimates.
+    /// Because each use of an argument is bounded independently, using an argument more than once may give wider bounds than necessary.
+    /// ex: # Experimental!
+    ///   : interval(-.) [5 1]
+    ///
+    /// Only scalar arithmetic and stack manipulation are supported.
+    ([1], Interval, OtherModifier, "interval"),
     /// Run a function at compile time
     ///
     /// ex: F ← (⌊×10[⚂⚂⚂])
@@ -2326,12 +2642,32 @@ primitive!(
     /// If you would like to pass arguments to [comptime]'s function, make them part of the function
     /// ex! comptime(+) 1 2
     /// ex: comptime(+ 1 2)
+    /// Values produced by [comptime] are baked into the compiled program.
+    /// This makes it useful for generating lookup tables.
+    /// ex: comptime(⊞+.⇡5)
+    /// [comptime]'s function can read files, such as assets that should be embedded in the program. Relative paths are read relative to the file that contains the [comptime] if the file exists there.
     ([1], Comptime, Comptime, "comptime"),
+    /// Choose a function at compile time
+    ///
+    /// [cfg] takes 3 functions. The last one is the condition, which is run at compile time and must return a boolean.
+    /// If the condition is true, the second function is compiled. Otherwise, the first function is compiled.
+    /// ex: # Experimental!
+    ///   : cfg("web"|"native"|≍"native" Target)
+    /// Only the chosen function is compiled, so the other one may use features that are unavailable.
+    /// This is useful for providing fallbacks for system functions that are not available in the browser.
+    /// ex: # Experimental!
+    ///   : F ← cfg(⋅0|&fe|≍"native" Target)
+    ///   : F "example.ua"
+    /// The `Features` constant is a list of the features enabled with the `--feature` flag.
+    /// ex: # Experimental!
+    ///   : cfg(+|-|∊Features□"subtract") 3 5
+    /// Like [comptime], the condition must take no arguments and may not reference runtime bindings.
+    ([3], Cfg, Comptime, "cfg"),
     /// Spawn a thread
     ///
     /// Expects a function.
     /// In the native interpreter, the function is called in a new OS thread.
-    /// In the web editor, the function is called and blocks until it returns.
+    /// In the web editor, the function is called in a new thread if the page allows it. Otherwise, it is called and blocks until it returns.
     /// A thread id that can be passed to [wait] is pushed to the stack. Handles are just numbers.
     /// [wait] consumes the thread id and appends the thread's stack to the current stack.
     /// ex:      spawn⇡ 10
@@ -2355,6 +2691,26 @@ primitive!(
     /// The thread pool has as many threads as the machine has processors.
     /// If all threads in the pool are busy, then [pool] will block until a thread is available.
     ([1], Pool, Thread, "pool", Impure),
+    /// Call a function on each row of arrays using a bounded number of worker threads
+    ///
+    /// Expects a maximum number of worker threads and arrays to iterate over.
+    /// The results are the same as [rows], and they are collected in the same order as the rows.
+    /// ex: # Experimental!
+    ///   : parallel(/+⇡) 4 [10 100 1000]
+    ///
+    /// Unlike doing [rows][spawn], [parallel] does not create a thread for every row, so it works well with many rows.
+    /// There are never more workers than rows, and at most four workers are used per processor, no matter how many are requested.
+    /// Idle workers take rows that have not yet been started from busy ones, so rows that take different amounts of time are still spread evenly.
+    /// ex: # Experimental!
+    ///   : /+parallel(/+⇡) 8 ⇡1000
+    ///
+    /// Like [rows], [parallel] can iterate over multiple arrays.
+    /// ex: # Experimental!
+    ///   : parallel(□⊂⊙⇡) 2 [1 2 3] [3 2 1]
+    ///
+    /// If the environment does not allow spawning threads, the function is called on each row in the current thread.
+    /// In the web editor, the rows are also processed one after another unless the page allows spawning threads.
+    ([1], Parallel, Thread, "parallel", Impure),
     /// Wait for a thread to finish and push its results to the stack
     ///
     /// The argument must be a thread id returned by [spawn] or [pool].
@@ -2772,6 +3128,43 @@ primitive!(
     ///
     /// The opposite of [quote] is [stringify].
     (0[1], Quote, Comptime, "quote"),
+    /// Convert a function into data at compile time
+    ///
+    /// The result is a list of boxed items, one for each word of the function.
+    /// Each item is a list of 3 boxes: a kind, a value, and a span.
+    /// ex: # Experimental!
+    ///   : reflect(+1)
+    /// The span is the start line, start column, end line, and end column of the word.
+    /// The kind is one of the following:
+    /// - `"primitive"` - The value is the primitive's name
+    /// - `"constant"` - The value is the constant itself
+    /// - `"name"` - The value is the name of a binding
+    /// - `"strand"`, `"array"`, `"box array"`, or `"function"` - The value is a list of items
+    /// - `"modifier"` - The value is a list of the modifier's name followed by an item list for each of its functions
+    /// - `"code"` - The value is the formatted source of anything else
+    /// ex: # Experimental!
+    ///   : X ← 5
+    ///   : ≡(⊢°□) reflect(/+⇡ "hi" X)
+    /// ex: # Experimental!
+    ///   : ≡(◇⊡1) ▽⊸≡(≍□"primitive"⊢°□) reflect(+1 ×2 ⇌ "hi")
+    ///
+    /// The opposite of [reflect] is [reify].
+    (0[1], Reflect, Comptime, "reflect"),
+    /// Convert data into a function at compile time
+    ///
+    /// The data must be in the format produced by [reflect].
+    /// The function is evaluated at compile time.
+    /// ex: # Experimental!
+    ///   : reify(reflect(+1)) 5
+    /// This allows functions to be analyzed and transformed by Uiua code.
+    /// ex: # Experimental!
+    ///   : [reify(⇌ reflect(1 2 3))]
+    /// ex: # Experimental!
+    ///   : Sub ← ⍜(⊡1|□"subtract"◌)
+    ///   : reify(≡(□⍥Sub≍□"add"⊡1.°□) reflect(+1 +2 ×3)) 10
+    ///
+    /// The opposite of [reify] is [reflect].
+    (0[1], Reify, Comptime, "reify"),
     /// Get the signature of a function
     ///
     /// ex: # Experimental!
@@ -2790,6 +3183,42 @@ primitive!(
     /// At the moment, this is only useful for debugging.
     /// While theoretically, it could be used in a macro to choose a branch of a [switch] appropriate for the function, this is not yet possible because of the way that macros and signature checking work.
     (0(2)[1], Sig, Comptime, "signature"),
+    /// Check whether a function can be inverted
+    ///
+    /// Pushes `1` if the function can be used with [un] and `0` otherwise.
+    /// ex: # Experimental!
+    ///   : invertible(+1)
+    /// ex: # Experimental!
+    ///   : invertible⊂
+    /// ex: # Experimental!
+    ///   : invertible(⊂1)
+    /// This allows macros to check the functions they are given.
+    /// ex: # Experimental!
+    ///   : Inv! ← ⍤"Function must be invertible" invertible^0
+    ///   : Inv!⇌
+    /// ex! # Experimental!
+    ///   : Inv! ← ⍤"Function must be invertible" invertible^0
+    ///   : Inv!⊢
+    ///
+    /// The check is done at compile time, so the function is not called.
+    /// A function's signature can be gotten by giving a code macro a second argument.
+    (0(1)[1], Invertible, Comptime, "invertible"),
+    /// Get a function as math notation
+    ///
+    /// The function is converted to a string of a mathematical expression of `x` at compile time.
+    /// ex: # Experimental!
+    ///   : math(+1×3)
+    /// ex: # Experimental!
+    ///   : math(÷:1 +1)
+    /// This is useful for checking the results of [derivative] and [integral].
+    /// ex: # Experimental!
+    ///   : math∂(ⁿ3 +1)
+    /// ex: # Experimental!
+    ///   : math∫(×.)
+    /// Only functions that the algebra system understands can be converted.
+    /// ex! # Experimental!
+    ///   : math(⌊×2)
+    (0[1], Math, Comptime, "math"),
     /// Run the Fast Fourier Transform on an array
     ///
     /// The Fast Fourier Transform (FFT) is an optimized algorithm for computing the Discrete Fourier Transform (DFT). The DFT is a transformation that converts a signal from the time domain to the frequency domain.
@@ -2930,7 +3359,7 @@ primitive!(
     ((2)[2], Path, Misc, "path"),
     /// Calculate the derivative of a mathematical expression
     ///
-    /// Basic polynomials are supported, along with [sine] and [logarithm].
+    /// Scalar arithmetic is supported, including [power], [sine], [logarithm], and calls to other functions.
     /// ex: # Experimental!
     ///   : # x² → 2x
     ///   : ∂(×.) 5
@@ -2944,14 +3373,22 @@ primitive!(
     /// ex: # Experimental!
     ///   : # sin(x) → cos(x)
     ///   : ⍜×⁅1e3 ∂∿ ×τ÷⟜⇡8
-    /// Most derivatives that would require the chain or product rule are not supported.
-    /// ex! # Experimental!
+    /// Derivatives that require the chain or product rule are supported.
+    /// ex: # Experimental!
     ///   : # xsin(x)  →  sin(x) + xcos(x)
     ///   : ∂(×∿.) ×τ÷⟜⇡8
-    /// They do work if the inner derivative is a constant.
     /// ex: # Experimental!
-    ///   : # sin(2x) → 2cos(2x)
-    ///   : ∂(∿×2) ×τ÷⟜⇡8
+    ///   : # eˣ → eˣ
+    ///   : ∂(ⁿ:e) [0 1 2]
+    /// ex: # Experimental!
+    ///   : # 1/(1 + x²)  →  -2x/(1 + x²)²
+    ///   : F ← ÷:1 +1 ×.
+    ///   : ∂F [0 1 2]
+    /// Simple expressions are differentiated algebraically when compiled.
+    /// Other expressions are differentiated numerically at each point using [dual numbers](https://en.wikipedia.org/wiki/Dual_number).
+    /// Functions that do not operate on scalars, like [reverse] or [reduce], cannot be differentiated.
+    /// ex! # Experimental!
+    ///   : ∂(/+) [1 2 3]
     ///
     /// See also: [integral]
     ([1], Derivative, Misc, ("derivative", '∂')),
@@ -2978,6 +3415,67 @@ primitive!(
     ///
     /// See also: [derivative]
     ([1], Integral, Misc, ("integral", '∫')),
+    /// Find a root of a function
+    ///
+    /// The function must take 1 number and return 1 number.
+    /// [solve] finds a number where the function returns 0.
+    /// If the starting point is a single number, the search starts from that number.
+    /// ex: # Experimental!
+    ///   : solve(-2×.) 1
+    /// ex: # Experimental!
+    ///   : solve(-0.5 ∿) 0
+    /// If the starting point is a pair of numbers, a root between them is found.
+    /// The function must have different signs at each end.
+    /// ex: # Experimental!
+    ///   : solve(-2×.) ¯2_0
+    /// ex! # Experimental!
+    ///   : solve(-2×.) 2_3
+    /// When possible, steps are taken using the [derivative] of the function. Otherwise, the derivative is estimated.
+    /// ex: # Experimental!
+    ///   : solve(-2◿5×.) 1
+    ///
+    /// See also: [minimize]
+    ([1], Solve, Misc, "solve"),
+    /// Find a local minimum of a function
+    ///
+    /// The function must take 1 argument and return 1 number.
+    /// If the starting point is a number, the function is called on numbers.
+    /// ex: # Experimental!
+    ///   : ⁅₆ minimize(ⁿ2-3) 0
+    /// ex: # Experimental!
+    ///   : minimize∿ 0
+    /// If the starting point is a list, the function is called on lists of the same length.
+    /// ex: # Experimental!
+    ///   : ⁅₆ minimize(/+ⁿ2 - 1_2_3) 0_0_0
+    /// This uses the [Nelder-Mead method](https://en.wikipedia.org/wiki/Nelder%E2%80%93Mead_method), which works best with only a few dimensions.
+    /// ex: # Experimental!
+    ///   : # Rosenbrock function
+    ///   : F ← +⊃(ⁿ2¬⊢|×100ⁿ2-⊃(ⁿ2⊢|⊡1))
+    ///   : ⁅₄ minimize F ¯1_1
+    /// Results are accurate to about 8 significant digits.
+    ///
+    /// See also: [solve]
+    ([1], Minimize, Misc, "minimize"),
+    /// Integrate an ordinary differential equation
+    ///
+    /// The function calculates the derivative of a state.
+    /// The first argument is the initial state, and the second argument is a list of times.
+    /// The result has a row for the state at each time.
+    /// ex: # Experimental!
+    ///   : # y' = y
+    ///   : ode(∘) 1 [0 1 2]
+    /// If the function takes 2 arguments, the second is the current time.
+    /// ex: # Experimental!
+    ///   : # y' = 2t
+    ///   : ode(×2⋅∘) 0 ⇡5
+    /// The state can be an array, which makes it easy to integrate systems of equations.
+    /// Here is a simple harmonic oscillator with a position and velocity.
+    /// ex: # Experimental!
+    ///   : ⁅₃ ode(⊂⊃⊣(¯⊢)) [1 0] ×η⇡5
+    /// Steps are taken using an adaptive [Runge-Kutta method](https://en.wikipedia.org/wiki/Dormand%E2%80%93Prince_method), so the times do not need to be close together.
+    /// ex: # Experimental!
+    ///   : ode(×0.1) 1 [0 100]
+    ([1], Ode, Misc, "ode"),
     /// Encode an array into a JSON string
     ///
     /// ex: json [1 2 3]
@@ -3042,6 +3540,42 @@ primitive!(
     /// While it is not useful to display the output bytes here, we can see how the result of decoding works:
     /// ex: °xlsx xlsx . ↯3_6⇡18
     (1, Xlsx, Encoding, "xlsx"),
+    /// Encode a map into an INI string
+    ///
+    /// INI files are a simple format for configuration.
+    /// The input must be a [map] from section names to maps of keys and values.
+    /// Values must be strings or numbers.
+    /// ex: # Experimental!
+    ///   : ini map {"server" "log"} {map {"host" "port"} {"localhost" 8080} map {"level"} {"info"}}
+    /// Keys whose values are not maps are written before the first section.
+    /// ex: # Experimental!
+    ///   : ini map {"name" "db"} {"app" map {"path"} {"data.db"}}
+    ///
+    /// You can use [un][ini] to decode an INI string into a map of maps.
+    /// Every value is decoded as a string.
+    /// Keys that come before the first section are put in a section with an empty name.
+    /// ex: # Experimental!
+    ///   : °ini "name = app\n[server]\nhost = localhost\nport = 8080"
+    /// Keys and values can be separated by `=` or `:`. Lines that start with `;` or `#` are comments.
+    /// ex: # Experimental!
+    ///   : °ini "; Settings\n[db]\npath: data.db\n# user = admin"
+    (1, Ini, Encoding, "ini"),
+    /// Encode a map into the contents of a `.env` file
+    ///
+    /// `.env` files hold environment variables, like API tokens, for a program to use.
+    /// The input must be a [map] from variable names to values.
+    /// Values must be strings or numbers. Values that have spaces or special characters are quoted.
+    /// ex: # Experimental!
+    ///   : dotenv map {"PORT" "GREETING"} {8080 "Hello, World!"}
+    ///
+    /// You can use [un][dotenv] to decode a `.env` file into a map.
+    /// Values can be unquoted or in single or double quotes. Escape sequences like `\n` only work in double quotes.
+    /// Lines that start with `#` are comments, and lines can start with `export`.
+    /// ex: # Experimental!
+    ///   : °dotenv "# Settings\nexport PORT=8080\nNAME='My App'\nMOTD=\"Hi\\nthere\" # comment"
+    ///
+    /// To make the variables in a `.env` file available to [&var], run a program with `uiua run --dotenv`.
+    (1, Dotenv, Encoding, "dotenv"),
     /// Encode an array into a compact binary representation
     ///
     /// This is useful for saving arrays to files.
@@ -3095,6 +3629,29 @@ primitive!(
     ///   : ⍜⊜□⍚(⊂@,)∊" \n". repr # add commas
     ///   : &p ⍜▽∵⋅@-=@¯.        # replace negate glyphs with minus signs
     (1, Repr, Misc, "repr"),
+    /// Compile and run a string of code in a sandbox
+    ///
+    /// The code is run in a new environment with no access to system functions or to the surrounding code's bindings.
+    /// There are two outputs. The first is a boxed list of the values the code left on the stack, in the same order as if the code had been wrapped in `{}`.
+    /// ex: # Experimental!
+    ///   : evaluate "+ 1 2"
+    /// ex: # Experimental!
+    ///   : evaluate "⇡3 5"
+    /// The second output is empty if the code ran successfully.
+    /// If the code fails to compile or run, the boxed list is empty and the second output is the error.
+    /// ex: # Experimental!
+    ///   : evaluate "⊂ 1 ("
+    /// ex: # Experimental!
+    ///   : evaluate "⍤\"Oh no!\" 0"
+    /// ex: # Experimental!
+    ///   : evaluate "&fras \"secrets.txt\""
+    ///
+    /// Evaluated code that runs for longer than 1 second is stopped.
+    /// Evaluated code cannot create an array larger than 64 MB.
+    /// ex: # Experimental!
+    ///   : evaluate "⇡1e8"
+    /// This is not a hard limit on memory use. Evaluated code that creates many arrays or builds an array up bit by bit can still use more memory than this, so do not [evaluate] untrusted code in an environment where memory is scarce.
+    (1(2), Evaluate, Misc, "evaluate", Impure),
     /// Encode an image into a byte array with the specified format
     ///
     /// The first argument is the format, and the second is the image.
@@ -3205,8 +3762,12 @@ macro_rules! impl_primitive {
                 $variant,
             )*
             DeshapeSub(i32),
+            SignedN(u8),
+            UnsignedN(u8),
             UndoDeshape(Option<i32>),
             EachSub(i32),
+            AlongSub(i32),
+            DecimalSub(u8),
             TransposeN(i32),
             UndoTransposeN(usize, i32),
             UndoReverse { n: usize, all: bool },
@@ -3220,6 +3781,7 @@ macro_rules! impl_primitive {
                 Some(match self {
                     $($(ImplPrimitive::$variant => $args,)?)*
                     ImplPrimitive::DeshapeSub(_) => 1,
+                    ImplPrimitive::SignedN(_) | ImplPrimitive::UnsignedN(_) => 1,
                     ImplPrimitive::UndoDeshape(_) => 2,
                     ImplPrimitive::TransposeN(_) => 1,
                     ImplPrimitive::UndoTransposeN(n, _) => *n,
@@ -3246,6 +3808,8 @@ macro_rules! impl_primitive {
                     $($(ImplPrimitive::$variant => Some($margs),)?)*
                     ImplPrimitive::ReduceDepth(_) => Some(1),
                     ImplPrimitive::EachSub(_) => Some(1),
+                    ImplPrimitive::AlongSub(_) => Some(1),
+                    ImplPrimitive::DecimalSub(_) => Some(1),
                     _ => None
                 }
             }
@@ -3268,6 +3832,7 @@ impl_primitive!(
     (1, Acos),
     (0, UnPop),
     (1, UnBits),
+    (1, UnPackBits),
     (1, UnWhere),
     (1(2), UnCouple),
     (1, UnUtf8),
@@ -3298,6 +3863,8 @@ impl_primitive!(
     (1, UnBinary),
     (1, UnCsv),
     (1, UnXlsx),
+    (1, UnIni),
+    (1, UnDotenv),
     (1, UnFft),
     (1, UnDatetime),
     (2, ProgressiveIndexOf),
@@ -3313,6 +3880,7 @@ impl_primitive!(
     (1, UndoFix),
     (2, UndoUnbits),
     (2, AntiBase),
+    (2, AntiModulus),
     (3, UndoSelect),
     (3, UndoPick),
     (3, UndoTake),
@@ -3367,11 +3935,17 @@ impl_primitive!(
     (2, MatrixDiv),
     // Implementation details
     (1, Utf16),
+    (2, ConvolveClamp),
+    (2, ConvolveWrap),
     ([2], RepeatWithInverse),
     ([1], RepeatCountConvergence),
     (2(1), ValidateType),
     (2(0), ValidateTypeConsume),
     (2(0), TestAssert, Impure),
+    (1(0)[1], TestProperty, Impure),
+    (1[1], AutoDerivative),
+    /// Record the stack after a top-level line
+    (0(0), RecordLine, Impure),
     /// Validate that a non-boxed variant field has a valid type and rank
     (1, ValidateNonBoxedVariant),
     (2(1), ValidateVariant),
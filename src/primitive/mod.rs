@@ -30,10 +30,10 @@ use crate::{
     array::Array,
     boxed::Boxed,
     encode,
-    lex::{AsciiToken, SUBSCRIPT_DIGITS},
+    lex::{AsciiToken, Span, SUBSCRIPT_DIGITS},
     sys::*,
     value::*,
-    FunctionId, Ops, Shape, Signature, Uiua, UiuaErrorKind, UiuaResult,
+    Compiler, FunctionId, Ops, Shape, Signature, Uiua, UiuaErrorKind, UiuaResult,
 };
 
 /// Categories of primitives
@@ -176,16 +176,33 @@ impl fmt::Display for ImplPrimitive {
                 write!(f, "{Deshape}")?;
                 fmt_subscript(f, i)
             }
+            &SignedN(bits) => {
+                write!(f, "{Signed}")?;
+                fmt_subscript(f, bits as i32)
+            }
+            &UnsignedN(bits) => {
+                write!(f, "{Unsigned}")?;
+                fmt_subscript(f, bits as i32)
+            }
             &EachSub(i) => {
                 write!(f, "{Each}")?;
                 fmt_subscript(f, i)
             }
+            &DecimalSub(places) => {
+                write!(f, "{Decimal}")?;
+                fmt_subscript(f, places as i32)
+            }
+            &AlongSub(i) => {
+                write!(f, "{Along}")?;
+                fmt_subscript(f, i)
+            }
             Root => write!(f, "{Anti}{Pow}"),
             Cos => write!(f, "cos"),
             Asin => write!(f, "{Un}{Sin}"),
             Acos => write!(f, "{Un}{Cos}"),
             UnPop => write!(f, "{Un}{Pop}"),
             UnBits => write!(f, "{Un}{Bits}"),
+            UnPackBits => write!(f, "{Un}{PackBits}"),
             UnWhere => write!(f, "{Un}{Where}"),
             UnCouple => write!(f, "{Un}{Couple}"),
             UnMap => write!(f, "{Un}{Map}"),
@@ -194,6 +211,8 @@ impl fmt::Display for ImplPrimitive {
             UnUtf8 => write!(f, "{Un}{Utf8}"),
             UnUtf16 => write!(f, "{Un}{Utf16}"),
             Utf16 => write!(f, "utf₁₆"),
+            ConvolveClamp => write!(f, "{Convolve}₁"),
+            ConvolveWrap => write!(f, "{Convolve}₂"),
             UnGraphemes => write!(f, "{Un}{Graphemes}"),
             UnParse => write!(f, "{Un}{Parse}"),
             UnFix => write!(f, "{Un}{Fix}"),
@@ -214,6 +233,8 @@ impl fmt::Display for ImplPrimitive {
             UnBinary => write!(f, "{Un}{Binary}"),
             UnCsv => write!(f, "{Un}{Csv}"),
             UnXlsx => write!(f, "{Un}{Xlsx}"),
+            UnIni => write!(f, "{Un}{Ini}"),
+            UnDotenv => write!(f, "{Un}{Dotenv}"),
             UnFft => write!(f, "{Un}{Fft}"),
             UnDatetime => write!(f, "{Un}{DateTime}"),
             UnBoth => write!(f, "{Un}{Both}"),
@@ -225,7 +246,8 @@ impl fmt::Display for ImplPrimitive {
             UnClip => write!(f, "{Un}{}", Primitive::Sys(SysOp::Clip)),
             ProgressiveIndexOf => write!(f, "{Un}{By}{Select}"),
             UndoUnbits => write!(f, "{Under}{Un}{Bits}"),
-            AntiBase => write!(f, "{Under}{Base}"),
+            AntiBase => write!(f, "{Anti}{Base}"),
+            AntiModulus => write!(f, "{Anti}{Modulus}"),
             UndoReverse { n, .. } => write!(f, "{Under}{Reverse}({n})"),
             UndoTransposeN(n, _) => write!(f, "{Under}{Transpose}({n})"),
             UndoRotate(n) => write!(f, "{Under}{Rotate}({n})"),
@@ -319,6 +341,9 @@ impl fmt::Display for ImplPrimitive {
             ValidateType => write!(f, "{Un}…{Type}{Dup}"),
             ValidateTypeConsume => write!(f, "{Un}…{Type}"),
             TestAssert => write!(f, "{Assert}"),
+            TestProperty => write!(f, "{Property}"),
+            AutoDerivative => write!(f, "{Derivative}"),
+            RecordLine => write!(f, "<record line>"),
             ValidateNonBoxedVariant => write!(f, "|…[…]"),
             ValidateVariant => write!(f, "|…°[…]"),
             TagVariant => write!(f, "<tag variant>"),
@@ -431,6 +456,16 @@ impl Primitive {
     pub fn name(&self) -> &'static str {
         self.names().text
     }
+    /// Get the name of the primitive as it should be read aloud
+    ///
+    /// This is the same as [`Primitive::name`], except that system functions
+    /// use their long names rather than their `&`-prefixed short names.
+    pub fn spoken_name(&self) -> String {
+        match self {
+            Primitive::Sys(op) => op.long_name().replace(" - ", " "),
+            prim => prim.name().into(),
+        }
+    }
     /// Get the ASCII token that formats to the primitive
     pub fn ascii(&self) -> Option<AsciiToken> {
         self.names().ascii
@@ -492,7 +527,11 @@ impl Primitive {
             (Couple | Box, Some(n)) if n >= 0 => Signature::new(n as usize, 1),
             (Couple, None) => Signature::new(2, 1),
             (Box, None) => Signature::new(1, 1),
-            (Transpose | Sqrt | Round | Floor | Ceil | Rand | Utf8, _) => return self.sig(),
+            (
+                Transpose | Sqrt | Round | Floor | Ceil | Rand | Utf8 | Convolve | Signed
+                | Unsigned,
+                _,
+            ) => return self.sig(),
             (Stack, Some(n)) if n >= 0 => Signature::new(n as usize, n as usize),
             _ => return None,
         })
@@ -523,16 +562,62 @@ impl Primitive {
             self,
             (Reach | Backward | Above | Around)
                 | (Or | Base | Fft | Layout | Binary)
+                | (Ini | Dotenv)
+                | (Union | Intersection | Difference | SymDiff)
+                | Convolve
                 | Astar
-                | (Derivative | Integral)
-                | Sys(Ffi | MemCopy | MemFree | TlsListen | Breakpoint)
-                | (Stringify | Quote | Sig)
+                | Parallel
+                | Along
+                | Aggregate
+                | Decimal
+                | Interval
+                | Property
+                | Evaluate
+                | Cfg
+                | (Derivative | Integral | Solve | Minimize | Ode)
+                | (BitAnd | BitOr | BitXor | BitShift | PopCount | PackBits | Signed | Unsigned)
+                | Sys(Ffi
+                    | FfiCallback
+                    | MemCopy
+                    | MemFree
+                    | Plugin
+                    | Python
+                    | Wasm
+                    | TlsListen
+                    | Use
+                    | ReadChunks
+                    | Breakpoint)
+                | Sys(ClipWrite)
+                | Sys(Progress)
+                | Sys(WindowShow | WindowClose | WindowEvents | WindowFrame)
+                | Sys(Prompt | Password | Menu)
+                | Sys(DnsLookup | Ping | InterfaceAddrs)
+                | Sys(OscListen | OscSend | OscReceive)
+                | Sys(MqttConnect | MqttSubscribe | MqttPublish | MqttReceive)
+                | Sys(SmtpSend)
+                | Sys(KeyringGet | KeyringSet | KeyringDelete)
+                | Sys(GpioRead | GpioWrite | I2cTransfer)
+                | Sys(Chan | ChanSend | ChanRecv | ChanSelect | ChanClose)
+                | Sys(Atom | AtomLoad | AtomCas | AtomAdd | AtomFree)
+                | Sys(LogMessage | LogLevel | LogOutput)
+                | Sys(MetricRegister | MetricCounter | MetricGauge | MetricObserve | MetricExport)
+                | Sys(TermMove | TermClear | TermColor | TermCursor | TermMouse | TermEvent)
+                | (Stringify | Quote | Reflect | Reify | Sig | Invertible | Math)
         )
     }
     /// Check if this primitive is deprecated
     pub fn is_deprecated(&self) -> bool {
         self.deprecation_suggestion().is_some()
     }
+    /// Check if this dyadic primitive is [`Primitive::Anti`]-invertible on its own
+    ///
+    /// This compiles the primitive with [`Primitive::Anti`], so it is not particularly fast
+    pub fn is_anti_invertible(&self) -> bool {
+        self.args() == Some(2)
+            && (Compiler::new().experimental(true))
+                .load_str(&format!("{}{self}", Primitive::Anti))
+                .is_ok()
+    }
     /// Get the short aliases for this primitive
     pub fn aliases(&self) -> &'static [&'static str] {
         ALIASES.get(self).copied().unwrap_or_default()
@@ -776,10 +861,22 @@ impl Primitive {
             Primitive::Le => env.dyadic_oo_env(Value::other_is_le)?,
             Primitive::Gt => env.dyadic_oo_env(Value::other_is_gt)?,
             Primitive::Ge => env.dyadic_oo_env(Value::other_is_ge)?,
-            Primitive::Add => env.dyadic_oo_env(Value::add)?,
-            Primitive::Sub => env.dyadic_oo_env(Value::sub)?,
-            Primitive::Mul => env.dyadic_oo_env(Value::mul)?,
-            Primitive::Div => env.dyadic_oo_env(Value::div)?,
+            Primitive::Add => {
+                env.dyadic_oo_env(Value::add)?;
+                env.round_decimal_result();
+            }
+            Primitive::Sub => {
+                env.dyadic_oo_env(Value::sub)?;
+                env.round_decimal_result();
+            }
+            Primitive::Mul => {
+                env.dyadic_oo_env(Value::mul)?;
+                env.round_decimal_result();
+            }
+            Primitive::Div => {
+                env.dyadic_oo_env(Value::div)?;
+                env.round_decimal_result();
+            }
             Primitive::Modulus => env.dyadic_oo_env(Value::modulus)?,
             Primitive::Or => env.dyadic_oo_env(Value::or)?,
             Primitive::Pow => env.dyadic_oo_env(Value::pow)?,
@@ -788,6 +885,14 @@ impl Primitive {
             Primitive::Max => env.dyadic_oo_env(Value::max)?,
             Primitive::Atan => env.dyadic_oo_env(Value::atan2)?,
             Primitive::Complex => env.dyadic_oo_env(Value::complex)?,
+            Primitive::BitAnd => env.dyadic_oo_env(Value::bit_and)?,
+            Primitive::BitOr => env.dyadic_oo_env(Value::bit_or)?,
+            Primitive::BitXor => env.dyadic_oo_env(Value::bit_xor)?,
+            Primitive::BitShift => env.dyadic_oo_env(Value::bit_shift)?,
+            Primitive::PopCount => env.monadic_env(Value::popcount)?,
+            Primitive::Signed => env.monadic_mut_env(|val, env| val.wrap_int(32, true, env))?,
+            Primitive::Unsigned => env.monadic_mut_env(|val, env| val.wrap_int(32, false, env))?,
+            Primitive::PackBits => env.monadic_ref_env(Value::pack_bits)?,
             Primitive::Match => env.dyadic_rr(|a, b| a == b)?,
             Primitive::Join => env.dyadic_oo_env(|a, b, env| a.join(b, true, env))?,
             Primitive::Transpose => env.monadic_mut(Value::transpose)?,
@@ -818,6 +923,13 @@ impl Primitive {
             Primitive::Find => env.dyadic_rr_env(Value::find)?,
             Primitive::Mask => env.dyadic_rr_env(Value::mask)?,
             Primitive::IndexOf => env.dyadic_rr_env(Value::index_of)?,
+            Primitive::Union => env.dyadic_rr_env(Value::union)?,
+            Primitive::Intersection => env.dyadic_rr_env(Value::intersection)?,
+            Primitive::Difference => env.dyadic_rr_env(Value::difference)?,
+            Primitive::SymDiff => env.dyadic_rr_env(Value::sym_diff)?,
+            Primitive::Convolve => {
+                env.dyadic_rr_env(|k, a, env| k.convolve(a, Boundary::Fill, env))?
+            }
             Primitive::Box => {
                 let val = env.pop(1)?;
                 if val.box_nesting() > 1000 {
@@ -826,6 +938,7 @@ impl Primitive {
                 env.push(val.box_depth(0));
             }
             Primitive::Repr => env.monadic_ref(Value::representation)?,
+            Primitive::Evaluate => eval::evaluate(env)?,
             Primitive::Parse => env.monadic_ref_env(Value::parse_num)?,
             Primitive::Utf8 => env.monadic_ref_env(Value::utf8)?,
             Primitive::Graphemes => env.monadic_ref_env(Value::graphemes)?,
@@ -966,6 +1079,8 @@ impl Primitive {
             Primitive::Xlsx => {
                 env.monadic_ref_env(|value, env| value.to_xlsx(env).map(EcoVec::from))?
             }
+            Primitive::Ini => env.monadic_ref_env(encode::ini_encode)?,
+            Primitive::Dotenv => env.monadic_ref_env(encode::dotenv_encode)?,
             Primitive::ImageEncode => encode::image_encode(env)?,
             Primitive::GifEncode => encode::gif_encode(env)?,
             Primitive::AudioEncode => encode::audio_encode(env)?,
@@ -973,8 +1088,13 @@ impl Primitive {
             Primitive::Fft => algorithm::fft(env)?,
             Primitive::Stringify
             | Primitive::Quote
+            | Primitive::Reflect
+            | Primitive::Reify
             | Primitive::Sig
+            | Primitive::Invertible
+            | Primitive::Math
             | Primitive::Comptime
+            | Primitive::Cfg
             | Primitive::Un
             | Primitive::Anti
             | Primitive::Under
@@ -1020,6 +1140,10 @@ impl Primitive {
                 let [f] = get_ops(ops, env)?;
                 zip::rows(f, true, env)?
             }
+            Primitive::Along => {
+                let [f] = get_ops(ops, env)?;
+                zip::along(f, 1, env)?
+            }
             Primitive::Table => table::table(ops, env)?,
             Primitive::Repeat => loops::repeat(ops, false, false, env)?,
             Primitive::Do => loops::do_(ops, env)?,
@@ -1027,6 +1151,27 @@ impl Primitive {
                 let [f] = get_ops(ops, env)?;
                 loops::group(f, env)?
             }
+            Primitive::Aggregate => loops::aggregate(ops, env)?,
+            Primitive::Decimal => {
+                let [f] = get_ops(ops, env)?;
+                decimal::decimal(f, 2, env)?
+            }
+            Primitive::Interval => {
+                let [f] = get_ops(ops, env)?;
+                interval::interval(f, env)?
+            }
+            Primitive::Solve => {
+                let [f] = get_ops(ops, env)?;
+                solve::solve(f, env)?
+            }
+            Primitive::Minimize => {
+                let [f] = get_ops(ops, env)?;
+                solve::minimize(f, env)?
+            }
+            Primitive::Ode => {
+                let [f] = get_ops(ops, env)?;
+                ode::ode(f, env)?
+            }
             Primitive::Partition => {
                 let [f] = get_ops(ops, env)?;
                 loops::partition(f, env)?
@@ -1161,6 +1306,8 @@ impl Primitive {
                 let [f] = get_ops(ops, env)?;
                 env.spawn(f.sig.args, true, f)?;
             }
+            Primitive::Parallel => zip::parallel(ops, env)?,
+            Primitive::Property => property::property(ops, false, env)?,
             Primitive::Sys(op) => op.run_mod(ops, env)?,
             prim => {
                 return Err(env.error(if prim.modifier_args().is_some() {
@@ -1188,6 +1335,12 @@ impl ImplPrimitive {
             ImplPrimitive::DeshapeSub(i) => {
                 env.monadic_mut_env(|val, env| val.deshape_sub(*i, true, env))?
             }
+            &ImplPrimitive::SignedN(bits) => {
+                env.monadic_mut_env(|val, env| val.wrap_int(bits, true, env))?
+            }
+            &ImplPrimitive::UnsignedN(bits) => {
+                env.monadic_mut_env(|val, env| val.wrap_int(bits, false, env))?
+            }
             ImplPrimitive::Root => env.dyadic_oo_env(Value::root)?,
             ImplPrimitive::Cos => env.monadic_env(Value::cos)?,
             ImplPrimitive::Asin => env.monadic_env(Value::asin)?,
@@ -1210,10 +1363,17 @@ impl ImplPrimitive {
             }
             ImplPrimitive::UnWhere => env.monadic_ref_env(Value::unwhere)?,
             ImplPrimitive::Utf16 => env.monadic_ref_env(Value::utf16)?,
+            ImplPrimitive::ConvolveClamp => {
+                env.dyadic_rr_env(|k, a, env| k.convolve(a, Boundary::Clamp, env))?
+            }
+            ImplPrimitive::ConvolveWrap => {
+                env.dyadic_rr_env(|k, a, env| k.convolve(a, Boundary::Wrap, env))?
+            }
             ImplPrimitive::UnUtf8 => env.monadic_ref_env(Value::unutf8)?,
             ImplPrimitive::UnUtf16 => env.monadic_ref_env(Value::unutf16)?,
             ImplPrimitive::UnGraphemes => env.monadic_env(Value::ungraphemes)?,
             ImplPrimitive::UnBits => env.monadic_ref_env(Value::unbits)?,
+            ImplPrimitive::UnPackBits => env.monadic_ref_env(Value::unpack_bits)?,
             ImplPrimitive::AntiDrop => env.dyadic_ro_env(Value::anti_drop)?,
             ImplPrimitive::AntiSelect => env.dyadic_oo_env(Value::anti_select)?,
             ImplPrimitive::AntiPick => env.dyadic_oo_env(Value::anti_pick)?,
@@ -1303,6 +1463,16 @@ impl ImplPrimitive {
                 let val = Value::from_xlsx(&xlsx, env)?;
                 env.push(val);
             }
+            ImplPrimitive::UnIni => {
+                let ini = env.pop(1)?.as_string(env, "INI expects a string")?;
+                let val = encode::ini_decode(&ini, env)?;
+                env.push(val);
+            }
+            ImplPrimitive::UnDotenv => {
+                let dotenv = env.pop(1)?.as_string(env, "Dotenv expects a string")?;
+                let val = encode::dotenv_decode(&dotenv, env)?;
+                env.push(val);
+            }
             ImplPrimitive::UnFft => algorithm::unfft(env)?,
             ImplPrimitive::UnDatetime => env.monadic_ref_env(Value::undatetime)?,
             ImplPrimitive::ProgressiveIndexOf => env.dyadic_rr_env(Value::progressive_index_of)?,
@@ -1327,6 +1497,27 @@ impl ImplPrimitive {
                 env.push(val.undo_un_bits(&orig_shape, env)?);
             }
             ImplPrimitive::AntiBase => env.dyadic_rr_env(Value::antibase)?,
+            ImplPrimitive::AntiModulus => {
+                let modulus = env.pop(1)?;
+                let val = env.pop(2)?;
+                let rem = modulus.clone().modulus(val.clone(), env)?;
+                if rem.is_eq(val.clone(), env)?.all_true() {
+                    env.push(val);
+                    return Ok(());
+                }
+                let message = if modulus.rank() <= 1 && modulus.row_count() <= 10 {
+                    format!(
+                        "Not all values are possible results of {} {modulus}",
+                        Primitive::Modulus
+                    )
+                } else {
+                    format!(
+                        "Not all values are possible results of {}",
+                        Primitive::Modulus
+                    )
+                };
+                return Err(env.error(format!("Pattern match failed: {message}")));
+            }
             &ImplPrimitive::UndoReverse { n, all } => {
                 env.require_height(n)?;
                 let end = env.stack_height() - n;
@@ -1669,6 +1860,12 @@ impl ImplPrimitive {
                 }
                 env.rt.test_results.push(res);
             }
+            ImplPrimitive::RecordLine => {
+                if let Span::Code(span) = env.span() {
+                    let stack = env.rt.stack.clone();
+                    env.rt.line_history.push((span, stack));
+                }
+            }
             ImplPrimitive::ValidateNonBoxedVariant => {
                 let val = env.pop(1)?;
                 if !matches!(val, Value::Num(_) | Value::Byte(_) | Value::Box(_)) {
@@ -1761,6 +1958,7 @@ impl ImplPrimitive {
             ImplPrimitive::RepeatCountConvergence => loops::repeat(ops, false, true, env)?,
             ImplPrimitive::UnScan => reduce::unscan(ops, env)?,
             ImplPrimitive::UnDump => dump(ops, env, true)?,
+            ImplPrimitive::TestProperty => property::property(ops, true, env)?,
             ImplPrimitive::UnFill => fill!(ops, env, with_unfill, without_unfill_but),
             ImplPrimitive::ReduceTable => table::reduce_table(ops, env)?,
             ImplPrimitive::UnBoth => {
@@ -1789,6 +1987,18 @@ impl ImplPrimitive {
                 let [f] = get_ops(ops, env)?;
                 loops::split_by(f, false, true, env)?;
             }
+            &ImplPrimitive::AlongSub(n) => {
+                let [f] = get_ops(ops, env)?;
+                zip::along(f, n, env)?
+            }
+            &ImplPrimitive::DecimalSub(places) => {
+                let [f] = get_ops(ops, env)?;
+                decimal::decimal(f, places, env)?
+            }
+            ImplPrimitive::AutoDerivative => {
+                let [f] = get_ops(ops, env)?;
+                dual::derivative(f, env)?
+            }
             &ImplPrimitive::EachSub(n) => {
                 let [f] = get_ops(ops, env)?;
                 let sig = f.sig;
@@ -2730,4 +2940,35 @@ mod tests {
 
         std::fs::write("uiua.tmLanguage.json", text).expect("Failed to write grammar file");
     }
+
+    #[test]
+    fn spoken_names() {
+        use crate::*;
+        assert_eq!(Primitive::Reduce.spoken_name(), "reduce");
+        assert_eq!(Primitive::Sys(SysOp::Prin).spoken_name(), "print and flush");
+        assert_eq!(
+            Primitive::Sys(SysOp::AudioStream).spoken_name(),
+            "audio stream"
+        );
+        for prim in Primitive::all() {
+            let name = prim.spoken_name();
+            assert!(!name.is_empty() && !name.starts_with('&'), "{name}");
+        }
+    }
+
+    #[test]
+    fn anti_invertible_primitives() {
+        use crate::*;
+        for prim in [
+            Primitive::Add,
+            Primitive::Modulus,
+            Primitive::Base,
+            Primitive::Min,
+        ] {
+            assert!(prim.is_anti_invertible(), "{prim} is not anti-invertible");
+        }
+        for prim in [Primitive::Find, Primitive::Reverse, Primitive::Under] {
+            assert!(!prim.is_anti_invertible(), "{prim} is anti-invertible");
+        }
+    }
 }
@@ -14,6 +14,10 @@ use std::{
 };
 
 use crossbeam_channel::{Receiver, Sender, TryRecvError};
+#[cfg(not(target_arch = "wasm32"))]
+use once_cell::sync::Lazy;
+#[cfg(not(target_arch = "wasm32"))]
+use parking_lot::Mutex;
 use thread_local::ThreadLocal;
 
 use crate::{
@@ -21,6 +25,8 @@ use crate::{
     fill::Fill,
     invert::match_format_pattern,
     lex::Span,
+    sys::{SharedAtomicCells, SharedChannels, SharedLogger},
+    wasm::SharedWasmInstances,
     Array, ArrayLen, Assembly, BindingKind, BindingMeta, Boxed, CodeSpan, Compiler, Function,
     FunctionId, Ident, Inputs, IntoSysBackend, LocalName, Node, Primitive, Report, SafeSys,
     SigNode, Signature, SysBackend, TraceFrame, UiuaError, UiuaErrorKind, UiuaResult, Value,
@@ -54,12 +60,16 @@ pub(crate) struct Runtime {
     fill_boundary_stack: Vec<(usize, usize)>,
     /// The depth of arrays under construction
     pub(crate) array_depth: usize,
+    /// The number of decimal places to round arithmetic to
+    pub(crate) decimal_places: Option<u8>,
     /// A limit on the execution duration in milliseconds
     pub(crate) execution_limit: Option<f64>,
     /// The time at which execution started
     pub(crate) execution_start: f64,
     /// The recursion limit
     recursion_limit: usize,
+    /// The maximum size of a single array in bytes
+    pub(crate) array_size_limit: Option<usize>,
     /// Whether the program was interrupted
     pub(crate) interrupted: Option<Arc<dyn Fn() -> bool + Send + Sync>>,
     /// Whether to print the time taken to execute each instruction
@@ -82,8 +92,18 @@ pub(crate) struct Runtime {
     pub(crate) output_comments: HashMap<usize, Vec<Vec<Value>>>,
     /// Memoized values
     pub(crate) memo: Arc<ThreadLocal<RefCell<MemoMap>>>,
+    /// The logging configuration
+    pub(crate) logger: SharedLogger,
+    /// Channels for communicating between threads
+    pub(crate) channels: SharedChannels,
+    /// Atomic cells for sharing state between threads
+    pub(crate) atomic_cells: SharedAtomicCells,
+    /// Instantiated WASM modules
+    pub(crate) wasm_instances: SharedWasmInstances,
     /// The results of tests
     pub(crate) test_results: Vec<UiuaResult>,
+    /// The stack after each recorded top-level line
+    pub(crate) line_history: Vec<(CodeSpan, Vec<Value>)>,
     /// Reports to print
     pub(crate) reports: Vec<Report>,
 }
@@ -140,10 +160,7 @@ impl Default for ThisThread {
 
 #[derive(Debug, Clone)]
 struct Thread {
-    #[cfg(not(target_arch = "wasm32"))]
     pub recv: Receiver<UiuaResult<Vec<Value>>>,
-    #[cfg(target_arch = "wasm32")]
-    pub result: UiuaResult<Vec<Value>>,
     pub channel: Channel,
 }
 
@@ -193,6 +210,7 @@ impl Default for Runtime {
             fill_boundary_stack: Vec::new(),
             unfill_stack: Vec::new(),
             array_depth: 0,
+            decimal_places: None,
             backend: Arc::new(SafeSys::default()),
             time_instrs: false,
             last_time: 0.0,
@@ -200,6 +218,7 @@ impl Default for Runtime {
             cli_file_path: PathBuf::new(),
             execution_limit: None,
             execution_start: 0.0,
+            array_size_limit: None,
             #[cfg(debug_assertions)]
             recursion_limit: 20,
             #[cfg(not(debug_assertions))]
@@ -211,8 +230,13 @@ impl Default for Runtime {
             thread: ThisThread::default(),
             output_comments: HashMap::new(),
             memo: Arc::new(ThreadLocal::new()),
+            logger: SharedLogger::default(),
+            channels: SharedChannels::default(),
+            atomic_cells: SharedAtomicCells::default(),
+            wasm_instances: SharedWasmInstances::default(),
             unevaluated_constants: HashMap::new(),
             test_results: Vec::new(),
+            line_history: Vec::new(),
             reports: Vec::new(),
         }
     }
@@ -294,6 +318,13 @@ impl Uiua {
         self.rt.recursion_limit = limit;
         self
     }
+    /// Limit the size of each array that is created
+    ///
+    /// This does not limit the total memory used, and some ways of building up an array are not checked.
+    pub fn with_array_size_limit(mut self, bytes: usize) -> Self {
+        self.rt.array_size_limit = Some(bytes);
+        self
+    }
     /// Set the interrupted hook
     pub fn with_interrupt_hook(mut self, hook: impl Fn() -> bool + Send + Sync + 'static) -> Self {
         self.rt.interrupted = Some(Arc::new(hook));
@@ -474,9 +505,9 @@ impl Uiua {
             Node::Run(nodes) => nodes.into_iter().try_for_each(|node| self.exec(node)),
             Node::Prim(prim, span) => self.with_prim_span(span, Some(prim), |env| prim.run(env)),
             Node::ImplPrim(prim, span) => self.with_span(span, |env| prim.run(env)),
-            Node::Mod(prim, args, span) => {
-                self.with_prim_span(span, Some(prim), |env| prim.run_mod(args, env))
-            }
+            Node::Mod(prim, args, span) => self
+                .with_prim_span(span, Some(prim), |env| prim.run_mod(args, env))
+                .map_err(|err| self.trace_modifier(err, prim, span)),
             Node::ImplMod(prim, args, span) => self.with_span(span, |env| prim.run_mod(args, env)),
             Node::Push(val) => {
                 self.rt.stack.push(val);
@@ -756,6 +787,18 @@ impl Uiua {
         }
         Ok(())
     }
+    /// Check that an array would not be larger than the array size limit
+    pub(crate) fn respect_array_size_limit(&self, elements: usize, elem_size: usize) -> UiuaResult {
+        if let Some(limit) = self.rt.array_size_limit {
+            if elements.saturating_mul(elem_size) > limit {
+                return Err(self.error(format!(
+                    "Array of {elements} elements would be larger \
+                    than the limit of {limit} bytes"
+                )));
+            }
+        }
+        Ok(())
+    }
     pub(crate) fn with_span<T>(
         &mut self,
         span: usize,
@@ -893,6 +936,21 @@ impl Uiua {
         }
         Ok(())
     }
+    /// Add a modifier to the trace of an error that came from its operands
+    fn trace_modifier(&self, mut err: UiuaError, prim: Primitive, span: usize) -> UiuaError {
+        let span = self.asm.spans[span].clone();
+        let from_modifier = match &err.kind {
+            UiuaErrorKind::Run { message, .. } => message.span == span,
+            UiuaErrorKind::Throw(_, thrown, _) => *thrown == span,
+            UiuaErrorKind::Timeout(..) | UiuaErrorKind::Interrupted => true,
+            _ => false,
+        };
+        if !(from_modifier && err.trace.is_empty()) {
+            let id = Some(FunctionId::Primitive(prim));
+            err.trace.push(TraceFrame { id, span });
+        }
+        err
+    }
     pub(crate) fn span_index(&self) -> usize {
         self.rt.call_stack.last().map_or(0, |frame| {
             (frame.spans.last())
@@ -993,6 +1051,14 @@ impl Uiua {
         self.pop_convert(Value::as_string)
     }
     /// Simulates popping a value and immediately pushing it back
+    /// Round the top of the stack if decimal arithmetic is enabled
+    pub(crate) fn round_decimal_result(&mut self) {
+        if let Some(places) = self.rt.decimal_places {
+            if let Some(val) = self.rt.stack.last_mut() {
+                val.round_decimal(places);
+            }
+        }
+    }
     pub(crate) fn touch_stack(&self, n: usize) -> UiuaResult {
         self.require_height(n).map(drop)
     }
@@ -1021,6 +1087,7 @@ impl Uiua {
             let elems: usize = values.iter().map(Value::element_count).sum();
             let elem_size = values.first().map_or(size_of::<f64>(), Value::elem_size);
             validate_size_impl(elem_size, [elems]).map_err(|e| self.error(e))?;
+            self.respect_array_size_limit(elems, elem_size)?;
             Value::from_row_values(values, self)?
         };
         self.push(val);
@@ -1048,6 +1115,12 @@ impl Uiua {
         let under = take(&mut self.rt.under_stack);
         (stack, under)
     }
+    /// Take the stack after each recorded top-level line
+    ///
+    /// Lines are only recorded if they were compiled with [`Compiler::record_lines`].
+    pub fn take_line_history(&mut self) -> Vec<(CodeSpan, Vec<Value>)> {
+        take(&mut self.rt.line_history)
+    }
     /// Take some values from the stack
     pub fn take_n(&mut self, n: usize) -> UiuaResult<Vec<Value>> {
         let height = self.require_height(n)?;
@@ -1380,33 +1453,12 @@ impl Uiua {
             Ok(())
         }
     }
-    /// Spawn a thread
-    pub(crate) fn spawn(&mut self, capture_count: usize, _pool: bool, f: SigNode) -> UiuaResult {
-        if !self.rt.backend.allow_thread_spawning() {
-            return Err(self.error("Thread spawning is not allowed in this environment"));
-        }
-        if self.rt.stack.len() < capture_count {
-            return Err(self.error(format!(
-                "Expected at least {} value(s) on the stack, but there are {}",
-                capture_count,
-                self.rt.stack.len()
-            )))?;
-        }
-        let (this_send, child_recv) = crossbeam_channel::unbounded();
-        let (child_send, this_recv) = crossbeam_channel::unbounded();
-        let thread = ThisThread {
-            parent: Some(Channel {
-                send: child_send,
-                recv: child_recv,
-            }),
-            ..ThisThread::default()
-        };
-        let mut env = Uiua {
+    /// Create the environment for a new thread
+    fn thread_env(&self, stack: Vec<Value>, thread: ThisThread) -> Uiua {
+        Uiua {
             asm: self.asm.clone(),
             rt: Runtime {
-                stack: (self.rt.stack)
-                    .drain(self.rt.stack.len() - capture_count..)
-                    .collect(),
+                stack,
                 under_stack: Vec::new(),
                 fill_stack: Vec::new(),
                 fill_boundary_stack: Vec::new(),
@@ -1414,6 +1466,7 @@ impl Uiua {
                 recur_stack: self.rt.recur_stack.clone(),
                 call_stack: Vec::from_iter(self.rt.call_stack.last().cloned()),
                 array_depth: 0,
+                decimal_places: self.rt.decimal_places,
                 time_instrs: self.rt.time_instrs,
                 last_time: self.rt.last_time,
                 cli_arguments: self.rt.cli_arguments.clone(),
@@ -1422,15 +1475,47 @@ impl Uiua {
                 execution_limit: self.rt.execution_limit,
                 execution_start: self.rt.execution_start,
                 recursion_limit: self.rt.recursion_limit,
+                array_size_limit: self.rt.array_size_limit,
                 interrupted: self.rt.interrupted.clone(),
                 output_comments: HashMap::new(),
                 memo: self.rt.memo.clone(),
+                logger: self.rt.logger.clone(),
+                channels: self.rt.channels.clone(),
+                atomic_cells: self.rt.atomic_cells.clone(),
+                wasm_instances: self.rt.wasm_instances.clone(),
                 unevaluated_constants: HashMap::new(),
                 test_results: Vec::new(),
+                line_history: Vec::new(),
                 reports: Vec::new(),
                 thread,
             },
+        }
+    }
+    /// Spawn a thread
+    pub(crate) fn spawn(&mut self, capture_count: usize, _pool: bool, f: SigNode) -> UiuaResult {
+        if !self.rt.backend.allow_thread_spawning() {
+            return Err(self.error("Thread spawning is not allowed in this environment"));
+        }
+        if self.rt.stack.len() < capture_count {
+            return Err(self.error(format!(
+                "Expected at least {} value(s) on the stack, but there are {}",
+                capture_count,
+                self.rt.stack.len()
+            )))?;
+        }
+        let (this_send, child_recv) = crossbeam_channel::unbounded();
+        let (child_send, this_recv) = crossbeam_channel::unbounded();
+        let thread = ThisThread {
+            parent: Some(Channel {
+                send: child_send,
+                recv: child_recv,
+            }),
+            ..ThisThread::default()
         };
+        let stack = (self.rt.stack)
+            .drain(self.rt.stack.len() - capture_count..)
+            .collect();
+        let mut env = self.thread_env(stack, thread);
         #[cfg(not(target_arch = "wasm32"))]
         let recv = {
             let (send, recv) = crossbeam_channel::unbounded();
@@ -1443,18 +1528,26 @@ impl Uiua {
             }
             recv
         };
+        // Threads are spawned by the backend if it can, and run immediately otherwise
         #[cfg(target_arch = "wasm32")]
-        let result = env.exec(f).map(|_| env.take_stack());
+        let recv = {
+            let (send, recv) = crossbeam_channel::unbounded();
+            let run = move || _ = send.send(env.exec(f).map(|_| env.take_stack()));
+            if self.rt.backend.can_spawn_threads() {
+                (self.rt.backend.spawn_thread(Box::new(run)))
+                    .map_err(|e| self.error(format!("Error spawning thread: {e}")))?;
+            } else {
+                run();
+            }
+            recv
+        };
 
         let id = self.rt.thread.next_child_id;
         self.rt.thread.next_child_id += 1;
         self.rt.thread.children.insert(
             id,
             Thread {
-                #[cfg(not(target_arch = "wasm32"))]
                 recv,
-                #[cfg(target_arch = "wasm32")]
-                result,
                 channel: Channel {
                     send: this_send,
                     recv: this_recv,
@@ -1464,12 +1557,88 @@ impl Uiua {
         self.push(id);
         Ok(())
     }
+    /// Run a function on each of a list of argument stacks using a bounded number of worker threads
+    ///
+    /// Results are returned in the same order as the jobs.
+    /// There are never more workers than jobs or than [`MAX_WORKERS_PER_CORE`] per core.
+    /// In the web editor, the jobs are run one after another unless the backend can spawn threads.
+    pub(crate) fn run_parallel(
+        &self,
+        workers: usize,
+        f: &SigNode,
+        jobs: Vec<Vec<Value>>,
+    ) -> UiuaResult<Vec<Vec<Value>>> {
+        let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let workers = (workers.min(jobs.len()))
+            .min(cores * MAX_WORKERS_PER_CORE)
+            .max(1);
+        let template = self.thread_env(Vec::new(), ThisThread::default());
+        #[cfg(target_arch = "wasm32")]
+        {
+            if workers <= 1 || !self.rt.backend.can_spawn_threads() {
+                let mut env = template;
+                return jobs
+                    .into_iter()
+                    .map(|job| {
+                        env.rt.stack = job;
+                        env.exec(f.clone())?;
+                        Ok(env.take_stack())
+                    })
+                    .collect();
+            }
+            // Workers take jobs from a shared queue, and the results are put back in order
+            let job_count = jobs.len();
+            let (job_send, job_recv) = crossbeam_channel::unbounded();
+            for job in jobs.into_iter().enumerate() {
+                _ = job_send.send(job);
+            }
+            drop(job_send);
+            let (send, recv) = crossbeam_channel::unbounded();
+            let mut template = Some(template);
+            for _ in 0..workers.min(job_count) {
+                let mut env = (template.take())
+                    .unwrap_or_else(|| self.thread_env(Vec::new(), ThisThread::default()));
+                let (job_recv, send, f) = (job_recv.clone(), send.clone(), f.clone());
+                let run = move || {
+                    for (i, job) in job_recv {
+                        env.rt.stack = job;
+                        let result = env.exec(f.clone()).map(|_| env.take_stack());
+                        if send.send((i, result)).is_err() {
+                            break;
+                        }
+                    }
+                };
+                (self.rt.backend.spawn_thread(Box::new(run)))
+                    .map_err(|e| self.error(format!("Error spawning thread: {e}")))?;
+            }
+            drop(send);
+            let mut results = vec![Vec::new(); job_count];
+            for (i, result) in recv {
+                results[i] = result?;
+            }
+            Ok(results)
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            use rayon::prelude::*;
+            let pool = thread_pool(workers)
+                .map_err(|e| self.error(format!("Error creating thread pool: {e}")))?;
+            pool.install(|| {
+                jobs.into_par_iter()
+                    .map_with(template, |env, job| {
+                        env.rt.stack = job;
+                        env.exec(f.clone())?;
+                        Ok(env.take_stack())
+                    })
+                    .collect()
+            })
+        }
+    }
     /// Wait for a thread to finish
     pub(crate) fn wait(&mut self, id: Value) -> UiuaResult {
         let ids = id.as_natural_array(self, "Thread id must be an array of natural numbers")?;
         if ids.shape.is_empty() {
             let handle = ids.data[0];
-            #[cfg(not(target_arch = "wasm32"))]
             let mut thread_stack = self
                 .rt
                 .thread
@@ -1479,14 +1648,6 @@ impl Uiua {
                 .recv
                 .recv()
                 .unwrap()?;
-            #[cfg(target_arch = "wasm32")]
-            let mut thread_stack = self
-                .rt
-                .thread
-                .children
-                .remove(&handle)
-                .ok_or_else(|| self.error("Invalid thread id"))?
-                .result?;
             match thread_stack.len() {
                 0 => self.push(Value::default()),
                 1 => self.push(thread_stack.into_iter().next().unwrap()),
@@ -1498,7 +1659,6 @@ impl Uiua {
         } else {
             let mut rows = Vec::new();
             for handle in ids.data {
-                #[cfg(not(target_arch = "wasm32"))]
                 let mut thread_stack = self
                     .rt
                     .thread
@@ -1508,14 +1668,6 @@ impl Uiua {
                     .recv
                     .recv()
                     .unwrap()?;
-                #[cfg(target_arch = "wasm32")]
-                let mut thread_stack = self
-                    .rt
-                    .thread
-                    .children
-                    .remove(&handle)
-                    .ok_or_else(|| self.error("Invalid thread id"))?
-                    .result?;
                 let row = if thread_stack.len() == 1 {
                     thread_stack.into_iter().next().unwrap()
                 } else {
@@ -1533,7 +1685,7 @@ impl Uiua {
         Ok(())
     }
     pub(crate) fn send(&self, id: Value, value: Value) -> UiuaResult {
-        if cfg!(target_arch = "wasm32") {
+        if cfg!(target_arch = "wasm32") && !self.rt.backend.can_spawn_threads() {
             return Err(self.error("send is not supported in this environment"));
         }
         let ids = id.as_natural_array(self, "Thread id must be an array of natural numbers")?;
@@ -1546,7 +1698,7 @@ impl Uiua {
         Ok(())
     }
     pub(crate) fn recv(&mut self, id: Value) -> UiuaResult {
-        if cfg!(target_arch = "wasm32") {
+        if cfg!(target_arch = "wasm32") && !self.rt.backend.can_spawn_threads() {
             return Err(self.error("recv is not supported in this environment"));
         }
         let ids = id.as_natural_array(self, "Thread id must be an array of natural numbers")?;
@@ -1568,7 +1720,7 @@ impl Uiua {
         Ok(())
     }
     pub(crate) fn try_recv(&mut self, id: Value) -> UiuaResult {
-        if cfg!(target_arch = "wasm32") {
+        if cfg!(target_arch = "wasm32") && !self.rt.backend.can_spawn_threads() {
             return Err(self.error("try_recv is not supported in this environment"));
         }
         let id = id.as_nat(self, "Thread id must be a natural number")?;
@@ -1661,3 +1813,62 @@ where
         self().arg_name()
     }
 }
+
+/// The maximum number of workers per core that [`Uiua::run_parallel`] will use
+const MAX_WORKERS_PER_CORE: usize = 4;
+
+/// The maximum number of thread pools that are kept for reuse
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_CACHED_POOLS: usize = 4;
+
+/// Get a thread pool with the given number of threads
+///
+/// Pools are reused so that running jobs in parallel does not spawn new threads every time.
+/// Only the most recently used pools are kept. Evicted pools shut down once their jobs are done.
+#[cfg(not(target_arch = "wasm32"))]
+fn thread_pool(threads: usize) -> Result<Arc<rayon::ThreadPool>, rayon::ThreadPoolBuildError> {
+    type Pools = Vec<(usize, Arc<rayon::ThreadPool>)>;
+    static POOLS: Lazy<Mutex<Pools>> = Lazy::new(Default::default);
+    let mut pools = POOLS.lock();
+    // The most recently used pool is at the end
+    if let Some(i) = pools.iter().position(|(n, _)| *n == threads) {
+        let entry = pools.remove(i);
+        let pool = entry.1.clone();
+        pools.push(entry);
+        return Ok(pool);
+    }
+    let pool = Arc::new(
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()?,
+    );
+    if pools.len() == MAX_CACHED_POOLS {
+        pools.remove(0);
+    }
+    pools.push((threads, pool.clone()));
+    Ok(pool)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn line_history() {
+        use crate::*;
+        let mut comp = Compiler::new();
+        comp.record_lines(true);
+        comp.load_str("1 2\nX ← 3\n+\n×X").unwrap();
+        let mut env = Uiua::with_safe_sys();
+        env.run_compiler(&mut comp).unwrap();
+        let history: Vec<_> = (env.take_line_history().into_iter())
+            .map(|(span, stack)| (span.start.line, stack))
+            .collect();
+        assert_eq!(
+            history,
+            [
+                (1, vec![2.into(), 1.into()]),
+                (3, vec![3.into()]),
+                (4, vec![9.into()])
+            ]
+        );
+    }
+}
@@ -0,0 +1,101 @@
+//! Saving and restoring interpreter state
+
+use rand::Rng;
+use serde::*;
+
+use crate::{primitive::RNG, seed_random, Assembly, CodeSpan, Uiua, Value, VERSION};
+
+/// A saved interpreter state
+///
+/// This contains the interpreter's assembly, including the values of its bindings,
+/// the stack, and the seed of the random number generator.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    version: String,
+    asm: String,
+    /// Binding spans are not stored in the assembly's serialization
+    binding_spans: Vec<CodeSpan>,
+    stack: Vec<Value>,
+    seed: u64,
+}
+
+impl Snapshot {
+    /// Get the values on the snapshot's stack, from bottom to top
+    pub fn stack(&self) -> &[Value] {
+        &self.stack
+    }
+    /// Serialize the snapshot into bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap()
+    }
+    /// Deserialize a snapshot from bytes
+    ///
+    /// Snapshots can only be loaded by the version of Uiua that created them.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let snapshot: Snapshot =
+            serde_json::from_slice(bytes).map_err(|e| format!("Invalid snapshot: {e}"))?;
+        if snapshot.version != VERSION {
+            return Err(format!(
+                "Snapshot was created by Uiua {}, but this is Uiua {VERSION}",
+                snapshot.version
+            ));
+        }
+        Ok(snapshot)
+    }
+}
+
+impl Uiua {
+    /// Save the interpreter's state
+    ///
+    /// The random number generator is reseeded so that random numbers
+    /// generated after restoring the snapshot are the same as those
+    /// generated after taking it.
+    pub fn snapshot(&self) -> Snapshot {
+        let seed = RNG.with_borrow_mut(|rng| rng.gen());
+        seed_random(seed);
+        Snapshot {
+            version: VERSION.into(),
+            asm: self.asm.to_uasm(),
+            binding_spans: (self.asm.bindings.iter())
+                .map(|binding| binding.span.clone())
+                .collect(),
+            stack: self.stack().to_vec(),
+            seed,
+        }
+    }
+    /// Restore the interpreter's state from a [`Snapshot`]
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), String> {
+        let mut asm = Assembly::from_uasm(&snapshot.asm)?;
+        for (binding, span) in asm
+            .bindings
+            .make_mut()
+            .iter_mut()
+            .zip(&snapshot.binding_spans)
+        {
+            binding.span = span.clone();
+        }
+        self.asm = asm;
+        self.rt.stack = snapshot.stack.clone();
+        seed_random(snapshot.seed);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn snapshot_round_trip() {
+        use crate::*;
+        let mut env = Uiua::with_safe_sys();
+        env.run_str("X ← ⚂\n[1 2 3] \"hi\" ℂ1 2 {5 ⊚3} X").unwrap();
+        let snapshot = Snapshot::from_bytes(&env.snapshot().to_bytes()).unwrap();
+        let x = env.bound_values()["X"].clone();
+        let stack = env.take_stack();
+        let after = random();
+        let mut restored = Uiua::with_safe_sys();
+        restored.restore(&snapshot).unwrap();
+        assert_eq!(restored.stack(), stack);
+        assert_eq!(restored.bound_values()["X"], x);
+        assert_eq!(random(), after);
+    }
+}
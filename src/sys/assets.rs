@@ -0,0 +1,437 @@
+//! A system backend that serves embedded assets to compile-time code
+
+use std::{
+    any::Any,
+    net::{IpAddr, SocketAddr},
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+#[cfg(feature = "image")]
+use image::DynamicImage;
+
+use super::{
+    AudioStreamFn, Email, GitTarget, Handle, MemoryBackend, MqttMessage, MqttOptions,
+    ReadLinesReturnFn, SmtpAuth, SmtpServer, SysBackend, TermCommand, TermEvent, WebcamImage,
+    WindowEvent,
+};
+use crate::{FfiCallbackFn, FfiType, Uiua, Value};
+
+/// A system backend that reads files from a set of assets before deferring to another backend
+///
+/// Relative paths that are not assets are read relative to a base directory if they exist there,
+/// so that code run at compile time can read files next to the source file that contains it.
+pub(crate) struct AssetSys {
+    inner: Arc<dyn SysBackend>,
+    assets: Arc<MemoryBackend>,
+    base: Option<PathBuf>,
+}
+
+impl AssetSys {
+    /// Wrap a backend with a set of assets and a base directory
+    pub fn new(
+        inner: Arc<dyn SysBackend>,
+        assets: Arc<MemoryBackend>,
+        base: Option<PathBuf>,
+    ) -> Self {
+        AssetSys {
+            inner,
+            assets,
+            base,
+        }
+    }
+    /// Check if a path is an asset or a directory of assets
+    fn is_asset(&self, path: &str) -> bool {
+        let path = Path::new(path);
+        path.components()
+            .any(|comp| matches!(comp, Component::Normal(_)))
+            && self.assets.file_exists(&path.to_string_lossy())
+    }
+    /// Resolve a path that is not an asset against the base directory
+    fn resolve(&self, path: &str) -> String {
+        if let Some(base) = &self.base {
+            if Path::new(path).is_relative() {
+                let joined = base.join(path).to_string_lossy().into_owned();
+                if self.inner.file_exists(&joined) {
+                    return joined;
+                }
+            }
+        }
+        path.into()
+    }
+}
+
+impl SysBackend for AssetSys {
+    fn any(&self) -> &dyn Any {
+        self
+    }
+    fn any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn save_error_color(&self, message: String, colored: String) {
+        self.inner.save_error_color(message, colored)
+    }
+    fn print_str_stdout(&self, s: &str) -> Result<(), String> {
+        self.inner.print_str_stdout(s)
+    }
+    fn print_str_stderr(&self, s: &str) -> Result<(), String> {
+        self.inner.print_str_stderr(s)
+    }
+    fn print_str_trace(&self, s: &str) {
+        self.inner.print_str_trace(s)
+    }
+    fn show(&self, value: Value) -> Result<(), String> {
+        self.inner.show(value)
+    }
+    fn scan_line_stdin(&self) -> Result<Option<String>, String> {
+        self.inner.scan_line_stdin()
+    }
+    fn prompt(&self, prompt: &str, hidden: bool) -> Result<Option<String>, String> {
+        self.inner.prompt(prompt, hidden)
+    }
+    fn scan_stdin(&self, count: Option<usize>) -> Result<Vec<u8>, String> {
+        self.inner.scan_stdin(count)
+    }
+    fn scan_until_stdin(&self, delim: &[u8]) -> Result<Vec<u8>, String> {
+        self.inner.scan_until_stdin(delim)
+    }
+    fn set_raw_mode(&self, raw_mode: bool) -> Result<(), String> {
+        self.inner.set_raw_mode(raw_mode)
+    }
+    fn get_raw_mode(&self) -> Result<bool, String> {
+        self.inner.get_raw_mode()
+    }
+    fn var(&self, name: &str) -> Option<String> {
+        self.inner.var(name)
+    }
+    fn term_size(&self) -> Result<(usize, usize), String> {
+        self.inner.term_size()
+    }
+    fn term_command(&self, command: TermCommand) -> Result<(), String> {
+        self.inner.term_command(command)
+    }
+    fn term_event(&self, timeout: Option<Duration>) -> Result<Option<TermEvent>, String> {
+        self.inner.term_event(timeout)
+    }
+    fn exit(&self, status: i32) -> Result<(), String> {
+        self.inner.exit(status)
+    }
+    fn file_exists(&self, path: &str) -> bool {
+        self.is_asset(path) || self.inner.file_exists(&self.resolve(path))
+    }
+    fn list_dir(&self, path: &str) -> Result<Vec<String>, String> {
+        if self.is_asset(path) {
+            self.assets.list_dir(path)
+        } else {
+            self.inner.list_dir(&self.resolve(path))
+        }
+    }
+    fn is_file(&self, path: &str) -> Result<bool, String> {
+        if self.is_asset(path) {
+            self.assets.is_file(path)
+        } else {
+            self.inner.is_file(&self.resolve(path))
+        }
+    }
+    fn delete(&self, path: &str) -> Result<(), String> {
+        self.inner.delete(path)
+    }
+    fn trash(&self, path: &str) -> Result<(), String> {
+        self.inner.trash(path)
+    }
+    fn read(&self, handle: Handle, count: usize) -> Result<Vec<u8>, String> {
+        self.inner.read(handle, count)
+    }
+    fn read_all(&self, handle: Handle) -> Result<Vec<u8>, String> {
+        self.inner.read_all(handle)
+    }
+    fn read_until(&self, handle: Handle, delim: &[u8]) -> Result<Vec<u8>, String> {
+        self.inner.read_until(handle, delim)
+    }
+    fn read_lines<'a>(&self, handle: Handle) -> Result<ReadLinesReturnFn<'a>, String> {
+        self.inner.read_lines(handle)
+    }
+    fn write(&self, handle: Handle, contents: &[u8]) -> Result<(), String> {
+        self.inner.write(handle, contents)
+    }
+    fn create_file(&self, path: &Path) -> Result<Handle, String> {
+        self.inner.create_file(path)
+    }
+    fn open_file(&self, path: &Path, write: bool) -> Result<Handle, String> {
+        if write {
+            self.inner.open_file(path, write)
+        } else {
+            let path = self.resolve(&path.to_string_lossy());
+            self.inner.open_file(path.as_ref(), write)
+        }
+    }
+    fn make_dir(&self, path: &Path) -> Result<(), String> {
+        self.inner.make_dir(path)
+    }
+    fn file_read_all(&self, path: &Path) -> Result<Vec<u8>, String> {
+        if let Some(contents) = self.assets.file(path) {
+            Ok(contents)
+        } else {
+            let path = self.resolve(&path.to_string_lossy());
+            self.inner.file_read_all(path.as_ref())
+        }
+    }
+    fn file_write_all(&self, path: &Path, contents: &[u8]) -> Result<(), String> {
+        self.inner.file_write_all(path, contents)
+    }
+    fn file_append(&self, path: &Path, contents: &[u8]) -> Result<(), String> {
+        self.inner.file_append(path, contents)
+    }
+    fn clipboard(&self) -> Result<String, String> {
+        self.inner.clipboard()
+    }
+    fn set_clipboard(&self, contents: &str) -> Result<(), String> {
+        self.inner.set_clipboard(contents)
+    }
+    fn sleep(&self, seconds: f64) -> Result<(), String> {
+        self.inner.sleep(seconds)
+    }
+    fn allow_thread_spawning(&self) -> bool {
+        self.inner.allow_thread_spawning()
+    }
+    fn can_spawn_threads(&self) -> bool {
+        self.inner.can_spawn_threads()
+    }
+    fn spawn_thread(&self, f: Box<dyn FnOnce() + Send>) -> Result<(), String> {
+        self.inner.spawn_thread(f)
+    }
+    #[cfg(feature = "image")]
+    fn show_image(&self, image: DynamicImage, label: Option<&str>) -> Result<(), String> {
+        self.inner.show_image(image, label)
+    }
+    fn progress(&self, current: f64, total: f64, label: &str) -> Result<(), String> {
+        self.inner.progress(current, total, label)
+    }
+    fn show_gif(&self, gif_bytes: Vec<u8>, label: Option<&str>) -> Result<(), String> {
+        self.inner.show_gif(gif_bytes, label)
+    }
+    fn play_audio(&self, wave_bytes: Vec<u8>, label: Option<&str>) -> Result<(), String> {
+        self.inner.play_audio(wave_bytes, label)
+    }
+    #[cfg(feature = "image")]
+    fn window_show(&self, name: &str, image: DynamicImage) -> Result<(), String> {
+        self.inner.window_show(name, image)
+    }
+    fn window_close(&self, name: &str) -> Result<(), String> {
+        self.inner.window_close(name)
+    }
+    fn window_events(&self, name: &str) -> Result<Vec<WindowEvent>, String> {
+        self.inner.window_events(name)
+    }
+    fn audio_sample_rate(&self) -> u32 {
+        self.inner.audio_sample_rate()
+    }
+    fn stream_audio(&self, f: AudioStreamFn) -> Result<(), String> {
+        self.inner.stream_audio(f)
+    }
+    fn now(&self) -> f64 {
+        self.inner.now()
+    }
+    fn tcp_listen(&self, addr: &str) -> Result<Handle, String> {
+        self.inner.tcp_listen(addr)
+    }
+    fn tls_listen(&self, addr: &str, cert: &[u8], key: &[u8]) -> Result<Handle, String> {
+        self.inner.tls_listen(addr, cert, key)
+    }
+    fn tcp_accept(&self, handle: Handle) -> Result<Handle, String> {
+        self.inner.tcp_accept(handle)
+    }
+    fn tcp_connect(&self, addr: &str) -> Result<Handle, String> {
+        self.inner.tcp_connect(addr)
+    }
+    fn tls_connect(&self, addr: &str) -> Result<Handle, String> {
+        self.inner.tls_connect(addr)
+    }
+    fn tcp_addr(&self, handle: Handle) -> Result<SocketAddr, String> {
+        self.inner.tcp_addr(handle)
+    }
+    fn tcp_set_non_blocking(&self, handle: Handle, non_blocking: bool) -> Result<(), String> {
+        self.inner.tcp_set_non_blocking(handle, non_blocking)
+    }
+    fn tcp_set_read_timeout(
+        &self,
+        handle: Handle,
+        timeout: Option<Duration>,
+    ) -> Result<(), String> {
+        self.inner.tcp_set_read_timeout(handle, timeout)
+    }
+    fn tcp_set_write_timeout(
+        &self,
+        handle: Handle,
+        timeout: Option<Duration>,
+    ) -> Result<(), String> {
+        self.inner.tcp_set_write_timeout(handle, timeout)
+    }
+    fn udp_bind(&self, addr: &str) -> Result<Handle, String> {
+        self.inner.udp_bind(addr)
+    }
+    fn udp_addr(&self, handle: Handle) -> Result<SocketAddr, String> {
+        self.inner.udp_addr(handle)
+    }
+    fn udp_send(&self, addr: &str, packet: &[u8]) -> Result<(), String> {
+        self.inner.udp_send(addr, packet)
+    }
+    fn udp_receive(
+        &self,
+        handle: Handle,
+        timeout: Option<Duration>,
+    ) -> Result<Option<Vec<u8>>, String> {
+        self.inner.udp_receive(handle, timeout)
+    }
+    fn dns_lookup(&self, record_type: &str, name: &str) -> Result<Vec<String>, String> {
+        self.inner.dns_lookup(record_type, name)
+    }
+    fn ping(&self, addr: &str, timeout: Option<Duration>) -> Result<Option<f64>, String> {
+        self.inner.ping(addr, timeout)
+    }
+    fn interface_addrs(&self) -> Result<Vec<(String, IpAddr)>, String> {
+        self.inner.interface_addrs()
+    }
+    fn mqtt_connect(&self, options: &MqttOptions) -> Result<Handle, String> {
+        self.inner.mqtt_connect(options)
+    }
+    fn mqtt_subscribe(&self, handle: Handle, topic: &str, qos: u8) -> Result<(), String> {
+        self.inner.mqtt_subscribe(handle, topic, qos)
+    }
+    fn mqtt_publish(&self, handle: Handle, message: &MqttMessage) -> Result<(), String> {
+        self.inner.mqtt_publish(handle, message)
+    }
+    fn mqtt_receive(
+        &self,
+        handle: Handle,
+        timeout: Option<Duration>,
+    ) -> Result<Option<MqttMessage>, String> {
+        self.inner.mqtt_receive(handle, timeout)
+    }
+    fn smtp_send(
+        &self,
+        server: &SmtpServer,
+        auth: Option<&SmtpAuth>,
+        email: &Email,
+    ) -> Result<(), String> {
+        self.inner.smtp_send(server, auth, email)
+    }
+    fn keyring_get(&self, service: &str, account: &str) -> Result<Option<String>, String> {
+        self.inner.keyring_get(service, account)
+    }
+    fn keyring_set(&self, service: &str, account: &str, secret: &str) -> Result<(), String> {
+        self.inner.keyring_set(service, account, secret)
+    }
+    fn keyring_delete(&self, service: &str, account: &str) -> Result<(), String> {
+        self.inner.keyring_delete(service, account)
+    }
+    fn gpio_read(&self, chip: usize, pin: u32) -> Result<bool, String> {
+        self.inner.gpio_read(chip, pin)
+    }
+    fn gpio_write(&self, chip: usize, pin: u32, value: bool) -> Result<(), String> {
+        self.inner.gpio_write(chip, pin, value)
+    }
+    fn i2c_transfer(
+        &self,
+        bus: usize,
+        addr: u16,
+        write: &[u8],
+        read_len: usize,
+    ) -> Result<Vec<u8>, String> {
+        self.inner.i2c_transfer(bus, addr, write, read_len)
+    }
+    fn close(&self, handle: Handle) -> Result<(), String> {
+        self.inner.close(handle)
+    }
+    fn invoke(&self, path: &str) -> Result<(), String> {
+        self.inner.invoke(path)
+    }
+    fn run_command_inherit(&self, command: &str, args: &[&str]) -> Result<i32, String> {
+        self.inner.run_command_inherit(command, args)
+    }
+    fn run_command_capture(
+        &self,
+        command: &str,
+        args: &[&str],
+    ) -> Result<(i32, String, String), String> {
+        self.inner.run_command_capture(command, args)
+    }
+    fn run_command_stream(&self, command: &str, args: &[&str]) -> Result<[Handle; 3], String> {
+        self.inner.run_command_stream(command, args)
+    }
+    fn change_directory(&self, path: &str) -> Result<(), String> {
+        self.inner.change_directory(path)
+    }
+    fn https_get(&self, request: &str, handle: Handle) -> Result<String, String> {
+        self.inner.https_get(request, handle)
+    }
+    fn webcam_capture(&self, index: usize) -> Result<WebcamImage, String> {
+        self.inner.webcam_capture(index)
+    }
+    fn ffi(
+        &self,
+        file: &str,
+        result_ty: FfiType,
+        name: &str,
+        arg_tys: &[FfiType],
+        args: &[Value],
+    ) -> Result<Value, String> {
+        self.inner.ffi(file, result_ty, name, arg_tys, args)
+    }
+    fn mem_copy(&self, ty: FfiType, ptr: *const (), len: usize) -> Result<Value, String> {
+        self.inner.mem_copy(ty, ptr, len)
+    }
+    fn ffi_callback(
+        &self,
+        result_ty: FfiType,
+        arg_tys: &[FfiType],
+        f: FfiCallbackFn,
+    ) -> Result<Value, String> {
+        self.inner.ffi_callback(result_ty, arg_tys, f)
+    }
+    fn mem_free(&self, ptr: *const ()) -> Result<(), String> {
+        self.inner.mem_free(ptr)
+    }
+    fn plugin_call(&self, name: &str, arg: &str) -> Result<String, String> {
+        self.inner.plugin_call(name, arg)
+    }
+    fn python_call(&self, function: &str, args: &[Value]) -> Result<Value, String> {
+        self.inner.python_call(function, args)
+    }
+    fn load_git_module(&self, url: &str, target: GitTarget) -> Result<PathBuf, String> {
+        self.inner.load_git_module(url, target)
+    }
+    fn timezone(&self) -> Result<f64, String> {
+        self.inner.timezone()
+    }
+    fn breakpoint(&self, env: &Uiua) -> Result<bool, String> {
+        self.inner.breakpoint(env)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn comptime_assets() {
+        use crate::*;
+        let mut comp = Compiler::with_backend(MemoryBackend::new());
+        comp.asset("data/primes.txt", "2 3 5 7");
+        let code = r#"
+            Primes ← comptime(⊜⋕⊸≠@ &fras "data/primes.txt")
+            Table ← comptime(⊞× ⊃∘∘ Primes)
+            ⊡ 1_2 Table
+        "#;
+        let asm = comp.load_str(code).unwrap().finish();
+        // The assets are baked into the assembly and not read at runtime
+        let asm = Assembly::from_uasm(&asm.to_uasm()).unwrap();
+        let mut env = Uiua::with_backend(MemoryBackend::new());
+        env.run_asm(asm).unwrap();
+        assert_eq!(env.pop_num().unwrap(), 15.0);
+        // Without the asset, compile-time evaluation fails
+        let res = Compiler::with_backend(MemoryBackend::new())
+            .load_str(code)
+            .map(drop);
+        assert!(res.is_err());
+    }
+}
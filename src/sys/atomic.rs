@@ -0,0 +1,98 @@
+//! Atomic cells for sharing mutable state between threads
+
+use std::sync::{
+    atomic::{self, AtomicUsize},
+    Arc,
+};
+
+use dashmap::DashMap;
+use parking_lot::Mutex;
+
+use crate::{Uiua, UiuaResult, Value};
+
+/// The atomic cells of a program
+///
+/// Each cell's value is behind its own lock, so every operation on a cell is
+/// sequentially consistent with the other operations on that cell.
+#[derive(Default)]
+pub(crate) struct AtomicCells {
+    cells: DashMap<usize, Arc<Mutex<Value>>>,
+    next_id: AtomicUsize,
+}
+
+/// Atomic cells shared between threads
+pub(crate) type SharedAtomicCells = Arc<AtomicCells>;
+
+fn id(value: &Value, env: &Uiua) -> UiuaResult<usize> {
+    value.as_nat(env, "Atomic cell id must be a natural number")
+}
+
+fn get(id_val: &Value, env: &Uiua) -> UiuaResult<Arc<Mutex<Value>>> {
+    let id = id(id_val, env)?;
+    let cell = env.rt.atomic_cells.cells.get(&id);
+    (cell.map(|cell| cell.clone())).ok_or_else(|| env.error("Invalid atomic cell id"))
+}
+
+pub(crate) fn create(value: Value, env: &Uiua) -> Value {
+    let cells = &env.rt.atomic_cells;
+    let id = cells.next_id.fetch_add(1, atomic::Ordering::Relaxed);
+    cells.cells.insert(id, Arc::new(Mutex::new(value)));
+    id.into()
+}
+
+pub(crate) fn free(id_val: Value, env: &Uiua) -> UiuaResult {
+    let id = id(&id_val, env)?;
+    (env.rt.atomic_cells.cells)
+        .remove(&id)
+        .ok_or_else(|| env.error("Invalid atomic cell id"))?;
+    Ok(())
+}
+
+pub(crate) fn load(id_val: Value, env: &Uiua) -> UiuaResult<Value> {
+    Ok(get(&id_val, env)?.lock().clone())
+}
+
+pub(crate) fn compare_swap(
+    expected: Value,
+    new: Value,
+    id_val: Value,
+    env: &Uiua,
+) -> UiuaResult<bool> {
+    let cell = get(&id_val, env)?;
+    let mut value = cell.lock();
+    Ok(if *value == expected {
+        *value = new;
+        true
+    } else {
+        false
+    })
+}
+
+pub(crate) fn fetch_add(amount: Value, id_val: Value, env: &Uiua) -> UiuaResult<Value> {
+    let cell = get(&id_val, env)?;
+    let mut value = cell.lock();
+    let new = Value::add(amount, value.clone(), env)?;
+    Ok(std::mem::replace(&mut *value, new))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Uiua;
+
+    #[test]
+    fn cells_belong_to_runtime() {
+        let mut env = Uiua::with_safe_sys();
+        env.run_str("# Experimental!\n&atom 5").unwrap();
+        let id = env.pop_nat().unwrap();
+        let code = format!("# Experimental!\n&atomld {id}");
+        let Err(err) = Uiua::with_safe_sys().run_str(&code) else {
+            panic!("Atomic cell was shared between runtimes");
+        };
+        assert!(err.to_string().contains("Invalid atomic cell id"), "{err}");
+        env.run_str(&code).unwrap();
+        assert_eq!(env.pop_num().unwrap(), 5.0);
+        env.run_str(&format!("# Experimental!\n&atomfr {id}"))
+            .unwrap();
+        assert!(env.run_str(&code).is_err());
+    }
+}
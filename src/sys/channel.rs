@@ -0,0 +1,154 @@
+//! Channels for communication between threads
+
+use std::{
+    sync::{
+        atomic::{self, AtomicUsize},
+        Arc,
+    },
+    time::Duration,
+};
+
+use crossbeam_channel::{Receiver, RecvTimeoutError, Select, Sender, TryRecvError};
+use dashmap::DashMap;
+
+use crate::{Uiua, UiuaResult, Value};
+
+struct Channel {
+    send: Sender<Value>,
+    recv: Receiver<Value>,
+}
+
+/// The channels of a program
+#[derive(Default)]
+pub(crate) struct Channels {
+    channels: DashMap<usize, Channel>,
+    next_id: AtomicUsize,
+}
+
+/// Channels shared between threads
+pub(crate) type SharedChannels = Arc<Channels>;
+
+/// Parse a timeout in seconds, where infinity means no timeout
+fn timeout(value: Value, env: &Uiua) -> UiuaResult<Option<Duration>> {
+    let secs = value.as_num(env, "Timeout must be a number")?;
+    if secs.is_nan() || secs < 0.0 {
+        return Err(env.error(format!("Timeout must be non-negative, but it is {secs}")));
+    }
+    Ok(if secs.is_infinite() {
+        None
+    } else {
+        Some(Duration::from_secs_f64(secs))
+    })
+}
+
+fn id(value: &Value, env: &Uiua) -> UiuaResult<usize> {
+    value.as_nat(env, "Channel id must be a natural number")
+}
+
+fn get<T>(id: usize, env: &Uiua, f: impl FnOnce(&Channel) -> T) -> UiuaResult<T> {
+    let channel = env.rt.channels.channels.get(&id);
+    (channel.map(|ch| f(&ch))).ok_or_else(|| env.error("Invalid channel id"))
+}
+
+pub(crate) fn create(capacity: Value, env: &Uiua) -> UiuaResult<Value> {
+    let capacity = capacity.as_num(env, "Channel capacity must be a number")?;
+    let (send, recv) = if capacity.is_infinite() && capacity > 0.0 {
+        crossbeam_channel::unbounded()
+    } else if capacity >= 0.0 && capacity.fract() == 0.0 {
+        crossbeam_channel::bounded(capacity as usize)
+    } else {
+        return Err(env.error(format!(
+            "Channel capacity must be a natural number or infinity, but it is {capacity}"
+        )));
+    };
+    let channels = &env.rt.channels;
+    let id = channels.next_id.fetch_add(1, atomic::Ordering::Relaxed) + 1;
+    channels.channels.insert(id, Channel { send, recv });
+    Ok(id.into())
+}
+
+pub(crate) fn send(value: Value, id_val: Value, env: &Uiua) -> UiuaResult {
+    let send = get(id(&id_val, env)?, env, |ch| ch.send.clone())?;
+    if cfg!(target_arch = "wasm32") {
+        return (send.try_send(value)).map_err(|_| env.error("Channel is full"));
+    }
+    (send.send(value)).map_err(|_| env.error("Channel is closed"))
+}
+
+pub(crate) fn recv(timeout_val: Value, id_val: Value, env: &Uiua) -> UiuaResult<Value> {
+    let timeout = timeout(timeout_val, env)?;
+    let recv = get(id(&id_val, env)?, env, |ch| ch.recv.clone())?;
+    if cfg!(target_arch = "wasm32") || timeout == Some(Duration::ZERO) {
+        return recv.try_recv().map_err(|e| match e {
+            TryRecvError::Empty => env.error("Channel is empty"),
+            TryRecvError::Disconnected => env.error("Channel is closed"),
+        });
+    }
+    match timeout {
+        Some(timeout) => recv.recv_timeout(timeout).map_err(|e| match e {
+            RecvTimeoutError::Timeout => env.error("Channel receive timed out"),
+            RecvTimeoutError::Disconnected => env.error("Channel is closed"),
+        }),
+        None => recv.recv().map_err(|_| env.error("Channel is closed")),
+    }
+}
+
+pub(crate) fn select(timeout_val: Value, ids_val: Value, env: &Uiua) -> UiuaResult<(usize, Value)> {
+    let timeout = timeout(timeout_val, env)?;
+    let ids = ids_val.as_nats(env, "Channel ids must be a list of natural numbers")?;
+    if ids.is_empty() {
+        return Err(env.error("Cannot select from no channels"));
+    }
+    let receivers = (ids.iter())
+        .map(|&id| get(id, env, |ch| ch.recv.clone()))
+        .collect::<UiuaResult<Vec<_>>>()?;
+    if cfg!(target_arch = "wasm32") || timeout == Some(Duration::ZERO) {
+        for (i, recv) in receivers.iter().enumerate() {
+            if let Ok(value) = recv.try_recv() {
+                return Ok((i, value));
+            }
+        }
+        return Err(env.error("All channels are empty"));
+    }
+    let mut sel = Select::new();
+    for recv in &receivers {
+        sel.recv(recv);
+    }
+    let oper = match timeout {
+        Some(timeout) => {
+            (sel.select_timeout(timeout)).map_err(|_| env.error("Channel select timed out"))?
+        }
+        None => sel.select(),
+    };
+    let i = oper.index();
+    let value = (oper.recv(&receivers[i])).map_err(|_| env.error("Channel is closed"))?;
+    Ok((i, value))
+}
+
+pub(crate) fn close(id_val: Value, env: &Uiua) -> UiuaResult {
+    let id = id(&id_val, env)?;
+    (env.rt.channels.channels)
+        .remove(&id)
+        .ok_or_else(|| env.error("Invalid channel id"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Uiua;
+
+    #[test]
+    fn channels_belong_to_runtime() {
+        let mut env = Uiua::with_safe_sys();
+        env.run_str("# Experimental!\nC ← &chan ∞\n&chs 5 C\nC")
+            .unwrap();
+        let id = env.pop_nat().unwrap();
+        let code = format!("# Experimental!\n&chr 0 {id}");
+        let Err(err) = Uiua::with_safe_sys().run_str(&code) else {
+            panic!("Channel was shared between runtimes");
+        };
+        assert!(err.to_string().contains("Invalid channel id"), "{err}");
+        env.run_str(&code).unwrap();
+        assert_eq!(env.pop_num().unwrap(), 5.0);
+    }
+}
@@ -0,0 +1,213 @@
+//! DNS queries for TXT records, which the system resolver cannot look up
+//!
+//! Queries are sent to the nameservers in `/etc/resolv.conf` over UDP.
+//! If a response is truncated, the query is sent again over TCP.
+
+use std::{
+    fs,
+    io::{Read, Write},
+    net::{IpAddr, SocketAddr, TcpStream, UdpSocket},
+    time::Duration,
+};
+
+const TXT: u16 = 16;
+const CLASS_IN: u16 = 1;
+/// How long to wait for a nameserver to respond
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Look up the TXT records of a domain name
+pub(crate) fn lookup_txt(name: &str) -> Result<Vec<String>, String> {
+    let servers = nameservers();
+    if servers.is_empty() {
+        return Err("Could not find a DNS server in /etc/resolv.conf".into());
+    }
+    let id = rand::random();
+    let query = query(id, name, TXT)?;
+    let mut error = String::new();
+    for server in servers {
+        match query_server(server, &query, id) {
+            Ok(response) => {
+                let records = parse_response(&response, TXT, name)?;
+                return Ok(records.iter().map(|data| txt_data(data)).collect());
+            }
+            Err(e) => error = e,
+        }
+    }
+    Err(error)
+}
+
+fn nameservers() -> Vec<SocketAddr> {
+    let conf = fs::read_to_string("/etc/resolv.conf").unwrap_or_default();
+    (conf.lines())
+        .filter_map(|line| line.trim().strip_prefix("nameserver"))
+        .filter_map(|addr| addr.trim().parse::<IpAddr>().ok())
+        .map(|ip| SocketAddr::new(ip, 53))
+        .collect()
+}
+
+fn query_server(server: SocketAddr, query: &[u8], id: u16) -> Result<Vec<u8>, String> {
+    let io_err = |e: std::io::Error| format!("DNS query to {server} failed: {e}");
+    let local = if server.is_ipv6() {
+        "[::]:0"
+    } else {
+        "0.0.0.0:0"
+    };
+    let socket = UdpSocket::bind(local).map_err(io_err)?;
+    socket.set_read_timeout(Some(TIMEOUT)).map_err(io_err)?;
+    socket.send_to(query, server).map_err(io_err)?;
+    let mut buf = vec![0; 1 << 16];
+    let response = loop {
+        let (len, from) = socket.recv_from(&mut buf).map_err(io_err)?;
+        // Ignore packets that are not the response to this query
+        if from == server && buf[..len].starts_with(&id.to_be_bytes()) {
+            break &buf[..len];
+        }
+    };
+    let truncated = response.get(2).is_some_and(|flags| flags & 0b10 != 0);
+    if !truncated {
+        return Ok(response.to_vec());
+    }
+    let mut stream = TcpStream::connect_timeout(&server, TIMEOUT).map_err(io_err)?;
+    stream.set_read_timeout(Some(TIMEOUT)).map_err(io_err)?;
+    let mut packet = (query.len() as u16).to_be_bytes().to_vec();
+    packet.extend(query);
+    stream.write_all(&packet).map_err(io_err)?;
+    let mut len = [0; 2];
+    stream.read_exact(&mut len).map_err(io_err)?;
+    let mut response = vec![0; u16::from_be_bytes(len) as usize];
+    stream.read_exact(&mut response).map_err(io_err)?;
+    Ok(response)
+}
+
+/// Build a query for one question with recursion desired
+fn query(id: u16, name: &str, record_type: u16) -> Result<Vec<u8>, String> {
+    let mut packet = Vec::with_capacity(name.len() + 18);
+    packet.extend(id.to_be_bytes());
+    packet.extend([0x01, 0x00]);
+    packet.extend([0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(format!("{name:?} is not a valid domain name"));
+        }
+        packet.push(label.len() as u8);
+        packet.extend(label.as_bytes());
+    }
+    packet.push(0);
+    packet.extend(record_type.to_be_bytes());
+    packet.extend(CLASS_IN.to_be_bytes());
+    Ok(packet)
+}
+
+/// Get the data of the answers of a type from a response
+fn parse_response(response: &[u8], record_type: u16, name: &str) -> Result<Vec<Vec<u8>>, String> {
+    let invalid = || "Invalid DNS response".to_string();
+    let u16_at = |i: usize| -> Result<u16, String> {
+        let bytes = response.get(i..i + 2).ok_or_else(invalid)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    };
+    match u16_at(2)? & 0xF {
+        0 => {}
+        3 => return Err(format!("Domain {name} does not exist")),
+        code => return Err(format!("DNS server failed to look up {name} (code {code})")),
+    }
+    let questions = u16_at(4)?;
+    let answers = u16_at(6)?;
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = skip_name(response, pos).ok_or_else(invalid)? + 4;
+    }
+    let mut records = Vec::new();
+    for _ in 0..answers {
+        pos = skip_name(response, pos).ok_or_else(invalid)?;
+        let kind = u16_at(pos)?;
+        let len = u16_at(pos + 8)? as usize;
+        let data = (response.get(pos + 10..pos + 10 + len)).ok_or_else(invalid)?;
+        pos += 10 + len;
+        if kind == record_type {
+            records.push(data.to_vec());
+        }
+    }
+    Ok(records)
+}
+
+/// Get the position after a possibly compressed name
+fn skip_name(response: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let len = *response.get(pos)?;
+        if len == 0 {
+            return Some(pos + 1);
+        }
+        if len & 0xC0 == 0xC0 {
+            return Some(pos + 2);
+        }
+        pos += 1 + len as usize;
+    }
+}
+
+/// Join the character strings of a TXT record
+fn txt_data(data: &[u8]) -> String {
+    let mut text = Vec::with_capacity(data.len());
+    let mut rest = data;
+    while let Some((&len, tail)) = rest.split_first() {
+        let len = (len as usize).min(tail.len());
+        text.extend(&tail[..len]);
+        rest = &tail[len..];
+    }
+    String::from_utf8_lossy(&text).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn txt_response() {
+        let mut response = query(7, "example.com", TXT).unwrap();
+        // Mark it as a response with two answers
+        response[2] = 0x81;
+        response[3] = 0x80;
+        response[7] = 2;
+        for (kind, data) in [(TXT, &b"\x05hello\x06 world"[..]), (1, &[127, 0, 0, 1])] {
+            response.extend([0xC0, 12]);
+            response.extend(kind.to_be_bytes());
+            response.extend(CLASS_IN.to_be_bytes());
+            response.extend(300u32.to_be_bytes());
+            response.extend((data.len() as u16).to_be_bytes());
+            response.extend(data);
+        }
+        let records = parse_response(&response, TXT, "example.com").unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(txt_data(&records[0]), "hello world");
+        response[3] = 0x83;
+        assert!(parse_response(&response, TXT, "example.com").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "native_sys")]
+    fn network_utilities() {
+        use crate::*;
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut env = Uiua::with_native_sys();
+        let code = format!(
+            r#"
+            # Experimental!
+            /↥≡◇(≍"127.0.0.1") &dnslookup "a" "localhost"
+            &ping 5 "{addr}"
+            "#
+        );
+        env.run_str(&code).unwrap();
+        let time = env.pop("time").unwrap().as_num(&env, "").unwrap();
+        assert!(time.is_finite() && time >= 0.0);
+        assert!(env.pop("found").unwrap().as_bool(&env, "").unwrap());
+        let Err(err) = env.run_str("# Experimental!\n&dnslookup \"MX\" \"localhost\"") else {
+            panic!("Unknown record type should fail");
+        };
+        assert!(err.to_string().contains("Unknown DNS record type"));
+        if cfg!(unix) {
+            env.run_str("# Experimental!\n/↥≡◇(≍\"127.0.0.1\") /◇⊂ ◌°map &ifaddrs")
+                .unwrap();
+            assert!(env.pop("found").unwrap().as_bool(&env, "").unwrap());
+        }
+    }
+}
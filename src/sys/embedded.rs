@@ -0,0 +1,230 @@
+//! GPIO and I2C access on Linux
+//!
+//! GPIO lines are requested through the GPIO character device, `/dev/gpiochipN`.
+//! A requested line keeps its direction and value until it is requested again with a different direction.
+//!
+//! I2C transfers go through the I2C device interface, `/dev/i2c-N`.
+//! A write followed by a read is done as a single transaction with a repeated start condition,
+//! which is how most sensors expect registers to be read.
+
+use std::{
+    fs::{File, OpenOptions},
+    io,
+    mem::size_of,
+    os::fd::{AsRawFd, FromRawFd},
+};
+
+use dashmap::DashMap;
+
+const GPIO_MAX_NAME_SIZE: usize = 32;
+const GPIO_V2_LINES_MAX: usize = 64;
+const GPIO_V2_LINE_NUM_ATTRS_MAX: usize = 10;
+const GPIO_V2_LINE_FLAG_INPUT: u64 = 1 << 2;
+const GPIO_V2_LINE_FLAG_OUTPUT: u64 = 1 << 3;
+const GPIO_V2_LINE_ATTR_ID_OUTPUT_VALUES: u32 = 2;
+
+const I2C_RDWR: libc::c_ulong = 0x0707;
+const I2C_M_RD: u16 = 0x0001;
+
+const fn iowr<T>(nr: libc::c_ulong) -> libc::c_ulong {
+    (3 << 30) | ((size_of::<T>() as libc::c_ulong) << 16) | (0xB4 << 8) | nr
+}
+
+const GPIO_V2_GET_LINE_IOCTL: libc::c_ulong = iowr::<GpioV2LineRequest>(0x07);
+const GPIO_V2_LINE_GET_VALUES_IOCTL: libc::c_ulong = iowr::<GpioV2LineValues>(0x0E);
+const GPIO_V2_LINE_SET_VALUES_IOCTL: libc::c_ulong = iowr::<GpioV2LineValues>(0x0F);
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct GpioV2LineAttribute {
+    id: u32,
+    padding: u32,
+    value: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct GpioV2LineConfigAttribute {
+    attr: GpioV2LineAttribute,
+    mask: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct GpioV2LineConfig {
+    flags: u64,
+    num_attrs: u32,
+    padding: [u32; 5],
+    attrs: [GpioV2LineConfigAttribute; GPIO_V2_LINE_NUM_ATTRS_MAX],
+}
+
+#[repr(C)]
+struct GpioV2LineRequest {
+    offsets: [u32; GPIO_V2_LINES_MAX],
+    consumer: [u8; GPIO_MAX_NAME_SIZE],
+    config: GpioV2LineConfig,
+    num_lines: u32,
+    event_buffer_size: u32,
+    padding: [u32; 5],
+    fd: i32,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct GpioV2LineValues {
+    bits: u64,
+    mask: u64,
+}
+
+#[repr(C)]
+struct I2cMsg {
+    addr: u16,
+    flags: u16,
+    len: u16,
+    buf: *mut u8,
+}
+
+#[repr(C)]
+struct I2cRdwrIoctlData {
+    msgs: *mut I2cMsg,
+    nmsgs: u32,
+}
+
+// The kernel checks these sizes, so make sure they match its headers
+const _: () = assert!(size_of::<GpioV2LineConfig>() == 272);
+const _: () = assert!(size_of::<GpioV2LineRequest>() == 592);
+
+fn ioctl<T>(fd: &impl AsRawFd, request: libc::c_ulong, arg: &mut T) -> io::Result<()> {
+    // Safety: every request is paired with the argument type that the kernel expects for it
+    let res = unsafe { libc::ioctl(fd.as_raw_fd(), request as _, arg as *mut T) };
+    if res < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// A requested GPIO line
+struct GpioLine {
+    file: File,
+    output: bool,
+}
+
+/// The GPIO lines that have been requested
+#[derive(Default)]
+pub(crate) struct GpioState {
+    lines: DashMap<(usize, u32), GpioLine>,
+}
+
+impl GpioState {
+    /// Read the value of a pin
+    ///
+    /// Pins that have not been written to are requested as inputs.
+    pub fn read(&self, chip: usize, pin: u32) -> Result<bool, String> {
+        if !self.lines.contains_key(&(chip, pin)) {
+            self.request(chip, pin, None)?;
+        }
+        let line = self.lines.get(&(chip, pin)).unwrap();
+        let mut values = GpioV2LineValues { bits: 0, mask: 1 };
+        ioctl(&line.file, GPIO_V2_LINE_GET_VALUES_IOCTL, &mut values)
+            .map_err(|e| format!("Failed to read GPIO pin {pin}: {e}"))?;
+        Ok(values.bits & 1 == 1)
+    }
+    /// Set the value of a pin, making it an output
+    pub fn write(&self, chip: usize, pin: u32, value: bool) -> Result<(), String> {
+        if let Some(line) = self.lines.get(&(chip, pin)).filter(|line| line.output) {
+            let mut values = GpioV2LineValues {
+                bits: value as u64,
+                mask: 1,
+            };
+            return ioctl(&line.file, GPIO_V2_LINE_SET_VALUES_IOCTL, &mut values)
+                .map_err(|e| format!("Failed to write GPIO pin {pin}: {e}"));
+        }
+        self.request(chip, pin, Some(value))
+    }
+    /// Request a line as an input, or as an output with an initial value
+    fn request(&self, chip: usize, pin: u32, output: Option<bool>) -> Result<(), String> {
+        // A line can only be requested once, so release it first
+        self.lines.remove(&(chip, pin));
+        let path = format!("/dev/gpiochip{chip}");
+        let chip_file = File::open(&path).map_err(|e| format!("Failed to open {path}: {e}"))?;
+        let mut config = GpioV2LineConfig::default();
+        if let Some(value) = output {
+            config.flags = GPIO_V2_LINE_FLAG_OUTPUT;
+            config.num_attrs = 1;
+            config.attrs[0] = GpioV2LineConfigAttribute {
+                attr: GpioV2LineAttribute {
+                    id: GPIO_V2_LINE_ATTR_ID_OUTPUT_VALUES,
+                    padding: 0,
+                    value: value as u64,
+                },
+                mask: 1,
+            };
+        } else {
+            config.flags = GPIO_V2_LINE_FLAG_INPUT;
+        }
+        let mut request = GpioV2LineRequest {
+            offsets: [0; GPIO_V2_LINES_MAX],
+            consumer: [0; GPIO_MAX_NAME_SIZE],
+            config,
+            num_lines: 1,
+            event_buffer_size: 0,
+            padding: [0; 5],
+            fd: -1,
+        };
+        request.offsets[0] = pin;
+        request.consumer[..4].copy_from_slice(b"uiua");
+        ioctl(&chip_file, GPIO_V2_GET_LINE_IOCTL, &mut request)
+            .map_err(|e| format!("Failed to request GPIO pin {pin} on {path}: {e}"))?;
+        // Safety: the kernel returns a new file descriptor that nothing else owns
+        let file = unsafe { File::from_raw_fd(request.fd) };
+        let output = output.is_some();
+        self.lines.insert((chip, pin), GpioLine { file, output });
+        Ok(())
+    }
+}
+
+/// Write bytes to an I2C device and then read bytes from it
+pub(crate) fn i2c_transfer(
+    bus: usize,
+    addr: u16,
+    write: &[u8],
+    read_len: usize,
+) -> Result<Vec<u8>, String> {
+    if write.len() > u16::MAX as usize || read_len > u16::MAX as usize {
+        return Err("I2C transfers cannot be longer than 65535 bytes".into());
+    }
+    let path = format!("/dev/i2c-{bus}");
+    let file = (OpenOptions::new().read(true).write(true))
+        .open(&path)
+        .map_err(|e| format!("Failed to open {path}: {e}"))?;
+    let mut write = write.to_vec();
+    let mut read = vec![0; read_len];
+    let mut msgs = Vec::with_capacity(2);
+    if !write.is_empty() {
+        msgs.push(I2cMsg {
+            addr,
+            flags: 0,
+            len: write.len() as u16,
+            buf: write.as_mut_ptr(),
+        });
+    }
+    if !read.is_empty() {
+        msgs.push(I2cMsg {
+            addr,
+            flags: I2C_M_RD,
+            len: read_len as u16,
+            buf: read.as_mut_ptr(),
+        });
+    }
+    if msgs.is_empty() {
+        return Ok(read);
+    }
+    let mut data = I2cRdwrIoctlData {
+        msgs: msgs.as_mut_ptr(),
+        nmsgs: msgs.len() as u32,
+    };
+    ioctl(&file, I2C_RDWR, &mut data)
+        .map_err(|e| format!("I2C transfer with device {addr} on {path} failed: {e}"))?;
+    Ok(read)
+}
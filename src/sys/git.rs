@@ -0,0 +1,181 @@
+//! Git modules and the lockfile that records their commits and package versions
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use serde::*;
+
+use crate::GitTarget;
+
+/// The name of the file that records the commits of Git modules and the versions of packages
+pub const LOCKFILE_NAME: &str = "uiua.lock";
+
+const LOCKFILE_HEADER: &str = "\
+# This file is generated by Uiua. It records the commits of Git modules
+# and the versions of packages.
+# Run `uiua module update` or `uiua fetch --update` to update them.
+";
+
+/// The commits that Git modules and the versions that packages are locked to
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Lockfile {
+    /// The locked modules
+    #[serde(default, rename = "module", skip_serializing_if = "Vec::is_empty")]
+    pub modules: Vec<LockedModule>,
+    /// The locked packages
+    #[serde(default, rename = "package", skip_serializing_if = "Vec::is_empty")]
+    pub packages: Vec<LockedPackage>,
+}
+
+/// A Git module locked to a commit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LockedModule {
+    /// The URL of the repository
+    pub url: String,
+    /// The branch that was requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    /// The tag that was requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// The commit that was requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+    /// The commit that the target resolved to
+    pub commit: String,
+}
+
+/// A package locked to a version
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LockedPackage {
+    /// The name of the package
+    pub name: String,
+    /// The version that the requirement resolved to
+    pub version: String,
+}
+
+impl LockedModule {
+    /// Create a new locked module
+    pub fn new(url: impl Into<String>, target: &GitTarget, commit: impl Into<String>) -> Self {
+        let mut module = LockedModule {
+            url: url.into(),
+            branch: None,
+            tag: None,
+            rev: None,
+            commit: commit.into(),
+        };
+        match target {
+            GitTarget::Default => {}
+            GitTarget::Branch(branch) => module.branch = Some(branch.clone()),
+            GitTarget::Tag(tag) => module.tag = Some(tag.clone()),
+            GitTarget::Commit(rev) => module.rev = Some(rev.clone()),
+        }
+        module
+    }
+    /// Get the target that was requested
+    pub fn target(&self) -> GitTarget {
+        if let Some(branch) = &self.branch {
+            GitTarget::Branch(branch.clone())
+        } else if let Some(tag) = &self.tag {
+            GitTarget::Tag(tag.clone())
+        } else if let Some(rev) = &self.rev {
+            GitTarget::Commit(rev.clone())
+        } else {
+            GitTarget::Default
+        }
+    }
+}
+
+impl Lockfile {
+    /// The path of the lockfile in the current directory
+    pub fn path() -> PathBuf {
+        PathBuf::from(LOCKFILE_NAME)
+    }
+    /// Load the lockfile from the current directory
+    ///
+    /// Returns an empty lockfile if it does not exist
+    pub fn load() -> io::Result<Self> {
+        let text = match fs::read_to_string(Self::path()) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e),
+        };
+        toml::from_str(&text).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid {LOCKFILE_NAME}: {}", e.message()),
+            )
+        })
+    }
+    /// Save the lockfile to the current directory
+    pub fn save(&self) -> io::Result<()> {
+        let text = toml::to_string(self).map_err(io::Error::other)?;
+        fs::write(Self::path(), format!("{LOCKFILE_HEADER}\n{text}"))
+    }
+    /// Get the locked module with the given URL
+    pub fn get(&self, url: &str) -> Option<&LockedModule> {
+        self.modules.iter().find(|module| module.url == url)
+    }
+    /// Insert a locked module, replacing the one with the same URL
+    ///
+    /// Returns whether the lockfile changed
+    pub fn insert(&mut self, module: LockedModule) -> bool {
+        if let Some(existing) = self.modules.iter_mut().find(|m| m.url == module.url) {
+            if *existing == module {
+                return false;
+            }
+            *existing = module;
+        } else {
+            self.modules.push(module);
+            self.modules.sort_by(|a, b| a.url.cmp(&b.url));
+        }
+        true
+    }
+    /// Get the locked package with the given name
+    pub fn get_package(&self, name: &str) -> Option<&LockedPackage> {
+        self.packages.iter().find(|package| package.name == name)
+    }
+}
+
+/// Run a Git command in a directory and get its trimmed output
+pub(crate) fn git(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| e.to_string())?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().into())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().into())
+    }
+}
+
+/// Update a cloned Git module to the latest commit of its target
+///
+/// The target is read from the lockfile, and the lockfile is updated with the new commit.
+/// Returns `None` if the module is pinned to a tag or commit.
+pub fn update_git_module(lockfile: &mut Lockfile, dir: &Path) -> Result<Option<String>, String> {
+    let url = git(dir, &["config", "--get", "remote.origin.url"])?;
+    let target = lockfile
+        .get(&url)
+        .map(LockedModule::target)
+        .unwrap_or_default();
+    let remote_ref = match &target {
+        GitTarget::Default => "origin/HEAD".to_string(),
+        GitTarget::Branch(branch) => format!("origin/{branch}"),
+        GitTarget::Tag(_) | GitTarget::Commit(_) => return Ok(None),
+    };
+    git(dir, &["fetch", "origin"]).map_err(|e| format!("Failed to fetch `{url}`: {e}"))?;
+    git(dir, &["checkout", "--detach", &remote_ref])
+        .map_err(|e| format!("Failed to checkout `{remote_ref}`: {e}"))?;
+    let commit = git(dir, &["rev-parse", "HEAD"])?;
+    lockfile.insert(LockedModule::new(url, &target, commit.clone()));
+    Ok(Some(commit))
+}
@@ -0,0 +1,598 @@
+//! A system backend that renders windows to frames instead of showing them
+
+use std::{
+    any::Any,
+    collections::BTreeMap,
+    fs,
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use ecow::EcoVec;
+use image::{DynamicImage, ImageOutputFormat};
+use parking_lot::Mutex;
+use serde::*;
+
+use super::{
+    AudioStreamFn, Email, GitTarget, Handle, IntoSysBackend, MqttMessage, MqttOptions,
+    ReadLinesReturnFn, SmtpAuth, SmtpServer, SysBackend, TermCommand, TermEvent, WebcamImage,
+    WindowEvent,
+};
+use crate::{
+    encode::{image_to_bytes, rgba_image_to_array},
+    Array, FfiCallbackFn, FfiType, Uiua, Value,
+};
+
+/// A system backend that wraps another and records the images shown in windows as frames
+///
+/// This lets programs that use windows run where there is no display,
+/// like in CI or on a server.
+///
+/// Time is simulated. The clock starts at `0`, so runs are reproducible.
+/// Sleeping advances the clock without waiting,
+/// so a program that waits for each frame runs as fast as it can render.
+///
+/// Window input comes from [`ScriptedEvent`]s.
+pub struct HeadlessSys {
+    inner: Arc<dyn SysBackend>,
+    clock: Mutex<f64>,
+    frame_limit: Option<usize>,
+    windows: Mutex<BTreeMap<String, HeadlessWindow>>,
+    events: Mutex<Vec<ScriptedEvent>>,
+}
+
+/// A frame rendered by a [`HeadlessSys`]
+#[derive(Debug, Clone)]
+pub struct HeadlessFrame {
+    /// The time the frame was rendered, in seconds
+    pub time: f64,
+    /// The image that was shown
+    pub image: DynamicImage,
+}
+
+/// An input event that a [`HeadlessSys`] gives to a window
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScriptedEvent {
+    /// The name of the window
+    pub window: String,
+    /// The number of frames the window must have shown before the event happens
+    pub frame: usize,
+    /// The event
+    pub event: WindowEvent,
+}
+
+#[derive(Default)]
+struct HeadlessWindow {
+    frames: Vec<HeadlessFrame>,
+    open: bool,
+}
+
+impl HeadlessSys {
+    /// Wrap a backend so that its windows are rendered to frames
+    pub fn new(inner: impl IntoSysBackend) -> Self {
+        HeadlessSys {
+            inner: inner.into_sys_backend(),
+            clock: Mutex::new(0.0),
+            frame_limit: None,
+            windows: Default::default(),
+            events: Default::default(),
+        }
+    }
+    /// Set the most frames a window can render
+    ///
+    /// When a window reaches the limit, it gets a close event.
+    /// Showing more frames in it fails.
+    pub fn with_frame_limit(mut self, limit: usize) -> Self {
+        self.frame_limit = Some(limit);
+        self
+    }
+    /// Give windows scripted input events
+    pub fn with_events(self, mut events: Vec<ScriptedEvent>) -> Self {
+        events.sort_by_key(|e| e.frame);
+        *self.events.lock() = events;
+        self
+    }
+    /// Get the names of the windows that have been shown
+    pub fn window_names(&self) -> Vec<String> {
+        self.windows.lock().keys().cloned().collect()
+    }
+    /// Get the frames rendered in a window
+    pub fn frames(&self, name: &str) -> Vec<HeadlessFrame> {
+        (self.windows.lock().get(name)).map_or_else(Vec::new, |window| window.frames.clone())
+    }
+    /// Get the frames rendered in a window as a single array
+    ///
+    /// The array has a row for each frame, and each row is an RGBA image.
+    pub fn frames_array(&self, name: &str) -> Result<Value, String> {
+        let frames = self.frames(name);
+        let Some(first) = frames.first() else {
+            return Err(format!("Window {name:?} did not render any frames"));
+        };
+        let (width, height) = (first.image.width(), first.image.height());
+        let mut data = EcoVec::new();
+        for frame in &frames {
+            if (frame.image.width(), frame.image.height()) != (width, height) {
+                return Err(format!(
+                    "Window {name:?} rendered frames of different sizes, \
+                    {width}×{height} and {}×{}",
+                    frame.image.width(),
+                    frame.image.height()
+                ));
+            }
+            data.extend(
+                rgba_image_to_array(frame.image.to_rgba8())
+                    .data
+                    .iter()
+                    .copied(),
+            );
+        }
+        let shape = [frames.len(), height as usize, width as usize, 4];
+        Ok(Array::new(shape, data).into())
+    }
+    /// Get the average frame rate of a window
+    ///
+    /// Returns `None` if the window rendered fewer than 2 frames or they were all rendered at the same time.
+    pub fn frame_rate(&self, name: &str) -> Option<f64> {
+        let frames = self.frames(name);
+        let (first, last) = (frames.first()?, frames.last()?);
+        let duration = last.time - first.time;
+        (duration > 0.0).then(|| (frames.len() - 1) as f64 / duration)
+    }
+    /// Save the rendered frames to a directory
+    ///
+    /// Each window's frames are saved as numbered PNG files in a subdirectory named after the window.
+    /// If GIF encoding is enabled, each window's frames are also saved as a GIF.
+    ///
+    /// Returns the paths of the saved files.
+    pub fn save(&self, dir: impl AsRef<Path>) -> Result<Vec<PathBuf>, String> {
+        let dir = dir.as_ref();
+        let mut saved = Vec::new();
+        for name in self.window_names() {
+            let file_name: String = (name.chars())
+                .map(|c| {
+                    if c.is_alphanumeric() || "-_ ".contains(c) {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect();
+            let frames_dir = dir.join(&file_name);
+            fs::create_dir_all(&frames_dir)
+                .map_err(|e| format!("Failed to create {}: {e}", frames_dir.display()))?;
+            for (i, frame) in self.frames(&name).iter().enumerate() {
+                let path = frames_dir.join(format!("{i:05}.png"));
+                let bytes = image_to_bytes(&frame.image, ImageOutputFormat::Png)?;
+                fs::write(&path, bytes)
+                    .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+                saved.push(path);
+            }
+            #[cfg(feature = "gif")]
+            {
+                let frames = self.frames_array(&name)?;
+                let frame_rate = self.frame_rate(&name).unwrap_or(1.0);
+                let bytes = crate::encode::value_to_gif_bytes(&frames, frame_rate)?;
+                let path = dir.join(format!("{file_name}.gif"));
+                fs::write(&path, bytes)
+                    .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+                saved.push(path);
+            }
+        }
+        Ok(saved)
+    }
+}
+
+impl SysBackend for HeadlessSys {
+    fn any(&self) -> &dyn Any {
+        self
+    }
+    fn any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn save_error_color(&self, message: String, colored: String) {
+        self.inner.save_error_color(message, colored)
+    }
+    fn print_str_stdout(&self, s: &str) -> Result<(), String> {
+        self.inner.print_str_stdout(s)
+    }
+    fn print_str_stderr(&self, s: &str) -> Result<(), String> {
+        self.inner.print_str_stderr(s)
+    }
+    fn print_str_trace(&self, s: &str) {
+        self.inner.print_str_trace(s)
+    }
+    fn show(&self, value: Value) -> Result<(), String> {
+        self.inner.show(value)
+    }
+    fn scan_line_stdin(&self) -> Result<Option<String>, String> {
+        self.inner.scan_line_stdin()
+    }
+    fn prompt(&self, prompt: &str, hidden: bool) -> Result<Option<String>, String> {
+        self.inner.prompt(prompt, hidden)
+    }
+    fn scan_stdin(&self, count: Option<usize>) -> Result<Vec<u8>, String> {
+        self.inner.scan_stdin(count)
+    }
+    fn scan_until_stdin(&self, delim: &[u8]) -> Result<Vec<u8>, String> {
+        self.inner.scan_until_stdin(delim)
+    }
+    fn set_raw_mode(&self, raw_mode: bool) -> Result<(), String> {
+        self.inner.set_raw_mode(raw_mode)
+    }
+    fn get_raw_mode(&self) -> Result<bool, String> {
+        self.inner.get_raw_mode()
+    }
+    fn var(&self, name: &str) -> Option<String> {
+        self.inner.var(name)
+    }
+    fn term_size(&self) -> Result<(usize, usize), String> {
+        self.inner.term_size()
+    }
+    fn term_command(&self, command: TermCommand) -> Result<(), String> {
+        self.inner.term_command(command)
+    }
+    fn term_event(&self, timeout: Option<Duration>) -> Result<Option<TermEvent>, String> {
+        self.inner.term_event(timeout)
+    }
+    fn exit(&self, status: i32) -> Result<(), String> {
+        self.inner.exit(status)
+    }
+    fn file_exists(&self, path: &str) -> bool {
+        self.inner.file_exists(path)
+    }
+    fn list_dir(&self, path: &str) -> Result<Vec<String>, String> {
+        self.inner.list_dir(path)
+    }
+    fn is_file(&self, path: &str) -> Result<bool, String> {
+        self.inner.is_file(path)
+    }
+    fn delete(&self, path: &str) -> Result<(), String> {
+        self.inner.delete(path)
+    }
+    fn trash(&self, path: &str) -> Result<(), String> {
+        self.inner.trash(path)
+    }
+    fn read(&self, handle: Handle, count: usize) -> Result<Vec<u8>, String> {
+        self.inner.read(handle, count)
+    }
+    fn read_all(&self, handle: Handle) -> Result<Vec<u8>, String> {
+        self.inner.read_all(handle)
+    }
+    fn read_until(&self, handle: Handle, delim: &[u8]) -> Result<Vec<u8>, String> {
+        self.inner.read_until(handle, delim)
+    }
+    fn read_lines<'a>(&self, handle: Handle) -> Result<ReadLinesReturnFn<'a>, String> {
+        self.inner.read_lines(handle)
+    }
+    fn write(&self, handle: Handle, contents: &[u8]) -> Result<(), String> {
+        self.inner.write(handle, contents)
+    }
+    fn create_file(&self, path: &Path) -> Result<Handle, String> {
+        self.inner.create_file(path)
+    }
+    fn open_file(&self, path: &Path, write: bool) -> Result<Handle, String> {
+        self.inner.open_file(path, write)
+    }
+    fn make_dir(&self, path: &Path) -> Result<(), String> {
+        self.inner.make_dir(path)
+    }
+    fn file_read_all(&self, path: &Path) -> Result<Vec<u8>, String> {
+        self.inner.file_read_all(path)
+    }
+    fn file_write_all(&self, path: &Path, contents: &[u8]) -> Result<(), String> {
+        self.inner.file_write_all(path, contents)
+    }
+    fn file_append(&self, path: &Path, contents: &[u8]) -> Result<(), String> {
+        self.inner.file_append(path, contents)
+    }
+    fn clipboard(&self) -> Result<String, String> {
+        self.inner.clipboard()
+    }
+    fn set_clipboard(&self, contents: &str) -> Result<(), String> {
+        self.inner.set_clipboard(contents)
+    }
+    fn sleep(&self, seconds: f64) -> Result<(), String> {
+        *self.clock.lock() += seconds.max(0.0);
+        Ok(())
+    }
+    fn allow_thread_spawning(&self) -> bool {
+        self.inner.allow_thread_spawning()
+    }
+    fn can_spawn_threads(&self) -> bool {
+        self.inner.can_spawn_threads()
+    }
+    fn spawn_thread(&self, f: Box<dyn FnOnce() + Send>) -> Result<(), String> {
+        self.inner.spawn_thread(f)
+    }
+    fn show_image(&self, image: DynamicImage, label: Option<&str>) -> Result<(), String> {
+        self.inner.show_image(image, label)
+    }
+    fn progress(&self, current: f64, total: f64, label: &str) -> Result<(), String> {
+        self.inner.progress(current, total, label)
+    }
+    fn show_gif(&self, gif_bytes: Vec<u8>, label: Option<&str>) -> Result<(), String> {
+        self.inner.show_gif(gif_bytes, label)
+    }
+    fn play_audio(&self, wave_bytes: Vec<u8>, label: Option<&str>) -> Result<(), String> {
+        self.inner.play_audio(wave_bytes, label)
+    }
+    fn window_show(&self, name: &str, image: DynamicImage) -> Result<(), String> {
+        let time = *self.clock.lock();
+        let mut windows = self.windows.lock();
+        let window = windows.entry(name.into()).or_default();
+        if self
+            .frame_limit
+            .is_some_and(|limit| window.frames.len() >= limit)
+        {
+            return Err(format!(
+                "Window {name:?} was closed after the headless frame limit of {} frames",
+                window.frames.len()
+            ));
+        }
+        window.open = true;
+        window.frames.push(HeadlessFrame { time, image });
+        Ok(())
+    }
+    fn window_close(&self, name: &str) -> Result<(), String> {
+        if let Some(window) = self.windows.lock().get_mut(name) {
+            window.open = false;
+        }
+        Ok(())
+    }
+    fn window_events(&self, name: &str) -> Result<Vec<WindowEvent>, String> {
+        let mut windows = self.windows.lock();
+        let Some(window) = windows.get_mut(name) else {
+            return Ok(Vec::new());
+        };
+        let shown = window.frames.len();
+        let mut events = Vec::new();
+        let mut scripted = self.events.lock();
+        scripted.retain(|e| {
+            let ready = e.window == name && e.frame <= shown;
+            if ready {
+                events.push(e.event.clone());
+            }
+            !ready
+        });
+        // Reaching the frame limit closes the window
+        if window.open && self.frame_limit.is_some_and(|limit| shown >= limit) {
+            window.open = false;
+            events.push(WindowEvent::Close);
+        }
+        Ok(events)
+    }
+    fn audio_sample_rate(&self) -> u32 {
+        self.inner.audio_sample_rate()
+    }
+    fn stream_audio(&self, f: AudioStreamFn) -> Result<(), String> {
+        self.inner.stream_audio(f)
+    }
+    fn now(&self) -> f64 {
+        *self.clock.lock()
+    }
+    fn tcp_listen(&self, addr: &str) -> Result<Handle, String> {
+        self.inner.tcp_listen(addr)
+    }
+    fn tls_listen(&self, addr: &str, cert: &[u8], key: &[u8]) -> Result<Handle, String> {
+        self.inner.tls_listen(addr, cert, key)
+    }
+    fn tcp_accept(&self, handle: Handle) -> Result<Handle, String> {
+        self.inner.tcp_accept(handle)
+    }
+    fn tcp_connect(&self, addr: &str) -> Result<Handle, String> {
+        self.inner.tcp_connect(addr)
+    }
+    fn tls_connect(&self, addr: &str) -> Result<Handle, String> {
+        self.inner.tls_connect(addr)
+    }
+    fn tcp_addr(&self, handle: Handle) -> Result<SocketAddr, String> {
+        self.inner.tcp_addr(handle)
+    }
+    fn tcp_set_non_blocking(&self, handle: Handle, non_blocking: bool) -> Result<(), String> {
+        self.inner.tcp_set_non_blocking(handle, non_blocking)
+    }
+    fn tcp_set_read_timeout(
+        &self,
+        handle: Handle,
+        timeout: Option<Duration>,
+    ) -> Result<(), String> {
+        self.inner.tcp_set_read_timeout(handle, timeout)
+    }
+    fn tcp_set_write_timeout(
+        &self,
+        handle: Handle,
+        timeout: Option<Duration>,
+    ) -> Result<(), String> {
+        self.inner.tcp_set_write_timeout(handle, timeout)
+    }
+    fn udp_bind(&self, addr: &str) -> Result<Handle, String> {
+        self.inner.udp_bind(addr)
+    }
+    fn udp_addr(&self, handle: Handle) -> Result<SocketAddr, String> {
+        self.inner.udp_addr(handle)
+    }
+    fn udp_send(&self, addr: &str, packet: &[u8]) -> Result<(), String> {
+        self.inner.udp_send(addr, packet)
+    }
+    fn udp_receive(
+        &self,
+        handle: Handle,
+        timeout: Option<Duration>,
+    ) -> Result<Option<Vec<u8>>, String> {
+        self.inner.udp_receive(handle, timeout)
+    }
+    fn dns_lookup(&self, record_type: &str, name: &str) -> Result<Vec<String>, String> {
+        self.inner.dns_lookup(record_type, name)
+    }
+    fn ping(&self, addr: &str, timeout: Option<Duration>) -> Result<Option<f64>, String> {
+        self.inner.ping(addr, timeout)
+    }
+    fn interface_addrs(&self) -> Result<Vec<(String, IpAddr)>, String> {
+        self.inner.interface_addrs()
+    }
+    fn mqtt_connect(&self, options: &MqttOptions) -> Result<Handle, String> {
+        self.inner.mqtt_connect(options)
+    }
+    fn mqtt_subscribe(&self, handle: Handle, topic: &str, qos: u8) -> Result<(), String> {
+        self.inner.mqtt_subscribe(handle, topic, qos)
+    }
+    fn mqtt_publish(&self, handle: Handle, message: &MqttMessage) -> Result<(), String> {
+        self.inner.mqtt_publish(handle, message)
+    }
+    fn mqtt_receive(
+        &self,
+        handle: Handle,
+        timeout: Option<Duration>,
+    ) -> Result<Option<MqttMessage>, String> {
+        self.inner.mqtt_receive(handle, timeout)
+    }
+    fn smtp_send(
+        &self,
+        server: &SmtpServer,
+        auth: Option<&SmtpAuth>,
+        email: &Email,
+    ) -> Result<(), String> {
+        self.inner.smtp_send(server, auth, email)
+    }
+    fn keyring_get(&self, service: &str, account: &str) -> Result<Option<String>, String> {
+        self.inner.keyring_get(service, account)
+    }
+    fn keyring_set(&self, service: &str, account: &str, secret: &str) -> Result<(), String> {
+        self.inner.keyring_set(service, account, secret)
+    }
+    fn keyring_delete(&self, service: &str, account: &str) -> Result<(), String> {
+        self.inner.keyring_delete(service, account)
+    }
+    fn gpio_read(&self, chip: usize, pin: u32) -> Result<bool, String> {
+        self.inner.gpio_read(chip, pin)
+    }
+    fn gpio_write(&self, chip: usize, pin: u32, value: bool) -> Result<(), String> {
+        self.inner.gpio_write(chip, pin, value)
+    }
+    fn i2c_transfer(
+        &self,
+        bus: usize,
+        addr: u16,
+        write: &[u8],
+        read_len: usize,
+    ) -> Result<Vec<u8>, String> {
+        self.inner.i2c_transfer(bus, addr, write, read_len)
+    }
+    fn close(&self, handle: Handle) -> Result<(), String> {
+        self.inner.close(handle)
+    }
+    fn invoke(&self, path: &str) -> Result<(), String> {
+        self.inner.invoke(path)
+    }
+    fn run_command_inherit(&self, command: &str, args: &[&str]) -> Result<i32, String> {
+        self.inner.run_command_inherit(command, args)
+    }
+    fn run_command_capture(
+        &self,
+        command: &str,
+        args: &[&str],
+    ) -> Result<(i32, String, String), String> {
+        self.inner.run_command_capture(command, args)
+    }
+    fn run_command_stream(&self, command: &str, args: &[&str]) -> Result<[Handle; 3], String> {
+        self.inner.run_command_stream(command, args)
+    }
+    fn change_directory(&self, path: &str) -> Result<(), String> {
+        self.inner.change_directory(path)
+    }
+    fn https_get(&self, request: &str, handle: Handle) -> Result<String, String> {
+        self.inner.https_get(request, handle)
+    }
+    fn webcam_capture(&self, index: usize) -> Result<WebcamImage, String> {
+        self.inner.webcam_capture(index)
+    }
+    fn ffi(
+        &self,
+        file: &str,
+        result_ty: FfiType,
+        name: &str,
+        arg_tys: &[FfiType],
+        args: &[Value],
+    ) -> Result<Value, String> {
+        self.inner.ffi(file, result_ty, name, arg_tys, args)
+    }
+    fn mem_copy(&self, ty: FfiType, ptr: *const (), len: usize) -> Result<Value, String> {
+        self.inner.mem_copy(ty, ptr, len)
+    }
+    fn ffi_callback(
+        &self,
+        result_ty: FfiType,
+        arg_tys: &[FfiType],
+        f: FfiCallbackFn,
+    ) -> Result<Value, String> {
+        self.inner.ffi_callback(result_ty, arg_tys, f)
+    }
+    fn mem_free(&self, ptr: *const ()) -> Result<(), String> {
+        self.inner.mem_free(ptr)
+    }
+    fn plugin_call(&self, name: &str, arg: &str) -> Result<String, String> {
+        self.inner.plugin_call(name, arg)
+    }
+    fn python_call(&self, function: &str, args: &[Value]) -> Result<Value, String> {
+        self.inner.python_call(function, args)
+    }
+    fn load_git_module(&self, url: &str, target: GitTarget) -> Result<PathBuf, String> {
+        self.inner.load_git_module(url, target)
+    }
+    fn timezone(&self) -> Result<f64, String> {
+        self.inner.timezone()
+    }
+    fn breakpoint(&self, env: &Uiua) -> Result<bool, String> {
+        self.inner.breakpoint(env)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    #[cfg(feature = "image")]
+    fn headless_windows() {
+        use crate::*;
+        let key = WindowEvent::Key {
+            key: "Space".into(),
+            pressed: true,
+            ctrl: false,
+            alt: false,
+            shift: false,
+        };
+        let events = vec![ScriptedEvent {
+            window: "W".into(),
+            frame: 2,
+            event: key,
+        }];
+        let backend = HeadlessSys::new(SafeSys::default())
+            .with_frame_limit(4)
+            .with_events(events);
+        let mut env = Uiua::with_backend(backend);
+        let code = r#"
+            # Experimental!
+            Events ← ⇌[⍥(
+              ◌&winframe 10
+              &winshow "W" ↯2_3 0.5
+              □&winev "W"
+            )4]
+            ≡(□◇get "type") /◇⊂ Events
+        "#;
+        env.run_str(code).unwrap();
+        let types: Vec<String> = (env.pop("types").unwrap().into_rows())
+            .map(|row| row.unboxed().as_string(&env, "").unwrap())
+            .collect();
+        assert_eq!(types, ["key", "close"]);
+        let backend = env.downcast_backend::<HeadlessSys>().unwrap();
+        assert_eq!(backend.window_names(), ["W"]);
+        assert_eq!(backend.frames("W").len(), 4);
+        let frame_rate = backend.frame_rate("W").unwrap();
+        assert!((frame_rate - 10.0).abs() < 1e-9, "{frame_rate}");
+        let frames = backend.frames_array("W").unwrap();
+        assert_eq!(frames.shape().dims(), [4, 2, 3, 4]);
+        // The clock is simulated, and the first frame does not wait
+        assert!((backend.now() - 0.3).abs() < 1e-9, "{}", backend.now());
+    }
+}
@@ -0,0 +1,150 @@
+//! Access to the operating system's keychain
+//!
+//! On macOS, secrets are stored in the login keychain with the `security` tool.
+//! On other Unix-like systems, they are stored with the Secret Service through `secret-tool`,
+//! which is part of libsecret.
+//!
+//! Secrets are always passed to these tools through stdin so that they do not show up in process lists.
+//!
+//! Windows is not supported. The Credential Manager has no command line tool that can read secrets.
+
+use std::{
+    io::{ErrorKind, Write},
+    process::{Command, Output, Stdio},
+};
+
+#[cfg(target_os = "macos")]
+const TOOL: &str = "security";
+#[cfg(not(target_os = "macos"))]
+const TOOL: &str = "secret-tool";
+
+/// Run the keychain tool, optionally writing to its stdin
+fn run(args: &[&str], input: Option<&str>) -> Result<Output, String> {
+    let mut child = Command::new(TOOL)
+        .args(args)
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            if e.kind() == ErrorKind::NotFound {
+                format!("Accessing the keyring requires {TOOL}, but it is not installed")
+            } else {
+                format!("Failed to run {TOOL}: {e}")
+            }
+        })?;
+    if let Some(input) = input {
+        let mut stdin = child.stdin.take().unwrap();
+        stdin
+            .write_all(input.as_bytes())
+            .map_err(|e| format!("Failed to write to {TOOL}: {e}"))?;
+    }
+    (child.wait_with_output()).map_err(|e| format!("Failed to run {TOOL}: {e}"))
+}
+
+fn failure(output: &Output) -> String {
+    let message = String::from_utf8_lossy(&output.stderr);
+    format!("Keyring access failed: {}", message.trim())
+}
+
+/// Quote an argument for `security`'s interactive mode
+///
+/// Interactive mode reads one command per line, so control characters are rejected
+/// to keep them from ending the command early.
+#[cfg(target_os = "macos")]
+fn quote(s: &str) -> Result<String, String> {
+    if s.chars().any(char::is_control) {
+        return Err("Keyring services, accounts, and secrets \
+            cannot contain control characters"
+            .into());
+    }
+    Ok(format!(
+        "\"{}\"",
+        s.replace('\\', "\\\\").replace('"', "\\\"")
+    ))
+}
+
+/// The exit code `security` uses when an item is not found
+#[cfg(target_os = "macos")]
+const NOT_FOUND: i32 = 44;
+
+/// Get a secret, or `None` if there is none
+pub(crate) fn get(service: &str, account: &str) -> Result<Option<String>, String> {
+    #[cfg(target_os = "macos")]
+    let output = run(
+        &["find-generic-password", "-s", service, "-a", account, "-w"],
+        None,
+    )?;
+    #[cfg(not(target_os = "macos"))]
+    let output = run(&["lookup", "service", service, "account", account], None)?;
+    if output.status.success() {
+        let secret = String::from_utf8_lossy(&output.stdout);
+        return Ok(Some(secret.strip_suffix('\n').unwrap_or(&secret).into()));
+    }
+    #[cfg(target_os = "macos")]
+    let not_found = output.status.code() == Some(NOT_FOUND);
+    // secret-tool fails without a message when there is no secret
+    #[cfg(not(target_os = "macos"))]
+    let not_found = output.stderr.is_empty();
+    if not_found {
+        Ok(None)
+    } else {
+        Err(failure(&output))
+    }
+}
+
+/// Store a secret, replacing any existing one
+pub(crate) fn set(service: &str, account: &str, secret: &str) -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    let output = {
+        let command = format!(
+            "add-generic-password -U -s {} -a {} -w {}\n",
+            quote(service)?,
+            quote(account)?,
+            quote(secret)?
+        );
+        run(&["-i"], Some(&command))?
+    };
+    #[cfg(not(target_os = "macos"))]
+    let output = run(
+        &[
+            "store",
+            "--label",
+            &format!("{service} ({account})"),
+            "service",
+            service,
+            "account",
+            account,
+        ],
+        Some(secret),
+    )?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(failure(&output))
+    }
+}
+
+/// Delete a secret if it exists
+pub(crate) fn delete(service: &str, account: &str) -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    let output = run(
+        &["delete-generic-password", "-s", service, "-a", account],
+        None,
+    )?;
+    #[cfg(not(target_os = "macos"))]
+    let output = run(&["clear", "service", service, "account", account], None)?;
+    #[cfg(target_os = "macos")]
+    let deleted = output.status.success() || output.status.code() == Some(NOT_FOUND);
+    #[cfg(not(target_os = "macos"))]
+    let deleted = output.status.success() || output.stderr.is_empty();
+    if deleted {
+        Ok(())
+    } else {
+        Err(failure(&output))
+    }
+}
@@ -0,0 +1,227 @@
+//! Structured logging
+
+use std::{fmt, path::PathBuf, str::FromStr, sync::Arc};
+
+use parking_lot::Mutex;
+use time::OffsetDateTime;
+
+use crate::{Uiua, UiuaResult, Value};
+
+/// The severity of a log record
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    const ALL: [Self; 5] = [
+        Self::Trace,
+        Self::Debug,
+        Self::Info,
+        Self::Warn,
+        Self::Error,
+    ];
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Trace => "trace",
+            Self::Debug => "debug",
+            Self::Info => "info",
+            Self::Warn => "warn",
+            Self::Error => "error",
+        }
+    }
+    fn from_value(value: &Value, env: &Uiua) -> UiuaResult<Self> {
+        let name = value.as_string(env, "Log level must be a string")?;
+        name.parse().map_err(|e| env.error(e))
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        (Self::ALL.into_iter())
+            .find(|level| level.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                format!(
+                    "Unknown log level {s:?}. \
+                    Expected one of trace, debug, info, warn, or error."
+                )
+            })
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(&self.name().to_uppercase())
+    }
+}
+
+/// How log records are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LogFormat {
+    /// One human-readable line per record
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("Unknown log format {s:?}. Expected text or json.")),
+        }
+    }
+}
+
+/// The logging configuration of a runtime and the threads it spawns
+#[derive(Debug)]
+pub(crate) struct Logger {
+    level: LogLevel,
+    format: LogFormat,
+    /// The file to append records to, or `None` for stderr
+    path: Option<PathBuf>,
+}
+
+impl Default for Logger {
+    fn default() -> Self {
+        Self {
+            level: LogLevel::Info,
+            format: LogFormat::Text,
+            path: None,
+        }
+    }
+}
+
+/// A logger shared between threads
+pub(crate) type SharedLogger = Arc<Mutex<Logger>>;
+
+pub(crate) fn set_level(level: Value, env: &Uiua) -> UiuaResult {
+    let level = LogLevel::from_value(&level, env)?;
+    env.rt.logger.lock().level = level;
+    Ok(())
+}
+
+pub(crate) fn set_output(format: Value, path: Value, env: &Uiua) -> UiuaResult {
+    let format = format.as_string(env, "Log format must be a string")?;
+    let format = format.parse().map_err(|e| env.error(e))?;
+    let path = path.as_string(env, "Log path must be a string")?;
+    let mut logger = env.rt.logger.lock();
+    logger.format = format;
+    logger.path = (!path.is_empty()).then(|| path.into());
+    Ok(())
+}
+
+pub(crate) fn log(level: Value, message: Value, fields: Value, env: &Uiua) -> UiuaResult {
+    let level = LogLevel::from_value(&level, env)?;
+    // The lock is held while writing so that records from different threads are not interleaved
+    let logger = env.rt.logger.lock();
+    if level < logger.level {
+        return Ok(());
+    }
+    let message = message.unpacked().format();
+    let fields: Vec<(String, Value)> = if fields.is_map() {
+        (fields.map_kv().into_iter())
+            .map(|(key, value)| {
+                let key = key.as_string(env, "Log field names must be strings")?;
+                Ok((key, value.unpacked()))
+            })
+            .collect::<UiuaResult<_>>()?
+    } else if fields.row_count() == 0 {
+        Vec::new()
+    } else {
+        return Err(env.error(format!(
+            "Log fields must be a map, but they are {}",
+            fields.type_name_plural()
+        )));
+    };
+    let time = timestamp(env.rt.backend.now());
+    let mut line = match logger.format {
+        LogFormat::Text => {
+            let mut line = format!("{time} {level:<5} {message}");
+            for (key, value) in fields {
+                let value = value.format();
+                if value.is_empty() || value.contains(char::is_whitespace) {
+                    line.push_str(&format!(" {key}={value:?}"));
+                } else {
+                    line.push_str(&format!(" {key}={value}"));
+                }
+            }
+            line
+        }
+        LogFormat::Json => {
+            let mut field_map = serde_json::Map::new();
+            for (key, value) in fields {
+                field_map.insert(key, value.to_json_value(env)?);
+            }
+            serde_json::json!({
+                "time": time,
+                "level": level.name(),
+                "message": message,
+                "fields": field_map,
+            })
+            .to_string()
+        }
+    };
+    line.push('\n');
+    match &logger.path {
+        Some(path) => env.rt.backend.file_append(path, line.as_bytes()),
+        None => env.rt.backend.print_str_stderr(&line),
+    }
+    .map_err(|e| env.error(e))
+}
+
+/// Format a number of seconds since the Unix epoch as an RFC 3339 UTC timestamp
+fn timestamp(seconds: f64) -> String {
+    let time = (time::Duration::checked_seconds_f64(seconds))
+        .and_then(|dur| OffsetDateTime::UNIX_EPOCH.checked_add(dur))
+        .unwrap_or(OffsetDateTime::UNIX_EPOCH);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        time.year(),
+        time.month() as u8,
+        time.day(),
+        time.hour(),
+        time.minute(),
+        time.second(),
+        time.millisecond()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn structured_logging() {
+        use crate::*;
+        let mut env = Uiua::with_backend(MemoryBackend::new().with_time(100.0));
+        let code = r#"
+            # Experimental!
+            &log "info" "Started" map {"port" "name"} {8080 "my app"}
+            &log "debug" "Hidden" []
+            &loglvl "debug"
+            &log "debug" "Shown" []
+            &logout "json" "app.log"
+            &log "warn" "Slow" map {"ms"} {250}
+            &loglvl "error"
+            &log "warn" "Hidden" []
+        "#;
+        env.run_str(code).unwrap();
+        let backend = env.downcast_backend::<MemoryBackend>().unwrap();
+        assert_eq!(
+            String::from_utf8(backend.take_stderr()).unwrap(),
+            "1970-01-01T00:01:40.000Z INFO  Started port=8080 name=\"my app\"\n\
+             1970-01-01T00:01:40.000Z DEBUG Shown\n"
+        );
+        assert_eq!(
+            String::from_utf8(backend.file("app.log").unwrap()).unwrap(),
+            "{\"fields\":{\"ms\":250},\"level\":\"warn\",\
+             \"message\":\"Slow\",\"time\":\"1970-01-01T00:01:40.000Z\"}\n"
+        );
+    }
+}
@@ -0,0 +1,449 @@
+//! A system backend that keeps all IO in memory
+
+use std::{
+    any::Any,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    mem::take,
+    path::{Component, Path, PathBuf},
+    sync::atomic::{self, AtomicU64},
+};
+
+use parking_lot::Mutex;
+
+use super::{Handle, ReadLinesFn, ReadLinesReturnFn, SysBackend};
+use crate::{Uiua, UiuaResult};
+
+/// A system backend that keeps all IO in memory
+///
+/// It has a virtual filesystem, captures stdout and stderr, reads stdin from a preset buffer, and has a fake clock.
+/// This makes it possible to run programs that do IO without touching the real system.
+///
+/// The clock starts at `0` and only advances when the program sleeps.
+/// `&exit` records the exit status and stops the program with an error.
+/// ```
+/// # use uiua::{Uiua, MemoryBackend};
+/// let backend = MemoryBackend::new()
+///     .with_file("in.txt", "Hello")
+///     .with_stdin("World\n");
+/// let mut env = Uiua::with_backend(backend);
+/// env.run_str(r#"&fwa "out.txt" ⊂⊂ &fras "in.txt" ", " &sc"#).unwrap();
+/// let backend = env.downcast_backend::<MemoryBackend>().unwrap();
+/// assert_eq!(backend.file("out.txt").unwrap(), b"Hello, World");
+/// ```
+#[derive(Default)]
+pub struct MemoryBackend {
+    files: Mutex<BTreeMap<PathBuf, Vec<u8>>>,
+    dirs: Mutex<BTreeSet<PathBuf>>,
+    streams: Mutex<HashMap<Handle, MemoryStream>>,
+    next_handle: AtomicU64,
+    stdin: Mutex<(Vec<u8>, usize)>,
+    stdout: Mutex<Vec<u8>>,
+    stderr: Mutex<Vec<u8>>,
+    vars: HashMap<String, String>,
+    clock: Mutex<f64>,
+    exit_status: Mutex<Option<i32>>,
+    allow_thread_spawning: bool,
+}
+
+struct MemoryStream {
+    path: PathBuf,
+    contents: Vec<u8>,
+    pos: usize,
+    writeable: bool,
+}
+
+/// Normalize a path so that equivalent paths map to the same file
+///
+/// `.` components are removed and `..` components remove the previous component.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for comp in path.components() {
+        match comp {
+            Component::CurDir => {}
+            Component::ParentDir => _ = normalized.pop(),
+            comp => normalized.push(comp),
+        }
+    }
+    normalized
+}
+
+impl MemoryBackend {
+    /// Create a new memory backend with an empty filesystem
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Add a file to the filesystem
+    pub fn with_file(self, path: impl AsRef<Path>, contents: impl Into<Vec<u8>>) -> Self {
+        self.set_file(path, contents);
+        self
+    }
+    /// Set the contents of stdin
+    pub fn with_stdin(self, stdin: impl Into<Vec<u8>>) -> Self {
+        *self.stdin.lock() = (stdin.into(), 0);
+        self
+    }
+    /// Set an environment variable
+    pub fn with_var(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.vars.insert(name.into(), value.into());
+        self
+    }
+    /// Set the time on the clock in seconds
+    pub fn with_time(self, seconds: f64) -> Self {
+        *self.clock.lock() = seconds;
+        self
+    }
+    /// Allow spawning threads
+    pub fn with_thread_spawning(mut self) -> Self {
+        self.allow_thread_spawning = true;
+        self
+    }
+    /// Set the contents of a file
+    pub fn set_file(&self, path: impl AsRef<Path>, contents: impl Into<Vec<u8>>) {
+        let path = normalize(path.as_ref());
+        self.files.lock().insert(path, contents.into());
+    }
+    /// Get the contents of a file
+    pub fn file(&self, path: impl AsRef<Path>) -> Option<Vec<u8>> {
+        self.files.lock().get(&normalize(path.as_ref())).cloned()
+    }
+    /// Get the paths of all files
+    pub fn file_paths(&self) -> Vec<PathBuf> {
+        self.files.lock().keys().cloned().collect()
+    }
+    /// Take the captured stdout
+    pub fn take_stdout(&self) -> Vec<u8> {
+        take(&mut *self.stdout.lock())
+    }
+    /// Take the captured stderr
+    pub fn take_stderr(&self) -> Vec<u8> {
+        take(&mut *self.stderr.lock())
+    }
+    /// Get the time on the clock in seconds
+    pub fn time(&self) -> f64 {
+        *self.clock.lock()
+    }
+    /// Get the status the program exited with, if it called `&exit`
+    pub fn exit_status(&self) -> Option<i32> {
+        *self.exit_status.lock()
+    }
+    fn is_dir(&self, path: &Path) -> bool {
+        path.as_os_str().is_empty()
+            || (self.files.lock().keys()).any(|file| file.starts_with(path) && file != path)
+            || self.dirs.lock().contains(path)
+    }
+    fn new_handle(&self, path: PathBuf, contents: Vec<u8>, writeable: bool) -> Handle {
+        let handle = Handle(
+            Handle::FIRST_UNRESERVED.0 + self.next_handle.fetch_add(1, atomic::Ordering::Relaxed),
+        );
+        let stream = MemoryStream {
+            path,
+            contents,
+            pos: 0,
+            writeable,
+        };
+        self.streams.lock().insert(handle, stream);
+        handle
+    }
+    fn stream<T>(
+        &self,
+        handle: Handle,
+        f: impl FnOnce(&mut MemoryStream) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let mut streams = self.streams.lock();
+        f(streams.get_mut(&handle).ok_or("Invalid stream handle")?)
+    }
+}
+
+impl SysBackend for MemoryBackend {
+    fn any(&self) -> &dyn Any {
+        self
+    }
+    fn any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn print_str_stdout(&self, s: &str) -> Result<(), String> {
+        self.stdout.lock().extend_from_slice(s.as_bytes());
+        Ok(())
+    }
+    fn print_str_stderr(&self, s: &str) -> Result<(), String> {
+        self.stderr.lock().extend_from_slice(s.as_bytes());
+        Ok(())
+    }
+    fn print_str_trace(&self, s: &str) {
+        self.stderr.lock().extend_from_slice(s.as_bytes());
+    }
+    fn scan_line_stdin(&self) -> Result<Option<String>, String> {
+        let mut stdin = self.stdin.lock();
+        let (buffer, pos) = &mut *stdin;
+        if *pos >= buffer.len() {
+            return Ok(None);
+        }
+        let rest = &buffer[*pos..];
+        let len = rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len());
+        let line = String::from_utf8_lossy(&rest[..len]);
+        let line = line.strip_suffix('\r').unwrap_or(&line).to_string();
+        *pos = (*pos + len + 1).min(buffer.len());
+        Ok(Some(line))
+    }
+    fn prompt(&self, prompt: &str, _hidden: bool) -> Result<Option<String>, String> {
+        self.print_str_stdout(prompt)?;
+        self.scan_line_stdin()
+    }
+    fn scan_stdin(&self, count: Option<usize>) -> Result<Vec<u8>, String> {
+        let mut stdin = self.stdin.lock();
+        let (buffer, pos) = &mut *stdin;
+        let end = count.map_or(buffer.len(), |count| (*pos + count).min(buffer.len()));
+        let bytes = buffer[*pos..end].to_vec();
+        *pos = end;
+        Ok(bytes)
+    }
+    fn var(&self, name: &str) -> Option<String> {
+        self.vars.get(name).cloned()
+    }
+    fn exit(&self, status: i32) -> Result<(), String> {
+        *self.exit_status.lock() = Some(status);
+        Err(format!("Exited with status {status}"))
+    }
+    fn file_exists(&self, path: &str) -> bool {
+        let path = normalize(path.as_ref());
+        self.files.lock().contains_key(&path) || self.is_dir(&path)
+    }
+    fn list_dir(&self, path: &str) -> Result<Vec<String>, String> {
+        let path = normalize(path.as_ref());
+        if !self.is_dir(&path) {
+            return Err(format!("{} is not a directory", path.display()));
+        }
+        let files = self.files.lock();
+        let dirs = self.dirs.lock();
+        let entries: BTreeSet<String> = (files.keys().chain(dirs.iter()))
+            .filter_map(|entry| entry.strip_prefix(&path).ok())
+            .filter_map(|rest| rest.components().next())
+            .map(|comp| comp.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        Ok(entries.into_iter().collect())
+    }
+    fn is_file(&self, path: &str) -> Result<bool, String> {
+        let path = normalize(path.as_ref());
+        if self.files.lock().contains_key(&path) {
+            Ok(true)
+        } else if self.is_dir(&path) {
+            Ok(false)
+        } else {
+            Err(format!("{} does not exist", path.display()))
+        }
+    }
+    fn delete(&self, path: &str) -> Result<(), String> {
+        let path = normalize(path.as_ref());
+        let mut files = self.files.lock();
+        let mut dirs = self.dirs.lock();
+        let count = files.len() + dirs.len();
+        files.retain(|file, _| !file.starts_with(&path));
+        dirs.retain(|dir| !dir.starts_with(&path));
+        if files.len() + dirs.len() == count {
+            return Err(format!("{} does not exist", path.display()));
+        }
+        Ok(())
+    }
+    fn trash(&self, path: &str) -> Result<(), String> {
+        self.delete(path)
+    }
+    fn read(&self, handle: Handle, count: usize) -> Result<Vec<u8>, String> {
+        self.stream(handle, |stream| {
+            let end = stream.pos.saturating_add(count).min(stream.contents.len());
+            let bytes = stream.contents[stream.pos..end].to_vec();
+            stream.pos = end;
+            Ok(bytes)
+        })
+    }
+    fn read_all(&self, handle: Handle) -> Result<Vec<u8>, String> {
+        self.read(handle, usize::MAX)
+    }
+    fn read_until(&self, handle: Handle, delim: &[u8]) -> Result<Vec<u8>, String> {
+        self.stream(handle, |stream| {
+            let rest = &stream.contents[stream.pos..];
+            let end = (rest.windows(delim.len().max(1)))
+                .position(|w| w == delim)
+                .map_or(rest.len(), |i| i + delim.len());
+            let bytes = rest[..end].to_vec();
+            stream.pos += end;
+            Ok(bytes)
+        })
+    }
+    fn read_lines<'a>(&self, handle: Handle) -> Result<ReadLinesReturnFn<'a>, String> {
+        let bytes = self.read_all(handle)?;
+        Ok(Box::new(
+            move |env: &mut Uiua, mut f: ReadLinesFn| -> UiuaResult {
+                for line in String::from_utf8_lossy(&bytes).lines() {
+                    f(line.into(), env)?;
+                }
+                Ok(())
+            },
+        ))
+    }
+    fn write(&self, handle: Handle, contents: &[u8]) -> Result<(), String> {
+        self.stream(handle, |stream| {
+            if !stream.writeable {
+                return Err("Stream is not writeable".into());
+            }
+            let end = stream.pos + contents.len();
+            if stream.contents.len() < end {
+                stream.contents.resize(end, 0);
+            }
+            stream.contents[stream.pos..end].copy_from_slice(contents);
+            stream.pos = end;
+            Ok(())
+        })
+    }
+    fn create_file(&self, path: &Path) -> Result<Handle, String> {
+        let path = normalize(path);
+        if self.is_dir(&path) {
+            return Err(format!("{} is a directory", path.display()));
+        }
+        self.files.lock().insert(path.clone(), Vec::new());
+        Ok(self.new_handle(path, Vec::new(), true))
+    }
+    fn open_file(&self, path: &Path, write: bool) -> Result<Handle, String> {
+        let path = normalize(path);
+        let contents = (self.files.lock().get(&path).cloned())
+            .ok_or_else(|| format!("File not found: {}", path.display()))?;
+        Ok(self.new_handle(path, contents, write))
+    }
+    fn make_dir(&self, path: &Path) -> Result<(), String> {
+        let path = normalize(path);
+        if self.files.lock().contains_key(&path) {
+            return Err(format!("{} is a file", path.display()));
+        }
+        let mut dirs = self.dirs.lock();
+        for ancestor in path.ancestors() {
+            if !ancestor.as_os_str().is_empty() {
+                dirs.insert(ancestor.to_path_buf());
+            }
+        }
+        Ok(())
+    }
+    fn file_read_all(&self, path: &Path) -> Result<Vec<u8>, String> {
+        let path = normalize(path);
+        (self.files.lock().get(&path).cloned())
+            .ok_or_else(|| format!("File not found: {}", path.display()))
+    }
+    fn file_write_all(&self, path: &Path, contents: &[u8]) -> Result<(), String> {
+        let path = normalize(path);
+        if self.is_dir(&path) {
+            return Err(format!("{} is a directory", path.display()));
+        }
+        self.files.lock().insert(path, contents.to_vec());
+        Ok(())
+    }
+    fn file_append(&self, path: &Path, contents: &[u8]) -> Result<(), String> {
+        let path = normalize(path);
+        if self.is_dir(&path) {
+            return Err(format!("{} is a directory", path.display()));
+        }
+        (self.files.lock().entry(path).or_default()).extend_from_slice(contents);
+        Ok(())
+    }
+    fn sleep(&self, seconds: f64) -> Result<(), String> {
+        *self.clock.lock() += seconds.max(0.0);
+        Ok(())
+    }
+    fn allow_thread_spawning(&self) -> bool {
+        self.allow_thread_spawning
+    }
+    fn now(&self) -> f64 {
+        *self.clock.lock()
+    }
+    fn timezone(&self) -> Result<f64, String> {
+        Ok(0.0)
+    }
+    fn close(&self, handle: Handle) -> Result<(), String> {
+        let stream = (self.streams.lock().remove(&handle)).ok_or("Invalid stream handle")?;
+        if stream.writeable {
+            self.files.lock().insert(stream.path, stream.contents);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn memory_backend() {
+        use crate::*;
+        use std::path::PathBuf;
+        let backend = MemoryBackend::new()
+            .with_file("in/names.txt", "Ada\nAlan\nGrace")
+            .with_stdin("hello\nworld\n")
+            .with_time(100.0);
+        let mut env = Uiua::with_backend(backend);
+        let code = r#"
+            &fwa "out.txt" ⊂⊂ &sc ", " &sc
+            &fwa "count.txt" $"_" ⧻ ⊜□⊸≠@\n &fras "in/names.txt"
+            &sl 2.5
+            &p now
+            &fde "in"
+            &p &fe "in/names.txt"
+            &exit 3
+        "#;
+        let Err(err) = env.run_str(code) else {
+            panic!("Program did not exit");
+        };
+        assert!(err.to_string().contains("Exited with status 3"));
+        let backend = env.downcast_backend::<MemoryBackend>().unwrap();
+        assert_eq!(backend.exit_status(), Some(3));
+        assert_eq!(backend.file("out.txt").unwrap(), b"world, hello");
+        assert_eq!(backend.file("count.txt").unwrap(), b"3");
+        assert_eq!(backend.take_stdout(), b"102.5\n0\n");
+        assert_eq!(
+            backend.file_paths(),
+            ["count.txt", "out.txt"].map(PathBuf::from)
+        );
+    }
+
+    #[test]
+    fn terminal_commands() {
+        use crate::*;
+        let mut env = Uiua::with_backend(MemoryBackend::new());
+        let code = r#"
+            # Experimental!
+            &tclr
+            &tmv [2 5]
+            &tcol Red []
+            &tcol [] [0 0.5 1]
+            &tcurs 0
+        "#;
+        env.run_str(code).unwrap();
+        let backend = env.downcast_backend::<MemoryBackend>().unwrap();
+        assert_eq!(
+            String::from_utf8(backend.take_stdout()).unwrap(),
+            "\x1b[2J\x1b[H\x1b[3;6H\x1b[38;2;255;0;0;49m\x1b[39;48;2;0;128;255m\x1b[?25l"
+        );
+        let Err(err) = env.run_str("# Experimental!\n&tev 0") else {
+            panic!("Reading an event did not fail");
+        };
+        assert!(err.to_string().contains("not supported"));
+    }
+
+    #[test]
+    fn prompt_and_menu() {
+        use crate::*;
+        let backend = MemoryBackend::new().with_stdin("Uiua\nsecret\n5\n2\n");
+        let mut env = Uiua::with_backend(backend);
+        env.run_str(
+            r#"# Experimental!
+            &menu "Pick: " {"red" "blue"} &password "Pass: " &prompt "Name: ""#,
+        )
+        .unwrap();
+        let choice = env.pop_num().unwrap();
+        let password = env.pop_string().unwrap();
+        let name = env.pop_string().unwrap();
+        assert_eq!(
+            (name.as_str(), password.as_str(), choice),
+            ("Uiua", "secret", 1.0)
+        );
+        let backend = env.downcast_backend::<MemoryBackend>().unwrap();
+        assert_eq!(
+            String::from_utf8(backend.take_stdout()).unwrap(),
+            "Name: Pass: 1) red\n2) blue\nPick: Enter a number from 1 to 2\nPick: "
+        );
+    }
+}
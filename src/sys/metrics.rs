@@ -0,0 +1,291 @@
+//! Metrics in the Prometheus text exposition format
+//!
+//! Metrics are shared by every thread in the process, like atomic cells.
+//! A metric name may have labels in Prometheus syntax, like `requests_total{path="/"}`.
+//! Each distinct set of labels is its own series of the metric.
+
+use std::{collections::BTreeMap, fmt::Write, str::FromStr};
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+
+use crate::{Uiua, UiuaResult, Value};
+
+/// The default histogram buckets, which are the same as the official Prometheus clients'
+const DEFAULT_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+static METRICS: Lazy<Mutex<BTreeMap<String, Metric>>> = Lazy::new(Default::default);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MetricKind {
+    Counter,
+    Gauge,
+    Histogram,
+}
+
+impl MetricKind {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Counter => "counter",
+            Self::Gauge => "gauge",
+            Self::Histogram => "histogram",
+        }
+    }
+}
+
+impl FromStr for MetricKind {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "counter" => Ok(Self::Counter),
+            "gauge" => Ok(Self::Gauge),
+            "histogram" => Ok(Self::Histogram),
+            _ => Err(format!(
+                "Unknown metric type {s:?}. Expected counter, gauge, or histogram."
+            )),
+        }
+    }
+}
+
+/// A metric and all of its series
+#[derive(Debug)]
+struct Metric {
+    kind: MetricKind,
+    help: String,
+    /// The upper bounds of the histogram buckets, not including `+Inf`
+    buckets: Vec<f64>,
+    /// The series, keyed by their labels without the braces
+    series: BTreeMap<String, Series>,
+}
+
+impl Metric {
+    fn new(kind: MetricKind) -> Self {
+        Metric {
+            kind,
+            help: String::new(),
+            buckets: DEFAULT_BUCKETS.to_vec(),
+            series: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Series {
+    Value(f64),
+    Histogram {
+        /// The number of observations in each bucket, not cumulative
+        counts: Vec<u64>,
+        sum: f64,
+        count: u64,
+    },
+}
+
+/// Split a metric name into its base name and its labels
+fn parse_name(name: &str) -> Result<(&str, &str), String> {
+    let (base, labels) = match name.split_once('{') {
+        Some((base, rest)) => {
+            let labels = (rest.strip_suffix('}'))
+                .ok_or_else(|| format!("Metric labels in {name:?} must end with }}"))?;
+            (base, labels.trim_end_matches(','))
+        }
+        None => (name, ""),
+    };
+    let valid = base.starts_with(|c: char| c.is_ascii_alphabetic() || "_:".contains(c))
+        && base
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_:".contains(c));
+    if !valid {
+        return Err(format!(
+            "{base:?} is not a valid metric name. Metric names may only contain \
+            ASCII letters, digits, underscores, and colons, and may not start with a digit."
+        ));
+    }
+    Ok((base, labels))
+}
+
+/// Update a series of a metric, registering the metric if it does not exist
+fn update(
+    name: Value,
+    kind: MetricKind,
+    env: &Uiua,
+    f: impl FnOnce(&[f64], &mut Series),
+) -> UiuaResult {
+    let name = name.as_string(env, "Metric name must be a string")?;
+    let (base, labels) = parse_name(&name).map_err(|e| env.error(e))?;
+    let mut metrics = METRICS.lock();
+    let metric = (metrics.entry(base.into())).or_insert_with(|| Metric::new(kind));
+    if metric.kind != kind {
+        return Err(env.error(format!(
+            "{base} is a {}, not a {}",
+            metric.kind.name(),
+            kind.name()
+        )));
+    }
+    let series = (metric.series.entry(labels.into())).or_insert_with(|| match kind {
+        MetricKind::Counter | MetricKind::Gauge => Series::Value(0.0),
+        MetricKind::Histogram => Series::Histogram {
+            counts: vec![0; metric.buckets.len()],
+            sum: 0.0,
+            count: 0,
+        },
+    });
+    f(&metric.buckets, series);
+    Ok(())
+}
+
+pub(crate) fn register(options: Value, env: &Uiua) -> UiuaResult {
+    if !options.is_map() {
+        return Err(env.error(format!(
+            "Metric options must be a map, but they are {}",
+            options.type_name_plural()
+        )));
+    }
+    let mut name = None;
+    let mut kind = None;
+    let mut help = String::new();
+    let mut buckets = None;
+    for (key, val) in options.map_kv() {
+        let key = key.as_string(env, "Metric option names must be strings")?;
+        let val = val.unpacked();
+        match key.as_str() {
+            "name" => name = Some(val.as_string(env, "Metric name must be a string")?),
+            "type" => {
+                let s = val.as_string(env, "Metric type must be a string")?;
+                kind = Some(s.parse::<MetricKind>().map_err(|e| env.error(e))?);
+            }
+            "help" => help = val.as_string(env, "Metric help must be a string")?,
+            "buckets" => {
+                let mut bounds = val.as_nums(env, "Histogram buckets must be a list of numbers")?;
+                bounds.retain(|b| *b != f64::INFINITY);
+                if bounds.windows(2).any(|w| w[0] >= w[1]) {
+                    return Err(env.error("Histogram buckets must be in increasing order"));
+                }
+                buckets = Some(bounds);
+            }
+            _ => {
+                return Err(env.error(format!(
+                    "Unknown metric option {key:?}. The options are name, type, help, and buckets"
+                )))
+            }
+        }
+    }
+    let name = name.ok_or_else(|| env.error("Metric options must include a name"))?;
+    let kind = kind.ok_or_else(|| env.error("Metric options must include a type"))?;
+    let (base, labels) = parse_name(&name).map_err(|e| env.error(e))?;
+    if !labels.is_empty() {
+        return Err(env.error("Metrics are registered without labels"));
+    }
+    if buckets.is_some() && kind != MetricKind::Histogram {
+        return Err(env.error("Only histograms can have buckets"));
+    }
+    let mut metrics = METRICS.lock();
+    let metric = (metrics.entry(base.into())).or_insert_with(|| Metric::new(kind));
+    if metric.kind != kind {
+        return Err(env.error(format!(
+            "{base} is already registered as a {}",
+            metric.kind.name()
+        )));
+    }
+    metric.help = help;
+    if let Some(buckets) = buckets {
+        if metric.buckets != buckets {
+            metric.buckets = buckets;
+            metric.series.clear();
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn counter_add(amount: Value, name: Value, env: &Uiua) -> UiuaResult {
+    let amount = amount.as_num(env, "Counter amount must be a number")?;
+    if amount < 0.0 || amount.is_nan() {
+        return Err(env.error(format!(
+            "Counters can only increase, but the amount is {amount}"
+        )));
+    }
+    update(name, MetricKind::Counter, env, |_, series| {
+        if let Series::Value(value) = series {
+            *value += amount;
+        }
+    })
+}
+
+pub(crate) fn gauge_set(value: Value, name: Value, env: &Uiua) -> UiuaResult {
+    let new = value.as_num(env, "Gauge value must be a number")?;
+    update(name, MetricKind::Gauge, env, |_, series| {
+        if let Series::Value(value) = series {
+            *value = new;
+        }
+    })
+}
+
+pub(crate) fn observe(value: Value, name: Value, env: &Uiua) -> UiuaResult {
+    let value = value.as_num(env, "Observed value must be a number")?;
+    update(name, MetricKind::Histogram, env, |buckets, series| {
+        if let Series::Histogram { counts, sum, count } = series {
+            if let Some(i) = buckets.iter().position(|&bound| value <= bound) {
+                counts[i] += 1;
+            }
+            *sum += value;
+            *count += 1;
+        }
+    })
+}
+
+/// Format every metric in the text exposition format
+pub(crate) fn export() -> String {
+    let metrics = METRICS.lock();
+    let mut text = String::new();
+    for (name, metric) in metrics.iter() {
+        if !metric.help.is_empty() {
+            let help = metric.help.replace('\\', r"\\").replace('\n', r"\n");
+            _ = writeln!(text, "# HELP {name} {help}");
+        }
+        _ = writeln!(text, "# TYPE {name} {}", metric.kind.name());
+        for (labels, series) in &metric.series {
+            match series {
+                Series::Value(value) => {
+                    _ = writeln!(text, "{name}{} {}", braced(labels), number(*value));
+                }
+                Series::Histogram { counts, sum, count } => {
+                    let sep = if labels.is_empty() { "" } else { "," };
+                    let mut cumulative = 0;
+                    for (bound, n) in metric.buckets.iter().zip(counts) {
+                        cumulative += n;
+                        let bound = number(*bound);
+                        _ = writeln!(
+                            text,
+                            "{name}_bucket{{{labels}{sep}le=\"{bound}\"}} {cumulative}"
+                        );
+                    }
+                    _ = writeln!(text, "{name}_bucket{{{labels}{sep}le=\"+Inf\"}} {count}");
+                    _ = writeln!(text, "{name}_sum{} {}", braced(labels), number(*sum));
+                    _ = writeln!(text, "{name}_count{} {count}", braced(labels));
+                }
+            }
+        }
+    }
+    text
+}
+
+fn braced(labels: &str) -> String {
+    if labels.is_empty() {
+        String::new()
+    } else {
+        format!("{{{labels}}}")
+    }
+}
+
+fn number(n: f64) -> String {
+    if n.is_nan() {
+        "NaN".into()
+    } else if n == f64::INFINITY {
+        "+Inf".into()
+    } else if n == f64::NEG_INFINITY {
+        "-Inf".into()
+    } else {
+        n.to_string()
+    }
+}
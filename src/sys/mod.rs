@@ -1,11 +1,37 @@
+mod assets;
+mod atomic;
+mod channel;
+#[cfg(feature = "native_sys")]
+mod dns;
+#[cfg(all(feature = "embedded", target_os = "linux"))]
+pub(crate) mod embedded;
+#[cfg(feature = "native_sys")]
+mod git;
+#[cfg(feature = "image")]
+mod headless;
+#[cfg(all(feature = "keyring", unix))]
+mod keyring;
+mod log;
+mod memory;
+mod metrics;
+mod mqtt;
 #[cfg(feature = "native_sys")]
 pub(crate) mod native;
+mod osc;
+#[cfg(feature = "native_sys")]
+mod package;
+#[cfg(feature = "python")]
+mod python;
+mod replay;
+mod sandbox;
+mod smtp;
 
 use std::{
     any::Any,
+    collections::BTreeMap,
     fmt,
     mem::take,
-    net::SocketAddr,
+    net::{IpAddr, SocketAddr},
     path::{Path, PathBuf},
     sync::{Arc, OnceLock},
     time::Duration,
@@ -19,14 +45,28 @@ use parking_lot::Mutex;
 use serde::*;
 use time::UtcOffset;
 
+#[cfg(feature = "image")]
+pub use self::headless::*;
+pub(crate) use self::{
+    assets::AssetSys, atomic::SharedAtomicCells, channel::SharedChannels, log::SharedLogger,
+};
 #[cfg(feature = "native_sys")]
-pub use self::native::*;
+pub use self::{git::*, native::*, package::*};
+pub use self::{
+    memory::*,
+    mqtt::{MqttMessage, MqttOptions},
+    replay::*,
+    sandbox::*,
+    smtp::{Email, SmtpAuth, SmtpSecurity, SmtpServer},
+};
 use crate::{
     algorithm::{multi_output, validate_size},
     cowslice::cowslice,
     get_ops,
     primitive::PrimDoc,
-    Array, Boxed, FfiType, Ops, Primitive, Purity, Uiua, UiuaErrorKind, UiuaResult, Value,
+    wasm::{self, WasmType},
+    Array, Boxed, FfiCallbackFn, FfiType, Ops, Primitive, Purity, Uiua, UiuaErrorKind, UiuaResult,
+    Value,
 };
 
 /// The text of Uiua's example module
@@ -152,10 +192,22 @@ pub enum SysOpClass {
     Filesystem,
     StdIO,
     Env,
+    Terminal,
     Stream,
     Command,
     Media,
+    Window,
     Tcp,
+    Network,
+    Osc,
+    Mqtt,
+    Email,
+    Keyring,
+    Embedded,
+    Channel,
+    Atomic,
+    Log,
+    Metrics,
     Ffi,
     Misc,
 }
@@ -220,12 +272,50 @@ sys_op! {
     ///
     /// See also: [&p], [&epf]
     (1(0), PrintErr, StdIO, "&ep", "print error with newline", Mutating),
+    /// Report the progress of a long computation
+    ///
+    /// Expects the current amount of work done, the total amount of work, and a label string.
+    /// In a terminal, this draws a progress bar on stderr, so it does not mix with normal output.
+    /// The bar is finished when the current amount reaches the total.
+    /// In the pad, the latest progress for each label is shown as a progress bar in the output.
+    ///
+    /// ex: # Experimental!
+    ///   : &progress 3 10 "Loading"
+    (3(0), Progress, StdIO, "&progress", "report progress", Mutating),
     /// Read a line from stdin
     ///
     /// The normal output is a string.
     /// If EOF is reached, the number `0` is returned instead.
     /// Programs that wish to properly handle EOF should check for this.
     (0, ScanLine, StdIO, "&sc", "scan line", Mutating),
+    /// Prompt the user for a line of input
+    ///
+    /// Expects a prompt string, which is printed before reading the line.
+    /// In a terminal, the line can be edited with the arrow keys, and previous inputs can be recalled with up and down.
+    ///
+    /// Like [&sc], the normal output is a string, and the number `0` is returned if EOF is reached.
+    ///
+    /// See also: [&password], [&menu]
+    (1, Prompt, StdIO, "&prompt", "prompt for input", Mutating),
+    /// Prompt the user for a line of input without showing what they type
+    ///
+    /// Expects a prompt string, which is printed before reading the line.
+    /// Like [&sc], the normal output is a string, and the number `0` is returned if EOF is reached.
+    ///
+    /// On the web, the input is shown in a dialog and is not hidden.
+    ///
+    /// See also: [&prompt]
+    (1, Password, StdIO, "&password", "prompt for hidden input", Mutating),
+    /// Ask the user to choose from a list of options
+    ///
+    /// Expects a prompt string and a list of option strings.
+    /// The options are printed with a number next to each one, and the user is prompted until they enter a valid number.
+    /// Returns the index of the chosen option.
+    ///
+    /// If EOF is reached, the number [infinity] is returned instead.
+    ///
+    /// See also: [&prompt]
+    (2, Menu, StdIO, "&menu", "choose from a menu", Mutating),
     /// Get the size of the terminal
     ///
     /// The result is a 2-element array of the height and width of the terminal.
@@ -244,6 +334,48 @@ sys_op! {
     /// [un][&raw] will return the current state of the terminal.
     /// [under][&raw] will set raw mode, and then revert it to the previous state.
     (1(0), RawMode, Env, "&raw", "set raw mode", Mutating),
+    /// Move the terminal cursor
+    ///
+    /// Expects a 2-element array of a row and a column, starting from `0` at the top left.
+    /// Row comes first to match [&ts].
+    ///
+    /// Text printed with [&pf] afterwards starts at the new position.
+    (1(0), TermMove, Terminal, "&tmv", "terminal - move cursor", Mutating),
+    /// Clear the terminal screen
+    ///
+    /// The cursor is moved to the top left.
+    (0(0), TermClear, Terminal, "&tclr", "terminal - clear", Mutating),
+    /// Set the terminal's text colors
+    ///
+    /// Expects a foreground color and a background color.
+    /// Colors are lists of red, green, and blue values between `0` and `1`, like the color constants.
+    /// An empty list resets the color to the terminal's default.
+    /// For example, `&tcol Black Yellow` prints black text on a yellow background, and `&tcol [] []` resets both colors.
+    ///
+    /// Colors stay set until they are changed, so it is a good idea to reset them when the program is done.
+    (2(0), TermColor, Terminal, "&tcol", "terminal - set colors", Mutating),
+    /// Show or hide the terminal cursor
+    ///
+    /// Expects a boolean.
+    (1(0), TermCursor, Terminal, "&tcurs", "terminal - show cursor", Mutating),
+    /// Enable or disable mouse events
+    ///
+    /// Expects a boolean.
+    /// While enabled, [&tev] returns mouse events, and the terminal does not handle the mouse itself.
+    (1(0), TermMouse, Terminal, "&tmouse", "terminal - capture mouse", Mutating),
+    /// Read a terminal event
+    ///
+    /// Expects a timeout in seconds.
+    /// A timeout of [infinity] waits until there is an event. A timeout of `0` does not wait at all.
+    /// Returns a map describing the event, or an empty map if there was no event before the timeout.
+    ///
+    /// The map's `"type"` is one of `"key"`, `"mouse"`, or `"resize"`. The other keys depend on the type.
+    /// - Key events have a `"key"`, which is either the typed character or a name like `"enter"`, `"esc"`, `"up"`, or `"f1"`. They also have `"ctrl"`, `"alt"`, and `"shift"` booleans.
+    /// - Mouse events have a `"kind"`, which is one of `"down"`, `"up"`, `"drag"`, `"move"`, `"scroll up"`, or `"scroll down"`. They have a `"row"` and a `"col"`, and presses, releases, and drags have a `"button"`, which is one of `"left"`, `"right"`, or `"middle"`. Mouse events must be enabled with [&tmouse].
+    /// - Resize events have the new `"rows"` and `"cols"` of the terminal.
+    ///
+    /// Without [&raw] mode, key events are not available until enter is pressed.
+    (1, TermEvent, Terminal, "&tev", "terminal - read event", Mutating),
     /// Get the command line arguments
     ///
     /// The first element will always be the name of your script
@@ -284,10 +416,19 @@ sys_op! {
     ///
     /// The inverse sets the clipboard, expecting a string.
     /// ex: °&clip +@A⇡6 # Try running then pasting!
+    ///
+    /// See also: [&clipw]
     (0, Clip, Misc, "&clip", "get clipboard contents"),
+    /// Set the contents of the clipboard
+    ///
+    /// Expects a string. This is the same as [un][&clip].
+    /// On the web, this does nothing if the page is not allowed to access the clipboard.
+    /// ex: # Experimental!
+    ///   : &clipw +@A⇡6 # Try running then pasting!
+    (1(0), ClipWrite, Misc, "&clipw", "write clipboard contents", Mutating),
     /// Sleep for n seconds
     ///
-    /// On the web, this example will hang for 1 second.
+    /// On the web, the page does not hang. Instead, output after the sleep is shown after a delay.
     /// ex: ⚂ &sl 1
     (1(0), Sleep, Misc, "&sl", "sleep", Mutating),
     /// Read characters formed by at most n bytes from a stream
@@ -328,6 +469,22 @@ sys_op! {
     /// Additional arguments to the function will be bellow the line.
     /// Outputs in excess of the number of accumulators will be collected into arrays.
     (1[1], ReadLines, Stream, "&rl", "read lines", Mutating),
+    /// Read fixed-size chunks of bytes from a stream
+    ///
+    /// Expects a chunk size and a stream handle.
+    /// [&rc] calls its function on each chunk of at most that many bytes without reading the entire stream into memory.
+    /// For each chunk, it will be pushed onto the stack as a byte array and the function will be called.
+    /// Additional arguments to the function will be bellow the chunk and act as accumulators.
+    /// ex: # Experimental!
+    ///   : &rc(+⧻) 4 &fo "example.txt" 0
+    /// Outputs in excess of the number of accumulators will be collected into arrays.
+    /// ex: # Experimental!
+    ///   : &rc(⧻) 4 &fo "example.txt"
+    /// ex: # Experimental!
+    ///   : &rc(⟜+⧻) 4 &fo "example.txt" 0
+    ///
+    /// See also: [&rb]
+    (2[1], ReadChunks, Stream, "&rc", "read chunks", Mutating),
     /// Write an array to a stream
     ///
     /// If the stream is a file, the file may not be written to until it is closed with [&cl].
@@ -342,6 +499,19 @@ sys_op! {
     ///
     /// This will close files, tcp listeners, and tcp sockets.
     (1(0), Close, Stream, "&cl", "close handle", Mutating),
+    /// Call a function with a handle and close the handle afterwards
+    ///
+    /// The handle is passed to the function as its first argument.
+    /// It is closed with [&cl] after the function returns, even if the function errors.
+    /// ex: # Experimental!
+    ///   : &use(&w "Hello, world!") &fc "file.txt"
+    ///   : &fras "file.txt"
+    /// This is unlike [under][&fo] and similar functions, which do not close the handle if their function errors.
+    /// ex: # Experimental!
+    ///   : ⍣(&use(⍤"Oh no!" 0◌) &fc "file.txt")&p
+    ///
+    /// The function should not close the handle itself.
+    (1[1], Use, Stream, "&use", "use handle", Mutating),
     /// Open a file and return a handle to it
     ///
     /// ex: &fo "example.txt"
@@ -418,6 +588,7 @@ sys_op! {
     /// The file will be created if it does not exist and overwritten if it does.
     ///
     /// The editor on the website has a virtual filesystem. Files written with [&fwa] can be read with [&fras] or [&frab].
+    /// Files written in the pad are kept between visits.
     /// ex: Path ← "test.txt"
     ///   : &fwa Path +@A⇡26
     ///   : &fras Path
@@ -489,6 +660,45 @@ sys_op! {
     /// On the web, this will simply use the function to generate a fixed amount of audio.
     /// How long the audio is can be configured in the editor settings.
     (0(0)[1], AudioStream, Media, "&ast", "audio - stream", Mutating),
+    /// Show an image in a window
+    ///
+    /// Expects a window name and an image.
+    /// If no window with the name is open, one is opened. The name is also the window's title.
+    /// The image replaces whatever the window was showing before, and it is scaled to fit the window.
+    /// Any number of windows can be open at once.
+    ///
+    /// The image's format must conform to that of [img].
+    ///
+    /// Showing an image on each iteration of a loop that also uses [&winev] and [&winframe] makes an interactive program like a small game.
+    ///
+    /// Windows are only supported by the native interpreter when it is built with the `window` feature.
+    (2(0), WindowShow, Window, "&winshow", "window - show image", Mutating),
+    /// Close a window
+    ///
+    /// Expects a window name.
+    /// Closing a window that is not open does nothing.
+    (1(0), WindowClose, Window, "&wincl", "window - close", Mutating),
+    /// Get the input events of a window
+    ///
+    /// Expects a window name.
+    /// Returns a list of boxed maps describing the events that happened in the window since the last call.
+    /// This never waits. If there were no events, the list is empty.
+    ///
+    /// The map's `"type"` is one of `"key"`, `"mouse"`, or `"close"`. The other keys depend on the type.
+    /// - Key events have a `"key"`, which is the name of the key, like `"A"`, `"Space"`, `"Enter"`, or `"ArrowUp"`. They have a `"pressed"` boolean, which is false when the key is released, and `"ctrl"`, `"alt"`, and `"shift"` booleans.
+    /// - Mouse events have a `"kind"`, which is one of `"down"`, `"up"`, `"move"`, `"scroll up"`, or `"scroll down"`. They have an `"x"` and a `"y"` in pixels of the shown image, and presses and releases have a `"button"`, which is one of `"left"`, `"right"`, or `"middle"`.
+    /// - Close events mean that the window was closed. Showing another image in it with [&winshow] opens it again.
+    (1, WindowEvents, Window, "&winev", "window - poll events", Mutating),
+    /// Wait for the next frame
+    ///
+    /// Expects a frame rate in frames per second.
+    /// Waits until a frame's worth of time has passed since the last call on the same thread.
+    /// Returns the number of seconds since the last call, which is `0` for the first call.
+    ///
+    /// This keeps loops that show images with [&winshow] at a steady pace,
+    /// and the returned time can be used to move things at a steady speed even if frames are late.
+    /// A frame rate of [infinity] does not wait at all.
+    (1, WindowFrame, Window, "&winframe", "window - wait for next frame", Mutating),
     /// Create a TCP listener and bind it to an address
     ///
     /// Use [&tcpa] on the returned handle to accept connections.
@@ -562,7 +772,232 @@ sys_op! {
     /// - 2 trailing newlines (if there is no body)
     /// - The HTTP version
     /// - The `Host` header (if not defined)
+    ///
+    /// On the web, requests are made with `fetch`, so the server must allow cross-origin requests.
     (2, HttpsWrite, Tcp, "&httpsw", "https - Make an HTTP(S) request", Mutating),
+    /// Look up DNS records
+    ///
+    /// Expects a record type and a domain name.
+    /// The record type must be `"A"` for IPv4 addresses, `"AAAA"` for IPv6 addresses, or `"TXT"` for text records.
+    /// Returns a list of boxed strings.
+    /// ex! # Experimental!
+    ///   : &dnslookup "A" "example.com"
+    ///   : &dnslookup "TXT" "example.com"
+    /// Addresses are looked up with the system's resolver, so they include names in the hosts file.
+    /// ex! # Experimental!
+    ///   : &dnslookup "A" "localhost"
+    /// Text records are looked up with the DNS servers in `/etc/resolv.conf`.
+    (2, DnsLookup, Network, "&dnslookup", "dns lookup", Mutating),
+    /// Check whether a host is reachable
+    ///
+    /// Expects a timeout in seconds and an address.
+    /// If the address has no port, port `80` is used.
+    /// Returns the number of seconds it took for the host to respond, or [infinity] if it did not respond before the timeout.
+    /// ex! # Experimental!
+    ///   : &ping 2 "example.com"
+    ///   : &ping 2 "192.168.1.1:22"
+    /// Unlike the `ping` command, which requires special permissions on many systems, [&ping] starts a TCP connection and closes it without sending anything.
+    /// A host that refuses the connection still counts as reachable.
+    (2, Ping, Network, "&ping", "ping", Mutating),
+    /// Get the addresses of the network interfaces
+    ///
+    /// Returns a map from interface names to lists of IPv4 and IPv6 addresses.
+    /// ex! # Experimental!
+    ///   : &ifaddrs
+    ///   : get "lo" &ifaddrs
+    /// This is only supported on Unix-like systems.
+    (0, InterfaceAddrs, Network, "&ifaddrs", "network interface addresses", Mutating),
+    /// Create a UDP socket for receiving OSC messages
+    ///
+    /// Expects an address to bind to, like `"127.0.0.1:57121"`. A port of `0` lets the system choose one.
+    /// Returns a socket handle. Use [&oscr] to receive messages with it.
+    /// [under][&oscl] calls [&cl] automatically.
+    (1, OscListen, Osc, "&oscl", "osc - listen", Mutating),
+    /// Send an OSC message over UDP
+    ///
+    /// Expects a message and the address to send it to.
+    /// An [Open Sound Control](https://opensoundcontrol.stanford.edu) message is a list of boxes. The first is the address pattern, and the rest are the arguments.
+    /// ex! # Experimental!
+    ///   : &oscs {"/s_new" "default" 1000 0 0 "freq" 440} "127.0.0.1:57110"
+    /// Arguments are converted to OSC types automatically.
+    /// - Integers become `int32`s, and other numbers become `float32`s
+    /// - Strings become `string`s
+    /// - Byte arrays, like those returned by [utf₈], become `blob`s
+    /// - Other lists become OSC arrays
+    ///
+    /// A bundle is a list of boxes that starts with `"#bundle"` and a time, followed by the messages and bundles it contains.
+    /// The time is in seconds since the Unix epoch, like the time returned by [now]. A time of `0` means immediately.
+    /// ex! # Experimental!
+    ///   : &oscs {"#bundle" +0.5 now {"/a" 1} {"/b" 2}} "127.0.0.1:57120"
+    ///
+    /// Messages are sent from a socket that the system chooses.
+    (2(0), OscSend, Osc, "&oscs", "osc - send", Mutating),
+    /// Receive an OSC message over UDP
+    ///
+    /// Expects a timeout in seconds and a socket handle from [&oscl].
+    /// A timeout of [infinity] waits until a message arrives.
+    /// If no message arrives before the timeout, an error is thrown. The error can be caught with [try].
+    /// ex! # Experimental!
+    ///   : ⍜(&oscl "127.0.0.1:57121"|&oscr ∞)
+    ///
+    /// Messages and bundles are returned in the same form that [&oscs] takes.
+    /// Arguments are converted to Uiua values automatically.
+    /// - Integers, floats, doubles, and time tags become numbers
+    /// - Strings, symbols, and characters become strings and characters
+    /// - Blobs, MIDI messages, and colors become byte arrays
+    /// - `true` and `false` become `1` and `0`
+    /// - `nil` becomes an empty list, and `impulse` becomes [infinity]
+    /// - Arrays become lists of boxes
+    (2, OscReceive, Osc, "&oscr", "osc - receive", Mutating),
+    /// Connect to an MQTT broker
+    ///
+    /// Expects the address of the broker. If it has no port, the default port `1883` is used.
+    /// Returns a client handle. Use [&mqtts] to subscribe to topics and [&mqttp] to publish messages.
+    /// [under][&mqttc] calls [&cl] automatically, which disconnects from the broker.
+    /// ex! # Experimental!
+    ///   : ⍜(&mqttc "localhost"|&mqttp "greetings" "Hello!" 0)
+    ///
+    /// Instead of an address, a map of options can be given.
+    /// - `address` - The address of the broker
+    /// - `client_id` - The client identifier. By default, the broker assigns one.
+    /// - `username` and `password` - Credentials for the broker
+    /// - `keep_alive` - The keep alive interval in seconds. The default is `60`.
+    /// - `clean_session` - Whether to start a new session instead of resuming the previous one. The default is `1`.
+    /// ex! # Experimental!
+    ///   : &mqttc map {"address" "client_id" "clean_session"} {"broker.local:1883" "logger" 0}
+    (1, MqttConnect, Mqtt, "&mqttc", "mqtt - connect", Mutating),
+    /// Subscribe to an MQTT topic
+    ///
+    /// Expects a topic filter, a QoS level, and a client handle from [&mqttc].
+    /// The topic filter can contain the `+` and `#` wildcards.
+    /// The QoS level is the highest quality of service that messages will be delivered with. It must be `0`, `1`, or `2`.
+    /// Use [&mqttr] to receive messages from subscribed topics.
+    /// ex! # Experimental!
+    ///   : C ← &mqttc "localhost"
+    ///   : &mqtts "sensors/+/temperature" 1 C
+    (3(0), MqttSubscribe, Mqtt, "&mqtts", "mqtt - subscribe", Mutating),
+    /// Publish an MQTT message
+    ///
+    /// Expects a topic, a payload, a QoS level, and a client handle from [&mqttc].
+    /// The payload must be a string or a list of bytes.
+    /// The QoS level must be `0`, `1`, or `2`. For levels above `0`, [&mqttp] waits for the broker to acknowledge the message.
+    /// ex! # Experimental!
+    ///   : C ← &mqttc "localhost"
+    ///   : &mqttp "sensors/kitchen/temperature" json 21.5 1 C
+    /// The QoS level can also be paired with a retain flag. The broker sends a retained message to clients that subscribe later.
+    /// ex! # Experimental!
+    ///   : C ← &mqttc "localhost"
+    ///   : &mqttp "lights/porch" "on" 1_1 C
+    (4(0), MqttPublish, Mqtt, "&mqttp", "mqtt - publish", Mutating),
+    /// Receive an MQTT message
+    ///
+    /// Expects a timeout in seconds and a client handle from [&mqttc].
+    /// A timeout of [infinity] waits until a message arrives.
+    /// If no message arrives before the timeout, an error is thrown. The error can be caught with [try].
+    /// Returns a map with the message's `topic`, `payload`, `qos`, and `retain` flag.
+    /// The payload is a string if it is valid UTF-8 and a list of bytes otherwise.
+    /// ex! # Experimental!
+    ///   : C ← &mqttc "localhost"
+    ///   : &mqtts "sensors/#" 0 C
+    ///   : ⍥(&p get "payload" &mqttr ∞ C)10
+    (2, MqttReceive, Mqtt, "&mqttr", "mqtt - receive", Mutating),
+    /// Send an email
+    ///
+    /// Expects a message, credentials, and a server.
+    /// The message is a map with these keys:
+    /// - `from` - The sender's address
+    /// - `to` - A recipient's address or a list of recipients' addresses
+    /// - `cc` and `bcc` - Addresses of recipients who receive a copy. These are optional. Addresses in `bcc` are not listed in the message.
+    /// - `subject` - The subject line
+    /// - `body` - The plain text body
+    /// - `attachments` - A map from file names to contents. This is optional. The contents can be strings or lists of bytes.
+    ///
+    /// The credentials are a map with `username` and `password` keys, or an empty list if the server does not require authentication.
+    /// The server is an address. If it has no port, port `587` is used.
+    /// ex! # Experimental!
+    ///   : Message ← map {"from" "to" "subject" "body"} {"me@example.com" "you@example.com" "Hello" "Hi there!"}
+    ///   : Auth    ← map {"username" "password"} {"me@example.com" &var "SMTP_PASSWORD"}
+    ///   : &smtpsend Message Auth "smtp.example.com"
+    ///
+    /// Attachments can be made with functions like [&frab] or [img].
+    /// ex! # Experimental!
+    ///   : Report ← csv [1_2 3_4]
+    ///   : Message ← map {"from" "to" "subject" "attachments"} {"me@example.com" {"ann@example.com" "bob@example.com"} "Report" map {"report.csv"} {Report}}
+    ///   : &smtpsend Message [] "localhost:25"
+    ///
+    /// The server can also be a map with these keys:
+    /// - `address` - The server's address
+    /// - `security` - One of `"starttls"`, `"tls"`, or `"none"`. The default is `"tls"` for port `465` and `"starttls"` otherwise.
+    /// With `"starttls"`, sending fails if the server does not support encryption.
+    ///
+    /// Sending email requires the `smtp` feature, which is enabled by default.
+    (3(0), SmtpSend, Email, "&smtpsend", "smtp - send email", Mutating),
+    /// Get a secret from the operating system's keychain
+    ///
+    /// Expects a service name and an account name.
+    /// If there is no secret for the service and account, an error is thrown. The error can be caught with [try].
+    /// ex! # Experimental!
+    ///   : Token ← &keyget "api.github.com" "me"
+    ///
+    /// The keychain is only supported by the native interpreter when it is built with the `keyring` feature, which is enabled by default.
+    /// On macOS, the login keychain is used.
+    /// On Linux and other Unix-like systems, the Secret Service is used through `secret-tool`, which must be installed.
+    /// Windows is not supported.
+    (2, KeyringGet, Keyring, "&keyget", "keyring - get secret", Mutating),
+    /// Store a secret in the operating system's keychain
+    ///
+    /// Expects a service name, an account name, and a secret string.
+    /// Any existing secret for the service and account is replaced.
+    /// ex! # Experimental!
+    ///   : &keyset "api.github.com" "me" &sc
+    ///
+    /// See [&keyget] for which keychain is used.
+    (3(0), KeyringSet, Keyring, "&keyset", "keyring - set secret", Mutating),
+    /// Delete a secret from the operating system's keychain
+    ///
+    /// Expects a service name and an account name.
+    /// Deleting a secret that does not exist does nothing.
+    /// ex! # Experimental!
+    ///   : &keydel "api.github.com" "me"
+    ///
+    /// See [&keyget] for which keychain is used.
+    (2(0), KeyringDelete, Keyring, "&keydel", "keyring - delete secret", Mutating),
+    /// Read the value of a GPIO pin
+    ///
+    /// Expects a pin number and returns `0` or `1`.
+    /// The pin can also be a pair of a GPIO chip number and a pin number. The default chip is `0`.
+    /// A pin that has not been written to with [&gpiow] is read as an input.
+    /// ex! # Experimental!
+    ///   : &gpior 17
+    ///
+    /// GPIO is only supported by the native interpreter on Linux when it is built with the `embedded` feature.
+    (1, GpioRead, Embedded, "&gpior", "gpio - read", Mutating),
+    /// Set the value of a GPIO pin
+    ///
+    /// Expects a value of `0` or `1` and a pin number.
+    /// The pin can also be a pair of a GPIO chip number and a pin number. The default chip is `0`.
+    /// The pin becomes an output and keeps its value until it is written to again or the program ends.
+    /// ex! # Experimental!
+    ///   : ⍥(&sl 0.5 &gpiow ⊙17 ⊸¬)10 0
+    ///
+    /// GPIO is only supported by the native interpreter on Linux when it is built with the `embedded` feature.
+    (2(0), GpioWrite, Embedded, "&gpiow", "gpio - write", Mutating),
+    /// Write bytes to an I2C device and read bytes back
+    ///
+    /// I2C is also called IIC.
+    /// Expects the bytes to write, the number of bytes to read, and the address of the device.
+    /// The address can also be a pair of an I2C bus number and an address. The default bus is `1`, which is the one on a Raspberry Pi's pins.
+    /// The write and the read happen in a single transaction, which is how most devices expect a register to be read.
+    /// Returns the bytes that were read.
+    /// For example, this reads the 2-byte temperature register `0` of a TMP102 sensor at address `72`:
+    /// ex! # Experimental!
+    ///   : ÷256 +×256 °⊟ &iic [0] 2 72
+    /// Either side of the transaction can be empty.
+    /// ex! # Experimental!
+    ///   : &iic [1 96] 0 72
+    ///
+    /// I2C is only supported by the native interpreter on Linux when it is built with the `embedded` feature.
+    (3, I2cTransfer, Embedded, "&iic", "i2c - transfer", Mutating),
     /// Capture an image from a webcam
     ///
     /// Takes the index of the webcam to capture from.
@@ -633,11 +1068,47 @@ sys_op! {
     ///   : Add ← Lib {VecII "vec2_add" VecII VecII}
     ///   : Add {[1 2] [3 4]} # [4 6]
     ///
+    /// Structs of any size can be passed and returned by value.
+    ///
     /// If a foreign function returns or has an out-parameter that is a pointer type, a special array is returned representing the pointer. This array is not useful as a normal array, but it can be passed back as an [&ffi] argument, read from with [&memcpy], or freed with [&memfree].
     ///
+    /// Variadic functions can be called by putting `"..."` after the fixed parameters, followed by the types of the variadic arguments for this call.
+    /// Following C's rules, variadic `float`s are passed as `double`s, and integers smaller than `int` are passed as `int`s.
+    /// Length indices for list parameters do not count the `"..."`.
+    /// If we want to call `int printf(const char* format, ...)` from the C standard library, we can do it like this:
+    /// ex! # Experimental!
+    ///   : Lib ← &ffi ⊂□"libc.so.6"
+    ///   : Lib {"int" "printf" "const char*" "..." "int" "double"} {"%d %.2f\n" 5 1.5}
+    ///
+    /// A foreign function that takes a function pointer can be passed one made with [&ffcb].
+    ///
+    /// The `uiua ffi-gen` command can generate a module of [&ffi] wrappers from the declarations in a C header.
+    ///
     /// Coverage of types that are supported for binding is currently best-effort.
     /// If you encounter a type that you need support for, please [open an issue](https://github.com/uiua-lang/uiua/issues/new).
     (2, Ffi, Ffi, "&ffi", "foreign function interface", Mutating),
+    /// Create a foreign function callback
+    ///
+    /// *Warning ⚠️: Foreign function callbacks can lead to undefined behavior if used incorrectly.*
+    ///
+    /// Expects a list of boxed type strings, starting with the return type followed by the parameter types.
+    /// Types are specified in the same way as in [&ffi].
+    /// Returns a pointer to a C function that calls the given Uiua function.
+    /// The pointer can be passed to [&ffi] as a `const void*` argument wherever a function pointer is expected.
+    ///
+    /// The function's signature must match the callback's. It takes one argument for each parameter, with the first parameter on top of the stack, and returns one value unless the return type is `void`.
+    /// It runs in its own copy of the interpreter, so it cannot access or modify the stack it was created on.
+    /// If we have a C function `int apply(int (*f)(int), int x)` in a shared library `example.dll`, we can call it like this:
+    /// ex! # Experimental!
+    ///   : Lib ← &ffi ⊂□"example.dll"
+    ///   : Double ← &ffcb(×2) {"int" "int"}
+    ///   : Lib {"int" "apply" "const void*" "int"} {Double 5} # 10
+    ///
+    /// Pointer parameters other than `const char*` are passed to the function as pointer values, which can be read with [&memcpy].
+    /// If the function throws an error, the callback returns zero, and the error is thrown when the [&ffi] call that invoked it returns.
+    ///
+    /// Callbacks are never freed, so the pointer remains valid for the rest of the program.
+    (1[1], FfiCallback, Ffi, "&ffcb", "foreign function callback", Mutating),
     /// Copy data from a pointer into an array
     ///
     /// *Warning ⚠️: [&memcpy] can lead to undefined behavior if used incorrectly.*
@@ -673,6 +1144,299 @@ sys_op! {
     /// Expects a pointer.
     /// See [&memcpy] for an example.
     (1(0), MemFree, Ffi, "&memfree", "free memory", Mutating),
+    /// Call a function from a plugin
+    ///
+    /// Expects a function name and a single argument.
+    /// Plugins are shared libraries that are loaded from the directory passed to `uiua run` with `--plugin-dir`.
+    /// No plugins are loaded unless a directory is passed.
+    /// They are loaded the first time [&plugin] is called.
+    /// ex! # Experimental!
+    ///   : &plugin "distance" [1_2 4_6] # 5
+    ///
+    /// The argument and return value are passed to and from the plugin as JSON, so they are subject to the same restrictions as [json].
+    /// Multiple arguments can be passed as a list of boxes.
+    ///
+    /// A plugin must export the following C functions:
+    /// - `uint32_t uiua_plugin_abi_version(void)`, which must return `1`
+    /// - `const UiuaPluginFunction* uiua_plugin_functions(void)`, which returns an array of `struct { const char* name; char* (*call)(const char* arg, int* is_error); }` terminated by an entry with a null name
+    /// - `void uiua_plugin_free(char* s)`, which frees strings returned by the plugin's functions
+    ///
+    /// A plugin function that fails should set `*is_error` to a non-zero value and return an error message.
+    /// Rust plugins can use the `PluginFunction` type and `PLUGIN_ABI_VERSION` constant from the `uiua` crate.
+    (2, Plugin, Ffi, "&plugin", "call plugin function", Mutating),
+    /// Call a Python function
+    ///
+    /// Expects the name of a Python function and a list of arguments.
+    /// The name may start with the module the function is in, which is imported the first time it is used. Names without a module refer to Python's builtins.
+    /// ex! # Experimental!
+    ///   : &py "math.gcd" {12 18} # 6
+    /// ex! # Experimental!
+    ///   : &py "len" {"hello"} # 5
+    /// If the name refers to something that is not callable, like `"math.pi"`, it is returned as long as there are no arguments.
+    ///
+    /// Arrays are converted to and from Python values automatically.
+    /// Numbers become `int`s or `float`s, strings become `str`s, and lists of boxes become `list`s. Maps become `dict`s.
+    /// If NumPy is installed, other arrays become NumPy arrays with the same shape, and NumPy arrays that are returned keep their shape.
+    /// Without NumPy, they become nested `list`s.
+    /// Returned `list`s become arrays if their items fit together, and lists of boxes otherwise.
+    /// ex! # Experimental!
+    ///   : &py "numpy.linalg.inv" {[1_2 3_4]}
+    ///
+    /// Python runs in a separate process that is started the first time [&py] is called, so Python state like module globals is kept between calls.
+    /// The `python3` executable is used, or `python` on Windows. Set the `UIUA_PYTHON` environment variable to use a different one.
+    /// Anything that Python code prints goes to stderr.
+    /// Python exceptions are thrown as Uiua errors.
+    (2, Python, Ffi, "&py", "call Python function", Mutating),
+    /// Call a function from a WebAssembly module
+    ///
+    /// Expects a signature array and a list of arguments, like [&ffi].
+    /// The signature is a list of boxed strings containing the path of the `.wasm` file, the result type, the function's name, and the argument types.
+    /// If we have a WASM module `example.wasm` that exports a function `(func (export "add") (param i32 i32) (result i32))`, we can call it like this:
+    /// ex! # Experimental!
+    ///   : &wasm {"example.wasm" "i32" "add" "i32" "i32"} {2 3} # 5
+    ///
+    /// The scalar types are `"i32"`, `"i64"`, `"f32"`, and `"f64"`. `"void"` means a function returns nothing.
+    /// Lists have the type of their elements followed by `[]`, like `"u8[]"` or `"f64[]"`. The element types are `u8`, `i8`, `u16`, `i16`, `u32`, `i32`, `u64`, `i64`, `f32`, and `f64`.
+    /// A string has the type `"str"` and is a list of its UTF-8 bytes.
+    ///
+    /// List arguments are copied into the module's memory with the `(func (export "alloc") (param $size i32) (result i32))` it must export.
+    /// The function receives a list as two `i32` parameters: a pointer and the number of elements.
+    /// A function that returns a list returns an `i64` with the pointer in the high 32 bits and the number of elements in the low 32 bits.
+    /// If the module exports `(func (export "dealloc") (param $ptr i32) (param $size i32))`, it is called to free list arguments after the call and returned lists after they are copied.
+    /// ex! # Experimental!
+    ///   : Sum ← &wasm {"example.wasm" "f64" "sum" "f64[]"}
+    ///   : Sum {[1 2 3 4]} # 10
+    ///
+    /// A module is loaded the first time one of its functions is called, and its memory and globals are kept between calls.
+    /// Modules run in an interpreter, so unlike [&ffi], they cannot access anything but their own memory. They cannot import anything.
+    /// A module that traps throws an error.
+    (2, Wasm, Ffi, "&wasm", "call WebAssembly function", Mutating),
+    /// Create a channel
+    ///
+    /// Expects a capacity, which is the number of values the channel can hold before [&chs] blocks.
+    /// A capacity of [infinity] makes the channel unbounded. A capacity of `0` makes every [&chs] wait for a matching [&chr].
+    /// Returns a channel id. Like thread ids, channel ids are just numbers.
+    /// Unlike [send] and [recv], channels are not tied to a particular thread, so they can be shared between any number of threads.
+    /// ex: # Experimental!
+    ///   : C ← &chan 2
+    ///   : &chs 1 C
+    ///   : &chs 2 C
+    ///   : &chr ∞ C
+    ///   : &chr ∞ C
+    /// ex: # Experimental!
+    ///   : C ← &chan ∞
+    ///   : ◌wait≡spawn(&chs ×10 ⊙C) ⇡3
+    ///   : ⍆[⍥(&chr ∞ C)3]
+    ///
+    /// Channels are not freed automatically. Use [&chcl] to close a channel.
+    (1, Chan, Channel, "&chan", "channel - create", Mutating),
+    /// Send a value on a channel
+    ///
+    /// Expects a value and a channel id.
+    /// If the channel is full, [&chs] blocks until there is room.
+    /// In the web editor, sending on a full channel is an error instead.
+    /// ex: # Experimental!
+    ///   : C ← &chan 1
+    ///   : &chs "Hi!" C
+    ///   : &chr 0 C
+    (2(0), ChanSend, Channel, "&chs", "channel - send", Mutating),
+    /// Receive a value from a channel
+    ///
+    /// Expects a timeout in seconds and a channel id.
+    /// A timeout of [infinity] waits until a value is available. A timeout of `0` does not wait at all.
+    /// If no value is received before the timeout, an error is thrown. The error can be caught with [try].
+    /// ex: # Experimental!
+    ///   : C ← &chan ∞
+    ///   : &chs 5 C
+    ///   : &chr ∞ C
+    /// ex: # Experimental!
+    ///   : ⍣(&chr 0.1)"nothing" &chan ∞
+    /// In the web editor, threads do not run concurrently, so every receive behaves as if the timeout were `0`.
+    (2, ChanRecv, Channel, "&chr", "channel - receive", Mutating),
+    /// Receive a value from whichever of several channels is ready first
+    ///
+    /// Expects a timeout in seconds and a list of channel ids.
+    /// Returns the received value and the index of the channel it came from in the list.
+    /// The timeout works the same as for [&chr].
+    /// ex: # Experimental!
+    ///   : A ← &chan ∞
+    ///   : B ← &chan ∞
+    ///   : &chs "from B" B
+    ///   : &chsel ∞ [A B]
+    (2(2), ChanSelect, Channel, "&chsel", "channel - select", Mutating),
+    /// Close a channel
+    ///
+    /// Values that were sent but not received are discarded.
+    /// Blocked [&chs]s and [&chr]s on the channel in other threads will throw an error once there are no other references to it.
+    /// ex! # Experimental!
+    ///   : C ← &chan ∞
+    ///   : &chcl C
+    ///   : &chr 0 C
+    (1(0), ChanClose, Channel, "&chcl", "channel - close", Mutating),
+    /// Create an atomic cell
+    ///
+    /// Expects an initial value.
+    /// Returns a cell id. Like channel ids, cell ids are just numbers, so they can be captured by [spawn]ed functions.
+    /// An atomic cell holds a single value that any thread can read and update with [&atomld], [&atomcas], and [&atomadd].
+    ///
+    /// Each operation on a cell is atomic. No thread can observe a cell partway through an update, and two updates to the same cell never overlap.
+    /// All operations on a single cell happen in one order that every thread agrees on, and an update is visible to every operation that comes after it in that order.
+    /// Operations on different cells are only ordered relative to each other by the threads that perform them.
+    /// Loading a value copies it out of the cell, so changing the copy does not change the cell.
+    /// ex: # Experimental!
+    ///   : C ← &atom 0
+    ///   : ◌wait≡spawn(&atomadd ⊙C) ⇡5
+    ///   : &atomld C
+    ///
+    /// A counter can be used to share work between threads. Each thread claims the next job index with [&atomadd] until there are none left.
+    /// ex: # Experimental!
+    ///   : Jobs ← [3 1 4 1 5 9]
+    ///   : Next ← &atom 0
+    ///   : Work ← ◌⍢(⊂ⁿ2⊡:Jobs|<⧻Jobs.&atomadd 1 Next)
+    ///   : ⍆/◇⊂wait≡spawn(□Work[]◌) ⇡3
+    ///
+    /// Cells belong to the program that created them and the threads it [spawn]s.
+    /// They are not freed automatically. Use [&atomfr] to free a cell.
+    (1, Atom, Atomic, "&atom", "atomic - create", Mutating),
+    /// Load the value in an atomic cell
+    ///
+    /// Expects a cell id returned by [&atom].
+    /// ex: # Experimental!
+    ///   : &atomld &atom "Hi!"
+    (1, AtomLoad, Atomic, "&atomld", "atomic - load", Mutating),
+    /// Replace the value in an atomic cell if it matches an expected value
+    ///
+    /// Expects an expected value, a new value, and a cell id returned by [&atom].
+    /// If the cell's value [match]es the expected value, it is replaced with the new value and `1` is returned.
+    /// Otherwise, the cell is unchanged and `0` is returned.
+    /// The comparison and replacement happen as a single atomic operation.
+    /// ex: # Experimental!
+    ///   : C ← &atom 5
+    ///   : &atomcas 5 10 C
+    ///   : &atomcas 5 20 C
+    ///   : &atomld C
+    ///
+    /// To update a cell with any function, load its value, compute the new value, and retry until [&atomcas] succeeds.
+    /// ex: # Experimental!
+    ///   : C ← &atom 1
+    ///   : Double ← ⍢()(¬&atomcas ⟜(×2) ⊸&atomld C)
+    ///   : ◌wait≡spawn(Double◌) ⇡5
+    ///   : &atomld C
+    (3, AtomCas, Atomic, "&atomcas", "atomic - compare and swap", Mutating),
+    /// Add to the value in an atomic cell and return the old value
+    ///
+    /// Expects an amount and a cell id returned by [&atom].
+    /// The addition works like [add], so it is pervasive.
+    /// ex: # Experimental!
+    ///   : C ← &atom [0 0]
+    ///   : &atomadd [1 2] C
+    ///   : &atomadd [1 2] C
+    ///   : &atomld C
+    (2, AtomAdd, Atomic, "&atomadd", "atomic - fetch and add", Mutating),
+    /// Free an atomic cell
+    ///
+    /// Expects a cell id returned by [&atom].
+    /// Using the cell after it is freed is an error. Threads that are in the middle of an operation on the cell finish it first.
+    /// ex! # Experimental!
+    ///   : C ← &atom 5
+    ///   : &atomfr C
+    ///   : &atomld C
+    (1(0), AtomFree, Atomic, "&atomfr", "atomic - free", Mutating),
+    /// Log a message
+    ///
+    /// Expects a level, a message, and a map of fields.
+    /// The level is one of `"trace"`, `"debug"`, `"info"`, `"warn"`, or `"error"`.
+    /// Each field's key is a name, and its value is included in the record.
+    /// ex: # Experimental!
+    ///   : &log "info" "Listening" map {"host" "port"} {"localhost" 8080}
+    /// If there are no fields, an empty list can be used instead of a map.
+    /// ex: # Experimental!
+    ///   : &log "warn" "Disk almost full" []
+    ///
+    /// Messages below the minimum level are ignored. The minimum level is `"info"` unless it is changed with [&loglvl].
+    /// Records are written to stderr as text unless this is changed with [&logout].
+    (3(0), LogMessage, Log, "&log", "log", Mutating),
+    /// Set the minimum log level
+    ///
+    /// Expects a level. Messages logged with [&log] below this level are ignored.
+    /// The level is shared with [spawn]ed threads.
+    /// ex: # Experimental!
+    ///   : &loglvl "debug"
+    ///   : &log "debug" "Shown" []
+    ///   : &loglvl "error"
+    ///   : &log "warn" "Not shown" []
+    (1(0), LogLevel, Log, "&loglvl", "log - set level", Mutating),
+    /// Set where and how log records are written
+    ///
+    /// Expects a format and a path.
+    /// The format is either `"text"` for one human-readable line per record, or `"json"` for one JSON object per line.
+    /// Records are appended to the file at the path, which is created if it does not exist. An empty path writes records to stderr.
+    /// ex: # Experimental!
+    ///   : &logout "json" ""
+    ///   : &log "error" "Request failed" map {"status" "path"} {500 "/"}
+    /// For example, `&logout "json" "app.log"` appends JSON lines to `app.log`.
+    (2(0), LogOutput, Log, "&logout", "log - set output", Mutating),
+    /// Register a metric
+    ///
+    /// Expects a map of options.
+    /// - `name` - The name of the metric
+    /// - `type` - One of `"counter"`, `"gauge"`, or `"histogram"`
+    /// - `help` - A description of the metric. This is optional.
+    /// - `buckets` - The upper bounds of a histogram's buckets. This is optional. The default is `[0.005 0.01 0.025 0.05 0.1 0.25 0.5 1 2.5 5 10]`.
+    /// ex: # Experimental!
+    ///   : &metr map {"name" "type" "help"} {"jobs_total" "counter" "Jobs processed"}
+    ///   : &metc 3 "jobs_total"
+    ///   : &metx
+    ///
+    /// Registering a metric is optional. [&metc], [&metg], and [&meth] register metrics without help text the first time they are used.
+    /// Metrics are shared by all threads and are never removed.
+    (1(0), MetricRegister, Metrics, "&metr", "metrics - register", Mutating),
+    /// Increase a counter
+    ///
+    /// Expects an amount and a metric name. The amount cannot be negative.
+    /// The name can have labels in Prometheus syntax. Each distinct set of labels is counted separately.
+    /// ex: # Experimental!
+    ///   : &metc 1 $ requests_total{path="/"}
+    ///   : &metc 1 $ requests_total{path="/about"}
+    ///   : &metc 1 $ requests_total{path="/"}
+    ///   : &metx
+    (2(0), MetricCounter, Metrics, "&metc", "metrics - increase counter", Mutating),
+    /// Set a gauge
+    ///
+    /// Expects a value and a metric name.
+    /// The name can have labels in Prometheus syntax.
+    /// ex: # Experimental!
+    ///   : &metg 21.5 $ temperature_celsius{room="kitchen"}
+    ///   : &metx
+    (2(0), MetricGauge, Metrics, "&metg", "metrics - set gauge", Mutating),
+    /// Observe a value in a histogram
+    ///
+    /// Expects a value and a metric name.
+    /// The name can have labels in Prometheus syntax.
+    /// The value is counted in every bucket whose upper bound it does not exceed. Use [&metr] to change the buckets.
+    /// ex: # Experimental!
+    ///   : &metr map {"name" "type" "buckets"} {"latency_seconds" "histogram" [0.1 0.5 1]}
+    ///   : &meth 0.3 "latency_seconds"
+    ///   : &meth 0.05 "latency_seconds"
+    ///   : &metx
+    ///
+    /// [&tt] can be used to time a function.
+    /// ex! # Experimental!
+    ///   : &meth ⊙◌&tt(&sl 0.2) "sleep_seconds"
+    (2(0), MetricObserve, Metrics, "&meth", "metrics - observe", Mutating),
+    /// Export all metrics in the Prometheus text format
+    ///
+    /// Returns a string that can be served to Prometheus from an HTTP server.
+    /// ex: # Experimental!
+    ///   : &metc 1 "exports_total"
+    ///   : &metx
+    /// The response to a scrape should have the content type `text/plain; version=0.0.4`.
+    /// ex! # Experimental!
+    ///   : Head ← "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\n\r\n"
+    ///   : Respond ← ⊃&cl(&w utf₈ ⊂Head &metx ◌⊸&ru "\r\n\r\n")
+    ///   : L ← &tcpl "0.0.0.0:9090"
+    ///   : ⍢(Respond &tcpa L)1
+    (0, MetricExport, Metrics, "&metx", "metrics - export", Mutating),
 }
 
 /// A handle to an IO stream
@@ -680,7 +1444,7 @@ sys_op! {
 /// 0 is stdin, 1 is stdout, 2 is stderr.
 ///
 /// Other handles can be used by files or sockets.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct Handle(pub u64);
 
 impl Handle {
@@ -740,6 +1504,8 @@ pub enum HandleKind {
     TlsListener(SocketAddr),
     TcpSocket(SocketAddr),
     TlsSocket(SocketAddr),
+    UdpSocket(SocketAddr),
+    MqttClient(String),
     ChildStdin(String),
     ChildStdout(String),
     ChildStderr(String),
@@ -753,6 +1519,8 @@ impl fmt::Display for HandleKind {
             Self::TlsListener(addr) => write!(f, "tls listener {}", addr),
             Self::TcpSocket(addr) => write!(f, "tcp socket {}", addr),
             Self::TlsSocket(addr) => write!(f, "tls socket {}", addr),
+            Self::UdpSocket(addr) => write!(f, "udp socket {}", addr),
+            Self::MqttClient(addr) => write!(f, "mqtt client {addr}"),
             Self::ChildStdin(com) => write!(f, "stdin {com}"),
             Self::ChildStdout(com) => write!(f, "stdout {com}"),
             Self::ChildStderr(com) => write!(f, "stderr {com}"),
@@ -794,6 +1562,16 @@ pub trait SysBackend: Any + Send + Sync + 'static {
     fn scan_line_stdin(&self) -> Result<Option<String>, String> {
         Err("Reading from stdin is not supported in this environment".into())
     }
+    /// Print a prompt and read a line from stdin
+    ///
+    /// If `hidden` is true, the typed text should not be shown.
+    fn prompt(&self, prompt: &str, hidden: bool) -> Result<Option<String>, String> {
+        if hidden {
+            return Err("Hidden input is not supported in this environment".into());
+        }
+        self.print_str_stdout(prompt)?;
+        self.scan_line_stdin()
+    }
     /// Read a number of bytes from stdin
     ///
     /// If `count` is `None`, read until EOF.
@@ -831,6 +1609,29 @@ pub trait SysBackend: Any + Send + Sync + 'static {
     fn term_size(&self) -> Result<(usize, usize), String> {
         Err("Getting the terminal size is not supported in this environment".into())
     }
+    /// Control the terminal
+    ///
+    /// By default, this prints the command's ANSI escape sequence to stdout
+    fn term_command(&self, command: TermCommand) -> Result<(), String> {
+        self.print_str_stdout(&command.ansi())
+    }
+    /// Read a terminal event, waiting at most the timeout if there is one
+    fn term_event(&self, timeout: Option<Duration>) -> Result<Option<TermEvent>, String> {
+        Err("Reading terminal events is not supported in this environment".into())
+    }
+    /// Show an image in a named window, opening the window if necessary
+    #[cfg(feature = "image")]
+    fn window_show(&self, name: &str, image: DynamicImage) -> Result<(), String> {
+        Err("Windows are not supported in this environment".into())
+    }
+    /// Close a named window
+    fn window_close(&self, name: &str) -> Result<(), String> {
+        Err("Windows are not supported in this environment".into())
+    }
+    /// Take the events that have happened in a named window since the last call
+    fn window_events(&self, name: &str) -> Result<Vec<WindowEvent>, String> {
+        Err("Windows are not supported in this environment".into())
+    }
     /// Exit the program with a status code
     fn exit(&self, status: i32) -> Result<(), String> {
         Err("Exiting is not supported in this environment".into())
@@ -912,6 +1713,10 @@ pub trait SysBackend: Any + Send + Sync + 'static {
         self.close(handle)?;
         Ok(())
     }
+    /// Append bytes to a file, creating it if it does not exist
+    fn file_append(&self, path: &Path, contents: &[u8]) -> Result<(), String> {
+        Err("Appending to files is not supported in this environment".into())
+    }
     /// Get the clipboard contents
     fn clipboard(&self) -> Result<String, String> {
         Err("Getting the clipboard is not supported in this environment".into())
@@ -928,11 +1733,30 @@ pub trait SysBackend: Any + Send + Sync + 'static {
     fn allow_thread_spawning(&self) -> bool {
         false
     }
+    /// Whether [`SysBackend::spawn_thread`] can run functions on other threads
+    ///
+    /// This is only used on the web, where `std::thread` is unavailable.
+    /// If this is `false`, spawned threads are run immediately on the current thread.
+    fn can_spawn_threads(&self) -> bool {
+        false
+    }
+    /// Run a function on a new thread
+    ///
+    /// This is only called if [`SysBackend::can_spawn_threads`] returns `true`.
+    fn spawn_thread(&self, f: Box<dyn FnOnce() + Send>) -> Result<(), String> {
+        Err("Spawning threads is not supported in this environment".into())
+    }
     /// Show an image
     #[cfg(feature = "image")]
     fn show_image(&self, image: DynamicImage, label: Option<&str>) -> Result<(), String> {
         Err("Showing images not supported in this environment".into())
     }
+    /// Report the progress of a long computation
+    ///
+    /// By default, this does nothing
+    fn progress(&self, current: f64, total: f64, label: &str) -> Result<(), String> {
+        Ok(())
+    }
     /// Show a GIF
     fn show_gif(&self, gif_bytes: Vec<u8>, label: Option<&str>) -> Result<(), String> {
         Err("Showing gifs not supported in this environment".into())
@@ -999,6 +1823,105 @@ pub trait SysBackend: Any + Send + Sync + 'static {
     ) -> Result<(), String> {
         Err("TCP sockets are not supported in this environment".into())
     }
+    /// Create a UDP socket and bind it to an address
+    fn udp_bind(&self, addr: &str) -> Result<Handle, String> {
+        Err("UDP sockets are not supported in this environment".into())
+    }
+    /// Get the local address of a UDP socket
+    fn udp_addr(&self, handle: Handle) -> Result<SocketAddr, String> {
+        Err("UDP sockets are not supported in this environment".into())
+    }
+    /// Send a UDP packet to an address
+    fn udp_send(&self, addr: &str, packet: &[u8]) -> Result<(), String> {
+        Err("UDP sockets are not supported in this environment".into())
+    }
+    /// Receive a UDP packet with a socket
+    ///
+    /// Returns `None` if the timeout is reached
+    fn udp_receive(
+        &self,
+        handle: Handle,
+        timeout: Option<Duration>,
+    ) -> Result<Option<Vec<u8>>, String> {
+        Err("UDP sockets are not supported in this environment".into())
+    }
+    /// Look up DNS records of a type for a domain name
+    fn dns_lookup(&self, record_type: &str, name: &str) -> Result<Vec<String>, String> {
+        Err("DNS lookup is not supported in this environment".into())
+    }
+    /// Check whether a host is reachable
+    ///
+    /// Returns the number of seconds it took to respond, or `None` if the timeout is reached
+    fn ping(&self, addr: &str, timeout: Option<Duration>) -> Result<Option<f64>, String> {
+        Err("Pinging is not supported in this environment".into())
+    }
+    /// Get the names and addresses of the network interfaces
+    fn interface_addrs(&self) -> Result<Vec<(String, IpAddr)>, String> {
+        Err("Listing network interfaces is not supported in this environment".into())
+    }
+    /// Connect to an MQTT broker
+    fn mqtt_connect(&self, options: &MqttOptions) -> Result<Handle, String> {
+        Err("MQTT is not supported in this environment".into())
+    }
+    /// Subscribe to an MQTT topic
+    fn mqtt_subscribe(&self, handle: Handle, topic: &str, qos: u8) -> Result<(), String> {
+        Err("MQTT is not supported in this environment".into())
+    }
+    /// Publish an MQTT message
+    fn mqtt_publish(&self, handle: Handle, message: &MqttMessage) -> Result<(), String> {
+        Err("MQTT is not supported in this environment".into())
+    }
+    /// Receive an MQTT message from a subscribed topic
+    ///
+    /// Returns `None` if the timeout is reached
+    fn mqtt_receive(
+        &self,
+        handle: Handle,
+        timeout: Option<Duration>,
+    ) -> Result<Option<MqttMessage>, String> {
+        Err("MQTT is not supported in this environment".into())
+    }
+    /// Send an email through an SMTP server
+    fn smtp_send(
+        &self,
+        server: &SmtpServer,
+        auth: Option<&SmtpAuth>,
+        email: &Email,
+    ) -> Result<(), String> {
+        Err("Sending email is not supported in this environment".into())
+    }
+    /// Get a secret from the keychain
+    ///
+    /// Returns `None` if there is no secret for the service and account
+    fn keyring_get(&self, service: &str, account: &str) -> Result<Option<String>, String> {
+        Err("The keychain is not supported in this environment".into())
+    }
+    /// Store a secret in the keychain
+    fn keyring_set(&self, service: &str, account: &str, secret: &str) -> Result<(), String> {
+        Err("The keychain is not supported in this environment".into())
+    }
+    /// Delete a secret from the keychain
+    fn keyring_delete(&self, service: &str, account: &str) -> Result<(), String> {
+        Err("The keychain is not supported in this environment".into())
+    }
+    /// Read the value of a GPIO pin
+    fn gpio_read(&self, chip: usize, pin: u32) -> Result<bool, String> {
+        Err("GPIO is not supported in this environment".into())
+    }
+    /// Set the value of a GPIO pin
+    fn gpio_write(&self, chip: usize, pin: u32, value: bool) -> Result<(), String> {
+        Err("GPIO is not supported in this environment".into())
+    }
+    /// Write bytes to an I2C device and then read bytes from it
+    fn i2c_transfer(
+        &self,
+        bus: usize,
+        addr: u16,
+        write: &[u8],
+        read_len: usize,
+    ) -> Result<Vec<u8>, String> {
+        Err("I2C is not supported in this environment".into())
+    }
     /// Close a stream
     fn close(&self, handle: Handle) -> Result<(), String> {
         Ok(())
@@ -1046,6 +1969,15 @@ pub trait SysBackend: Any + Send + Sync + 'static {
     ) -> Result<Value, String> {
         Err("FFI is not supported in this environment".into())
     }
+    /// Create a pointer to a foreign-callable function that calls a Uiua function
+    fn ffi_callback(
+        &self,
+        result_ty: FfiType,
+        arg_tys: &[FfiType],
+        f: FfiCallbackFn,
+    ) -> Result<Value, String> {
+        Err("FFI callbacks are not supported in this environment".into())
+    }
     /// Copy the data from a pointer into an array
     fn mem_copy(&self, ty: FfiType, ptr: *const (), len: usize) -> Result<Value, String> {
         Err("Pointer copying is not supported in this environment".into())
@@ -1054,6 +1986,14 @@ pub trait SysBackend: Any + Send + Sync + 'static {
     fn mem_free(&self, ptr: *const ()) -> Result<(), String> {
         Err("Pointer freeing is not supported in this environment".into())
     }
+    /// Call a plugin function with a JSON argument, returning a JSON result
+    fn plugin_call(&self, name: &str, arg: &str) -> Result<String, String> {
+        Err("Plugins are not supported in this environment".into())
+    }
+    /// Call a Python function
+    fn python_call(&self, function: &str, args: &[Value]) -> Result<Value, String> {
+        Err("Python is not supported in this environment".into())
+    }
     /// Load a git repo as a module
     ///
     /// The returned path should be loadable via [`SysBackend::file_read_all`]
@@ -1081,7 +2021,7 @@ pub trait SysBackend: Any + Send + Sync + 'static {
 }
 
 /// A target for a git repository
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum GitTarget {
     /// The latest commit on the default branch
     #[default]
@@ -1090,6 +2030,112 @@ pub enum GitTarget {
     Branch(String),
     /// A specific commit
     Commit(String),
+    /// A specific tag
+    Tag(String),
+}
+
+/// A command that controls the terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TermCommand {
+    /// Move the cursor to a row and column
+    MoveCursor(u16, u16),
+    /// Clear the screen and move the cursor to the top left
+    Clear,
+    /// Set the foreground and background colors, or reset them if `None`
+    SetColors(Option<[u8; 3]>, Option<[u8; 3]>),
+    /// Show or hide the cursor
+    ShowCursor(bool),
+    /// Enable or disable mouse events
+    CaptureMouse(bool),
+}
+
+impl TermCommand {
+    /// Get the ANSI escape sequence for the command
+    pub fn ansi(&self) -> String {
+        match *self {
+            TermCommand::MoveCursor(row, col) => format!("\x1b[{};{}H", row + 1, col + 1),
+            TermCommand::Clear => "\x1b[2J\x1b[H".into(),
+            TermCommand::SetColors(fg, bg) => {
+                let fg = fg.map_or("39".into(), |[r, g, b]| format!("38;2;{r};{g};{b}"));
+                let bg = bg.map_or("49".into(), |[r, g, b]| format!("48;2;{r};{g};{b}"));
+                format!("\x1b[{fg};{bg}m")
+            }
+            TermCommand::ShowCursor(show) => format!("\x1b[?25{}", if show { 'h' } else { 'l' }),
+            TermCommand::CaptureMouse(capture) => {
+                let c = if capture { 'h' } else { 'l' };
+                format!("\x1b[?1000{c}\x1b[?1002{c}\x1b[?1003{c}\x1b[?1006{c}")
+            }
+        }
+    }
+}
+
+/// An event read from the terminal
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TermEvent {
+    /// A key was pressed
+    Key {
+        /// The typed character, or the name of the key
+        key: String,
+        /// Whether control was held
+        ctrl: bool,
+        /// Whether alt was held
+        alt: bool,
+        /// Whether shift was held
+        shift: bool,
+    },
+    /// The mouse was used
+    Mouse {
+        /// What the mouse did
+        kind: String,
+        /// The button that was pressed, released, or dragged
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        button: Option<String>,
+        /// The row of the mouse
+        row: u16,
+        /// The column of the mouse
+        col: u16,
+    },
+    /// The terminal was resized
+    Resize {
+        /// The new number of rows
+        rows: u16,
+        /// The new number of columns
+        cols: u16,
+    },
+}
+
+/// An event from a window opened with [`SysOp::WindowShow`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WindowEvent {
+    /// A key was pressed or released
+    Key {
+        /// The name of the key
+        key: String,
+        /// Whether the key was pressed rather than released
+        pressed: bool,
+        /// Whether control was held
+        ctrl: bool,
+        /// Whether alt was held
+        alt: bool,
+        /// Whether shift was held
+        shift: bool,
+    },
+    /// The mouse was used
+    Mouse {
+        /// What the mouse did
+        kind: String,
+        /// The button that was pressed or released
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        button: Option<String>,
+        /// The x position of the mouse in pixels of the shown image
+        x: f64,
+        /// The y position of the mouse in pixels of the shown image
+        y: f64,
+    },
+    /// The window was closed
+    Close,
 }
 
 impl fmt::Debug for dyn SysBackend {
@@ -1210,6 +2256,57 @@ impl SysOp {
                     env.push(0u8);
                 }
             }
+            SysOp::Progress => {
+                let current = env
+                    .pop(1)?
+                    .as_num(env, "Current progress must be a number")?;
+                let total = env.pop(2)?.as_num(env, "Total progress must be a number")?;
+                let label = env.pop(3)?.as_string(env, "Label must be a string")?;
+                (env.rt.backend.progress(current, total, &label)).map_err(|e| env.error(e))?;
+            }
+            SysOp::Prompt | SysOp::Password => {
+                let prompt = env.pop(1)?.as_string(env, "Prompt must be a string")?;
+                let hidden = *self == SysOp::Password;
+                let start = env.rt.backend.now();
+                let res = (env.rt.backend.prompt(&prompt, hidden)).map_err(|e| env.error(e));
+                env.rt.execution_start += env.rt.backend.now() - start;
+                if let Some(line) = res? {
+                    env.push(line);
+                } else {
+                    env.push(0u8);
+                }
+            }
+            SysOp::Menu => {
+                let prompt = env.pop(1)?.as_string(env, "Prompt must be a string")?;
+                let options = env.pop(2)?;
+                let options = options.as_strings(env, "Options must be a list of strings")?;
+                if options.is_empty() {
+                    return Err(env.error("Menu must have at least one option"));
+                }
+                let mut menu = String::new();
+                for (i, option) in options.iter().enumerate() {
+                    menu.push_str(&format!("{}) {option}\n", i + 1));
+                }
+                (env.rt.backend.print_str_stdout(&menu)).map_err(|e| env.error(e))?;
+                let start = env.rt.backend.now();
+                let choice = loop {
+                    let res = (env.rt.backend.prompt(&prompt, false)).map_err(|e| env.error(e));
+                    let Some(line) = res? else {
+                        break f64::INFINITY;
+                    };
+                    match line.trim().parse::<usize>() {
+                        Ok(n) if (1..=options.len()).contains(&n) => break (n - 1) as f64,
+                        _ => (env.rt.backend)
+                            .print_str_stdout(&format!(
+                                "Enter a number from 1 to {}\n",
+                                options.len()
+                            ))
+                            .map_err(|e| env.error(e))?,
+                    }
+                };
+                env.rt.execution_start += env.rt.backend.now() - start;
+                env.push(choice);
+            }
             SysOp::TermSize => {
                 let (width, height) = env.rt.backend.term_size().map_err(|e| env.error(e))?;
                 env.push(cowslice![height as f64, width as f64])
@@ -1224,6 +2321,53 @@ impl SysOp {
                     .set_raw_mode(raw_mode)
                     .map_err(|e| env.error(e))?;
             }
+            SysOp::TermMove => {
+                let pos = env.pop(1)?;
+                let pos = pos.as_nats(env, "Cursor position must be a list of natural numbers")?;
+                let [row, col] = *pos.as_slice() else {
+                    return Err(env.error(format!(
+                        "Cursor position must have 2 elements, but it has {}",
+                        pos.len()
+                    )));
+                };
+                let command = TermCommand::MoveCursor(row as u16, col as u16);
+                (env.rt.backend.term_command(command)).map_err(|e| env.error(e))?;
+            }
+            SysOp::TermClear => {
+                (env.rt.backend.term_command(TermCommand::Clear)).map_err(|e| env.error(e))?;
+            }
+            SysOp::TermColor => {
+                let fg = term_color(env.pop(1)?, env)?;
+                let bg = term_color(env.pop(2)?, env)?;
+                let command = TermCommand::SetColors(fg, bg);
+                (env.rt.backend.term_command(command)).map_err(|e| env.error(e))?;
+            }
+            SysOp::TermCursor => {
+                let show = env
+                    .pop(1)?
+                    .as_bool(env, "Cursor visibility must be a boolean")?;
+                let command = TermCommand::ShowCursor(show);
+                (env.rt.backend.term_command(command)).map_err(|e| env.error(e))?;
+            }
+            SysOp::TermMouse => {
+                let capture = env
+                    .pop(1)?
+                    .as_bool(env, "Mouse capture must be a boolean")?;
+                let command = TermCommand::CaptureMouse(capture);
+                (env.rt.backend.term_command(command)).map_err(|e| env.error(e))?;
+            }
+            SysOp::TermEvent => {
+                let timeout = env.pop(1)?;
+                let timeout = timeout.as_num(env, "Timeout must be a number")?;
+                let timeout =
+                    (timeout.is_finite()).then(|| Duration::from_secs_f64(timeout.max(0.0)));
+                let event = (env.rt.backend.term_event(timeout)).map_err(|e| env.error(e))?;
+                let json = event.map_or_else(
+                    || serde_json::json!({}),
+                    |event| serde_json::to_value(event).unwrap_or_default(),
+                );
+                env.push(Value::from_json_value(json, env)?);
+            }
             SysOp::Args => {
                 let mut args = Vec::new();
                 args.push(env.file_path().to_string_lossy().into_owned());
@@ -1544,6 +2688,57 @@ impl SysOp {
                 #[cfg(not(feature = "image"))]
                 return Err(env.error("Image encoding is not supported in this environment"));
             }
+            SysOp::WindowShow => {
+                #[cfg(feature = "image")]
+                {
+                    let name = env.pop(1)?.as_string(env, "Window name must be a string")?;
+                    let value = env.pop(2)?;
+                    let image = crate::encode::value_to_image(&value).map_err(|e| env.error(e))?;
+                    (env.rt.backend)
+                        .window_show(&name, image)
+                        .map_err(|e| env.error(e))?;
+                }
+                #[cfg(not(feature = "image"))]
+                return Err(env.error("Image encoding is not supported in this environment"));
+            }
+            SysOp::WindowClose => {
+                let name = env.pop(1)?.as_string(env, "Window name must be a string")?;
+                (env.rt.backend)
+                    .window_close(&name)
+                    .map_err(|e| env.error(e))?;
+            }
+            SysOp::WindowEvents => {
+                let name = env.pop(1)?.as_string(env, "Window name must be a string")?;
+                let events = (env.rt.backend)
+                    .window_events(&name)
+                    .map_err(|e| env.error(e))?;
+                let mut rows = Vec::with_capacity(events.len());
+                for event in events {
+                    let json = serde_json::to_value(event).unwrap_or_default();
+                    rows.push(Boxed(Value::from_json_value(json, env)?));
+                }
+                env.push(Array::<Boxed>::from_iter(rows));
+            }
+            SysOp::WindowFrame => {
+                let rate = env.pop(1)?.as_num(env, "Frame rate must be a number")?;
+                if rate.is_nan() || rate <= 0.0 {
+                    return Err(env.error("Frame rate must be positive"));
+                }
+                thread_local! {
+                    static LAST_FRAME: std::cell::Cell<Option<f64>> = const { std::cell::Cell::new(None) };
+                }
+                let elapsed = if let Some(last) = LAST_FRAME.get() {
+                    let wait = 1.0 / rate - (env.rt.backend.now() - last);
+                    if wait > 0.0 {
+                        env.rt.backend.sleep(wait).map_err(|e| env.error(e))?;
+                    }
+                    env.rt.backend.now() - last
+                } else {
+                    0.0
+                };
+                LAST_FRAME.set(Some(env.rt.backend.now()));
+                env.push(elapsed);
+            }
             SysOp::GifShow => {
                 #[cfg(feature = "gif")]
                 {
@@ -1582,6 +2777,12 @@ impl SysOp {
                 let contents = env.rt.backend.clipboard().map_err(|e| env.error(e))?;
                 env.push(contents);
             }
+            SysOp::ClipWrite => {
+                let contents = env.pop(1)?.as_string(env, "Contents must be a string")?;
+                (env.rt.backend)
+                    .set_clipboard(&contents)
+                    .map_err(|e| env.error(e))?;
+            }
             SysOp::Sleep => {
                 let mut seconds = env.pop(1)?.as_num(env, "Sleep time must be a number")?;
                 if seconds < 0.0 {
@@ -1685,6 +2886,204 @@ impl SysOp {
                     .tcp_set_write_timeout(handle, timeout)
                     .map_err(|e| env.error(e))?;
             }
+            SysOp::OscListen => {
+                let addr = env.pop(1)?.as_string(env, "Address must be a string")?;
+                let handle = (env.rt.backend).udp_bind(&addr).map_err(|e| env.error(e))?;
+                let sock_addr = env.rt.backend.udp_addr(handle).map_err(|e| env.error(e))?;
+                let handle = handle.value(HandleKind::UdpSocket(sock_addr));
+                env.push(handle);
+            }
+            SysOp::OscSend => {
+                let packet = osc::encode_packet(&env.pop(1)?, env)?;
+                let addr = env.pop(2)?.as_string(env, "Address must be a string")?;
+                (env.rt.backend)
+                    .udp_send(&addr, &packet)
+                    .map_err(|e| env.error(e))?;
+            }
+            SysOp::OscReceive => {
+                let timeout = env.pop(1)?.as_num(env, "Timeout must be a number")?.abs();
+                let timeout = if timeout.is_infinite() {
+                    None
+                } else {
+                    Some(Duration::from_secs_f64(timeout))
+                };
+                let handle = env.pop(2)?.as_handle(env, "")?;
+                let packet = (env.rt.backend)
+                    .udp_receive(handle, timeout)
+                    .map_err(|e| env.error(e))?
+                    .ok_or_else(|| env.error("Timed out waiting for an OSC message"))?;
+                let message = osc::decode_packet(&packet).map_err(|e| env.error(e))?;
+                env.push(message);
+            }
+            SysOp::DnsLookup => {
+                let record_type = (env.pop(1)?)
+                    .as_string(env, "DNS record type must be a string")?
+                    .to_uppercase();
+                if !["A", "AAAA", "TXT"].contains(&record_type.as_str()) {
+                    return Err(env.error(format!(
+                        "Unknown DNS record type {record_type:?}. Expected A, AAAA, or TXT."
+                    )));
+                }
+                let name = env.pop(2)?.as_string(env, "Domain name must be a string")?;
+                let records = (env.rt.backend)
+                    .dns_lookup(&record_type, &name)
+                    .map_err(|e| env.error(e))?;
+                env.push(Array::<Boxed>::from_iter(records));
+            }
+            SysOp::Ping => {
+                let timeout = env.pop(1)?.as_num(env, "Timeout must be a number")?;
+                if timeout <= 0.0 {
+                    return Err(env.error("Timeout must be positive"));
+                }
+                let timeout = (!timeout.is_infinite()).then(|| Duration::from_secs_f64(timeout));
+                let addr = env.pop(2)?.as_string(env, "Address must be a string")?;
+                let time = (env.rt.backend)
+                    .ping(&addr, timeout)
+                    .map_err(|e| env.error(e))?;
+                env.push(time.unwrap_or(f64::INFINITY));
+            }
+            SysOp::InterfaceAddrs => {
+                let addrs = (env.rt.backend)
+                    .interface_addrs()
+                    .map_err(|e| env.error(e))?;
+                let mut interfaces: BTreeMap<String, Vec<String>> = BTreeMap::new();
+                for (name, addr) in addrs {
+                    interfaces.entry(name).or_default().push(addr.to_string());
+                }
+                let json = serde_json::to_value(interfaces).unwrap_or_default();
+                env.push(Value::from_json_value(json, env)?);
+            }
+            SysOp::MqttConnect => {
+                let options = MqttOptions::from_value(&env.pop(1)?, env)?;
+                let handle = (env.rt.backend)
+                    .mqtt_connect(&options)
+                    .map_err(|e| env.error(e))?;
+                env.push(handle.value(HandleKind::MqttClient(options.address)));
+            }
+            SysOp::MqttSubscribe => {
+                let topic = env.pop(1)?.as_string(env, "Topic must be a string")?;
+                let qos = env.pop(2)?.as_nat(env, "QoS must be a natural number")?;
+                let qos = mqtt_qos(qos, env)?;
+                let handle = env.pop(3)?.as_handle(env, "")?;
+                (env.rt.backend)
+                    .mqtt_subscribe(handle, &topic, qos)
+                    .map_err(|e| env.error(e))?;
+            }
+            SysOp::MqttPublish => {
+                let topic = env.pop(1)?.as_string(env, "Topic must be a string")?;
+                let payload = (env.pop(2)?)
+                    .into_bytes(env, "MQTT payload must be a string or a list of bytes")?;
+                let qos_val = env.pop(3)?;
+                let (qos, retain) = match *qos_val.as_nats(env, MQTT_QOS)?.as_slice() {
+                    [qos] if qos_val.rank() == 0 => (qos, false),
+                    [qos, retain @ (0 | 1)] => (qos, retain == 1),
+                    _ => {
+                        let shape = qos_val.shape();
+                        return Err(env.error(format!("{MQTT_QOS}, but its shape is {shape}")));
+                    }
+                };
+                let handle = env.pop(4)?.as_handle(env, "")?;
+                let message = MqttMessage {
+                    topic,
+                    payload,
+                    qos: mqtt_qos(qos, env)?,
+                    retain,
+                };
+                (env.rt.backend)
+                    .mqtt_publish(handle, &message)
+                    .map_err(|e| env.error(e))?;
+            }
+            SysOp::MqttReceive => {
+                let timeout = env.pop(1)?.as_num(env, "Timeout must be a number")?.abs();
+                let timeout = if timeout.is_infinite() {
+                    None
+                } else {
+                    Some(Duration::from_secs_f64(timeout))
+                };
+                let handle = env.pop(2)?.as_handle(env, "")?;
+                let message = (env.rt.backend)
+                    .mqtt_receive(handle, timeout)
+                    .map_err(|e| env.error(e))?
+                    .ok_or_else(|| env.error("Timed out waiting for an MQTT message"))?;
+                env.push(message.into_value(env)?);
+            }
+            SysOp::SmtpSend => {
+                let email = Email::from_value(&env.pop(1)?, env)?;
+                let auth = SmtpAuth::from_value(&env.pop(2)?, env)?;
+                let server = SmtpServer::from_value(&env.pop(3)?, env)?;
+                (env.rt.backend)
+                    .smtp_send(&server, auth.as_ref(), &email)
+                    .map_err(|e| env.error(e))?;
+            }
+            SysOp::KeyringGet => {
+                let service = env
+                    .pop(1)?
+                    .as_string(env, "Service name must be a string")?;
+                let account = env
+                    .pop(2)?
+                    .as_string(env, "Account name must be a string")?;
+                let secret = (env.rt.backend)
+                    .keyring_get(&service, &account)
+                    .map_err(|e| env.error(e))?
+                    .ok_or_else(|| {
+                        env.error(format!(
+                            "There is no secret for account {account:?} of service {service:?}"
+                        ))
+                    })?;
+                env.push(secret);
+            }
+            SysOp::KeyringSet => {
+                let service = env
+                    .pop(1)?
+                    .as_string(env, "Service name must be a string")?;
+                let account = env
+                    .pop(2)?
+                    .as_string(env, "Account name must be a string")?;
+                let secret = env.pop(3)?.as_string(env, "Secret must be a string")?;
+                (env.rt.backend)
+                    .keyring_set(&service, &account, &secret)
+                    .map_err(|e| env.error(e))?;
+            }
+            SysOp::KeyringDelete => {
+                let service = env
+                    .pop(1)?
+                    .as_string(env, "Service name must be a string")?;
+                let account = env
+                    .pop(2)?
+                    .as_string(env, "Account name must be a string")?;
+                (env.rt.backend)
+                    .keyring_delete(&service, &account)
+                    .map_err(|e| env.error(e))?;
+            }
+            SysOp::GpioRead => {
+                let (chip, pin) = device(env.pop(1)?, 0, env, GPIO_PIN)?;
+                let value = (env.rt.backend)
+                    .gpio_read(chip, pin as u32)
+                    .map_err(|e| env.error(e))?;
+                env.push(value);
+            }
+            SysOp::GpioWrite => {
+                let value = env.pop(1)?.as_bool(env, "GPIO value must be 0 or 1")?;
+                let (chip, pin) = device(env.pop(2)?, 0, env, GPIO_PIN)?;
+                (env.rt.backend)
+                    .gpio_write(chip, pin as u32, value)
+                    .map_err(|e| env.error(e))?;
+            }
+            SysOp::I2cTransfer => {
+                let write = env
+                    .pop(1)?
+                    .as_bytes(env, "I2C data must be a list of bytes")?;
+                let read_len =
+                    (env.pop(2)?).as_nat(env, "I2C read length must be a natural number")?;
+                let (bus, addr) = device(env.pop(3)?, 1, env, I2C_ADDRESS)?;
+                if addr > 0x3FF {
+                    return Err(env.error(format!("{addr} is not a valid I2C address")));
+                }
+                let read = (env.rt.backend)
+                    .i2c_transfer(bus, addr as u16, &write, read_len)
+                    .map_err(|e| env.error(e))?;
+                env.push(read);
+            }
             SysOp::HttpsWrite => {
                 let http = env
                     .pop(1)?
@@ -1821,6 +3220,161 @@ impl SysOp {
                     .ok_or_else(|| env.error("Freed pointer must be a pointer value"))?;
                 (env.rt.backend).mem_free(ptr).map_err(|e| env.error(e))?;
             }
+            SysOp::Plugin => {
+                let name = env
+                    .pop(1)?
+                    .as_string(env, "Plugin function name must be a string")?;
+                let arg = env.pop(2)?.to_json_string(env)?;
+                let result = (env.rt.backend)
+                    .plugin_call(&name, &arg)
+                    .map_err(|e| env.error(e))?;
+                env.push(Value::from_json_string(&result, env)?);
+            }
+            SysOp::Python => {
+                let function = env
+                    .pop(1)?
+                    .as_string(env, "Python function name must be a string")?;
+                let args = env.pop(2)?;
+                let args: Vec<Value> = args.into_rows().map(Value::unpacked).collect();
+                let result = (env.rt.backend)
+                    .python_call(&function, &args)
+                    .map_err(|e| env.error(e))?;
+                env.push(result);
+            }
+            SysOp::Wasm => {
+                let sig_def = env.pop(1)?;
+                let sig_def = match sig_def {
+                    Value::Box(arr) if arr.rank() == 1 && arr.row_count() >= 3 => arr,
+                    _ => {
+                        return Err(env.error(
+                            "WASM signature must be a list of at least three boxed strings",
+                        ))
+                    }
+                };
+                let mut sig_frags = sig_def.data.into_iter().map(|b| b.0);
+                let path = (sig_frags.next().unwrap())
+                    .as_string(env, "WASM module path must be a string")?;
+                let result_ty = (sig_frags.next().unwrap())
+                    .as_string(env, "WASM result type must be a string")?
+                    .parse::<WasmType>()
+                    .map_err(|e| env.error(e))?;
+                let name = (sig_frags.next().unwrap())
+                    .as_string(env, "WASM function name must be a string")?;
+                let arg_tys = sig_frags
+                    .map(|frag| {
+                        frag.as_string(env, "WASM argument type must be a string")
+                            .and_then(|ty| ty.parse::<WasmType>().map_err(|e| env.error(e)))
+                    })
+                    .collect::<UiuaResult<Vec<_>>>()?;
+                let args = env.pop(2)?;
+                let args: Vec<Value> = args.into_rows().map(Value::unpacked).collect();
+                let load = || env.rt.backend.file_read_all(Path::new(&path));
+                let interrupted = || env.respect_execution_limit().is_err();
+                let instances = &env.rt.wasm_instances;
+                let result = wasm::call(
+                    instances,
+                    &path,
+                    load,
+                    result_ty,
+                    &name,
+                    &arg_tys,
+                    &args,
+                    &interrupted,
+                )
+                .map_err(|e| {
+                    // Report a timeout or interruption as such
+                    (env.respect_execution_limit().err()).unwrap_or_else(|| env.error(e))
+                })?;
+                env.push(result);
+            }
+            SysOp::Chan => {
+                let capacity = env.pop(1)?;
+                let id = channel::create(capacity, env)?;
+                env.push(id);
+            }
+            SysOp::ChanSend => {
+                let value = env.pop(1)?;
+                let id = env.pop(2)?;
+                channel::send(value, id, env)?;
+            }
+            SysOp::ChanRecv => {
+                let timeout = env.pop(1)?;
+                let id = env.pop(2)?;
+                let value = channel::recv(timeout, id, env)?;
+                env.push(value);
+            }
+            SysOp::ChanSelect => {
+                let timeout = env.pop(1)?;
+                let ids = env.pop(2)?;
+                let (index, value) = channel::select(timeout, ids, env)?;
+                env.push(value);
+                env.push(index);
+            }
+            SysOp::ChanClose => {
+                let id = env.pop(1)?;
+                channel::close(id, env)?;
+            }
+            SysOp::Atom => {
+                let value = env.pop(1)?;
+                env.push(atomic::create(value, env));
+            }
+            SysOp::AtomLoad => {
+                let id = env.pop(1)?;
+                let value = atomic::load(id, env)?;
+                env.push(value);
+            }
+            SysOp::AtomCas => {
+                let expected = env.pop(1)?;
+                let new = env.pop(2)?;
+                let id = env.pop(3)?;
+                let swapped = atomic::compare_swap(expected, new, id, env)?;
+                env.push(swapped);
+            }
+            SysOp::AtomAdd => {
+                let amount = env.pop(1)?;
+                let id = env.pop(2)?;
+                let old = atomic::fetch_add(amount, id, env)?;
+                env.push(old);
+            }
+            SysOp::AtomFree => {
+                let id = env.pop(1)?;
+                atomic::free(id, env)?;
+            }
+            SysOp::LogMessage => {
+                let level = env.pop(1)?;
+                let message = env.pop(2)?;
+                let fields = env.pop(3)?;
+                log::log(level, message, fields, env)?;
+            }
+            SysOp::LogLevel => {
+                let level = env.pop(1)?;
+                log::set_level(level, env)?;
+            }
+            SysOp::LogOutput => {
+                let format = env.pop(1)?;
+                let path = env.pop(2)?;
+                log::set_output(format, path, env)?;
+            }
+            SysOp::MetricRegister => {
+                let options = env.pop(1)?;
+                metrics::register(options, env)?;
+            }
+            SysOp::MetricCounter => {
+                let amount = env.pop(1)?;
+                let name = env.pop(2)?;
+                metrics::counter_add(amount, name, env)?;
+            }
+            SysOp::MetricGauge => {
+                let value = env.pop(1)?;
+                let name = env.pop(2)?;
+                metrics::gauge_set(value, name, env)?;
+            }
+            SysOp::MetricObserve => {
+                let value = env.pop(1)?;
+                let name = env.pop(2)?;
+                metrics::observe(value, name, env)?;
+            }
+            SysOp::MetricExport => env.push(metrics::export()),
             SysOp::Breakpoint => {
                 if !env.rt.backend.breakpoint(env).map_err(|e| env.error(e))? {
                     return Err(UiuaErrorKind::Interrupted.into());
@@ -1880,6 +3434,108 @@ impl SysOp {
                     env.push(val);
                 }
             }
+            SysOp::ReadChunks => {
+                let [f] = get_ops(ops, env)?;
+                let size = env
+                    .pop(1)?
+                    .as_nat(env, "Chunk size must be a natural number")?;
+                if size == 0 {
+                    return Err(env.error("Chunk size must be positive"));
+                }
+                let handle = env.pop(2)?.as_handle(env, "")?;
+                if f.sig.args == 0 {
+                    return Err(env.error(format!(
+                        "&rc's function must take at least 1 argument, \
+                        but its signature is {}",
+                        f.sig
+                    )));
+                }
+                let acc_count = f.sig.args - 1;
+                let out_count = f.sig.outputs.saturating_sub(acc_count);
+                let mut outputs = multi_output(out_count, Vec::new());
+                env.without_fill(|env| -> UiuaResult {
+                    loop {
+                        let bytes = (env.rt.backend)
+                            .read(handle, size)
+                            .map_err(|e| env.error(e))?;
+                        if bytes.is_empty() {
+                            break Ok(());
+                        }
+                        env.push(Value::from(Array::from_iter(bytes)));
+                        env.exec(f.clone())?;
+                        for output in &mut outputs {
+                            output.push(env.pop("read chunks output")?);
+                        }
+                    }
+                })?;
+                for rows in outputs.into_iter().rev() {
+                    let val = Value::from_row_values(rows, env)?;
+                    env.push(val);
+                }
+            }
+            SysOp::FfiCallback => {
+                let [f] = get_ops(ops, env)?;
+                let Value::Box(sig_def) = env.pop(1)? else {
+                    return Err(env.error("FFI callback signature must be a box array"));
+                };
+                let mut tys = (sig_def.data.into_iter())
+                    .map(|frag| {
+                        (frag.0)
+                            .as_string(env, "FFI callback types must be strings")
+                            .and_then(|ty| ty.parse::<FfiType>().map_err(|e| env.error(e)))
+                    })
+                    .collect::<UiuaResult<Vec<_>>>()?;
+                if tys.is_empty() {
+                    return Err(env.error("FFI callback signature must have a return type"));
+                }
+                let result_ty = tys.remove(0);
+                let outputs = (result_ty != FfiType::Void) as usize;
+                if f.sig != (tys.len(), outputs) {
+                    return Err(env.error(format!(
+                        "&ffcb's function must have signature |{}.{}, \
+                        but its signature is {}",
+                        tys.len(),
+                        outputs,
+                        f.sig
+                    )));
+                }
+                let mut template = env.clone();
+                template.take_stack();
+                let template = Mutex::new(template);
+                let callback: FfiCallbackFn = Box::new(move |args| {
+                    let mut env = template.lock().clone();
+                    for arg in args.into_iter().rev() {
+                        env.push(arg);
+                    }
+                    env.exec(f.clone()).map_err(|e| e.to_string())?;
+                    Ok(if outputs == 0 {
+                        Value::default()
+                    } else {
+                        env.pop(1).map_err(|e| e.to_string())?
+                    })
+                });
+                let ptr = (env.rt.backend)
+                    .ffi_callback(result_ty, &tys, callback)
+                    .map_err(|e| env.error(e))?;
+                env.push(ptr);
+            }
+            SysOp::Use => {
+                let [f] = get_ops(ops, env)?;
+                if f.sig.args == 0 {
+                    return Err(env.error(format!(
+                        "&use's function must take at least 1 argument, \
+                        but its signature is {}",
+                        f.sig
+                    )));
+                }
+                let value = env.pop(1)?;
+                let handle = value.as_handle(env, "")?;
+                env.push(value);
+                let res = env.exec(f);
+                let closed = env.rt.backend.close(handle).map_err(|e| env.error(e));
+                res?;
+                closed?;
+            }
             SysOp::AudioStream => {
                 let [f] = get_ops(ops, env)?;
                 let push_time = f.sig.args > 0;
@@ -1900,33 +3556,8 @@ impl SysOp {
                         stream_env.push(time_array);
                     }
                     stream_env.exec(f.clone())?;
-                    let samples = &stream_env.pop(1)?;
-                    let samples = samples.as_num_array().ok_or_else(|| {
-                        stream_env.error("Audio stream function must return a numeric array")
-                    })?;
-                    match samples.shape().dims() {
-                        [_] => Ok(samples.data.iter().map(|&x| [x, x]).collect()),
-                        &[n, 2] => {
-                            let mut samps: Vec<[f64; 2]> = Vec::with_capacity(n);
-                            for samp in samples.data.chunks_exact(2) {
-                                samps.push([samp[0], samp[1]]);
-                            }
-                            Ok(samps)
-                        }
-                        &[2, n] => {
-                            let mut samps: Vec<[f64; 2]> = Vec::with_capacity(n);
-                            for i in 0..n {
-                                samps.push([samples.data[i], samples.data[i + n]]);
-                            }
-                            Ok(samps)
-                        }
-                        _ => Err(stream_env.error(format!(
-                            "Audio stream function must return either a \
-                            rank 1 array or a rank 2 array with 2 rows, \
-                            but its shape is {}",
-                            samples.shape()
-                        ))),
-                    }
+                    let samples = stream_env.pop(1)?;
+                    stereo_samples(&samples, &stream_env)
                 }));
                 res.map_err(|e| env.error(e))?;
             }
@@ -1950,6 +3581,22 @@ impl SysOp {
     }
 }
 
+fn term_color(value: Value, env: &Uiua) -> UiuaResult<Option<[u8; 3]>> {
+    if value.row_count() == 0 {
+        return Ok(None);
+    }
+    let nums = value.as_nums(env, "Color must be a list of numbers")?;
+    let [r, g, b] = *nums.as_slice() else {
+        return Err(env.error(format!(
+            "Color must have 3 elements, but it has {}",
+            nums.len()
+        )));
+    };
+    Ok(Some(
+        [r, g, b].map(|n| (n.clamp(0.0, 1.0) * 255.0).round() as u8),
+    ))
+}
+
 fn value_to_command(value: &Value, env: &Uiua) -> UiuaResult<(String, Vec<String>)> {
     let mut strings = Vec::new();
     match value {
@@ -2056,3 +3703,169 @@ pub(crate) fn terminal_size() -> Option<(usize, usize)> {
     #[cfg(not(all(not(target_arch = "wasm32"), feature = "terminal_size")))]
     None
 }
+
+/// Convert the output of an audio stream function to stereo samples
+pub(crate) fn stereo_samples(samples: &Value, env: &Uiua) -> UiuaResult<Vec<[f64; 2]>> {
+    let samples = samples
+        .as_num_array()
+        .ok_or_else(|| env.error("Audio stream function must return a numeric array"))?;
+    match samples.shape().dims() {
+        [_] => Ok(samples.data.iter().map(|&x| [x, x]).collect()),
+        &[n, 2] => {
+            let mut samps: Vec<[f64; 2]> = Vec::with_capacity(n);
+            for samp in samples.data.chunks_exact(2) {
+                samps.push([samp[0], samp[1]]);
+            }
+            Ok(samps)
+        }
+        &[2, n] => {
+            let mut samps: Vec<[f64; 2]> = Vec::with_capacity(n);
+            for i in 0..n {
+                samps.push([samples.data[i], samples.data[i + n]]);
+            }
+            Ok(samps)
+        }
+        _ => Err(env.error(format!(
+            "Audio stream function must return either a \
+            rank 1 array or a rank 2 array with 2 rows, \
+            but its shape is {}",
+            samples.shape()
+        ))),
+    }
+}
+
+const MQTT_QOS: &str =
+    "QoS must be a natural number or a pair of a natural number and a retain flag";
+
+fn mqtt_qos(qos: usize, env: &Uiua) -> UiuaResult<u8> {
+    if qos > 2 {
+        return Err(env.error(format!("QoS must be 0, 1, or 2, but it is {qos}")));
+    }
+    Ok(qos as u8)
+}
+
+const GPIO_PIN: &str = "GPIO pin must be a natural number or a pair of natural numbers";
+const I2C_ADDRESS: &str = "I2C address must be a natural number or a pair of natural numbers";
+
+/// Get a device number that may be paired with the number of the chip or bus it is on
+fn device(
+    value: Value,
+    default: usize,
+    env: &Uiua,
+    requirement: &'static str,
+) -> UiuaResult<(usize, usize)> {
+    let nums = value.as_nats(env, requirement)?;
+    match *nums.as_slice() {
+        [n] if value.rank() == 0 => Ok((default, n)),
+        [group, n] => Ok((group, n)),
+        _ => Err(env.error(format!("{requirement}, but its shape is {}", value.shape()))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn embedded_io() {
+        use crate::*;
+        use parking_lot::Mutex;
+        use std::{any::Any, collections::HashMap};
+        #[derive(Default)]
+        struct Board {
+            pins: Mutex<HashMap<(usize, u32), bool>>,
+        }
+        impl SysBackend for Board {
+            fn any(&self) -> &dyn Any {
+                self
+            }
+            fn any_mut(&mut self) -> &mut dyn Any {
+                self
+            }
+            fn gpio_read(&self, chip: usize, pin: u32) -> Result<bool, String> {
+                Ok(self.pins.lock().get(&(chip, pin)).copied().unwrap_or(false))
+            }
+            fn gpio_write(&self, chip: usize, pin: u32, value: bool) -> Result<(), String> {
+                self.pins.lock().insert((chip, pin), value);
+                Ok(())
+            }
+            fn i2c_transfer(
+                &self,
+                bus: usize,
+                addr: u16,
+                write: &[u8],
+                read_len: usize,
+            ) -> Result<Vec<u8>, String> {
+                let first = bus as u8 + addr as u8 + write.iter().sum::<u8>();
+                Ok((0..read_len).map(|i| first + i as u8).collect())
+            }
+        }
+        let mut env = Uiua::with_backend(Board::default());
+        let code = r#"
+            # Experimental!
+            &gpiow 1 17
+            &gpiow 1 4_18
+            [&gpior 17 &gpior 4_17 &gpior 4_18]
+            &iic [1 2] 3 72
+            &iic [] 1 3_72
+        "#;
+        env.run_str(code).unwrap();
+        assert_eq!(env.pop("bus").unwrap(), Value::from(vec![75u8]));
+        assert_eq!(env.pop("read").unwrap(), Value::from(vec![76u8, 77, 78]));
+        assert_eq!(env.pop("pins").unwrap(), Value::from([1, 0, 1]));
+        let Err(err) = env.run_str("# Experimental!\n&gpior [1 2 3]") else {
+            panic!("Reading a pin with 3 numbers did not fail");
+        };
+        assert!(err.to_string().contains("pair"), "{err}");
+    }
+
+    #[test]
+    fn keyring() {
+        use crate::*;
+        use parking_lot::Mutex;
+        use std::{any::Any, collections::HashMap};
+        #[derive(Default)]
+        struct Keychain {
+            secrets: Mutex<HashMap<(String, String), String>>,
+        }
+        impl SysBackend for Keychain {
+            fn any(&self) -> &dyn Any {
+                self
+            }
+            fn any_mut(&mut self) -> &mut dyn Any {
+                self
+            }
+            fn keyring_get(&self, service: &str, account: &str) -> Result<Option<String>, String> {
+                let key = (service.to_string(), account.to_string());
+                Ok(self.secrets.lock().get(&key).cloned())
+            }
+            fn keyring_set(
+                &self,
+                service: &str,
+                account: &str,
+                secret: &str,
+            ) -> Result<(), String> {
+                let key = (service.to_string(), account.to_string());
+                self.secrets.lock().insert(key, secret.into());
+                Ok(())
+            }
+            fn keyring_delete(&self, service: &str, account: &str) -> Result<(), String> {
+                let key = (service.to_string(), account.to_string());
+                self.secrets.lock().remove(&key);
+                Ok(())
+            }
+        }
+        let mut env = Uiua::with_backend(Keychain::default());
+        let code = r#"
+            # Experimental!
+            &keyset "api" "me" "hunter2"
+            &keyset "api" "you" "swordfish"
+            &keyset "api" "me" "correct horse"
+            &keyget "api" "me"
+            &keydel "api" "you"
+            &keydel "api" "nobody"
+            ⍣(&keyget "api" "you")"missing"
+        "#;
+        env.run_str(code).unwrap();
+        assert_eq!(env.pop("deleted").unwrap(), Value::from("missing"));
+        assert_eq!(env.pop("secret").unwrap(), Value::from("correct horse"));
+    }
+}
@@ -0,0 +1,592 @@
+//! MQTT clients
+//!
+//! This implements the parts of MQTT 3.1.1 that a client needs.
+//! Each connection has a thread that reads packets from the broker,
+//! acknowledges the messages it receives, and keeps the connection alive.
+
+use serde::*;
+
+use crate::{Uiua, UiuaResult, Value};
+
+/// Options for connecting to an MQTT broker
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MqttOptions {
+    /// The address of the broker
+    ///
+    /// If there is no port, the default port `1883` is used.
+    pub address: String,
+    /// The client identifier
+    ///
+    /// If it is empty, the broker assigns one.
+    pub client_id: String,
+    /// The user name to authenticate with
+    pub username: Option<String>,
+    /// The password to authenticate with
+    ///
+    /// This is not serialized so that it does not end up in recordings.
+    #[serde(skip_serializing, default)]
+    pub password: Option<String>,
+    /// The keep alive interval in seconds
+    pub keep_alive: u16,
+    /// Whether to start a new session instead of resuming the previous one
+    pub clean_session: bool,
+}
+
+impl Default for MqttOptions {
+    fn default() -> Self {
+        Self {
+            address: String::new(),
+            client_id: String::new(),
+            username: None,
+            password: None,
+            keep_alive: 60,
+            clean_session: true,
+        }
+    }
+}
+
+impl MqttOptions {
+    /// Get options from an address string or a map of options
+    pub(crate) fn from_value(value: &Value, env: &Uiua) -> UiuaResult<Self> {
+        if !value.is_map() {
+            let address = value.as_string(env, "MQTT broker must be an address or a map")?;
+            return Ok(MqttOptions {
+                address,
+                ..Default::default()
+            });
+        }
+        let mut options = MqttOptions::default();
+        for (key, val) in value.map_kv() {
+            let key = key.as_string(env, "MQTT option names must be strings")?;
+            match key.as_str() {
+                "address" => options.address = val.as_string(env, "Address must be a string")?,
+                "client_id" => {
+                    options.client_id = val.as_string(env, "Client id must be a string")?
+                }
+                "username" => {
+                    options.username = Some(val.as_string(env, "User name must be a string")?)
+                }
+                "password" => {
+                    options.password = Some(val.as_string(env, "Password must be a string")?)
+                }
+                "keep_alive" => {
+                    let secs = val.as_nat(env, "Keep alive must be a natural number")?;
+                    options.keep_alive = secs.min(u16::MAX as usize) as u16;
+                }
+                "clean_session" => {
+                    options.clean_session = val.as_bool(env, "Clean session must be a boolean")?
+                }
+                _ => {
+                    return Err(env.error(format!(
+                        "Unknown MQTT option {key:?}. The options are \
+                        address, client_id, username, password, keep_alive, and clean_session"
+                    )))
+                }
+            }
+        }
+        if options.address.is_empty() {
+            return Err(env.error("MQTT options must include an address"));
+        }
+        Ok(options)
+    }
+}
+
+/// A message published to an MQTT topic
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MqttMessage {
+    /// The topic the message was published to
+    pub topic: String,
+    /// The message's contents
+    pub payload: Vec<u8>,
+    /// The quality of service level
+    pub qos: u8,
+    /// Whether the broker should keep the message for future subscribers
+    pub retain: bool,
+}
+
+impl MqttMessage {
+    /// Convert the message to a map
+    ///
+    /// The payload is a string if it is valid UTF-8 and a list of bytes otherwise.
+    pub(crate) fn into_value(self, env: &Uiua) -> UiuaResult<Value> {
+        let payload = match String::from_utf8(self.payload) {
+            Ok(s) => serde_json::Value::from(s),
+            Err(e) => serde_json::Value::from(e.into_bytes()),
+        };
+        let json = serde_json::json!({
+            "topic": self.topic,
+            "payload": payload,
+            "qos": self.qos,
+            "retain": self.retain,
+        });
+        Value::from_json_value(json, env)
+    }
+}
+
+#[cfg(feature = "native_sys")]
+pub(crate) use client::MqttClient;
+
+#[cfg(feature = "native_sys")]
+mod client {
+    use std::{
+        collections::HashMap,
+        io::{self, ErrorKind, Read, Write},
+        net::{Shutdown, TcpStream},
+        sync::{
+            atomic::{AtomicU16, Ordering},
+            Arc,
+        },
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
+    use parking_lot::Mutex;
+
+    use super::{MqttMessage, MqttOptions};
+
+    const CONNECT: u8 = 1;
+    const CONNACK: u8 = 2;
+    const PUBLISH: u8 = 3;
+    const PUBACK: u8 = 4;
+    const PUBREC: u8 = 5;
+    const PUBREL: u8 = 6;
+    const PUBCOMP: u8 = 7;
+    const SUBSCRIBE: u8 = 8;
+    const SUBACK: u8 = 9;
+    const PINGREQ: u8 = 12;
+    const DISCONNECT: u8 = 14;
+
+    const DEFAULT_PORT: u16 = 1883;
+    /// How long to wait for the broker to respond
+    const RESPONSE_TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// A connection to an MQTT broker
+    #[derive(Clone)]
+    pub(crate) struct MqttClient {
+        shared: Arc<Shared>,
+        incoming: Receiver<MqttMessage>,
+        next_id: Arc<AtomicU16>,
+    }
+
+    struct Shared {
+        writer: Mutex<Writer>,
+        /// Senders for the acknowledgements that are being waited for, by packet id
+        acks: Mutex<HashMap<u16, Sender<Ack>>>,
+        /// The error that closed the connection
+        error: Mutex<Option<String>>,
+    }
+
+    /// The kind and body of a packet that completes a request
+    type Ack = (u8, Vec<u8>);
+
+    struct Writer {
+        stream: TcpStream,
+        last_sent: Instant,
+    }
+
+    impl Shared {
+        fn send(&self, packet: &[u8]) -> Result<(), String> {
+            let mut writer = self.writer.lock();
+            (writer.stream.write_all(packet))
+                .map_err(|e| format!("MQTT connection failed: {e}"))?;
+            writer.last_sent = Instant::now();
+            Ok(())
+        }
+        fn closed_error(&self) -> String {
+            (self.error.lock().clone()).unwrap_or_else(|| "MQTT connection is closed".into())
+        }
+    }
+
+    impl MqttClient {
+        /// Connect to a broker
+        pub fn connect(options: &MqttOptions) -> Result<Self, String> {
+            let mut address = options.address.clone();
+            if !address.contains(':') {
+                address = format!("{address}:{DEFAULT_PORT}");
+            }
+            let mut stream = TcpStream::connect(&address)
+                .map_err(|e| format!("Failed to connect to MQTT broker at {address}: {e}"))?;
+            let io_err = |e: io::Error| format!("MQTT connection failed: {e}");
+            stream.write_all(&connect_packet(options)).map_err(io_err)?;
+            stream
+                .set_read_timeout(Some(RESPONSE_TIMEOUT))
+                .map_err(io_err)?;
+            let (header, body) = read_packet(&mut stream).map_err(io_err)?;
+            if header >> 4 != CONNACK || body.len() < 2 {
+                return Err("Invalid response from MQTT broker".into());
+            }
+            let refusal = match body[1] {
+                0 => None,
+                1 => Some("it does not support the protocol version"),
+                2 => Some("it rejected the client id"),
+                3 => Some("it is unavailable"),
+                4 => Some("the user name or password is wrong"),
+                5 => Some("the client is not authorized"),
+                _ => Some("of an unknown reason"),
+            };
+            if let Some(reason) = refusal {
+                return Err(format!(
+                    "MQTT broker refused the connection because {reason}"
+                ));
+            }
+            // The reader wakes up at least twice per keep alive interval to send pings
+            let ping_interval = (options.keep_alive > 0)
+                .then(|| Duration::from_secs(options.keep_alive as u64) / 2);
+            stream.set_read_timeout(ping_interval).map_err(io_err)?;
+            let reader = stream.try_clone().map_err(io_err)?;
+            let shared = Arc::new(Shared {
+                writer: Mutex::new(Writer {
+                    stream,
+                    last_sent: Instant::now(),
+                }),
+                acks: Default::default(),
+                error: Default::default(),
+            });
+            let (send, incoming) = crossbeam_channel::unbounded();
+            let thread_shared = shared.clone();
+            thread::spawn(move || {
+                let error = read_loop(reader, &thread_shared, send, ping_interval);
+                *thread_shared.error.lock() = Some(error);
+                // Dropping the senders wakes up anything waiting for an acknowledgement
+                thread_shared.acks.lock().clear();
+            });
+            Ok(MqttClient {
+                shared,
+                incoming,
+                next_id: Arc::new(AtomicU16::new(1)),
+            })
+        }
+        /// Subscribe to a topic filter
+        pub fn subscribe(&self, topic: &str, qos: u8) -> Result<(), String> {
+            let id = self.next_id();
+            let mut body = id.to_be_bytes().to_vec();
+            write_str(&mut body, topic.as_bytes())?;
+            body.push(qos);
+            let (kind, body) = self.request(id, &packet(SUBSCRIBE << 4 | 0b10, &body))?;
+            if kind != SUBACK || body.get(2).map_or(true, |&code| code == 0x80) {
+                return Err(format!("MQTT broker refused the subscription to {topic:?}"));
+            }
+            Ok(())
+        }
+        /// Publish a message
+        ///
+        /// For QoS levels above `0`, this waits for the broker to acknowledge the message.
+        pub fn publish(&self, message: &MqttMessage) -> Result<(), String> {
+            let id = (message.qos > 0).then(|| self.next_id());
+            let packet = publish_packet(message, id)?;
+            match id {
+                Some(id) => self.request(id, &packet).map(drop),
+                None => self.shared.send(&packet),
+            }
+        }
+        /// Receive a message from a subscribed topic
+        ///
+        /// Returns `None` if the timeout is reached
+        pub fn receive(&self, timeout: Option<Duration>) -> Result<Option<MqttMessage>, String> {
+            let res = match timeout {
+                Some(timeout) => self.incoming.recv_timeout(timeout),
+                None => self
+                    .incoming
+                    .recv()
+                    .map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match res {
+                Ok(message) => Ok(Some(message)),
+                Err(RecvTimeoutError::Timeout) => Ok(None),
+                Err(RecvTimeoutError::Disconnected) => Err(self.shared.closed_error()),
+            }
+        }
+        /// Disconnect from the broker
+        pub fn disconnect(&self) -> Result<(), String> {
+            let res = self.shared.send(&packet(DISCONNECT << 4, &[]));
+            _ = self.shared.writer.lock().stream.shutdown(Shutdown::Both);
+            res
+        }
+        fn next_id(&self) -> u16 {
+            loop {
+                let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+                if id != 0 {
+                    break id;
+                }
+            }
+        }
+        /// Send a packet and wait for the packet that completes it
+        fn request(&self, id: u16, packet: &[u8]) -> Result<Ack, String> {
+            let (send, recv) = crossbeam_channel::bounded(1);
+            self.shared.acks.lock().insert(id, send);
+            self.shared.send(packet)?;
+            let res = recv.recv_timeout(RESPONSE_TIMEOUT);
+            self.shared.acks.lock().remove(&id);
+            match res {
+                Ok(ack) => Ok(ack),
+                Err(RecvTimeoutError::Timeout) => Err("MQTT broker did not respond in time".into()),
+                Err(RecvTimeoutError::Disconnected) => Err(self.shared.closed_error()),
+            }
+        }
+    }
+
+    /// Read packets until the connection closes, and return the reason it closed
+    fn read_loop(
+        mut reader: TcpStream,
+        shared: &Shared,
+        incoming: Sender<MqttMessage>,
+        ping_interval: Option<Duration>,
+    ) -> String {
+        let ack = |kind: u8, id: &[u8]| shared.send(&packet(kind, id));
+        loop {
+            if let Some(interval) = ping_interval {
+                let last_sent = shared.writer.lock().last_sent;
+                if last_sent.elapsed() >= interval {
+                    if let Err(e) = shared.send(&packet(PINGREQ << 4, &[])) {
+                        return e;
+                    }
+                }
+            }
+            let mut header = [0];
+            match reader.read(&mut header) {
+                Ok(0) => return "MQTT broker closed the connection".into(),
+                Ok(_) => {}
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    continue
+                }
+                Err(e) => return format!("MQTT connection failed: {e}"),
+            }
+            let body = match read_body(&mut reader) {
+                Ok(body) => body,
+                Err(e) => return format!("MQTT connection failed: {e}"),
+            };
+            let header = header[0];
+            let id = body.get(..2).unwrap_or_default();
+            let res = match header >> 4 {
+                PUBLISH => {
+                    let Some((message, id)) = parse_publish(header, &body) else {
+                        return "Invalid message from MQTT broker".into();
+                    };
+                    let res = match (message.qos, id) {
+                        (1, Some(id)) => ack(PUBACK << 4, &id.to_be_bytes()),
+                        (2, Some(id)) => ack(PUBREC << 4, &id.to_be_bytes()),
+                        _ => Ok(()),
+                    };
+                    _ = incoming.send(message);
+                    res
+                }
+                PUBREC => ack(PUBREL << 4 | 0b10, id),
+                PUBREL => ack(PUBCOMP << 4, id),
+                PUBACK | PUBCOMP | SUBACK => {
+                    if let Ok(id) = <[u8; 2]>::try_from(id) {
+                        if let Some(send) = shared.acks.lock().remove(&u16::from_be_bytes(id)) {
+                            _ = send.send((header >> 4, body));
+                        }
+                    }
+                    Ok(())
+                }
+                _ => Ok(()),
+            };
+            if let Err(e) = res {
+                return e;
+            }
+        }
+    }
+
+    fn connect_packet(options: &MqttOptions) -> Vec<u8> {
+        let mut flags = 0;
+        if options.clean_session {
+            flags |= 0b10;
+        }
+        if options.username.is_some() {
+            flags |= 0x80;
+        }
+        if options.password.is_some() {
+            flags |= 0x40;
+        }
+        let mut body = Vec::new();
+        _ = write_str(&mut body, b"MQTT");
+        body.push(4); // Protocol level 3.1.1
+        body.push(flags);
+        body.extend(options.keep_alive.to_be_bytes());
+        _ = write_str(&mut body, options.client_id.as_bytes());
+        for field in [&options.username, &options.password].into_iter().flatten() {
+            _ = write_str(&mut body, field.as_bytes());
+        }
+        packet(CONNECT << 4, &body)
+    }
+
+    fn publish_packet(message: &MqttMessage, id: Option<u16>) -> Result<Vec<u8>, String> {
+        let mut body = Vec::new();
+        write_str(&mut body, message.topic.as_bytes())?;
+        if let Some(id) = id {
+            body.extend(id.to_be_bytes());
+        }
+        body.extend(&message.payload);
+        let flags = (message.qos << 1) | message.retain as u8;
+        Ok(packet(PUBLISH << 4 | flags, &body))
+    }
+
+    fn parse_publish(header: u8, body: &[u8]) -> Option<(MqttMessage, Option<u16>)> {
+        let qos = (header >> 1) & 0b11;
+        let retain = header & 1 == 1;
+        let topic_len = u16::from_be_bytes(body.get(..2)?.try_into().ok()?) as usize;
+        let topic = String::from_utf8(body.get(2..2 + topic_len)?.to_vec()).ok()?;
+        let mut rest = &body[2 + topic_len..];
+        let id = if qos > 0 {
+            let id = u16::from_be_bytes(rest.get(..2)?.try_into().ok()?);
+            rest = &rest[2..];
+            Some(id)
+        } else {
+            None
+        };
+        let message = MqttMessage {
+            topic,
+            payload: rest.to_vec(),
+            qos,
+            retain,
+        };
+        Some((message, id))
+    }
+
+    /// Build a packet from its first byte and body
+    fn packet(header: u8, body: &[u8]) -> Vec<u8> {
+        let mut packet = vec![header];
+        let mut len = body.len();
+        loop {
+            let mut byte = (len % 128) as u8;
+            len /= 128;
+            if len > 0 {
+                byte |= 0x80;
+            }
+            packet.push(byte);
+            if len == 0 {
+                break;
+            }
+        }
+        packet.extend(body);
+        packet
+    }
+
+    /// Write a length-prefixed string
+    fn write_str(buf: &mut Vec<u8>, s: &[u8]) -> Result<(), String> {
+        let len = u16::try_from(s.len())
+            .map_err(|_| "MQTT strings cannot be longer than 65535 bytes".to_string())?;
+        buf.extend(len.to_be_bytes());
+        buf.extend(s);
+        Ok(())
+    }
+
+    fn read_packet(reader: &mut impl Read) -> io::Result<(u8, Vec<u8>)> {
+        let mut header = [0];
+        reader.read_exact(&mut header)?;
+        Ok((header[0], read_body(reader)?))
+    }
+
+    /// Read the remaining length and body of a packet
+    fn read_body(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+        let mut len = 0;
+        for i in 0..4 {
+            let mut byte = [0];
+            reader.read_exact(&mut byte)?;
+            len |= ((byte[0] & 0x7F) as usize) << (7 * i);
+            if byte[0] & 0x80 == 0 {
+                let mut body = vec![0; len];
+                reader.read_exact(&mut body)?;
+                return Ok(body);
+            }
+        }
+        Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "Invalid packet length from MQTT broker",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{MemoryBackend, RecordSys, SysBackend, Uiua};
+
+    #[test]
+    fn password_not_recorded() {
+        let path = std::env::temp_dir().join("uiua_mqtt_password_test.jsonl");
+        let backend = Arc::new(MemoryBackend::new()) as Arc<dyn SysBackend>;
+        let backend = RecordSys::create(&path, backend).unwrap();
+        let mut env = Uiua::with_backend(backend);
+        // The memory backend cannot connect, but the call is still recorded
+        _ = env.run_str(
+            r#"# Experimental!
+&mqttc map {"address" "username" "password"} {"localhost" "user" "hunter2"}"#,
+        );
+        drop(env);
+        let recording = std::fs::read_to_string(&path).unwrap();
+        _ = std::fs::remove_file(&path);
+        assert!(recording.contains("mqtt_connect"));
+        assert!(recording.contains("user"));
+        assert!(!recording.contains("hunter2"));
+    }
+
+    #[test]
+    #[cfg(feature = "native_sys")]
+    fn mqtt_client() {
+        use crate::*;
+        use std::{
+            io::{Read, Write},
+            net::{TcpListener, TcpStream},
+            thread,
+        };
+        fn read_packet(stream: &mut TcpStream) -> Option<Vec<u8>> {
+            let mut header = [0; 2];
+            stream.read_exact(&mut header).ok()?;
+            let mut body = vec![0; header[1] as usize];
+            stream.read_exact(&mut body).ok()?;
+            Some([&header[..], &body].concat())
+        }
+        // A broker that acknowledges everything and echoes published messages back
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let broker = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut received = Vec::new();
+            while let Some(packet) = read_packet(&mut stream) {
+                let reply = match packet[0] >> 4 {
+                    1 => vec![0x20, 2, 0, 0],
+                    8 => vec![0x90, 3, packet[2], packet[3], packet[packet.len() - 1]],
+                    3 => {
+                        let topic_len = u16::from_be_bytes([packet[2], packet[3]]) as usize;
+                        let id = &packet[4 + topic_len..6 + topic_len];
+                        [&[0x40, 2], id, &packet].concat()
+                    }
+                    _ => Vec::new(),
+                };
+                stream.write_all(&reply).unwrap();
+                received.push(packet[0]);
+            }
+            received
+        });
+        let mut env = Uiua::with_native_sys();
+        let code = format!(
+            r#"
+            # Experimental!
+            C ← &mqttc "{addr}"
+            &mqtts "sensors/#" 1 C
+            &mqttp "sensors/door" "open" 1 C
+            M ← &mqttr 5 C
+            ⍣(&mqttr 0 C|"timeout")
+            [°□get "qos" M °□get "retain" M]
+            °□get "payload" M
+            °□get "topic" M
+            &cl C
+            "#
+        );
+        env.run_str(&code).unwrap();
+        let topic = env.pop("topic").unwrap();
+        assert_eq!(topic.as_string(&env, "").unwrap(), "sensors/door");
+        let payload = env.pop("payload").unwrap();
+        assert_eq!(payload.as_string(&env, "").unwrap(), "open");
+        let flags = env.pop("flags").unwrap();
+        assert_eq!(flags.as_nats(&env, "").unwrap(), [1, 0]);
+        let timeout = env.pop("timeout").unwrap();
+        assert_eq!(timeout.as_string(&env, "").unwrap(), "timeout");
+        // Connect, subscribe, publish, acknowledge the echo, and disconnect
+        assert_eq!(broker.join().unwrap(), [0x10, 0x82, 0x32, 0x40, 0xE0]);
+    }
+}
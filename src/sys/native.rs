@@ -1,8 +1,8 @@
 use std::{
     any::Any,
-    env::{self, set_current_dir},
+    env,
     fs::{self, File, OpenOptions},
-    io::{stderr, stdin, stdout, BufRead, BufReader, Read, Write},
+    io::{stderr, stdin, stdout, BufRead, BufReader, ErrorKind, Read, Write},
     net::*,
     path::{Path, PathBuf},
     process::{Child, ChildStderr, ChildStdin, ChildStdout, Command, Stdio},
@@ -12,21 +12,31 @@ use std::{
         Arc,
     },
     thread::sleep,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use colored::Colorize;
 use dashmap::DashMap;
 use once_cell::sync::Lazy;
 
+use super::{
+    git::{git, LockedModule, Lockfile},
+    mqtt::MqttClient,
+};
 use crate::{
-    terminal_size, GitTarget, Handle, ReadLinesFn, ReadLinesReturnFn, Span, SysBackend, Uiua, Value,
+    terminal_size, GitTarget, Handle, MqttMessage, MqttOptions, ReadLinesFn, ReadLinesReturnFn,
+    Span, SysBackend, Uiua, Value,
 };
+#[cfg(feature = "tui")]
+use crate::{TermCommand, TermEvent};
 
 /// The default native system backend
 #[derive(Default)]
 pub struct NativeSys;
 
+#[cfg(all(feature = "keyring", not(unix)))]
+const KEYRING_UNSUPPORTED: &str = "The keychain is not supported on Windows";
+
 struct GlobalNativeSys {
     output_enabled: AtomicBool,
     next_handle: AtomicU64,
@@ -38,6 +48,10 @@ struct GlobalNativeSys {
     tls_listeners: DashMap<Handle, TlsListener>,
     tcp_sockets: DashMap<Handle, TcpStream>,
     tls_sockets: DashMap<Handle, TlsSocket>,
+    udp_sockets: DashMap<Handle, UdpSocket>,
+    /// Sockets for sending UDP packets, keyed by whether they are IPv6
+    udp_senders: DashMap<bool, UdpSocket>,
+    mqtt_clients: DashMap<Handle, MqttClient>,
     #[cfg(feature = "webcam")]
     cam_channels: DashMap<usize, WebcamChannel>,
     hostnames: DashMap<Handle, String>,
@@ -49,8 +63,16 @@ struct GlobalNativeSys {
     colored_errors: DashMap<String, String>,
     #[cfg(feature = "ffi")]
     ffi: crate::FfiState,
+    #[cfg(feature = "ffi")]
+    plugins: crate::PluginState,
     #[cfg(all(feature = "gif", feature = "invoke"))]
     gifs_child: parking_lot::Mutex<Option<Child>>,
+    #[cfg(feature = "python")]
+    python: parking_lot::Mutex<Option<super::python::PythonBridge>>,
+    #[cfg(all(feature = "embedded", target_os = "linux"))]
+    gpio: super::embedded::GpioState,
+    #[cfg(feature = "tui")]
+    prompt_history: parking_lot::Mutex<Vec<String>>,
 }
 
 enum SysStream<'a> {
@@ -213,6 +235,9 @@ impl Default for GlobalNativeSys {
             tls_listeners: DashMap::new(),
             tcp_sockets: DashMap::new(),
             tls_sockets: DashMap::new(),
+            udp_sockets: DashMap::new(),
+            udp_senders: DashMap::new(),
+            mqtt_clients: DashMap::new(),
             #[cfg(feature = "webcam")]
             cam_channels: DashMap::new(),
             hostnames: DashMap::new(),
@@ -224,8 +249,16 @@ impl Default for GlobalNativeSys {
             colored_errors: DashMap::new(),
             #[cfg(feature = "ffi")]
             ffi: Default::default(),
+            #[cfg(feature = "ffi")]
+            plugins: Default::default(),
             #[cfg(all(feature = "gif", feature = "invoke"))]
             gifs_child: parking_lot::Mutex::new(None),
+            #[cfg(feature = "python")]
+            python: parking_lot::Mutex::new(None),
+            #[cfg(all(feature = "embedded", target_os = "linux"))]
+            gpio: Default::default(),
+            #[cfg(feature = "tui")]
+            prompt_history: parking_lot::Mutex::new(Vec::new()),
         }
     }
 }
@@ -241,6 +274,8 @@ impl GlobalNativeSys {
                 && !self.tcp_listeners.contains_key(&handle)
                 && !self.tcp_sockets.contains_key(&handle)
                 && !self.tls_sockets.contains_key(&handle)
+                && !self.udp_sockets.contains_key(&handle)
+                && !self.mqtt_clients.contains_key(&handle)
             {
                 return handle;
             }
@@ -278,6 +313,12 @@ impl GlobalNativeSys {
             (self.tls_sockets.get(&handle)).map(|sock| f(&sock.stream))
         }
     }
+    /// Clone a client so that the map is not locked while waiting on the broker
+    fn mqtt_client(&self, handle: Handle) -> Result<MqttClient, String> {
+        (self.mqtt_clients.get(&handle))
+            .map(|client| client.clone())
+            .ok_or_else(|| "Invalid mqtt client handle".into())
+    }
 }
 
 static NATIVE_SYS: Lazy<GlobalNativeSys> = Lazy::new(Default::default);
@@ -297,6 +338,15 @@ pub fn set_audio_stream_time_port(port: u16) -> std::io::Result<()> {
     Ok(())
 }
 
+/// Set the directory that [`NativeSys`] loads plugins from
+///
+/// Plugins are only loaded from a directory set this way.
+/// Returns `false` if a directory was already set.
+#[cfg(feature = "ffi")]
+pub fn set_plugin_dir(dir: impl Into<PathBuf>) -> bool {
+    NATIVE_SYS.plugins.set_dir(dir.into())
+}
+
 pub(crate) fn output_enabled() -> bool {
     NATIVE_SYS.output_enabled.load(atomic::Ordering::Relaxed)
 }
@@ -322,6 +372,29 @@ impl SysBackend for NativeSys {
         stdout.write_all(s.as_bytes()).map_err(|e| e.to_string())?;
         stdout.flush().map_err(|e| e.to_string())
     }
+    fn progress(&self, current: f64, total: f64, label: &str) -> Result<(), String> {
+        use std::io::IsTerminal;
+        const WIDTH: usize = 30;
+        if !output_enabled() || !stderr().is_terminal() {
+            return Ok(());
+        }
+        let fraction = if total > 0.0 {
+            (current / total).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        let filled = (fraction * WIDTH as f64).round() as usize;
+        let mut line = format!(
+            "\r\x1b[2K{label} {}{} {:>3.0}% ({current}/{total})",
+            "█".repeat(filled),
+            "░".repeat(WIDTH - filled),
+            fraction * 100.0
+        );
+        if current >= total {
+            line.push('\n');
+        }
+        self.print_str_stderr(&line)
+    }
     fn print_str_stderr(&self, s: &str) -> Result<(), String> {
         if !output_enabled() {
             return Ok(());
@@ -368,6 +441,94 @@ impl SysBackend for NativeSys {
         }
         Ok(Some(String::from_utf8(buffer).map_err(|e| e.to_string())?))
     }
+    #[cfg(feature = "tui")]
+    fn prompt(&self, prompt: &str, hidden: bool) -> Result<Option<String>, String> {
+        use std::io::IsTerminal;
+
+        use crossterm::{
+            cursor,
+            event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+            execute, terminal,
+        };
+        if !output_enabled() {
+            return Ok(None);
+        }
+        self.print_str_stdout(prompt)?;
+        if !stdin().is_terminal() {
+            return self.scan_line_stdin();
+        }
+        let was_raw = terminal::is_raw_mode_enabled().map_err(|e| e.to_string())?;
+        terminal::enable_raw_mode().map_err(|e| e.to_string())?;
+        let mut history = NATIVE_SYS.prompt_history.lock();
+        let mut history_index = history.len();
+        let mut line: Vec<char> = Vec::new();
+        let mut pos = 0;
+        let res = (|| -> std::io::Result<Option<String>> {
+            let mut stdout = stdout().lock();
+            let (start_col, _) = cursor::position()?;
+            loop {
+                let Event::Key(key) = event::read()? else {
+                    continue;
+                };
+                if key.kind == KeyEventKind::Release {
+                    continue;
+                }
+                let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+                match key.code {
+                    KeyCode::Enter => break,
+                    KeyCode::Char('c' | 'd') if ctrl => return Ok(None),
+                    KeyCode::Char(c) => {
+                        line.insert(pos, c);
+                        pos += 1;
+                    }
+                    KeyCode::Backspace if pos > 0 => {
+                        pos -= 1;
+                        line.remove(pos);
+                    }
+                    KeyCode::Delete if pos < line.len() => _ = line.remove(pos),
+                    KeyCode::Left => pos = pos.saturating_sub(1),
+                    KeyCode::Right => pos = (pos + 1).min(line.len()),
+                    KeyCode::Home => pos = 0,
+                    KeyCode::End => pos = line.len(),
+                    KeyCode::Up if !hidden && history_index > 0 => {
+                        history_index -= 1;
+                        line = history[history_index].chars().collect();
+                        pos = line.len();
+                    }
+                    KeyCode::Down if !hidden && history_index < history.len() => {
+                        history_index += 1;
+                        line = (history.get(history_index))
+                            .map(|s| s.chars().collect())
+                            .unwrap_or_default();
+                        pos = line.len();
+                    }
+                    _ => continue,
+                }
+                if !hidden {
+                    let text: String = line.iter().collect();
+                    execute!(
+                        stdout,
+                        cursor::MoveToColumn(start_col),
+                        terminal::Clear(terminal::ClearType::UntilNewLine),
+                        crossterm::style::Print(text),
+                        cursor::MoveToColumn(start_col + pos as u16),
+                    )?;
+                }
+            }
+            Ok(Some(line.iter().collect()))
+        })();
+        if !was_raw {
+            _ = terminal::disable_raw_mode();
+        }
+        self.print_str_stdout("\r\n")?;
+        let line = res.map_err(|e| e.to_string())?;
+        if let Some(line) = &line {
+            if !hidden && !line.is_empty() && history.last() != Some(line) {
+                history.push(line.clone());
+            }
+        }
+        Ok(line)
+    }
     fn scan_stdin(&self, count: Option<usize>) -> Result<Vec<u8>, String> {
         if !output_enabled() {
             return Ok(Vec::new());
@@ -391,6 +552,111 @@ impl SysBackend for NativeSys {
         let (w, h) = terminal_size().ok_or("Failed to get terminal size")?;
         Ok((w, h.saturating_sub(1)))
     }
+    #[cfg(feature = "tui")]
+    fn term_command(&self, command: TermCommand) -> Result<(), String> {
+        use crossterm::{cursor, event, execute, style, terminal};
+        if !output_enabled() {
+            return Ok(());
+        }
+        let mut stdout = stdout().lock();
+        let color = |color: Option<[u8; 3]>| {
+            color.map_or(style::Color::Reset, |[r, g, b]| style::Color::Rgb {
+                r,
+                g,
+                b,
+            })
+        };
+        match command {
+            TermCommand::MoveCursor(row, col) => execute!(stdout, cursor::MoveTo(col, row)),
+            TermCommand::Clear => execute!(
+                stdout,
+                terminal::Clear(terminal::ClearType::All),
+                cursor::MoveTo(0, 0)
+            ),
+            TermCommand::SetColors(fg, bg) => execute!(
+                stdout,
+                style::SetForegroundColor(color(fg)),
+                style::SetBackgroundColor(color(bg))
+            ),
+            TermCommand::ShowCursor(true) => execute!(stdout, cursor::Show),
+            TermCommand::ShowCursor(false) => execute!(stdout, cursor::Hide),
+            TermCommand::CaptureMouse(true) => execute!(stdout, event::EnableMouseCapture),
+            TermCommand::CaptureMouse(false) => execute!(stdout, event::DisableMouseCapture),
+        }
+        .map_err(|e| e.to_string())
+    }
+    #[cfg(feature = "tui")]
+    fn term_event(&self, timeout: Option<Duration>) -> Result<Option<TermEvent>, String> {
+        use crossterm::event::{
+            self, Event, KeyCode, KeyEventKind, KeyModifiers, MouseButton, MouseEventKind,
+        };
+        let start = std::time::Instant::now();
+        loop {
+            if let Some(timeout) = timeout {
+                let remaining = timeout.saturating_sub(start.elapsed());
+                if !event::poll(remaining).map_err(|e| e.to_string())? {
+                    return Ok(None);
+                }
+            }
+            let event = match event::read().map_err(|e| e.to_string())? {
+                Event::Key(key) if key.kind != KeyEventKind::Release => {
+                    let name = match key.code {
+                        KeyCode::Char(c) => c.to_string(),
+                        KeyCode::F(n) => format!("f{n}"),
+                        KeyCode::Enter => "enter".into(),
+                        KeyCode::Esc => "esc".into(),
+                        KeyCode::Backspace => "backspace".into(),
+                        KeyCode::Tab => "tab".into(),
+                        KeyCode::BackTab => "backtab".into(),
+                        KeyCode::Delete => "delete".into(),
+                        KeyCode::Insert => "insert".into(),
+                        KeyCode::Home => "home".into(),
+                        KeyCode::End => "end".into(),
+                        KeyCode::PageUp => "pageup".into(),
+                        KeyCode::PageDown => "pagedown".into(),
+                        KeyCode::Up => "up".into(),
+                        KeyCode::Down => "down".into(),
+                        KeyCode::Left => "left".into(),
+                        KeyCode::Right => "right".into(),
+                        _ => continue,
+                    };
+                    TermEvent::Key {
+                        key: name,
+                        ctrl: key.modifiers.contains(KeyModifiers::CONTROL),
+                        alt: key.modifiers.contains(KeyModifiers::ALT),
+                        shift: key.modifiers.contains(KeyModifiers::SHIFT),
+                    }
+                }
+                Event::Mouse(mouse) => {
+                    let button = |button: MouseButton| {
+                        Some(match button {
+                            MouseButton::Left => "left".into(),
+                            MouseButton::Right => "right".into(),
+                            MouseButton::Middle => "middle".into(),
+                        })
+                    };
+                    let (kind, button) = match mouse.kind {
+                        MouseEventKind::Down(b) => ("down", button(b)),
+                        MouseEventKind::Up(b) => ("up", button(b)),
+                        MouseEventKind::Drag(b) => ("drag", button(b)),
+                        MouseEventKind::Moved => ("move", None),
+                        MouseEventKind::ScrollUp => ("scroll up", None),
+                        MouseEventKind::ScrollDown => ("scroll down", None),
+                        _ => continue,
+                    };
+                    TermEvent::Mouse {
+                        kind: kind.into(),
+                        button,
+                        row: mouse.row,
+                        col: mouse.column,
+                    }
+                }
+                Event::Resize(cols, rows) => TermEvent::Resize { rows, cols },
+                _ => continue,
+            };
+            return Ok(Some(event));
+        }
+    }
     fn exit(&self, code: i32) -> Result<(), String> {
         std::process::exit(code)
     }
@@ -451,6 +717,14 @@ impl SysBackend for NativeSys {
         NATIVE_SYS.files.insert(handle, BufReader::new(file));
         Ok(handle)
     }
+    fn file_append(&self, path: &Path, contents: &[u8]) -> Result<(), String> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("{e} {}", path.display()))?;
+        file.write_all(contents).map_err(|e| e.to_string())
+    }
     fn make_dir(&self, path: &Path) -> Result<(), String> {
         fs::create_dir_all(path).map_err(|e| e.to_string())
     }
@@ -744,6 +1018,24 @@ impl SysBackend for NativeSys {
             Err(e) => Err(format!("Failed to initialize audio output stream: {e}").to_string()),
         }
     }
+    #[cfg(feature = "window")]
+    fn window_show(&self, name: &str, image: image::DynamicImage) -> Result<(), String> {
+        crate::window::Request::WindowShow {
+            name: name.into(),
+            width: image.width(),
+            height: image.height(),
+            rgba: image.into_rgba8().into_raw(),
+        }
+        .send()
+    }
+    #[cfg(feature = "window")]
+    fn window_close(&self, name: &str) -> Result<(), String> {
+        crate::window::Request::WindowClose(name.into()).send()
+    }
+    #[cfg(feature = "window")]
+    fn window_events(&self, name: &str) -> Result<Vec<crate::WindowEvent>, String> {
+        crate::window::Request::WindowEvents(name.into()).send_and_receive()
+    }
     #[cfg(feature = "audio")]
     fn audio_sample_rate(&self) -> u32 {
         hodaun::default_output_device()
@@ -931,6 +1223,190 @@ impl SysBackend for NativeSys {
             .ok_or_else(|| "Invalid tcp socket handle".to_string())?
             .map_err(|e| e.to_string())
     }
+    fn udp_bind(&self, addr: &str) -> Result<Handle, String> {
+        let handle = NATIVE_SYS.new_handle();
+        let socket = UdpSocket::bind(addr).map_err(|e| e.to_string())?;
+        NATIVE_SYS.udp_sockets.insert(handle, socket);
+        Ok(handle)
+    }
+    fn udp_addr(&self, handle: Handle) -> Result<SocketAddr, String> {
+        (NATIVE_SYS.udp_sockets.get(&handle))
+            .ok_or_else(|| "Invalid udp socket handle".to_string())?
+            .local_addr()
+            .map_err(|e| e.to_string())
+    }
+    fn udp_send(&self, addr: &str, packet: &[u8]) -> Result<(), String> {
+        let addr = (addr.to_socket_addrs())
+            .map_err(|e| e.to_string())?
+            .next()
+            .ok_or_else(|| format!("Could not resolve {addr}"))?;
+        let socket = match NATIVE_SYS.udp_senders.entry(addr.is_ipv6()) {
+            dashmap::mapref::entry::Entry::Occupied(entry) => entry.into_ref(),
+            dashmap::mapref::entry::Entry::Vacant(entry) => {
+                let local = if addr.is_ipv6() {
+                    "[::]:0"
+                } else {
+                    "0.0.0.0:0"
+                };
+                entry.insert(UdpSocket::bind(local).map_err(|e| e.to_string())?)
+            }
+        };
+        socket.send_to(packet, addr).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+    fn udp_receive(
+        &self,
+        handle: Handle,
+        timeout: Option<Duration>,
+    ) -> Result<Option<Vec<u8>>, String> {
+        // Clone the socket so that it is not locked while waiting
+        let socket = (NATIVE_SYS.udp_sockets.get(&handle))
+            .ok_or_else(|| "Invalid udp socket handle".to_string())?
+            .try_clone()
+            .map_err(|e| e.to_string())?;
+        if timeout == Some(Duration::ZERO) {
+            socket.set_nonblocking(true)
+        } else {
+            (socket.set_nonblocking(false)).and_then(|_| socket.set_read_timeout(timeout))
+        }
+        .map_err(|e| e.to_string())?;
+        let mut buffer = vec![0; 1 << 16];
+        match socket.recv(&mut buffer) {
+            Ok(len) => {
+                buffer.truncate(len);
+                Ok(Some(buffer))
+            }
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+    fn dns_lookup(&self, record_type: &str, name: &str) -> Result<Vec<String>, String> {
+        if record_type == "TXT" {
+            return super::dns::lookup_txt(name);
+        }
+        let addrs =
+            ((name, 0).to_socket_addrs()).map_err(|e| format!("Failed to look up {name}: {e}"))?;
+        let mut records = Vec::new();
+        for addr in addrs {
+            let ip = addr.ip();
+            if ip.is_ipv6() == (record_type == "AAAA") && !records.contains(&ip) {
+                records.push(ip);
+            }
+        }
+        Ok(records.into_iter().map(|ip| ip.to_string()).collect())
+    }
+    fn ping(&self, addr: &str, timeout: Option<Duration>) -> Result<Option<f64>, String> {
+        let addr = if addr.parse::<IpAddr>().is_ok() || !addr.contains(':') {
+            (addr.trim_matches(['[', ']']), 80).to_socket_addrs()
+        } else {
+            addr.to_socket_addrs()
+        }
+        .map_err(|e| format!("Failed to look up {addr}: {e}"))?
+        .next()
+        .ok_or_else(|| format!("Could not resolve {addr}"))?;
+        let start = Instant::now();
+        let res = match timeout {
+            Some(timeout) => TcpStream::connect_timeout(&addr, timeout),
+            None => TcpStream::connect(addr),
+        };
+        Ok(match res {
+            Ok(_) => Some(start.elapsed().as_secs_f64()),
+            Err(e) if e.kind() == ErrorKind::ConnectionRefused => {
+                Some(start.elapsed().as_secs_f64())
+            }
+            Err(_) => None,
+        })
+    }
+    #[cfg(unix)]
+    fn interface_addrs(&self) -> Result<Vec<(String, IpAddr)>, String> {
+        use std::ffi::CStr;
+        let mut first = std::ptr::null_mut();
+        // Safety: getifaddrs initializes the list, which is freed below
+        if unsafe { libc::getifaddrs(&mut first) } != 0 {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+        let mut addrs = Vec::new();
+        let mut curr = first;
+        while !curr.is_null() {
+            // Safety: every entry in the list is valid until it is freed,
+            // and the address is interpreted according to its family
+            unsafe {
+                let ifa = &*curr;
+                curr = ifa.ifa_next;
+                if ifa.ifa_addr.is_null() {
+                    continue;
+                }
+                let ip = match (*ifa.ifa_addr).sa_family as libc::c_int {
+                    libc::AF_INET => {
+                        let sin = &*(ifa.ifa_addr as *const libc::sockaddr_in);
+                        IpAddr::from(u32::from_be(sin.sin_addr.s_addr).to_be_bytes())
+                    }
+                    libc::AF_INET6 => {
+                        let sin6 = &*(ifa.ifa_addr as *const libc::sockaddr_in6);
+                        IpAddr::from(sin6.sin6_addr.s6_addr)
+                    }
+                    _ => continue,
+                };
+                let name = CStr::from_ptr(ifa.ifa_name).to_string_lossy().into_owned();
+                addrs.push((name, ip));
+            }
+        }
+        // Safety: the list was allocated by getifaddrs and is no longer used
+        unsafe { libc::freeifaddrs(first) };
+        Ok(addrs)
+    }
+    #[cfg(feature = "smtp")]
+    fn smtp_send(
+        &self,
+        server: &crate::SmtpServer,
+        auth: Option<&crate::SmtpAuth>,
+        email: &crate::Email,
+    ) -> Result<(), String> {
+        super::smtp::send(server, auth, email)
+    }
+    #[cfg(all(feature = "keyring", unix))]
+    fn keyring_get(&self, service: &str, account: &str) -> Result<Option<String>, String> {
+        super::keyring::get(service, account)
+    }
+    #[cfg(all(feature = "keyring", unix))]
+    fn keyring_set(&self, service: &str, account: &str, secret: &str) -> Result<(), String> {
+        super::keyring::set(service, account, secret)
+    }
+    #[cfg(all(feature = "keyring", unix))]
+    fn keyring_delete(&self, service: &str, account: &str) -> Result<(), String> {
+        super::keyring::delete(service, account)
+    }
+    #[cfg(all(feature = "keyring", not(unix)))]
+    fn keyring_get(&self, _: &str, _: &str) -> Result<Option<String>, String> {
+        Err(KEYRING_UNSUPPORTED.into())
+    }
+    #[cfg(all(feature = "keyring", not(unix)))]
+    fn keyring_set(&self, _: &str, _: &str, _: &str) -> Result<(), String> {
+        Err(KEYRING_UNSUPPORTED.into())
+    }
+    #[cfg(all(feature = "keyring", not(unix)))]
+    fn keyring_delete(&self, _: &str, _: &str) -> Result<(), String> {
+        Err(KEYRING_UNSUPPORTED.into())
+    }
+    fn mqtt_connect(&self, options: &MqttOptions) -> Result<Handle, String> {
+        let client = MqttClient::connect(options)?;
+        let handle = NATIVE_SYS.new_handle();
+        NATIVE_SYS.mqtt_clients.insert(handle, client);
+        Ok(handle)
+    }
+    fn mqtt_subscribe(&self, handle: Handle, topic: &str, qos: u8) -> Result<(), String> {
+        NATIVE_SYS.mqtt_client(handle)?.subscribe(topic, qos)
+    }
+    fn mqtt_publish(&self, handle: Handle, message: &MqttMessage) -> Result<(), String> {
+        NATIVE_SYS.mqtt_client(handle)?.publish(message)
+    }
+    fn mqtt_receive(
+        &self,
+        handle: Handle,
+        timeout: Option<Duration>,
+    ) -> Result<Option<MqttMessage>, String> {
+        NATIVE_SYS.mqtt_client(handle)?.receive(timeout)
+    }
     fn close(&self, handle: Handle) -> Result<(), String> {
         if NATIVE_SYS.child_stdins.remove(&handle).is_some()
             | NATIVE_SYS.child_stdouts.remove(&handle).is_some()
@@ -950,6 +1426,10 @@ impl SysBackend for NativeSys {
         {
             NATIVE_SYS.hostnames.remove(&handle);
             Ok(())
+        } else if NATIVE_SYS.udp_sockets.remove(&handle).is_some() {
+            Ok(())
+        } else if let Some((_, client)) = NATIVE_SYS.mqtt_clients.remove(&handle) {
+            client.disconnect()
         } else {
             Err("Invalid stream handle".to_string())
         }
@@ -1114,6 +1594,15 @@ impl SysBackend for NativeSys {
             .do_ffi(file, return_ty, name, arg_tys, arg_values)
     }
     #[cfg(feature = "ffi")]
+    fn ffi_callback(
+        &self,
+        result_ty: crate::FfiType,
+        arg_tys: &[crate::FfiType],
+        f: crate::FfiCallbackFn,
+    ) -> Result<crate::Value, String> {
+        NATIVE_SYS.ffi.make_callback(result_ty, arg_tys, f)
+    }
+    #[cfg(feature = "ffi")]
     fn mem_copy(
         &self,
         ty: crate::FfiType,
@@ -1127,6 +1616,32 @@ impl SysBackend for NativeSys {
         crate::ffi_free(ptr);
         Ok(())
     }
+    #[cfg(feature = "ffi")]
+    fn plugin_call(&self, name: &str, arg: &str) -> Result<String, String> {
+        NATIVE_SYS.plugins.call(name, arg)
+    }
+    #[cfg(feature = "python")]
+    fn python_call(&self, function: &str, args: &[crate::Value]) -> Result<crate::Value, String> {
+        super::python::PythonBridge::call(&mut NATIVE_SYS.python.lock(), function, args)
+    }
+    #[cfg(all(feature = "embedded", target_os = "linux"))]
+    fn gpio_read(&self, chip: usize, pin: u32) -> Result<bool, String> {
+        NATIVE_SYS.gpio.read(chip, pin)
+    }
+    #[cfg(all(feature = "embedded", target_os = "linux"))]
+    fn gpio_write(&self, chip: usize, pin: u32, value: bool) -> Result<(), String> {
+        NATIVE_SYS.gpio.write(chip, pin, value)
+    }
+    #[cfg(all(feature = "embedded", target_os = "linux"))]
+    fn i2c_transfer(
+        &self,
+        bus: usize,
+        addr: u16,
+        write: &[u8],
+        read_len: usize,
+    ) -> Result<Vec<u8>, String> {
+        super::embedded::i2c_transfer(bus, addr, write, read_len)
+    }
     fn load_git_module(&self, url: &str, target: GitTarget) -> Result<PathBuf, String> {
         if let Some(path) = NATIVE_SYS.git_paths.get(url) {
             if path.is_err() || path.as_ref().unwrap().exists() {
@@ -1140,9 +1655,9 @@ impl SysBackend for NativeSys {
             return Err("Invalid git url".to_string());
         }
 
-        let mut changed_dir = false;
-        // Add submodule
         let res = (|| {
+            let mut lockfile = Lockfile::load().map_err(|e| e.to_string())?;
+            let locked = (lockfile.get(url)).filter(|module| module.target() == target);
             let parent_path = Path::new("uiua-modules").join(repo_owner);
             let submodule_path = parent_path.join(repo_name);
             let lib_path = submodule_path.join("lib.ua");
@@ -1163,61 +1678,43 @@ impl SysBackend for NativeSys {
                     fs::create_dir_all(&parent_path).map_err(|e| e.to_string())?;
                 }
                 // Clone the repo
-                let mut child = Command::new("git")
-                    .args(["clone", url, &submodule_path.to_string_lossy()])
-                    .stderr(Stdio::piped())
-                    .stdout(Stdio::null())
-                    .spawn()
-                    .map_err(|e| e.to_string())?;
-                let status = child.wait().map_err(|e| e.to_string())?;
-                if !status.success() {
-                    let stderr = child.stderr.as_mut().unwrap();
-                    let mut err = String::new();
-                    stderr.read_to_string(&mut err).map_err(|e| e.to_string())?;
-                    return Err(format!("Failed to clone git repository `{url}`: {err}"));
-                }
+                git(
+                    Path::new("."),
+                    &["clone", url, &submodule_path.to_string_lossy()],
+                )
+                .map_err(|e| format!("Failed to clone git repository `{url}`: {e}"))?;
+            }
 
-                set_current_dir(&*submodule_path).map_err(|e| e.to_string())?;
-                changed_dir = true;
-                match &target {
-                    GitTarget::Default => {}
-                    GitTarget::Branch(branch) => {
-                        let mut child = Command::new("git")
-                            .args(["checkout", branch])
-                            .stderr(Stdio::piped())
-                            .stdout(Stdio::null())
-                            .spawn()
-                            .map_err(|e| e.to_string())?;
-                        let status = child.wait().map_err(|e| e.to_string())?;
-                        if !status.success() {
-                            let stderr = child.stderr.as_mut().unwrap();
-                            let mut err = String::new();
-                            stderr.read_to_string(&mut err).map_err(|e| e.to_string())?;
-                            return Err(format!("Failed to checkout branch `{branch}`: {err}"));
-                        }
-                    }
-                    GitTarget::Commit(hash) => {
-                        let mut child = Command::new("git")
-                            .args(["checkout", hash])
-                            .stderr(Stdio::piped())
-                            .stdout(Stdio::null())
-                            .spawn()
-                            .map_err(|e| e.to_string())?;
-                        let status = child.wait().map_err(|e| e.to_string())?;
-                        if !status.success() {
-                            let stderr = child.stderr.as_mut().unwrap();
-                            let mut err = String::new();
-                            stderr.read_to_string(&mut err).map_err(|e| e.to_string())?;
-                            return Err(format!("Failed to checkout commit `{hash}`: {err}"));
-                        }
-                    }
+            if let Some(locked) = locked {
+                // Checkout the locked commit
+                let commit = &locked.commit;
+                if git(&submodule_path, &["rev-parse", "HEAD"])? != *commit {
+                    git(&submodule_path, &["checkout", commit])
+                        .or_else(|_| {
+                            git(&submodule_path, &["fetch", "origin"])?;
+                            git(&submodule_path, &["checkout", commit])
+                        })
+                        .map_err(|e| format!("Failed to checkout locked commit `{commit}`: {e}"))?;
+                }
+            } else {
+                // Checkout the target and lock it
+                let checkout = match &target {
+                    GitTarget::Default => None,
+                    GitTarget::Branch(branch) => Some(("branch", branch)),
+                    GitTarget::Tag(tag) => Some(("tag", tag)),
+                    GitTarget::Commit(hash) => Some(("commit", hash)),
+                };
+                if let Some((kind, name)) = checkout {
+                    git(&submodule_path, &["checkout", name])
+                        .map_err(|e| format!("Failed to checkout {kind} `{name}`: {e}"))?;
+                }
+                let commit = git(&submodule_path, &["rev-parse", "HEAD"])?;
+                if lockfile.insert(LockedModule::new(url, &target, commit)) {
+                    lockfile.save().map_err(|e| e.to_string())?;
                 }
             }
             Ok(lib_path)
         })();
-        if changed_dir {
-            _ = set_current_dir("../../..");
-        }
         NATIVE_SYS.git_paths.insert(url.to_string(), res.clone());
         res
     }
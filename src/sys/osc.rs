@@ -0,0 +1,259 @@
+//! Encoding and decoding of Open Sound Control packets
+//!
+//! A message is a list of boxes. The first item is the address pattern, and the rest are the arguments.
+//! A bundle is a list of boxes that starts with `"#bundle"` and a time, followed by its elements.
+
+use ecow::EcoVec;
+
+use crate::{Array, Boxed, Uiua, UiuaResult, Value};
+
+const BUNDLE: &str = "#bundle";
+/// The number of seconds between the NTP epoch, which OSC time tags use, and the Unix epoch
+const NTP_UNIX_OFFSET: f64 = 2_208_988_800.0;
+/// The time tag that means "immediately"
+const IMMEDIATELY: u64 = 1;
+
+/// Encode a message or bundle as an OSC packet
+pub(crate) fn encode_packet(packet: &Value, env: &Uiua) -> UiuaResult<Vec<u8>> {
+    let items: Vec<Value> = match packet {
+        Value::Box(arr) if arr.rank() == 1 => arr.data.iter().map(|b| b.0.clone()).collect(),
+        Value::Char(arr) if arr.rank() == 1 => vec![packet.clone()],
+        _ => {
+            return Err(env.error("OSC message must be a list of boxes that starts with an address"))
+        }
+    };
+    let Some(address) = items.first() else {
+        return Err(env.error("OSC message cannot be empty"));
+    };
+    let address = address.as_string(env, "OSC address must be a string")?;
+    let mut buf = Vec::new();
+    if address == BUNDLE {
+        let time = (items.get(1))
+            .ok_or_else(|| env.error("OSC bundle must have a time"))?
+            .as_num(env, "OSC bundle time must be a number")?;
+        write_str(&mut buf, BUNDLE);
+        buf.extend(time_tag(time).to_be_bytes());
+        for element in &items[2..] {
+            let element = encode_packet(element, env)?;
+            buf.extend((element.len() as u32).to_be_bytes());
+            buf.extend(element);
+        }
+        return Ok(buf);
+    }
+    if !address.starts_with('/') {
+        return Err(env.error(format!(
+            "OSC address must start with /, but it is {address:?}"
+        )));
+    }
+    let mut tags = String::from(",");
+    let mut args = Vec::new();
+    for arg in &items[1..] {
+        encode_arg(arg, &mut tags, &mut args, env)?;
+    }
+    write_str(&mut buf, &address);
+    write_str(&mut buf, &tags);
+    buf.extend(args);
+    Ok(buf)
+}
+
+fn encode_arg(arg: &Value, tags: &mut String, buf: &mut Vec<u8>, env: &Uiua) -> UiuaResult {
+    match arg {
+        Value::Box(arr) if arr.rank() == 0 => return encode_arg(&arr.data[0].0, tags, buf, env),
+        Value::Num(arr) if arr.rank() == 0 => encode_num(arr.data[0], tags, buf),
+        Value::Byte(arr) if arr.rank() == 0 => encode_num(arr.data[0] as f64, tags, buf),
+        Value::Char(arr) if arr.rank() <= 1 => {
+            tags.push('s');
+            write_str(buf, &arr.data.iter().collect::<String>());
+        }
+        Value::Byte(arr) if arr.rank() == 1 => {
+            tags.push('b');
+            buf.extend((arr.data.len() as u32).to_be_bytes());
+            buf.extend(arr.data.iter().copied());
+            pad(buf);
+        }
+        Value::Num(_) | Value::Box(_) if arg.rank() == 1 => {
+            tags.push('[');
+            for row in arg.rows() {
+                encode_arg(&row, tags, buf, env)?;
+            }
+            tags.push(']');
+        }
+        Value::Complex(_) => return Err(env.error("Complex numbers cannot be OSC arguments")),
+        _ => {
+            return Err(env.error(format!(
+                "OSC arguments must be scalars, strings, or lists, \
+                but one is a rank {} array",
+                arg.rank()
+            )))
+        }
+    }
+    Ok(())
+}
+
+fn encode_num(n: f64, tags: &mut String, buf: &mut Vec<u8>) {
+    if n.fract() == 0.0 && (i32::MIN as f64..=i32::MAX as f64).contains(&n) {
+        tags.push('i');
+        buf.extend((n as i32).to_be_bytes());
+    } else {
+        tags.push('f');
+        buf.extend((n as f32).to_be_bytes());
+    }
+}
+
+/// Write a null-terminated, padded string
+fn write_str(buf: &mut Vec<u8>, s: &str) {
+    buf.extend(s.as_bytes());
+    buf.push(0);
+    pad(buf);
+}
+
+/// Pad a buffer to a multiple of 4 bytes
+fn pad(buf: &mut Vec<u8>) {
+    while buf.len() % 4 != 0 {
+        buf.push(0);
+    }
+}
+
+fn time_tag(seconds: f64) -> u64 {
+    if seconds <= 0.0 {
+        return IMMEDIATELY;
+    }
+    let ntp = seconds + NTP_UNIX_OFFSET;
+    ((ntp.trunc() as u64) << 32) | (ntp.fract() * (1u64 << 32) as f64) as u64
+}
+
+/// Decode an OSC packet into a message or bundle
+pub(crate) fn decode_packet(bytes: &[u8]) -> Result<Value, String> {
+    let mut reader = Reader { bytes, pos: 0 };
+    let address = reader.str()?;
+    let mut items = vec![Boxed(address.as_str().into())];
+    if address == BUNDLE {
+        items.push(Boxed(reader.time_tag()?.into()));
+        while !reader.is_done() {
+            let size = reader.u32()? as usize;
+            items.push(Boxed(decode_packet(reader.take(size)?)?));
+        }
+    } else {
+        // Very old implementations may leave out the type tags
+        let tags = if reader.is_done() {
+            ",".into()
+        } else {
+            reader.str()?
+        };
+        let tags =
+            (tags.strip_prefix(',')).ok_or_else(|| format!("Invalid OSC type tags {tags:?}"))?;
+        let mut lists: Vec<Vec<Boxed>> = vec![Vec::new()];
+        for tag in tags.chars() {
+            let arg: Value = match tag {
+                'i' => (reader.u32()? as i32).into(),
+                'h' => (reader.u64()? as i64).into(),
+                'f' => (f32::from_bits(reader.u32()?) as f64).into(),
+                'd' => f64::from_bits(reader.u64()?).into(),
+                't' => reader.time_tag()?.into(),
+                's' | 'S' => reader.str()?.into(),
+                'c' => char::from_u32(reader.u32()?)
+                    .ok_or("Invalid OSC character")?
+                    .into(),
+                'b' => {
+                    let size = reader.u32()? as usize;
+                    let blob = reader.take(size)?.to_vec();
+                    reader.align();
+                    blob.into()
+                }
+                'm' | 'r' => reader.take(4)?.to_vec().into(),
+                'T' => true.into(),
+                'F' => false.into(),
+                'N' => Value::default(),
+                'I' => f64::INFINITY.into(),
+                '[' => {
+                    lists.push(Vec::new());
+                    continue;
+                }
+                ']' => {
+                    if lists.len() == 1 {
+                        return Err("Unopened OSC array".into());
+                    }
+                    let list = lists.pop().unwrap();
+                    Array::from(list.into_iter().collect::<EcoVec<_>>()).into()
+                }
+                tag => return Err(format!("Unknown OSC type tag {tag:?}")),
+            };
+            lists.last_mut().unwrap().push(Boxed(arg));
+        }
+        if lists.len() != 1 {
+            return Err("Unclosed OSC array".into());
+        }
+        items.extend(lists.pop().unwrap());
+    }
+    Ok(Array::from(items.into_iter().collect::<EcoVec<_>>()).into())
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn is_done(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let end = (self.pos.checked_add(n))
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or("OSC packet ended unexpectedly")?;
+        let taken = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(taken)
+    }
+    fn align(&mut self) {
+        self.pos = self.pos.div_ceil(4) * 4;
+    }
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+    fn str(&mut self) -> Result<String, String> {
+        let rest = &self.bytes[self.pos.min(self.bytes.len())..];
+        let len = (rest.iter().position(|&b| b == 0)).ok_or("Unterminated OSC string")?;
+        let s = std::str::from_utf8(&rest[..len])
+            .map_err(|_| "OSC string is not valid UTF-8")?
+            .to_string();
+        self.pos += len + 1;
+        self.align();
+        Ok(s)
+    }
+    /// Read a time tag as seconds since the Unix epoch
+    fn time_tag(&mut self) -> Result<f64, String> {
+        let tag = self.u64()?;
+        Ok(if tag == IMMEDIATELY {
+            0.0
+        } else {
+            (tag >> 32) as f64 + (tag as u32) as f64 / (1u64 << 32) as f64 - NTP_UNIX_OFFSET
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    #[cfg(feature = "native_sys")]
+    fn osc_messages() {
+        use crate::*;
+        let mut env = Uiua::with_native_sys();
+        let code = r#"
+            # Experimental!
+            S ← &oscl "127.0.0.1:57198"
+            &oscs {"/a" 1 0.5 "hi" [2 3]} "127.0.0.1:57198"
+            &oscr 1 S
+            ⍣(&oscr 0 S|"timeout")
+            &cl S
+        "#;
+        env.run_str(code).unwrap();
+        let timeout = env.pop("timeout").unwrap();
+        assert_eq!(timeout.as_string(&env, "").unwrap(), "timeout");
+        let message = env.pop("message").unwrap();
+        assert_eq!(message.show(), r#"{"/a" 1 0.5 "hi" {2 3}}"#);
+    }
+}
@@ -0,0 +1,505 @@
+//! Package manifests and a client for package registries
+//!
+//! A registry is any HTTP server that implements these endpoints:
+//! - `GET <registry>/<name>/versions` lists the published versions of a package, one per line
+//! - `GET <registry>/<name>/<version>` downloads a package bundle
+//! - `PUT <registry>/<name>/<version>` publishes a package bundle
+//!
+//! Publishing sends the `UIUA_REGISTRY_TOKEN` environment variable as a bearer token if it is set.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    env, fmt, fs,
+    io::{self, Write},
+    path::{Component, Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use serde::*;
+
+use super::git::{LockedPackage, Lockfile};
+
+/// The name of the package manifest file
+pub const MANIFEST_NAME: &str = "uiua.toml";
+/// The directory that fetched packages are stored in
+pub const PACKAGES_DIR: &str = "uiua-packages";
+
+/// A package manifest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    /// Information about the package
+    pub package: PackageInfo,
+    /// The package's dependencies and their version requirements
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub dependencies: BTreeMap<String, String>,
+}
+
+/// Information about a package
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PackageInfo {
+    /// The name of the package
+    pub name: String,
+    /// The version of the package
+    pub version: String,
+    /// A description of the package
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The URL of the registry to fetch dependencies from and publish to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registry: Option<String>,
+}
+
+/// The files of a published package
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PackageBundle {
+    /// The contents of each file by its path relative to the package root
+    pub files: BTreeMap<String, String>,
+}
+
+/// A package version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version {
+    /// The major version
+    pub major: u64,
+    /// The minor version
+    pub minor: u64,
+    /// The patch version
+    pub patch: u64,
+}
+
+/// A package that was resolved by [`fetch_packages`]
+#[derive(Debug, Clone)]
+pub struct FetchedPackage {
+    /// The name of the package
+    pub name: String,
+    /// The version that was resolved
+    pub version: Version,
+    /// Whether the package had to be downloaded
+    pub downloaded: bool,
+}
+
+impl Manifest {
+    /// The path of the manifest in the current directory
+    pub fn path() -> PathBuf {
+        PathBuf::from(MANIFEST_NAME)
+    }
+    /// Load the manifest in the current directory
+    pub fn load() -> io::Result<Self> {
+        let text = fs::read_to_string(Self::path()).map_err(|e| {
+            if e.kind() == io::ErrorKind::NotFound {
+                io::Error::new(
+                    e.kind(),
+                    format!("No {MANIFEST_NAME} found. Create a package with `uiua new`"),
+                )
+            } else {
+                e
+            }
+        })?;
+        Self::parse(&text)
+    }
+    fn parse(text: &str) -> io::Result<Self> {
+        let manifest: Self = toml::from_str(text).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid {MANIFEST_NAME}: {}", e.message()),
+            )
+        })?;
+        manifest.validate().map_err(invalid_data)?;
+        Ok(manifest)
+    }
+    fn validate(&self) -> Result<(), String> {
+        validate_package_name(&self.package.name)?;
+        self.package.version.parse::<Version>()?;
+        for (name, req) in &self.dependencies {
+            validate_package_name(name)?;
+            validate_requirement(req).map_err(|e| format!("Dependency `{name}`: {e}"))?;
+        }
+        Ok(())
+    }
+    /// Get the registry URL to use
+    ///
+    /// The `UIUA_REGISTRY` environment variable takes precedence over the manifest
+    pub fn registry(&self) -> Result<String, String> {
+        env::var("UIUA_REGISTRY")
+            .ok()
+            .or_else(|| self.package.registry.clone())
+            .map(|url| url.trim_end_matches('/').to_string())
+            .ok_or_else(|| {
+                format!(
+                    "No registry specified. Set `registry` in {MANIFEST_NAME} \
+                    or the UIUA_REGISTRY environment variable."
+                )
+            })
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl std::str::FromStr for Version {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<u64> = (s.split('.').map(str::parse))
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("Invalid version `{s}`"))?;
+        let [major, minor, patch] = parts[..] else {
+            return Err(format!(
+                "Invalid version `{s}`. Versions must have the form `major.minor.patch`."
+            ));
+        };
+        Ok(Version {
+            major,
+            minor,
+            patch,
+        })
+    }
+}
+
+impl Version {
+    /// Check if the version satisfies a requirement
+    ///
+    /// A requirement is either `*`, which matches any version,
+    /// or a version prefix like `1`, `1.2`, or `1.2.3`.
+    pub fn matches(&self, req: &str) -> bool {
+        let req = req.trim();
+        if req == "*" {
+            return true;
+        }
+        let parts = [self.major, self.minor, self.patch];
+        let mut req_parts = req.split('.');
+        parts
+            .iter()
+            .zip(&mut req_parts)
+            .all(|(part, req)| req.parse::<u64>().is_ok_and(|req| req == *part))
+            && req_parts.next().is_none()
+    }
+}
+
+fn validate_package_name(name: &str) -> Result<(), String> {
+    if name.is_empty()
+        || !(name.chars()).all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "-_".contains(c))
+    {
+        return Err(format!(
+            "Invalid package name `{name}`. Package names may only contain \
+            lowercase letters, digits, `-`, and `_`."
+        ));
+    }
+    Ok(())
+}
+
+fn validate_requirement(req: &str) -> Result<(), String> {
+    let req = req.trim();
+    if req == "*" {
+        return Ok(());
+    }
+    let parts = req.split('.').collect::<Vec<_>>();
+    if parts.len() > 3 || parts.iter().any(|part| part.parse::<u64>().is_err()) {
+        return Err(format!(
+            "Invalid version requirement `{req}`. \
+            Requirements must be `*` or a version prefix like `1.2`."
+        ));
+    }
+    Ok(())
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Create a new package in a new directory
+pub fn new_package(name: &str) -> io::Result<PathBuf> {
+    validate_package_name(name).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let dir = PathBuf::from(name);
+    if dir.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", dir.display()),
+        ));
+    }
+    fs::create_dir_all(&dir)?;
+    let manifest = Manifest {
+        package: PackageInfo {
+            name: name.into(),
+            version: "0.1.0".into(),
+            description: None,
+            registry: None,
+        },
+        dependencies: BTreeMap::new(),
+    };
+    let manifest = toml::to_string(&manifest).map_err(io::Error::other)?;
+    fs::write(
+        dir.join(MANIFEST_NAME),
+        format!("{manifest}\n[dependencies]\n"),
+    )?;
+    fs::write(dir.join("lib.ua"), "# Add one to a number\nIncr ← +1\n")?;
+    fs::write(dir.join("main.ua"), "~ \"lib.ua\" ~ Incr\nIncr 5\n")?;
+    fs::write(
+        dir.join(".gitignore"),
+        format!("/{PACKAGES_DIR}\n/uiua-modules\n"),
+    )?;
+    Ok(dir)
+}
+
+/// Fetch the dependencies of the package in the current directory
+///
+/// Locked versions are reused as long as they still satisfy the requirements,
+/// unless `update` is set. The lockfile is updated with the resolved versions.
+pub fn fetch_packages(update: bool) -> Result<Vec<FetchedPackage>, String> {
+    let manifest = Manifest::load().map_err(|e| e.to_string())?;
+    let mut lockfile = Lockfile::load().map_err(|e| e.to_string())?;
+    let mut registry_url: Option<String> = None;
+    let mut registry = || -> Result<String, String> {
+        if let Some(url) = &registry_url {
+            return Ok(url.clone());
+        }
+        Ok(registry_url.insert(manifest.registry()?).clone())
+    };
+    // Requirements that were found to conflict with earlier choices
+    let mut constraints: BTreeMap<String, Vec<(String, String)>> = BTreeMap::new();
+    let mut downloaded = BTreeSet::new();
+    let resolved = 'resolve: loop {
+        let mut resolved: BTreeMap<String, Version> = BTreeMap::new();
+        let mut queue: VecDeque<(String, String, String)> = (manifest.dependencies.iter())
+            .map(|(name, req)| (name.clone(), req.clone(), manifest.package.name.clone()))
+            .collect();
+        while let Some((name, req, dependent)) = queue.pop_front() {
+            // Check for conflicts with already resolved packages
+            if let Some(version) = resolved.get(&name) {
+                if version.matches(&req) {
+                    continue;
+                }
+                // Resolve again with the conflicting requirement
+                let reqs = constraints.entry(name.clone()).or_default();
+                if reqs.iter().any(|(r, d)| *r == req && *d == dependent) {
+                    return Err(format!(
+                        "Conflicting requirements for {name}: {dependent} requires `{req}`, \
+                        but {version} was chosen"
+                    ));
+                }
+                reqs.push((req, dependent));
+                continue 'resolve;
+            }
+            let mut reqs = vec![(req, dependent)];
+            reqs.extend(constraints.get(&name).into_iter().flatten().cloned());
+            let dir = Path::new(PACKAGES_DIR).join(&name);
+            let locked = (lockfile.get_package(&name))
+                .and_then(|package| package.version.parse::<Version>().ok())
+                .filter(|version| !update && reqs.iter().all(|(req, _)| version.matches(req)));
+            let version = if let Some(version) = locked {
+                version
+            } else {
+                resolve_version(&registry()?, &name, &reqs)?
+            };
+            // Download the package if the fetched version is different
+            if installed_version(&dir) != Some(version) {
+                download_package(&registry()?, &name, version, &dir)?;
+                downloaded.insert((name.clone(), version));
+            }
+            // Queue the package's own dependencies
+            let package_manifest = Manifest::parse(
+                &fs::read_to_string(dir.join(MANIFEST_NAME))
+                    .map_err(|e| format!("Failed to read the manifest of {name}: {e}"))?,
+            )
+            .map_err(|e| format!("{name}: {e}"))?;
+            for (dep, dep_req) in package_manifest.dependencies {
+                queue.push_back((dep, dep_req, name.clone()));
+            }
+            resolved.insert(name, version);
+        }
+        break resolved;
+    };
+    let fetched = (resolved.iter())
+        .map(|(name, &version)| FetchedPackage {
+            name: name.clone(),
+            version,
+            downloaded: downloaded.contains(&(name.clone(), version)),
+        })
+        .collect();
+    // Record the resolved versions
+    let packages: Vec<LockedPackage> = (resolved.iter())
+        .map(|(name, version)| LockedPackage {
+            name: name.clone(),
+            version: version.to_string(),
+        })
+        .collect();
+    if packages != lockfile.packages {
+        lockfile.packages = packages;
+        lockfile.save().map_err(|e| e.to_string())?;
+    }
+    Ok(fetched)
+}
+
+/// Publish the package in the current directory
+///
+/// Returns the name and version of the published package
+pub fn publish_package() -> Result<(String, Version), String> {
+    let manifest = Manifest::load().map_err(|e| e.to_string())?;
+    let registry = manifest.registry()?;
+    let name = manifest.package.name.clone();
+    let version: Version = manifest.package.version.parse()?;
+    if !Path::new("lib.ua").exists() {
+        return Err("A package must have a lib.ua file".into());
+    }
+    let mut bundle = PackageBundle::default();
+    collect_package_files(Path::new("."), &mut bundle).map_err(|e| e.to_string())?;
+    let body = toml::to_string(&bundle).map_err(|e| e.to_string())?;
+    // The body and token are passed as a config through stdin
+    // so that the token does not show up in process lists
+    let mut config = format!("data-raw = {}\n", curl_config_quote(&body));
+    if let Ok(token) = env::var("UIUA_REGISTRY_TOKEN") {
+        let header = format!("Authorization: Bearer {token}");
+        config.push_str(&format!("header = {}\n", curl_config_quote(&header)));
+    }
+    let args = [
+        "-X".to_string(),
+        "PUT".into(),
+        "-H".into(),
+        "Content-Type: application/toml".into(),
+        "--config".into(),
+        "-".into(),
+        format!("{registry}/{name}/{version}"),
+    ];
+    curl(&args, Some(&config)).map_err(|e| format!("Failed to publish {name} {version}: {e}"))?;
+    Ok((name, version))
+}
+
+/// Quote a value for a curl config file
+fn curl_config_quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            '\x0b' => quoted.push_str("\\v"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn collect_package_files(dir: &Path, bundle: &mut PackageBundle) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let file_name = entry.file_name();
+        let file_name = file_name.to_string_lossy();
+        if file_name.starts_with('.') || [PACKAGES_DIR, "uiua-modules"].contains(&&*file_name) {
+            continue;
+        }
+        if path.is_dir() {
+            collect_package_files(&path, bundle)?;
+        } else if path.extension().is_some_and(|ext| ext == "ua")
+            || file_name == MANIFEST_NAME
+            || dir == Path::new(".") && file_name.to_lowercase().starts_with("readme")
+        {
+            let key = (path.strip_prefix(".").unwrap_or(&path).components())
+                .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+                .join("/");
+            bundle.files.insert(key, fs::read_to_string(&path)?);
+        }
+    }
+    Ok(())
+}
+
+fn installed_version(dir: &Path) -> Option<Version> {
+    let text = fs::read_to_string(dir.join(MANIFEST_NAME)).ok()?;
+    let manifest = Manifest::parse(&text).ok()?;
+    manifest.package.version.parse().ok()
+}
+
+fn resolve_version(
+    registry: &str,
+    name: &str,
+    reqs: &[(String, String)],
+) -> Result<Version, String> {
+    let versions = curl(&[format!("{registry}/{name}/versions")], None)
+        .map_err(|e| format!("Failed to get the versions of {name}: {e}"))?;
+    (versions.lines())
+        .filter_map(|line| line.trim().parse::<Version>().ok())
+        .filter(|version| reqs.iter().all(|(req, _)| version.matches(req)))
+        .max()
+        .ok_or_else(|| {
+            let reqs: Vec<_> = (reqs.iter())
+                .map(|(req, dependent)| format!("`{req}` from {dependent}"))
+                .collect();
+            format!("No version of {name} matches {}", reqs.join(" and "))
+        })
+}
+
+fn download_package(
+    registry: &str,
+    name: &str,
+    version: Version,
+    dir: &Path,
+) -> Result<(), String> {
+    let text = curl(&[format!("{registry}/{name}/{version}")], None)
+        .map_err(|e| format!("Failed to download {name} {version}: {e}"))?;
+    let bundle: PackageBundle = toml::from_str(&text).map_err(|e| {
+        format!(
+            "Invalid package bundle for {name} {version}: {}",
+            e.message()
+        )
+    })?;
+    if !bundle.files.contains_key(MANIFEST_NAME) {
+        return Err(format!("{name} {version} does not have a {MANIFEST_NAME}"));
+    }
+    for path in bundle.files.keys() {
+        if !(Path::new(path).components()).all(|c| matches!(c, Component::Normal(_))) {
+            return Err(format!(
+                "{name} {version} contains an invalid path `{path}`"
+            ));
+        }
+    }
+    if dir.exists() {
+        fs::remove_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    for (path, contents) in bundle.files {
+        let path = dir.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        fs::write(path, contents).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Run curl and get its output
+fn curl(args: &[String], input: Option<&str>) -> Result<String, String> {
+    let mut child = Command::new("curl")
+        .args(["-fsSL"])
+        .args(args)
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run curl: {e}"))?;
+    if let Some(input) = input {
+        let mut stdin = child.stdin.take().unwrap();
+        stdin
+            .write_all(input.as_bytes())
+            .map_err(|e| e.to_string())?;
+    }
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().into())
+    }
+}
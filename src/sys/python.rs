@@ -0,0 +1,284 @@
+//! A bridge for calling Python functions
+//!
+//! Python runs in a subprocess that speaks a line-based JSON protocol.
+//! Arrays are sent with their shapes so that they can become NumPy arrays.
+
+use std::{
+    env,
+    io::{BufRead, BufReader, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+};
+
+use ecow::EcoVec;
+use serde_json::{json, Map, Value as Json};
+
+use crate::{algorithm::IgnoreError, val_as_arr, Array, Boxed, Complex, Value};
+
+const BRIDGE: &str = include_str!("python_bridge.py");
+
+/// A running Python subprocess
+pub(crate) struct PythonBridge {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Drop for PythonBridge {
+    fn drop(&mut self) {
+        _ = self.child.kill();
+    }
+}
+
+impl PythonBridge {
+    fn spawn() -> Result<Self, String> {
+        let python = env::var("UIUA_PYTHON")
+            .unwrap_or_else(|_| if cfg!(windows) { "python" } else { "python3" }.into());
+        let mut child = Command::new(&python)
+            .args(["-c", BRIDGE])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to start Python with `{python}`: {e}"))?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        Ok(PythonBridge {
+            child,
+            stdin,
+            stdout,
+        })
+    }
+    /// Call a Python function, starting Python if it is not running
+    pub fn call(
+        bridge: &mut Option<Self>,
+        function: &str,
+        args: &[Value],
+    ) -> Result<Value, String> {
+        let running = match bridge {
+            Some(running) => running,
+            None => bridge.insert(Self::spawn()?),
+        };
+        let request = json!({
+            "function": function,
+            "args": args.iter().map(encode).collect::<Vec<_>>(),
+        });
+        let mut line = String::new();
+        let res = writeln!(running.stdin, "{request}")
+            .and_then(|_| running.stdin.flush())
+            .and_then(|_| running.stdout.read_line(&mut line));
+        if !matches!(res, Ok(n) if n > 0) {
+            // Start a new process on the next call
+            *bridge = None;
+            return Err(match res {
+                Err(e) => format!("Failed to communicate with Python: {e}"),
+                Ok(_) => "Python exited unexpectedly".into(),
+            });
+        }
+        let response: Json = serde_json::from_str(&line)
+            .map_err(|e| format!("Invalid response from Python: {e}"))?;
+        match response {
+            Json::Object(mut obj) => {
+                if let Some(Json::String(error)) = obj.remove("error") {
+                    Err(error)
+                } else {
+                    decode(obj.remove("ok").unwrap_or_default())
+                }
+            }
+            _ => Err("Invalid response from Python".into()),
+        }
+    }
+}
+
+fn encode_num(n: f64) -> Json {
+    if n.fract() == 0.0 && n.abs() < (1u64 << 53) as f64 {
+        (n as i64).into()
+    } else if let Some(n) = serde_json::Number::from_f64(n) {
+        n.into()
+    } else {
+        json!({ "float": n.to_string() })
+    }
+}
+
+fn encode_complex(c: &Complex) -> Json {
+    json!([encode_num(c.re), encode_num(c.im)])
+}
+
+fn encode_array<T>(ty: &str, arr: &Array<T>, f: impl Fn(&T) -> Json) -> Json
+where
+    T: crate::ArrayValue,
+{
+    json!({
+        "type": ty,
+        "shape": &*arr.shape,
+        "array": arr.data.iter().map(f).collect::<Vec<_>>(),
+    })
+}
+
+fn encode(val: &Value) -> Json {
+    if val.is_map() {
+        let pairs = (val.map_kv().into_iter())
+            .map(|(k, v)| json!([encode(&k), encode(&v)]))
+            .collect::<Vec<_>>();
+        return json!({ "dict": pairs });
+    }
+    match val {
+        Value::Num(arr) if arr.rank() == 0 => encode_num(arr.data[0]),
+        Value::Byte(arr) if arr.rank() == 0 => arr.data[0].into(),
+        Value::Complex(arr) if arr.rank() == 0 => {
+            json!({ "complex": encode_complex(&arr.data[0]) })
+        }
+        Value::Char(arr) if arr.rank() <= 1 => Json::String(arr.data.iter().collect()),
+        Value::Box(arr) if arr.rank() == 0 => encode(&arr.data[0].0),
+        Value::Num(arr) => encode_array("num", arr, |&n| encode_num(n)),
+        Value::Byte(arr) => encode_array("byte", arr, |&b| b.into()),
+        Value::Complex(arr) => encode_array("complex", arr, encode_complex),
+        Value::Char(arr) => encode_array("char", arr, |c| c.to_string().into()),
+        Value::Box(arr) => encode_array("box", arr, |b| encode(&b.0)),
+    }
+}
+
+fn decode_num(json: &Json) -> Result<f64, String> {
+    match json {
+        Json::Number(n) => n.as_f64().ok_or_else(|| format!("Invalid number {n}")),
+        Json::Object(obj) => match obj.get("float").and_then(Json::as_str) {
+            Some("nan") => Ok(f64::NAN),
+            Some("inf") => Ok(f64::INFINITY),
+            Some("-inf") => Ok(f64::NEG_INFINITY),
+            _ => Err("Invalid number from Python".into()),
+        },
+        _ => Err("Invalid number from Python".into()),
+    }
+}
+
+fn decode_complex(json: &Json) -> Result<Complex, String> {
+    match json.as_array().map(Vec::as_slice) {
+        Some([re, im]) => Ok(Complex::new(decode_num(re)?, decode_num(im)?)),
+        _ => Err("Invalid complex number from Python".into()),
+    }
+}
+
+/// Combine values into rows of an array, boxing them if they do not fit together
+fn from_rows(rows: Vec<Value>) -> Value {
+    if rows.is_empty() {
+        return Value::default();
+    }
+    // Strings stay boxed, like they do when decoding JSON
+    let fit = !rows
+        .iter()
+        .any(|row| row.is_map() || matches!(row, Value::Char(arr) if arr.rank() > 0))
+        && (rows.windows(2))
+            .all(|w| w[0].shape() == w[1].shape() && w[0].type_id() == w[1].type_id());
+    if fit {
+        Value::from_row_values_infallible(rows)
+    } else {
+        Array::from(
+            rows.into_iter()
+                .map(Value::boxed_if_not)
+                .collect::<EcoVec<_>>(),
+        )
+        .into()
+    }
+}
+
+fn decode(json: Json) -> Result<Value, String> {
+    let mut obj = match json {
+        Json::Number(_) => return decode_num(&json).map(Into::into),
+        Json::String(s) => return Ok(s.into()),
+        Json::Object(obj) => obj,
+        json => return Err(format!("Invalid value from Python: {json}")),
+    };
+    if obj.contains_key("float") {
+        return decode_num(&Json::Object(obj)).map(Into::into);
+    }
+    if let Some(c) = obj.remove("complex") {
+        return decode_complex(&c).map(Into::into);
+    }
+    if let Some(Json::Array(items)) = obj.remove("list") {
+        let rows = items.into_iter().map(decode).collect::<Result<_, _>>()?;
+        return Ok(from_rows(rows));
+    }
+    if let Some(Json::Array(pairs)) = obj.remove("dict") {
+        let mut keys = Vec::with_capacity(pairs.len());
+        let mut values = Vec::with_capacity(pairs.len());
+        for pair in pairs {
+            let Json::Array(pair) = pair else {
+                return Err("Invalid dict from Python".into());
+            };
+            let [k, v] = <[Json; 2]>::try_from(pair).map_err(|_| "Invalid dict from Python")?;
+            keys.push(decode(k)?);
+            values.push(decode(v)?);
+        }
+        let mut values = from_rows(values);
+        let keys = from_rows(keys);
+        val_as_arr!(&mut values, |arr| arr.map(keys, &IgnoreError))
+            .map_err(|_| "Python dict keys cannot be the keys of a map")?;
+        return Ok(values);
+    }
+    decode_array(obj)
+}
+
+fn decode_array(mut obj: Map<String, Json>) -> Result<Value, String> {
+    let invalid = || "Invalid array from Python".to_string();
+    let (Some(Json::String(ty)), Some(Json::Array(shape)), Some(Json::Array(data))) =
+        (obj.remove("type"), obj.remove("shape"), obj.remove("array"))
+    else {
+        return Err(invalid());
+    };
+    let shape = (shape.iter())
+        .map(|d| d.as_u64().map(|d| d as usize).ok_or_else(invalid))
+        .collect::<Result<Vec<_>, _>>()?;
+    if shape.iter().product::<usize>() != data.len() {
+        return Err(invalid());
+    }
+    Ok(match ty.as_str() {
+        "num" => {
+            let data = data
+                .iter()
+                .map(decode_num)
+                .collect::<Result<EcoVec<_>, _>>()?;
+            Array::new(shape, data).into()
+        }
+        "byte" => {
+            let data = (data.iter())
+                .map(|b| b.as_u64().map(|b| b as u8).ok_or_else(invalid))
+                .collect::<Result<EcoVec<_>, _>>()?;
+            Array::new(shape, data).into()
+        }
+        "complex" => {
+            let data = data
+                .iter()
+                .map(decode_complex)
+                .collect::<Result<EcoVec<_>, _>>()?;
+            Array::new(shape, data).into()
+        }
+        "box" => {
+            let data = (data.into_iter())
+                .map(|v| decode(v).map(Boxed))
+                .collect::<Result<EcoVec<_>, _>>()?;
+            Array::new(shape, data).into()
+        }
+        _ => return Err(invalid()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    #[cfg(feature = "python")]
+    fn python_bridge() {
+        use crate::*;
+        let mut env = Uiua::with_native_sys();
+        env.run_str(
+            r#"
+# Experimental!
+⍤⟜≍: 6 &py "math.gcd" {12 18}
+⍤⟜≍: π &py "math.pi" {}
+⍤⟜≍: [1_2 3_4] &py "list" {[1_2 3_4]}
+⍤⟜≍: {"a" "b"} &py "sorted" {{"b" "a"}}
+⍤⟜≍: map {"a" "b"} [1 2] &py "dict" {map {"a" "b"} [1 2]}
+⍤⟜≍: ℂ2 1 &py "complex" {1 2}
+⍤⟜≍: 1 ⍣(0&py "math.sqrt" {¯1}|1◌)
+"#,
+        )
+        .unwrap_or_else(|e| panic!("{e}"));
+    }
+}
@@ -0,0 +1,147 @@
+# The Python side of the bridge used by Uiua's &py
+#
+# Uiua sends one JSON request per line on stdin and reads one JSON response
+# per line from stdout. Anything Python code prints goes to stderr instead so
+# that it does not interfere with the protocol.
+
+import builtins
+import importlib
+import json
+import math
+import sys
+
+try:
+    import numpy as np
+except ImportError:
+    np = None
+
+protocol = sys.stdout
+sys.stdout = sys.stderr
+
+
+def reshape(flat, shape):
+    if len(shape) <= 1:
+        return list(flat)
+    size = math.prod(shape[1:])
+    return [reshape(flat[i * size : (i + 1) * size], shape[1:]) for i in range(shape[0])]
+
+
+def decode_num(x):
+    if isinstance(x, dict):
+        return float(x["float"])
+    return x
+
+
+def hashable(x):
+    if np is not None and isinstance(x, np.ndarray):
+        x = x.tolist()
+    if isinstance(x, list):
+        return tuple(hashable(v) for v in x)
+    return x
+
+
+def decode(x):
+    if isinstance(x, (int, float, str)):
+        return x
+    if "float" in x:
+        return decode_num(x)
+    if "complex" in x:
+        return complex(*map(decode_num, x["complex"]))
+    if "dict" in x:
+        return {hashable(decode(k)): decode(v) for k, v in x["dict"]}
+    ty, shape, flat = x["type"], x["shape"], x["array"]
+    if ty == "char":
+        # Rows of characters become strings
+        width = shape[-1]
+        rows = ["".join(flat[i * width : (i + 1) * width]) for i in range(math.prod(shape[:-1]))]
+        return reshape(rows, shape[:-1])
+    if ty == "box":
+        return reshape([decode(v) for v in flat], shape)
+    if ty == "complex":
+        flat = [complex(*map(decode_num, c)) for c in flat]
+    else:
+        flat = [decode_num(v) for v in flat]
+    if np is not None:
+        dtype = {"num": np.float64, "byte": np.uint8, "complex": np.complex128}[ty]
+        return np.array(flat, dtype=dtype).reshape(shape)
+    return reshape(flat, shape)
+
+
+def encode_num(x):
+    if math.isfinite(x):
+        return x
+    return {"float": str(x)}
+
+
+def encode_array(x):
+    shape = list(x.shape)
+    kind = x.dtype.kind
+    if kind == "b" or (kind == "u" and x.dtype.itemsize == 1):
+        return {"type": "byte", "shape": shape, "array": x.astype(np.uint8).ravel().tolist()}
+    if kind in "iuf":
+        flat = [encode_num(v) for v in x.astype(np.float64).ravel().tolist()]
+        return {"type": "num", "shape": shape, "array": flat}
+    if kind == "c":
+        flat = [[encode_num(c.real), encode_num(c.imag)] for c in x.ravel().tolist()]
+        return {"type": "complex", "shape": shape, "array": flat}
+    return {"type": "box", "shape": shape, "array": [encode(v) for v in x.ravel().tolist()]}
+
+
+def encode(x):
+    if x is None:
+        return {"type": "num", "shape": [0], "array": []}
+    if isinstance(x, bool):
+        return int(x)
+    if isinstance(x, int):
+        return x
+    if isinstance(x, float):
+        return encode_num(x)
+    if isinstance(x, complex):
+        return {"complex": [encode_num(x.real), encode_num(x.imag)]}
+    if isinstance(x, str):
+        return x
+    if isinstance(x, (bytes, bytearray)):
+        return {"type": "byte", "shape": [len(x)], "array": list(x)}
+    if isinstance(x, dict):
+        return {"dict": [[encode(k), encode(v)] for k, v in x.items()]}
+    if isinstance(x, (list, tuple, range)):
+        return {"list": [encode(v) for v in x]}
+    if np is not None:
+        if isinstance(x, np.ndarray):
+            return encode_array(x)
+        if isinstance(x, np.generic):
+            return encode(x.item())
+    raise TypeError(f"a Python {type(x).__name__} cannot be converted to a Uiua value")
+
+
+def lookup(name):
+    parts = name.split(".")
+    obj = builtins
+    for i in range(len(parts) - 1, 0, -1):
+        try:
+            obj = importlib.import_module(".".join(parts[:i]))
+        except ImportError:
+            continue
+        parts = parts[i:]
+        break
+    for part in parts:
+        obj = getattr(obj, part)
+    return obj
+
+
+for line in sys.stdin:
+    try:
+        request = json.loads(line)
+        obj = lookup(request["function"])
+        args = [decode(arg) for arg in request["args"]]
+        if callable(obj):
+            result = obj(*args)
+        elif args:
+            raise TypeError(f"{request['function']} is not callable")
+        else:
+            result = obj
+        response = {"ok": encode(result)}
+    except Exception as e:
+        response = {"error": f"{type(e).__name__}: {e}"}
+    protocol.write(json.dumps(response) + "\n")
+    protocol.flush()
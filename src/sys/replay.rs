@@ -0,0 +1,561 @@
+//! System backends that record a program's system calls and replay them later
+
+use std::{
+    any::Any,
+    collections::VecDeque,
+    fs::{self, File},
+    io::Write,
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+#[cfg(feature = "image")]
+use image::DynamicImage;
+use parking_lot::Mutex;
+use serde::{de::DeserializeOwned, *};
+
+use super::{
+    AudioStreamFn, Email, GitTarget, Handle, IntoSysBackend, MqttMessage, MqttOptions,
+    ReadLinesReturnFn, SmtpAuth, SmtpServer, SysBackend, TermCommand, TermEvent, WebcamImage,
+    WindowEvent,
+};
+use crate::{primitive::seed_random, FfiCallbackFn, FfiType, Uiua, UiuaResult, Value};
+
+/// The first line of a recording
+#[derive(Serialize, Deserialize)]
+struct Header {
+    seed: u64,
+}
+
+/// A single recorded system call
+#[derive(Serialize, Deserialize)]
+struct Event {
+    call: String,
+    args: serde_json::Value,
+    ret: serde_json::Value,
+}
+
+fn to_json(value: impl Serialize) -> serde_json::Value {
+    serde_json::to_value(value).unwrap_or_default()
+}
+
+/// The types of a callback, as they are recorded
+fn callback_sig(result_ty: &FfiType, arg_tys: &[FfiType]) -> (String, Vec<String>) {
+    let arg_tys = arg_tys.iter().map(ToString::to_string).collect();
+    (result_ty.to_string(), arg_tys)
+}
+
+/// A return type that can be produced when a replay diverges from its recording
+trait Replayed: Serialize + DeserializeOwned {
+    fn diverged(message: String) -> Self;
+}
+
+impl<T: Serialize + DeserializeOwned> Replayed for Result<T, String> {
+    fn diverged(message: String) -> Self {
+        Err(message)
+    }
+}
+
+macro_rules! replayed_default {
+    ($($ty:ty),*) => {
+        $(impl Replayed for $ty {
+            fn diverged(_: String) -> Self {
+                Self::default()
+            }
+        })*
+    };
+}
+
+replayed_default!(bool, u32, f64, Option<String>);
+
+/// Implement system calls that are recorded and replayed verbatim
+macro_rules! logged {
+    ($($name:ident($($arg:ident: $ty:ty),*) -> $ret:ty;)*) => {
+        $(fn $name(&self, $($arg: $ty),*) -> $ret {
+            let args = to_json(($(&$arg,)*));
+            self.call(stringify!($name), args, |inner| inner.$name($($arg),*))
+        })*
+    };
+}
+
+macro_rules! logged_calls {
+    () => {
+        logged! {
+            scan_line_stdin() -> Result<Option<String>, String>;
+            prompt(prompt: &str, hidden: bool) -> Result<Option<String>, String>;
+            scan_stdin(count: Option<usize>) -> Result<Vec<u8>, String>;
+            scan_until_stdin(delim: &[u8]) -> Result<Vec<u8>, String>;
+            set_raw_mode(raw_mode: bool) -> Result<(), String>;
+            get_raw_mode() -> Result<bool, String>;
+            var(name: &str) -> Option<String>;
+            term_size() -> Result<(usize, usize), String>;
+            term_event(timeout: Option<Duration>) -> Result<Option<TermEvent>, String>;
+            window_events(name: &str) -> Result<Vec<WindowEvent>, String>;
+            file_exists(path: &str) -> bool;
+            list_dir(path: &str) -> Result<Vec<String>, String>;
+            is_file(path: &str) -> Result<bool, String>;
+            delete(path: &str) -> Result<(), String>;
+            trash(path: &str) -> Result<(), String>;
+            read(handle: Handle, count: usize) -> Result<Vec<u8>, String>;
+            read_all(handle: Handle) -> Result<Vec<u8>, String>;
+            read_until(handle: Handle, delim: &[u8]) -> Result<Vec<u8>, String>;
+            write(handle: Handle, contents: &[u8]) -> Result<(), String>;
+            create_file(path: &Path) -> Result<Handle, String>;
+            open_file(path: &Path, write: bool) -> Result<Handle, String>;
+            make_dir(path: &Path) -> Result<(), String>;
+            file_read_all(path: &Path) -> Result<Vec<u8>, String>;
+            file_write_all(path: &Path, contents: &[u8]) -> Result<(), String>;
+            file_append(path: &Path, contents: &[u8]) -> Result<(), String>;
+            clipboard() -> Result<String, String>;
+            set_clipboard(contents: &str) -> Result<(), String>;
+            sleep(seconds: f64) -> Result<(), String>;
+            allow_thread_spawning() -> bool;
+            audio_sample_rate() -> u32;
+            now() -> f64;
+            tcp_listen(addr: &str) -> Result<Handle, String>;
+            tcp_accept(handle: Handle) -> Result<Handle, String>;
+            tcp_connect(addr: &str) -> Result<Handle, String>;
+            tls_connect(addr: &str) -> Result<Handle, String>;
+            tcp_addr(handle: Handle) -> Result<SocketAddr, String>;
+            tcp_set_non_blocking(handle: Handle, non_blocking: bool) -> Result<(), String>;
+            tcp_set_read_timeout(handle: Handle, timeout: Option<Duration>) -> Result<(), String>;
+            tcp_set_write_timeout(handle: Handle, timeout: Option<Duration>) -> Result<(), String>;
+            udp_bind(addr: &str) -> Result<Handle, String>;
+            udp_addr(handle: Handle) -> Result<SocketAddr, String>;
+            udp_send(addr: &str, packet: &[u8]) -> Result<(), String>;
+            udp_receive(handle: Handle, timeout: Option<Duration>) -> Result<Option<Vec<u8>>, String>;
+            dns_lookup(record_type: &str, name: &str) -> Result<Vec<String>, String>;
+            ping(addr: &str, timeout: Option<Duration>) -> Result<Option<f64>, String>;
+            interface_addrs() -> Result<Vec<(String, IpAddr)>, String>;
+            mqtt_connect(options: &MqttOptions) -> Result<Handle, String>;
+            mqtt_subscribe(handle: Handle, topic: &str, qos: u8) -> Result<(), String>;
+            mqtt_publish(handle: Handle, message: &MqttMessage) -> Result<(), String>;
+            mqtt_receive(handle: Handle, timeout: Option<Duration>) -> Result<Option<MqttMessage>, String>;
+            smtp_send(server: &SmtpServer, auth: Option<&SmtpAuth>, email: &Email) -> Result<(), String>;
+            keyring_delete(service: &str, account: &str) -> Result<(), String>;
+            gpio_read(chip: usize, pin: u32) -> Result<bool, String>;
+            gpio_write(chip: usize, pin: u32, value: bool) -> Result<(), String>;
+            i2c_transfer(bus: usize, addr: u16, write: &[u8], read_len: usize) -> Result<Vec<u8>, String>;
+            close(handle: Handle) -> Result<(), String>;
+            invoke(path: &str) -> Result<(), String>;
+            run_command_inherit(command: &str, args: &[&str]) -> Result<i32, String>;
+            run_command_capture(command: &str, args: &[&str]) -> Result<(i32, String, String), String>;
+            run_command_stream(command: &str, args: &[&str]) -> Result<[Handle; 3], String>;
+            change_directory(path: &str) -> Result<(), String>;
+            https_get(request: &str, handle: Handle) -> Result<String, String>;
+            plugin_call(name: &str, arg: &str) -> Result<String, String>;
+            python_call(function: &str, args: &[Value]) -> Result<Value, String>;
+            load_git_module(url: &str, target: GitTarget) -> Result<PathBuf, String>;
+            timezone() -> Result<f64, String>;
+        }
+    };
+}
+
+/// Implement system calls that only produce output, which is not recorded
+macro_rules! output_calls {
+    () => {
+        fn save_error_color(&self, message: String, colored: String) {
+            self.inner.save_error_color(message, colored)
+        }
+        fn print_str_stdout(&self, s: &str) -> Result<(), String> {
+            self.inner.print_str_stdout(s)
+        }
+        fn print_str_stderr(&self, s: &str) -> Result<(), String> {
+            self.inner.print_str_stderr(s)
+        }
+        fn term_command(&self, command: TermCommand) -> Result<(), String> {
+            self.inner.term_command(command)
+        }
+        fn print_str_trace(&self, s: &str) {
+            self.inner.print_str_trace(s)
+        }
+        fn show(&self, value: Value) -> Result<(), String> {
+            self.inner.show(value)
+        }
+        #[cfg(feature = "image")]
+        fn show_image(&self, image: DynamicImage, label: Option<&str>) -> Result<(), String> {
+            self.inner.show_image(image, label)
+        }
+        fn progress(&self, current: f64, total: f64, label: &str) -> Result<(), String> {
+            self.inner.progress(current, total, label)
+        }
+        fn show_gif(&self, gif_bytes: Vec<u8>, label: Option<&str>) -> Result<(), String> {
+            self.inner.show_gif(gif_bytes, label)
+        }
+        fn play_audio(&self, wave_bytes: Vec<u8>, label: Option<&str>) -> Result<(), String> {
+            self.inner.play_audio(wave_bytes, label)
+        }
+        #[cfg(feature = "image")]
+        fn window_show(&self, name: &str, image: DynamicImage) -> Result<(), String> {
+            self.inner.window_show(name, image)
+        }
+        fn window_close(&self, name: &str) -> Result<(), String> {
+            self.inner.window_close(name)
+        }
+        fn breakpoint(&self, env: &Uiua) -> Result<bool, String> {
+            self.inner.breakpoint(env)
+        }
+    };
+}
+
+struct Log(Mutex<File>);
+
+impl Log {
+    fn write(&self, call: &str, args: serde_json::Value, ret: impl Serialize) {
+        let event = Event {
+            call: call.into(),
+            args,
+            ret: to_json(ret),
+        };
+        if let Ok(mut line) = serde_json::to_string(&event) {
+            line.push('\n');
+            _ = self.0.lock().write_all(line.as_bytes());
+        }
+    }
+}
+
+/// A system backend that wraps another and records every system call to a file
+///
+/// The recording can be replayed with [`ReplaySys`].
+///
+/// Output to stdout, stderr, and the like is not recorded, as the replayed program produces it again.
+/// Creating the recorder seeds the calling thread's random number generator
+/// with a seed that is saved in the recording.
+///
+/// Calls made from multiple threads are recorded in whatever order they happen,
+/// so only single-threaded programs are guaranteed to replay faithfully.
+pub struct RecordSys {
+    inner: Arc<dyn SysBackend>,
+    log: Arc<Log>,
+}
+
+impl RecordSys {
+    /// Start recording the system calls made to a backend
+    pub fn create(path: impl AsRef<Path>, inner: impl IntoSysBackend) -> Result<Self, String> {
+        let path = path.as_ref();
+        let mut file = File::create(path)
+            .map_err(|e| format!("Failed to create recording {}: {e}", path.display()))?;
+        let seed = rand::random();
+        let header = serde_json::to_string(&Header { seed }).map_err(|e| e.to_string())?;
+        writeln!(file, "{header}")
+            .map_err(|e| format!("Failed to write recording {}: {e}", path.display()))?;
+        seed_random(seed);
+        Ok(RecordSys {
+            inner: inner.into_sys_backend(),
+            log: Arc::new(Log(Mutex::new(file))),
+        })
+    }
+    fn call<T: Replayed>(
+        &self,
+        name: &str,
+        args: serde_json::Value,
+        f: impl FnOnce(&dyn SysBackend) -> T,
+    ) -> T {
+        let ret = f(&*self.inner);
+        self.log.write(name, args, &ret);
+        ret
+    }
+}
+
+impl SysBackend for RecordSys {
+    fn any(&self) -> &dyn Any {
+        self
+    }
+    fn any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    output_calls!();
+    logged_calls!();
+    fn exit(&self, status: i32) -> Result<(), String> {
+        self.log.write("exit", to_json((status,)), ());
+        self.inner.exit(status)
+    }
+    fn read_lines<'a>(&self, handle: Handle) -> Result<ReadLinesReturnFn<'a>, String> {
+        let read = self.inner.read_lines(handle);
+        let started: Result<(), &String> = read.as_ref().map(|_| ());
+        self.log.write("read_lines", to_json((handle,)), started);
+        let mut read = read?;
+        let log = self.log.clone();
+        Ok(Box::new(move |env: &mut Uiua, mut f| -> UiuaResult {
+            let line_log = log.clone();
+            let res = read(
+                env,
+                Box::new(move |line, env| {
+                    line_log.write("read_line", to_json((handle,)), &line);
+                    f(line, env)
+                }),
+            );
+            log.write("read_lines_end", to_json((handle,)), ());
+            res
+        }))
+    }
+    fn tls_listen(&self, addr: &str, cert: &[u8], key: &[u8]) -> Result<Handle, String> {
+        // The certificate and key are deliberately left out of the recording
+        let args = to_json((addr,));
+        self.call("tls_listen", args, |inner| {
+            inner.tls_listen(addr, cert, key)
+        })
+    }
+    fn keyring_get(&self, service: &str, account: &str) -> Result<Option<String>, String> {
+        // Secrets are never recorded, so a replay reads them from the keychain again
+        self.log
+            .write("keyring_get", to_json((service, account)), ());
+        self.inner.keyring_get(service, account)
+    }
+    fn keyring_set(&self, service: &str, account: &str, secret: &str) -> Result<(), String> {
+        let args = to_json((service, account));
+        self.call("keyring_set", args, |inner| {
+            inner.keyring_set(service, account, secret)
+        })
+    }
+    fn ffi(
+        &self,
+        file: &str,
+        result_ty: FfiType,
+        name: &str,
+        arg_tys: &[FfiType],
+        args: &[Value],
+    ) -> Result<Value, String> {
+        let logged = to_json((file, name, args));
+        self.call("ffi", logged, |inner| {
+            inner.ffi(file, result_ty, name, arg_tys, args)
+        })
+    }
+    fn mem_copy(&self, ty: FfiType, ptr: *const (), len: usize) -> Result<Value, String> {
+        let args = to_json((ptr as usize, len));
+        self.call("mem_copy", args, |inner| inner.mem_copy(ty, ptr, len))
+    }
+    fn mem_free(&self, ptr: *const ()) -> Result<(), String> {
+        let args = to_json((ptr as usize,));
+        self.call("mem_free", args, |inner| inner.mem_free(ptr))
+    }
+    fn ffi_callback(
+        &self,
+        result_ty: FfiType,
+        arg_tys: &[FfiType],
+        f: FfiCallbackFn,
+    ) -> Result<Value, String> {
+        let args = to_json(callback_sig(&result_ty, arg_tys));
+        self.call("ffi_callback", args, |inner| {
+            inner.ffi_callback(result_ty, arg_tys, f)
+        })
+    }
+    fn stream_audio(&self, f: AudioStreamFn) -> Result<(), String> {
+        self.inner.stream_audio(f)
+    }
+    fn webcam_capture(&self, index: usize) -> Result<WebcamImage, String> {
+        self.inner.webcam_capture(index)
+    }
+}
+
+#[derive(Default)]
+struct Replay {
+    events: VecDeque<Event>,
+    diverged: Option<String>,
+}
+
+impl Replay {
+    fn next<T: DeserializeOwned>(
+        &mut self,
+        call: &str,
+        args: &serde_json::Value,
+    ) -> Result<T, String> {
+        if let Some(message) = &self.diverged {
+            return Err(message.clone());
+        }
+        let res = match self.events.pop_front() {
+            None => Err(format!(
+                "Replay diverged: `{call}` was called after the end of the recording"
+            )),
+            Some(event) if event.call != call || event.args != *args => Err(format!(
+                "Replay diverged: `{call}` was called with {args}, \
+                but the recording has `{}` with {}",
+                event.call, event.args
+            )),
+            Some(event) => {
+                serde_json::from_value(event.ret).map_err(|e| format!("Invalid recording: {e}"))
+            }
+        };
+        if let Err(message) = &res {
+            self.diverged = Some(message.clone());
+        }
+        res
+    }
+}
+
+/// A system backend that replays a recording made with [`RecordSys`]
+///
+/// System calls are answered from the recording instead of the real system,
+/// so time, the network, files, commands, and randomness behave exactly as they did when it was made.
+/// Output and exiting are passed on to another backend.
+///
+/// If the program makes a different system call than the one that was recorded,
+/// that call and every call after it fail with an error describing the difference.
+pub struct ReplaySys {
+    inner: Arc<dyn SysBackend>,
+    replay: Arc<Mutex<Replay>>,
+}
+
+impl ReplaySys {
+    /// Load a recording, sending output to another backend
+    ///
+    /// This seeds the calling thread's random number generator with the recorded seed.
+    pub fn open(path: impl AsRef<Path>, inner: impl IntoSysBackend) -> Result<Self, String> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read recording {}: {e}", path.display()))?;
+        let invalid = |e: serde_json::Error| format!("Invalid recording {}: {e}", path.display());
+        let mut lines = text.lines();
+        let header: Header =
+            serde_json::from_str(lines.next().unwrap_or_default()).map_err(invalid)?;
+        let events = (lines.filter(|line| !line.trim().is_empty()))
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()
+            .map_err(invalid)?;
+        seed_random(header.seed);
+        Ok(ReplaySys {
+            inner: inner.into_sys_backend(),
+            replay: Arc::new(Mutex::new(Replay {
+                events,
+                diverged: None,
+            })),
+        })
+    }
+    /// Get the message of the first place the replay diverged from its recording, if any
+    pub fn divergence(&self) -> Option<String> {
+        self.replay.lock().diverged.clone()
+    }
+    /// Get the number of recorded system calls that have not been replayed yet
+    pub fn remaining(&self) -> usize {
+        self.replay.lock().events.len()
+    }
+    fn call<T: Replayed>(
+        &self,
+        name: &str,
+        args: serde_json::Value,
+        _: impl FnOnce(&dyn SysBackend) -> T,
+    ) -> T {
+        let res = self.replay.lock().next(name, &args);
+        res.unwrap_or_else(T::diverged)
+    }
+}
+
+impl SysBackend for ReplaySys {
+    fn any(&self) -> &dyn Any {
+        self
+    }
+    fn any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    output_calls!();
+    logged_calls!();
+    fn exit(&self, status: i32) -> Result<(), String> {
+        self.replay.lock().next::<()>("exit", &to_json((status,)))?;
+        self.inner.exit(status)
+    }
+    fn read_lines<'a>(&self, handle: Handle) -> Result<ReadLinesReturnFn<'a>, String> {
+        let args = to_json((handle,));
+        let started: Result<(), String> = self.replay.lock().next("read_lines", &args)?;
+        started?;
+        let replay = self.replay.clone();
+        Ok(Box::new(move |env: &mut Uiua, mut f| -> UiuaResult {
+            loop {
+                let call = (replay.lock().events.front()).map(|event| event.call.clone());
+                if call.as_deref() == Some("read_line") {
+                    let line = replay.lock().next("read_line", &args);
+                    let res = line.map_err(|e| env.error(e)).and_then(|line| f(line, env));
+                    if res.is_err() {
+                        // The recording ended the loop early too
+                        _ = replay.lock().next::<()>("read_lines_end", &args);
+                        return res;
+                    }
+                } else {
+                    return (replay.lock().next::<()>("read_lines_end", &args))
+                        .map_err(|e| env.error(e));
+                }
+            }
+        }))
+    }
+    fn tls_listen(&self, addr: &str, _cert: &[u8], _key: &[u8]) -> Result<Handle, String> {
+        self.call("tls_listen", to_json((addr,)), |_| unreachable!())
+    }
+    fn keyring_get(&self, service: &str, account: &str) -> Result<Option<String>, String> {
+        let args = to_json((service, account));
+        self.replay.lock().next::<()>("keyring_get", &args)?;
+        self.inner.keyring_get(service, account)
+    }
+    fn keyring_set(&self, service: &str, account: &str, _secret: &str) -> Result<(), String> {
+        let args = to_json((service, account));
+        self.call("keyring_set", args, |_| unreachable!())
+    }
+    fn ffi(
+        &self,
+        file: &str,
+        _result_ty: FfiType,
+        name: &str,
+        _arg_tys: &[FfiType],
+        args: &[Value],
+    ) -> Result<Value, String> {
+        self.call("ffi", to_json((file, name, args)), |_| unreachable!())
+    }
+    fn mem_copy(&self, _ty: FfiType, ptr: *const (), len: usize) -> Result<Value, String> {
+        self.call("mem_copy", to_json((ptr as usize, len)), |_| unreachable!())
+    }
+    fn mem_free(&self, ptr: *const ()) -> Result<(), String> {
+        self.call("mem_free", to_json((ptr as usize,)), |_| unreachable!())
+    }
+    fn ffi_callback(
+        &self,
+        result_ty: FfiType,
+        arg_tys: &[FfiType],
+        _: FfiCallbackFn,
+    ) -> Result<Value, String> {
+        let args = to_json(callback_sig(&result_ty, arg_tys));
+        self.call("ffi_callback", args, |_| unreachable!())
+    }
+    fn stream_audio(&self, _: AudioStreamFn) -> Result<(), String> {
+        Err("Streaming audio cannot be replayed".into())
+    }
+    fn webcam_capture(&self, _: usize) -> Result<WebcamImage, String> {
+        Err("Capturing from the webcam cannot be replayed".into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn record_replay() {
+        use crate::*;
+        use std::sync::Arc;
+        let path = std::env::temp_dir().join("uiua_record_replay_test.jsonl");
+        let code = "&p ⚂\n&p &sc\n&p &fras \"in.txt\"\n&sl 1\n&p now";
+        let recorded = Arc::new(
+            MemoryBackend::new()
+                .with_file("in.txt", "from a file")
+                .with_stdin("from stdin\n")
+                .with_time(5.0),
+        );
+        let backend = RecordSys::create(&path, recorded.clone() as Arc<dyn SysBackend>).unwrap();
+        Uiua::with_backend(backend).run_str(code).unwrap();
+        let expected = recorded.take_stdout();
+        assert!(expected.ends_with(b"from stdin\nfrom a file\n6\n"));
+        // Replaying needs none of the original inputs
+        let replayed = Arc::new(MemoryBackend::new());
+        let backend = ReplaySys::open(&path, replayed.clone() as Arc<dyn SysBackend>).unwrap();
+        let mut env = Uiua::with_backend(backend);
+        env.run_str(code).unwrap();
+        assert_eq!(replayed.take_stdout(), expected);
+        let replay = env.downcast_backend::<ReplaySys>().unwrap();
+        assert_eq!(replay.divergence(), None);
+        assert_eq!(replay.remaining(), 0);
+        // A different program diverges from the recording
+        let mut env = Uiua::with_backend(ReplaySys::open(&path, MemoryBackend::new()).unwrap());
+        let Err(err) = env.run_str(r#"&p &fras "other.txt""#) else {
+            panic!("Replay did not diverge");
+        };
+        assert!(err.to_string().contains("Replay diverged"));
+        _ = std::fs::remove_file(path);
+    }
+}
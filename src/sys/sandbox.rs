@@ -0,0 +1,621 @@
+//! A system backend that restricts what another backend is allowed to do
+
+use std::{
+    any::Any,
+    env, fs,
+    net::{IpAddr, SocketAddr},
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+#[cfg(feature = "image")]
+use image::DynamicImage;
+
+use super::{
+    AudioStreamFn, Email, GitTarget, Handle, IntoSysBackend, MqttMessage, MqttOptions,
+    ReadLinesReturnFn, SmtpAuth, SmtpServer, SysBackend, TermCommand, TermEvent, WebcamImage,
+    WindowEvent,
+};
+use crate::{FfiCallbackFn, FfiType, Uiua, Value};
+
+/// The restrictions placed on a [`SandboxSys`]
+#[derive(Debug, Clone, Default)]
+pub struct SandboxProfile {
+    /// Deny network access
+    pub no_net: bool,
+    /// Deny running commands, invoking paths with other programs, calling Python, and accessing the keychain
+    pub no_commands: bool,
+    /// Deny FFI, pointer operations, and plugins
+    pub no_ffi: bool,
+    /// Deny capturing images from webcams
+    pub no_webcam: bool,
+    /// Deny reading environment variables
+    pub no_env: bool,
+    /// Deny reading and writing the clipboard
+    pub no_clipboard: bool,
+    /// Deny creating, modifying, and deleting files and directories
+    pub fs_read_only: bool,
+    /// If not empty, the filesystem can only be accessed within these directories
+    pub allowed_paths: Vec<PathBuf>,
+}
+
+impl SandboxProfile {
+    /// A profile that denies network access, commands, FFI, webcams, environment variables, the clipboard, and filesystem modification
+    pub fn strict() -> Self {
+        Self {
+            no_net: true,
+            no_commands: true,
+            no_ffi: true,
+            no_webcam: true,
+            no_env: true,
+            no_clipboard: true,
+            fs_read_only: true,
+            allowed_paths: Vec::new(),
+        }
+    }
+    /// Check if the profile does not restrict anything
+    pub fn is_unrestricted(&self) -> bool {
+        !(self.no_net
+            || self.no_commands
+            || self.no_ffi
+            || self.no_webcam
+            || self.no_env
+            || self.no_clipboard
+            || self.fs_read_only)
+            && self.allowed_paths.is_empty()
+    }
+}
+
+/// A system backend that wraps another and denies operations according to a [`SandboxProfile`]
+///
+/// Denied operations fail with an error that says what was denied.
+pub struct SandboxSys {
+    inner: Arc<dyn SysBackend>,
+    profile: SandboxProfile,
+}
+
+impl SandboxSys {
+    /// Wrap a backend in a sandbox
+    pub fn new(inner: impl IntoSysBackend, mut profile: SandboxProfile) -> Self {
+        for path in &mut profile.allowed_paths {
+            *path = resolve(path);
+        }
+        SandboxSys {
+            inner: inner.into_sys_backend(),
+            profile,
+        }
+    }
+    /// Get the sandbox's profile
+    pub fn profile(&self) -> &SandboxProfile {
+        &self.profile
+    }
+    fn net(&self) -> Result<(), String> {
+        if self.profile.no_net {
+            Err("Network access is denied by the sandbox".into())
+        } else {
+            Ok(())
+        }
+    }
+    fn commands(&self) -> Result<(), String> {
+        if self.profile.no_commands {
+            Err("Running commands is denied by the sandbox".into())
+        } else {
+            Ok(())
+        }
+    }
+    fn ffi_allowed(&self) -> Result<(), String> {
+        if self.profile.no_ffi {
+            Err("FFI is denied by the sandbox".into())
+        } else {
+            Ok(())
+        }
+    }
+    fn webcam(&self) -> Result<(), String> {
+        if self.profile.no_webcam {
+            Err("Webcam access is denied by the sandbox".into())
+        } else {
+            Ok(())
+        }
+    }
+    fn clipboard_allowed(&self) -> Result<(), String> {
+        if self.profile.no_clipboard {
+            Err("Clipboard access is denied by the sandbox".into())
+        } else {
+            Ok(())
+        }
+    }
+    fn read_path(&self, path: &Path) -> Result<(), String> {
+        let allowed = &self.profile.allowed_paths;
+        if allowed.is_empty() {
+            return Ok(());
+        }
+        let resolved = resolve(path);
+        if allowed.iter().any(|dir| resolved.starts_with(dir)) {
+            Ok(())
+        } else {
+            Err(format!(
+                "Access to {} is denied by the sandbox",
+                path.display()
+            ))
+        }
+    }
+    fn write_path(&self, path: &Path) -> Result<(), String> {
+        if self.profile.fs_read_only {
+            return Err(format!(
+                "Modifying {} is denied by the sandbox",
+                path.display()
+            ));
+        }
+        self.read_path(path)
+    }
+}
+
+/// Make a path absolute and resolve symlinks and `..`s
+///
+/// Parts of the path that do not exist yet are appended to its deepest existing ancestor.
+fn resolve(path: &Path) -> PathBuf {
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        env::current_dir().unwrap_or_default().join(path)
+    };
+    let mut existing = path.as_path();
+    let mut rest = Vec::new();
+    loop {
+        if let Ok(canon) = fs::canonicalize(existing) {
+            let mut resolved = canon;
+            for comp in rest.into_iter().rev() {
+                match comp {
+                    Component::ParentDir => _ = resolved.pop(),
+                    Component::CurDir => {}
+                    comp => resolved.push(comp),
+                }
+            }
+            return resolved;
+        }
+        match (existing.parent(), existing.components().next_back()) {
+            (Some(parent), Some(comp)) => {
+                rest.push(comp);
+                existing = parent;
+            }
+            _ => return path,
+        }
+    }
+}
+
+impl SysBackend for SandboxSys {
+    fn any(&self) -> &dyn Any {
+        self
+    }
+    fn any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn save_error_color(&self, message: String, colored: String) {
+        self.inner.save_error_color(message, colored)
+    }
+    fn print_str_stdout(&self, s: &str) -> Result<(), String> {
+        self.inner.print_str_stdout(s)
+    }
+    fn print_str_stderr(&self, s: &str) -> Result<(), String> {
+        self.inner.print_str_stderr(s)
+    }
+    fn print_str_trace(&self, s: &str) {
+        self.inner.print_str_trace(s)
+    }
+    fn show(&self, value: Value) -> Result<(), String> {
+        self.inner.show(value)
+    }
+    fn scan_line_stdin(&self) -> Result<Option<String>, String> {
+        self.inner.scan_line_stdin()
+    }
+    fn prompt(&self, prompt: &str, hidden: bool) -> Result<Option<String>, String> {
+        self.inner.prompt(prompt, hidden)
+    }
+    fn scan_stdin(&self, count: Option<usize>) -> Result<Vec<u8>, String> {
+        self.inner.scan_stdin(count)
+    }
+    fn scan_until_stdin(&self, delim: &[u8]) -> Result<Vec<u8>, String> {
+        self.inner.scan_until_stdin(delim)
+    }
+    fn set_raw_mode(&self, raw_mode: bool) -> Result<(), String> {
+        self.inner.set_raw_mode(raw_mode)
+    }
+    fn get_raw_mode(&self) -> Result<bool, String> {
+        self.inner.get_raw_mode()
+    }
+    fn var(&self, name: &str) -> Option<String> {
+        if self.profile.no_env {
+            return None;
+        }
+        self.inner.var(name)
+    }
+    fn term_size(&self) -> Result<(usize, usize), String> {
+        self.inner.term_size()
+    }
+    fn term_command(&self, command: TermCommand) -> Result<(), String> {
+        self.inner.term_command(command)
+    }
+    fn term_event(&self, timeout: Option<Duration>) -> Result<Option<TermEvent>, String> {
+        self.inner.term_event(timeout)
+    }
+    fn exit(&self, status: i32) -> Result<(), String> {
+        self.inner.exit(status)
+    }
+    fn file_exists(&self, path: &str) -> bool {
+        self.read_path(path.as_ref()).is_ok() && self.inner.file_exists(path)
+    }
+    fn list_dir(&self, path: &str) -> Result<Vec<String>, String> {
+        self.read_path(path.as_ref())?;
+        self.inner.list_dir(path)
+    }
+    fn is_file(&self, path: &str) -> Result<bool, String> {
+        self.read_path(path.as_ref())?;
+        self.inner.is_file(path)
+    }
+    fn delete(&self, path: &str) -> Result<(), String> {
+        self.write_path(path.as_ref())?;
+        self.inner.delete(path)
+    }
+    fn trash(&self, path: &str) -> Result<(), String> {
+        self.write_path(path.as_ref())?;
+        self.inner.trash(path)
+    }
+    fn read(&self, handle: Handle, count: usize) -> Result<Vec<u8>, String> {
+        self.inner.read(handle, count)
+    }
+    fn read_all(&self, handle: Handle) -> Result<Vec<u8>, String> {
+        self.inner.read_all(handle)
+    }
+    fn read_until(&self, handle: Handle, delim: &[u8]) -> Result<Vec<u8>, String> {
+        self.inner.read_until(handle, delim)
+    }
+    fn read_lines<'a>(&self, handle: Handle) -> Result<ReadLinesReturnFn<'a>, String> {
+        self.inner.read_lines(handle)
+    }
+    fn write(&self, handle: Handle, contents: &[u8]) -> Result<(), String> {
+        self.inner.write(handle, contents)
+    }
+    fn create_file(&self, path: &Path) -> Result<Handle, String> {
+        self.write_path(path)?;
+        self.inner.create_file(path)
+    }
+    fn open_file(&self, path: &Path, write: bool) -> Result<Handle, String> {
+        if write {
+            self.write_path(path)?;
+        } else {
+            self.read_path(path)?;
+        }
+        self.inner.open_file(path, write)
+    }
+    fn make_dir(&self, path: &Path) -> Result<(), String> {
+        self.write_path(path)?;
+        self.inner.make_dir(path)
+    }
+    fn file_read_all(&self, path: &Path) -> Result<Vec<u8>, String> {
+        self.read_path(path)?;
+        self.inner.file_read_all(path)
+    }
+    fn file_write_all(&self, path: &Path, contents: &[u8]) -> Result<(), String> {
+        self.write_path(path)?;
+        self.inner.file_write_all(path, contents)
+    }
+    fn file_append(&self, path: &Path, contents: &[u8]) -> Result<(), String> {
+        self.write_path(path)?;
+        self.inner.file_append(path, contents)
+    }
+    fn clipboard(&self) -> Result<String, String> {
+        self.clipboard_allowed()?;
+        self.inner.clipboard()
+    }
+    fn set_clipboard(&self, contents: &str) -> Result<(), String> {
+        self.clipboard_allowed()?;
+        self.inner.set_clipboard(contents)
+    }
+    fn sleep(&self, seconds: f64) -> Result<(), String> {
+        self.inner.sleep(seconds)
+    }
+    fn allow_thread_spawning(&self) -> bool {
+        self.inner.allow_thread_spawning()
+    }
+    fn can_spawn_threads(&self) -> bool {
+        self.inner.can_spawn_threads()
+    }
+    fn spawn_thread(&self, f: Box<dyn FnOnce() + Send>) -> Result<(), String> {
+        self.inner.spawn_thread(f)
+    }
+    #[cfg(feature = "image")]
+    fn show_image(&self, image: DynamicImage, label: Option<&str>) -> Result<(), String> {
+        self.inner.show_image(image, label)
+    }
+    fn progress(&self, current: f64, total: f64, label: &str) -> Result<(), String> {
+        self.inner.progress(current, total, label)
+    }
+    fn show_gif(&self, gif_bytes: Vec<u8>, label: Option<&str>) -> Result<(), String> {
+        self.inner.show_gif(gif_bytes, label)
+    }
+    fn play_audio(&self, wave_bytes: Vec<u8>, label: Option<&str>) -> Result<(), String> {
+        self.inner.play_audio(wave_bytes, label)
+    }
+    #[cfg(feature = "image")]
+    fn window_show(&self, name: &str, image: DynamicImage) -> Result<(), String> {
+        self.inner.window_show(name, image)
+    }
+    fn window_close(&self, name: &str) -> Result<(), String> {
+        self.inner.window_close(name)
+    }
+    fn window_events(&self, name: &str) -> Result<Vec<WindowEvent>, String> {
+        self.inner.window_events(name)
+    }
+    fn audio_sample_rate(&self) -> u32 {
+        self.inner.audio_sample_rate()
+    }
+    fn stream_audio(&self, f: AudioStreamFn) -> Result<(), String> {
+        self.inner.stream_audio(f)
+    }
+    fn now(&self) -> f64 {
+        self.inner.now()
+    }
+    fn tcp_listen(&self, addr: &str) -> Result<Handle, String> {
+        self.net()?;
+        self.inner.tcp_listen(addr)
+    }
+    fn tls_listen(&self, addr: &str, cert: &[u8], key: &[u8]) -> Result<Handle, String> {
+        self.net()?;
+        self.inner.tls_listen(addr, cert, key)
+    }
+    fn tcp_accept(&self, handle: Handle) -> Result<Handle, String> {
+        self.net()?;
+        self.inner.tcp_accept(handle)
+    }
+    fn tcp_connect(&self, addr: &str) -> Result<Handle, String> {
+        self.net()?;
+        self.inner.tcp_connect(addr)
+    }
+    fn tls_connect(&self, addr: &str) -> Result<Handle, String> {
+        self.net()?;
+        self.inner.tls_connect(addr)
+    }
+    fn tcp_addr(&self, handle: Handle) -> Result<SocketAddr, String> {
+        self.net()?;
+        self.inner.tcp_addr(handle)
+    }
+    fn tcp_set_non_blocking(&self, handle: Handle, non_blocking: bool) -> Result<(), String> {
+        self.net()?;
+        self.inner.tcp_set_non_blocking(handle, non_blocking)
+    }
+    fn tcp_set_read_timeout(
+        &self,
+        handle: Handle,
+        timeout: Option<Duration>,
+    ) -> Result<(), String> {
+        self.net()?;
+        self.inner.tcp_set_read_timeout(handle, timeout)
+    }
+    fn tcp_set_write_timeout(
+        &self,
+        handle: Handle,
+        timeout: Option<Duration>,
+    ) -> Result<(), String> {
+        self.net()?;
+        self.inner.tcp_set_write_timeout(handle, timeout)
+    }
+    fn udp_bind(&self, addr: &str) -> Result<Handle, String> {
+        self.net()?;
+        self.inner.udp_bind(addr)
+    }
+    fn udp_addr(&self, handle: Handle) -> Result<SocketAddr, String> {
+        self.net()?;
+        self.inner.udp_addr(handle)
+    }
+    fn udp_send(&self, addr: &str, packet: &[u8]) -> Result<(), String> {
+        self.net()?;
+        self.inner.udp_send(addr, packet)
+    }
+    fn udp_receive(
+        &self,
+        handle: Handle,
+        timeout: Option<Duration>,
+    ) -> Result<Option<Vec<u8>>, String> {
+        self.net()?;
+        self.inner.udp_receive(handle, timeout)
+    }
+    fn dns_lookup(&self, record_type: &str, name: &str) -> Result<Vec<String>, String> {
+        self.net()?;
+        self.inner.dns_lookup(record_type, name)
+    }
+    fn ping(&self, addr: &str, timeout: Option<Duration>) -> Result<Option<f64>, String> {
+        self.net()?;
+        self.inner.ping(addr, timeout)
+    }
+    fn interface_addrs(&self) -> Result<Vec<(String, IpAddr)>, String> {
+        self.net()?;
+        self.inner.interface_addrs()
+    }
+    fn mqtt_connect(&self, options: &MqttOptions) -> Result<Handle, String> {
+        self.net()?;
+        self.inner.mqtt_connect(options)
+    }
+    fn mqtt_subscribe(&self, handle: Handle, topic: &str, qos: u8) -> Result<(), String> {
+        self.net()?;
+        self.inner.mqtt_subscribe(handle, topic, qos)
+    }
+    fn mqtt_publish(&self, handle: Handle, message: &MqttMessage) -> Result<(), String> {
+        self.net()?;
+        self.inner.mqtt_publish(handle, message)
+    }
+    fn mqtt_receive(
+        &self,
+        handle: Handle,
+        timeout: Option<Duration>,
+    ) -> Result<Option<MqttMessage>, String> {
+        self.net()?;
+        self.inner.mqtt_receive(handle, timeout)
+    }
+    fn smtp_send(
+        &self,
+        server: &SmtpServer,
+        auth: Option<&SmtpAuth>,
+        email: &Email,
+    ) -> Result<(), String> {
+        self.net()?;
+        self.inner.smtp_send(server, auth, email)
+    }
+    fn keyring_get(&self, service: &str, account: &str) -> Result<Option<String>, String> {
+        self.commands()?;
+        self.inner.keyring_get(service, account)
+    }
+    fn keyring_set(&self, service: &str, account: &str, secret: &str) -> Result<(), String> {
+        self.commands()?;
+        self.inner.keyring_set(service, account, secret)
+    }
+    fn keyring_delete(&self, service: &str, account: &str) -> Result<(), String> {
+        self.commands()?;
+        self.inner.keyring_delete(service, account)
+    }
+    fn gpio_read(&self, chip: usize, pin: u32) -> Result<bool, String> {
+        self.read_path(Path::new(&format!("/dev/gpiochip{chip}")))?;
+        self.inner.gpio_read(chip, pin)
+    }
+    fn gpio_write(&self, chip: usize, pin: u32, value: bool) -> Result<(), String> {
+        self.write_path(Path::new(&format!("/dev/gpiochip{chip}")))?;
+        self.inner.gpio_write(chip, pin, value)
+    }
+    fn i2c_transfer(
+        &self,
+        bus: usize,
+        addr: u16,
+        write: &[u8],
+        read_len: usize,
+    ) -> Result<Vec<u8>, String> {
+        self.write_path(Path::new(&format!("/dev/i2c-{bus}")))?;
+        self.inner.i2c_transfer(bus, addr, write, read_len)
+    }
+    fn close(&self, handle: Handle) -> Result<(), String> {
+        self.inner.close(handle)
+    }
+    fn invoke(&self, path: &str) -> Result<(), String> {
+        self.commands()?;
+        self.inner.invoke(path)
+    }
+    fn run_command_inherit(&self, command: &str, args: &[&str]) -> Result<i32, String> {
+        self.commands()?;
+        self.inner.run_command_inherit(command, args)
+    }
+    fn run_command_capture(
+        &self,
+        command: &str,
+        args: &[&str],
+    ) -> Result<(i32, String, String), String> {
+        self.commands()?;
+        self.inner.run_command_capture(command, args)
+    }
+    fn run_command_stream(&self, command: &str, args: &[&str]) -> Result<[Handle; 3], String> {
+        self.commands()?;
+        self.inner.run_command_stream(command, args)
+    }
+    fn change_directory(&self, path: &str) -> Result<(), String> {
+        self.read_path(path.as_ref())?;
+        self.inner.change_directory(path)
+    }
+    fn https_get(&self, request: &str, handle: Handle) -> Result<String, String> {
+        self.net()?;
+        self.inner.https_get(request, handle)
+    }
+    fn webcam_capture(&self, index: usize) -> Result<WebcamImage, String> {
+        self.webcam()?;
+        self.inner.webcam_capture(index)
+    }
+    fn ffi(
+        &self,
+        file: &str,
+        result_ty: FfiType,
+        name: &str,
+        arg_tys: &[FfiType],
+        args: &[Value],
+    ) -> Result<Value, String> {
+        self.ffi_allowed()?;
+        self.inner.ffi(file, result_ty, name, arg_tys, args)
+    }
+    fn mem_copy(&self, ty: FfiType, ptr: *const (), len: usize) -> Result<Value, String> {
+        self.ffi_allowed()?;
+        self.inner.mem_copy(ty, ptr, len)
+    }
+    fn ffi_callback(
+        &self,
+        result_ty: FfiType,
+        arg_tys: &[FfiType],
+        f: FfiCallbackFn,
+    ) -> Result<Value, String> {
+        self.ffi_allowed()?;
+        self.inner.ffi_callback(result_ty, arg_tys, f)
+    }
+    fn mem_free(&self, ptr: *const ()) -> Result<(), String> {
+        self.ffi_allowed()?;
+        self.inner.mem_free(ptr)
+    }
+    fn plugin_call(&self, name: &str, arg: &str) -> Result<String, String> {
+        self.ffi_allowed()?;
+        self.inner.plugin_call(name, arg)
+    }
+    fn python_call(&self, function: &str, args: &[Value]) -> Result<Value, String> {
+        self.commands()?;
+        self.inner.python_call(function, args)
+    }
+    fn load_git_module(&self, url: &str, target: GitTarget) -> Result<PathBuf, String> {
+        self.net()?;
+        // Git modules are cloned into the modules directory and recorded in the lockfile
+        self.write_path(Path::new("uiua-modules"))?;
+        self.write_path(Path::new("uiua.lock"))?;
+        self.inner.load_git_module(url, target)
+    }
+    fn timezone(&self) -> Result<f64, String> {
+        self.inner.timezone()
+    }
+    fn breakpoint(&self, env: &Uiua) -> Result<bool, String> {
+        self.inner.breakpoint(env)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    #[cfg(feature = "native_sys")]
+    fn sandbox() {
+        use crate::*;
+        let profile = SandboxProfile {
+            allowed_paths: vec!["tests".into()],
+            ..SandboxProfile::strict()
+        };
+        for (code, should_succeed) in [
+            ("&fras \"tests/box.ua\"", true),
+            ("&fras \"Cargo.toml\"", false),
+            ("&fras \"tests/../Cargo.toml\"", false),
+            ("&fwa \"tests/sandbox.txt\" \"x\"", false),
+            ("&runc \"echo\"", false),
+            ("&tcpc \"localhost:1\"", false),
+            ("&camcap 0", false),
+            ("&clip", false),
+        ] {
+            let mut env = Uiua::with_backend(SandboxSys::new(NativeSys, profile.clone()));
+            match env.run_str(code) {
+                Ok(_) if !should_succeed => panic!("Sandbox allowed `{code}`"),
+                Err(e) if should_succeed => panic!("Sandbox denied `{code}`:\n{}", e.report()),
+                Err(e) if !e.to_string().contains("denied by the sandbox") => {
+                    panic!("`{code}` failed for the wrong reason:\n{}", e.report())
+                }
+                _ => {}
+            }
+        }
+        let sys = SandboxSys::new(NativeSys, profile);
+        assert_eq!(sys.var("PATH"), None);
+        let read_only = SandboxProfile {
+            fs_read_only: true,
+            ..Default::default()
+        };
+        let sys = SandboxSys::new(NativeSys, read_only);
+        let err = (sys.load_git_module("https://github.com/uiua-lang/example", GitTarget::Default))
+            .unwrap_err();
+        assert!(err.contains("denied by the sandbox"), "{err}");
+    }
+}
@@ -0,0 +1,634 @@
+//! Sending email over SMTP
+//!
+//! Messages are sent as MIME messages with base64-encoded parts, so any text or attachment can be sent.
+
+use serde::*;
+
+use crate::{Uiua, UiuaResult, Value};
+
+/// An SMTP server to send email through
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmtpServer {
+    /// The address of the server, including the port
+    pub address: String,
+    /// How the connection is secured
+    pub security: SmtpSecurity,
+}
+
+/// How an SMTP connection is secured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SmtpSecurity {
+    /// TLS from the start of the connection
+    Tls,
+    /// A plain connection that is upgraded with `STARTTLS`
+    StartTls,
+    /// No encryption
+    None,
+}
+
+/// Credentials for an SMTP server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmtpAuth {
+    /// The user name
+    pub username: String,
+    /// The password
+    ///
+    /// This is not serialized so that it does not end up in recordings.
+    #[serde(skip_serializing, default)]
+    pub password: String,
+}
+
+/// An email message
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Email {
+    /// The sender
+    pub from: String,
+    /// The recipients
+    pub to: Vec<String>,
+    /// The recipients who receive a copy
+    pub cc: Vec<String>,
+    /// The recipients who receive a copy without being listed in the message
+    pub bcc: Vec<String>,
+    /// The subject line
+    pub subject: String,
+    /// The plain text body
+    pub body: String,
+    /// The attachments' file names and contents
+    pub attachments: Vec<(String, Vec<u8>)>,
+}
+
+impl SmtpServer {
+    /// Get a server from an address string or a map of options
+    pub(crate) fn from_value(value: &Value, env: &Uiua) -> UiuaResult<Self> {
+        let mut address = None;
+        let mut security = None;
+        if value.is_map() {
+            for (key, val) in value.map_kv() {
+                let key = key.as_string(env, "SMTP server option names must be strings")?;
+                let val = val.unpacked();
+                match key.as_str() {
+                    "address" => address = Some(val.as_string(env, "Address must be a string")?),
+                    "security" => {
+                        let s = val.as_string(env, "Security must be a string")?;
+                        security = Some(match s.to_ascii_lowercase().as_str() {
+                            "tls" => SmtpSecurity::Tls,
+                            "starttls" => SmtpSecurity::StartTls,
+                            "none" => SmtpSecurity::None,
+                            _ => {
+                                return Err(env.error(format!(
+                                    "Unknown SMTP security {s:?}. \
+                                    Expected tls, starttls, or none."
+                                )))
+                            }
+                        });
+                    }
+                    _ => {
+                        return Err(env.error(format!(
+                            "Unknown SMTP server option {key:?}. \
+                            The options are address and security"
+                        )))
+                    }
+                }
+            }
+        } else {
+            address = Some(value.as_string(env, "SMTP server must be an address or a map")?);
+        }
+        let mut address =
+            address.ok_or_else(|| env.error("SMTP server options must include an address"))?;
+        let port = (address.rsplit_once(':'))
+            .filter(|(host, _)| !host.is_empty() && !host.ends_with(':'))
+            .and_then(|(_, port)| port.parse::<u16>().ok());
+        let security = security.unwrap_or(if port == Some(465) {
+            SmtpSecurity::Tls
+        } else {
+            SmtpSecurity::StartTls
+        });
+        if port.is_none() {
+            let port = if security == SmtpSecurity::Tls {
+                465
+            } else {
+                587
+            };
+            address = format!("{address}:{port}");
+        }
+        Ok(SmtpServer { address, security })
+    }
+}
+
+impl SmtpAuth {
+    /// Get credentials from a map, or `None` from an empty list
+    pub(crate) fn from_value(value: &Value, env: &Uiua) -> UiuaResult<Option<Self>> {
+        if !value.is_map() {
+            if value.row_count() == 0 {
+                return Ok(None);
+            }
+            return Err(env.error(format!(
+                "SMTP credentials must be a map or an empty list, but they are {}",
+                value.type_name_plural()
+            )));
+        }
+        let mut username = None;
+        let mut password = None;
+        for (key, val) in value.map_kv() {
+            let key = key.as_string(env, "SMTP credential names must be strings")?;
+            let val = val.unpacked();
+            match key.as_str() {
+                "username" => username = Some(val.as_string(env, "User name must be a string")?),
+                "password" => password = Some(val.as_string(env, "Password must be a string")?),
+                _ => {
+                    return Err(env.error(format!(
+                        "Unknown SMTP credential {key:?}. \
+                        The credentials are username and password"
+                    )))
+                }
+            }
+        }
+        match (username, password) {
+            (Some(username), Some(password)) => Ok(Some(SmtpAuth { username, password })),
+            _ => Err(env.error("SMTP credentials must include a username and a password")),
+        }
+    }
+}
+
+impl Email {
+    /// Get a message from a map
+    pub(crate) fn from_value(value: &Value, env: &Uiua) -> UiuaResult<Self> {
+        if !value.is_map() {
+            return Err(env.error(format!(
+                "Email message must be a map, but it is {}",
+                value.type_name_plural()
+            )));
+        }
+        let mut email = Email::default();
+        for (key, val) in value.map_kv() {
+            let key = key.as_string(env, "Email message keys must be strings")?;
+            let val = val.unpacked();
+            match key.as_str() {
+                "from" => email.from = val.as_string(env, "Sender must be a string")?,
+                "to" => email.to = addresses(&val, env)?,
+                "cc" => email.cc = addresses(&val, env)?,
+                "bcc" => email.bcc = addresses(&val, env)?,
+                "subject" => email.subject = val.as_string(env, "Subject must be a string")?,
+                "body" => email.body = val.as_string(env, "Body must be a string")?,
+                "attachments" => {
+                    if !val.is_map() {
+                        return Err(
+                            env.error("Attachments must be a map from file names to contents")
+                        );
+                    }
+                    for (name, contents) in val.map_kv() {
+                        let name = name.as_string(env, "Attachment names must be strings")?;
+                        let contents = (contents.unpacked()).into_bytes(
+                            env,
+                            "Attachment contents must be strings or lists of bytes",
+                        )?;
+                        email.attachments.push((name, contents));
+                    }
+                }
+                _ => {
+                    return Err(env.error(format!(
+                        "Unknown email message key {key:?}. The keys are \
+                        from, to, cc, bcc, subject, body, and attachments"
+                    )))
+                }
+            }
+        }
+        if email.from.is_empty() {
+            return Err(env.error("Email message must have a sender"));
+        }
+        if email.to.is_empty() && email.cc.is_empty() && email.bcc.is_empty() {
+            return Err(env.error("Email message must have at least one recipient"));
+        }
+        single_line(&email.from, "Sender", env)?;
+        for address in email.to.iter().chain(&email.cc).chain(&email.bcc) {
+            single_line(address, "Email addresses", env)?;
+        }
+        single_line(&email.subject, "Subject", env)?;
+        for (name, _) in &email.attachments {
+            single_line(name, "Attachment names", env)?;
+        }
+        Ok(email)
+    }
+}
+
+/// Check that a header value or address cannot start a new header or SMTP command
+fn single_line(s: &str, what: &str, env: &Uiua) -> UiuaResult {
+    if s.contains(['\r', '\n', '\0']) {
+        Err(env.error(format!(
+            "{what} cannot contain line breaks or null characters"
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+/// Get a single address or a list of boxed addresses
+fn addresses(value: &Value, env: &Uiua) -> UiuaResult<Vec<String>> {
+    if let Value::Box(arr) = value {
+        (arr.data.iter())
+            .map(|b| b.0.as_string(env, "Email addresses must be strings"))
+            .collect()
+    } else {
+        Ok(vec![
+            value.as_string(env, "Email addresses must be strings")?
+        ])
+    }
+}
+
+#[cfg(feature = "smtp")]
+pub(crate) use client::send;
+
+#[cfg(feature = "smtp")]
+mod client {
+    use std::{
+        io::{self, Read, Write},
+        net::{IpAddr, TcpStream},
+        sync::Arc,
+        time::Duration,
+    };
+
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use once_cell::sync::Lazy;
+    use time::OffsetDateTime;
+
+    use super::{Email, SmtpAuth, SmtpSecurity, SmtpServer};
+
+    /// How long to wait for the server to respond
+    const TIMEOUT: Duration = Duration::from_secs(60);
+
+    static TLS_CONFIG: Lazy<Arc<rustls::ClientConfig>> = Lazy::new(|| {
+        let mut store = rustls::RootCertStore::empty();
+        store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        rustls::ClientConfig::builder()
+            .with_root_certificates(store)
+            .with_no_client_auth()
+            .into()
+    });
+
+    trait Stream: Read + Write {}
+    impl<T: Read + Write> Stream for T {}
+
+    struct Connection {
+        tcp: TcpStream,
+        stream: Box<dyn Stream>,
+        /// Bytes that have been read but not yet parsed
+        buffer: Vec<u8>,
+    }
+
+    fn io_err(e: io::Error) -> String {
+        format!("SMTP connection failed: {e}")
+    }
+
+    impl Connection {
+        fn read_line(&mut self) -> Result<String, String> {
+            loop {
+                if let Some(i) = self.buffer.windows(2).position(|w| w == b"\r\n") {
+                    let line = String::from_utf8_lossy(&self.buffer[..i]).into_owned();
+                    self.buffer.drain(..i + 2);
+                    return Ok(line);
+                }
+                let mut chunk = [0; 1024];
+                let n = self.stream.read(&mut chunk).map_err(io_err)?;
+                if n == 0 {
+                    return Err("SMTP server closed the connection".into());
+                }
+                self.buffer.extend(&chunk[..n]);
+            }
+        }
+        /// Read a reply and check that its code is in the same class as the expected one
+        fn expect(&mut self, expected: u16, what: &str) -> Result<Vec<String>, String> {
+            let mut lines = Vec::new();
+            loop {
+                let line = self.read_line()?;
+                let code: u16 = (line.get(..3))
+                    .and_then(|code| code.parse().ok())
+                    .ok_or_else(|| format!("Invalid response from SMTP server: {line:?}"))?;
+                let more = line.as_bytes().get(3) == Some(&b'-');
+                lines.push(line.get(4..).unwrap_or_default().to_string());
+                if more {
+                    continue;
+                }
+                if code / 100 != expected / 100 {
+                    return Err(format!(
+                        "SMTP server rejected {what}: {code} {}",
+                        lines.join(" ")
+                    ));
+                }
+                return Ok(lines);
+            }
+        }
+        fn command(&mut self, command: &str, expected: u16) -> Result<Vec<String>, String> {
+            let what = command.split_whitespace().next().unwrap_or_default();
+            (self.stream.write_all(format!("{command}\r\n").as_bytes())).map_err(io_err)?;
+            self.expect(expected, what)
+        }
+        fn start_tls(&mut self, host: &str) -> Result<(), String> {
+            let server_name = rustls::pki_types::ServerName::try_from(host.to_string())
+                .map_err(|e| e.to_string())?;
+            let conn = rustls::ClientConnection::new(TLS_CONFIG.clone(), server_name)
+                .map_err(|e| e.to_string())?;
+            let tcp = self.tcp.try_clone().map_err(io_err)?;
+            self.stream = Box::new(rustls::StreamOwned::new(conn, tcp));
+            self.buffer.clear();
+            Ok(())
+        }
+        fn hello(&mut self) -> Result<Vec<String>, String> {
+            let name = match self.tcp.local_addr().map_err(io_err)?.ip() {
+                IpAddr::V4(ip) => format!("[{ip}]"),
+                IpAddr::V6(ip) => format!("[IPv6:{ip}]"),
+            };
+            self.command(&format!("EHLO {name}"), 250)
+        }
+    }
+
+    /// Send an email
+    pub(crate) fn send(
+        server: &SmtpServer,
+        auth: Option<&SmtpAuth>,
+        email: &Email,
+    ) -> Result<(), String> {
+        let tcp = TcpStream::connect(&server.address)
+            .map_err(|e| format!("Failed to connect to SMTP server {}: {e}", server.address))?;
+        tcp.set_read_timeout(Some(TIMEOUT)).map_err(io_err)?;
+        let host = (server.address.rsplit_once(':'))
+            .map_or(server.address.as_str(), |(host, _)| host)
+            .trim_matches(['[', ']']);
+        let mut conn = Connection {
+            stream: Box::new(tcp.try_clone().map_err(io_err)?),
+            tcp,
+            buffer: Vec::new(),
+        };
+        if server.security == SmtpSecurity::Tls {
+            conn.start_tls(host)?;
+        }
+        conn.expect(220, "the connection")?;
+        let mut extensions = conn.hello()?;
+        if server.security == SmtpSecurity::StartTls {
+            if !extensions
+                .iter()
+                .any(|ext| ext.eq_ignore_ascii_case("STARTTLS"))
+            {
+                return Err(format!(
+                    "SMTP server {} does not support STARTTLS. \
+                    Set security to none to send without encryption.",
+                    server.address
+                ));
+            }
+            conn.command("STARTTLS", 220)?;
+            conn.start_tls(host)?;
+            extensions = conn.hello()?;
+        }
+        if let Some(auth) = auth {
+            let mechanisms = (extensions.iter())
+                .find_map(|ext| {
+                    let (name, mechanisms) = ext.split_once(' ')?;
+                    name.eq_ignore_ascii_case("AUTH")
+                        .then(|| mechanisms.to_uppercase())
+                })
+                .unwrap_or_else(|| "PLAIN".into());
+            if mechanisms.split_whitespace().any(|m| m == "PLAIN") {
+                let credentials = format!("\0{}\0{}", auth.username, auth.password);
+                conn.command(&format!("AUTH PLAIN {}", STANDARD.encode(credentials)), 235)?;
+            } else if mechanisms.split_whitespace().any(|m| m == "LOGIN") {
+                conn.command("AUTH LOGIN", 334)?;
+                conn.command(&STANDARD.encode(&auth.username), 334)?;
+                conn.command(&STANDARD.encode(&auth.password), 235)?;
+            } else {
+                return Err(format!(
+                    "SMTP server only supports {mechanisms} authentication, \
+                    but only PLAIN and LOGIN are supported"
+                ));
+            }
+        }
+        conn.command(&format!("MAIL FROM:<{}>", mailbox(&email.from)), 250)?;
+        for to in email.to.iter().chain(&email.cc).chain(&email.bcc) {
+            conn.command(&format!("RCPT TO:<{}>", mailbox(to)), 250)?;
+        }
+        conn.command("DATA", 354)?;
+        // Lines that start with a dot are escaped with another dot
+        let message = message(email).replace("\r\n.", "\r\n..");
+        (conn.stream.write_all(message.as_bytes())).map_err(io_err)?;
+        conn.stream.write_all(b".\r\n").map_err(io_err)?;
+        conn.expect(250, "the message")?;
+        _ = conn.command("QUIT", 221);
+        Ok(())
+    }
+
+    /// Get the address from an address that may have a display name, like `Name <address>`
+    fn mailbox(address: &str) -> &str {
+        (address.rsplit_once('<'))
+            .and_then(|(_, rest)| rest.split_once('>'))
+            .map_or(address, |(addr, _)| addr)
+            .trim()
+    }
+
+    /// Encode a header value if it is not ASCII
+    fn header(value: &str) -> String {
+        if value.is_ascii() {
+            value.into()
+        } else {
+            format!("=?UTF-8?B?{}?=", STANDARD.encode(value))
+        }
+    }
+
+    /// Encode bytes as base64 split into lines
+    fn base64_lines(bytes: &[u8]) -> String {
+        let encoded = STANDARD.encode(bytes);
+        let lines: Vec<&str> = (encoded.as_bytes().chunks(76))
+            .map(|line| std::str::from_utf8(line).unwrap())
+            .collect();
+        lines.join("\r\n")
+    }
+
+    fn content_type(name: &str) -> &'static str {
+        let ext = name.rsplit_once('.').map_or("", |(_, ext)| ext);
+        match ext.to_ascii_lowercase().as_str() {
+            "txt" | "ua" => "text/plain; charset=utf-8",
+            "csv" => "text/csv; charset=utf-8",
+            "html" | "htm" => "text/html; charset=utf-8",
+            "json" => "application/json",
+            "pdf" => "application/pdf",
+            "zip" => "application/zip",
+            "png" => "image/png",
+            "jpg" | "jpeg" => "image/jpeg",
+            "gif" => "image/gif",
+            "svg" => "image/svg+xml",
+            "wav" => "audio/wav",
+            "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            _ => "application/octet-stream",
+        }
+    }
+
+    fn message(email: &Email) -> String {
+        let now = OffsetDateTime::now_utc();
+        let domain = mailbox(&email.from)
+            .rsplit_once('@')
+            .map_or("localhost", |(_, d)| d);
+        let mut message = format!(
+            "Date: {}, {:02} {} {} {:02}:{:02}:{:02} +0000\r\n\
+            Message-ID: <{:016x}@{domain}>\r\n\
+            From: {}\r\n",
+            &now.weekday().to_string()[..3],
+            now.day(),
+            &now.month().to_string()[..3],
+            now.year(),
+            now.hour(),
+            now.minute(),
+            now.second(),
+            rand::random::<u64>(),
+            header(&email.from),
+        );
+        for (name, addresses) in [("To", &email.to), ("Cc", &email.cc)] {
+            if !addresses.is_empty() {
+                let addresses: Vec<String> = addresses.iter().map(|a| header(a)).collect();
+                message.push_str(&format!("{name}: {}\r\n", addresses.join(", ")));
+            }
+        }
+        message.push_str(&format!(
+            "Subject: {}\r\nMIME-Version: 1.0\r\n",
+            header(&email.subject)
+        ));
+        let text_part = format!(
+            "Content-Type: text/plain; charset=utf-8\r\n\
+            Content-Transfer-Encoding: base64\r\n\r\n{}\r\n",
+            base64_lines(email.body.as_bytes())
+        );
+        if email.attachments.is_empty() {
+            message.push_str(&text_part);
+            return message;
+        }
+        let boundary = format!("uiua-{:016x}", rand::random::<u64>());
+        message.push_str(&format!(
+            "Content-Type: multipart/mixed; boundary=\"{boundary}\"\r\n\r\n\
+            --{boundary}\r\n{text_part}"
+        ));
+        for (name, contents) in &email.attachments {
+            let ty = content_type(name);
+            let name = header(name).replace('"', "");
+            message.push_str(&format!(
+                "--{boundary}\r\n\
+                Content-Type: {ty}; name=\"{name}\"\r\n\
+                Content-Disposition: attachment; filename=\"{name}\"\r\n\
+                Content-Transfer-Encoding: base64\r\n\r\n{}\r\n",
+                base64_lines(contents)
+            ));
+        }
+        message.push_str(&format!("--{boundary}--\r\n"));
+        message
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn header_injection() {
+        use crate::*;
+        for (key, value) in [
+            ("from", r#""a@example.com\r\nBcc: x@example.com""#),
+            ("to", r#""a@example.com>\r\nRCPT TO:<x@example.com""#),
+            ("cc", r#"□{"b@example.com" "a@example.com\n"}"#),
+            ("bcc", r#""a@example.com\0""#),
+            ("subject", r#""Hi\r\nBcc: x@example.com""#),
+            ("attachments", r#"□map {"a\r\nb.txt"} {"x"}"#),
+        ] {
+            let code = format!(
+                r#"
+                # Experimental!
+                Message ← insert "{key}" {value} map {{"from" "to"}} {{"a@example.com" "b@example.com"}}
+                &smtpsend Message [] "localhost"
+                "#
+            );
+            let Err(err) = Uiua::with_safe_sys().run_str(&code) else {
+                panic!("Line break in {key} was not rejected");
+            };
+            assert!(err.to_string().contains("line breaks"), "{key}: {err}");
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "smtp")]
+    fn smtp_send() {
+        use crate::*;
+        use std::{
+            io::{BufRead, BufReader, Write},
+            net::TcpListener,
+            thread,
+        };
+        // A server that accepts everything and records what it receives
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut received = String::new();
+            stream.write_all(b"220 test\r\n").unwrap();
+            let mut in_data = false;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap() == 0 {
+                    break;
+                }
+                received.push_str(&line);
+                let reply: &[u8] = if in_data {
+                    if line != ".\r\n" {
+                        continue;
+                    }
+                    in_data = false;
+                    b"250 queued\r\n"
+                } else if line.starts_with("EHLO") {
+                    b"250-test\r\n250 AUTH LOGIN PLAIN\r\n"
+                } else if line.starts_with("AUTH") {
+                    b"235 ok\r\n"
+                } else if line.starts_with("DATA") {
+                    in_data = true;
+                    b"354 go\r\n"
+                } else if line.starts_with("QUIT") {
+                    stream.write_all(b"221 bye\r\n").unwrap();
+                    break;
+                } else {
+                    b"250 ok\r\n"
+                };
+                stream.write_all(reply).unwrap();
+            }
+            received
+        });
+        let mut env = Uiua::with_native_sys();
+        let code = format!(
+            r#"
+            # Experimental!
+            Message ← map {{"from" "to" "bcc" "subject" "body" "attachments"}} {{
+              "Uiua <uiua@example.com>" {{"a@example.com" "b@example.com"}} "c@example.com"
+              "Report" "See attached" map {{"data.csv"}} {{"1,2"}}
+            }}
+            Auth ← map {{"username" "password"}} {{"user" "pass"}}
+            &smtpsend Message Auth map {{"address" "security"}} {{"{addr}" "none"}}
+            "#
+        );
+        env.run_str(&code).unwrap();
+        let received = server.join().unwrap();
+        assert!(received.contains("AUTH PLAIN AHVzZXIAcGFzcw==\r\n"));
+        assert!(received.contains("MAIL FROM:<uiua@example.com>\r\n"));
+        for to in ["a", "b", "c"] {
+            assert!(received.contains(&format!("RCPT TO:<{to}@example.com>\r\n")));
+        }
+        assert!(received.contains("To: a@example.com, b@example.com\r\n"));
+        // The Bcc recipient only appears in the envelope
+        let (_, data) = received.split_once("DATA\r\n").unwrap();
+        let (headers, _) = data.split_once("\r\n\r\n").unwrap();
+        // Unfold long headers so that each one is on a single line
+        let headers = headers.replace("\r\n ", " ").replace("\r\n\t", " ");
+        for header in headers.split("\r\n") {
+            assert!(!header.to_lowercase().starts_with("bcc:"), "{header}");
+            if ["to:", "cc:"]
+                .iter()
+                .any(|name| header.to_lowercase().starts_with(name))
+            {
+                assert!(!header.contains("c@example.com"), "{header}");
+            }
+        }
+        assert!(received.contains("Subject: Report\r\n"));
+        assert!(received.contains("U2VlIGF0dGFjaGVk\r\n"));
+        assert!(received.contains("filename=\"data.csv\""));
+        assert!(received.contains("MSwy\r\n"));
+    }
+}
@@ -1,8 +1,8 @@
-use std::{array, cmp::Ordering, mem::take};
+use std::{array, cmp::Ordering, collections::HashMap, fmt, mem::take};
 
 use crate::{
-    cowslice::CowSlice, Array, Assembly, Boxed, Complex, ImplPrimitive, Node, PersistentMeta,
-    Primitive, Shape, SigNode, Uiua, Value,
+    cowslice::CowSlice, Array, Assembly, BindingKind, Boxed, Complex, ImplPrimitive, Node,
+    PersistentMeta, Primitive, Shape, SigNode, Uiua, Value,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -13,6 +13,17 @@ enum ScalarType {
     Box(Option<Box<Ty>>),
 }
 
+impl ScalarType {
+    fn name(&self) -> &'static str {
+        match self {
+            ScalarType::Real => "number",
+            ScalarType::Complex => "complex",
+            ScalarType::Char => "character",
+            ScalarType::Box(_) => "box",
+        }
+    }
+}
+
 impl Value {
     fn scalar_ty(&self) -> ScalarType {
         match self {
@@ -58,10 +69,22 @@ impl Ty {
     }
 }
 
+impl fmt::Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.shape.is_empty() {
+            write!(f, "{}", self.scalar.name())
+        } else {
+            write!(f, "{} {}", self.shape, self.scalar.name())
+        }
+    }
+}
+
 enum TypeError {
     StackUnderflow,
     NotSupported,
     Other,
+    /// A mismatch that is certain to cause an error, found while checking
+    Mismatch(usize, String),
 }
 
 fn make_val(mut ty: Ty) -> Value {
@@ -107,6 +130,7 @@ where
         stack,
         under_stack: Vec::new(),
         asm: &env.asm,
+        check: false,
     };
     match rt.node(&f.node) {
         Ok(()) => {
@@ -129,10 +153,176 @@ where
     }
 }
 
+/// The results of statically checking the types and shapes of code
+#[derive(Default)]
+pub(crate) struct TypeCheck {
+    /// Mismatches that are certain to cause errors, with their span indices
+    pub mismatches: Vec<(usize, String)>,
+    /// The inferred output types of nodes by span index
+    ///
+    /// This is `None` if the node's outputs are not known or differ between runs
+    pub types: HashMap<usize, Option<String>>,
+}
+
+/// Statically check top-level code for type and shape mismatches
+///
+/// This is conservative. Values whose types cannot be known are skipped.
+pub(crate) fn check_types(nodes: &[Node], asm: &Assembly) -> TypeCheck {
+    let mut checker = TypeChecker {
+        asm,
+        stack: Vec::new(),
+        under_stack: Vec::new(),
+        globals: HashMap::new(),
+        call_stack: Vec::new(),
+        results: TypeCheck::default(),
+    };
+    for node in nodes {
+        checker.node(node);
+    }
+    checker.results
+}
+
+struct TypeChecker<'a> {
+    asm: &'a Assembly,
+    /// Values whose types are not known are `None`
+    stack: Vec<Option<Ty>>,
+    under_stack: Vec<Option<Ty>>,
+    globals: HashMap<usize, Option<Ty>>,
+    call_stack: Vec<usize>,
+    results: TypeCheck,
+}
+
+impl TypeChecker<'_> {
+    fn node(&mut self, node: &Node) {
+        match node {
+            Node::Run(nodes) => {
+                for node in nodes {
+                    self.node(node);
+                }
+            }
+            Node::Push(val) => self.stack.push(Some(val.ty())),
+            &Node::BindGlobal { index, .. } => {
+                let ty = self.pop();
+                self.globals.insert(index, ty);
+            }
+            &Node::CallGlobal(index, sig) => {
+                for _ in 0..sig.args {
+                    self.pop();
+                }
+                let ty = match self.globals.get(&index) {
+                    Some(ty) => ty.clone(),
+                    None => match &self.asm.bindings[index].kind {
+                        BindingKind::Const(Some(val)) => Some(val.ty()),
+                        _ => None,
+                    },
+                };
+                if sig.outputs == 1 {
+                    self.stack.push(ty);
+                } else {
+                    self.push_unknown(sig.outputs);
+                }
+            }
+            Node::Call(f, span) if !self.call_stack.contains(&f.index) => {
+                self.call_stack.push(f.index);
+                self.node(&self.asm[f]);
+                self.call_stack.pop();
+                self.record(*span, f.sig.outputs);
+            }
+            Node::Call(f, _) => {
+                // Recursion is not followed
+                for _ in 0..f.sig.args {
+                    self.pop();
+                }
+                self.push_unknown(f.sig.outputs);
+            }
+            Node::NoInline(inner) | Node::TrackCaller(inner) => self.node(inner),
+            &Node::PushUnder(n, _) => {
+                for _ in 0..n {
+                    let ty = self.pop();
+                    self.under_stack.push(ty);
+                }
+            }
+            &Node::CopyToUnder(n, _) => {
+                for _ in 0..n {
+                    let ty = self.pop();
+                    self.under_stack.push(ty);
+                }
+                let copied: Vec<_> = self.under_stack.iter().rev().take(n).cloned().collect();
+                self.stack.extend(copied);
+            }
+            &Node::PopUnder(n, _) => {
+                for _ in 0..n {
+                    let ty = self.under_stack.pop().flatten();
+                    self.stack.push(ty);
+                }
+            }
+            node => self.leaf(node),
+        }
+    }
+    /// Check a node whose arguments are all checked at once
+    fn leaf(&mut self, node: &Node) {
+        let Ok(sig) = node.sig() else {
+            // The stack can no longer be tracked
+            self.stack.clear();
+            self.under_stack.clear();
+            return;
+        };
+        let args: Vec<Option<Ty>> = (0..sig.args).map(|_| self.pop()).collect();
+        let outputs = if let Some(mut stack) = args.into_iter().collect::<Option<Vec<_>>>() {
+            stack.reverse();
+            let mut rt = TypeRt {
+                stack,
+                under_stack: Vec::new(),
+                asm: self.asm,
+                check: true,
+            };
+            match rt.node(node) {
+                Ok(()) if rt.stack.len() == sig.outputs => Some(rt.stack),
+                Err(TypeError::Mismatch(span, message)) => {
+                    self.results.mismatches.push((span, message));
+                    None
+                }
+                _ => None,
+            }
+        } else {
+            None
+        };
+        match outputs {
+            Some(tys) => self.stack.extend(tys.into_iter().map(Some)),
+            None => self.push_unknown(sig.outputs),
+        }
+        if let Some(span) = node.span() {
+            self.record(span, sig.outputs);
+        }
+    }
+    /// Record the types of a node's outputs
+    fn record(&mut self, span: usize, outputs: usize) {
+        if outputs == 0 || self.stack.len() < outputs {
+            return;
+        }
+        let tys: Option<Vec<String>> = (self.stack.iter().rev().take(outputs))
+            .map(|ty| ty.as_ref().map(Ty::to_string))
+            .collect();
+        let tys = tys.map(|tys| tys.join(", "));
+        let entry = (self.results.types.entry(span)).or_insert_with(|| tys.clone());
+        if *entry != tys {
+            *entry = None;
+        }
+    }
+    fn pop(&mut self) -> Option<Ty> {
+        self.stack.pop().flatten()
+    }
+    fn push_unknown(&mut self, n: usize) {
+        self.stack.extend((0..n).map(|_| None));
+    }
+}
+
 struct TypeRt<'a> {
     stack: Vec<Ty>,
     under_stack: Vec<Ty>,
     asm: &'a Assembly,
+    /// Whether to check for mismatches rather than compute row types
+    check: bool,
 }
 
 impl TypeRt<'_> {
@@ -140,8 +330,15 @@ impl TypeRt<'_> {
     fn node(&mut self, node: &Node) -> Result<(), TypeError> {
         use Primitive::*;
         match node {
+            Node::Run(nodes) if self.check => {
+                for node in nodes {
+                    self.node(node)?;
+                }
+            }
+            Node::Push(val) if self.check => self.stack.push(val.ty()),
             Node::Push(val) => self.stack.push(val.row_ty()),
             Node::Call(f, _) => self.node(&self.asm[f])?,
+            &Node::Prim(prim, span) if self.check && self.check_prim(prim, span)? => {}
             Node::Prim(prim, _) => match prim {
                 Dup => {
                     let val = self.pop()?;
@@ -300,6 +497,136 @@ impl TypeRt<'_> {
         }
         Ok(())
     }
+    /// Check a primitive for mismatches
+    ///
+    /// Returns whether the primitive was handled
+    fn check_prim(&mut self, prim: Primitive, span: usize) -> Result<bool, TypeError> {
+        use Primitive::*;
+        use ScalarType::{Char, Real};
+        let mismatch = |message: String| Err(TypeError::Mismatch(span, message));
+        match prim {
+            Rand => self.stack.push(Ty::new(Real, [])),
+            Not | Sqrt | Floor | Ceil | Round => {
+                let x = self.pop()?;
+                if x.scalar == Char {
+                    let op = match prim {
+                        Not => "not",
+                        Sqrt => "take the square root of",
+                        Floor => "get the floor of",
+                        Ceil => "get the ceiling of",
+                        _ => "get the rounded value of",
+                    };
+                    return mismatch(format!("Cannot {op} character"));
+                }
+                self.stack.push(x);
+            }
+            Add | Sub | Modulus | Log | Atan | Max | Min | Eq | Ne | Lt | Le | Gt | Ge => {
+                let a = self.pop()?;
+                let b = self.pop()?;
+                if matches!(a.scalar, ScalarType::Box(_)) || matches!(b.scalar, ScalarType::Box(_))
+                {
+                    return Err(TypeError::NotSupported);
+                }
+                let (an, bn) = (a.scalar.name(), b.scalar.name());
+                let chars = (a.scalar == Char, b.scalar == Char);
+                let message = match prim {
+                    Add if chars.0 && b.scalar != Real || chars.1 && a.scalar != Real => {
+                        Some(format!("Cannot add {an} and {bn}"))
+                    }
+                    Sub if chars.0 && !chars.1 || chars.1 && a.scalar == ScalarType::Complex => {
+                        Some(format!("Cannot subtract {an} from {bn}"))
+                    }
+                    Modulus if chars.0 || chars.1 => Some(format!("Cannot modulo {an} and {bn}")),
+                    Log if chars.0 || chars.1 => {
+                        Some(format!("Cannot get the log base {an} of {bn}"))
+                    }
+                    Atan if chars.0 || chars.1 => {
+                        Some(format!("Cannot get the atan2 of {an} and {bn}"))
+                    }
+                    Max | Min if chars.0 != chars.1 => {
+                        let op = if prim == Max { "max" } else { "min" };
+                        Some(format!("Cannot get the {op} of {an} and {bn}"))
+                    }
+                    _ => None,
+                };
+                if let Some(message) = message {
+                    return mismatch(message);
+                }
+                let compatible =
+                    (a.shape.iter().zip(&b.shape)).all(|(&a, &b)| a == b || a == 1 || b == 1);
+                if !compatible {
+                    return mismatch(format!(
+                        "Shapes {} and {} are not compatible",
+                        a.shape, b.shape
+                    ));
+                }
+                let scalar = match prim {
+                    Add if chars.0 || chars.1 => Char,
+                    Sub if chars.0 && chars.1 => Real,
+                    Sub if chars.1 => Char,
+                    Eq | Ne | Lt | Le | Gt | Ge => Real,
+                    _ => a.scalar.max(b.scalar),
+                };
+                let mut shape = Vec::new();
+                for i in 0..a.shape.len().max(b.shape.len()) {
+                    let dim = match (a.shape.get(i), b.shape.get(i)) {
+                        (Some(1), Some(&b)) => b,
+                        (Some(&a), _) | (None, Some(&a)) => a,
+                        (None, None) => unreachable!(),
+                    };
+                    shape.push(dim);
+                }
+                self.stack.push(Ty::new(scalar, shape));
+            }
+            Couple | Join => {
+                let a = self.pop()?;
+                let b = self.pop()?;
+                let boxed = matches!(a.scalar, ScalarType::Box(_))
+                    || matches!(b.scalar, ScalarType::Box(_));
+                if boxed {
+                    self.stack.push(b);
+                    self.stack.push(a);
+                    return Ok(false);
+                }
+                let op = if prim == Couple { "couple" } else { "join" };
+                if (a.scalar == Char) != (b.scalar == Char) {
+                    let conj = if prim == Couple { "with" } else { "and" };
+                    return mismatch(format!(
+                        "Cannot {op} {} array {conj} {} array",
+                        a.scalar.name(),
+                        b.scalar.name()
+                    ));
+                }
+                let compatible = if a.shape.is_empty() || b.shape.is_empty() {
+                    true
+                } else if prim == Couple {
+                    a.shape.len() != b.shape.len() || a.shape == b.shape
+                } else {
+                    match a.shape.len().abs_diff(b.shape.len()) {
+                        0 => a.shape[1..] == b.shape[1..],
+                        1 if a.shape.len() < b.shape.len() => a.shape[..] == b.shape[1..],
+                        1 => a.shape[1..] == b.shape[..],
+                        _ => return Err(TypeError::NotSupported),
+                    }
+                };
+                if !compatible {
+                    return mismatch(if prim == Couple {
+                        format!(
+                            "Cannot couple arrays with shapes {} and {}",
+                            a.shape, b.shape
+                        )
+                    } else {
+                        format!("Cannot join arrays of shapes {} and {}", a.shape, b.shape)
+                    });
+                }
+                self.stack.push(b);
+                self.stack.push(a);
+                return Ok(false);
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
     fn pop(&mut self) -> Result<Ty, TypeError> {
         self.stack.pop().ok_or(TypeError::StackUnderflow)
     }
@@ -314,3 +641,40 @@ fn get_args<const N: usize>(args: &[SigNode]) -> Result<[&SigNode; N], TypeError
     }
     Ok(array::from_fn(|i| &args[i]))
 }
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn type_check() {
+        use crate::*;
+        let code = "\
+X ← [1 2]
+⊟ X [1 2 3]
++@a @b
+⬚0⊟ [1 2] [1 2 3]
+⍣(-@a 1)0
+⊟ [1 2 3] ⊟⚂⚂
+▽ 2⊂ X ⚂";
+        let mut comp = Compiler::new();
+        comp.type_check(true).load_str(code).unwrap();
+        let messages: Vec<_> = (comp.take_diagnostics().into_iter())
+            .filter(|diag| diag.kind == DiagnosticKind::Warning)
+            .map(|diag| diag.message)
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "Cannot couple arrays with shapes [2] and [3]",
+                "Cannot add character and character",
+                "Cannot couple arrays with shapes [3] and [2]",
+            ]
+        );
+        let ty = |line: u16, col: u16| {
+            (comp.code_meta().inferred_types.iter())
+                .find(|(span, _)| span.start.line == line && span.start.col == col)
+                .map(|(_, ty)| ty.as_str())
+        };
+        assert_eq!(ty(7, 4), Some("[3] number"));
+        assert_eq!(ty(7, 1), None);
+    }
+}
@@ -0,0 +1,782 @@
+//! Execution of WebAssembly code
+
+use std::sync::Arc;
+
+use super::parse::*;
+
+const PAGE_SIZE: usize = 0x10000;
+/// The most memory a module can have, which is less than the 4 GiB allowed by the spec
+const MAX_PAGES: u32 = if cfg!(target_pointer_width = "32") {
+    0x1000
+} else {
+    0x4000
+};
+/// The most elements a table can have
+const MAX_TABLE_LEN: u32 = 0x10_0000;
+const MAX_CALL_DEPTH: usize = 1000;
+/// The number of instructions executed between checks for interruption
+const CHECK_INTERVAL: u64 = 0x10000;
+
+/// A function that checks whether execution should stop
+pub(super) type Interrupted<'a> = &'a dyn Fn() -> bool;
+
+/// An instantiated module
+///
+/// All values are stored as 64-bit integers.
+/// 32-bit values are zero-extended, and floats are stored as their bits.
+pub(super) struct Instance {
+    pub module: Arc<Module>,
+    pub memory: Vec<u8>,
+    max_pages: u32,
+    globals: Vec<u64>,
+    tables: Vec<Vec<Option<u32>>>,
+    data: Vec<Option<Vec<u8>>>,
+    depth: usize,
+    steps: u64,
+}
+
+struct Label {
+    /// The instruction to continue at after branching
+    cont: usize,
+    /// The stack height at the start of the block
+    height: usize,
+    /// The number of values a branch keeps
+    arity: usize,
+    is_loop: bool,
+}
+
+impl Instance {
+    pub fn new(module: Module, interrupted: Interrupted) -> Result<Self, String> {
+        let module = Arc::new(module);
+        let (memory, max_pages) = match module.memory {
+            Some(limits) => {
+                if limits.min > MAX_PAGES {
+                    return Err("WASM memory is too large".into());
+                }
+                (
+                    vec![0; limits.min as usize * PAGE_SIZE],
+                    limits.max.unwrap_or(MAX_PAGES).min(MAX_PAGES),
+                )
+            }
+            None => (Vec::new(), 0),
+        };
+        if module
+            .tables
+            .iter()
+            .any(|limits| limits.min > MAX_TABLE_LEN)
+        {
+            return Err("WASM table is too large".into());
+        }
+        let tables = (module.tables.iter())
+            .map(|limits| vec![None; limits.min as usize])
+            .collect();
+        let mut instance = Instance {
+            module: module.clone(),
+            memory,
+            max_pages,
+            globals: Vec::with_capacity(module.globals.len()),
+            tables,
+            data: Vec::with_capacity(module.data.len()),
+            depth: 0,
+            steps: 0,
+        };
+        for global in &module.globals {
+            let value = instance.eval(global.init)?;
+            instance.globals.push(value);
+        }
+        for seg in &module.elements {
+            if let Some((table, offset)) = seg.active {
+                let offset = instance.eval(offset)? as u32 as usize;
+                let table = &mut instance.tables[table as usize];
+                let dest = (table.get_mut(offset..offset + seg.items.len()))
+                    .ok_or("WASM element segment does not fit in its table")?;
+                dest.copy_from_slice(&seg.items);
+            }
+        }
+        for seg in &module.data {
+            if let Some((_, offset)) = seg.active {
+                let offset = instance.eval(offset)? as u32 as usize;
+                let dest = (instance.memory.get_mut(offset..offset + seg.items.len()))
+                    .ok_or("WASM data segment does not fit in memory")?;
+                dest.copy_from_slice(&seg.items);
+                instance.data.push(None);
+            } else {
+                instance.data.push(Some(seg.items.clone()));
+            }
+        }
+        if let Some(start) = module.start {
+            instance.invoke(start, &[], interrupted)?;
+        }
+        Ok(instance)
+    }
+    fn eval(&self, expr: ConstExpr) -> Result<u64, String> {
+        Ok(match expr {
+            ConstExpr::Value(value) => value,
+            ConstExpr::GlobalGet(i) => *(self.globals.get(i as usize))
+                .ok_or("WASM global initializer refers to a later global")?,
+            ConstExpr::RefFunc(i) => i as u64,
+        })
+    }
+    /// Get the type of a function
+    pub fn func_type(&self, func: u32) -> &FuncType {
+        &self.module.types[self.module.funcs[func as usize].ty as usize]
+    }
+    /// Call a function with some arguments
+    pub fn invoke(
+        &mut self,
+        func: u32,
+        args: &[u64],
+        interrupted: Interrupted,
+    ) -> Result<Vec<u64>, String> {
+        let mut stack = args.to_vec();
+        self.depth = 0;
+        self.call(func, &mut stack, interrupted)
+            .map_err(|e| format!("WASM trap: {e}"))?;
+        Ok(stack)
+    }
+    fn call(
+        &mut self,
+        func: u32,
+        stack: &mut Vec<u64>,
+        interrupted: Interrupted,
+    ) -> Result<(), String> {
+        if self.depth >= MAX_CALL_DEPTH {
+            return Err("call stack exhausted".into());
+        }
+        self.depth += 1;
+        let module = self.module.clone();
+        let func = &module.funcs[func as usize];
+        let ty = &module.types[func.ty as usize];
+        let base = height(stack, ty.params.len())?;
+        let mut locals: Vec<u64> = stack.drain(base..).collect();
+        locals.extend(func.locals.iter().map(ValType::default_value));
+        let code = &func.code;
+        let mut labels = vec![Label {
+            cont: code.len(),
+            height: base,
+            arity: ty.results.len(),
+            is_loop: false,
+        }];
+        let mut pc = 0;
+        macro_rules! pop {
+            () => {
+                stack.pop().ok_or("stack underflow")?
+            };
+        }
+        while pc < code.len() {
+            self.steps += 1;
+            if self.steps % CHECK_INTERVAL == 0 && interrupted() {
+                return Err("interrupted".into());
+            }
+            match &code[pc] {
+                Instr::Unreachable => return Err("unreachable".into()),
+                Instr::Nop => {}
+                Instr::Block { ty, end } => {
+                    let (params, results) = self.block_arity(ty);
+                    labels.push(Label {
+                        cont: end + 1,
+                        height: height(stack, params)?,
+                        arity: results,
+                        is_loop: false,
+                    });
+                }
+                Instr::Loop { ty } => {
+                    let (params, _) = self.block_arity(ty);
+                    labels.push(Label {
+                        cont: pc + 1,
+                        height: height(stack, params)?,
+                        arity: params,
+                        is_loop: true,
+                    });
+                }
+                Instr::If { ty, els, end } => {
+                    let cond = pop!() as u32;
+                    if cond == 0 && els == end {
+                        pc = end + 1;
+                        continue;
+                    }
+                    let (params, results) = self.block_arity(ty);
+                    labels.push(Label {
+                        cont: end + 1,
+                        height: height(stack, params)?,
+                        arity: results,
+                        is_loop: false,
+                    });
+                    if cond == 0 {
+                        pc = *els;
+                    }
+                }
+                Instr::Else { end } => {
+                    pc = *end;
+                    continue;
+                }
+                Instr::End => {
+                    labels.pop();
+                }
+                Instr::Br(depth) => {
+                    pc = branch(&mut labels, stack, *depth)?;
+                    continue;
+                }
+                Instr::BrIf(depth) => {
+                    if pop!() as u32 != 0 {
+                        pc = branch(&mut labels, stack, *depth)?;
+                        continue;
+                    }
+                }
+                Instr::BrTable(targets) => {
+                    let i = (pop!() as u32 as usize).min(targets.len() - 1);
+                    pc = branch(&mut labels, stack, targets[i])?;
+                    continue;
+                }
+                Instr::Return => {
+                    pc = branch(&mut labels, stack, u32::MAX)?;
+                    continue;
+                }
+                Instr::Call(f) => self.call(*f, stack, interrupted)?,
+                Instr::CallIndirect { ty, table } => {
+                    let i = pop!() as u32 as usize;
+                    let f = (self.tables[*table as usize].get(i))
+                        .ok_or("undefined element")?
+                        .ok_or("uninitialized element")?;
+                    if f as usize >= module.funcs.len()
+                        || self.func_type(f) != &module.types[*ty as usize]
+                    {
+                        return Err("indirect call type mismatch".into());
+                    }
+                    self.call(f, stack, interrupted)?
+                }
+                Instr::Drop => _ = pop!(),
+                Instr::Select => {
+                    let cond = pop!() as u32;
+                    let b = pop!();
+                    let a = pop!();
+                    stack.push(if cond != 0 { a } else { b });
+                }
+                Instr::LocalGet(i) => stack.push(locals[*i as usize]),
+                Instr::LocalSet(i) => locals[*i as usize] = pop!(),
+                Instr::LocalTee(i) => {
+                    locals[*i as usize] = *stack.last().ok_or("stack underflow")?
+                }
+                Instr::GlobalGet(i) => stack.push(self.globals[*i as usize]),
+                Instr::GlobalSet(i) => {
+                    if !module.globals[*i as usize].mutable {
+                        return Err("assignment to immutable global".into());
+                    }
+                    self.globals[*i as usize] = pop!()
+                }
+                Instr::TableGet(t) => {
+                    let i = pop!() as u32 as usize;
+                    let f =
+                        (self.tables[*t as usize].get(i)).ok_or("out of bounds table access")?;
+                    stack.push(f.map_or(NULL_REF, |f| f as u64));
+                }
+                Instr::TableSet(t) => {
+                    let f = pop!();
+                    let i = pop!() as u32 as usize;
+                    let entry = (self.tables[*t as usize].get_mut(i))
+                        .ok_or("out of bounds table access")?;
+                    *entry = (f != NULL_REF).then_some(f as u32);
+                }
+                Instr::Load { op, offset } => {
+                    let addr = pop!() as u32 as usize + *offset as usize;
+                    let value = self.load(*op, addr)?;
+                    stack.push(value);
+                }
+                Instr::Store { op, offset } => {
+                    let value = pop!();
+                    let addr = pop!() as u32 as usize + *offset as usize;
+                    self.store(*op, addr, value)?;
+                }
+                Instr::MemorySize => stack.push((self.memory.len() / PAGE_SIZE) as u64),
+                Instr::MemoryGrow => {
+                    let delta = pop!() as u32;
+                    let pages = (self.memory.len() / PAGE_SIZE) as u32;
+                    if pages
+                        .checked_add(delta)
+                        .is_some_and(|n| n <= self.max_pages)
+                    {
+                        self.memory.resize((pages + delta) as usize * PAGE_SIZE, 0);
+                        stack.push(pages as u64);
+                    } else {
+                        stack.push(u32::MAX as u64);
+                    }
+                }
+                Instr::Const(value) => stack.push(*value),
+                Instr::Numeric(op) => numeric(*op, stack)?,
+                Instr::TruncSat(op) => {
+                    let x = pop!();
+                    let (f32x, f64x) = (f32::from_bits(x as u32), f64::from_bits(x));
+                    stack.push(match op {
+                        0 => f32x as i32 as u32 as u64,
+                        1 => f32x as u32 as u64,
+                        2 => f64x as i32 as u32 as u64,
+                        3 => f64x as u32 as u64,
+                        4 => f32x as i64 as u64,
+                        5 => f32x as u64,
+                        6 => f64x as i64 as u64,
+                        _ => f64x as u64,
+                    });
+                }
+                Instr::MemoryInit(d) => {
+                    let n = pop!() as u32 as usize;
+                    let src = pop!() as u32 as usize;
+                    let dest = pop!() as u32 as usize;
+                    let data = self.data[*d as usize].as_deref().unwrap_or_default();
+                    let src = (data.get(src..src + n)).ok_or("out of bounds memory access")?;
+                    let dest = (self.memory.get_mut(dest..dest + n))
+                        .ok_or("out of bounds memory access")?;
+                    dest.copy_from_slice(src);
+                }
+                Instr::DataDrop(d) => self.data[*d as usize] = None,
+                Instr::MemoryCopy => {
+                    let n = pop!() as u32 as usize;
+                    let src = pop!() as u32 as usize;
+                    let dest = pop!() as u32 as usize;
+                    if src + n > self.memory.len() || dest + n > self.memory.len() {
+                        return Err("out of bounds memory access".into());
+                    }
+                    self.memory.copy_within(src..src + n, dest);
+                }
+                Instr::MemoryFill => {
+                    let n = pop!() as u32 as usize;
+                    let value = pop!() as u8;
+                    let dest = pop!() as u32 as usize;
+                    (self.memory.get_mut(dest..dest + n))
+                        .ok_or("out of bounds memory access")?
+                        .fill(value);
+                }
+                Instr::RefNull => stack.push(NULL_REF),
+                Instr::RefIsNull => {
+                    let r = pop!();
+                    stack.push((r == NULL_REF) as u64);
+                }
+                Instr::RefFunc(f) => stack.push(*f as u64),
+            }
+            pc += 1;
+        }
+        self.depth -= 1;
+        Ok(())
+    }
+    fn block_arity(&self, ty: &BlockType) -> (usize, usize) {
+        match ty {
+            BlockType::Empty => (0, 0),
+            BlockType::Value => (0, 1),
+            BlockType::Func(i) => {
+                let ty = &self.module.types[*i as usize];
+                (ty.params.len(), ty.results.len())
+            }
+        }
+    }
+    fn bytes<const N: usize>(&self, addr: usize) -> Result<[u8; N], String> {
+        let bytes = (self.memory.get(addr..addr + N)).ok_or("out of bounds memory access")?;
+        Ok(bytes.try_into().unwrap())
+    }
+    fn load(&self, op: u8, addr: usize) -> Result<u64, String> {
+        Ok(match op {
+            0x28 | 0x2a | 0x35 => u32::from_le_bytes(self.bytes(addr)?) as u64,
+            0x29 | 0x2b => u64::from_le_bytes(self.bytes(addr)?),
+            0x2c => i8::from_le_bytes(self.bytes(addr)?) as i32 as u32 as u64,
+            0x2d | 0x31 => u8::from_le_bytes(self.bytes(addr)?) as u64,
+            0x2e => i16::from_le_bytes(self.bytes(addr)?) as i32 as u32 as u64,
+            0x2f | 0x33 => u16::from_le_bytes(self.bytes(addr)?) as u64,
+            0x30 => i8::from_le_bytes(self.bytes(addr)?) as u64,
+            0x32 => i16::from_le_bytes(self.bytes(addr)?) as u64,
+            _ => i32::from_le_bytes(self.bytes(addr)?) as u64,
+        })
+    }
+    fn store(&mut self, op: u8, addr: usize, value: u64) -> Result<(), String> {
+        let len = match op {
+            0x37 | 0x39 => 8,
+            0x36 | 0x38 | 0x3e => 4,
+            0x3b | 0x3d => 2,
+            _ => 1,
+        };
+        let dest = (self.memory.get_mut(addr..addr + len)).ok_or("out of bounds memory access")?;
+        dest.copy_from_slice(&value.to_le_bytes()[..len]);
+        Ok(())
+    }
+}
+
+/// Get the height of the stack below some values
+fn height(stack: &[u64], values: usize) -> Result<usize, String> {
+    (stack.len().checked_sub(values)).ok_or_else(|| "stack underflow".into())
+}
+
+/// Branch to a label, returning the instruction to continue at
+fn branch(labels: &mut Vec<Label>, stack: &mut Vec<u64>, depth: u32) -> Result<usize, String> {
+    let i = labels.len().saturating_sub(depth as usize + 1);
+    let label = &labels[i];
+    let keep = height(stack, label.arity)?;
+    if keep < label.height {
+        return Err("stack underflow".into());
+    }
+    stack.drain(label.height..keep);
+    let cont = label.cont;
+    labels.truncate(i + label.is_loop as usize);
+    Ok(cont)
+}
+
+fn numeric(op: u8, stack: &mut Vec<u64>) -> Result<(), String> {
+    macro_rules! pop {
+        () => {
+            stack.pop().ok_or("stack underflow")?
+        };
+    }
+    macro_rules! un {
+        ($from:ident, $to:ident, $f:expr) => {{
+            let a = $from(pop!());
+            stack.push($to($f(a)));
+        }};
+    }
+    macro_rules! un_try {
+        ($from:ident, $to:ident, $f:expr) => {{
+            let a = $from(pop!());
+            stack.push($to($f(a)?));
+        }};
+    }
+    macro_rules! bin {
+        ($from:ident, $to:ident, $f:expr) => {{
+            let b = $from(pop!());
+            let a = $from(pop!());
+            stack.push($to($f(a, b)));
+        }};
+    }
+    macro_rules! bin_try {
+        ($from:ident, $to:ident, $f:expr) => {{
+            let b = $from(pop!());
+            let a = $from(pop!());
+            stack.push($to($f(a, b)?));
+        }};
+    }
+    match op {
+        // Comparisons
+        0x45 => un!(i32_, bool_, |a: i32| a == 0),
+        0x46 => bin!(i32_, bool_, |a, b| a == b),
+        0x47 => bin!(i32_, bool_, |a, b| a != b),
+        0x48 => bin!(i32_, bool_, |a, b| a < b),
+        0x49 => bin!(u32_, bool_, |a, b| a < b),
+        0x4a => bin!(i32_, bool_, |a, b| a > b),
+        0x4b => bin!(u32_, bool_, |a, b| a > b),
+        0x4c => bin!(i32_, bool_, |a, b| a <= b),
+        0x4d => bin!(u32_, bool_, |a, b| a <= b),
+        0x4e => bin!(i32_, bool_, |a, b| a >= b),
+        0x4f => bin!(u32_, bool_, |a, b| a >= b),
+        0x50 => un!(i64_, bool_, |a: i64| a == 0),
+        0x51 => bin!(i64_, bool_, |a, b| a == b),
+        0x52 => bin!(i64_, bool_, |a, b| a != b),
+        0x53 => bin!(i64_, bool_, |a, b| a < b),
+        0x54 => bin!(u64_, bool_, |a, b| a < b),
+        0x55 => bin!(i64_, bool_, |a, b| a > b),
+        0x56 => bin!(u64_, bool_, |a, b| a > b),
+        0x57 => bin!(i64_, bool_, |a, b| a <= b),
+        0x58 => bin!(u64_, bool_, |a, b| a <= b),
+        0x59 => bin!(i64_, bool_, |a, b| a >= b),
+        0x5a => bin!(u64_, bool_, |a, b| a >= b),
+        0x5b => bin!(f32_, bool_, |a, b| a == b),
+        0x5c => bin!(f32_, bool_, |a, b| a != b),
+        0x5d => bin!(f32_, bool_, |a, b| a < b),
+        0x5e => bin!(f32_, bool_, |a, b| a > b),
+        0x5f => bin!(f32_, bool_, |a, b| a <= b),
+        0x60 => bin!(f32_, bool_, |a, b| a >= b),
+        0x61 => bin!(f64_, bool_, |a, b| a == b),
+        0x62 => bin!(f64_, bool_, |a, b| a != b),
+        0x63 => bin!(f64_, bool_, |a, b| a < b),
+        0x64 => bin!(f64_, bool_, |a, b| a > b),
+        0x65 => bin!(f64_, bool_, |a, b| a <= b),
+        0x66 => bin!(f64_, bool_, |a, b| a >= b),
+        // i32 arithmetic
+        0x67 => un!(u32_, from_u32, u32::leading_zeros),
+        0x68 => un!(u32_, from_u32, u32::trailing_zeros),
+        0x69 => un!(u32_, from_u32, u32::count_ones),
+        0x6a => bin!(u32_, from_u32, u32::wrapping_add),
+        0x6b => bin!(u32_, from_u32, u32::wrapping_sub),
+        0x6c => bin!(u32_, from_u32, u32::wrapping_mul),
+        0x6d => bin_try!(i32_, from_i32, |a: i32, b: i32| match b {
+            0 => Err("integer divide by zero"),
+            -1 if a == i32::MIN => Err("integer overflow"),
+            _ => Ok(a / b),
+        }),
+        0x6e => bin_try!(u32_, from_u32, |a: u32, b| a.checked_div(b).ok_or(DIV_ZERO)),
+        0x6f => bin_try!(i32_, from_i32, |a: i32, b| match b {
+            0 => Err(DIV_ZERO),
+            _ => Ok(a.wrapping_rem(b)),
+        }),
+        0x70 => bin_try!(u32_, from_u32, |a: u32, b| a.checked_rem(b).ok_or(DIV_ZERO)),
+        0x71 => bin!(u32_, from_u32, |a, b| a & b),
+        0x72 => bin!(u32_, from_u32, |a, b| a | b),
+        0x73 => bin!(u32_, from_u32, |a, b| a ^ b),
+        0x74 => bin!(u32_, from_u32, |a: u32, b| a.wrapping_shl(b)),
+        0x75 => bin!(i32_, from_i32, |a: i32, b| a.wrapping_shr(b as u32)),
+        0x76 => bin!(u32_, from_u32, |a: u32, b| a.wrapping_shr(b)),
+        0x77 => bin!(u32_, from_u32, |a: u32, b| a.rotate_left(b)),
+        0x78 => bin!(u32_, from_u32, |a: u32, b| a.rotate_right(b)),
+        // i64 arithmetic
+        0x79 => un!(u64_, from_u64, |a: u64| a.leading_zeros() as u64),
+        0x7a => un!(u64_, from_u64, |a: u64| a.trailing_zeros() as u64),
+        0x7b => un!(u64_, from_u64, |a: u64| a.count_ones() as u64),
+        0x7c => bin!(u64_, from_u64, u64::wrapping_add),
+        0x7d => bin!(u64_, from_u64, u64::wrapping_sub),
+        0x7e => bin!(u64_, from_u64, u64::wrapping_mul),
+        0x7f => bin_try!(i64_, from_i64, |a: i64, b: i64| match b {
+            0 => Err("integer divide by zero"),
+            -1 if a == i64::MIN => Err("integer overflow"),
+            _ => Ok(a / b),
+        }),
+        0x80 => bin_try!(u64_, from_u64, |a: u64, b| a.checked_div(b).ok_or(DIV_ZERO)),
+        0x81 => bin_try!(i64_, from_i64, |a: i64, b| match b {
+            0 => Err(DIV_ZERO),
+            _ => Ok(a.wrapping_rem(b)),
+        }),
+        0x82 => bin_try!(u64_, from_u64, |a: u64, b| a.checked_rem(b).ok_or(DIV_ZERO)),
+        0x83 => bin!(u64_, from_u64, |a, b| a & b),
+        0x84 => bin!(u64_, from_u64, |a, b| a | b),
+        0x85 => bin!(u64_, from_u64, |a, b| a ^ b),
+        0x86 => bin!(u64_, from_u64, |a: u64, b| a.wrapping_shl(b as u32)),
+        0x87 => bin!(i64_, from_i64, |a: i64, b| a.wrapping_shr(b as u32)),
+        0x88 => bin!(u64_, from_u64, |a: u64, b| a.wrapping_shr(b as u32)),
+        0x89 => bin!(u64_, from_u64, |a: u64, b| a.rotate_left(b as u32)),
+        0x8a => bin!(u64_, from_u64, |a: u64, b| a.rotate_right(b as u32)),
+        // f32 arithmetic
+        0x8b => un!(f32_, from_f32, f32::abs),
+        0x8c => un!(f32_, from_f32, |a: f32| -a),
+        0x8d => un!(f32_, from_f32, f32::ceil),
+        0x8e => un!(f32_, from_f32, f32::floor),
+        0x8f => un!(f32_, from_f32, f32::trunc),
+        0x90 => un!(f32_, from_f32, f32::round_ties_even),
+        0x91 => un!(f32_, from_f32, f32::sqrt),
+        0x92 => bin!(f32_, from_f32, |a, b| a + b),
+        0x93 => bin!(f32_, from_f32, |a, b| a - b),
+        0x94 => bin!(f32_, from_f32, |a, b| a * b),
+        0x95 => bin!(f32_, from_f32, |a, b| a / b),
+        0x96 => bin!(f32_, from_f32, |a: f32, b: f32| {
+            if a.is_nan() || b.is_nan() {
+                f32::NAN
+            } else if a == b {
+                if a.is_sign_negative() {
+                    a
+                } else {
+                    b
+                }
+            } else {
+                a.min(b)
+            }
+        }),
+        0x97 => bin!(f32_, from_f32, |a: f32, b: f32| {
+            if a.is_nan() || b.is_nan() {
+                f32::NAN
+            } else if a == b {
+                if a.is_sign_positive() {
+                    a
+                } else {
+                    b
+                }
+            } else {
+                a.max(b)
+            }
+        }),
+        0x98 => bin!(f32_, from_f32, f32::copysign),
+        // f64 arithmetic
+        0x99 => un!(f64_, from_f64, f64::abs),
+        0x9a => un!(f64_, from_f64, |a: f64| -a),
+        0x9b => un!(f64_, from_f64, f64::ceil),
+        0x9c => un!(f64_, from_f64, f64::floor),
+        0x9d => un!(f64_, from_f64, f64::trunc),
+        0x9e => un!(f64_, from_f64, f64::round_ties_even),
+        0x9f => un!(f64_, from_f64, f64::sqrt),
+        0xa0 => bin!(f64_, from_f64, |a, b| a + b),
+        0xa1 => bin!(f64_, from_f64, |a, b| a - b),
+        0xa2 => bin!(f64_, from_f64, |a, b| a * b),
+        0xa3 => bin!(f64_, from_f64, |a, b| a / b),
+        0xa4 => bin!(f64_, from_f64, |a: f64, b: f64| {
+            if a.is_nan() || b.is_nan() {
+                f64::NAN
+            } else if a == b {
+                if a.is_sign_negative() {
+                    a
+                } else {
+                    b
+                }
+            } else {
+                a.min(b)
+            }
+        }),
+        0xa5 => bin!(f64_, from_f64, |a: f64, b: f64| {
+            if a.is_nan() || b.is_nan() {
+                f64::NAN
+            } else if a == b {
+                if a.is_sign_positive() {
+                    a
+                } else {
+                    b
+                }
+            } else {
+                a.max(b)
+            }
+        }),
+        0xa6 => bin!(f64_, from_f64, f64::copysign),
+        // Conversions
+        0xa7 => un!(u64_, from_u64, |a: u64| a as u32 as u64),
+        0xa8 => un_try!(f32_, from_i32, |a| trunc(
+            a as f64,
+            -2147483649.0,
+            2147483648.0
+        )
+        .map(|a| a as i32)),
+        0xa9 => un_try!(f32_, from_u32, |a| trunc(a as f64, -1.0, 4294967296.0)
+            .map(|a| a as u32)),
+        0xaa => un_try!(f64_, from_i32, |a| trunc(a, -2147483649.0, 2147483648.0)
+            .map(|a| a as i32)),
+        0xab => un_try!(f64_, from_u32, |a| trunc(a, -1.0, 4294967296.0)
+            .map(|a| a as u32)),
+        0xac => un!(i32_, from_i64, |a| a as i64),
+        0xad => un!(u32_, from_u64, |a| a as u64),
+        0xae => un_try!(f32_, from_i64, |a| trunc(a as f64, I64_MIN, I64_END)
+            .map(|a| a as i64)),
+        0xaf => un_try!(f32_, from_u64, |a| trunc(a as f64, -1.0, U64_END)
+            .map(|a| a as u64)),
+        0xb0 => un_try!(f64_, from_i64, |a| trunc(a, I64_MIN, I64_END)
+            .map(|a| a as i64)),
+        0xb1 => un_try!(f64_, from_u64, |a| trunc(a, -1.0, U64_END)
+            .map(|a| a as u64)),
+        0xb2 => un!(i32_, from_f32, |a| a as f32),
+        0xb3 => un!(u32_, from_f32, |a| a as f32),
+        0xb4 => un!(i64_, from_f32, |a| a as f32),
+        0xb5 => un!(u64_, from_f32, |a| a as f32),
+        0xb6 => un!(f64_, from_f32, |a| a as f32),
+        0xb7 => un!(i32_, from_f64, |a| a as f64),
+        0xb8 => un!(u32_, from_f64, |a| a as f64),
+        0xb9 => un!(i64_, from_f64, |a| a as f64),
+        0xba => un!(u64_, from_f64, |a| a as f64),
+        0xbb => un!(f32_, from_f64, |a| a as f64),
+        // Reinterpretations do not change the bits
+        0xbc..=0xbf => {}
+        0xc0 => un!(u32_, from_i32, |a| a as i8 as i32),
+        0xc1 => un!(u32_, from_i32, |a| a as i16 as i32),
+        0xc2 => un!(u64_, from_i64, |a| a as i8 as i64),
+        0xc3 => un!(u64_, from_i64, |a| a as i16 as i64),
+        0xc4 => un!(u64_, from_i64, |a| a as i32 as i64),
+        _ => return Err(format!("unsupported instruction 0x{op:02x}")),
+    }
+    Ok(())
+}
+
+const DIV_ZERO: &str = "integer divide by zero";
+const I64_MIN: f64 = -9223372036854775808.0;
+const I64_END: f64 = 9223372036854775808.0;
+const U64_END: f64 = 18446744073709551616.0;
+
+/// Truncate a float that must end up in the exclusive range between `min` and `end`
+///
+/// The range includes `min` if it is the minimum of a signed 64-bit integer,
+/// because it is not exclusive of any representable value there.
+fn trunc(a: f64, min: f64, end: f64) -> Result<f64, String> {
+    if a.is_nan() {
+        return Err("invalid conversion to integer".into());
+    }
+    let a = a.trunc();
+    if (a > min || a == I64_MIN && min == I64_MIN) && a < end {
+        Ok(a)
+    } else {
+        Err("integer overflow".into())
+    }
+}
+
+fn i32_(v: u64) -> i32 {
+    v as u32 as i32
+}
+fn u32_(v: u64) -> u32 {
+    v as u32
+}
+fn i64_(v: u64) -> i64 {
+    v as i64
+}
+fn u64_(v: u64) -> u64 {
+    v
+}
+fn f32_(v: u64) -> f32 {
+    f32::from_bits(v as u32)
+}
+fn f64_(v: u64) -> f64 {
+    f64::from_bits(v)
+}
+fn bool_(b: bool) -> u64 {
+    b as u64
+}
+fn from_i32(v: i32) -> u64 {
+    v as u32 as u64
+}
+fn from_u32(v: u32) -> u64 {
+    v as u64
+}
+fn from_i64(v: i64) -> u64 {
+    v as u64
+}
+fn from_u64(v: u64) -> u64 {
+    v
+}
+fn from_f32(v: f32) -> u64 {
+    v.to_bits() as u64
+}
+fn from_f64(v: f64) -> u64 {
+    v.to_bits()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    /// Build a module from its sections
+    fn module(sections: &[(u8, &[u8])]) -> Module {
+        let mut bytes = b"\0asm\x01\0\0\0".to_vec();
+        for &(id, contents) in sections {
+            bytes.push(id);
+            bytes.push(contents.len() as u8);
+            bytes.extend(contents);
+        }
+        Module::parse(&bytes).unwrap()
+    }
+
+    /// A module that exports a function that loops forever
+    fn looping() -> Module {
+        module(&[
+            (1, &[1, 0x60, 0, 0]),
+            (3, &[1, 0]),
+            (7, &[1, 4, b'l', b'o', b'o', b'p', 0, 0]),
+            (10, &[1, 7, 0, 0x03, 0x40, 0x0c, 0, 0x0b, 0x0b]),
+        ])
+    }
+
+    #[test]
+    fn oversized_limits() {
+        let never = || false;
+        // A table with 2^31 - 1 elements
+        let table = module(&[(4, &[1, 0x70, 0, 0xff, 0xff, 0xff, 0xff, 0x07])]);
+        let Err(e) = Instance::new(table, &never) else {
+            panic!("Oversized table was allowed");
+        };
+        assert!(e.contains("table is too large"));
+        // A memory with 2^16 pages
+        let memory = module(&[(5, &[1, 0, 0x80, 0x80, 0x04])]);
+        let Err(e) = Instance::new(memory, &never) else {
+            panic!("Oversized memory was allowed");
+        };
+        assert!(e.contains("memory is too large"));
+    }
+
+    #[test]
+    fn interrupt_loop() {
+        let mut instance = Instance::new(looping(), &|| false).unwrap();
+        let checks = Cell::new(0);
+        let interrupted = || {
+            checks.set(checks.get() + 1);
+            checks.get() == 3
+        };
+        let Err(e) = instance.invoke(0, &[], &interrupted) else {
+            panic!("Loop was not interrupted");
+        };
+        assert!(e.contains("interrupted"));
+        assert_eq!(checks.get(), 3);
+    }
+}
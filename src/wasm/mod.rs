@@ -0,0 +1,395 @@
+//! Loading WebAssembly modules and calling their functions
+//!
+//! Modules are run by an interpreter, so they can only access their own memory.
+//! Lists are copied into a module's memory with its exported `alloc` function.
+
+mod exec;
+mod parse;
+
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use ecow::EcoVec;
+
+use crate::Value;
+
+use exec::{Instance, Interrupted};
+use parse::{Export, FuncType, Module, ValType};
+
+/// A type in a [`&wasm`](crate::SysOp::Wasm) signature
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum WasmType {
+    Void,
+    Scalar(ValType),
+    List(Elem),
+    Str,
+}
+
+/// The type of the elements of a list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Elem {
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    U64,
+    I64,
+    F32,
+    F64,
+}
+
+impl FromStr for WasmType {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        Ok(match s {
+            "void" => WasmType::Void,
+            "i32" => WasmType::Scalar(ValType::I32),
+            "i64" => WasmType::Scalar(ValType::I64),
+            "f32" => WasmType::Scalar(ValType::F32),
+            "f64" => WasmType::Scalar(ValType::F64),
+            "str" => WasmType::Str,
+            _ => {
+                let elem = s.strip_suffix("[]").map(str::trim_end);
+                WasmType::List(match elem {
+                    Some("u8") => Elem::U8,
+                    Some("i8") => Elem::I8,
+                    Some("u16") => Elem::U16,
+                    Some("i16") => Elem::I16,
+                    Some("u32") => Elem::U32,
+                    Some("i32") => Elem::I32,
+                    Some("u64") => Elem::U64,
+                    Some("i64") => Elem::I64,
+                    Some("f32") => Elem::F32,
+                    Some("f64") => Elem::F64,
+                    _ => return Err(format!("Unknown WASM type: {s}")),
+                })
+            }
+        })
+    }
+}
+
+impl fmt::Display for WasmType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WasmType::Void => write!(f, "void"),
+            WasmType::Scalar(ty) => write!(f, "{}", ty.name()),
+            WasmType::List(elem) => write!(f, "{}[]", format!("{elem:?}").to_lowercase()),
+            WasmType::Str => write!(f, "str"),
+        }
+    }
+}
+
+impl Elem {
+    fn size(self) -> usize {
+        match self {
+            Elem::U8 | Elem::I8 => 1,
+            Elem::U16 | Elem::I16 => 2,
+            Elem::U32 | Elem::I32 | Elem::F32 => 4,
+            Elem::U64 | Elem::I64 | Elem::F64 => 8,
+        }
+    }
+    fn write(self, n: f64, bytes: &mut Vec<u8>) {
+        match self {
+            Elem::U8 => bytes.push(n as u8),
+            Elem::I8 => bytes.push(n as i8 as u8),
+            Elem::U16 => bytes.extend((n as u16).to_le_bytes()),
+            Elem::I16 => bytes.extend((n as i16).to_le_bytes()),
+            Elem::U32 => bytes.extend((n as u32).to_le_bytes()),
+            Elem::I32 => bytes.extend((n as i32).to_le_bytes()),
+            Elem::U64 => bytes.extend((n as u64).to_le_bytes()),
+            Elem::I64 => bytes.extend((n as i64).to_le_bytes()),
+            Elem::F32 => bytes.extend((n as f32).to_le_bytes()),
+            Elem::F64 => bytes.extend(n.to_le_bytes()),
+        }
+    }
+    fn read(self, bytes: &[u8]) -> f64 {
+        match self {
+            Elem::U8 => bytes[0] as f64,
+            Elem::I8 => bytes[0] as i8 as f64,
+            Elem::U16 => u16::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            Elem::I16 => i16::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            Elem::U32 => u32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            Elem::I32 => i32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            Elem::U64 => u64::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            Elem::I64 => i64::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            Elem::F32 => f32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            Elem::F64 => f64::from_le_bytes(bytes.try_into().unwrap()),
+        }
+    }
+}
+
+impl WasmType {
+    /// The WASM types a value of this type is passed as
+    fn params(&self) -> Vec<ValType> {
+        match self {
+            WasmType::Void => Vec::new(),
+            WasmType::Scalar(ty) => vec![*ty],
+            WasmType::List(_) | WasmType::Str => vec![ValType::I32, ValType::I32],
+        }
+    }
+    /// The WASM types a value of this type is returned as
+    fn results(&self) -> Vec<ValType> {
+        match self {
+            WasmType::Void => Vec::new(),
+            WasmType::Scalar(ty) => vec![*ty],
+            WasmType::List(_) | WasmType::Str => vec![ValType::I64],
+        }
+    }
+}
+
+fn type_string(ty: &FuncType) -> String {
+    let list = |tys: &[ValType]| {
+        (tys.iter().map(ValType::name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    format!("({}) -> ({})", list(&ty.params), list(&ty.results))
+}
+
+/// The instantiated WASM modules of a program, keyed by their canonical paths
+#[derive(Default)]
+pub(crate) struct WasmInstances {
+    instances: Mutex<HashMap<PathBuf, Arc<Mutex<Instance>>>>,
+}
+
+/// WASM instances shared between threads
+pub(crate) type SharedWasmInstances = Arc<WasmInstances>;
+
+/// Call a function exported by a WASM module
+///
+/// The module is loaded and instantiated the first time one of its functions is called.
+/// Its state is kept between calls in the same program, even if the module is referred to by a different path.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn call(
+    instances: &WasmInstances,
+    path: &str,
+    load: impl FnOnce() -> Result<Vec<u8>, String>,
+    result_ty: WasmType,
+    name: &str,
+    arg_tys: &[WasmType],
+    args: &[Value],
+    interrupted: Interrupted,
+) -> Result<Value, String> {
+    let instance = {
+        let key = fs::canonicalize(path).unwrap_or_else(|_| path.into());
+        let mut instances = instances.instances.lock().unwrap();
+        match instances.get(&key) {
+            Some(instance) => instance.clone(),
+            None => {
+                let instance = (load().and_then(|bytes| Module::parse(&bytes)))
+                    .and_then(|module| Instance::new(module, interrupted))
+                    .map_err(|e| format!("Failed to load WASM module {path}: {e}"))?;
+                let instance = Arc::new(Mutex::new(instance));
+                instances.insert(key, instance.clone());
+                instance
+            }
+        }
+    };
+    let mut instance = instance.lock().unwrap();
+    let func = instance.export_func(name)?;
+    let ty = instance.func_type(func).clone();
+    let expected = FuncType {
+        params: arg_tys.iter().flat_map(WasmType::params).collect(),
+        results: result_ty.results(),
+    };
+    if ty != expected {
+        return Err(format!(
+            "WASM function `{name}` has type {}, \
+            but the signature describes {}",
+            type_string(&ty),
+            type_string(&expected)
+        ));
+    }
+    if arg_tys.len() != args.len() {
+        return Err(format!(
+            "Expected {} argument(s), but got {}",
+            arg_tys.len(),
+            args.len()
+        ));
+    }
+
+    // Convert arguments
+    let mut wasm_args = Vec::with_capacity(ty.params.len());
+    let mut allocations = Vec::new();
+    let mut res = Ok(());
+    for (i, (ty, val)) in arg_tys.iter().zip(args).enumerate() {
+        res = arg_bytes(ty, val)
+            .map_err(|e| format!("WASM argument {}: {e}", i + 1))
+            .and_then(|arg| match arg {
+                Arg::Scalar(value) => {
+                    wasm_args.push(value);
+                    Ok(())
+                }
+                Arg::List(bytes, len) => {
+                    let ptr = instance.alloc(&bytes, interrupted)?;
+                    allocations.push((ptr, bytes.len()));
+                    wasm_args.extend([ptr as u64, len as u64]);
+                    Ok(())
+                }
+            });
+        if res.is_err() {
+            break;
+        }
+    }
+    let res = res.and_then(|_| {
+        let results = instance.invoke(func, &wasm_args, interrupted)?;
+        instance.result(result_ty, &results, interrupted)
+    });
+    for (ptr, size) in allocations {
+        instance.dealloc(ptr, size, interrupted)?;
+    }
+    res
+}
+
+enum Arg {
+    Scalar(u64),
+    /// The bytes of a list and its length
+    List(Vec<u8>, usize),
+}
+
+fn arg_bytes(ty: &WasmType, val: &Value) -> Result<Arg, String> {
+    let nums: Vec<f64> = match val {
+        Value::Num(arr) => arr.data.iter().copied().collect(),
+        Value::Byte(arr) => arr.data.iter().map(|&b| b as f64).collect(),
+        Value::Char(arr) if *ty == WasmType::Str && arr.rank() <= 1 => {
+            let s: String = arr.data.iter().collect();
+            let len = s.len();
+            return Ok(Arg::List(s.into_bytes(), len));
+        }
+        val => return Err(format!("Cannot pass {} as {ty}", val.type_name_plural())),
+    };
+    match ty {
+        WasmType::Scalar(vt) => {
+            if val.rank() != 0 {
+                return Err(format!(
+                    "{ty} must be a scalar, but it is rank {}",
+                    val.rank()
+                ));
+            }
+            let n = nums[0];
+            Ok(Arg::Scalar(match vt {
+                ValType::I32 => n as i64 as u32 as u64,
+                ValType::I64 => n as i64 as u64,
+                ValType::F32 => (n as f32).to_bits() as u64,
+                _ => n.to_bits(),
+            }))
+        }
+        WasmType::List(elem) => {
+            if val.rank() > 1 {
+                return Err(format!(
+                    "{ty} must be a list, but it is rank {}",
+                    val.rank()
+                ));
+            }
+            let mut bytes = Vec::with_capacity(nums.len() * elem.size());
+            for &n in &nums {
+                elem.write(n, &mut bytes);
+            }
+            Ok(Arg::List(bytes, nums.len()))
+        }
+        _ => Err(format!("Cannot pass {} as {ty}", val.type_name_plural())),
+    }
+}
+
+impl Instance {
+    fn export_func(&self, name: &str) -> Result<u32, String> {
+        match self.module.exports.get(name) {
+            Some(Export::Func(func)) => Ok(*func),
+            _ => Err(format!("WASM module has no function `{name}`")),
+        }
+    }
+    /// Copy bytes into the module's memory
+    fn alloc(&mut self, bytes: &[u8], interrupted: Interrupted) -> Result<u32, String> {
+        let alloc = (self.export_func("alloc"))
+            .map_err(|_| "Passing lists to WASM requires the module to export `alloc`")?;
+        let ty = self.func_type(alloc);
+        if ty.params != [ValType::I32] || ty.results != [ValType::I32] {
+            return Err("WASM `alloc` function must have type (i32) -> (i32)".into());
+        }
+        let ptr = self.invoke(alloc, &[bytes.len() as u64], interrupted)?[0] as u32;
+        let start = ptr as usize;
+        let dest = (self.memory.get_mut(start..start + bytes.len()))
+            .ok_or("WASM `alloc` returned a pointer outside of memory")?;
+        dest.copy_from_slice(bytes);
+        Ok(ptr)
+    }
+    /// Free memory allocated by the module, if it exports `dealloc`
+    fn dealloc(&mut self, ptr: u32, size: usize, interrupted: Interrupted) -> Result<(), String> {
+        let Ok(dealloc) = self.export_func("dealloc") else {
+            return Ok(());
+        };
+        let ty = self.func_type(dealloc);
+        if ty.params != [ValType::I32, ValType::I32] || !ty.results.is_empty() {
+            return Err("WASM `dealloc` function must have type (i32, i32) -> ()".into());
+        }
+        self.invoke(dealloc, &[ptr as u64, size as u64], interrupted)?;
+        Ok(())
+    }
+    fn result(
+        &mut self,
+        ty: WasmType,
+        results: &[u64],
+        interrupted: Interrupted,
+    ) -> Result<Value, String> {
+        let elem = match ty {
+            WasmType::Void => return Ok(Value::default()),
+            WasmType::Scalar(ValType::I32) => return Ok((results[0] as u32 as i32 as f64).into()),
+            WasmType::Scalar(ValType::I64) => return Ok((results[0] as i64 as f64).into()),
+            WasmType::Scalar(ValType::F32) => {
+                return Ok((f32::from_bits(results[0] as u32) as f64).into())
+            }
+            WasmType::Scalar(_) => return Ok(f64::from_bits(results[0]).into()),
+            WasmType::List(elem) => elem,
+            WasmType::Str => Elem::U8,
+        };
+        // Lists are returned as a pointer in the high 32 bits and a length in the low 32 bits
+        let ptr = (results[0] >> 32) as usize;
+        let len = results[0] as u32 as usize;
+        let size = len * elem.size();
+        let bytes = (self.memory.get(ptr..ptr + size))
+            .ok_or("WASM function returned a list outside of memory")?
+            .to_vec();
+        self.dealloc(ptr as u32, size, interrupted)?;
+        Ok(match ty {
+            WasmType::Str => String::from_utf8(bytes)
+                .map_err(|_| "WASM function returned invalid UTF-8")?
+                .into(),
+            WasmType::List(Elem::U8) => bytes.into(),
+            _ => (bytes.chunks_exact(elem.size()))
+                .map(|b| elem.read(b))
+                .collect::<EcoVec<_>>()
+                .into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    #[cfg(feature = "native_sys")]
+    fn instances_belong_to_runtime() {
+        use crate::*;
+        let code = "# Experimental!\n&wasm {\"tests/wasm/test.wasm\" \"i32\" \"next\"} {}";
+        let mut env = Uiua::with_native_sys();
+        env.run_str(code).unwrap();
+        env.run_str(code).unwrap();
+        let second = env.pop_num().unwrap();
+        let first = env.pop_num().unwrap();
+        assert_eq!(second, first + 1.0);
+        let mut other = Uiua::with_native_sys();
+        other.run_str(code).unwrap();
+        assert_eq!(
+            other.pop_num().unwrap(),
+            first,
+            "Instance was shared between runtimes"
+        );
+    }
+}
@@ -0,0 +1,723 @@
+//! Parsing of the WebAssembly binary format
+
+use std::collections::HashMap;
+
+/// A value type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ValType {
+    I32,
+    I64,
+    F32,
+    F64,
+    FuncRef,
+    ExternRef,
+}
+
+impl ValType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ValType::I32 => "i32",
+            ValType::I64 => "i64",
+            ValType::F32 => "f32",
+            ValType::F64 => "f64",
+            ValType::FuncRef => "funcref",
+            ValType::ExternRef => "externref",
+        }
+    }
+    /// The value a local of this type starts with
+    pub fn default_value(&self) -> u64 {
+        match self {
+            ValType::FuncRef | ValType::ExternRef => NULL_REF,
+            _ => 0,
+        }
+    }
+}
+
+/// The representation of a null reference
+pub(super) const NULL_REF: u64 = u64::MAX;
+
+/// A function type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct FuncType {
+    pub params: Vec<ValType>,
+    pub results: Vec<ValType>,
+}
+
+/// The size limits of a memory or table
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Limits {
+    pub min: u32,
+    pub max: Option<u32>,
+}
+
+/// A constant expression
+#[derive(Debug, Clone, Copy)]
+pub(super) enum ConstExpr {
+    Value(u64),
+    GlobalGet(u32),
+    RefFunc(u32),
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct Global {
+    pub mutable: bool,
+    pub init: ConstExpr,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Export {
+    Func(u32),
+    Table,
+    Memory,
+    Global,
+}
+
+/// A segment that is copied into memory or a table
+#[derive(Debug, Clone)]
+pub(super) struct Segment<T> {
+    /// The index of the table or memory and the offset to copy to, if the segment is active
+    pub active: Option<(u32, ConstExpr)>,
+    pub items: Vec<T>,
+}
+
+/// A function defined in a module
+#[derive(Debug, Clone)]
+pub(super) struct Func {
+    pub ty: u32,
+    pub locals: Vec<ValType>,
+    pub code: Vec<Instr>,
+}
+
+/// The type of a block
+#[derive(Debug, Clone, Copy)]
+pub(super) enum BlockType {
+    Empty,
+    /// A single result
+    Value,
+    Func(u32),
+}
+
+/// A decoded instruction
+///
+/// Blocks know where they end so that branches do not have to search for it.
+#[derive(Debug, Clone)]
+pub(super) enum Instr {
+    Unreachable,
+    Nop,
+    Block {
+        ty: BlockType,
+        end: usize,
+    },
+    Loop {
+        ty: BlockType,
+    },
+    If {
+        ty: BlockType,
+        els: usize,
+        end: usize,
+    },
+    Else {
+        end: usize,
+    },
+    End,
+    Br(u32),
+    BrIf(u32),
+    /// The last target is the default
+    BrTable(Box<[u32]>),
+    Return,
+    Call(u32),
+    CallIndirect {
+        ty: u32,
+        table: u32,
+    },
+    Drop,
+    Select,
+    LocalGet(u32),
+    LocalSet(u32),
+    LocalTee(u32),
+    GlobalGet(u32),
+    GlobalSet(u32),
+    TableGet(u32),
+    TableSet(u32),
+    Load {
+        op: u8,
+        offset: u32,
+    },
+    Store {
+        op: u8,
+        offset: u32,
+    },
+    MemorySize,
+    MemoryGrow,
+    Const(u64),
+    /// An instruction from `0x45` to `0xC4`, none of which have immediates
+    Numeric(u8),
+    /// A saturating truncation, numbered by its `0xFC` sub-opcode
+    TruncSat(u32),
+    MemoryInit(u32),
+    DataDrop(u32),
+    MemoryCopy,
+    MemoryFill,
+    RefNull,
+    RefIsNull,
+    RefFunc(u32),
+}
+
+/// A parsed module
+#[derive(Debug, Default)]
+pub(super) struct Module {
+    pub types: Vec<FuncType>,
+    pub funcs: Vec<Func>,
+    pub tables: Vec<Limits>,
+    pub memory: Option<Limits>,
+    pub globals: Vec<Global>,
+    pub exports: HashMap<String, Export>,
+    pub start: Option<u32>,
+    pub elements: Vec<Segment<Option<u32>>>,
+    pub data: Vec<Segment<u8>>,
+}
+
+impl Module {
+    pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+        let mut r = Reader { bytes, pos: 0 };
+        if r.take(4)? != b"\0asm" {
+            return Err("Not a WASM module".into());
+        }
+        if r.take(4)? != [1, 0, 0, 0] {
+            return Err("Unsupported WASM version".into());
+        }
+        let mut module = Module::default();
+        let mut func_types = Vec::new();
+        while r.pos < r.bytes.len() {
+            let id = r.byte()?;
+            let len = r.u32()? as usize;
+            let mut s = Reader {
+                bytes: r.take(len)?,
+                pos: 0,
+            };
+            match id {
+                0 | 12 => {}
+                1 => {
+                    module.types = s.vec(|s| {
+                        if s.byte()? != 0x60 {
+                            return Err("invalid function type".into());
+                        }
+                        let params = s.vec(Reader::val_type)?;
+                        let results = s.vec(Reader::val_type)?;
+                        Ok(FuncType { params, results })
+                    })?
+                }
+                2 => {
+                    if let Some((module_name, name)) = s
+                        .vec(|s| {
+                            let names = (s.name()?, s.name()?);
+                            match s.byte()? {
+                                0 => _ = s.u32()?,
+                                1 => _ = (s.val_type()?, s.limits()?),
+                                2 => _ = s.limits()?,
+                                3 => _ = (s.val_type()?, s.byte()?),
+                                _ => return Err("invalid import".into()),
+                            }
+                            Ok(names)
+                        })?
+                        .into_iter()
+                        .next()
+                    {
+                        return Err(format!(
+                            "WASM modules with imports are not supported, \
+                            but this one imports {module_name}.{name}"
+                        ));
+                    }
+                }
+                3 => func_types = s.vec(Reader::u32)?,
+                4 => {
+                    module.tables = s.vec(|s| {
+                        s.val_type()?;
+                        s.limits()
+                    })?
+                }
+                5 => {
+                    let mems = s.vec(Reader::limits)?;
+                    if mems.len() > 1 {
+                        return Err("WASM modules with multiple memories are not supported".into());
+                    }
+                    module.memory = mems.into_iter().next();
+                }
+                6 => {
+                    module.globals = s.vec(|s| {
+                        s.val_type()?;
+                        let mutable = s.byte()? == 1;
+                        let init = s.const_expr()?;
+                        Ok(Global { mutable, init })
+                    })?
+                }
+                7 => {
+                    module.exports = s
+                        .vec(|s| {
+                            let name = s.name()?;
+                            let export = match s.byte()? {
+                                0 => Export::Func(s.u32()?),
+                                1 => (s.u32()?, Export::Table).1,
+                                2 => (s.u32()?, Export::Memory).1,
+                                3 => (s.u32()?, Export::Global).1,
+                                _ => return Err("invalid export".into()),
+                            };
+                            Ok((name, export))
+                        })?
+                        .into_iter()
+                        .collect()
+                }
+                8 => module.start = Some(s.u32()?),
+                9 => module.elements = s.vec(Reader::element)?,
+                10 => {
+                    let bodies = s.vec(|s| {
+                        let len = s.u32()? as usize;
+                        let mut s = Reader {
+                            bytes: s.take(len)?,
+                            pos: 0,
+                        };
+                        let mut locals = Vec::new();
+                        for _ in 0..s.u32()? {
+                            let count = s.u32()? as usize;
+                            if locals.len() + count > 50_000 {
+                                return Err("too many locals".into());
+                            }
+                            let ty = s.val_type()?;
+                            locals.extend(std::iter::repeat(ty).take(count));
+                        }
+                        Ok((locals, s.code()?))
+                    })?;
+                    if bodies.len() != func_types.len() {
+                        return Err("function and code section lengths do not match".into());
+                    }
+                    module.funcs = (func_types.iter().zip(bodies))
+                        .map(|(&ty, (locals, code))| Func { ty, locals, code })
+                        .collect();
+                }
+                11 => module.data = s.vec(Reader::data)?,
+                _ => return Err(format!("invalid section id {id}")),
+            }
+        }
+        if module.funcs.len() != func_types.len() {
+            return Err("function section has no code".into());
+        }
+        module.check()?;
+        Ok(module)
+    }
+    /// Check the indices used by the module so that execution does not have to
+    fn check(&self) -> Result<(), String> {
+        let func = |i: u32| {
+            if (i as usize) < self.funcs.len() {
+                Ok(())
+            } else {
+                Err(format!("function index {i} out of bounds"))
+            }
+        };
+        let ty = |i: u32| {
+            if (i as usize) < self.types.len() {
+                Ok(())
+            } else {
+                Err(format!("type index {i} out of bounds"))
+            }
+        };
+        let global = |i: u32| {
+            if (i as usize) < self.globals.len() {
+                Ok(())
+            } else {
+                Err(format!("global index {i} out of bounds"))
+            }
+        };
+        let table = |i: u32| {
+            if (i as usize) < self.tables.len() {
+                Ok(())
+            } else {
+                Err(format!("table index {i} out of bounds"))
+            }
+        };
+        let memory = || {
+            if self.memory.is_some() {
+                Ok(())
+            } else {
+                Err("memory instruction used without a memory".to_string())
+            }
+        };
+        let data = |i: u32| {
+            if (i as usize) < self.data.len() {
+                Ok(())
+            } else {
+                Err(format!("data index {i} out of bounds"))
+            }
+        };
+        let block = |bt: &BlockType| match bt {
+            BlockType::Func(i) => ty(*i),
+            _ => Ok(()),
+        };
+        for f in &self.funcs {
+            ty(f.ty)?;
+            let locals = self.types[f.ty as usize].params.len() + f.locals.len();
+            let local = |i: u32| {
+                if (i as usize) < locals {
+                    Ok(())
+                } else {
+                    Err(format!("local index {i} out of bounds"))
+                }
+            };
+            for instr in &f.code {
+                match instr {
+                    Instr::Block { ty, .. } | Instr::Loop { ty } | Instr::If { ty, .. } => {
+                        block(ty)?
+                    }
+                    Instr::Call(i) | Instr::RefFunc(i) => func(*i)?,
+                    Instr::CallIndirect { ty: i, table: t } => {
+                        ty(*i)?;
+                        table(*t)?
+                    }
+                    Instr::LocalGet(i) | Instr::LocalSet(i) | Instr::LocalTee(i) => local(*i)?,
+                    Instr::GlobalGet(i) | Instr::GlobalSet(i) => global(*i)?,
+                    Instr::TableGet(i) | Instr::TableSet(i) => table(*i)?,
+                    Instr::Load { .. }
+                    | Instr::Store { .. }
+                    | Instr::MemorySize
+                    | Instr::MemoryGrow
+                    | Instr::MemoryCopy
+                    | Instr::MemoryFill => memory()?,
+                    Instr::MemoryInit(i) => {
+                        memory()?;
+                        data(*i)?
+                    }
+                    Instr::DataDrop(i) => data(*i)?,
+                    _ => {}
+                }
+            }
+        }
+        for g in &self.globals {
+            match g.init {
+                ConstExpr::GlobalGet(i) => global(i)?,
+                ConstExpr::RefFunc(i) => func(i)?,
+                ConstExpr::Value(_) => {}
+            }
+        }
+        for export in self.exports.values() {
+            if let Export::Func(i) = export {
+                func(*i)?;
+            }
+        }
+        if let Some(start) = self.start {
+            func(start)?;
+        }
+        for seg in &self.elements {
+            if let Some((t, _)) = seg.active {
+                table(t)?;
+            }
+            for i in seg.items.iter().flatten() {
+                func(*i)?;
+            }
+        }
+        for seg in &self.data {
+            if seg.active.is_some() {
+                memory()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Result<u8, String> {
+        let b = *(self.bytes.get(self.pos)).ok_or("unexpected end of module")?;
+        self.pos += 1;
+        Ok(b)
+    }
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let bytes = (self.bytes.get(self.pos..self.pos.saturating_add(n)))
+            .ok_or("unexpected end of module")?;
+        self.pos += n;
+        Ok(bytes)
+    }
+    fn uleb(&mut self, bits: u32) -> Result<u64, String> {
+        let mut result = 0u64;
+        let mut shift = 0;
+        loop {
+            let b = self.byte()?;
+            result |= ((b & 0x7f) as u64) << shift;
+            shift += 7;
+            if b & 0x80 == 0 {
+                break;
+            }
+            if shift >= bits {
+                return Err("integer too large".into());
+            }
+        }
+        Ok(result)
+    }
+    fn sleb(&mut self, bits: u32) -> Result<i64, String> {
+        let mut result = 0i64;
+        let mut shift = 0;
+        loop {
+            let b = self.byte()?;
+            result |= ((b & 0x7f) as i64) << shift;
+            shift += 7;
+            if b & 0x80 == 0 {
+                if shift < 64 && b & 0x40 != 0 {
+                    result |= -1 << shift;
+                }
+                break;
+            }
+            if shift >= bits {
+                return Err("integer too large".into());
+            }
+        }
+        Ok(result)
+    }
+    fn u32(&mut self) -> Result<u32, String> {
+        self.uleb(32).map(|n| n as u32)
+    }
+    fn name(&mut self) -> Result<String, String> {
+        let len = self.u32()? as usize;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| "invalid name".into())
+    }
+    fn vec<T>(&mut self, f: impl Fn(&mut Self) -> Result<T, String>) -> Result<Vec<T>, String> {
+        let len = self.u32()? as usize;
+        // Each item takes at least one byte
+        if len > self.bytes.len() - self.pos {
+            return Err("unexpected end of module".into());
+        }
+        (0..len).map(|_| f(self)).collect()
+    }
+    fn val_type(&mut self) -> Result<ValType, String> {
+        Ok(match self.byte()? {
+            0x7f => ValType::I32,
+            0x7e => ValType::I64,
+            0x7d => ValType::F32,
+            0x7c => ValType::F64,
+            0x70 => ValType::FuncRef,
+            0x6f => ValType::ExternRef,
+            0x7b => return Err("WASM SIMD is not supported".into()),
+            b => return Err(format!("invalid value type 0x{b:02x}")),
+        })
+    }
+    fn limits(&mut self) -> Result<Limits, String> {
+        Ok(match self.byte()? {
+            0 => Limits {
+                min: self.u32()?,
+                max: None,
+            },
+            1 => Limits {
+                min: self.u32()?,
+                max: Some(self.u32()?),
+            },
+            _ => return Err("invalid limits".into()),
+        })
+    }
+    fn const_expr(&mut self) -> Result<ConstExpr, String> {
+        let expr = match self.byte()? {
+            0x41 => ConstExpr::Value(self.sleb(32)? as u32 as u64),
+            0x42 => ConstExpr::Value(self.sleb(64)? as u64),
+            0x43 => ConstExpr::Value(u32::from_le_bytes(self.take(4)?.try_into().unwrap()) as u64),
+            0x44 => ConstExpr::Value(u64::from_le_bytes(self.take(8)?.try_into().unwrap())),
+            0x23 => ConstExpr::GlobalGet(self.u32()?),
+            0xd0 => {
+                self.byte()?;
+                ConstExpr::Value(NULL_REF)
+            }
+            0xd2 => ConstExpr::RefFunc(self.u32()?),
+            _ => return Err("unsupported constant expression".into()),
+        };
+        if self.byte()? != 0x0b {
+            return Err("unsupported constant expression".into());
+        }
+        Ok(expr)
+    }
+    fn element(&mut self) -> Result<Segment<Option<u32>>, String> {
+        let flags = self.u32()?;
+        if flags > 7 {
+            return Err("invalid element segment".into());
+        }
+        let active = if flags & 1 == 0 {
+            let table = if flags & 2 != 0 { self.u32()? } else { 0 };
+            Some((table, self.const_expr()?))
+        } else {
+            None
+        };
+        // Element kind or reference type
+        if flags & 3 != 0 {
+            self.byte()?;
+        }
+        let items = if flags & 4 == 0 {
+            self.vec(|s| s.u32().map(Some))?
+        } else {
+            self.vec(|s| match s.const_expr()? {
+                ConstExpr::RefFunc(i) => Ok(Some(i)),
+                ConstExpr::Value(NULL_REF) => Ok(None),
+                _ => Err("unsupported element expression".into()),
+            })?
+        };
+        // Declarative segments are only used for validation
+        let items = if flags & 3 == 3 { Vec::new() } else { items };
+        Ok(Segment { active, items })
+    }
+    fn data(&mut self) -> Result<Segment<u8>, String> {
+        let active = match self.u32()? {
+            0 => Some((0, self.const_expr()?)),
+            1 => None,
+            2 => Some((self.u32()?, self.const_expr()?)),
+            _ => return Err("invalid data segment".into()),
+        };
+        let len = self.u32()? as usize;
+        let items = self.take(len)?.to_vec();
+        Ok(Segment { active, items })
+    }
+    fn block_type(&mut self) -> Result<BlockType, String> {
+        if self.bytes.get(self.pos) == Some(&0x40) {
+            self.pos += 1;
+            return Ok(BlockType::Empty);
+        }
+        let idx = self.sleb(33)?;
+        if idx >= 0 {
+            return Ok(BlockType::Func(idx as u32));
+        }
+        self.pos -= 1;
+        self.val_type().map(|_| BlockType::Value)
+    }
+    /// Decode the instructions of a function body
+    fn code(&mut self) -> Result<Vec<Instr>, String> {
+        let mut code = Vec::new();
+        // The open blocks and the `else`s of `if`s
+        let mut open: Vec<(usize, Option<usize>)> = Vec::new();
+        loop {
+            let pc = code.len();
+            let op = self.byte()?;
+            let instr = match op {
+                0x00 => Instr::Unreachable,
+                0x01 => Instr::Nop,
+                0x02 => Instr::Block {
+                    ty: self.block_type()?,
+                    end: 0,
+                },
+                0x03 => Instr::Loop {
+                    ty: self.block_type()?,
+                },
+                0x04 => Instr::If {
+                    ty: self.block_type()?,
+                    els: 0,
+                    end: 0,
+                },
+                0x05 => {
+                    match open.last_mut() {
+                        Some((start, els @ None)) if matches!(code[*start], Instr::If { .. }) => {
+                            *els = Some(pc)
+                        }
+                        _ => return Err("else without if".into()),
+                    }
+                    Instr::Else { end: 0 }
+                }
+                0x0b => {
+                    let Some((start, els)) = open.pop() else {
+                        code.push(Instr::End);
+                        return Ok(code);
+                    };
+                    match &mut code[start] {
+                        Instr::Block { end, .. } => *end = pc,
+                        Instr::If { els: e, end, .. } => {
+                            *e = els.unwrap_or(pc);
+                            *end = pc;
+                        }
+                        _ => {}
+                    }
+                    if let Some(Instr::Else { end }) = els.map(|els| &mut code[els]) {
+                        *end = pc;
+                    }
+                    Instr::End
+                }
+                0x0c => Instr::Br(self.u32()?),
+                0x0d => Instr::BrIf(self.u32()?),
+                0x0e => {
+                    let mut targets = self.vec(Reader::u32)?;
+                    targets.push(self.u32()?);
+                    Instr::BrTable(targets.into())
+                }
+                0x0f => Instr::Return,
+                0x10 => Instr::Call(self.u32()?),
+                0x11 => {
+                    let ty = self.u32()?;
+                    let table = self.u32()?;
+                    Instr::CallIndirect { ty, table }
+                }
+                0x1a => Instr::Drop,
+                0x1b => Instr::Select,
+                0x1c => {
+                    self.vec(Reader::val_type)?;
+                    Instr::Select
+                }
+                0x20 => Instr::LocalGet(self.u32()?),
+                0x21 => Instr::LocalSet(self.u32()?),
+                0x22 => Instr::LocalTee(self.u32()?),
+                0x23 => Instr::GlobalGet(self.u32()?),
+                0x24 => Instr::GlobalSet(self.u32()?),
+                0x25 => Instr::TableGet(self.u32()?),
+                0x26 => Instr::TableSet(self.u32()?),
+                0x28..=0x3e => {
+                    let align = self.u32()?;
+                    if align & 0x40 != 0 {
+                        return Err("WASM multiple memories are not supported".into());
+                    }
+                    let offset = self.u32()?;
+                    if op <= 0x35 {
+                        Instr::Load { op, offset }
+                    } else {
+                        Instr::Store { op, offset }
+                    }
+                }
+                0x3f => {
+                    self.byte()?;
+                    Instr::MemorySize
+                }
+                0x40 => {
+                    self.byte()?;
+                    Instr::MemoryGrow
+                }
+                0x41 => Instr::Const(self.sleb(32)? as u32 as u64),
+                0x42 => Instr::Const(self.sleb(64)? as u64),
+                0x43 => Instr::Const(u32::from_le_bytes(self.take(4)?.try_into().unwrap()) as u64),
+                0x44 => Instr::Const(u64::from_le_bytes(self.take(8)?.try_into().unwrap())),
+                0x45..=0xc4 => Instr::Numeric(op),
+                0xd0 => {
+                    self.val_type()?;
+                    Instr::RefNull
+                }
+                0xd1 => Instr::RefIsNull,
+                0xd2 => Instr::RefFunc(self.u32()?),
+                0xfc => match self.u32()? {
+                    sub @ 0..=7 => Instr::TruncSat(sub),
+                    8 => {
+                        let data = self.u32()?;
+                        self.byte()?;
+                        Instr::MemoryInit(data)
+                    }
+                    9 => Instr::DataDrop(self.u32()?),
+                    10 => {
+                        self.take(2)?;
+                        Instr::MemoryCopy
+                    }
+                    11 => {
+                        self.byte()?;
+                        Instr::MemoryFill
+                    }
+                    sub => return Err(format!("unsupported WASM instruction 0xfc {sub}")),
+                },
+                0xfd => return Err("WASM SIMD is not supported".into()),
+                _ => return Err(format!("unsupported WASM instruction 0x{op:02x}")),
+            };
+            if let Instr::Block { .. } | Instr::Loop { .. } | Instr::If { .. } = instr {
+                open.push((pc, None));
+            }
+            code.push(instr);
+        }
+    }
+}
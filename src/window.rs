@@ -1,8 +1,10 @@
 use std::{
+    collections::HashMap,
     env::current_exe,
     fs,
     io::{ErrorKind, Read, Write},
-    net::{SocketAddr, TcpListener, TcpStream},
+    mem::take,
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream},
     path::Path,
     process::{exit, Command, Stdio},
     sync::{
@@ -17,9 +19,10 @@ use crossbeam_channel::Receiver;
 use eframe::egui::*;
 use image::{GenericImageView, ImageFormat};
 use load::SizedTexture;
-use serde::*;
+use parking_lot::Mutex;
+use serde::{de::DeserializeOwned, *};
 
-use crate::encode::SmartOutput;
+use crate::{encode::SmartOutput, WindowEvent};
 
 static USE_WINDOW: AtomicBool = AtomicBool::new(false);
 pub fn use_window() -> bool {
@@ -46,6 +49,32 @@ pub enum Request {
     Separator,
     ClearBeforeNext,
     Shutdown,
+    WindowShow {
+        name: String,
+        width: u32,
+        height: u32,
+        rgba: Vec<u8>,
+    },
+    WindowClose(String),
+    WindowEvents(String),
+}
+
+/// The most events that are kept for a window that is not being polled
+const MAX_WINDOW_EVENTS: usize = 1000;
+
+type WindowEvents = Arc<Mutex<HashMap<String, Vec<WindowEvent>>>>;
+
+fn encode(value: &impl Serialize) -> Vec<u8> {
+    let mut serializer = rmp_serde::Serializer::new(Vec::new())
+        .with_struct_map()
+        .with_human_readable();
+    value.serialize(&mut serializer).unwrap();
+    serializer.into_inner()
+}
+
+fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, String> {
+    let mut deserializer = rmp_serde::Deserializer::new(bytes).with_human_readable();
+    T::deserialize(&mut deserializer).map_err(|e| e.to_string())
 }
 
 const RETRIES: usize = 20;
@@ -53,9 +82,20 @@ const RETRIES: usize = 20;
 impl Request {
     /// Send the request
     pub fn send(self) -> Result<(), String> {
-        self.send_impl(RETRIES)
+        self.send_impl(RETRIES).map(drop)
+    }
+    /// Send the request and wait for the window's response
+    pub fn send_and_receive<T: DeserializeOwned>(self) -> Result<T, String> {
+        let mut stream =
+            (self.send_impl(RETRIES)?).ok_or_else(|| "Window is not running".to_string())?;
+        stream
+            .shutdown(Shutdown::Write)
+            .map_err(|e| e.to_string())?;
+        let mut buffer = Vec::new();
+        (stream.read_to_end(&mut buffer)).map_err(|e| e.to_string())?;
+        decode(&buffer)
     }
-    fn send_impl(self, retries: usize) -> Result<(), String> {
+    fn send_impl(self, retries: usize) -> Result<Option<TcpStream>, String> {
         let socket_addr = ([127, 0, 0, 1], PORT).into();
         let timeout = Duration::from_secs_f32(0.1);
         let mut stream = match TcpStream::connect_timeout(&socket_addr, timeout) {
@@ -65,7 +105,7 @@ impl Request {
                     && [ErrorKind::TimedOut, ErrorKind::ConnectionRefused].contains(&e.kind()) =>
             {
                 if let Request::Shutdown = self {
-                    return Ok(());
+                    return Ok(None);
                 }
                 if retries + 1 == RETRIES {
                     if cfg!(debug_assertions) {
@@ -94,19 +134,18 @@ impl Request {
                 return Err(format!("Failed to connect to window: {e}"));
             }
         };
-        let mut serializer = rmp_serde::Serializer::new(Vec::new())
-            .with_struct_map()
-            .with_human_readable();
-        self.serialize(&mut serializer).unwrap();
-        let bin = serializer.into_inner();
-        stream.write_all(&bin).map_err(|e| e.to_string())?;
+        stream
+            .write_all(&encode(&self))
+            .map_err(|e| e.to_string())?;
         stream.flush().map_err(|e| e.to_string())?;
-        Ok(())
+        Ok(Some(stream))
     }
 }
 
 pub fn run_window() {
     let (send, recv) = crossbeam_channel::unbounded();
+    let events = WindowEvents::default();
+    let listener_events = events.clone();
     thread::spawn(move || {
         let addr = SocketAddr::from(([0u8; 4], PORT));
         let listener = match TcpListener::bind(addr) {
@@ -124,9 +163,14 @@ pub fn run_window() {
                 Ok((mut stream, _)) => {
                     let mut buffer = Vec::new();
                     stream.read_to_end(&mut buffer).unwrap();
-                    let mut deserializer =
-                        rmp_serde::Deserializer::new(buffer.as_slice()).with_human_readable();
-                    match Request::deserialize(&mut deserializer) {
+                    match decode(&buffer) {
+                        // Events are answered here so that polling does not wait for a frame
+                        Ok(Request::WindowEvents(name)) => {
+                            let events = (listener_events.lock().get_mut(&name))
+                                .map(take)
+                                .unwrap_or_default();
+                            _ = stream.write_all(&encode(&events));
+                        }
                         Ok(req) => send.send(req).unwrap(),
                         Err(e) => {
                             eprintln!("Failed to decode request: {e}")
@@ -173,15 +217,22 @@ pub fn run_window() {
                 style.interaction.show_tooltips_only_when_still = false;
                 style.interaction.tooltip_delay = 0.2;
             });
-            Ok(Box::new(App::new(recv, &cc.egui_ctx)))
+            Ok(Box::new(App::new(recv, events, &cc.egui_ctx)))
         }),
     )
     .unwrap();
 }
 
+const TEXTURE_OPTIONS: TextureOptions = TextureOptions {
+    magnification: TextureFilter::Nearest,
+    ..TextureOptions::LINEAR
+};
+
 struct App {
     items: Vec<OutputItem>,
     recv: Receiver<Request>,
+    windows: HashMap<String, ProgramWindow>,
+    window_events: WindowEvents,
     cache: Cache,
     scroll_to_top: bool,
     clear: bool,
@@ -237,6 +288,12 @@ enum OutputItem {
     Separator,
 }
 
+/// A window opened by a program with `&winshow`
+struct ProgramWindow {
+    tex: TextureHandle,
+    size: [u32; 2],
+}
+
 struct ImageState {
     size: [u32; 2],
     label: Option<String>,
@@ -249,7 +306,7 @@ impl ImageState {
 }
 
 impl App {
-    fn new(recv: Receiver<Request>, ctx: &Context) -> Self {
+    fn new(recv: Receiver<Request>, window_events: WindowEvents, ctx: &Context) -> Self {
         let (ppp, clear) = ctx.memory_mut(|mem| {
             (
                 mem.data.get_persisted(Id::new("ppp")).unwrap_or(1.5),
@@ -260,6 +317,8 @@ impl App {
         App {
             items: Vec::new(),
             recv,
+            windows: HashMap::new(),
+            window_events,
             cache: Cache {
                 ppp,
                 image_scale: 1.0,
@@ -283,6 +342,24 @@ impl eframe::App for App {
     fn update(&mut self, ctx: &Context, _: &mut eframe::Frame) {
         let mut scroll = false;
         while let Ok(req) = self.recv.try_recv() {
+            // Program windows are separate from the output
+            let req = match req {
+                Request::WindowShow {
+                    name,
+                    width,
+                    height,
+                    rgba,
+                } => {
+                    self.show_window(name, [width, height], &rgba, ctx);
+                    continue;
+                }
+                Request::WindowClose(name) => {
+                    self.windows.remove(&name);
+                    self.window_events.lock().remove(&name);
+                    continue;
+                }
+                req => req,
+            };
             if self.clear_before_next {
                 self.clear_before_next = false;
                 for item in self.items.drain(..) {
@@ -325,6 +402,8 @@ impl eframe::App for App {
                 }
                 Request::ClearBeforeNext => self.clear_before_next = self.clear,
                 Request::Shutdown => ctx.send_viewport_cmd(ViewportCommand::Close),
+                Request::WindowShow { .. } | Request::WindowClose(_) | Request::WindowEvents(_) => {
+                }
             }
         }
 
@@ -417,6 +496,8 @@ impl eframe::App for App {
             }
         }
 
+        self.program_windows(ctx);
+
         if ctx.input(|input| input.viewport().close_requested()) {
             ctx.data_mut(|data| {
                 data.clear();
@@ -424,11 +505,141 @@ impl eframe::App for App {
                 data.insert_persisted(Id::new("clear"), self.clear);
             });
         }
-        ctx.request_repaint_after_secs(0.1);
+        if self.windows.is_empty() {
+            ctx.request_repaint_after_secs(0.1);
+        } else {
+            // Programs drawing to windows expect their frames to be shown right away
+            ctx.request_repaint();
+        }
         self.cache.last_frame = Instant::now();
     }
 }
 
+impl App {
+    fn show_window(&mut self, name: String, size: [u32; 2], rgba: &[u8], ctx: &Context) {
+        let image = ColorImage::from_rgba_unmultiplied([size[0] as usize, size[1] as usize], rgba);
+        if let Some(window) = self.windows.get_mut(&name) {
+            window.tex.set(image, TEXTURE_OPTIONS);
+            window.size = size;
+        } else {
+            let tex = ctx.load_texture(&name, image, TEXTURE_OPTIONS);
+            self.window_events.lock().entry(name.clone()).or_default();
+            self.windows.insert(name, ProgramWindow { tex, size });
+        }
+    }
+    fn program_windows(&mut self, ctx: &Context) {
+        let mut closed = Vec::new();
+        for (name, window) in &self.windows {
+            let [width, height] = window.size;
+            let builder = ViewportBuilder::default()
+                .with_title(name)
+                .with_inner_size([width.max(200) as f32, height.max(200) as f32]);
+            let id = ViewportId::from_hash_of(("program window", name));
+            let events = ctx.show_viewport_immediate(id, builder, |ctx, _| {
+                // Draw the image as large as it fits
+                let rect = CentralPanel::default()
+                    .frame(Frame::none().fill(Color32::BLACK))
+                    .show(ctx, |ui| {
+                        let available = ui.available_size();
+                        let scale = (available.x / width as f32).min(available.y / height as f32);
+                        let size = vec2(width as f32, height as f32) * scale;
+                        let image = SizedTexture::new(window.tex.id(), size);
+                        ui.centered_and_justified(|ui| ui.image(image).rect).inner
+                    })
+                    .inner;
+                // Convert positions to pixels of the image
+                let to_pixels = |pos: Pos2| {
+                    let x = (pos.x - rect.min.x) / rect.width() * width as f32;
+                    let y = (pos.y - rect.min.y) / rect.height() * height as f32;
+                    (x as f64, y as f64)
+                };
+                ctx.input(|input| {
+                    let mut events = Vec::new();
+                    for event in &input.events {
+                        events.push(match event {
+                            Event::Key {
+                                key,
+                                pressed,
+                                repeat: false,
+                                modifiers,
+                                ..
+                            } => WindowEvent::Key {
+                                key: key.name().into(),
+                                pressed: *pressed,
+                                ctrl: modifiers.ctrl,
+                                alt: modifiers.alt,
+                                shift: modifiers.shift,
+                            },
+                            Event::PointerMoved(pos) => {
+                                let (x, y) = to_pixels(*pos);
+                                let kind = "move".into();
+                                WindowEvent::Mouse {
+                                    kind,
+                                    button: None,
+                                    x,
+                                    y,
+                                }
+                            }
+                            Event::PointerButton {
+                                pos,
+                                button,
+                                pressed,
+                                ..
+                            } => {
+                                let button = match button {
+                                    PointerButton::Primary => "left",
+                                    PointerButton::Secondary => "right",
+                                    PointerButton::Middle => "middle",
+                                    _ => continue,
+                                };
+                                let (x, y) = to_pixels(*pos);
+                                let kind = if *pressed { "down" } else { "up" }.into();
+                                let button = Some(button.into());
+                                WindowEvent::Mouse { kind, button, x, y }
+                            }
+                            Event::MouseWheel { delta, .. } if delta.y != 0.0 => {
+                                let pos = input.pointer.latest_pos().unwrap_or_default();
+                                let (x, y) = to_pixels(pos);
+                                let kind = if delta.y > 0.0 {
+                                    "scroll up"
+                                } else {
+                                    "scroll down"
+                                };
+                                let kind = kind.into();
+                                WindowEvent::Mouse {
+                                    kind,
+                                    button: None,
+                                    x,
+                                    y,
+                                }
+                            }
+                            _ => continue,
+                        });
+                    }
+                    if input.viewport().close_requested() {
+                        events.push(WindowEvent::Close);
+                    }
+                    events
+                })
+            });
+            if events.contains(&WindowEvent::Close) {
+                closed.push(name.clone());
+            }
+            if !events.is_empty() {
+                let mut window_events = self.window_events.lock();
+                let queue = window_events.entry(name.clone()).or_default();
+                queue.extend(events);
+                let excess = queue.len().saturating_sub(MAX_WINDOW_EVENTS);
+                queue.drain(..excess);
+            }
+        }
+        // Closed windows are no longer drawn, but their events are kept until they are polled
+        for name in closed {
+            self.windows.remove(&name);
+        }
+    }
+}
+
 fn save_name(ovrride: Option<&str>, name: &str, ext: &str) -> String {
     if let Some(ovr) = ovrride {
         return format!("{ovr}.{ext}");
@@ -706,10 +917,6 @@ impl App {
         }
     }
     fn convert_smart_output(&mut self, output: SmartOutput, ctx: &Context) -> OutputItem {
-        const TEXTURE_OPTIONS: TextureOptions = TextureOptions {
-            magnification: TextureFilter::Nearest,
-            ..TextureOptions::LINEAR
-        };
         match output {
             SmartOutput::Normal(value) => OutputItem::Code(value.show()),
             #[cfg(feature = "image")]
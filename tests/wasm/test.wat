@@ -0,0 +1,237 @@
+;; The source of test.wasm, which is used by tests/wasm.ua
+(module
+  (type (func (param i32 i32) (result i32)))
+  (type (func (param i64) (result i64)))
+  (type (func (param f64 f64) (result f64)))
+  (type (func (param i32 i32) (result f64)))
+  (type (func (param i32 i32) (result i64)))
+  (type (func (param i32) (result i64)))
+  (type (func))
+  (type $i32->i32 (func (param i32) (result i32)))
+  (type (func (param i32 i32)))
+  (type (func (result i32)))
+  (type (func (param f32) (result f32)))
+
+  (memory (export "memory") 1)
+  (global $heap (mut i32) (i32.const 1024))
+  (global $live (mut i32) (i32.const 0))
+  (global $count (mut i32) (i32.const 0))
+  (table 2 funcref)
+  (elem (i32.const 0) $double $square)
+  (data (i32.const 16) "0123456789")
+
+  ;; A bump allocator that counts live allocations
+  (func $alloc (export "alloc") (param $size i32) (result i32) (local $ptr i32)
+    global.get $heap
+    local.set $ptr
+    global.get $heap
+    local.get $size
+    i32.add
+    i32.const 7
+    i32.add
+    i32.const -8
+    i32.and
+    global.set $heap
+    global.get $live
+    i32.const 1
+    i32.add
+    global.set $live
+    local.get $ptr)
+
+  (func (export "dealloc") (param $ptr i32) (param $size i32)
+    global.get $live
+    i32.const 1
+    i32.sub
+    global.set $live)
+
+  (func (export "add") (param i32 i32) (result i32)
+    local.get 0
+    local.get 1
+    i32.add)
+
+  (func $fact (export "fact") (param $n i64) (result i64)
+    local.get $n
+    i64.const 2
+    i64.lt_s
+    if (result i64)
+      i64.const 1
+    else
+      local.get $n
+      local.get $n
+      i64.const 1
+      i64.sub
+      call $fact
+      i64.mul
+    end)
+
+  (func (export "hypot") (param f64 f64) (result f64)
+    local.get 0
+    local.get 0
+    f64.mul
+    local.get 1
+    local.get 1
+    f64.mul
+    f64.add
+    f64.sqrt)
+
+  (func (export "sum") (param $ptr i32) (param $len i32) (result f64) (local $acc f64)
+    block
+      loop
+        local.get $len
+        i32.eqz
+        br_if 1
+        local.get $acc
+        local.get $ptr
+        f64.load
+        f64.add
+        local.set $acc
+        local.get $ptr
+        i32.const 8
+        i32.add
+        local.set $ptr
+        local.get $len
+        i32.const 1
+        i32.sub
+        local.set $len
+        br 0
+      end
+    end
+    local.get $acc)
+
+  (func (export "upper") (param $ptr i32) (param $len i32) (result i64)
+    (local $i i32) (local $c i32) (local $out i32)
+    local.get $len
+    call $alloc
+    local.set $out
+    block
+      loop
+        local.get $i
+        local.get $len
+        i32.ge_u
+        br_if 1
+        local.get $ptr
+        local.get $i
+        i32.add
+        i32.load8_u
+        local.set $c
+        local.get $c
+        i32.const 97
+        i32.sub
+        i32.const 26
+        i32.lt_u
+        if
+          local.get $c
+          i32.const 32
+          i32.sub
+          local.set $c
+        end
+        local.get $out
+        local.get $i
+        i32.add
+        local.get $c
+        i32.store8
+        local.get $i
+        i32.const 1
+        i32.add
+        local.set $i
+        br 0
+      end
+    end
+    local.get $out
+    i64.extend_i32_u
+    i64.const 32
+    i64.shl
+    local.get $len
+    i64.extend_i32_u
+    i64.or)
+
+  (func (export "iota") (param $n i32) (result i64) (local $ptr i32) (local $i i32)
+    local.get $n
+    i32.const 4
+    i32.mul
+    call $alloc
+    local.set $ptr
+    block
+      loop
+        local.get $i
+        local.get $n
+        i32.ge_u
+        br_if 1
+        local.get $ptr
+        local.get $i
+        i32.const 4
+        i32.mul
+        i32.add
+        local.get $i
+        i32.store
+        local.get $i
+        i32.const 1
+        i32.add
+        local.set $i
+        br 0
+      end
+    end
+    local.get $ptr
+    i64.extend_i32_u
+    i64.const 32
+    i64.shl
+    local.get $n
+    i64.extend_i32_u
+    i64.or)
+
+  (func (export "trap")
+    unreachable)
+
+  (func (export "div") (param i32 i32) (result i32)
+    local.get 0
+    local.get 1
+    i32.div_s)
+
+  (func (export "classify") (param i32) (result i32)
+    block
+      block
+        block
+          local.get 0
+          br_table 0 1 2
+        end
+        i32.const 10
+        return
+      end
+      i32.const 20
+      return
+    end
+    i32.const 30)
+
+  (func $double (param i32) (result i32)
+    local.get 0
+    i32.const 2
+    i32.mul)
+
+  (func $square (param i32) (result i32)
+    local.get 0
+    local.get 0
+    i32.mul)
+
+  (func (export "apply") (param $f i32) (param $x i32) (result i32)
+    local.get $x
+    local.get $f
+    call_indirect (type $i32->i32))
+
+  (func (export "live") (result i32)
+    global.get $live)
+
+  (func (export "next") (result i32)
+    global.get $count
+    i32.const 1
+    i32.add
+    global.set $count
+    global.get $count)
+
+  (func (export "digit") (param i32) (result i32)
+    local.get 0
+    i32.load8_u offset=16)
+
+  (func (export "half") (param f32) (result f32)
+    local.get 0
+    f32.const 0.5
+    f32.mul))
@@ -0,0 +1,24 @@
+#include <stddef.h>
+
+#define ANSWER 42
+
+typedef struct {
+    double x;
+    double y;
+} Vec2;
+
+typedef struct {
+    const char* name;
+    int age;
+} Person;
+
+enum Direction { NORTH, EAST, SOUTH = 10, WEST };
+
+int add(int a, int b);
+int strlen(const char* s);
+void reverse(int* list, int len);
+double vec2_len(Vec2 v);
+Vec2 vec2_add(Vec2 a, Vec2 b);
+Vec2 vec2_list_sum(const Vec2* list, int len);
+Person person_new(const char* name, int age);
+int apply_twice(int (*f)(int), int x);
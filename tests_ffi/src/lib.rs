@@ -246,6 +246,84 @@ pub extern "C" fn make_void_struct_a(a: c_int) -> VoidStruct {
     }
 }
 
+#[repr(C)]
+pub struct Vec5 {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub e: f64,
+}
+
+#[no_mangle]
+pub extern "C" fn vec5_scale(v: Vec5, s: f64) -> Vec5 {
+    Vec5 {
+        a: v.a * s,
+        b: v.b * s,
+        c: v.c * s,
+        d: v.d * s,
+        e: v.e * s,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn apply_twice(f: extern "C" fn(c_int) -> c_int, x: c_int) -> c_int {
+    f(f(x))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fold_ints(
+    list: *const c_int,
+    len: c_int,
+    init: c_int,
+    f: extern "C" fn(c_int, c_int) -> c_int,
+) -> c_int {
+    let list = std::slice::from_raw_parts(list, len as usize);
+    list.iter().fold(init, |acc, &x| f(acc, x))
+}
+
+#[no_mangle]
+pub extern "C" fn uiua_plugin_abi_version() -> u32 {
+    uiua::PLUGIN_ABI_VERSION
+}
+
+unsafe extern "C" fn plugin_echo(arg: *const c_char, _is_error: *mut c_int) -> *mut c_char {
+    unsafe { CStr::from_ptr(arg) }.to_owned().into_raw()
+}
+
+unsafe extern "C" fn plugin_fail(_arg: *const c_char, is_error: *mut c_int) -> *mut c_char {
+    unsafe { *is_error = 1 };
+    CString::new("Plugin failure").unwrap().into_raw()
+}
+
+struct PluginFunctions([uiua::PluginFunction; 3]);
+unsafe impl Sync for PluginFunctions {}
+
+static PLUGIN_FUNCTIONS: PluginFunctions = PluginFunctions([
+    uiua::PluginFunction {
+        name: c"echo".as_ptr(),
+        call: Some(plugin_echo),
+    },
+    uiua::PluginFunction {
+        name: c"fail".as_ptr(),
+        call: Some(plugin_fail),
+    },
+    uiua::PluginFunction {
+        name: std::ptr::null(),
+        call: None,
+    },
+]);
+
+#[no_mangle]
+pub extern "C" fn uiua_plugin_functions() -> *const uiua::PluginFunction {
+    PLUGIN_FUNCTIONS.0.as_ptr()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn uiua_plugin_free(s: *mut c_char) {
+    drop(unsafe { CString::from_raw(s) });
+}
+
 #[test]
 fn ffi_test() {
     use std::{path::Path, process::Command};
@@ -262,7 +340,44 @@ fn ffi_test() {
     #[cfg(unix)]
     let dll_path = "../target/debug/libffi_lib.so";
     let lib_path = Path::new(dll_path);
+    set_plugin_dir(lib_path.parent().unwrap());
 
     let mut uiua = Uiua::with_native_sys().with_args(vec![lib_path.to_string_lossy().into_owned()]);
     uiua.run_file("test.ua").unwrap_or_else(|e| panic!("{e}"));
 }
+
+#[test]
+fn ffi_gen_test() {
+    use std::{fs, path::Path, process::Command};
+
+    use uiua::*;
+
+    Command::new("cargo")
+        .args(["build", "-p", "tests_ffi", "--lib"])
+        .status()
+        .unwrap();
+
+    #[cfg(windows)]
+    let dll_path = "../target/debug/ffi_lib.dll";
+    #[cfg(unix)]
+    let dll_path = "../target/debug/libffi_lib.so";
+    let lib_path = Path::new(dll_path).canonicalize().unwrap();
+
+    let header = fs::read_to_string("ffi_lib.h").unwrap();
+    let bindings =
+        ffi_gen::generate_bindings(&header, "ffi_lib.h", &lib_path.to_string_lossy()).unwrap();
+    let tests = r#"
+⍤⟜≍: 42 Answer
+⍤⟜≍: [0 1 10 11] [North East South West]
+⍤⟜≍: 8 Add 3 5
+⍤⟜≍: 13 Strlen "Hello, World!"
+⍤⟜≍: [3 2 1] Reverse [1 2 3]
+⍤⟜≍: 5 VecTwoLen [3 4]
+⍤⟜≍: [4 6] VecTwoAdd [1 2] [3 4]
+⍤⟜≍: [9 12] VecTwoListSum [1_2 3_4 5_6]
+⍤⟜≍: {"Dave" 31} PersonNew "Dave" 31
+⍤⟜≍: 20 ApplyTwice &ffcb(×2) {"int" "int"} 5
+"#;
+    let mut uiua = Uiua::with_native_sys();
+    (uiua.run_str(&format!("{bindings}{tests}"))).unwrap_or_else(|e| panic!("{e}"));
+}